@@ -0,0 +1,231 @@
+//! # Matrix
+//!
+//! A library for handling 4x4 matrices of affine transformations.
+
+#![allow(dead_code)]
+use super::{Axis, Float, Point3, Quat, Vec3};
+use std::{fmt, ops};
+
+/// Models a 4x4 row-major matrix that transforms column vectors.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Mat4 {
+    /// Rows of the matrix.
+    pub m: [[Float; 4]; 4],
+}
+
+impl fmt::Display for Mat4 {
+    /// Display the rows of the matrix.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.m)
+    }
+}
+
+impl Mat4 {
+    /// The identity matrix.
+    pub const IDENTITY: Mat4 = Mat4 {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    /// Creates a new matrix from its rows.
+    ///
+    /// * `m` - Rows of the matrix.
+    pub fn new(m: [[Float; 4]; 4]) -> Mat4 {
+        Mat4 { m }
+    }
+
+    /// Creates a new matrix from 16 values in column-major order.
+    ///
+    /// * `v` - The values.
+    pub fn from_column_major(v: &[Float]) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, x) in row.iter_mut().enumerate() {
+                *x = v[4 * j + i];
+            }
+        }
+        Mat4 { m }
+    }
+
+    /// Creates a translation matrix.
+    ///
+    /// * `d` - Translation.
+    pub fn translate(d: Vec3) -> Mat4 {
+        let mut m = Mat4::IDENTITY;
+        m.m[0][3] = d.x();
+        m.m[1][3] = d.y();
+        m.m[2][3] = d.z();
+        m
+    }
+
+    /// Creates a scaling matrix.
+    ///
+    /// * `s` - Scale factors along the x, y and z axes.
+    pub fn scale(s: Vec3) -> Mat4 {
+        let mut m = Mat4::IDENTITY;
+        m.m[0][0] = s.x();
+        m.m[1][1] = s.y();
+        m.m[2][2] = s.z();
+        m
+    }
+
+    /// Creates a rotation matrix about an arbitrary axis through the origin.
+    ///
+    /// * `axis` - Axis of rotation.
+    /// * `degrees` - Angle in degrees.
+    pub fn rotate(axis: Vec3, degrees: Float) -> Mat4 {
+        Quat::from_axis_angle(axis, degrees).to_mat4()
+    }
+
+    /// Creates a rotation matrix about a coordinate axis.
+    ///
+    /// * `axis` - Axis of rotation.
+    /// * `degrees` - Angle in degrees.
+    pub fn rotate_axis(axis: Axis, degrees: Float) -> Mat4 {
+        let mut a = [0.0; 3];
+        a[axis] = 1.0;
+        Mat4::rotate(Vec3::from_array(a), degrees)
+    }
+
+    /// Creates a shearing matrix. Each factor is the amount one coordinate
+    /// is offset by another, e.g. `xy` offsets x by `xy * y`.
+    ///
+    /// * `xy` - Shear of x by y.
+    /// * `xz` - Shear of x by z.
+    /// * `yx` - Shear of y by x.
+    /// * `yz` - Shear of y by z.
+    /// * `zx` - Shear of z by x.
+    /// * `zy` - Shear of z by y.
+    pub fn shear(xy: Float, xz: Float, yx: Float, yz: Float, zx: Float, zy: Float) -> Mat4 {
+        Mat4::new([
+            [1.0, xy, xz, 0.0],
+            [yx, 1.0, yz, 0.0],
+            [zx, zy, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Returns the transpose of the matrix.
+    pub fn transpose(&self) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, x) in row.iter_mut().enumerate() {
+                *x = self.m[j][i];
+            }
+        }
+        Mat4 { m }
+    }
+
+    /// Returns the inverse of the matrix using Gauss-Jordan elimination or
+    /// `None` if the matrix is singular.
+    pub fn inverse(&self) -> Option<Mat4> {
+        let mut a = self.m;
+        let mut inv = Mat4::IDENTITY.m;
+
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|i, j| a[*i][col].abs().total_cmp(&a[*j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let d = a[col][col];
+            for k in 0..4 {
+                a[col][k] /= d;
+                inv[col][k] /= d;
+            }
+
+            for row in 0..4 {
+                if row != col {
+                    let f = a[row][col];
+                    for k in 0..4 {
+                        a[row][k] -= f * a[col][k];
+                        inv[row][k] -= f * inv[col][k];
+                    }
+                }
+            }
+        }
+
+        Some(Mat4 { m: inv })
+    }
+
+    /// Returns the determinant of the upper 3x3 part of the matrix, i.e. the
+    /// change in volume of the transformation.
+    pub fn determinant3(&self) -> Float {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// Transform a point.
+    ///
+    /// * `p` - The point.
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        let m = &self.m;
+        let v = [p.x(), p.y(), p.z(), 1.0];
+        let r = |i: usize| (0..4).map(|k| m[i][k] * v[k]).sum::<Float>();
+        let w = r(3);
+        Point3::new(r(0) / w, r(1) / w, r(2) / w)
+    }
+
+    /// Transform a direction. Translation does not apply to directions.
+    ///
+    /// * `v` - The direction.
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        let v = [v.x(), v.y(), v.z()];
+        let r = |i: usize| (0..3).map(|k| m[i][k] * v[k]).sum::<Float>();
+        Vec3::new(r(0), r(1), r(2))
+    }
+
+    /// Transform a surface normal. The matrix must be the inverse of the
+    /// transformation applied to the surface since normals transform with
+    /// the inverse transpose.
+    ///
+    /// * `n` - The normal.
+    pub fn transform_normal(&self, n: Vec3) -> Vec3 {
+        let m = &self.m;
+        let n = [n.x(), n.y(), n.z()];
+        let r = |i: usize| (0..3).map(|k| m[k][i] * n[k]).sum::<Float>();
+        Vec3::new(r(0), r(1), r(2))
+    }
+}
+
+impl ops::Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+
+    /// Returns the product of two matrices. The right matrix is applied to
+    /// vectors first.
+    ///
+    /// * `other` - The right matrix.
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, x) in row.iter_mut().enumerate() {
+                *x = (0..4).map(|k| self.m[i][k] * other.m[k][j]).sum();
+            }
+        }
+        Mat4 { m }
+    }
+}
+
+impl From<Mat4> for toml::Value {
+    /// Returns the matrix as an array of 4 rows of 4 floats for scene files.
+    ///
+    /// * `m` - The matrix.
+    fn from(m: Mat4) -> Self {
+        toml::Value::Array(
+            m.m.iter()
+                .map(|row| toml::Value::Array(row.iter().map(|x| toml::Value::Float(*x)).collect()))
+                .collect(),
+        )
+    }
+}
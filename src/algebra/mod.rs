@@ -2,7 +2,9 @@
 //!
 //! A library for linear algebra routines
 
+mod matrix;
 mod onb;
+mod quaternion;
 mod ray;
 mod vector;
 
@@ -10,6 +12,8 @@ mod vector;
 use super::common::*;
 
 // Re-exports.
+pub use self::matrix::Mat4;
 pub use self::onb::ONB;
+pub use self::quaternion::Quat;
 pub use self::ray::Ray;
 pub use self::vector::{Axis, Colour, Point3, Vec3, AXES, X_AXIS, Y_AXIS, Z_AXIS};
@@ -0,0 +1,268 @@
+//! # Quaternion
+//!
+//! A library for handling unit quaternions that represent rotations. They
+//! compose and interpolate rotations about arbitrary axes without the gimbal
+//! lock of Euler angles.
+
+#![allow(dead_code)]
+use super::{Float, Mat4, Vec3};
+use std::{fmt, ops};
+
+/// Models a quaternion `w + xi + yj + zk`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quat {
+    /// Scalar part.
+    pub w: Float,
+
+    /// The i component of the vector part.
+    pub x: Float,
+
+    /// The j component of the vector part.
+    pub y: Float,
+
+    /// The k component of the vector part.
+    pub z: Float,
+}
+
+impl fmt::Display for Quat {
+    /// Display the quaternion components.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}, {}, {}, {}]", self.w, self.x, self.y, self.z)
+    }
+}
+
+impl Quat {
+    /// The identity rotation.
+    pub const IDENTITY: Quat = Quat {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    /// Creates a new quaternion.
+    ///
+    /// * `w` - Scalar part.
+    /// * `v` - Vector part.
+    pub fn new(w: Float, v: Vec3) -> Quat {
+        Quat {
+            w,
+            x: v.x(),
+            y: v.y(),
+            z: v.z(),
+        }
+    }
+
+    /// Creates a rotation about an axis through the origin.
+    ///
+    /// * `axis` - Axis of rotation.
+    /// * `degrees` - Angle in degrees.
+    pub fn from_axis_angle(axis: Vec3, degrees: Float) -> Quat {
+        let (sin, cos) = (0.5 * degrees.to_radians()).sin_cos();
+        Quat::new(cos, axis.unit_vector() * sin)
+    }
+
+    /// Returns the unit axis and the angle in degrees of the rotation. The
+    /// identity rotation returns the x-axis and an angle of 0.
+    pub fn to_axis_angle(&self) -> (Vec3, Float) {
+        let q = self.normalize();
+        let sin = q.vector().length();
+        if sin < 1e-12 {
+            return (Vec3::new(1.0, 0.0, 0.0), 0.0);
+        }
+        let degrees = (2.0 * sin.atan2(q.w)).to_degrees();
+        (q.vector() / sin, degrees)
+    }
+
+    /// Creates a rotation from the upper 3x3 part of a matrix which must be
+    /// a rotation, i.e. orthonormal without reflection.
+    ///
+    /// * `m` - The matrix.
+    pub fn from_mat4(m: &Mat4) -> Quat {
+        let m = &m.m;
+        let trace = m[0][0] + m[1][1] + m[2][2];
+
+        // Use the largest of w, x, y and z as the divisor for stability.
+        let q = if trace > 0.0 {
+            let s = 2.0 * (1.0 + trace).sqrt();
+            Quat {
+                w: 0.25 * s,
+                x: (m[2][1] - m[1][2]) / s,
+                y: (m[0][2] - m[2][0]) / s,
+                z: (m[1][0] - m[0][1]) / s,
+            }
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = 2.0 * (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt();
+            Quat {
+                w: (m[2][1] - m[1][2]) / s,
+                x: 0.25 * s,
+                y: (m[0][1] + m[1][0]) / s,
+                z: (m[0][2] + m[2][0]) / s,
+            }
+        } else if m[1][1] > m[2][2] {
+            let s = 2.0 * (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt();
+            Quat {
+                w: (m[0][2] - m[2][0]) / s,
+                x: (m[0][1] + m[1][0]) / s,
+                y: 0.25 * s,
+                z: (m[1][2] + m[2][1]) / s,
+            }
+        } else {
+            let s = 2.0 * (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt();
+            Quat {
+                w: (m[1][0] - m[0][1]) / s,
+                x: (m[0][2] + m[2][0]) / s,
+                y: (m[1][2] + m[2][1]) / s,
+                z: 0.25 * s,
+            }
+        };
+
+        q.normalize()
+    }
+
+    /// Returns the rotation matrix.
+    pub fn to_mat4(&self) -> Mat4 {
+        let Quat { w, x, y, z } = self.normalize();
+
+        Mat4::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Returns the vector part.
+    pub fn vector(&self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
+    /// Returns the conjugate which is the inverse rotation of a unit
+    /// quaternion.
+    pub fn conjugate(&self) -> Quat {
+        Quat {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+
+    /// Returns the dot product of two quaternions.
+    ///
+    /// * `q` - The other quaternion.
+    pub fn dot(&self, q: Quat) -> Float {
+        self.w * q.w + self.x * q.x + self.y * q.y + self.z * q.z
+    }
+
+    /// Returns the length of the quaternion.
+    pub fn length(&self) -> Float {
+        self.dot(*self).sqrt()
+    }
+
+    /// Returns the unit quaternion in the same direction.
+    pub fn normalize(&self) -> Quat {
+        *self * (1.0 / self.length())
+    }
+
+    /// Rotate a vector.
+    ///
+    /// * `v` - The vector.
+    pub fn rotate(&self, v: Vec3) -> Vec3 {
+        let q = self.vector();
+        let t = q.cross(v) * 2.0;
+        v + t * self.w + q.cross(t)
+    }
+
+    /// Returns the spherical linear interpolation between two rotations at
+    /// constant angular velocity. The shorter of the two arcs is taken.
+    ///
+    /// * `q` - The rotation at `t = 1`.
+    /// * `t` - Interpolation parameter in `[0, 1]`.
+    pub fn slerp(&self, q: Quat, t: Float) -> Quat {
+        let mut cos = self.dot(q);
+        let q = if cos < 0.0 {
+            cos = -cos;
+            q * -1.0
+        } else {
+            q
+        };
+
+        // Nearly identical rotations fall back to linear interpolation to
+        // avoid dividing by the sine of a tiny angle.
+        if cos > 0.9995 {
+            return (*self * (1.0 - t) + q * t).normalize();
+        }
+
+        let theta = cos.acos();
+        let sin = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin;
+        let b = (t * theta).sin() / sin;
+        *self * a + q * b
+    }
+}
+
+impl ops::Add<Quat> for Quat {
+    type Output = Quat;
+
+    /// Returns the component-wise sum of two quaternions.
+    ///
+    /// * `other` - The other quaternion.
+    fn add(self, other: Quat) -> Quat {
+        Quat {
+            w: self.w + other.w,
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl ops::Mul<Float> for Quat {
+    type Output = Quat;
+
+    /// Returns the quaternion scaled by a scalar.
+    ///
+    /// * `s` - The scalar.
+    fn mul(self, s: Float) -> Quat {
+        Quat {
+            w: self.w * s,
+            x: self.x * s,
+            y: self.y * s,
+            z: self.z * s,
+        }
+    }
+}
+
+impl ops::Mul<Quat> for Quat {
+    type Output = Quat;
+
+    /// Returns the Hamilton product of two quaternions. The right rotation
+    /// is applied to vectors first.
+    ///
+    /// * `other` - The right quaternion.
+    fn mul(self, other: Quat) -> Quat {
+        let (a, b) = (self.vector(), other.vector());
+        Quat::new(
+            self.w * other.w - a.dot(b),
+            b * self.w + a * other.w + a.cross(b),
+        )
+    }
+}
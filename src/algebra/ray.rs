@@ -16,6 +16,14 @@ pub struct Ray {
 
     /// Time at which ray exists.
     pub time: Float,
+
+    /// Component-wise reciprocal of `direction`. Axis-parallel rays have
+    /// infinite components here which the AABB slab test relies on.
+    pub inv_direction: Vec3,
+
+    /// Sign bits of `inv_direction` per axis; `1` if negative, `0` otherwise.
+    /// Used to select the near/far AABB slab without branching.
+    pub sign: [usize; 3],
 }
 
 impl fmt::Display for Ray {
@@ -38,10 +46,22 @@ impl Ray {
     /// * `direction` - The direction vector of the ray.
     /// * `time` - The time at which the ray exists.
     pub fn new(origin: Point3, direction: Vec3, time: Float) -> Ray {
+        let inv_direction = Vec3::new(
+            direction.x().recip(),
+            direction.y().recip(),
+            direction.z().recip(),
+        );
+
         Ray {
             origin,
             direction,
             time,
+            inv_direction,
+            sign: [
+                inv_direction.x().is_sign_negative() as usize,
+                inv_direction.y().is_sign_negative() as usize,
+                inv_direction.z().is_sign_negative() as usize,
+            ],
         }
     }
 
@@ -101,6 +101,16 @@ impl Vec3 {
         self.length_squared().sqrt()
     }
 
+    /// Returns the largest component of the vector.
+    pub fn max_component(&self) -> Float {
+        self.e[0].max(self.e[1]).max(self.e[2])
+    }
+
+    /// Returns true if no component is NaN or infinite.
+    pub fn is_finite(&self) -> bool {
+        self.e.iter().all(|x| x.is_finite())
+    }
+
     /// Returns the normalized unit vector.
     pub fn unit_vector(&self) -> Vec3 {
         let len = self.length().recip();
@@ -161,11 +171,17 @@ impl Vec3 {
         self as Colour
     }
 
-    /// Returns the gamma corrected sample colour value represented by this
-    /// vector.
+    /// Returns the luminance of the linear colour represented by this vector
+    /// using Rec. 709 weights.
+    pub fn luminance(self) -> Float {
+        0.2126 * self.e[0] + 0.7152 * self.e[1] + 0.0722 * self.e[2]
+    }
+
+    /// Returns the average linear colour value of the accumulated samples
+    /// represented by this vector.
     ///
     /// * `samples_per_pixel` - The number of samples per pixel.
-    pub fn to_colour_from_sample(self, samples_per_pixel: u32) -> Colour {
+    pub fn average_sample(self, samples_per_pixel: u32) -> Colour {
         // Replace NaN components with zero.
         let r = if self.e[0].is_nan() { 0.0 } else { self.e[0] };
         let g = if self.e[1].is_nan() { 0.0 } else { self.e[1] };
@@ -174,11 +190,21 @@ impl Vec3 {
         // Divide the color total by the number of samples
         let s = 1.0 / samples_per_pixel as Float;
 
+        Colour::new(s * r, s * g, s * b)
+    }
+
+    /// Returns the gamma corrected sample colour value represented by this
+    /// vector.
+    ///
+    /// * `samples_per_pixel` - The number of samples per pixel.
+    pub fn to_colour_from_sample(self, samples_per_pixel: u32) -> Colour {
+        let c = self.average_sample(samples_per_pixel);
+
         // Gamma-correct for a gamma value of 2.0 (sqrt)
         Colour::new(
-            256.0 * clamp((s * r).sqrt(), 0.0, 0.999),
-            256.0 * clamp((s * g).sqrt(), 0.0, 0.999),
-            256.0 * clamp((s * b).sqrt(), 0.0, 0.999),
+            256.0 * clamp(c.x().sqrt(), 0.0, 0.999),
+            256.0 * clamp(c.y().sqrt(), 0.0, 0.999),
+            256.0 * clamp(c.z().sqrt(), 0.0, 0.999),
         )
     }
 
@@ -187,6 +213,11 @@ impl Vec3 {
         [self.x() as u8, self.y() as u8, self.z() as u8]
     }
 
+    /// Returns an array of `f32` to be used as linear rgb values.
+    pub fn to_rgb32f(self) -> [f32; 3] {
+        [self.x() as f32, self.y() as f32, self.z() as f32]
+    }
+
     /// Returns an array of `u8` to be used as rgba values.
     /// For now alpha = 255.
     pub fn to_rgba(self) -> [u8; 4] {
@@ -368,3 +399,12 @@ impl ops::Index<usize> for Vec3 {
         &self.e[i]
     }
 }
+
+impl From<Vec3> for toml::Value {
+    /// Returns the vector as an array of 3 floats for scene files.
+    ///
+    /// * `v` - The vector.
+    fn from(v: Vec3) -> Self {
+        toml::Value::Array(v.e.iter().map(|x| toml::Value::Float(*x)).collect())
+    }
+}
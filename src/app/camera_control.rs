@@ -0,0 +1,53 @@
+//! # Camera control
+//!
+//! A library for sharing the camera between the GUI and the renderer. The GUI
+//! moves the camera and the renderer restarts whenever the generation of the
+//! camera changes.
+
+use crate::camera::Camera;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A camera that can be moved while rendering.
+pub struct CameraControl {
+    /// The camera.
+    camera: Mutex<Camera>,
+
+    /// Number of times the camera has been moved.
+    generation: AtomicUsize,
+}
+
+impl CameraControl {
+    /// Create a new camera control.
+    ///
+    /// * `camera` - The initial camera.
+    pub fn new(camera: Camera) -> Arc<CameraControl> {
+        Arc::new(CameraControl {
+            camera: Mutex::new(camera),
+            generation: AtomicUsize::new(0),
+        })
+    }
+
+    /// Returns the camera and its generation.
+    pub fn camera(&self) -> (Camera, usize) {
+        let camera = self.camera.lock().expect("Unable to lock camera");
+        (camera.clone(), self.generation())
+    }
+
+    /// Returns the number of times the camera has been moved.
+    pub fn generation(&self) -> usize {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Move the camera and start a new generation.
+    ///
+    /// * `f` - Returns the moved camera.
+    pub fn update<F>(&self, f: F)
+    where
+        F: FnOnce(&Camera) -> Camera,
+    {
+        let mut camera = self.camera.lock().expect("Unable to lock camera");
+        *camera = f(&camera);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+}
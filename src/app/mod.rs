@@ -1,22 +1,67 @@
 //! The application
+//!
+//! The camera can be moved while rendering. W, A, S, D or the arrow keys move
+//! the camera forward, left, back and right, Q and E move it down and up, and
+//! dragging with the left mouse button orbits it around the point it looks
+//! at. Each move cancels the tiles in flight and restarts the render.
+//!
+//! Space pauses and resumes the render, Ctrl+S saves the image rendered so
+//! far and Escape or closing the window aborts the render and saves the
+//! partial image before exiting.
+
+mod camera_control;
+mod preview;
+mod render_progress;
+mod render_state;
+mod viewer;
 
 use std::sync::{Arc, Mutex};
 
 use pixels::{Pixels, SurfaceTexture};
 use tao::{
-    dpi::LogicalSize,
-    event::{ElementState, Event, KeyEvent, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    keyboard::Key,
+    dpi::{LogicalSize, PhysicalPosition},
+    event::{ElementState, Event, KeyEvent, MouseButton, WindowEvent},
+    event_loop::{ControlFlow, EventLoop, EventLoopBuilder, EventLoopProxy},
+    keyboard::{Key, KeyCode, ModifiersState},
     window::{Window, WindowBuilder},
 };
 
-use crate::{ThreadPool, CONFIG};
+use crate::common::Float;
+use crate::threadpool::ThreadPool;
+
+// Re-exports.
+pub use self::camera_control::CameraControl;
+pub use self::preview::run_preview;
+pub use self::render_progress::RenderProgress;
+pub use self::render_state::RenderState;
+pub use self::viewer::view_image;
+
+/// Distance the camera moves per key press as a fraction of its focus distance.
+const MOVE_STEP: Float = 0.05;
+
+/// Degrees the camera orbits when dragging the mouse across the window.
+const ORBIT_DEGREES: Float = 180.0;
+
+/// Events sent to the event loop by the application and the render threads.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UserEvent {
+    /// Pause a running render or resume a paused one.
+    TogglePause,
+
+    /// Save the image rendered so far.
+    SaveNow,
+
+    /// Abort the render and save the partial image.
+    Abort,
+
+    /// Exit once the image has been saved.
+    Exit,
+}
 
 /// The application.
 pub struct App {
     /// Event loop.
-    event_loop: EventLoop<()>,
+    event_loop: EventLoop<UserEvent>,
 
     /// Window.
     pub window: Arc<Window>,
@@ -29,40 +74,41 @@ pub struct App {
 
     /// The image buffer used for rendering.
     image: Arc<Mutex<image::RgbaImage>>,
+
+    /// The camera moved by keyboard and mouse input.
+    camera: Arc<CameraControl>,
+
+    /// State of the render controlled by keyboard input.
+    render_state: Arc<RenderState>,
 }
 
 impl App {
-    /// Build a new `App`.
+    /// Build a new `App`. The window is sized to match the image buffer.
     ///
-    /// * `pool`  - Thread pool used for rendering the image in parallel.
-    /// * `image` - The image buffer used for rendering.
+    /// * `pool`   - Thread pool used for rendering the image in parallel.
+    /// * `image`  - The image buffer used for rendering.
+    /// * `camera` - The camera moved by keyboard and mouse input.
+    /// * `render_state` - State of the render controlled by keyboard input.
     pub fn build(
         pool: Arc<Mutex<ThreadPool>>,
         image: Arc<Mutex<image::RgbaImage>>,
+        camera: Arc<CameraControl>,
+        render_state: Arc<RenderState>,
     ) -> Result<Self, String> {
         // Initialize logger for tao.
         env_logger::init();
 
+        let (image_width, image_height) = image
+            .lock()
+            .map_err(|_| "Unable to lock image buffer")?
+            .dimensions();
+
         // Create a new event loop for the application.
-        let event_loop = EventLoop::new();
+        let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
 
         // Create a new window.
-        let window = WindowBuilder::new()
-            .with_title("Raytracing Series")
-            .with_inner_size(LogicalSize::new(CONFIG.image_width, CONFIG.image_height))
-            .with_resizable(false)
-            .build(&event_loop)
-            .map_err(|e| e.to_string())?;
-
-        let inner_size = window.inner_size();
-
-        // Create a surface texture that uses the logical inner size to render to the entire window's inner dimensions.
-        let surface_texture = SurfaceTexture::new(inner_size.width, inner_size.height, &window);
-
-        // Create pixel frame buffer that matches rendered image dimensions that will be used to display it in the
-        // window.
-        let pixels = Pixels::new(CONFIG.image_width, CONFIG.image_height, surface_texture)
-            .map_err(|e| e.to_string())?;
+        let (window, pixels) =
+            open_window(&event_loop, "Raytracing Series", image_width, image_height)?;
 
         Ok(Self {
             event_loop,
@@ -70,9 +116,16 @@ impl App {
             pixels: Arc::new(Mutex::new(pixels)),
             pool,
             image,
+            camera,
+            render_state,
         })
     }
 
+    /// Returns a proxy for sending events to the event loop from other threads.
+    pub fn proxy(&self) -> EventLoopProxy<UserEvent> {
+        self.event_loop.create_proxy()
+    }
+
     /// Run the event loop displaying the GUI window until it is closed or some error occurs.
     ///
     /// NOTE: This consumes `self` to avoid life time issues with references and also it runs indefinitely so it can
@@ -83,16 +136,29 @@ impl App {
             image,
             pixels,
             event_loop,
-            window: _,
+            window,
+            camera,
+            render_state,
         } = self;
 
+        // Keyboard shortcuts are sent as user events.
+        let proxy = event_loop.create_proxy();
+        let send = move |event: UserEvent| {
+            let _ = proxy.send_event(event);
+        };
+
+        // Last cursor position, whether the left mouse button is held and the modifier keys held.
+        let mut cursor: Option<PhysicalPosition<f64>> = None;
+        let mut dragging = false;
+        let mut modifiers = ModifiersState::empty();
+
         event_loop.run(move |event, _, control_flow| {
             //println!("{:?}", event);
             *control_flow = ControlFlow::Wait;
 
             match event {
                 Event::WindowEvent { event, .. } => match event {
-                    // When window is closed or destroyed or Escape key is pressed, stop rendering.
+                    // When window is closed or destroyed or Escape key is pressed, abort rendering and save.
                     WindowEvent::CloseRequested
                     | WindowEvent::Destroyed
                     | WindowEvent::KeyboardInput {
@@ -103,12 +169,73 @@ impl App {
                                 ..
                             },
                         ..
+                    } => send(UserEvent::Abort),
+
+                    WindowEvent::ModifiersChanged(state) => modifiers = state,
+
+                    WindowEvent::KeyboardInput {
+                        event:
+                            KeyEvent {
+                                physical_key,
+                                state: ElementState::Pressed,
+                                ..
+                            },
+                        ..
                     } => {
+                        if physical_key == KeyCode::Space {
+                            send(UserEvent::TogglePause);
+                        } else if physical_key == KeyCode::KeyS && modifiers.control_key() {
+                            send(UserEvent::SaveNow);
+                        } else if let Some((right, up, forward)) = key_motion(physical_key) {
+                            camera.update(|c| {
+                                let step = MOVE_STEP * c.focus_dist();
+                                c.translate(right * step, up * step, forward * step)
+                            });
+                        }
+                    }
+
+                    WindowEvent::MouseInput {
+                        button: MouseButton::Left,
+                        state,
+                        ..
+                    } => {
+                        dragging = state == ElementState::Pressed;
+                    }
+
+                    WindowEvent::CursorMoved { position, .. } => {
+                        if let (true, Some(last)) = (dragging, cursor) {
+                            let size = window.inner_size();
+                            let dx = (position.x - last.x) / size.width.max(1) as f64;
+                            let dy = (position.y - last.y) / size.height.max(1) as f64;
+                            camera.update(|c| {
+                                c.orbit(-dx as Float * ORBIT_DEGREES, -dy as Float * ORBIT_DEGREES)
+                            });
+                        }
+                        cursor = Some(position);
+                    }
+
+                    _ => (),
+                },
+                Event::UserEvent(event) => match event {
+                    UserEvent::TogglePause => {
+                        if render_state.toggle_pause() {
+                            eprintln!("\nRender paused. Press Space to resume.");
+                        } else {
+                            eprintln!("\nRender resumed.");
+                        }
+                    }
+                    UserEvent::SaveNow => render_state.request_save(),
+                    UserEvent::Abort => {
+                        if !render_state.is_aborted() {
+                            eprintln!("\nAborting render.");
+                            render_state.abort();
+                        }
+                    }
+                    UserEvent::Exit => {
                         eprintln!("Exiting application.");
                         pool.lock().unwrap().shutdown();
                         *control_flow = ControlFlow::Exit;
                     }
-                    _ => (),
                 },
                 Event::RedrawRequested(_) => {
                     // Draw the pixel frame buffer to the window. If there are errors show the error and stop rendering.
@@ -124,6 +251,52 @@ impl App {
     }
 }
 
+/// Open a window with a pixel frame buffer that matches the image dimensions.
+///
+/// * `event_loop` - Event loop of the window.
+/// * `title`      - Window title.
+/// * `width`      - Image width.
+/// * `height`     - Image height.
+fn open_window<T>(
+    event_loop: &EventLoop<T>,
+    title: &str,
+    width: u32,
+    height: u32,
+) -> Result<(Window, Pixels), String> {
+    let window = WindowBuilder::new()
+        .with_title(title)
+        .with_inner_size(LogicalSize::new(width, height))
+        .with_resizable(false)
+        .build(event_loop)
+        .map_err(|e| e.to_string())?;
+
+    let inner_size = window.inner_size();
+
+    // Create a surface texture that uses the logical inner size to render to the entire window's inner dimensions.
+    let surface_texture = SurfaceTexture::new(inner_size.width, inner_size.height, &window);
+
+    // Create pixel frame buffer that matches rendered image dimensions that will be used to display it in the window.
+    let pixels = Pixels::new(width, height, surface_texture).map_err(|e| e.to_string())?;
+
+    Ok((window, pixels))
+}
+
+/// Returns the direction the camera moves for a key as distances to the
+/// right, up and forward, or `None` if the key doesn't move the camera.
+///
+/// * `key` - The physical key.
+fn key_motion(key: KeyCode) -> Option<(Float, Float, Float)> {
+    match key {
+        KeyCode::KeyW | KeyCode::ArrowUp => Some((0.0, 0.0, 1.0)),
+        KeyCode::KeyS | KeyCode::ArrowDown => Some((0.0, 0.0, -1.0)),
+        KeyCode::KeyA | KeyCode::ArrowLeft => Some((-1.0, 0.0, 0.0)),
+        KeyCode::KeyD | KeyCode::ArrowRight => Some((1.0, 0.0, 0.0)),
+        KeyCode::KeyQ => Some((0.0, -1.0, 0.0)),
+        KeyCode::KeyE => Some((0.0, 1.0, 0.0)),
+        _ => None,
+    }
+}
+
 /// Copy the rendered image to pixel frame buffer and render to window.
 ///
 /// * `image`  - Rendered image.
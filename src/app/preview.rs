@@ -0,0 +1,382 @@
+//! # Preview
+//!
+//! A library for rendering a scene in a window. Tiles are rendered in
+//! parallel and shown as they complete. The render restarts when the camera
+//! is moved and the image is saved each time a render completes.
+
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tao::event_loop::EventLoopProxy;
+use tao::window::Window;
+
+use super::{App, CameraControl, RenderProgress, RenderState, UserEvent};
+use crate::app_config::AppConfig;
+use crate::pipeline::{PassContext, Pipeline};
+use crate::renderer::{samples_traced, to_rgba_image, Accumulator, RecursiveTracer};
+use crate::scene::Scene;
+use crate::signal::{exit_if_interrupted, interrupted};
+use crate::threadpool::ThreadPool;
+use crate::tiles::{copy_tile, get_tile_bounds, render_tile_cancellable, render_tile_pass};
+
+/// Render a scene in a window. The pipeline pre-passes run before the window
+/// opens so errors are reported early. This runs the event loop of the window
+/// on the calling thread and does not return once the window is open.
+///
+/// * `scene`  - Scene to render.
+/// * `config` - Program configuration.
+pub fn run_preview(scene: Scene, config: &AppConfig) -> Result<(), String> {
+    let config = Arc::new(config.clone());
+
+    let pipeline = Pipeline::build(&scene, &config)?;
+    let mut ctx = PassContext::new(scene.clone(), &config);
+    let result = pipeline.run_pre(&mut ctx);
+    print_messages(&ctx);
+    result?;
+    exit_if_interrupted();
+
+    // Allocate an image buffer for display and a linear framebuffer for saving.
+    let image = Arc::new(Mutex::new(image::RgbaImage::new(
+        config.image_width,
+        config.image_height,
+    )));
+    let framebuffer = Arc::new(Mutex::new(image::Rgb32FImage::new(
+        config.image_width,
+        config.image_height,
+    )));
+
+    // Create a thread pool for rendering tiles in parallel.
+    let pool = ThreadPool::build(config.threads(), config.worker_options()).unwrap();
+    for warning in pool.warnings() {
+        eprintln!("{}", warning);
+    }
+    let pool = Arc::new(Mutex::new(pool));
+
+    // Track the camera generation being rendered, its remaining tiles of all passes and rendering rates. It is used
+    // to report progress and save the image when a render completes.
+    let render_progress = RenderProgress::new(n_tile_passes(&config));
+
+    // The camera moved from the GUI and the state of the render controlled from it.
+    let camera = CameraControl::new(scene.camera.clone());
+    let render_state = RenderState::new();
+
+    // Create the GUI application.
+    let app = App::build(
+        Arc::clone(&pool),
+        Arc::clone(&image),
+        Arc::clone(&camera),
+        Arc::clone(&render_state),
+    )?;
+
+    let session = Session {
+        config,
+        pool,
+        framebuffer,
+        render_progress,
+        camera,
+        render_state,
+    };
+
+    // Start a separate thread that will queue all tiles and requeue them when the camera moves.
+    {
+        let session = session.clone();
+        let window = Arc::clone(&app.window);
+        thread::spawn(|| render(session, scene, image, window));
+    }
+
+    // Save the image each time a render completes.
+    {
+        let proxy = app.proxy();
+        thread::spawn(|| progress(session, proxy, pipeline, ctx));
+    }
+
+    // Run the event loop for the GUI. This will run in the main thread.
+    app.run()
+}
+
+/// State of a preview shared by the thread queuing tiles and the thread
+/// reporting progress.
+#[derive(Clone)]
+struct Session {
+    /// Program configuration.
+    config: Arc<AppConfig>,
+
+    /// Thread pool.
+    pool: Arc<Mutex<ThreadPool>>,
+
+    /// Linear image buffer to render.
+    framebuffer: Arc<Mutex<image::Rgb32FImage>>,
+
+    /// Progress of the render.
+    render_progress: Arc<RenderProgress>,
+
+    /// The camera moved from the GUI.
+    camera: Arc<CameraControl>,
+
+    /// State of the render controlled from the GUI.
+    render_state: Arc<RenderState>,
+}
+
+/// Print the messages reported by the passes of the pipeline.
+///
+/// * `ctx` - The pass context.
+fn print_messages(ctx: &PassContext) {
+    for message in ctx.messages.take() {
+        eprintln!("{}", message);
+    }
+}
+
+/// Returns the number of tiles to render over all progressive passes.
+///
+/// * `config` - Program configuration.
+fn n_tile_passes(config: &AppConfig) -> usize {
+    config.n_tiles() * config.progressive_passes() as usize
+}
+
+/// Render the scene in parallel using worker threads. With `--progressive`
+/// the tiles of each pass are queued after those of the previous pass and
+/// every tile shows the running average of the passes so far. When the camera
+/// moves, tiles in flight are cancelled and the render restarts.
+///
+/// * `session` - State of the preview.
+/// * `scene`   - Scene to render.
+/// * `image`   - Image buffer to display.
+/// * `window`  - Window to redraw image to.
+fn render(
+    session: Session,
+    scene: Scene,
+    image: Arc<Mutex<image::RgbaImage>>,
+    window: Arc<Window>,
+) {
+    let Session {
+        config,
+        pool,
+        framebuffer,
+        render_progress,
+        camera,
+        render_state,
+    } = session;
+
+    loop {
+        let (view, generation) = camera.camera();
+        render_progress.restart(generation);
+
+        // Setup rendering algorithm.
+        let renderer = Arc::new(RecursiveTracer::new(
+            Scene {
+                camera: view,
+                ..scene.clone()
+            },
+            AppConfig::clone(&config),
+        ));
+
+        // Sample sums of progressive passes.
+        let accumulator = Arc::new(Accumulator::new(config.image_width, config.image_height));
+
+        // Queue up the tiles to render.
+        let tiles = config.tile_indices();
+        for (pass, &tile_idx) in (0..config.progressive_passes())
+            .flat_map(|pass| tiles.iter().map(move |tile_idx| (pass, tile_idx)))
+        {
+            // Stop queuing tiles once an interrupt is requested or the render is aborted.
+            if interrupted() || render_state.is_aborted() {
+                eprintln!("\nInterrupted. Stopped queuing tiles.");
+                return;
+            }
+
+            // Stop queuing tiles once the camera moves.
+            if camera.generation() != generation {
+                break;
+            }
+
+            // Clone the `Arc`s for the worker thread.
+            let renderer = Arc::clone(&renderer);
+            let accumulator = Arc::clone(&accumulator);
+            let image = Arc::clone(&image);
+            let framebuffer = Arc::clone(&framebuffer);
+            let render_progress = Arc::clone(&render_progress);
+            let config = Arc::clone(&config);
+            let camera = Arc::clone(&camera);
+            let render_state = Arc::clone(&render_state);
+            let window = Arc::clone(&window);
+
+            pool.lock().unwrap().execute(move || {
+                // Drain tiles that were queued before the interrupt, abort or camera move. Tiles wait here while
+                // the render is paused.
+                let cancelled = || {
+                    interrupted()
+                        || render_state.wait_while_paused()
+                        || camera.generation() != generation
+                };
+                if cancelled() {
+                    return;
+                }
+                let start = Instant::now();
+                let samples = samples_traced();
+
+                thread_local! {
+                    // Allocate pixels for rendering a tile per thread so we don't allocate for each tile.
+                    pub static TILE_PIXELS: RefCell<image::Rgb32FImage> = RefCell::new(image::Rgb32FImage::new(0, 0));
+                }
+
+                TILE_PIXELS.with_borrow_mut(|tile_pixels| {
+                    let tile_size = config.tile_size as u32;
+                    if tile_pixels.dimensions() != (tile_size, tile_size) {
+                        eprintln!("\rAllocating tile pixels for {:?}", thread::current().id());
+                        *tile_pixels = image::Rgb32FImage::new(tile_size, tile_size);
+                    }
+
+                    // Calculate the tile bounds.
+                    let ctx = config.render_context();
+                    let tile_bounds = get_tile_bounds(&ctx, tile_idx);
+
+                    // Render whole tile and then copy to destination.
+                    if config.progressive.is_some() {
+                        render_tile_pass(renderer, &ctx, &accumulator, pass, &tile_bounds, tile_pixels);
+                    } else {
+                        render_tile_cancellable(
+                            renderer,
+                            &ctx,
+                            &tile_bounds,
+                            tile_pixels,
+                            cancelled,
+                        );
+                    }
+
+                    if !cancelled() {
+                        copy_tile(Arc::clone(&framebuffer), &tile_bounds, tile_pixels);
+                        copy_tile(Arc::clone(&image), &tile_bounds, &to_rgba_image(tile_pixels, config.gamma));
+                    }
+                });
+
+                // Update remaining tiles of the generation.
+                if !cancelled() {
+                    render_progress.tile_finished(generation, samples_traced() - samples, start.elapsed());
+                }
+
+                // Redraw window.
+                window.request_redraw();
+            });
+        }
+
+        eprintln!("\nQueued up all tiles to render.");
+
+        // Wait for the camera to move.
+        while camera.generation() == generation {
+            if interrupted() || render_state.is_aborted() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        eprintln!("\nCamera moved. Restarting render.");
+    }
+}
+
+/// Displays the progress of the render with rendering rates and an estimate of the time remaining. When a render
+/// completes it runs the pipeline passes and saves the image. Renders restarted by moving the camera are saved again
+/// when they complete and the image rendered so far is saved when requested from the GUI. When interrupted or aborted
+/// it saves the partial image and shuts down.
+///
+/// * `session`  - State of the preview.
+/// * `proxy`    - Sends events to the GUI event loop.
+/// * `pipeline` - Render pipeline.
+/// * `ctx`      - Pass context with the results of the pre-passes.
+fn progress(
+    session: Session,
+    proxy: EventLoopProxy<UserEvent>,
+    pipeline: Pipeline,
+    mut ctx: PassContext,
+) {
+    let Session {
+        config,
+        pool,
+        framebuffer,
+        render_progress,
+        camera,
+        render_state,
+    } = session;
+
+    // Camera generation that was last saved after completing and the one the auxiliary images were rendered for.
+    let mut saved = None;
+    let mut aux_generation = 0;
+
+    loop {
+        let (generation, remaining_tiles) = render_progress.remaining();
+        let (view, current) = camera.camera();
+
+        let paused = if render_state.is_paused() {
+            " (paused)"
+        } else {
+            ""
+        };
+        eprint!("\r{}{}    ", render_progress.report(), paused);
+
+        let stopping = interrupted() || render_state.is_aborted();
+        let complete = remaining_tiles == 0 && generation == current && saved != Some(generation);
+        let save_now = render_state.take_save_request();
+
+        if complete || stopping || save_now {
+            eprintln!();
+
+            if remaining_tiles > 0 {
+                eprintln!(
+                    "Render {}: {} of {} tiles completed",
+                    if stopping {
+                        "interrupted"
+                    } else {
+                        "in progress"
+                    },
+                    n_tile_passes(&config) - remaining_tiles,
+                    n_tile_passes(&config)
+                );
+            }
+
+            // Auxiliary images of a moved camera are rendered again unless stopping.
+            if generation != aux_generation {
+                ctx.scene.camera = view;
+                ctx.buffers.clear();
+                if !stopping {
+                    if let Err(e) = pipeline.run_pre(&mut ctx) {
+                        eprintln!("Error running pre-passes: {}", e);
+                    }
+                    print_messages(&ctx);
+                }
+                aux_generation = generation;
+            }
+
+            // Rows are rendered bottom to top.
+            match framebuffer.lock() {
+                Ok(img) => {
+                    ctx.image = image::imageops::flip_vertical(&*img);
+                    if let Err(e) = pipeline.run_post(&mut ctx) {
+                        eprintln!("Error running post passes: {}", e);
+                    }
+                    pipeline.save(&ctx, &config.output_path);
+                    print_messages(&ctx);
+                }
+                Err(_) => eprintln!("Error writing output image"),
+            }
+            if remaining_tiles == 0 {
+                saved = Some(generation);
+            }
+
+            if interrupted() {
+                pool.lock().unwrap().shutdown();
+
+                // The event loop doesn't return so exit here after the pool drains.
+                exit_if_interrupted();
+                break;
+            }
+
+            if stopping {
+                // The event loop shuts down the pool and exits.
+                let _ = proxy.send_event(UserEvent::Exit);
+                break;
+            }
+        }
+
+        thread::sleep(Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,189 @@
+//! # Render progress
+//!
+//! A library for tracking the progress of a render shared between the tile
+//! workers and the thread that reports it. The camera generation and its
+//! remaining tiles are packed into one atomic so a tile finished for an old
+//! generation never counts against a restarted render.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Progress of a render restarted for each camera generation.
+pub struct RenderProgress {
+    /// Number of tiles of all passes.
+    total: usize,
+
+    /// Camera generation in the high 32 bits and remaining tiles in the low
+    /// 32 bits.
+    state: AtomicU64,
+
+    /// Number of tiles finished since the render started.
+    tiles: AtomicU64,
+
+    /// Number of samples traced since the render started.
+    samples: AtomicU64,
+
+    /// Time spent rendering finished tiles in nanoseconds.
+    tile_nanos: AtomicU64,
+
+    /// Reference for start times.
+    origin: Instant,
+
+    /// Start of the render in nanoseconds since `origin`.
+    started: AtomicU64,
+}
+
+/// Snapshot of the progress of a render.
+#[derive(Debug, Copy, Clone)]
+pub struct ProgressReport {
+    /// Fraction of tiles completed.
+    pub fraction: f32,
+
+    /// Tiles rendered per second.
+    pub tiles_per_sec: f64,
+
+    /// Samples traced per second.
+    pub samples_per_sec: f64,
+
+    /// Average time to render a tile.
+    pub average_tile: Duration,
+
+    /// Time since the render started.
+    pub elapsed: Duration,
+
+    /// Estimated time until the render completes, once a tile has finished.
+    pub eta: Option<Duration>,
+}
+
+impl RenderProgress {
+    /// Create a new progress tracker for the first camera generation.
+    ///
+    /// * `total` - Number of tiles of all passes.
+    pub fn new(total: usize) -> Arc<RenderProgress> {
+        let progress = RenderProgress {
+            total,
+            state: AtomicU64::new(0),
+            tiles: AtomicU64::new(0),
+            samples: AtomicU64::new(0),
+            tile_nanos: AtomicU64::new(0),
+            origin: Instant::now(),
+            started: AtomicU64::new(0),
+        };
+        progress.restart(0);
+        Arc::new(progress)
+    }
+
+    /// Restart the render for a camera generation.
+    ///
+    /// * `generation` - Camera generation.
+    pub fn restart(&self, generation: usize) {
+        self.tiles.store(0, Ordering::SeqCst);
+        self.samples.store(0, Ordering::SeqCst);
+        self.tile_nanos.store(0, Ordering::SeqCst);
+        self.started
+            .store(self.origin.elapsed().as_nanos() as u64, Ordering::SeqCst);
+        self.state
+            .store(pack(generation, self.total), Ordering::SeqCst);
+    }
+
+    /// Returns the camera generation being rendered and its remaining tiles.
+    pub fn remaining(&self) -> (usize, usize) {
+        unpack(self.state.load(Ordering::SeqCst))
+    }
+
+    /// Record a finished tile. Tiles of other generations are ignored.
+    ///
+    /// * `generation` - Camera generation the tile was rendered for.
+    /// * `samples`    - Number of samples traced for the tile.
+    /// * `time`       - Time spent rendering the tile.
+    pub fn tile_finished(&self, generation: usize, samples: u64, time: Duration) {
+        let counted = self
+            .state
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |state| {
+                match unpack(state) {
+                    (g, remaining) if g == generation as u32 as usize && remaining > 0 => {
+                        Some(pack(generation, remaining - 1))
+                    }
+                    _ => None,
+                }
+            })
+            .is_ok();
+
+        if counted {
+            self.tiles.fetch_add(1, Ordering::SeqCst);
+            self.samples.fetch_add(samples, Ordering::SeqCst);
+            self.tile_nanos
+                .fetch_add(time.as_nanos() as u64, Ordering::SeqCst);
+        }
+    }
+
+    /// Returns a snapshot of the progress.
+    pub fn report(&self) -> ProgressReport {
+        let (_, remaining) = self.remaining();
+        let tiles = self.tiles.load(Ordering::SeqCst);
+        let samples = self.samples.load(Ordering::SeqCst);
+        let tile_nanos = self.tile_nanos.load(Ordering::SeqCst);
+
+        let started = Duration::from_nanos(self.started.load(Ordering::SeqCst));
+        let elapsed = self.origin.elapsed().saturating_sub(started);
+        let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+
+        let tiles_per_sec = tiles as f64 / secs;
+        let eta = if tiles > 0 {
+            Some(Duration::from_secs_f64(remaining as f64 / tiles_per_sec))
+        } else {
+            None
+        };
+
+        ProgressReport {
+            fraction: (self.total - remaining) as f32 / self.total.max(1) as f32,
+            tiles_per_sec,
+            samples_per_sec: samples as f64 / secs,
+            average_tile: Duration::from_nanos(tile_nanos.checked_div(tiles).unwrap_or(0)),
+            elapsed,
+            eta,
+        }
+    }
+}
+
+impl std::fmt::Display for ProgressReport {
+    /// Display the progress on one line.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Progress {:.2}% | {:.1} tiles/s | {:.2}M samples/s | avg tile {:.2}s | elapsed {} | ETA {}",
+            100_f32 * self.fraction,
+            self.tiles_per_sec,
+            self.samples_per_sec / 1e6,
+            self.average_tile.as_secs_f32(),
+            format_duration(self.elapsed),
+            self.eta.map_or("--:--:--".to_string(), format_duration),
+        )
+    }
+}
+
+/// Pack a camera generation and a number of remaining tiles.
+///
+/// * `generation` - Camera generation.
+/// * `remaining`  - Remaining tiles.
+fn pack(generation: usize, remaining: usize) -> u64 {
+    ((generation as u32 as u64) << 32) | remaining as u32 as u64
+}
+
+/// Unpack a camera generation and a number of remaining tiles.
+///
+/// * `state` - Packed state.
+fn unpack(state: u64) -> (usize, usize) {
+    ((state >> 32) as usize, (state & 0xffff_ffff) as usize)
+}
+
+/// Format a duration as hours, minutes and seconds.
+///
+/// * `d` - The duration.
+fn format_duration(d: Duration) -> String {
+    let s = d.as_secs();
+    format!("{:02}:{:02}:{:02}", s / 3600, (s / 60) % 60, s % 60)
+}
@@ -0,0 +1,84 @@
+//! # Render state
+//!
+//! A library for sharing the state of a render between the GUI and the tile
+//! workers. The GUI pauses, resumes and aborts the render and requests saves.
+//! Tile workers block while the render is paused and stop when it is aborted.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+/// State of a render controlled from the GUI.
+pub struct RenderState {
+    /// Whether the render is paused.
+    paused: Mutex<bool>,
+
+    /// Signalled when the render is resumed or aborted.
+    resumed: Condvar,
+
+    /// Whether the render was aborted.
+    aborted: AtomicBool,
+
+    /// Whether the image should be saved as soon as possible.
+    save_requested: AtomicBool,
+}
+
+impl RenderState {
+    /// Create a new render state for a running render.
+    pub fn new() -> Arc<RenderState> {
+        Arc::new(RenderState {
+            paused: Mutex::new(false),
+            resumed: Condvar::new(),
+            aborted: AtomicBool::new(false),
+            save_requested: AtomicBool::new(false),
+        })
+    }
+
+    /// Pause a running render or resume a paused one. Returns true if the
+    /// render is now paused.
+    pub fn toggle_pause(&self) -> bool {
+        let mut paused = self.paused.lock().expect("Unable to lock render state");
+        *paused = !*paused;
+        if !*paused {
+            self.resumed.notify_all();
+        }
+        *paused
+    }
+
+    /// Returns true if the render is paused.
+    pub fn is_paused(&self) -> bool {
+        *self.paused.lock().expect("Unable to lock render state")
+    }
+
+    /// Abort the render. Paused workers are released so they can stop.
+    pub fn abort(&self) {
+        let _paused = self.paused.lock().expect("Unable to lock render state");
+        self.aborted.store(true, Ordering::SeqCst);
+        self.resumed.notify_all();
+    }
+
+    /// Returns true if the render was aborted.
+    pub fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::SeqCst)
+    }
+
+    /// Request the image to be saved.
+    pub fn request_save(&self) {
+        self.save_requested.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true if a save was requested and clears the request.
+    pub fn take_save_request(&self) -> bool {
+        self.save_requested.swap(false, Ordering::SeqCst)
+    }
+
+    /// Block while the render is paused. Returns true if the render was
+    /// aborted.
+    pub fn wait_while_paused(&self) -> bool {
+        let paused = self.paused.lock().expect("Unable to lock render state");
+        let _paused = self
+            .resumed
+            .wait_while(paused, |paused| *paused && !self.is_aborted())
+            .expect("Unable to lock render state");
+        self.is_aborted()
+    }
+}
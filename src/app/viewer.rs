@@ -0,0 +1,115 @@
+//! # Viewer
+//!
+//! A library for viewing rendered images in a window, e.g. to inspect OpenEXR
+//! and Radiance HDR outputs without external tools. The exposure is adjusted
+//! with the up and down arrow keys, or + and -, in half stops and 0 resets it.
+//! Escape or closing the window exits.
+
+use tao::{
+    event::{ElementState, Event, KeyEvent, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    keyboard::{Key, KeyCode},
+};
+
+use super::open_window;
+use crate::common::Float;
+use crate::renderer::{load_linear_image, to_rgba_image, Gamma};
+
+/// Exposure change per key press in stops.
+const EXPOSURE_STEP: Float = 0.5;
+
+/// View an image in a window. This runs the event loop of the window on the
+/// calling thread and does not return once the window is open.
+///
+/// * `path`  - Image file path.
+/// * `gamma` - Transfer function used to encode and decode 8-bit images.
+pub fn view_image(path: &str, gamma: Gamma) -> Result<(), String> {
+    // Initialize logger for tao.
+    env_logger::init();
+
+    let image = load_linear_image(path, gamma)?;
+    let (width, height) = image.dimensions();
+
+    let event_loop = EventLoop::new();
+    let (window, mut pixels) = open_window(&event_loop, &title(path, 0.0), width, height)?;
+
+    let path = path.to_string();
+    let mut stops: Float = 0.0;
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Wait;
+
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::CloseRequested
+                | WindowEvent::Destroyed
+                | WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            logical_key: Key::Escape,
+                            state: ElementState::Released,
+                            ..
+                        },
+                    ..
+                } => *control_flow = ControlFlow::Exit,
+
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            physical_key,
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    let previous = stops;
+                    match physical_key {
+                        KeyCode::ArrowUp | KeyCode::Equal | KeyCode::NumpadAdd => {
+                            stops += EXPOSURE_STEP
+                        }
+                        KeyCode::ArrowDown | KeyCode::Minus | KeyCode::NumpadSubtract => {
+                            stops -= EXPOSURE_STEP
+                        }
+                        KeyCode::Digit0 | KeyCode::Numpad0 => stops = 0.0,
+                        _ => (),
+                    }
+
+                    if stops != previous {
+                        window.set_title(&title(&path, stops));
+                        window.request_redraw();
+                    }
+                }
+
+                _ => (),
+            },
+
+            Event::RedrawRequested(_) => {
+                // Scale the linear image by the exposure before gamma correction.
+                let scale = stops.exp2() as f32;
+                let mut exposed = image.clone();
+                exposed
+                    .pixels_mut()
+                    .for_each(|p| p.0 = p.0.map(|c| c * scale));
+
+                pixels
+                    .frame_mut()
+                    .copy_from_slice(to_rgba_image(&exposed, gamma).as_raw());
+
+                if let Err(err) = pixels.render() {
+                    eprintln!("pixels.render() failed with error.\n{}", err);
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+
+            _ => (),
+        }
+    })
+}
+
+/// Returns the window title showing the image and its exposure.
+///
+/// * `path`  - Image file path.
+/// * `stops` - Exposure change in stops.
+fn title(path: &str, stops: Float) -> String {
+    format!("{} ({:+.1} EV)", path, stops)
+}
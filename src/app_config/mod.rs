@@ -2,121 +2,874 @@
 //!
 //! A library for handling application configuration
 
-use crate::get_tile_count;
+use crate::{get_tile_order, RenderContext, TileOrder};
 
-use super::scene::Scenery;
-use clap::{builder::EnumValueParser, Parser};
+use super::background::BackgroundKind;
+use super::common::{Float, MemoryBudget, SamplerType};
+use super::object::BvhCache;
+#[cfg(feature = "cli")]
+use super::pipeline::parse_pass;
+use super::renderer::{Aov, Gamma, Integrator, Visualization};
+use super::scene::{Assets, LoadContext, SceneOverride, Scenery, Variation};
+use super::threadpool::WorkerOptions;
+#[cfg(feature = "cli")]
+use clap::{builder::EnumValueParser, ArgAction, Parser, Subcommand};
+use std::path::PathBuf;
 use std::thread::available_parallelism;
+use toml::Table;
 
-// RGBA color channels.
-pub const COLOR_CHANNELS: usize = 4;
+// RGB color channels of the linear framebuffer.
+pub const COLOR_CHANNELS: usize = 3;
+
+/// Subcommands that run instead of rendering.
+#[derive(Clone)]
+#[cfg_attr(feature = "cli", derive(Subcommand))]
+pub enum Command {
+    /// Compare two renders and write a heatmap of their differences.
+    Diff {
+        /// Reference image.
+        #[cfg_attr(
+            feature = "cli",
+            arg(value_name = "REFERENCE", help = "reference image")
+        )]
+        reference: String,
+
+        /// Image compared with the reference.
+        #[cfg_attr(
+            feature = "cli",
+            arg(value_name = "TEST", help = "image compared with the reference")
+        )]
+        test: String,
+
+        /// Heatmap output file path.
+        #[cfg_attr(
+            feature = "cli",
+            arg(
+                long = "heatmap",
+                value_name = "PATH",
+                help = "write a heatmap of the colour error to this path"
+            )
+        )]
+        heatmap: Option<String>,
+
+        /// Maximum mean colour error.
+        #[cfg_attr(
+            feature = "cli",
+            arg(
+                long = "max-flip",
+                value_name = "ERROR",
+                help = "fail if the mean FLIP colour error is above this value"
+            )
+        )]
+        max_flip: Option<Float>,
+    },
+
+    /// View an image in a window with exposure controls.
+    View {
+        /// Image to view.
+        #[cfg_attr(
+            feature = "cli",
+            arg(
+                value_name = "IMAGE",
+                help = "image to view, e.g. an OpenEXR or HDR render"
+            )
+        )]
+        path: String,
+    },
+
+    /// Bundle a scene file and the assets it references into a zip archive.
+    Pack {
+        /// Scene file to pack.
+        #[cfg_attr(
+            feature = "cli",
+            arg(value_name = "SCENE", help = "scene file to pack")
+        )]
+        scene: String,
+
+        /// Zip archive output file path.
+        #[cfg_attr(
+            feature = "cli",
+            arg(value_name = "OUTPUT", help = "zip archive to write")
+        )]
+        output: String,
+    },
+}
 
 /// Program configuration.
-#[derive(Parser, Clone)]
-#[command(author, version, about, long_about = None)]
+#[derive(Clone)]
+#[cfg_attr(feature = "cli", derive(Parser))]
+#[cfg_attr(
+    feature = "cli",
+    command(
+        author,
+        version,
+        about,
+        long_about = None,
+        subcommand_negates_reqs = true,
+        disable_help_flag = true
+    )
+)]
 pub struct AppConfig {
+    /// Subcommand to run instead of rendering.
+    #[cfg_attr(feature = "cli", command(subcommand))]
+    pub command: Option<Command>,
+
+    /// Print help. Only the long flag is available since `-h` sets the image
+    /// height.
+    #[cfg(feature = "cli")]
+    #[arg(long = "help", action = ArgAction::Help, help = "print help")]
+    help: Option<bool>,
+
     /// Image height.
-    #[arg(
-        long = "image-width",
-        short = 'w',
-        value_name = "WIDTH",
-        default_value_t = 200,
-        help = "image width in pixels"
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "image-width",
+            short = 'w',
+            value_name = "WIDTH",
+            default_value_t = 200,
+            help = "image width in pixels"
+        )
     )]
     pub image_width: u32,
 
     /// Image width.
-    #[arg(
-        long = "image-height",
-        short = 'h',
-        value_name = "HEIGHT",
-        default_value_t = 100,
-        help = "image height in pixels"
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "image-height",
+            short = 'h',
+            value_name = "HEIGHT",
+            default_value_t = 100,
+            help = "image height in pixels"
+        )
     )]
     pub image_height: u32,
 
     /// Samples per pixels for antialiasing.
-    #[arg(
-        long = "samples-per-pixel",
-        short = 's',
-        value_name = "SAMPLES",
-        default_value_t = 100,
-        help = "number of samples per pixel for antialiasing"
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "samples-per-pixel",
+            short = 's',
+            value_name = "SAMPLES",
+            default_value_t = 100,
+            help = "number of samples per pixel for antialiasing"
+        )
     )]
     pub samples_per_pixel: u32,
 
+    /// Samples per pixel of each progressive pass.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "progressive",
+            value_name = "SAMPLES",
+            num_args = 0..=1,
+            default_missing_value = "4",
+            requires = "gui",
+            conflicts_with_all = ["noise_threshold", "visualize"],
+            help = "render the whole image in passes of this many samples per pixel, updating the window after each pass [default: 4]"
+        )
+    )]
+    pub progressive: Option<u32>,
+
+    /// Minimum samples per pixel for adaptive sampling.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "min-samples",
+            value_name = "SAMPLES",
+            default_value_t = 16,
+            help = "minimum number of samples per pixel with --noise-threshold"
+        )
+    )]
+    pub min_samples: u32,
+
+    /// Maximum samples per pixel for adaptive sampling.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "max-samples",
+            value_name = "SAMPLES",
+            help = "maximum number of samples per pixel with --noise-threshold [default: SAMPLES]"
+        )
+    )]
+    pub max_samples: Option<u32>,
+
+    /// Noise threshold for adaptive sampling.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "noise-threshold",
+            value_name = "THRESHOLD",
+            help = "stop sampling a pixel once the 95% confidence interval of its luminance is \
+                    within this fraction of the mean"
+        )
+    )]
+    pub noise_threshold: Option<Float>,
+
     /// Max recursion depth
-    #[arg(
-        long = "max-depth",
-        short = 'd',
-        value_name = "DEPTH",
-        default_value_t = 50,
-        help = "maximum depth of recursion"
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "max-depth",
+            short = 'd',
+            value_name = "DEPTH",
+            default_value_t = 50,
+            help = "maximum depth of recursion"
+        )
     )]
     pub max_depth: u32,
 
+    /// Maximum radiance of indirect bounces.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "clamp-radiance",
+            value_name = "VALUE",
+            help = "clamp indirect bounce contributions to this value to suppress fireflies"
+        )
+    )]
+    pub clamp_radiance: Option<Float>,
+
+    /// Integrator.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "integrator",
+            value_name = "INTEGRATOR",
+            value_parser = EnumValueParser::<Integrator>::new(),
+            default_value_t = Integrator::Path,
+            help = "light transport algorithm: path tracing or bidirectional path tracing (bdpt)"
+        )
+    )]
+    pub integrator: Integrator,
+
+    /// Disable next event estimation.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "no-nee",
+            conflicts_with = "integrator",
+            help = "don't sample lights, only follow the directions sampled by materials (ground truth for \
+                    validating light sampling; point, spot and directional lights and the sun go dark)"
+        )
+    )]
+    pub no_nee: bool,
+
     /// Scene to render.
-    #[arg(
-        long = "scene",
-        value_name = "SCENE",
-        value_parser = EnumValueParser::<Scenery>::new(),
-        default_value = "random_spheres",
-        help = "scene to render",
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "scene",
+            value_name = "SCENE",
+            value_parser = EnumValueParser::<Scenery>::new(),
+            default_value = "final-one-weekend",
+            help = "scene to render",
+        )
     )]
     pub scenery: Scenery,
 
+    /// Seed for the randomly generated objects of the scene.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "scene-seed",
+            value_name = "SEED",
+            help = "seed for the randomly placed objects of the scene to reproduce a layout"
+        )
+    )]
+    pub scene_seed: Option<u64>,
+
+    /// Density of the randomly generated objects of the scene.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "scene-density",
+            value_name = "FACTOR",
+            default_value_t = 1.0,
+            value_parser = parse_scene_density,
+            help = "scale the number of randomly placed objects of the scene \
+                    (final-one-weekend, motion-blur, checkered-floor, final-next-week)"
+        )
+    )]
+    pub scene_density: Float,
+
+    /// Number of randomly generated objects of the scene.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "scene-count",
+            value_name = "COUNT",
+            conflicts_with = "scene_density",
+            help = "number of randomly placed objects of the scene, before overlapping ones are removed"
+        )
+    )]
+    pub scene_count: Option<usize>,
+
+    /// Scene description file to render instead of a built-in scene.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "scene-file",
+            value_name = "PATH",
+            help = "TOML scene description file to render instead of --scene"
+        )
+    )]
+    pub scene_file: Option<String>,
+
+    /// Scene parameter overrides.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "set",
+            value_name = "PATH=VALUE",
+            help = "override a scene parameter, e.g. camera.vfov=30 or light.emit=[20,20,20] (repeatable)"
+        )
+    )]
+    pub overrides: Vec<SceneOverride>,
+
     /// Enable bounding value hierarchy.
-    #[arg(
-        long = "bvh",
-        value_name = "BVH",
-        help = "enable bounding volume hierarchy"
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "bvh",
+            value_name = "BVH",
+            help = "enable bounding volume hierarchy"
+        )
     )]
     pub bvh_enabled: bool,
 
+    /// Frame the whole scene.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "auto-frame",
+            value_name = "AUTO_FRAME",
+            help = "move the camera along its viewing direction to frame the whole scene"
+        )
+    )]
+    pub auto_frame: bool,
+
+    /// Name of an object to focus on.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "focus-object",
+            value_name = "NAME",
+            help = "focus the camera on the centre of a named object of the scene file"
+        )
+    )]
+    pub focus_object: Option<String>,
+
+    /// Look at the object that is focused on.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "focus-look-at",
+            value_name = "FOCUS_LOOK_AT",
+            requires = "focus_object",
+            help = "turn the camera to look at the object given by --focus-object"
+        )
+    )]
+    pub focus_look_at: bool,
+
+    /// Background replacing the scene's background.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "background",
+            value_name = "BACKGROUND",
+            value_parser = EnumValueParser::<BackgroundKind>::new(),
+            help = "replace the scene's background"
+        )
+    )]
+    pub background: Option<BackgroundKind>,
+
+    /// Elevation of the sun for the sun and sky background.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "sun-elevation",
+            value_name = "DEGREES",
+            default_value_t = 45.0,
+            value_parser = parse_sun_elevation,
+            help = "angle of the sun above the horizon for --background sun-sky"
+        )
+    )]
+    pub sun_elevation: Float,
+
+    /// Azimuth of the sun for the sun and sky background.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "sun-azimuth",
+            value_name = "DEGREES",
+            default_value_t = 0.0,
+            help = "angle of the sun from the +z axis towards the +x axis for --background sun-sky"
+        )
+    )]
+    pub sun_azimuth: Float,
+
+    /// Haze of the atmosphere for the sun and sky background.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "turbidity",
+            value_name = "TURBIDITY",
+            default_value_t = 3.0,
+            value_parser = parse_turbidity,
+            help = "haze of the atmosphere from 2 (clear) to 10 (hazy) for --background sun-sky"
+        )
+    )]
+    pub turbidity: Float,
+
     /// Random number seed.
-    #[arg(
-        long = "seed",
-        value_name = "SEED",
-        help = "seed for random number generator (debug)"
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "seed",
+            value_name = "SEED",
+            help = "seed for random number generator (debug)"
+        )
     )]
     pub seed: Option<u64>,
 
     /// Output file path.
-    #[arg(
-        long = "out",
-        short = 'o',
-        value_name = "OUTPUT_PATH",
-        required = true,
-        help = "output file path. file extension determines image type."
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "out",
+            short = 'o',
+            value_name = "OUTPUT_PATH",
+            required_unless_present_any = ["contact_sheet", "save_scene"],
+            default_value = "",
+            help = "output file path. file extension determines image type."
+        )
     )]
     pub output_path: String,
 
+    /// Output transfer function.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "gamma",
+            value_name = "GAMMA",
+            default_value = "2.0-legacy",
+            value_parser = parse_gamma,
+            help = "transfer function for 8-bit images: a gamma value (e.g. 2.2), srgb or 2.0-legacy"
+        )
+    )]
+    pub gamma: Gamma,
+
     /// Number of threads.
-    #[arg(
-        long = "threads",
-        short = 't',
-        value_name = "THREADS",
-        default_value_t = get_max_threads(),
-        help = "number of threads to use (default = max logical cores)",
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "threads",
+            short = 't',
+            value_name = "THREADS",
+            default_value_t = get_max_threads(),
+            help = "number of threads to use (default = max logical cores)",
+        )
     )]
     num_threads: usize,
 
     /// Tile size.
-    #[arg(
-        long = "tile-size",
-        value_name = "TILE_SIZE",
-        default_value_t = 32,
-        help = "tile size in pixels (default = 32)"
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "tile-size",
+            value_name = "TILE_SIZE",
+            default_value_t = 32,
+            help = "tile size in pixels (default = 32)"
+        )
     )]
     pub tile_size: u8,
 
+    /// Tile order.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "tile-order",
+            value_name = "TILE_ORDER",
+            value_parser = EnumValueParser::<TileOrder>::new(),
+            default_value_t = TileOrder::Row,
+            help = "order in which tiles are rendered"
+        )
+    )]
+    pub tile_order: TileOrder,
+
     /// GUI.
-    #[arg(
-        long = "gui",
-        value_name = "GUI",
-        help = "show rendered image in a gui"
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "gui",
+            value_name = "GUI",
+            help = "show rendered image in a gui"
+        )
     )]
     pub gui: bool,
+
+    /// Contact sheet output file path.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "contact-sheet",
+            value_name = "CONTACT_SHEET_PATH",
+            help = "render thumbnails of all scenes into a labeled grid image at this path"
+        )
+    )]
+    pub contact_sheet: Option<String>,
+
+    /// Address to serve a live preview on.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "serve",
+            value_name = "ADDR",
+            help = "stream the in-progress render as MJPEG over HTTP on this address, e.g. 0.0.0.0:8080"
+        )
+    )]
+    pub serve: Option<String>,
+
+    /// Save the scene to a scene file instead of rendering it.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "save-scene",
+            value_name = "PATH",
+            help = "save the scene to a TOML scene file at this path instead of rendering it"
+        )
+    )]
+    pub save_scene: Option<String>,
+
+    /// Pixel to trace paths through for debugging.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "trace-pixel",
+            value_name = "X,Y",
+            value_parser = parse_pixel,
+            conflicts_with = "gui",
+            help = "trace paths through the pixel at X,Y (from top left) and write them to the output path as OBJ or PLY polylines"
+        )
+    )]
+    pub trace_pixel: Option<(u32, u32)>,
+
+    /// Number of paths to trace with `--trace-pixel`.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "trace-paths",
+            value_name = "PATHS",
+            default_value_t = 16,
+            requires = "trace_pixel",
+            help = "number of paths to trace with --trace-pixel"
+        )
+    )]
+    pub trace_paths: u32,
+
+    /// Stereo anaglyph output.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "anaglyph",
+            value_name = "ANAGLYPH",
+            conflicts_with = "gui",
+            help = "render a red/cyan stereo anaglyph image"
+        )
+    )]
+    pub anaglyph: bool,
+
+    /// Eye separation for stereo rendering.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "eye-separation",
+            value_name = "SEPARATION",
+            default_value_t = 1.0 / 30.0,
+            help = "distance between eyes as a fraction of the focus distance (anaglyph)"
+        )
+    )]
+    pub eye_separation: Float,
+
+    /// Depth of field aware adaptive sampling.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "dof-adaptive",
+            value_name = "DOF_ADAPTIVE",
+            help = "take more samples in pixels blurred by depth of field"
+        )
+    )]
+    pub dof_adaptive: bool,
+
+    /// Maximum sample count multiplier for depth of field aware sampling.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "dof-max-scale",
+            value_name = "SCALE",
+            default_value_t = 4.0,
+            help = "maximum multiplier for samples per pixel in defocused regions"
+        )
+    )]
+    pub dof_max_scale: Float,
+
+    /// Gradient-domain path tracing.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "gradient-domain",
+            value_name = "GRADIENT_DOMAIN",
+            conflicts_with_all = ["gui", "anaglyph"],
+            help = "experimental gradient-domain path tracing with poisson reconstruction"
+        )
+    )]
+    pub gradient_domain: bool,
+
+    /// Arbitrary output variables.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "aov",
+            value_name = "AOV",
+            value_parser = parse_aov,
+            value_delimiter = ',',
+            conflicts_with = "gui",
+            help = "also write arbitrary output variables as <out>.<aov>.exr, e.g. normal,depth,albedo; \
+                    requires the `exr` feature"
+        )
+    )]
+    pub aovs: Vec<Aov>,
+
+    /// Sample generation method.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "sampler",
+            value_name = "SAMPLER",
+            value_parser = EnumValueParser::<SamplerType>::new(),
+            default_value_t = SamplerType::Random,
+            help = "sample generator for pixel, lens, time and light samples"
+        )
+    )]
+    pub sampler: SamplerType,
+
+    /// Render pipeline passes.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "pass",
+            value_name = "TYPE[:KEY=VALUE,...]",
+            value_parser = parse_pass,
+            help = "add a render pipeline pass (aov, denoise, despeckle, bloom, exposure, tonemap) after those of the scene file (repeatable)"
+        )
+    )]
+    pub passes: Vec<Table>,
+
+    /// Remove fireflies after rendering.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "despeckle",
+            conflicts_with = "gui",
+            help = "replace outlier pixels with the median of their neighbours to remove fireflies"
+        )
+    )]
+    pub despeckle: bool,
+
+    /// Audit the energy returned by materials.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "energy-audit",
+            conflicts_with = "gui",
+            help = "print the mean energy returned by each material and flag those returning more than they receive"
+        )
+    )]
+    pub energy_audit: bool,
+
+    /// Render a fast preview before the full render.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "preview-pass",
+            conflicts_with_all = ["gui", "anaglyph", "gradient_domain"],
+            help = "first render and save a half resolution preview with few samples to check framing and exposure"
+        )
+    )]
+    pub preview_pass: bool,
+
+    /// Render sampling statistics instead of the image.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "visualize",
+            value_name = "STATS",
+            value_parser = EnumValueParser::<Visualization>::new(),
+            help = "render per-pixel sample counts or variance as a false colour image"
+        )
+    )]
+    pub visualize: Option<Visualization>,
+
+    /// Watchdog threshold for stalled tiles.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "watchdog-factor",
+            value_name = "FACTOR",
+            default_value_t = 10.0,
+            value_parser = parse_watchdog_factor,
+            help = "report tiles taking longer than this multiple of the median tile time (0 = off)"
+        )
+    )]
+    pub watchdog_factor: Float,
+
+    /// Requeue stalled tiles.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "watchdog-requeue",
+            value_name = "WATCHDOG_REQUEUE",
+            help = "requeue stalled tiles at reduced quality"
+        )
+    )]
+    pub watchdog_requeue: bool,
+
+    /// Run worker threads at the lowest priority.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "background-priority",
+            value_name = "BACKGROUND_PRIORITY",
+            help = "lower worker thread priority so the desktop stays responsive"
+        )
+    )]
+    pub background_priority: bool,
+
+    /// CPU affinity for worker threads.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "cpu-affinity",
+            value_name = "CPUS",
+            value_delimiter = ',',
+            help = "comma separated list of logical CPUs worker threads may run on (linux only)"
+        )
+    )]
+    pub cpu_affinity: Vec<usize>,
+
+    /// Memory budget for textures and meshes.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "memory-budget",
+            value_name = "MB",
+            help = "memory budget in MiB for textures and meshes; large images are downsampled to fit"
+        )
+    )]
+    pub memory_budget: Option<usize>,
+
+    /// Directory for cached bounding volume hierarchies.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "bvh-cache",
+            value_name = "DIR",
+            help = "directory to cache built BVHs of large scenes in so repeated renders skip the build"
+        )
+    )]
+    pub bvh_cache: Option<PathBuf>,
+
+    /// Directories searched for assets referenced by scene files.
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "asset-path",
+            value_name = "DIR",
+            help = "directory searched for textures and included files not found next to the scene file; can be repeated"
+        )
+    )]
+    pub asset_paths: Vec<PathBuf>,
+}
+
+#[cfg(feature = "cli")]
+impl Default for AppConfig {
+    /// Returns the configuration used when only the required output path is
+    /// given on the command line. The output path is `image.png`.
+    fn default() -> Self {
+        AppConfig::parse_from([env!("CARGO_PKG_NAME"), "--out", "image.png"])
+    }
+}
+
+#[cfg(not(feature = "cli"))]
+impl Default for AppConfig {
+    /// Returns the configuration used when only the required output path is
+    /// given on the command line. The output path is `image.png`.
+    fn default() -> Self {
+        AppConfig {
+            command: None,
+            image_width: 200,
+            image_height: 100,
+            samples_per_pixel: 100,
+            progressive: None,
+            min_samples: 16,
+            max_samples: None,
+            noise_threshold: None,
+            max_depth: 50,
+            clamp_radiance: None,
+            integrator: Integrator::Path,
+            no_nee: false,
+            scenery: Scenery::FinalOneWeekend,
+            scene_seed: None,
+            scene_density: 1.0,
+            scene_count: None,
+            scene_file: None,
+            overrides: Vec::new(),
+            bvh_enabled: false,
+            auto_frame: false,
+            focus_object: None,
+            focus_look_at: false,
+            background: None,
+            sun_elevation: 45.0,
+            sun_azimuth: 0.0,
+            turbidity: 3.0,
+            seed: None,
+            output_path: "image.png".to_string(),
+            gamma: Gamma::Legacy,
+            num_threads: get_max_threads(),
+            tile_size: 32,
+            tile_order: TileOrder::Row,
+            gui: false,
+            contact_sheet: None,
+            serve: None,
+            save_scene: None,
+            trace_pixel: None,
+            trace_paths: 16,
+            anaglyph: false,
+            eye_separation: 1.0 / 30.0,
+            dof_adaptive: false,
+            dof_max_scale: 4.0,
+            gradient_domain: false,
+            aovs: Vec::new(),
+            sampler: SamplerType::Random,
+            passes: Vec::new(),
+            despeckle: false,
+            energy_audit: false,
+            preview_pass: false,
+            visualize: None,
+            watchdog_factor: 10.0,
+            watchdog_requeue: false,
+            background_priority: false,
+            cpu_affinity: Vec::new(),
+            memory_budget: None,
+            bvh_cache: None,
+            asset_paths: Vec::new(),
+        }
+    }
 }
 
 impl AppConfig {
@@ -131,20 +884,95 @@ impl AppConfig {
         self.num_threads
     }
 
+    /// Returns the scheduling options for worker threads.
+    pub fn worker_options(&self) -> WorkerOptions {
+        WorkerOptions {
+            nice: if self.background_priority {
+                Some(19)
+            } else {
+                None
+            },
+            cpu_affinity: self.cpu_affinity.clone(),
+        }
+    }
+
+    /// Returns the memory budget in bytes.
+    pub fn memory_budget_bytes(&self) -> Option<usize> {
+        self.memory_budget.map(|mb| mb.saturating_mul(1024 * 1024))
+    }
+
+    /// Returns a new context for loading a scene. Each scene load needs its
+    /// own context so memory reserved by one scene isn't counted against
+    /// the next.
+    pub fn load_context(&self) -> LoadContext {
+        LoadContext::new(
+            MemoryBudget::new(self.memory_budget_bytes()),
+            self.bvh_cache.clone().map(BvhCache::new),
+            Assets::new(self.asset_paths.clone()),
+        )
+    }
+
+    /// Returns the maximum number of samples per pixel. This is
+    /// `--max-samples` with adaptive sampling and `--samples-per-pixel`
+    /// otherwise.
+    pub fn max_samples(&self) -> u32 {
+        match (self.noise_threshold, self.max_samples) {
+            (Some(_), Some(n)) => n,
+            _ => self.samples_per_pixel,
+        }
+    }
+
+    /// Returns the number of passes over the image. Progressive rendering
+    /// takes enough passes for the pixels with the most samples. Otherwise
+    /// the image is rendered in one pass.
+    pub fn progressive_passes(&self) -> u32 {
+        match self.progressive {
+            Some(samples) => {
+                let scale = if self.dof_adaptive {
+                    self.dof_max_scale.max(1.0)
+                } else {
+                    1.0
+                };
+                let max_samples = (self.max_samples() as Float * scale).ceil() as u32;
+                max_samples.div_ceil(samples.max(1)).max(1)
+            }
+            None => 1,
+        }
+    }
+
+    /// Returns the seed and number of randomly generated objects of the
+    /// scene.
+    pub fn variation(&self) -> Variation {
+        Variation::new(self.scene_seed, self.scene_density, self.scene_count)
+    }
+
+    /// Returns the render settings needed to render tiles.
+    pub fn render_context(&self) -> RenderContext {
+        RenderContext::new(self.image_width, self.image_height, self.tile_size)
+    }
+
     pub fn n_tiles_x(&self) -> usize {
-        get_tile_count(self.tile_size, self.image_width)
+        self.render_context().n_tiles_x()
     }
 
     pub fn n_tiles_y(&self) -> usize {
-        get_tile_count(self.tile_size, self.image_height)
+        self.render_context().n_tiles_y()
     }
 
     pub fn n_tiles(&self) -> usize {
         self.n_tiles_x() * self.n_tiles_y()
     }
 
+    /// Returns the tile indices in the order they are rendered.
+    pub fn tile_indices(&self) -> Vec<usize> {
+        get_tile_order(self.tile_order, self.n_tiles_x(), self.n_tiles_y())
+    }
+
     pub fn tiles_pixel_bytes(&self) -> usize {
-        self.tile_size as usize * self.tile_size as usize * COLOR_CHANNELS
+        self.tile_size as usize
+            * self.tile_size as usize
+            * COLOR_CHANNELS
+            * std::mem::size_of::<f32>()
     }
 }
 
@@ -152,3 +980,150 @@ impl AppConfig {
 fn get_max_threads() -> usize {
     available_parallelism().map_or(1, |n| n.get())
 }
+
+/// Parse pixel coordinates of the form `x,y`.
+///
+/// * `s` - The pixel coordinates.
+#[cfg(feature = "cli")]
+fn parse_pixel(s: &str) -> Result<(u32, u32), String> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected X,Y, found `{}`", s))?;
+
+    let x = x.trim().parse::<u32>().map_err(|e| format!("x: {}", e))?;
+    let y = y.trim().parse::<u32>().map_err(|e| format!("y: {}", e))?;
+    Ok((x, y))
+}
+
+/// Parse the elevation of the sun in degrees.
+///
+/// * `s` - The elevation.
+#[cfg(feature = "cli")]
+fn parse_sun_elevation(s: &str) -> Result<Float, String> {
+    let v = s.trim().parse::<Float>().map_err(|e| e.to_string())?;
+    if (0.0..=90.0).contains(&v) {
+        Ok(v)
+    } else {
+        Err("must be in [0, 90]".to_string())
+    }
+}
+
+/// Parse the turbidity of the atmosphere.
+///
+/// * `s` - The turbidity.
+#[cfg(feature = "cli")]
+fn parse_turbidity(s: &str) -> Result<Float, String> {
+    let v = s.trim().parse::<Float>().map_err(|e| e.to_string())?;
+    if (2.0..=10.0).contains(&v) {
+        Ok(v)
+    } else {
+        Err("must be in [2, 10]".to_string())
+    }
+}
+
+/// Parse the density of randomly generated objects.
+///
+/// * `s` - The density.
+#[cfg(feature = "cli")]
+fn parse_scene_density(s: &str) -> Result<Float, String> {
+    let v = s.trim().parse::<Float>().map_err(|e| e.to_string())?;
+    if v >= 0.0 {
+        Ok(v)
+    } else {
+        Err("must not be negative".to_string())
+    }
+}
+
+/// Parse the multiple of the median tile time after which tiles are
+/// reported as stalled.
+///
+/// * `s` - The factor.
+#[cfg(feature = "cli")]
+fn parse_watchdog_factor(s: &str) -> Result<Float, String> {
+    let v = s.trim().parse::<Float>().map_err(|e| e.to_string())?;
+    if v.is_finite() && v >= 0.0 {
+        Ok(v)
+    } else {
+        Err("must be a finite number that is not negative".to_string())
+    }
+}
+
+/// Parse an AOV. AOVs need OpenEXR output because motion vectors can be
+/// negative and depth can be infinite, which Radiance HDR cannot store.
+///
+/// * `s` - The AOV name.
+#[cfg(feature = "cli")]
+fn parse_aov(s: &str) -> Result<Aov, String> {
+    if !cfg!(feature = "exr") {
+        return Err("writing AOVs requires the `exr` feature".to_string());
+    }
+    s.parse::<Aov>()
+}
+
+/// Parse the output transfer function. This is either `srgb`, `2.0-legacy`
+/// for the gamma 2.0 used by earlier versions or a positive gamma value.
+///
+/// * `s` - The transfer function.
+#[cfg(feature = "cli")]
+fn parse_gamma(s: &str) -> Result<Gamma, String> {
+    match s.trim().to_lowercase().as_str() {
+        "srgb" => Ok(Gamma::Srgb),
+        "2.0-legacy" | "legacy" => Ok(Gamma::Legacy),
+        v => match v.parse::<Float>() {
+            Ok(g) if g > 0.0 => Ok(Gamma::Power(g)),
+            Ok(_) => Err("must be positive".to_string()),
+            Err(_) => Err("expected a gamma value, `srgb` or `2.0-legacy`".to_string()),
+        },
+    }
+}
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+    use crate::common::ColourStorage;
+    use crate::object::UvMapping;
+    use clap::ValueEnum;
+    use std::fmt::Display;
+
+    /// Returns the names of the variants as displayed and as parsed by clap.
+    fn names<T: ValueEnum + Display>() -> Vec<(String, String)> {
+        T::value_variants()
+            .iter()
+            .map(|v| {
+                (
+                    v.to_string(),
+                    v.to_possible_value().unwrap().get_name().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    /// The names displayed and parsed without clap match the command line.
+    #[test]
+    fn enum_names_match_command_line() {
+        let all = [
+            names::<Aov>(),
+            names::<ColourStorage>(),
+            names::<Integrator>(),
+            names::<SamplerType>(),
+            names::<Scenery>(),
+            names::<TileOrder>(),
+            names::<UvMapping>(),
+            names::<Visualization>(),
+        ];
+        for (displayed, parsed) in all.iter().flatten() {
+            assert_eq!(displayed, parsed);
+        }
+
+        assert_eq!(Scenery::ALL.len(), Scenery::value_variants().len());
+        for aov in Aov::value_variants() {
+            assert_eq!(aov.to_string().parse::<Aov>(), Ok(*aov));
+        }
+        for storage in ColourStorage::value_variants() {
+            assert_eq!(storage.to_string().parse::<ColourStorage>(), Ok(*storage));
+        }
+        for mapping in UvMapping::value_variants() {
+            assert_eq!(mapping.to_string().parse::<UvMapping>(), Ok(*mapping));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! # Backplate
+//!
+//! A library for compositing renders over a photo. Camera rays that don't
+//! hit any object show the photo stretched over the image instead of the
+//! background or environment map, which still light the scene.
+
+use super::{Colour, ColourBuffer, ColourStorage, Float, MemoryBudget};
+use image::ImageFormat;
+use std::fmt;
+use std::sync::Arc;
+
+/// Models a photo behind the objects of a scene.
+pub struct Backplate {
+    /// Width.
+    width: usize,
+
+    /// Height.
+    height: usize,
+
+    /// Linear colour values stored in row-major order starting at the top.
+    pixels: ColourBuffer,
+
+    /// Path to the image.
+    path: String,
+}
+
+impl Backplate {
+    /// Load a backplate. Colours of 8-bit images are converted to linear
+    /// values so they come out unchanged after the gamma correction of the
+    /// rendered image. High dynamic range images like `.hdr` and `.exr`
+    /// files are used as is.
+    ///
+    /// * `path` - Path to the image.
+    /// * `budget` - Memory budget the image is downsampled to fit in.
+    pub fn new(path: &str, budget: &MemoryBudget) -> Result<Arc<Backplate>, String> {
+        // Downsample the image if it doesn't fit in the memory budget.
+        let storage = ColourStorage::Half;
+        let mut img = budget
+            .open_image(path, storage.bytes_per_pixel())?
+            .into_rgb32f();
+
+        let is_hdr = matches!(
+            ImageFormat::from_path(path),
+            Ok(ImageFormat::Hdr) | Ok(ImageFormat::OpenExr)
+        );
+        if !is_hdr {
+            // Undo the gamma 2 correction applied to rendered images.
+            for p in img.pixels_mut() {
+                p.0 = p.0.map(|c| c * c);
+            }
+        }
+
+        Ok(Arc::new(Backplate {
+            width: img.width() as usize,
+            height: img.height() as usize,
+            pixels: ColourBuffer::from_image(&img, storage),
+            path: path.to_string(),
+        }))
+    }
+
+    /// Returns the path used to load the backplate.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the colour of the backplate at a point on the image.
+    ///
+    /// * `s` - Horizontal position in `[0, 1]` from the left edge.
+    /// * `t` - Vertical position in `[0, 1]` from the bottom edge.
+    pub fn value(&self, s: Float, t: Float) -> Colour {
+        let i = (s.clamp(0.0, 1.0) * self.width as Float) as usize;
+        let j = ((1.0 - t.clamp(0.0, 1.0)) * self.height as Float) as usize;
+
+        let i = i.min(self.width - 1);
+        let j = j.min(self.height - 1);
+        self.pixels.get(j * self.width + i)
+    }
+}
+
+impl fmt::Display for Backplate {
+    /// Display the backplate parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "backplate(path: {}, width: {}, height: {})",
+            self.path, self.width, self.height
+        )
+    }
+}
+
+impl fmt::Debug for Backplate {
+    /// Display the backplate parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Backplate")
+            .field("path", &self.path)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
@@ -0,0 +1,166 @@
+//! # Environment
+//!
+//! A library for lighting a scene with an equirectangular environment map.
+
+use super::{
+    Colour, ColourBuffer, ColourStorage, Distribution2D, Float, MemoryBudget, Sampler, Vec3, PI,
+    TWO_PI,
+};
+use std::fmt;
+use std::sync::Arc;
+
+/// Models an environment map surrounding the scene. Rays that don't hit any
+/// object pick up the colour of the map in their direction.
+pub struct Environment {
+    /// Width.
+    width: usize,
+
+    /// Height.
+    height: usize,
+
+    /// Linear colour values stored in row-major order starting at the top.
+    pixels: ColourBuffer,
+
+    /// Path to the image.
+    path: String,
+
+    /// Scale factor applied to the colour values.
+    intensity: Float,
+
+    /// Distribution proportional to the luminance of each pixel weighted by
+    /// the solid angle it covers.
+    distribution: Distribution2D,
+}
+
+impl Environment {
+    /// Load an equirectangular environment map. Any format supported by the
+    /// `image` crate can be used but HDR formats like `.hdr` and `.exr` give
+    /// the best results since they don't clip bright light sources.
+    ///
+    /// * `path` - Path to the image.
+    /// * `intensity` - Scale factor applied to the colour values.
+    /// * `storage` - Storage format for the colour values.
+    /// * `budget` - Memory budget the image is downsampled to fit in.
+    pub fn new(
+        path: &str,
+        intensity: Float,
+        storage: ColourStorage,
+        budget: &MemoryBudget,
+    ) -> Result<Arc<Environment>, String> {
+        // Downsample the image if it doesn't fit in the memory budget.
+        let img = budget
+            .open_image(path, storage.bytes_per_pixel())?
+            .into_rgb32f();
+
+        let width = img.width() as usize;
+        let height = img.height() as usize;
+
+        let pixels = ColourBuffer::from_image(&img, storage);
+
+        // Weight by sin(θ) to account for the stretching of rows near the poles.
+        // Use the decoded values so sampling matches what is rendered.
+        let func: Vec<Float> = (0..pixels.len())
+            .map(|i| {
+                let theta = PI * ((i / width) as Float + 0.5) / height as Float;
+                pixels.get(i).luminance().max(0.0) * theta.sin()
+            })
+            .collect();
+
+        let distribution = Distribution2D::new(&func, width, height);
+
+        Ok(Arc::new(Environment {
+            width,
+            height,
+            pixels,
+            path: path.to_string(),
+            intensity,
+            distribution,
+        }))
+    }
+
+    /// Returns the path, intensity and storage format used to load the
+    /// environment map.
+    pub fn source(&self) -> (&str, Float, ColourStorage) {
+        (&self.path, self.intensity, self.pixels.storage())
+    }
+
+    /// Returns the colour of the environment in a given direction.
+    ///
+    /// * `direction` - Direction.
+    pub fn value(&self, direction: Vec3) -> Colour {
+        let (u, v) = direction_to_uv(direction);
+
+        let i = ((u * self.width as Float) as usize).min(self.width - 1);
+        let j = ((v * self.height as Float) as usize).min(self.height - 1);
+
+        self.intensity * self.pixels.get(j * self.width + i)
+    }
+
+    /// Returns the solid angle probability density of sampling a direction.
+    ///
+    /// * `direction` - Direction.
+    pub fn pdf_value(&self, direction: Vec3) -> Float {
+        let (u, v) = direction_to_uv(direction);
+
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            0.0
+        } else {
+            self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+        }
+    }
+
+    /// Returns a random direction sampled proportional to the brightness of
+    /// the environment.
+    pub fn random(&self) -> Vec3 {
+        let (u0, u1) = Sampler::get_2d();
+        let ((u, v), _) = self.distribution.sample_continuous(u0, u1);
+        uv_to_direction(u, v)
+    }
+}
+
+impl fmt::Display for Environment {
+    /// Display the environment parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl fmt::Debug for Environment {
+    /// Display the environment parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Environment")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("path", &self.path)
+            .field("intensity", &self.intensity)
+            .field("pixels", &self.pixels)
+            .finish()
+    }
+}
+
+/// Map a direction to equirectangular image coordinates with `v = 0` at the
+/// top (+y). This matches the texture coordinates used for spheres.
+///
+/// * `direction` - Direction.
+fn direction_to_uv(direction: Vec3) -> (Float, Float) {
+    let d = direction.unit_vector();
+    let phi = d.z().atan2(d.x());
+    let theta = d.y().clamp(-1.0, 1.0).acos();
+    (1.0 - (phi + PI) / TWO_PI, theta / PI)
+}
+
+/// Map equirectangular image coordinates to a unit direction.
+///
+/// * `u` - Horizontal image coordinate in [0, 1).
+/// * `v` - Vertical image coordinate in [0, 1) with `v = 0` at the top.
+fn uv_to_direction(u: Float, v: Float) -> Vec3 {
+    let phi = (1.0 - u) * TWO_PI - PI;
+    let theta = v * PI;
+    let sin_theta = theta.sin();
+    Vec3::new(sin_theta * phi.cos(), theta.cos(), sin_theta * phi.sin())
+}
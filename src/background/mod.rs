@@ -3,7 +3,22 @@
 //! A library for providing colour values for rays that don't intersect
 //! any object in the scene.
 
-use super::algebra::{Colour, Ray};
+mod backplate;
+mod environment;
+mod sky;
+
+use super::algebra::{Colour, Ray, Vec3};
+use super::common::{
+    ColourBuffer, ColourStorage, Distribution2D, Float, MemoryBudget, Sampler, PI, TWO_PI,
+};
+use super::light::{ArcLight, DirectionalLight};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+
+/// Re-exports.
+pub use self::backplate::Backplate;
+pub use self::environment::Environment;
+pub use self::sky::Sky;
 
 /// Function type accepts a ray and returns background colour.
 pub type BackgroundFn = fn(ray: &Ray) -> Colour;
@@ -24,3 +39,43 @@ pub fn gradient_background(ray: &Ray) -> Colour {
     let t = 0.5 * (unit_direction.y() + 1.0);
     (1.0 - t) * Colour::new(1.0, 1.0, 1.0) + t * Colour::new(0.5, 0.7, 1.0)
 }
+
+/// Backgrounds that can be selected on the command line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum BackgroundKind {
+    /// Black background.
+    Black,
+
+    /// Blue to white gradient.
+    Gradient,
+
+    /// Preetham daylight sky with a sun.
+    SunSky,
+}
+
+/// Built-in backgrounds by name as used in scene files.
+const BACKGROUNDS: [(&str, BackgroundFn); 2] = [
+    ("black", black_background),
+    ("gradient", gradient_background),
+];
+
+/// Returns the background with the given name.
+///
+/// * `name` - Name of the background.
+pub fn background_from_name(name: &str) -> Option<BackgroundFn> {
+    BACKGROUNDS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, background)| *background)
+}
+
+/// Returns the name of a built-in background.
+///
+/// * `background` - The background.
+pub fn background_name(background: BackgroundFn) -> Option<&'static str> {
+    BACKGROUNDS
+        .iter()
+        .find(|(_, b)| std::ptr::fn_addr_eq(*b, background))
+        .map(|(name, _)| *name)
+}
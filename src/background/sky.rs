@@ -0,0 +1,242 @@
+//! # Sky
+//!
+//! A library for the Preetham analytic daylight sky model. The colour of
+//! the sky depends on the direction of the sun and the turbidity, the haze
+//! of the atmosphere, from 2 for a clear sky to 10 for a hazy one. The sun
+//! itself is a directional light attenuated by the same atmosphere.
+//!
+//! A. J. Preetham, P. Shirley, B. Smits. "A Practical Analytic Model for
+//! Daylight", SIGGRAPH 1999.
+
+use super::{ArcLight, Colour, DirectionalLight, Float, Vec3, PI};
+use std::fmt;
+use std::sync::Arc;
+
+/// Scales sky luminance in kcd/m² to scene units.
+const SKY_SCALE: Float = 0.1;
+
+/// Irradiance of the sun above the atmosphere in scene units.
+const SUN_IRRADIANCE: Float = 10.0;
+
+/// Wavelengths of the red, green and blue channels in micrometres.
+const WAVELENGTHS: [Float; 3] = [0.65, 0.55, 0.45];
+
+/// Coefficients of the Perez sky luminance distribution.
+#[derive(Debug, Copy, Clone)]
+struct Perez {
+    a: Float,
+    b: Float,
+    c: Float,
+    d: Float,
+    e: Float,
+}
+
+impl Perez {
+    /// Returns the relative luminance of a sky direction.
+    ///
+    /// * `cos_theta` - Cosine of the angle between the direction and zenith.
+    /// * `gamma` - Angle between the direction and the sun.
+    fn value(&self, cos_theta: Float, gamma: Float) -> Float {
+        let cos_gamma = gamma.cos();
+        (1.0 + self.a * (self.b / cos_theta).exp())
+            * (1.0 + self.c * (self.d * gamma).exp() + self.e * cos_gamma * cos_gamma)
+    }
+}
+
+/// Models a daylight sky.
+#[derive(Clone)]
+pub struct Sky {
+    /// Unit vector towards the sun.
+    sun_direction: Vec3,
+
+    /// Haze of the atmosphere.
+    turbidity: Float,
+
+    /// Scale factor applied to the sky colour.
+    intensity: Float,
+
+    /// Scale factor applied to the sun. Zero disables the sun.
+    sun_intensity: Float,
+
+    /// Perez coefficients for luminance `Y` and chromaticities `x`, `y`.
+    perez: [Perez; 3],
+
+    /// Zenith luminance `Y` and chromaticities `x`, `y` divided by the
+    /// Perez function at the zenith.
+    zenith: [Float; 3],
+}
+
+impl Sky {
+    /// Create a new sky.
+    ///
+    /// * `sun_elevation` - Angle of the sun above the horizon in degrees,
+    ///   clamped to `[0, 90]`.
+    /// * `sun_azimuth` - Angle of the sun from the +z axis towards the +x
+    ///   axis in degrees.
+    /// * `turbidity` - Haze of the atmosphere, clamped to `[2, 10]`.
+    /// * `intensity` - Scale factor applied to the sky colour.
+    /// * `sun_intensity` - Scale factor applied to the sun. Zero disables
+    ///   the sun.
+    pub fn new(
+        sun_elevation: Float,
+        sun_azimuth: Float,
+        turbidity: Float,
+        intensity: Float,
+        sun_intensity: Float,
+    ) -> Arc<Sky> {
+        let elevation = sun_elevation.clamp(0.0, 90.0).to_radians();
+        let azimuth = sun_azimuth.to_radians();
+        let sun_direction = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            elevation.cos() * azimuth.cos(),
+        );
+
+        let t = turbidity.clamp(2.0, 10.0);
+        let perez = [
+            Perez {
+                a: 0.1787 * t - 1.4630,
+                b: -0.3554 * t + 0.4275,
+                c: -0.0227 * t + 5.3251,
+                d: 0.1206 * t - 2.5771,
+                e: -0.0670 * t + 0.3703,
+            },
+            Perez {
+                a: -0.0193 * t - 0.2592,
+                b: -0.0665 * t + 0.0008,
+                c: -0.0004 * t + 0.2125,
+                d: -0.0641 * t - 0.8989,
+                e: -0.0033 * t + 0.0452,
+            },
+            Perez {
+                a: -0.0167 * t - 0.2608,
+                b: -0.0950 * t + 0.0092,
+                c: -0.0079 * t + 0.2102,
+                d: -0.0441 * t - 1.6537,
+                e: -0.0109 * t + 0.0529,
+            },
+        ];
+
+        // Zenith luminance in kcd/m² and chromaticities.
+        let theta_s = PI / 2.0 - elevation;
+        let (t2, s, s2, s3) = (
+            t * t,
+            theta_s,
+            theta_s * theta_s,
+            theta_s * theta_s * theta_s,
+        );
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_x = t2 * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s)
+            + t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s + 0.00394)
+            + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s + 0.25886);
+        let zenith_yc = t2 * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s)
+            + t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s + 0.00516)
+            + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s + 0.26688);
+
+        let zenith = [
+            zenith_y.max(0.0) / perez[0].value(1.0, theta_s),
+            zenith_x / perez[1].value(1.0, theta_s),
+            zenith_yc / perez[2].value(1.0, theta_s),
+        ];
+
+        Arc::new(Sky {
+            sun_direction,
+            turbidity: t,
+            intensity,
+            sun_intensity,
+            perez,
+            zenith,
+        })
+    }
+
+    /// Returns the parameters used to create the sky: sun elevation and
+    /// azimuth in degrees, turbidity, intensity and sun intensity.
+    pub fn source(&self) -> (Float, Float, Float, Float, Float) {
+        let d = self.sun_direction;
+        (
+            d.y().clamp(-1.0, 1.0).asin().to_degrees(),
+            d.x().atan2(d.z()).to_degrees(),
+            self.turbidity,
+            self.intensity,
+            self.sun_intensity,
+        )
+    }
+
+    /// Returns the colour of the sky in a given direction. Directions below
+    /// the horizon see the sky at the horizon.
+    ///
+    /// * `direction` - Direction.
+    pub fn value(&self, direction: Vec3) -> Colour {
+        let v = direction.unit_vector();
+        let cos_theta = v.y().max(0.001);
+        let gamma = v.dot(self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let luminance = self.zenith[0] * self.perez[0].value(cos_theta, gamma);
+        let x = self.zenith[1] * self.perez[1].value(cos_theta, gamma);
+        let y = self.zenith[2] * self.perez[2].value(cos_theta, gamma);
+        if y.is_nan() || y <= 0.0 {
+            return Colour::zero();
+        }
+
+        // Yxy to XYZ to linear sRGB.
+        let big_x = x / y * luminance;
+        let big_z = (1.0 - x - y) / y * luminance;
+        let r = 3.2404542 * big_x - 1.5371385 * luminance - 0.4985314 * big_z;
+        let g = -0.9692660 * big_x + 1.8760108 * luminance + 0.0415560 * big_z;
+        let b = 0.0556434 * big_x - 0.2040259 * luminance + 1.0572252 * big_z;
+
+        Colour::new(r.max(0.0), g.max(0.0), b.max(0.0)) * (SKY_SCALE * self.intensity)
+    }
+
+    /// Returns the sun as a directional light or `None` if it is disabled
+    /// or below the horizon. Sunlight is reddened by the Rayleigh and
+    /// aerosol scattering along its path through the atmosphere.
+    pub fn sun(&self) -> Option<ArcLight> {
+        let cos_theta = self.sun_direction.y();
+        if self.sun_intensity <= 0.0 || cos_theta <= 0.0 {
+            return None;
+        }
+
+        // Relative optical mass of the air the sunlight passes through.
+        let theta = cos_theta.acos().to_degrees();
+        let mass = 1.0 / (cos_theta + 0.15 * (93.885 - theta).powf(-1.253));
+
+        // Ångström turbidity coefficient.
+        let beta = 0.04608 * self.turbidity - 0.04586;
+
+        let mut rgb = [0.0; 3];
+        for (c, lambda) in rgb.iter_mut().zip(WAVELENGTHS.iter()) {
+            let rayleigh = 0.008735 * lambda.powf(-4.08);
+            let aerosol = beta * lambda.powf(-1.3);
+            *c = (-mass * (rayleigh + aerosol)).exp();
+        }
+
+        let irradiance = Colour::new(rgb[0], rgb[1], rgb[2])
+            * (SUN_IRRADIANCE * self.intensity * self.sun_intensity);
+        Some(DirectionalLight::new(-self.sun_direction, irradiance))
+    }
+}
+
+impl fmt::Display for Sky {
+    /// Display the sky parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl fmt::Debug for Sky {
+    /// Display the sky parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sky")
+            .field("sun_direction", &self.sun_direction)
+            .field("turbidity", &self.turbidity)
+            .field("intensity", &self.intensity)
+            .field("sun_intensity", &self.sun_intensity)
+            .finish()
+    }
+}
@@ -1,12 +1,19 @@
 //! # Camera
 //!
 //! A library for handling cameras that use a thin lens approximation to
-//! handle defocus blur.
+//! handle defocus blur. The camera can turn during the shutter interval for
+//! rotational motion blur and shake like a handheld camera.
 
 #![allow(dead_code)]
-use super::algebra::{Point3, Ray, Vec3};
-use super::common::{Float, Random};
+mod shake;
+
+use super::algebra::{Mat4, Point3, Quat, Ray, Vec3};
+use super::common::{Float, Sampler, INFINITY};
 use std::fmt;
+use toml::Table;
+
+// Re-exports.
+pub use self::shake::Shake;
 
 #[derive(Clone)]
 pub struct Camera {
@@ -25,6 +32,9 @@ pub struct Camera {
     // Radius of camera lens.
     lens_radius: Float,
 
+    /// The distance to focal plane.
+    focus_dist: Float,
+
     /// Orthonomal basis vector u describing the orientation.
     u: Vec3,
 
@@ -39,6 +49,19 @@ pub struct Camera {
 
     /// Keeps track of end time for motion blur.
     time1: Float,
+
+    /// Depth of the near clipping plane.
+    near: Float,
+
+    /// Depth of the far clipping plane.
+    far: Float,
+
+    /// Rotation of the camera about its origin at the end time relative to
+    /// the start time.
+    rotation: Quat,
+
+    /// Procedural shake applied on top of the camera's motion.
+    shake: Option<Shake>,
 }
 
 impl fmt::Display for Camera {
@@ -49,18 +72,23 @@ impl fmt::Display for Camera {
         write!(
             f,
             "camera(lower_left_corner: {}, horizontal: {}, vertical: {}, \
-                origin: {}, lens_radius: {}, u: {}, v: {}, w: {}, \
-                time0: {}, time1: {})",
+                origin: {}, lens_radius: {}, focus_dist: {}, u: {}, v: {}, w: {}, \
+                time0: {}, time1: {}, near: {}, far: {}, rotation: {}, shake: {:?})",
             self.lower_left_corner,
             self.horizontal,
             self.vertical,
             self.origin,
             self.lens_radius,
+            self.focus_dist,
             self.u,
             self.v,
             self.w,
             self.time0,
-            self.time1
+            self.time1,
+            self.near,
+            self.far,
+            self.rotation,
+            self.shake
         )
     }
 }
@@ -73,11 +101,16 @@ impl fmt::Debug for Camera {
             .field("vertical", &self.vertical)
             .field("origin", &self.origin)
             .field("lens_radius", &self.lens_radius)
+            .field("focus_dist", &self.focus_dist)
             .field("u", &self.u)
             .field("v", &self.v)
             .field("w", &self.w)
             .field("time0", &self.time0)
             .field("time1", &self.time1)
+            .field("near", &self.near)
+            .field("far", &self.far)
+            .field("rotation", &self.rotation)
+            .field("shake", &self.shake)
             .finish()
     }
 }
@@ -122,14 +155,373 @@ impl Camera {
             horizontal: u * (2.0 * half_width * focus_dist),
             vertical: v * (2.0 * half_height * focus_dist),
             lens_radius: aperture / 2.0,
+            focus_dist,
             u,
             v,
             w,
             time0,
             time1,
+            near: 0.0,
+            far: INFINITY,
+            rotation: Quat::IDENTITY,
+            shake: None,
         }
     }
 
+    /// Returns the camera turning during the shutter interval to look at
+    /// another point by the end time. The orientation is interpolated with
+    /// spherical linear interpolation and the camera stays where it is.
+    ///
+    /// * `lookat` - Point towards which camera is looking at the end time.
+    /// * `vup` - The vector representing the up direction at the end time.
+    pub fn with_end_orientation(&self, lookat: Point3, vup: Vec3) -> Camera {
+        let w = (self.origin - lookat).unit_vector();
+        let u = vup.cross(w).unit_vector();
+        let v = w.cross(u);
+
+        let rotation = basis(u, v, w) * basis(self.u, self.v, self.w).transpose();
+        Camera {
+            rotation: Quat::from_mat4(&rotation),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the camera with procedural shake.
+    ///
+    /// * `shake` - The shake.
+    pub fn with_shake(&self, shake: Shake) -> Camera {
+        Camera {
+            shake: Some(shake),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the camera with near and far clipping planes. Camera rays
+    /// only see geometry between the planes.
+    ///
+    /// * `near` - Depth of the near clipping plane.
+    /// * `far` - Depth of the far clipping plane.
+    pub fn with_clipping(&self, near: Float, far: Float) -> Camera {
+        Camera {
+            near,
+            far,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the range of the ray parameter between the clipping planes
+    /// for a camera ray. Rays that don't start between the planes or point
+    /// away from them return an empty range.
+    ///
+    /// * `ray` - The camera ray.
+    pub fn clip_range(&self, ray: &Ray) -> (Float, Float) {
+        if self.near <= 0.0 && self.far == INFINITY {
+            return (0.0, INFINITY);
+        }
+
+        // Depth along the ray changes linearly with the ray parameter.
+        let depth = self.depth(ray.origin);
+        let rate = -ray.direction.dot(self.w);
+        if rate <= 0.0 {
+            return (0.0, 0.0);
+        }
+
+        let t_near = ((self.near - depth) / rate).max(0.0);
+        let t_far = (self.far - depth) / rate;
+        (t_near, t_far)
+    }
+
+    /// Returns a camera for one eye of a stereo pair. The eye is moved along
+    /// the camera's horizontal axis while keeping the same image plane so
+    /// both eyes converge at the focus distance (off-axis stereo).
+    ///
+    /// * `separation` - Horizontal offset of the eye as a fraction of the
+    ///   focus distance. Negative values are for the left eye.
+    pub fn stereo_eye(&self, separation: Float) -> Camera {
+        Camera {
+            origin: self.origin + self.u * (separation * self.focus_dist),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the camera moved along its own axes. The point it looks at
+    /// moves with it.
+    ///
+    /// * `right` - Distance to move right.
+    /// * `up` - Distance to move up.
+    /// * `forward` - Distance to move forward.
+    pub fn translate(&self, right: Float, up: Float, forward: Float) -> Camera {
+        let offset = self.u * right + self.v * up - self.w * forward;
+        Camera {
+            origin: self.origin + offset,
+            lower_left_corner: self.lower_left_corner + offset,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the camera orbited around the point it looks at on the focal
+    /// plane. Yaw turns around the camera's up axis and pitch around its
+    /// horizontal axis.
+    ///
+    /// * `yaw` - Yaw angle in degrees.
+    /// * `pitch` - Pitch angle in degrees.
+    pub fn orbit(&self, yaw: Float, pitch: Float) -> Camera {
+        let look_at = self.origin - self.w * self.focus_dist;
+
+        let (yaw, pitch) = (yaw.to_radians(), pitch.to_radians());
+        let w = rotate(rotate(self.w, self.v, yaw), self.u, pitch);
+        let vup = rotate(self.v, self.u, pitch);
+
+        let vfov = 2.0 * (0.5 * self.vertical.length() / self.focus_dist).atan();
+        let aspect_ratio = self.horizontal.length() / self.vertical.length();
+
+        let camera = Camera::new(
+            look_at + w * self.focus_dist,
+            look_at,
+            vup,
+            vfov.to_degrees(),
+            aspect_ratio,
+            2.0 * self.lens_radius,
+            self.focus_dist,
+            self.time0,
+            self.time1,
+        );
+        Camera {
+            rotation: self.rotation,
+            shake: self.shake,
+            ..camera.with_clipping(self.near, self.far)
+        }
+    }
+
+    /// Returns the camera moved along its viewing direction so a sphere fills
+    /// the narrower of the horizontal and vertical fields of view. The camera
+    /// looks at and focuses on the centre of the sphere.
+    ///
+    /// * `centre` - Centre of the sphere.
+    /// * `radius` - Radius of the sphere.
+    pub fn frame(&self, centre: Point3, radius: Float) -> Camera {
+        let vfov = 2.0 * (0.5 * self.vertical.length() / self.focus_dist).atan();
+        let hfov = 2.0 * (0.5 * self.horizontal.length() / self.focus_dist).atan();
+        let aspect_ratio = self.horizontal.length() / self.vertical.length();
+
+        let distance = radius / (0.5 * vfov.min(hfov)).sin();
+
+        let camera = Camera::new(
+            centre + self.w * distance,
+            centre,
+            self.v,
+            vfov.to_degrees(),
+            aspect_ratio,
+            2.0 * self.lens_radius,
+            distance,
+            self.time0,
+            self.time1,
+        );
+        Camera {
+            rotation: self.rotation,
+            shake: self.shake,
+            ..camera.with_clipping(self.near, self.far)
+        }
+    }
+
+    /// Returns the camera focused on a point. The camera stays where it is
+    /// and either keeps its viewing direction or turns to look at the point.
+    /// Returns `None` if the point is behind a camera that keeps its viewing
+    /// direction.
+    ///
+    /// * `p` - The point.
+    /// * `look_at` - Whether to turn the camera to look at the point.
+    pub fn focus_on(&self, p: Point3, look_at: bool) -> Option<Camera> {
+        let (look_at, focus_dist) = if look_at {
+            (p, (p - self.origin).length())
+        } else {
+            let depth = self.depth(p);
+            (self.origin - self.w * depth, depth)
+        };
+        if focus_dist <= 0.0 {
+            return None;
+        }
+
+        let vfov = 2.0 * (0.5 * self.vertical.length() / self.focus_dist).atan();
+        let aspect_ratio = self.horizontal.length() / self.vertical.length();
+
+        let camera = Camera::new(
+            self.origin,
+            look_at,
+            self.v,
+            vfov.to_degrees(),
+            aspect_ratio,
+            2.0 * self.lens_radius,
+            focus_dist,
+            self.time0,
+            self.time1,
+        );
+        Some(Camera {
+            rotation: self.rotation,
+            shake: self.shake,
+            ..camera.with_clipping(self.near, self.far)
+        })
+    }
+
+    /// Returns true if the lens has a non-zero aperture and causes defocus blur.
+    pub fn has_defocus(&self) -> bool {
+        self.lens_radius > 0.0
+    }
+
+    /// Returns the ray through the centre of the lens for the given parametric
+    /// coordinates along the image plane at the start time. This is the ray
+    /// of a pinhole camera and is useful for estimating depth.
+    ///
+    /// * `s`: Horizontal parameter.
+    /// * `t`: Vertical parameter.
+    pub fn get_centre_ray(&self, s: Float, t: Float) -> Ray {
+        Ray::new(
+            self.origin,
+            self.lower_left_corner + self.horizontal * s + self.vertical * t - self.origin,
+            self.time0,
+        )
+    }
+
+    /// Returns the position of the camera.
+    pub fn origin(&self) -> Point3 {
+        self.origin
+    }
+
+    /// Returns the distance to the focal plane.
+    pub fn focus_dist(&self) -> Float {
+        self.focus_dist
+    }
+
+    /// Returns the start and end time of the shutter interval.
+    pub fn shutter(&self) -> (Float, Float) {
+        (self.time0, self.time1)
+    }
+
+    /// Project a point onto the image plane through the centre of the lens.
+    /// Returns the parametric coordinates `(s, t)` along the image plane or
+    /// `None` if the point is behind the camera. This is the inverse of
+    /// `get_centre_ray()`.
+    ///
+    /// * `p` - The point.
+    pub fn project(&self, p: Point3) -> Option<(Float, Float)> {
+        let depth = self.depth(p);
+        if depth <= 0.0 {
+            return None;
+        }
+
+        let q = self.origin + (p - self.origin) * (self.focus_dist / depth);
+        let d = q - self.lower_left_corner;
+
+        let s = d.dot(self.horizontal) / self.horizontal.length_squared();
+        let t = d.dot(self.vertical) / self.vertical.length_squared();
+        Some((s, t))
+    }
+
+    /// Project a point onto the image plane through the centre of the lens
+    /// with the camera turned and shaken as it is at the given time. Returns
+    /// the parametric coordinates `(s, t)` along the image plane or `None` if
+    /// the point is behind the camera.
+    ///
+    /// * `p` - The point.
+    /// * `time` - Time within the shutter interval.
+    pub fn project_at(&self, p: Point3, time: Float) -> Option<(Float, Float)> {
+        let (q, origin) = self.pose(time);
+
+        // Move the point back into the frame of the camera at the start time.
+        self.project(self.origin + q.conjugate().rotate(p - origin))
+    }
+
+    /// Returns the rotation of the camera about its origin relative to the
+    /// start time and the position of the origin at the given time.
+    ///
+    /// * `time` - Time within the shutter interval.
+    fn pose(&self, time: Float) -> (Quat, Point3) {
+        let mut q = Quat::IDENTITY.slerp(self.rotation, self.shutter_fraction(time));
+        let mut origin = self.origin;
+        if let Some(shake) = &self.shake {
+            let (angles, translation) = shake.offset(time);
+            let shake_q = Quat::from_axis_angle(self.u, angles.x())
+                * Quat::from_axis_angle(self.v, angles.y())
+                * Quat::from_axis_angle(self.w, angles.z());
+            q = shake_q * q;
+            origin = origin + self.u * translation.x() + self.v * translation.y()
+                - self.w * translation.z();
+        }
+        (q, origin)
+    }
+
+    /// Returns how far a time is through the shutter interval in [0, 1].
+    ///
+    /// * `time` - The time.
+    fn shutter_fraction(&self, time: Float) -> Float {
+        if self.time1 > self.time0 {
+            ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns a description of the camera in the scene file format. The
+    /// viewing parameters are recovered from the image plane so the camera
+    /// can be rebuilt with the same image aspect ratio.
+    pub fn describe(&self) -> Table {
+        let look_at = self.origin - self.w * self.focus_dist;
+        let vfov = 2.0 * (0.5 * self.vertical.length() / self.focus_dist).atan();
+
+        let mut t = Table::new();
+        t.insert("look_from".into(), self.origin.into());
+        t.insert("look_at".into(), look_at.into());
+        t.insert("vup".into(), self.v.into());
+        t.insert("vfov".into(), vfov.to_degrees().into());
+        t.insert("aperture".into(), (2.0 * self.lens_radius).into());
+        t.insert("focus_dist".into(), self.focus_dist.into());
+        t.insert("time0".into(), self.time0.into());
+        t.insert("time1".into(), self.time1.into());
+        if self.near > 0.0 {
+            t.insert("near".into(), self.near.into());
+        }
+        if self.far < INFINITY {
+            t.insert("far".into(), self.far.into());
+        }
+        if self.rotation != Quat::IDENTITY {
+            let w = self.rotation.rotate(self.w);
+            let look_at = self.origin - w * self.focus_dist;
+            t.insert("look_at1".into(), look_at.into());
+            t.insert("vup1".into(), self.rotation.rotate(self.v).into());
+        }
+        if let Some(shake) = &self.shake {
+            t.insert("shake".into(), shake.describe().into());
+        }
+        t
+    }
+
+    /// Returns the depth of a point along the camera's viewing direction.
+    ///
+    /// * `p` - The point.
+    pub fn depth(&self, p: Point3) -> Float {
+        (self.origin - p).dot(self.w)
+    }
+
+    /// Returns the diameter of the circle of confusion for a point at the
+    /// given depth as a fraction of the image width. Points on the focal plane
+    /// are sharp and return 0. Points at infinity approach the ratio of the
+    /// aperture to the width of the image plane.
+    ///
+    /// * `depth` - Depth of the point along the camera's viewing direction.
+    pub fn circle_of_confusion(&self, depth: Float) -> Float {
+        if depth <= 0.0 {
+            return 0.0;
+        }
+
+        let defocus = if depth.is_infinite() {
+            1.0
+        } else {
+            (depth - self.focus_dist).abs() / depth
+        };
+
+        2.0 * self.lens_radius * defocus / self.horizontal.length()
+    }
+
     /// Returns a ray for the given parametric coordinates along the image
     /// image plane. The ray's time paramter is set at random value between
     /// `time0` and `time1` for motion blur effect.
@@ -137,14 +529,44 @@ impl Camera {
     /// * `s`: Horizontal parameter.
     /// * `t`: Vertical parameter.
     pub fn get_ray(&self, s: Float, t: Float) -> Ray {
-        let rd = Random::vec3_in_unit_disk() * self.lens_radius;
+        let rd = Sampler::unit_disk() * self.lens_radius;
         let offset = self.u * rd.x() + self.v * rd.y();
-        let time = Random::sample_in_range(self.time0, self.time1);
+        let f = Sampler::get_1d();
+        let time = self.time0 + f * (self.time1 - self.time0);
 
-        Ray::new(
-            self.origin + offset,
-            self.lower_left_corner + self.horizontal * s + self.vertical * t - self.origin - offset,
-            time,
-        )
+        let direction =
+            self.lower_left_corner + self.horizontal * s + self.vertical * t - self.origin - offset;
+
+        if self.rotation == Quat::IDENTITY && self.shake.is_none() {
+            return Ray::new(self.origin + offset, direction, time);
+        }
+
+        // Turn the lens and image plane about the origin.
+        let (q, origin) = self.pose(time);
+        Ray::new(origin + q.rotate(offset), q.rotate(direction), time)
     }
 }
+
+/// Returns the matrix whose columns are the basis vectors of a frame.
+///
+/// * `u` - First basis vector.
+/// * `v` - Second basis vector.
+/// * `w` - Third basis vector.
+fn basis(u: Vec3, v: Vec3, w: Vec3) -> Mat4 {
+    Mat4::new([
+        [u.x(), v.x(), w.x(), 0.0],
+        [u.y(), v.y(), w.y(), 0.0],
+        [u.z(), v.z(), w.z(), 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ])
+}
+
+/// Rotate a vector around a unit axis using Rodrigues' rotation formula.
+///
+/// * `v` - The vector.
+/// * `axis` - Unit vector along the axis of rotation.
+/// * `angle` - Angle in radians.
+fn rotate(v: Vec3, axis: Vec3, angle: Float) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    v * cos + axis.cross(v) * sin + axis * (axis.dot(v) * (1.0 - cos))
+}
@@ -0,0 +1,107 @@
+//! # Shake
+//!
+//! A library for procedural camera shake that imitates a handheld camera.
+//! Random offsets are placed at regular intervals in time and interpolated
+//! with Catmull-Rom splines so the motion is smooth. The shake is a function
+//! of absolute time so it blurs a single frame over its shutter interval
+//! and continues from one frame of an animation to the next.
+
+use super::{Float, Vec3};
+use toml::Table;
+
+/// Number of independent channels: 3 rotations and 3 translations.
+const CHANNELS: u64 = 6;
+
+/// Models procedural camera shake.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Shake {
+    /// Largest rotation about each camera axis in degrees.
+    pub rotation: Float,
+
+    /// Largest translation along each camera axis.
+    pub translation: Float,
+
+    /// Number of random offsets per unit of time.
+    pub frequency: Float,
+
+    /// Seed of the random offsets.
+    pub seed: u64,
+}
+
+impl Shake {
+    /// Create a new camera shake.
+    ///
+    /// * `rotation` - Largest rotation about each camera axis in degrees.
+    /// * `translation` - Largest translation along each camera axis.
+    /// * `frequency` - Number of random offsets per unit of time.
+    /// * `seed` - Seed of the random offsets.
+    pub fn new(rotation: Float, translation: Float, frequency: Float, seed: u64) -> Shake {
+        Shake {
+            rotation,
+            translation,
+            frequency,
+            seed,
+        }
+    }
+
+    /// Returns the rotation about the camera's horizontal, vertical and
+    /// viewing axes in degrees and the translation along the camera's
+    /// right, up and forward directions at a time.
+    ///
+    /// * `time` - The time.
+    pub fn offset(&self, time: Float) -> (Vec3, Vec3) {
+        let x = time * self.frequency;
+        let knot = x.floor();
+        let f = x - knot;
+        let knot = knot as i64;
+
+        let channel = |c: u64| {
+            let p = |k: i64| self.random(k, c);
+            catmull_rom(p(knot - 1), p(knot), p(knot + 1), p(knot + 2), f)
+        };
+
+        let angles = Vec3::new(channel(0), channel(1), channel(2)) * self.rotation;
+        let translation = Vec3::new(channel(3), channel(4), channel(5)) * self.translation;
+        (angles, translation)
+    }
+
+    /// Returns a random value in `[-1, 1]` for a knot and channel.
+    ///
+    /// * `knot` - Index of the knot.
+    /// * `channel` - Index of the channel.
+    fn random(&self, knot: i64, channel: u64) -> Float {
+        let mut z = self
+            .seed
+            .wrapping_add((knot as u64).wrapping_mul(CHANNELS).wrapping_add(channel))
+            .wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+
+        2.0 * ((z >> 11) as Float / (1_u64 << 53) as Float) - 1.0
+    }
+
+    /// Returns a description of the shake in the scene file format.
+    pub fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("rotation".into(), self.rotation.into());
+        t.insert("translation".into(), self.translation.into());
+        t.insert("frequency".into(), self.frequency.into());
+        t.insert("seed".into(), (self.seed as i64).into());
+        t
+    }
+}
+
+/// Interpolate between `p1` and `p2` with a uniform Catmull-Rom spline.
+///
+/// * `p0` - Value before `p1`.
+/// * `p1` - Value at the start of the segment.
+/// * `p2` - Value at the end of the segment.
+/// * `p3` - Value after `p2`.
+/// * `f` - Fraction of the way along the segment.
+fn catmull_rom(p0: Float, p1: Float, p2: Float, p3: Float, f: Float) -> Float {
+    0.5 * (2.0 * p1
+        + (p2 - p0) * f
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * f * f
+        + (3.0 * (p1 - p2) + p3 - p0) * f * f * f)
+}
@@ -0,0 +1,77 @@
+//! # Blackbody
+//!
+//! A library for converting the colour temperature of a blackbody emitter
+//! into a linear sRGB colour, e.g. 3200K for tungsten lights and 6500K for
+//! daylight.
+
+use super::{Colour, Float};
+
+/// Second radiation constant `hc/k` in metre kelvin.
+const C2: Float = 1.4387769e-2;
+
+/// Shortest wavelength integrated in nanometres.
+const LAMBDA_MIN: Float = 380.0;
+
+/// Longest wavelength integrated in nanometres.
+const LAMBDA_MAX: Float = 780.0;
+
+/// Integration step in nanometres.
+const LAMBDA_STEP: Float = 5.0;
+
+/// Returns the linear sRGB colour of a blackbody at a temperature scaled
+/// to unit luminance so the temperature only changes the hue of a light and
+/// not its brightness.
+///
+/// * `kelvin` - Temperature in kelvin.
+pub fn blackbody(kelvin: Float) -> Colour {
+    // Integrate Planck's law against the CIE 1931 colour matching functions.
+    let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+
+    let steps = ((LAMBDA_MAX - LAMBDA_MIN) / LAMBDA_STEP) as usize;
+    for i in 0..=steps {
+        let lambda = LAMBDA_MIN + i as Float * LAMBDA_STEP;
+        let radiance = planck(lambda, kelvin);
+        let (xb, yb, zb) = cie_1931(lambda);
+        x += radiance * xb;
+        y += radiance * yb;
+        z += radiance * zb;
+    }
+
+    if y <= 0.0 || !y.is_finite() {
+        return Colour::zero();
+    }
+    let (x, z) = (x / y, z / y);
+
+    // CIE XYZ to linear sRGB (D65 white point). Out of gamut colours of
+    // very low temperatures are clipped.
+    let r = 3.2404542 * x - 1.5371385 - 0.4985314 * z;
+    let g = -0.9692660 * x + 1.8760108 + 0.0415560 * z;
+    let b = 0.0556434 * x - 0.2040259 + 1.0572252 * z;
+    Colour::new(r.max(0.0), g.max(0.0), b.max(0.0))
+}
+
+/// Returns the spectral radiance of a blackbody up to a constant factor.
+///
+/// * `lambda` - Wavelength in nanometres.
+/// * `kelvin` - Temperature in kelvin.
+fn planck(lambda: Float, kelvin: Float) -> Float {
+    let l = lambda * 1.0e-9;
+    1.0 / (l.powi(5) * ((C2 / (l * kelvin)).exp() - 1.0))
+}
+
+/// Returns the CIE 1931 colour matching functions at a wavelength using the
+/// multi-lobe Gaussian fit by Wyman, Sloan and Shirley (2013).
+///
+/// * `lambda` - Wavelength in nanometres.
+fn cie_1931(lambda: Float) -> (Float, Float, Float) {
+    let g = |mu: Float, sigma1: Float, sigma2: Float| {
+        let t = (lambda - mu) / if lambda < mu { sigma1 } else { sigma2 };
+        (-0.5 * t * t).exp()
+    };
+
+    let x =
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2);
+    let y = 0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1);
+    let z = 1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8);
+    (x, y, z)
+}
@@ -0,0 +1,223 @@
+//! # ColourBuffer
+//!
+//! A library for storing linear HDR colour values in compact formats that
+//! are decoded on the fly.
+
+use super::{Colour, Float};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use half::f16;
+use image::Rgb32FImage;
+use std::fmt;
+use std::str::FromStr;
+
+/// Number of mantissa bits per channel in the RGB9E5 format.
+const RGB9E5_MANTISSA_BITS: i32 = 9;
+
+/// Exponent bias in the RGB9E5 format.
+const RGB9E5_EXP_BIAS: i32 = 15;
+
+/// Largest exponent in the RGB9E5 format.
+const RGB9E5_MAX_EXP: i32 = 31;
+
+/// Largest value representable in the RGB9E5 format.
+const RGB9E5_MAX: Float = 65408.0;
+
+/// Storage formats for linear colour values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum ColourStorage {
+    /// 64-bit floats per channel (24 bytes per pixel).
+    Full,
+
+    /// 16-bit floats per channel (6 bytes per pixel).
+    Half,
+
+    /// Shared exponent with 9-bit mantissas (4 bytes per pixel).
+    Rgb9e5,
+}
+
+impl ColourStorage {
+    /// Returns the number of bytes used to store a pixel.
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            ColourStorage::Full => std::mem::size_of::<Colour>(),
+            ColourStorage::Half => std::mem::size_of::<[f16; 3]>(),
+            ColourStorage::Rgb9e5 => std::mem::size_of::<u32>(),
+        }
+    }
+}
+
+impl fmt::Display for ColourStorage {
+    /// Display the storage format name.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ColourStorage::Full => "full",
+            ColourStorage::Half => "half",
+            ColourStorage::Rgb9e5 => "rgb9e5",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ColourStorage {
+    type Err = String;
+
+    /// Parse the storage format name ignoring case.
+    ///
+    /// * `s` - The name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "full" => Ok(ColourStorage::Full),
+            "half" => Ok(ColourStorage::Half),
+            "rgb9e5" => Ok(ColourStorage::Rgb9e5),
+            _ => Err(format!("unknown storage format `{}`", s)),
+        }
+    }
+}
+
+/// A buffer of linear colour values stored in row-major order.
+#[derive(Clone)]
+pub enum ColourBuffer {
+    /// Uncompressed colours.
+    Full(Vec<Colour>),
+
+    /// Half float colours.
+    Half(Vec<[f16; 3]>),
+
+    /// Packed shared exponent colours.
+    Rgb9e5(Vec<u32>),
+}
+
+impl ColourBuffer {
+    /// Encode an image into the requested storage format.
+    ///
+    /// * `img` - The image.
+    /// * `storage` - Storage format.
+    pub fn from_image(img: &Rgb32FImage, storage: ColourStorage) -> ColourBuffer {
+        let colours = img
+            .pixels()
+            .map(|p| Colour::new(p[0] as Float, p[1] as Float, p[2] as Float));
+
+        match storage {
+            ColourStorage::Full => ColourBuffer::Full(colours.collect()),
+            ColourStorage::Half => ColourBuffer::Half(
+                colours
+                    .map(|c| {
+                        [
+                            f16::from_f64(c.x()),
+                            f16::from_f64(c.y()),
+                            f16::from_f64(c.z()),
+                        ]
+                    })
+                    .collect(),
+            ),
+            ColourStorage::Rgb9e5 => ColourBuffer::Rgb9e5(colours.map(encode_rgb9e5).collect()),
+        }
+    }
+
+    /// Returns the decoded colour at an index.
+    ///
+    /// * `idx` - Index of the pixel.
+    pub fn get(&self, idx: usize) -> Colour {
+        match self {
+            ColourBuffer::Full(p) => p[idx],
+            ColourBuffer::Half(p) => {
+                let [r, g, b] = p[idx];
+                Colour::new(r.to_f64(), g.to_f64(), b.to_f64())
+            }
+            ColourBuffer::Rgb9e5(p) => decode_rgb9e5(p[idx]),
+        }
+    }
+
+    /// Returns the number of pixels.
+    pub fn len(&self) -> usize {
+        match self {
+            ColourBuffer::Full(p) => p.len(),
+            ColourBuffer::Half(p) => p.len(),
+            ColourBuffer::Rgb9e5(p) => p.len(),
+        }
+    }
+
+    /// Returns true if there are no pixels.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the storage format.
+    pub fn storage(&self) -> ColourStorage {
+        match self {
+            ColourBuffer::Full(_) => ColourStorage::Full,
+            ColourBuffer::Half(_) => ColourStorage::Half,
+            ColourBuffer::Rgb9e5(_) => ColourStorage::Rgb9e5,
+        }
+    }
+
+    /// Returns the number of bytes used by the pixels.
+    pub fn bytes(&self) -> usize {
+        self.len() * self.storage().bytes_per_pixel()
+    }
+}
+
+impl fmt::Debug for ColourBuffer {
+    /// Display the buffer parameters without the pixels.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ColourBuffer")
+            .field("storage", &self.storage())
+            .field("len", &self.len())
+            .field("bytes", &self.bytes())
+            .finish()
+    }
+}
+
+/// Pack a colour into the RGB9E5 shared exponent format. Negative values and
+/// NaN are stored as zero and values that are too large are clamped.
+///
+/// * `c` - The colour.
+fn encode_rgb9e5(c: Colour) -> u32 {
+    let clamp_channel = |x: Float| {
+        if x.is_nan() {
+            0.0
+        } else {
+            x.clamp(0.0, RGB9E5_MAX)
+        }
+    };
+
+    let r = clamp_channel(c.x());
+    let g = clamp_channel(c.y());
+    let b = clamp_channel(c.z());
+    let max = r.max(g).max(b);
+
+    // Pick the smallest exponent that represents the largest channel.
+    let mut exp = (max.log2().floor() as i32).max(-RGB9E5_EXP_BIAS - 1) + 1 + RGB9E5_EXP_BIAS;
+    let mut scale = Float::powi(2.0, exp - RGB9E5_EXP_BIAS - RGB9E5_MANTISSA_BITS);
+
+    // Rounding may overflow the mantissa so use the next exponent instead.
+    if (max / scale + 0.5).floor() as i32 == 1 << RGB9E5_MANTISSA_BITS {
+        exp += 1;
+        scale *= 2.0;
+    }
+    let exp = exp.min(RGB9E5_MAX_EXP) as u32;
+
+    let mantissa = |x: Float| ((x / scale + 0.5).floor() as u32).min(511);
+
+    mantissa(r) | (mantissa(g) << 9) | (mantissa(b) << 18) | (exp << 27)
+}
+
+/// Unpack a colour from the RGB9E5 shared exponent format.
+///
+/// * `p` - The packed colour.
+fn decode_rgb9e5(p: u32) -> Colour {
+    let exp = (p >> 27) as i32;
+    let scale = Float::powi(2.0, exp - RGB9E5_EXP_BIAS - RGB9E5_MANTISSA_BITS);
+
+    Colour::new(
+        (p & 0x1ff) as Float * scale,
+        ((p >> 9) & 0x1ff) as Float * scale,
+        ((p >> 18) & 0x1ff) as Float * scale,
+    )
+}
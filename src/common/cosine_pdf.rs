@@ -3,7 +3,7 @@
 //! A library to handle cosine probability density function.
 
 #![allow(dead_code)]
-use super::{Float, Random, Vec3, ONB, PDF, PI};
+use super::{Float, Sampler, Vec3, ONB, PDF, PI};
 
 /// Models the cosine probability density function.
 #[derive(Debug, Clone)]
@@ -37,6 +37,6 @@ impl PDF for CosinePDF {
 
     /// Returns a random direction based on PDF.
     fn generate(&self) -> Vec3 {
-        self.uvw.local_from_vec3(&Random::cosine_direction())
+        self.uvw.local_from_vec3(&Sampler::cosine_direction())
     }
 }
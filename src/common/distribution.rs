@@ -0,0 +1,155 @@
+//! # Distribution
+//!
+//! A library for sampling piecewise-constant 1-d and 2-d distributions
+//! using cumulative distribution function (CDF) tables.
+
+use super::{clamp, Float};
+
+/// Models a piecewise-constant 1-d distribution over [0, 1).
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    /// Function values.
+    func: Vec<Float>,
+
+    /// Cumulative distribution function with `func.len() + 1` entries.
+    cdf: Vec<Float>,
+
+    /// Integral of the function over [0, 1).
+    func_int: Float,
+}
+
+impl Distribution1D {
+    /// Create a new distribution from non-negative function values. If the
+    /// function is zero everywhere a uniform distribution is used.
+    ///
+    /// * `func` - Function values.
+    pub fn new(func: Vec<Float>) -> Distribution1D {
+        let n = func.len();
+
+        let mut cdf = vec![0.0; n + 1];
+        for i in 1..=n {
+            cdf[i] = cdf[i - 1] + func[i - 1] / n as Float;
+        }
+
+        let func_int = cdf[n];
+        if func_int == 0.0 {
+            for (i, c) in cdf.iter_mut().enumerate() {
+                *c = i as Float / n as Float;
+            }
+        } else {
+            for c in cdf.iter_mut() {
+                *c /= func_int;
+            }
+        }
+
+        Distribution1D {
+            func,
+            cdf,
+            func_int,
+        }
+    }
+
+    /// Returns the number of function values.
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// Sample the distribution. Returns the sampled value in [0, 1), its
+    /// probability density and the index of the segment it falls in.
+    ///
+    /// * `u` - Uniform random value in [0, 1).
+    pub fn sample_continuous(&self, u: Float) -> (Float, Float, usize) {
+        // Find the last CDF entry that is <= u.
+        let offset = self
+            .cdf
+            .partition_point(|c| *c <= u)
+            .saturating_sub(1)
+            .min(self.count() - 1);
+
+        // Offset within the segment.
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0.0 {
+            du /= width;
+        }
+
+        let pdf = self.pdf_at(offset);
+        let x = (offset as Float + du) / self.count() as Float;
+        (x, pdf, offset)
+    }
+
+    /// Returns the probability density for a segment.
+    ///
+    /// * `offset` - Index of the segment.
+    fn pdf_at(&self, offset: usize) -> Float {
+        if self.func_int > 0.0 {
+            self.func[offset] / self.func_int
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Models a piecewise-constant 2-d distribution over [0, 1)^2 by sampling a
+/// marginal distribution of rows and a conditional distribution within the
+/// selected row.
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    /// Conditional distribution of `u` for each row `v`.
+    conditional: Vec<Distribution1D>,
+
+    /// Marginal distribution of `v`.
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// Create a new distribution from non-negative function values stored in
+    /// row-major order.
+    ///
+    /// * `func` - Function values.
+    /// * `nu` - Number of values in a row.
+    /// * `nv` - Number of rows.
+    pub fn new(func: &[Float], nu: usize, nv: usize) -> Distribution2D {
+        let conditional: Vec<Distribution1D> = func
+            .chunks(nu)
+            .take(nv)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+
+        let marginal = Distribution1D::new(conditional.iter().map(|c| c.func_int).collect());
+
+        Distribution2D {
+            conditional,
+            marginal,
+        }
+    }
+
+    /// Sample the distribution. Returns the sampled `(u, v)` and its
+    /// probability density.
+    ///
+    /// * `u0` - Uniform random value in [0, 1) used to pick `u`.
+    /// * `u1` - Uniform random value in [0, 1) used to pick `v`.
+    pub fn sample_continuous(&self, u0: Float, u1: Float) -> ((Float, Float), Float) {
+        let (v, pdf1, row) = self.marginal.sample_continuous(u1);
+        let (u, pdf0, _) = self.conditional[row].sample_continuous(u0);
+        ((u, v), pdf0 * pdf1)
+    }
+
+    /// Returns the probability density at `(u, v)`.
+    ///
+    /// * `u` - Value in [0, 1).
+    /// * `v` - Value in [0, 1).
+    pub fn pdf(&self, u: Float, v: Float) -> Float {
+        let nu = self.conditional[0].count();
+        let nv = self.marginal.count();
+
+        let iu = clamp(u * nu as Float, 0.0, (nu - 1) as Float) as usize;
+        let iv = clamp(v * nv as Float, 0.0, (nv - 1) as Float) as usize;
+
+        if self.marginal.func_int > 0.0 {
+            self.conditional[iv].func[iu] / self.marginal.func_int
+        } else {
+            1.0
+        }
+    }
+}
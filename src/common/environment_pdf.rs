@@ -0,0 +1,38 @@
+//! # EnvironmentPDF
+//!
+//! A library to handle sampling directions towards bright regions of an
+//! environment map.
+
+use super::{Environment, Float, Vec3, PDF};
+use std::sync::Arc;
+
+/// Models the probability density function for sampling directions
+/// proportional to the luminance of an environment map.
+#[derive(Debug, Clone)]
+pub struct EnvironmentPDF {
+    /// The environment map.
+    environment: Arc<Environment>,
+}
+
+impl EnvironmentPDF {
+    /// Create a new environment map density function.
+    ///
+    /// * `environment` - The environment map.
+    pub fn new(environment: Arc<Environment>) -> EnvironmentPDF {
+        EnvironmentPDF { environment }
+    }
+}
+
+impl PDF for EnvironmentPDF {
+    /// Returns the value of a PDF at a location.
+    ///
+    /// * `direction` - Direction of surface normal.
+    fn value(&self, direction: Vec3) -> Float {
+        self.environment.pdf_value(direction)
+    }
+
+    /// Returns a random direction based on PDF.
+    fn generate(&self) -> Vec3 {
+        self.environment.random()
+    }
+}
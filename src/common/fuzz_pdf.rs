@@ -0,0 +1,63 @@
+//! # FuzzPDF
+//!
+//! A library to handle the probability density function of directions
+//! towards a point picked uniformly in a sphere around a unit vector, e.g.
+//! the blurred reflections of fuzzy metals.
+
+use super::{Float, Random, Vec3, PDF, PI};
+
+/// Models the density of the directions of `reflected + fuzz * p` where `p`
+/// is a uniform random point in the unit sphere.
+#[derive(Debug, Clone)]
+pub struct FuzzPDF {
+    /// Unit vector at the centre of the sphere.
+    reflected: Vec3,
+
+    /// Radius of the sphere.
+    fuzz: Float,
+}
+
+impl FuzzPDF {
+    /// Create a new density function. `fuzz` must be positive.
+    ///
+    /// * `reflected` - Unit vector at the centre of the sphere.
+    /// * `fuzz` - Radius of the sphere.
+    pub fn new(reflected: Vec3, fuzz: Float) -> FuzzPDF {
+        FuzzPDF { reflected, fuzz }
+    }
+}
+
+impl PDF for FuzzPDF {
+    /// Returns the value of a PDF at a location. This is the volume of the
+    /// sphere swept by the direction's solid angle divided by the volume of
+    /// the sphere.
+    ///
+    /// * `direction` - Scattered direction.
+    fn value(&self, direction: Vec3) -> Float {
+        let b = direction.unit_vector().dot(self.reflected);
+        let c = 1.0 - self.fuzz * self.fuzz;
+        let disc = b * b - c;
+        if disc < 0.0 {
+            return 0.0;
+        }
+
+        // Distances to where the direction enters and leaves the sphere. The
+        // direction starts inside the sphere if the fuzz is at least 1.
+        let s = disc.sqrt();
+        let t1 = b + s;
+        let t0 = if c > 0.0 { b - s } else { 0.0 };
+        if t1 <= 0.0 || t0 < 0.0 {
+            return 0.0;
+        }
+
+        // ∫ t² dt over the chord, factored to avoid cancellation when the
+        // sphere is small.
+        let swept = (t1 - t0) * (t1 * t1 + t1 * t0 + t0 * t0) / 3.0;
+        swept / (4.0 / 3.0 * PI * self.fuzz.powi(3))
+    }
+
+    /// Returns a random direction based on PDF.
+    fn generate(&self) -> Vec3 {
+        self.reflected + self.fuzz * Random::vec3_in_unit_sphere()
+    }
+}
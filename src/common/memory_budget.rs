@@ -0,0 +1,130 @@
+//! # MemoryBudget
+//!
+//! A library for limiting the memory used by textures and meshes.
+
+use super::Messages;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Tracks memory reserved by the textures and meshes of a scene. Clones
+/// share the same budget. Each scene load gets its own budget so nothing is
+/// carried over from one scene to the next.
+#[derive(Debug, Clone)]
+pub struct MemoryBudget {
+    /// Maximum number of bytes available to textures and meshes.
+    limit: usize,
+
+    /// Number of bytes reserved so far.
+    used: Arc<AtomicUsize>,
+
+    /// Receives messages about downsampled images.
+    messages: Messages,
+}
+
+impl Default for MemoryBudget {
+    /// Returns a budget without a limit.
+    fn default() -> Self {
+        MemoryBudget::new(None)
+    }
+}
+
+impl MemoryBudget {
+    /// Create a new memory budget.
+    ///
+    /// * `bytes` - Maximum number of bytes or `None` for no limit.
+    pub fn new(bytes: Option<usize>) -> MemoryBudget {
+        MemoryBudget {
+            limit: bytes.unwrap_or(usize::MAX),
+            used: Arc::new(AtomicUsize::new(0)),
+            messages: Messages::default(),
+        }
+    }
+
+    /// Report messages about downsampled images to the given messages.
+    ///
+    /// * `messages` - Receives the messages.
+    pub fn with_messages(self, messages: Messages) -> MemoryBudget {
+        MemoryBudget { messages, ..self }
+    }
+
+    /// Returns the number of bytes still available.
+    pub fn available(&self) -> usize {
+        self.limit.saturating_sub(self.used.load(Ordering::SeqCst))
+    }
+
+    /// Reserve memory for a mesh. Returns an error describing how to fix the
+    /// problem if the mesh does not fit in the remaining budget.
+    ///
+    /// * `bytes` - Number of bytes needed by the mesh.
+    /// * `name`  - Name of the mesh used in the error message.
+    pub fn reserve_mesh(&self, bytes: usize, name: &str) -> Result<(), String> {
+        if self.try_reserve(bytes) {
+            Ok(())
+        } else {
+            Err(format!(
+                "{}: mesh needs {} but only {} of the {} memory budget is left; \
+                 increase --memory-budget or simplify the mesh",
+                name,
+                format_bytes(bytes),
+                format_bytes(self.available()),
+                format_bytes(self.limit),
+            ))
+        }
+    }
+
+    /// Open an image texture and reserve memory for it. The size is read from
+    /// the image header before the pixels are decoded. If the image does not
+    /// fit in the remaining budget it is repeatedly halved until it does and
+    /// downsampled to that size as soon as it is decoded.
+    ///
+    /// * `path`            - Path to the image.
+    /// * `bytes_per_pixel` - Number of bytes each pixel will be stored in.
+    pub fn open_image(&self, path: &str, bytes_per_pixel: usize) -> Result<DynamicImage, String> {
+        let (width, height) =
+            image::image_dimensions(path).map_err(|e| format!("Unable to open {}: {}", path, e))?;
+        let image_bytes = |w: u32, h: u32| w as usize * h as usize * bytes_per_pixel;
+
+        let (mut w, mut h) = (width, height);
+        while !self.try_reserve(image_bytes(w, h)) {
+            if w == 1 && h == 1 {
+                // Nothing left to shrink. Account for it so the budget stays accurate.
+                self.used.fetch_add(bytes_per_pixel, Ordering::SeqCst);
+                break;
+            }
+
+            w = (w / 2).max(1);
+            h = (h / 2).max(1);
+        }
+
+        let img = image::open(path).map_err(|e| format!("Unable to open {}: {}", path, e))?;
+        if (w, h) == (width, height) {
+            return Ok(img);
+        }
+
+        self.messages.push(format!(
+            "Memory budget exceeded: downsampled {} from {}x{} to {}x{}",
+            path, width, height, w, h
+        ));
+        Ok(img.resize_exact(w, h, FilterType::Triangle))
+    }
+
+    /// Reserve bytes if they fit in the remaining budget.
+    ///
+    /// * `bytes` - Number of bytes.
+    fn try_reserve(&self, bytes: usize) -> bool {
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                used.checked_add(bytes).filter(|total| *total <= self.limit)
+            })
+            .is_ok()
+    }
+}
+
+/// Format a number of bytes in MiB.
+///
+/// * `bytes` - Number of bytes.
+fn format_bytes(bytes: usize) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
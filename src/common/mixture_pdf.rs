@@ -3,7 +3,7 @@
 //! A library to handle mixing probability density functions.
 
 #![allow(dead_code)]
-use super::{ArcPDF, Float, Random, Vec3, PDF};
+use super::{ArcPDF, Float, Sampler, Vec3, PDF};
 use std::fmt;
 use std::sync::Arc;
 
@@ -45,7 +45,7 @@ impl PDF for MixturePDF {
 
     /// Returns a random direction based on PDF.
     fn generate(&self) -> Vec3 {
-        if Random::sample::<Float>() < 0.5 {
+        if Sampler::get_1d() < 0.5 {
             self.p[0].generate()
         } else {
             self.p[1].generate()
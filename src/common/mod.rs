@@ -2,13 +2,24 @@
 //!
 //! A library of common utility functinos.
 
+mod blackbody;
+mod colour_buffer;
 mod cosine_pdf;
+mod distribution;
+mod environment_pdf;
+mod fuzz_pdf;
 mod hittable_pdf;
+mod memory_budget;
 mod mixture_pdf;
+mod progress;
 mod random;
+mod sampler;
+mod scratch;
+mod sphere_pdf;
 mod util;
 
-use super::algebra::{Point3, Vec3, ONB};
+use super::algebra::{Colour, Point3, Vec3, ONB};
+use super::background::Environment;
 use super::object::ArcHittable;
 use std::fmt;
 use std::sync::Arc;
@@ -31,14 +42,28 @@ pub const INFINITY: Float = std::f64::INFINITY;
 /// Used to offset ray positions and AAB boundaries to avoid intersection issues.
 pub const RAY_EPSILON: Float = 0.001;
 
+/// Ray offset relative to the largest coordinate of a scene used when the
+/// scene doesn't set one.
+pub const RAY_EPSILON_SCALE: Float = 1.0e-6;
+
 /// Minimum thickness of AABB used for 2-d shapes like planes.
 pub const MIN_THICKNESS: Float = 0.0001;
 
 /// Re-exports.
+pub use self::blackbody::blackbody;
+pub use self::colour_buffer::{ColourBuffer, ColourStorage};
 pub use self::cosine_pdf::CosinePDF;
+pub use self::distribution::Distribution2D;
+pub use self::environment_pdf::EnvironmentPDF;
+pub use self::fuzz_pdf::FuzzPDF;
 pub use self::hittable_pdf::HittablePDF;
+pub use self::memory_budget::MemoryBudget;
 pub use self::mixture_pdf::MixturePDF;
+pub use self::progress::{Messages, Progress};
 pub use self::random::Random;
+pub use self::sampler::{Sampler, SamplerType};
+pub use self::scratch::Scratch;
+pub use self::sphere_pdf::SpherePDF;
 pub use self::util::*;
 
 /// Probability density functions.
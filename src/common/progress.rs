@@ -0,0 +1,54 @@
+//! # Progress
+//!
+//! A library for reporting progress and messages to the caller instead of
+//! printing them, so applications embedding the renderer decide what is
+//! shown.
+
+use std::sync::{Arc, Mutex};
+
+/// Receives the progress of a render and messages about it, e.g. stalled
+/// tiles or the energy audit. Closures taking the fraction completed can be
+/// used when the messages aren't needed.
+pub trait Progress {
+    /// Called with the fraction completed as work finishes.
+    ///
+    /// * `fraction` - Fraction completed.
+    fn update(&self, fraction: f32);
+
+    /// Called with a message about the render. Messages are ignored by
+    /// default.
+    ///
+    /// * `_message` - The message.
+    fn message(&self, _message: &str) {}
+}
+
+impl<F: Fn(f32)> Progress for F {
+    /// Call the closure with the fraction completed.
+    ///
+    /// * `fraction` - Fraction completed.
+    fn update(&self, fraction: f32) {
+        self(fraction)
+    }
+}
+
+/// Collects messages for the caller to report once the work is done, e.g.
+/// while loading a scene. Clones share the same messages.
+#[derive(Debug, Clone, Default)]
+pub struct Messages {
+    /// The messages in the order they were added.
+    messages: Arc<Mutex<Vec<String>>>,
+}
+
+impl Messages {
+    /// Add a message.
+    ///
+    /// * `message` - The message.
+    pub fn push(&self, message: String) {
+        self.messages.lock().unwrap().push(message);
+    }
+
+    /// Remove and return the messages added so far.
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.messages.lock().unwrap())
+    }
+}
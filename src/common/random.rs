@@ -20,6 +20,10 @@ thread_local! {
     }
 }
 
+/// Snapshot of the state of the thread local random number generator.
+#[derive(Clone)]
+pub struct RandomState(ChaCha20Rng);
+
 /// Wraps some common random sample generation routines using a thread_rng().
 pub struct Random {}
 
@@ -31,6 +35,20 @@ impl Random {
         RNG.with(|rng| *rng.borrow_mut() = SeedableRng::seed_from_u64(s))
     }
 
+    /// Returns a snapshot of the random number generator state for the
+    /// current thread. Restoring it replays the same sequence of random
+    /// numbers, e.g. to trace correlated paths.
+    pub fn save() -> RandomState {
+        RNG.with(|rng| RandomState(rng.borrow().clone()))
+    }
+
+    /// Restore the random number generator state for the current thread.
+    ///
+    /// * `state` - A snapshot returned by `Random::save()`.
+    pub fn restore(state: &RandomState) {
+        RNG.with(|rng| *rng.borrow_mut() = state.0.clone())
+    }
+
     /// Returns a random value.
     pub fn sample<T>() -> T
     where
@@ -146,19 +164,6 @@ impl Random {
         }
     }
 
-    /// Returns a random point inside unit disk in the xy-plane.
-    pub fn vec3_in_unit_disk() -> Vec3 {
-        RNG.with(|rng| {
-            let mut r = rng.borrow_mut();
-            loop {
-                let p = Vec3::new(r.gen_range(-1.0..1.0), r.gen_range(-1.0..1.0), 0.0);
-                if p.length_squared() < 1.0 {
-                    break p;
-                }
-            }
-        })
-    }
-
     /// Shuffle a `Vec<usize>` in place.
     ///
     /// * `v` - Vector to shuffle.
@@ -175,47 +180,4 @@ impl Random {
             }
         })
     }
-
-    /// Returns a random vector based on p(direction) = cos(θ) / π.
-    pub fn cosine_direction() -> Vec3 {
-        RNG.with(|rng| {
-            let mut r = rng.borrow_mut();
-
-            let r1 = r.gen::<Float>();
-            let r2 = r.gen::<Float>();
-            let z = (1.0 - r2).sqrt();
-
-            let phi = TWO_PI * r1;
-
-            let r2_sqrt = r2.sqrt();
-            let x = phi.cos() * r2_sqrt;
-            let y = phi.sin() * r2_sqrt;
-
-            Vec3::new(x, y, z)
-        })
-    }
-
-    // Return a random vector uniformly sampled from a sphere’s solid angle
-    // from a point outside the sphere
-    //
-    // * `distance_squared` - Square of distance to a point from sphere center.
-    pub fn vec3_to_sphere(radius: Float, distance_squared: Float) -> Vec3 {
-        RNG.with(|rng| {
-            let mut r = rng.borrow_mut();
-
-            let r1 = r.gen::<Float>();
-            let r2 = r.gen::<Float>();
-
-            let r_squared_over_d_squared = radius * radius / distance_squared;
-            let z = 1.0 + r2 * ((1.0 - r_squared_over_d_squared).sqrt() - 1.0);
-
-            let phi = TWO_PI * r1;
-
-            let sqrt_one_minus_z_squared = (1.0 - z * z).sqrt();
-            let x = phi.cos() * sqrt_one_minus_z_squared;
-            let y = phi.sin() * sqrt_one_minus_z_squared;
-
-            Vec3::new(x, y, z)
-        })
-    }
 }
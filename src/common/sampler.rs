@@ -0,0 +1,361 @@
+//! # Sampler
+//!
+//! A library for generating stratified and low-discrepancy sample values.
+//!
+//! Samples are generated per pixel sample. Before tracing a sample the
+//! renderer calls `Sampler::start_sample()` and each subsequent call to
+//! `Sampler::get_1d()` or `Sampler::get_2d()` consumes the next dimension
+//! of the sample vector, e.g. pixel position, lens position, time and then
+//! the light and BSDF samples of each bounce. Outside a pixel sample, or
+//! with `SamplerType::Random`, independent random values are returned.
+
+use super::{Float, Random, Vec3, PI, TWO_PI};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use std::cell::RefCell;
+use std::fmt;
+
+/// Largest float below 1.
+const ONE_MINUS_EPSILON: Float = 1.0 - Float::EPSILON;
+
+/// Bases of the Halton sequence dimensions. Dimensions past these use
+/// random values.
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+/// Sample generation methods.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum SamplerType {
+    /// Independent uniform random values.
+    Random,
+
+    /// Jittered strata with the strata of each dimension shuffled.
+    Stratified,
+
+    /// Halton sequence with a random offset per pixel.
+    Halton,
+
+    /// Scrambled Sobol (0,2)-sequence for each pair of dimensions.
+    Sobol,
+}
+
+impl fmt::Display for SamplerType {
+    /// Display the sampler name as used on the command line.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SamplerType::Random => "random",
+            SamplerType::Stratified => "stratified",
+            SamplerType::Halton => "halton",
+            SamplerType::Sobol => "sobol",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// State of the pixel sample being traced.
+struct SampleState {
+    /// Sample generation method.
+    kind: SamplerType,
+
+    /// Hash of the pixel coordinates used to decorrelate pixels.
+    pixel_hash: u64,
+
+    /// Index of the sample within the pixel.
+    index: u32,
+
+    /// Maximum number of samples in the pixel.
+    count: u32,
+
+    /// Next dimension of the sample vector.
+    dimension: u32,
+}
+
+thread_local! {
+    /// The pixel sample being traced by this thread.
+    static STATE: RefCell<Option<SampleState>> = const { RefCell::new(None) };
+}
+
+/// Generates sample values for the pixel sample being traced.
+pub struct Sampler {}
+
+impl Sampler {
+    /// Start a new pixel sample on the current thread.
+    ///
+    /// * `kind` - Sample generation method.
+    /// * `x` - Pixel x-coordinate.
+    /// * `y` - Pixel y-coordinate.
+    /// * `index` - Index of the sample within the pixel.
+    /// * `count` - Maximum number of samples in the pixel.
+    pub fn start_sample(kind: SamplerType, x: u32, y: u32, index: u32, count: u32) {
+        STATE.with(|state| {
+            *state.borrow_mut() = Some(SampleState {
+                kind,
+                pixel_hash: mix_bits(((x as u64) << 32) | y as u64),
+                index,
+                count: count.max(1),
+                dimension: 0,
+            })
+        });
+    }
+
+    /// Finish the pixel sample. Subsequent values are independent random
+    /// values until the next sample is started.
+    pub fn finish() {
+        STATE.with(|state| *state.borrow_mut() = None);
+    }
+
+    /// Returns the next sample value in [0, 1).
+    pub fn get_1d() -> Float {
+        STATE.with(|state| match state.borrow_mut().as_mut() {
+            Some(s) if s.kind != SamplerType::Random => s.next_1d(),
+            _ => Random::sample::<Float>(),
+        })
+    }
+
+    /// Returns the next two-dimensional sample value in [0, 1)^2.
+    pub fn get_2d() -> (Float, Float) {
+        STATE.with(|state| match state.borrow_mut().as_mut() {
+            Some(s) if s.kind != SamplerType::Random => s.next_2d(),
+            _ => (Random::sample::<Float>(), Random::sample::<Float>()),
+        })
+    }
+
+    /// Returns a point in the unit disk in the xy-plane using the
+    /// concentric mapping of the next two-dimensional sample.
+    pub fn unit_disk() -> Vec3 {
+        let (u, v) = Sampler::get_2d();
+        let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+
+        if a == 0.0 && b == 0.0 {
+            return Vec3::zero();
+        }
+
+        let (r, theta) = if a.abs() > b.abs() {
+            (a, 0.25 * PI * (b / a))
+        } else {
+            (b, 0.5 * PI - 0.25 * PI * (a / b))
+        };
+
+        Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+    }
+
+    /// Returns a direction uniformly sampled from a sphere's solid angle as
+    /// seen from a point outside the sphere using the next two-dimensional
+    /// sample.
+    ///
+    /// * `radius` - Radius of the sphere.
+    /// * `distance_squared` - Square of distance to a point from sphere center.
+    pub fn vec3_to_sphere(radius: Float, distance_squared: Float) -> Vec3 {
+        let (r1, r2) = Sampler::get_2d();
+
+        let r_squared_over_d_squared = radius * radius / distance_squared;
+        let z = 1.0 + r2 * ((1.0 - r_squared_over_d_squared).sqrt() - 1.0);
+
+        let phi = TWO_PI * r1;
+
+        let sqrt_one_minus_z_squared = (1.0 - z * z).sqrt();
+        Vec3::new(
+            phi.cos() * sqrt_one_minus_z_squared,
+            phi.sin() * sqrt_one_minus_z_squared,
+            z,
+        )
+    }
+
+    /// Returns a direction based on p(direction) = cos(θ) / π using the next
+    /// two-dimensional sample.
+    pub fn cosine_direction() -> Vec3 {
+        let (r1, r2) = Sampler::get_2d();
+        let z = (1.0 - r2).sqrt();
+
+        let phi = TWO_PI * r1;
+
+        let r2_sqrt = r2.sqrt();
+        Vec3::new(phi.cos() * r2_sqrt, phi.sin() * r2_sqrt, z)
+    }
+}
+
+impl SampleState {
+    /// Returns a scramble value for a dimension that is unique to the pixel.
+    ///
+    /// * `dimension` - The dimension.
+    fn scramble(&self, dimension: u32) -> u64 {
+        mix_bits(self.pixel_hash ^ (dimension as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+    }
+
+    /// Returns the next sample value.
+    fn next_1d(&mut self) -> Float {
+        let dimension = self.dimension;
+        self.dimension += 1;
+
+        let scramble = self.scramble(dimension);
+
+        match self.kind {
+            SamplerType::Random => Random::sample::<Float>(),
+
+            SamplerType::Stratified => {
+                if self.index >= self.count {
+                    return Random::sample::<Float>();
+                }
+                let stratum = permute(self.index, self.count, scramble as u32);
+                (stratum as Float + Random::sample::<Float>()) / self.count as Float
+            }
+
+            SamplerType::Halton => match PRIMES.get(dimension as usize) {
+                Some(base) => {
+                    let offset = (scramble >> 11) as Float / (1u64 << 53) as Float;
+                    let x = radical_inverse(*base, self.index) + offset;
+                    (x - x.floor()).min(ONE_MINUS_EPSILON)
+                }
+                None => Random::sample::<Float>(),
+            },
+
+            SamplerType::Sobol => to_unit(self.index.reverse_bits() ^ scramble as u32),
+        }
+    }
+
+    /// Returns the next two-dimensional sample value.
+    fn next_2d(&mut self) -> (Float, Float) {
+        match self.kind {
+            SamplerType::Stratified => {
+                let dimension = self.dimension;
+                self.dimension += 2;
+
+                // Use the largest square grid of strata that fits in the
+                // sample count.
+                let n = (self.count as Float).sqrt().floor().max(1.0) as u32;
+                if self.index >= n * n {
+                    return (Random::sample::<Float>(), Random::sample::<Float>());
+                }
+
+                let stratum = permute(self.index, n * n, self.scramble(dimension) as u32);
+                let (sx, sy) = (stratum % n, stratum / n);
+                (
+                    (sx as Float + Random::sample::<Float>()) / n as Float,
+                    (sy as Float + Random::sample::<Float>()) / n as Float,
+                )
+            }
+
+            SamplerType::Sobol => {
+                let dimension = self.dimension;
+                self.dimension += 2;
+
+                let scramble = self.scramble(dimension);
+                (
+                    to_unit(self.index.reverse_bits() ^ scramble as u32),
+                    to_unit(sobol_second_dimension(self.index) ^ (scramble >> 32) as u32),
+                )
+            }
+
+            SamplerType::Random | SamplerType::Halton => (self.next_1d(), self.next_1d()),
+        }
+    }
+}
+
+/// Returns the radical inverse of an index in a base.
+///
+/// * `base` - The base.
+/// * `index` - The index.
+fn radical_inverse(base: u32, index: u32) -> Float {
+    let inv_base = 1.0 / base as Float;
+    let mut inv_base_n = 1.0;
+    let mut reversed: u64 = 0;
+    let mut a = index;
+
+    while a > 0 {
+        let next = a / base;
+        let digit = a - next * base;
+        reversed = reversed * base as u64 + digit as u64;
+        inv_base_n *= inv_base;
+        a = next;
+    }
+
+    (reversed as Float * inv_base_n).min(ONE_MINUS_EPSILON)
+}
+
+/// Returns the second dimension of the Sobol sequence as 32-bit fixed point.
+///
+/// * `index` - The index.
+fn sobol_second_dimension(index: u32) -> u32 {
+    let mut r = 0;
+    let mut v: u32 = 1 << 31;
+    let mut a = index;
+
+    while a != 0 {
+        if a & 1 != 0 {
+            r ^= v;
+        }
+        a >>= 1;
+        v ^= v >> 1;
+    }
+
+    r
+}
+
+/// Convert a 32-bit fixed point value to a float in [0, 1).
+///
+/// * `x` - The value.
+fn to_unit(x: u32) -> Float {
+    (x as Float / (1u64 << 32) as Float).min(ONE_MINUS_EPSILON)
+}
+
+/// Returns the element at index `i` of a random permutation of `0..l` chosen
+/// by `p`, using Kensler's hash based permutation so no storage is needed.
+///
+/// * `i` - Index to permute.
+/// * `l` - Length of the permutation.
+/// * `p` - Permutation seed.
+fn permute(i: u32, l: u32, p: u32) -> u32 {
+    if l <= 1 {
+        return 0;
+    }
+
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    let mut i = i;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+
+        if i < l {
+            break;
+        }
+    }
+
+    (i.wrapping_add(p)) % l
+}
+
+/// Mix the bits of a value with the SplitMix64 finalizer.
+///
+/// * `v` - The value.
+fn mix_bits(v: u64) -> u64 {
+    let mut z = v.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,56 @@
+//! # Scratch
+//!
+//! A library for per-thread scratch memory reused across rays.
+
+use super::Float;
+use std::cell::RefCell;
+
+thread_local! {
+    /// Free list of scratch buffers for the current thread. Buffers are
+    /// returned here after use so their capacity is reused by later rays.
+    static SCRATCH: RefCell<Vec<Scratch>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Scratch memory used by intersection routines in hot loops so they don't
+/// allocate on the heap for every ray.
+///
+/// Intersection routines can nest (e.g. a medium whose boundary is a BVH),
+/// so each call to `Scratch::with` gets its own buffer from a per-thread free
+/// list rather than borrowing a single shared one.
+#[derive(Debug, Default)]
+pub struct Scratch {
+    /// Stack of node indices for stackless/iterative BVH traversal.
+    pub traversal_stack: Vec<usize>,
+
+    /// List of parametric `(t_enter, t_exit)` intervals along a ray, for
+    /// constructive solid geometry and media.
+    pub intervals: Vec<(Float, Float)>,
+}
+
+impl Scratch {
+    /// Run `f` with scratch memory for the current thread. The buffers are
+    /// empty when `f` is called but retain capacity from previous uses.
+    ///
+    /// * `f` - Function that uses the scratch memory.
+    pub fn with<R, F>(f: F) -> R
+    where
+        F: FnOnce(&mut Scratch) -> R,
+    {
+        let mut scratch = SCRATCH
+            .with(|free| free.borrow_mut().pop())
+            .unwrap_or_default();
+
+        let result = f(&mut scratch);
+
+        scratch.clear();
+        SCRATCH.with(|free| free.borrow_mut().push(scratch));
+
+        result
+    }
+
+    /// Clear all buffers retaining their capacity.
+    fn clear(&mut self) {
+        self.traversal_stack.clear();
+        self.intervals.clear();
+    }
+}
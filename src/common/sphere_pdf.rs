@@ -0,0 +1,35 @@
+//! # SpherePDF
+//!
+//! A library to handle the uniform probability density function over the
+//! sphere of directions.
+
+use super::{Float, Sampler, Vec3, PDF, PI, TWO_PI};
+
+/// Models the uniform probability density function over all directions.
+#[derive(Debug, Clone, Default)]
+pub struct SpherePDF {}
+
+impl SpherePDF {
+    /// Create a new uniform density function over all directions.
+    pub fn new() -> SpherePDF {
+        SpherePDF {}
+    }
+}
+
+impl PDF for SpherePDF {
+    /// Returns the value of a PDF at a location.
+    ///
+    /// * `_direction` - Direction (ignored).
+    fn value(&self, _direction: Vec3) -> Float {
+        1.0 / (4.0 * PI)
+    }
+
+    /// Returns a random direction based on PDF.
+    fn generate(&self) -> Vec3 {
+        let (r1, r2) = Sampler::get_2d();
+        let z = 1.0 - 2.0 * r2;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = TWO_PI * r1;
+        Vec3::new(phi.cos() * r, phi.sin() * r, z)
+    }
+}
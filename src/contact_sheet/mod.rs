@@ -0,0 +1,116 @@
+//! # Contact sheet
+//!
+//! A library for rendering thumbnails of every scene into a labeled grid.
+
+mod font;
+
+use super::app_config::AppConfig;
+use super::common::Progress;
+use super::renderer::{render_batch, to_rgba_image, RenderJob};
+use super::scene::{Scene, Scenery};
+use font::{draw_text, GLYPH_HEIGHT};
+use image::{imageops, Rgba, RgbaImage};
+
+/// Thumbnail width in pixels.
+const THUMBNAIL_WIDTH: u32 = 192;
+
+/// Thumbnail height in pixels.
+const THUMBNAIL_HEIGHT: u32 = 108;
+
+/// Maximum samples per pixel used for thumbnails.
+const THUMBNAIL_SAMPLES: u32 = 16;
+
+/// Number of thumbnails per row.
+const COLUMNS: u32 = 4;
+
+/// Space between thumbnails in pixels.
+const PADDING: u32 = 4;
+
+/// Height of the label strip below each thumbnail in pixels.
+const LABEL_HEIGHT: u32 = GLYPH_HEIGHT + 2 * PADDING;
+
+/// Background colour of the contact sheet.
+const BACKGROUND: Rgba<u8> = Rgba([32, 32, 32, 255]);
+
+/// Colour of the labels.
+const LABEL_COLOUR: Rgba<u8> = Rgba([230, 230, 230, 255]);
+
+/// Render every scene at low resolution and sample count and composite them
+/// into a grid with each thumbnail labeled with the scene name.
+///
+/// The thumbnails are rendered concurrently on a shared thread pool. The
+/// remaining settings (e.g. maximum depth, bvh) are taken from `config`.
+///
+/// * `config`   - Program configuration.
+/// * `progress` - Receives the fraction of all thumbnails completed as tiles
+///   finish and messages about the render.
+pub fn render_contact_sheet(
+    config: &AppConfig,
+    progress: &dyn Progress,
+) -> Result<RgbaImage, String> {
+    let sceneries = Scenery::ALL;
+
+    let jobs: Vec<RenderJob> = sceneries
+        .iter()
+        .map(|scenery| {
+            let mut config = config.clone();
+            config.scenery = *scenery;
+            config.image_width = THUMBNAIL_WIDTH;
+            config.image_height = THUMBNAIL_HEIGHT;
+            config.samples_per_pixel = config.max_samples().min(THUMBNAIL_SAMPLES);
+            config.max_samples = None;
+
+            let context = config.load_context();
+            let scene = Scene::generate(
+                config.scenery,
+                &config.variation(),
+                config.image_width,
+                config.image_height,
+                config.bvh_enabled,
+                &context,
+            );
+            for message in context.messages.take() {
+                progress.message(&message);
+            }
+            RenderJob::new(scene, config)
+        })
+        .collect();
+
+    let thumbnails = render_batch(jobs, config.threads(), progress)?;
+
+    let n = thumbnails.len() as u32;
+    let columns = COLUMNS.min(n).max(1);
+    let rows = n.div_ceil(columns);
+
+    let cell_width = THUMBNAIL_WIDTH + PADDING;
+    let cell_height = THUMBNAIL_HEIGHT + LABEL_HEIGHT;
+
+    let mut sheet = RgbaImage::from_pixel(
+        columns * cell_width + PADDING,
+        rows * cell_height + PADDING,
+        BACKGROUND,
+    );
+
+    for (idx, (scenery, thumbnail)) in sceneries.iter().zip(thumbnails.iter()).enumerate() {
+        let x = PADDING + (idx as u32 % columns) * cell_width;
+        let y = PADDING + (idx as u32 / columns) * cell_height;
+
+        imageops::overlay(
+            &mut sheet,
+            &to_rgba_image(thumbnail, config.gamma),
+            x as i64,
+            y as i64,
+        );
+
+        let name = scenery.to_string();
+        draw_text(
+            &mut sheet,
+            x,
+            y + THUMBNAIL_HEIGHT + PADDING,
+            &name,
+            LABEL_COLOUR,
+        );
+    }
+
+    Ok(sheet)
+}
@@ -0,0 +1,186 @@
+//! # Image diff
+//!
+//! A library for comparing two renders, e.g. for regression tests or A/B
+//! comparisons of render settings. Images are compared with the display
+//! values that are seen after saving. The report has the root mean square
+//! error, the peak signal to noise ratio and the mean of a FLIP style colour
+//! error. A heatmap of the colour error shows where the images differ.
+//!
+//! The colour error follows the colour pipeline of NVIDIA's FLIP: the HyAB
+//! distance between the colours in L*a*b* space is compressed and remapped
+//! to [0, 1] relative to the distance between green and blue. The spatial
+//! filtering and feature detection of FLIP are not applied so the error is
+//! per pixel.
+
+use super::algebra::Colour;
+use super::common::Float;
+use super::renderer::{load_display_image, viridis, Gamma};
+use image::{Rgb32FImage, Rgba, RgbaImage};
+use std::fmt;
+
+/// Exponent used to compress the HyAB distance.
+const HYAB_EXPONENT: Float = 0.7;
+
+/// Fraction of the maximum error below which errors are scaled up.
+const ERROR_KNEE: Float = 0.4;
+
+/// Remapped error at the knee.
+const ERROR_KNEE_VALUE: Float = 0.95;
+
+/// Differences between two images.
+#[derive(Debug, Copy, Clone)]
+pub struct DiffReport {
+    /// Root mean square error of the colour channels.
+    pub rmse: Float,
+
+    /// Peak signal to noise ratio in decibels. It is infinite for identical
+    /// images.
+    pub psnr: Float,
+
+    /// Mean colour error in [0, 1].
+    pub flip: Float,
+}
+
+impl fmt::Display for DiffReport {
+    /// Display the metrics.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RMSE: {:.6}, PSNR: {:.2} dB, FLIP: {:.6}",
+            self.rmse, self.psnr, self.flip
+        )
+    }
+}
+
+/// Compare two image files and return the metrics and a heatmap of the
+/// colour error.
+///
+/// * `reference` - Path of the reference image.
+/// * `test` - Path of the image compared with the reference.
+/// * `gamma` - Transfer function used to encode high dynamic range images.
+pub fn diff_files(
+    reference: &str,
+    test: &str,
+    gamma: Gamma,
+) -> Result<(DiffReport, RgbaImage), String> {
+    let a = load_display_image(reference, gamma)?;
+    let b = load_display_image(test, gamma)?;
+    diff_images(&a, &b)
+}
+
+/// Compare two images with display values in [0, 1] and return the metrics
+/// and a heatmap of the colour error.
+///
+/// * `a` - The reference image.
+/// * `b` - The image compared with the reference.
+pub fn diff_images(a: &Rgb32FImage, b: &Rgb32FImage) -> Result<(DiffReport, RgbaImage), String> {
+    if a.dimensions() != b.dimensions() {
+        return Err(format!(
+            "Image sizes differ: {}x{} and {}x{}",
+            a.width(),
+            a.height(),
+            b.width(),
+            b.height()
+        ));
+    }
+
+    let max_error = hyab(
+        lab(Colour::new(0.0, 1.0, 0.0)),
+        lab(Colour::new(0.0, 0.0, 1.0)),
+    )
+    .powf(HYAB_EXPONENT);
+
+    let mut squared_error = 0.0;
+    let mut flip_sum = 0.0;
+    let heatmap = RgbaImage::from_fn(a.width(), a.height(), |i, j| {
+        let ca = to_colour(a.get_pixel(i, j));
+        let cb = to_colour(b.get_pixel(i, j));
+
+        let d = ca - cb;
+        squared_error += d.length_squared();
+
+        let e = remap_error(hyab(lab(ca), lab(cb)).powf(HYAB_EXPONENT), max_error);
+        flip_sum += e;
+
+        let c = viridis(e);
+        Rgba([
+            (255.0 * c.x()) as u8,
+            (255.0 * c.y()) as u8,
+            (255.0 * c.z()) as u8,
+            255,
+        ])
+    });
+
+    let n = (a.width() as Float * a.height() as Float).max(1.0);
+    let rmse = (squared_error / (3.0 * n)).sqrt();
+    let report = DiffReport {
+        rmse,
+        psnr: -20.0 * rmse.log10(),
+        flip: flip_sum / n,
+    };
+
+    Ok((report, heatmap))
+}
+
+/// Returns the colour of a pixel.
+///
+/// * `p` - The pixel.
+fn to_colour(p: &image::Rgb<f32>) -> Colour {
+    Colour::new(p[0] as Float, p[1] as Float, p[2] as Float)
+}
+
+/// Remap a compressed colour distance to [0, 1]. Small differences are
+/// scaled up so they stay visible in the heatmap.
+///
+/// * `e` - The compressed distance.
+/// * `max_error` - The compressed distance between green and blue.
+fn remap_error(e: Float, max_error: Float) -> Float {
+    let knee = ERROR_KNEE * max_error;
+    let e = if e < knee {
+        e * ERROR_KNEE_VALUE / knee
+    } else {
+        ERROR_KNEE_VALUE + (e - knee) / (max_error - knee) * (1.0 - ERROR_KNEE_VALUE)
+    };
+    e.min(1.0)
+}
+
+/// Returns the HyAB distance between two L*a*b* colours.
+///
+/// * `a` - The first colour.
+/// * `b` - The second colour.
+fn hyab(a: Colour, b: Colour) -> Float {
+    let d = a - b;
+    d.x().abs() + (d.y() * d.y() + d.z() * d.z()).sqrt()
+}
+
+/// Convert a display sRGB colour to L*a*b* with a D65 white point.
+///
+/// * `c` - The colour.
+fn lab(c: Colour) -> Colour {
+    let linear = |v: Float| {
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(c.x()), linear(c.y()), linear(c.z()));
+
+    // XYZ relative to the D65 white point.
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: Float| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    Colour::new(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
@@ -0,0 +1,64 @@
+//! # Ray tracing series
+//!
+//! A library for rendering scenes with a recursive ray tracer that uses
+//! importance sampling. Scenes are built in code, loaded from scene files or
+//! picked from the built-in scenes and rendered to an image buffer with
+//! `render_to_buffer()`. Each scene load has its own `LoadContext` with the
+//! memory budget, asset search paths and BVH cache, and each render has its
+//! own thread pool and buffers, so scenes with different configurations can
+//! be loaded and rendered in the same process. The only process-wide state
+//! is the interrupt flag set by `signal::install_interrupt_handler()`, which
+//! cancels every render in progress, and per-thread random number
+//! generators.
+//!
+//! ```no_run
+//! use raytracing_series::{render_to_buffer, AppConfig, Scene, Scenery};
+//!
+//! let config = AppConfig::default();
+//! let scene = Scene::new(Scenery::CornellBox, config.image_width, config.image_height, true);
+//! let image = render_to_buffer(scene, &config).unwrap();
+//! ```
+
+pub mod algebra;
+#[cfg(feature = "gui")]
+pub mod app;
+pub mod app_config;
+pub mod background;
+pub mod camera;
+pub mod common;
+pub mod contact_sheet;
+pub mod image_diff;
+pub mod light;
+pub mod material;
+pub mod object;
+pub mod pack;
+pub mod pipeline;
+pub mod renderer;
+pub mod scene;
+pub mod server;
+pub mod signal;
+pub mod texture;
+pub mod threadpool;
+pub mod tiles;
+
+use app_config::*;
+use common::*;
+use renderer::*;
+use tiles::*;
+
+/// Re-exports.
+pub use self::app_config::AppConfig;
+pub use self::camera::Camera;
+pub use self::renderer::RecursiveTracer;
+pub use self::scene::{Scene, Scenery, Variation};
+
+use image::Rgb32FImage;
+
+/// Render a scene to an in-memory linear floating point image buffer with
+/// the first row at the top. Progress is not reported.
+///
+/// * `scene`  - The scene to render.
+/// * `config` - Program configuration.
+pub fn render_to_buffer(scene: Scene, config: &AppConfig) -> Result<Rgb32FImage, String> {
+    render_scene(scene, config, &|_| {})
+}
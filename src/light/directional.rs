@@ -0,0 +1,68 @@
+//! # Directional light
+//!
+//! A library for handling lights infinitely far away like the sun, that
+//! light every point from the same direction.
+
+use super::{ArcLight, Colour, Light, LightSample, Point3, Vec3, INFINITY};
+use std::fmt;
+use std::sync::Arc;
+use toml::Table;
+
+/// Models a directional light.
+#[derive(Debug, Clone)]
+pub struct DirectionalLight {
+    /// Unit vector the light travels along.
+    direction: Vec3,
+
+    /// Irradiance on a surface facing the light.
+    irradiance: Colour,
+}
+
+impl DirectionalLight {
+    /// Create a new directional light.
+    ///
+    /// * `direction` - Direction the light travels along.
+    /// * `irradiance` - Irradiance on a surface facing the light.
+    pub fn new(direction: Vec3, irradiance: Colour) -> ArcLight {
+        Arc::new(DirectionalLight {
+            direction: direction.unit_vector(),
+            irradiance,
+        })
+    }
+}
+
+impl fmt::Display for DirectionalLight {
+    /// Display the directional light parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "directional(direction: {}, irradiance: {})",
+            self.direction, self.irradiance
+        )
+    }
+}
+
+impl Light for DirectionalLight {
+    /// Sample the direction towards the light. It is the same for all points.
+    ///
+    /// * `_point` - Point being lit.
+    fn sample(&self, _point: Point3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction,
+            distance: INFINITY,
+            radiance: self.irradiance,
+            pdf: 1.0,
+        })
+    }
+
+    /// Returns a description of the light in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "directional".into());
+        t.insert("direction".into(), self.direction.into());
+        t.insert("irradiance".into(), self.irradiance.into());
+        t
+    }
+}
@@ -0,0 +1,63 @@
+//! # Light
+//!
+//! A library for handling light sources that aren't part of the scene
+//! geometry. Point, spot and directional lights emit from a single point or
+//! direction, so rays scattered off surfaces never hit them. The renderer
+//! samples them explicitly with shadow rays instead.
+
+mod directional;
+mod point;
+mod spot;
+
+use super::algebra::{Colour, Point3, Vec3};
+use super::common::{Float, INFINITY};
+use std::fmt;
+use std::sync::Arc;
+use toml::Table;
+
+// Re-exports.
+pub use self::directional::DirectionalLight;
+pub use self::point::PointLight;
+pub use self::spot::SpotLight;
+
+/// Models a direction sampled towards a light.
+#[derive(Debug, Copy, Clone)]
+pub struct LightSample {
+    /// Unit vector from the lit point towards the light.
+    pub direction: Vec3,
+
+    /// Distance to the light. Infinite for directional lights.
+    pub distance: Float,
+
+    /// Radiance arriving at the lit point.
+    pub radiance: Colour,
+
+    /// Probability density of sampling the direction. Lights that emit from
+    /// a single point or direction return 1.
+    pub pdf: Float,
+}
+
+/// Models a light source.
+pub trait Light: fmt::Display + fmt::Debug {
+    /// Sample a direction from a point towards the light. Returns `None` if
+    /// the light doesn't illuminate the point.
+    ///
+    /// * `point` - Point being lit.
+    fn sample(&self, point: Point3) -> Option<LightSample>;
+
+    /// Return the probability density of sampling a direction from a point.
+    /// Lights that emit from a single point or direction are never sampled
+    /// by chance so the default is 0.
+    ///
+    /// * `_point` - Point being lit.
+    /// * `_direction` - Direction towards the light.
+    fn pdf_value(&self, _point: Point3, _direction: Vec3) -> Float {
+        0.0
+    }
+
+    /// Returns a description of the light in the scene file format.
+    fn describe(&self) -> Table;
+}
+
+/// Atomic reference counted `Light`.
+pub type ArcLight = Arc<dyn Light + Send + Sync>;
@@ -0,0 +1,75 @@
+//! # Point light
+//!
+//! A library for handling lights that emit equally in all directions from a
+//! point.
+
+use super::{ArcLight, Colour, Light, LightSample, Point3};
+use std::fmt;
+use std::sync::Arc;
+use toml::Table;
+
+/// Models a point light.
+#[derive(Debug, Clone)]
+pub struct PointLight {
+    /// Position of the light.
+    position: Point3,
+
+    /// Radiant intensity. Irradiance falls off with the squared distance.
+    intensity: Colour,
+}
+
+impl PointLight {
+    /// Create a new point light.
+    ///
+    /// * `position` - Position of the light.
+    /// * `intensity` - Radiant intensity.
+    pub fn new(position: Point3, intensity: Colour) -> ArcLight {
+        Arc::new(PointLight {
+            position,
+            intensity,
+        })
+    }
+}
+
+impl fmt::Display for PointLight {
+    /// Display the point light parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "point(position: {}, intensity: {})",
+            self.position, self.intensity
+        )
+    }
+}
+
+impl Light for PointLight {
+    /// Sample the direction towards the light from a point.
+    ///
+    /// * `point` - Point being lit.
+    fn sample(&self, point: Point3) -> Option<LightSample> {
+        let to_light = self.position - point;
+        let distance_squared = to_light.length_squared();
+        if distance_squared <= 0.0 {
+            return None;
+        }
+
+        let distance = distance_squared.sqrt();
+        Some(LightSample {
+            direction: to_light / distance,
+            distance,
+            radiance: self.intensity / distance_squared,
+            pdf: 1.0,
+        })
+    }
+
+    /// Returns a description of the light in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "point".into());
+        t.insert("position".into(), self.position.into());
+        t.insert("intensity".into(), self.intensity.into());
+        t
+    }
+}
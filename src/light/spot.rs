@@ -0,0 +1,132 @@
+//! # Spot light
+//!
+//! A library for handling point lights that emit in a cone. The intensity
+//! is constant inside the inner cone and falls off smoothly to zero at the
+//! outer cone.
+
+use super::{ArcLight, Colour, Float, Light, LightSample, Point3, Vec3};
+use std::fmt;
+use std::sync::Arc;
+use toml::Table;
+
+/// Models a spot light.
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    /// Position of the light.
+    position: Point3,
+
+    /// Unit vector along the axis of the cone.
+    direction: Vec3,
+
+    /// Radiant intensity along the axis of the cone.
+    intensity: Colour,
+
+    /// Angle between the axis and the edge of the cone in degrees.
+    cone_angle: Float,
+
+    /// Angle between the axis and where the falloff starts in degrees.
+    falloff_angle: Float,
+
+    /// Cosine of `cone_angle`.
+    cos_total: Float,
+
+    /// Cosine of `falloff_angle`.
+    cos_falloff: Float,
+}
+
+impl SpotLight {
+    /// Create a new spot light.
+    ///
+    /// * `position` - Position of the light.
+    /// * `look_at` - Point the cone is aimed at.
+    /// * `intensity` - Radiant intensity along the axis of the cone.
+    /// * `cone_angle` - Angle between the axis and the edge of the cone in
+    ///   degrees.
+    /// * `falloff_angle` - Angle between the axis and where the falloff
+    ///   starts in degrees. Clamped to `cone_angle`.
+    pub fn new(
+        position: Point3,
+        look_at: Point3,
+        intensity: Colour,
+        cone_angle: Float,
+        falloff_angle: Float,
+    ) -> ArcLight {
+        let falloff_angle = falloff_angle.min(cone_angle);
+        Arc::new(SpotLight {
+            position,
+            direction: (look_at - position).unit_vector(),
+            intensity,
+            cone_angle,
+            falloff_angle,
+            cos_total: cone_angle.to_radians().cos(),
+            cos_falloff: falloff_angle.to_radians().cos(),
+        })
+    }
+
+    /// Returns the fraction of the intensity emitted in a direction.
+    ///
+    /// * `cos_theta` - Cosine of the angle between the direction and the axis.
+    fn falloff(&self, cos_theta: Float) -> Float {
+        if cos_theta <= self.cos_total {
+            0.0
+        } else if cos_theta >= self.cos_falloff {
+            1.0
+        } else {
+            let t = (cos_theta - self.cos_total) / (self.cos_falloff - self.cos_total);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+impl fmt::Display for SpotLight {
+    /// Display the spot light parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "spot(position: {}, direction: {}, intensity: {}, cone_angle: {}, falloff_angle: {})",
+            self.position, self.direction, self.intensity, self.cone_angle, self.falloff_angle
+        )
+    }
+}
+
+impl Light for SpotLight {
+    /// Sample the direction towards the light from a point. Points outside
+    /// the cone aren't lit.
+    ///
+    /// * `point` - Point being lit.
+    fn sample(&self, point: Point3) -> Option<LightSample> {
+        let to_light = self.position - point;
+        let distance_squared = to_light.length_squared();
+        if distance_squared <= 0.0 {
+            return None;
+        }
+
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+        let falloff = self.falloff(-direction.dot(self.direction));
+        if falloff <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.intensity * falloff / distance_squared,
+            pdf: 1.0,
+        })
+    }
+
+    /// Returns a description of the light in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "spot".into());
+        t.insert("position".into(), self.position.into());
+        t.insert("look_at".into(), (self.position + self.direction).into());
+        t.insert("intensity".into(), self.intensity.into());
+        t.insert("cone_angle".into(), self.cone_angle.into());
+        t.insert("falloff_angle".into(), self.falloff_angle.into());
+        t
+    }
+}
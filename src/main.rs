@@ -1,38 +1,70 @@
 //! # Recursive ray tracer
+//!
+//! Command line interface for the ray tracer library.
 
-mod algebra;
-mod app;
-mod app_config;
-mod background;
-mod camera;
-mod common;
-mod material;
-mod object;
-mod renderer;
-mod scene;
-mod texture;
-mod threadpool;
-mod tiles;
-
-use app::*;
-use app_config::*;
-use common::*;
-use renderer::*;
-use scene::*;
-use tao::window::Window;
-use threadpool::*;
-use tiles::*;
+#[cfg(feature = "gui")]
+use raytracing_series::app::*;
+use raytracing_series::app_config::*;
+use raytracing_series::common::*;
+use raytracing_series::contact_sheet::*;
+use raytracing_series::image_diff::*;
+use raytracing_series::pack::*;
+use raytracing_series::pipeline::*;
+use raytracing_series::renderer::*;
+use raytracing_series::scene::*;
+use raytracing_series::signal::*;
 
 use clap::Parser;
-use std::cell::RefCell;
-use std::sync::{Arc, LazyLock, Mutex};
-use std::thread;
-use std::time::Duration;
+use std::cell::Cell;
+use std::sync::LazyLock;
 
 static CONFIG: LazyLock<AppConfig> = LazyLock::new(|| AppConfig::parse());
 
 /// Entry point for the recursive raytracer.
 fn main() -> Result<(), String> {
+    if let Some(Command::Diff {
+        reference,
+        test,
+        heatmap,
+        max_flip,
+    }) = &CONFIG.command
+    {
+        // Compare two renders instead of rendering.
+        let (report, image) = diff_files(reference, test, CONFIG.gamma)?;
+        println!("{}", report);
+
+        if let Some(path) = heatmap {
+            write_rgba_image(&image, path);
+        }
+
+        return match max_flip {
+            Some(max) if report.flip > *max => {
+                Err(format!("FLIP error {:.6} is above {:.6}", report.flip, max))
+            }
+            _ => Ok(()),
+        };
+    }
+
+    if let Some(Command::View { path }) = &CONFIG.command {
+        // View an image instead of rendering.
+        return view_image(path, CONFIG.gamma);
+    }
+
+    if let Some(Command::Pack { scene, output }) = &CONFIG.command {
+        // Bundle a scene with its assets instead of rendering.
+        let report = pack_scene(scene, output, &CONFIG.asset_paths)?;
+        for file in report.files.iter() {
+            eprintln!("  {}", file);
+        }
+        println!(
+            "Packed {} files ({} bytes) into {}",
+            report.files.len(),
+            report.bytes,
+            output
+        );
+        return Ok(());
+    }
+
     // seed the random number generator.
     if let Some(seed) = CONFIG.seed {
         Random::seed(seed);
@@ -40,151 +72,193 @@ fn main() -> Result<(), String> {
 
     eprintln!("Running with {} threads", CONFIG.threads());
 
-    // Allocate an image buffer for rendering.
-    let image = Arc::new(Mutex::new(image::RgbaImage::new(
-        CONFIG.image_width,
-        CONFIG.image_height,
-    )));
+    // Handle Ctrl-C by cancelling the render and saving what was completed.
+    if let Err(e) = install_interrupt_handler() {
+        eprintln!("{}", e);
+    }
 
-    // Create a thread pool for rendering tiles in parallel.
-    let pool = Arc::new(Mutex::new(ThreadPool::build(CONFIG.threads()).unwrap()));
+    let console = Console::default();
 
-    // Track remaining tiles. It will be used to shutdown the thread pool.
-    let remaining_tiles = Arc::new(Mutex::new(CONFIG.n_tiles()));
+    if let Some(path) = &CONFIG.contact_sheet {
+        // Render thumbnails of every scene instead of a single scene.
+        let image = render_contact_sheet(&CONFIG, &console)?;
+        write_rgba_image(&image, path);
+        exit_if_interrupted();
+        return Ok(());
+    }
+
+    if let Some((x, y)) = CONFIG.trace_pixel {
+        // Write paths through a single pixel instead of rendering the image.
+        let paths = trace_pixel_paths(load_scene()?, &CONFIG, x, y, CONFIG.trace_paths)?;
+        for (n, path) in paths.iter().enumerate() {
+            eprintln!("Path {}: {}", n, path_summary(path));
+        }
+        save_paths(&paths, &CONFIG.output_path)?;
+        eprintln!("Saved {} paths to {}", paths.len(), CONFIG.output_path);
+        return Ok(());
+    }
+
+    if let Some(path) = &CONFIG.save_scene {
+        // Export the scene so it can be edited and rendered with --scene-file.
+        load_scene()?.save(path)?;
+        eprintln!("Saved scene to {}", path);
+        return Ok(());
+    }
+
+    if !CONFIG.gui {
+        // Render directly to an image buffer without a window.
+        let scene = load_scene()?;
+        let pipeline = Pipeline::build(&scene, &CONFIG)?;
+        let mut ctx = PassContext::new(scene, &CONFIG);
+
+        let result = pipeline.run_pre(&mut ctx);
+        print_messages(ctx.messages.take());
+        result?;
+        exit_if_interrupted();
+
+        ctx.image = if CONFIG.anaglyph {
+            render_anaglyph(ctx.scene.clone(), &CONFIG, &console)?
+        } else if CONFIG.gradient_domain {
+            render_gradient_domain(ctx.scene.clone(), &CONFIG, &console)?
+        } else {
+            render_scene(ctx.scene.clone(), &CONFIG, &console)?
+        };
+
+        // Post passes are skipped if the render was interrupted.
+        let result = pipeline.run_post(&mut ctx);
+        print_messages(ctx.messages.take());
+        result?;
+        pipeline.save(&ctx, &CONFIG.output_path);
+        print_messages(ctx.messages.take());
+        exit_if_interrupted();
+
+        return Ok(());
+    }
+
+    // Render in a window. The event loop runs in the main thread.
+    run_preview(load_scene()?, &CONFIG)
+}
 
-    // Create the GUI application if needed.
-    let app = if CONFIG.gui {
-        Some(App::build(Arc::clone(&pool), Arc::clone(&image))?)
+/// Load the scene from the scene file if one was given, otherwise build the
+/// selected built-in scene. Any `--set` overrides are applied, the
+/// background is replaced with `--background`, the camera frames the scene
+/// with `--auto-frame` and focuses on `--focus-object`.
+fn load_scene() -> Result<Scene, String> {
+    // Draw a scene seed if none was given and the scene has randomly
+    // generated objects and report it so the scene can be generated again.
+    // Scene files have none.
+    let variation = if CONFIG.scene_file.is_none() && CONFIG.scenery.uses_variation() {
+        CONFIG.variation().seeded()
     } else {
-        None
+        CONFIG.variation()
     };
+    if let (None, Some(seed)) = (CONFIG.scene_seed, variation.seed) {
+        eprintln!("scene seed: {}", seed);
+    }
 
-    // Start a separate thread that will queue all tiles.
-    let render_thread = {
-        let pool = Arc::clone(&pool);
-        let image = Arc::clone(&image);
-        let remaining_tiles = Arc::clone(&remaining_tiles);
-        let window = app.as_ref().map(|app| Arc::clone(&app.window));
-        thread::spawn(|| render(pool, image, remaining_tiles, window))
-    };
+    let context = CONFIG.load_context();
+    let scene = Scene::load(
+        CONFIG.scene_file.as_deref(),
+        CONFIG.scenery,
+        &CONFIG.overrides,
+        &variation,
+        CONFIG.image_width,
+        CONFIG.image_height,
+        CONFIG.bvh_enabled,
+        &context,
+    );
+    print_messages(context.messages.take());
+    let scene = scene?;
 
-    // Wait for render to complete, then save image and shutdown pool.
-    let progress_thread = {
-        let pool = Arc::clone(&pool);
-        let image = Arc::clone(&image);
-        let remaining_tiles = Arc::clone(&remaining_tiles);
-        thread::spawn(|| progress(pool, image, remaining_tiles))
+    let scene = match CONFIG.background {
+        Some(kind) => scene.with_background(
+            kind,
+            CONFIG.sun_elevation,
+            CONFIG.sun_azimuth,
+            CONFIG.turbidity,
+        ),
+        None => scene,
     };
 
-    if CONFIG.gui {
-        // Run the event loop for the GUI. This will run in the main thread.
-        app.unwrap().run()
+    let scene = if CONFIG.auto_frame {
+        scene.auto_frame()?
     } else {
-        // Wait for remaining threads to complete.
-        render_thread.join().map_err(|e| format!("{:?}", e))?;
-        progress_thread.join().map_err(|e| format!("{:?}", e))
+        scene
+    };
+
+    match &CONFIG.focus_object {
+        Some(name) => scene.focus_on(name, CONFIG.focus_look_at),
+        None => Ok(scene),
     }
 }
 
-/// Render the scene in parallel using worker threads.
-///
-/// * `pool`            - Thread pool.
-/// * `image`           - Image buffer to render.
-/// * `remaining_tiles` - Number of tiles remaining.
-/// * `window`          - Optional window to redraw image to.
-fn render(
-    pool: Arc<Mutex<ThreadPool>>,
-    image: Arc<Mutex<image::RgbaImage>>,
-    remaining_tiles: Arc<Mutex<usize>>,
-    window: Option<Arc<Window>>,
-) {
-    // Setup rendering algorithm.
-    let renderer = Arc::new(RecursiveTracer {
-        config: CONFIG.clone(),
-        scene: Scene::new(
-            CONFIG.scenery,
-            CONFIG.image_width,
-            CONFIG.image_height,
-            CONFIG.bvh_enabled,
-        ),
-    });
-
-    // Queue up the tiles to render.
-    for tile_idx in 0..CONFIG.n_tiles() {
-        // Clone the `Arc`s for the worker thread.
-        let renderer = Arc::clone(&renderer);
-        let image = Arc::clone(&image);
-        let remaining_tiles = Arc::clone(&remaining_tiles);
-        let window = window.clone();
-
-        pool.lock().unwrap().execute(move || {
-            thread_local! {
-                // Allocate pixels for rendering a tile per thread so we don't allocate for each tile.
-                pub static TILE_PIXELS: RefCell<image::RgbaImage> = {
-                    eprintln!("\rAllocating tile pixels for {:?}", thread::current().id());
-                    RefCell::new(image::RgbaImage::new(CONFIG.tile_size as u32, CONFIG.tile_size as u32))
-                };
-            }
-
-            TILE_PIXELS.with_borrow_mut(|tile_pixels| {
-                // Calculate the tile bounds.
-                let tile_bounds = get_tile_bounds(tile_idx);
-
-                // Render whole tile and then copy to destination.
-                render_tile(renderer, &tile_bounds, tile_pixels);
-                copy_tile(Arc::clone(&image), &tile_bounds, tile_pixels);
-            });
+/// Prints the progress of a render on a single line and messages about the
+/// render on lines of their own.
+#[derive(Default)]
+struct Console {
+    /// Set while the progress line hasn't been ended.
+    in_progress: Cell<bool>,
+}
 
-            // Update remaining tiles.
-            *remaining_tiles.lock().unwrap() -= 1;
+impl Progress for Console {
+    /// Print the progress on a single line, ending it once the render
+    /// completes.
+    ///
+    /// * `fraction` - Fraction of the render completed.
+    fn update(&self, fraction: f32) {
+        eprint!("\rProgress {:.2}%    ", 100_f32 * fraction);
+        self.in_progress.set(fraction < 1.0);
+        if fraction >= 1.0 {
+            eprintln!();
+        }
+    }
 
-            // Redraw window.
-            window.map(|w| w.request_redraw());
-        });
+    /// Print a message on a line of its own, ending the progress line first.
+    ///
+    /// * `message` - The message.
+    fn message(&self, message: &str) {
+        if self.in_progress.replace(false) {
+            eprintln!();
+        }
+        eprintln!("{}", message);
     }
+}
 
-    eprintln!("\nQueued up all tiles to render.");
+/// Print messages reported by the library.
+///
+/// * `messages` - The messages.
+fn print_messages(messages: Vec<String>) {
+    for message in messages.iter() {
+        eprintln!("{}", message);
+    }
 }
 
-/// Write the image to disk.
+/// Write an 8-bit image to disk.
 ///
 /// * `image` - Image to save to file.
-fn write_image(image: Arc<Mutex<image::RgbaImage>>) {
-    eprintln!("Saving output image to {}", CONFIG.output_path);
+/// * `path`  - Output file path. File extension determines image type.
+fn write_rgba_image(image: &image::RgbaImage, path: &str) {
+    eprintln!("Saving output image to {}", path);
 
-    if let Err(_) = image
-        .lock()
-        .map(|img| image::imageops::flip_vertical(&*img).save(&CONFIG.output_path))
-    {
-        eprintln!("Error writing output image");
+    if let Err(e) = image.save(path) {
+        eprintln!("Error writing output image: {}", e);
     }
 }
 
-/// Displays the progress of the render. When complete it saves the image and shuts down the thread pool.
+/// Without the `gui` feature there is no window to view images in.
 ///
-/// * `pool`            - Thread pool.
-/// * `image`           - Image buffer to render.
-/// * `remaining_tiles` - Number of tiles remaining.
-fn progress(
-    pool: Arc<Mutex<ThreadPool>>,
-    image: Arc<Mutex<image::RgbaImage>>,
-    remaining_tiles: Arc<Mutex<usize>>,
-) {
-    loop {
-        let remaining_tiles = *remaining_tiles.lock().unwrap();
-
-        let progress = (CONFIG.n_tiles() - remaining_tiles) as f32 / CONFIG.n_tiles() as f32;
-        eprint!("\rProgress {:.2}%    ", 100_f32 * progress);
-
-        if remaining_tiles == 0 {
-            eprintln!();
-
-            write_image(image);
-            pool.lock().unwrap().shutdown();
-
-            break;
-        }
+/// * `_path` - Path to the image.
+/// * `_gamma` - Output transfer function.
+#[cfg(not(feature = "gui"))]
+fn view_image(_path: &str, _gamma: Gamma) -> Result<(), String> {
+    Err("viewing images requires the `gui` feature".to_string())
+}
 
-        thread::sleep(Duration::from_secs(2));
-    }
+/// Without the `gui` feature there is no window to render in.
+///
+/// * `_scene` - The scene.
+/// * `_config` - Program configuration.
+#[cfg(not(feature = "gui"))]
+fn run_preview(_scene: Scene, _config: &AppConfig) -> Result<(), String> {
+    Err("--gui requires the `gui` feature".to_string())
 }
@@ -0,0 +1,81 @@
+//! # Absorber
+//!
+//! A library for handling collisions in absorbing media. A collision either
+//! absorbs the ray or lets it continue unchanged with some channels
+//! attenuated, so media of any colour are sampled with a single collision
+//! density.
+
+use super::{ArcMaterial, Colour, HitRecord, Material, Ray, ScatterRecord, Table};
+use std::fmt;
+use std::sync::Arc;
+
+/// Models a collision in an absorbing medium.
+#[derive(Clone)]
+pub struct Absorber {
+    /// Attenuation of rays that continue past the collision.
+    weight: Colour,
+}
+
+impl Absorber {
+    /// Creates a new material for absorbing medium.
+    ///
+    /// * `weight` - Attenuation of rays that continue past a collision.
+    ///   Black absorbs every ray.
+    pub fn new(weight: Colour) -> ArcMaterial {
+        Arc::new(Absorber { weight })
+    }
+}
+
+impl fmt::Display for Absorber {
+    /// Display the absorber parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "absorber(weight: {})", self.weight)
+    }
+}
+
+impl fmt::Debug for Absorber {
+    /// Display the absorber parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Absorber")
+            .field("weight", &self.weight)
+            .finish()
+    }
+}
+
+impl Material for Absorber {
+    /// Scatter an incident ray and determine the attenuation.
+    /// If the incident ray is absorbed, `None` is returned.
+    ///
+    /// The ray continues in the same direction from the collision.
+    ///
+    /// * `ray_in` - Incident ray.
+    /// * `rec` - The `HitRecord`.
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        if self.weight.max_component() <= 0.0 {
+            return None;
+        }
+
+        Some(ScatterRecord {
+            specular_ray: Some(Ray::new(
+                rec.offset_origin(ray_in.direction),
+                ray_in.direction,
+                ray_in.time,
+            )),
+            attenuation: self.weight,
+            scattered_ray: None,
+            pdf: None,
+        })
+    }
+
+    /// Returns a description of the material in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "absorber".into());
+        t.insert("weight".into(), self.weight.into());
+        t
+    }
+}
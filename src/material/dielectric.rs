@@ -1,11 +1,26 @@
 //! # Dielectric
 //!
 //! A library for handling dielectric material.
+//!
+//! Dispersive dielectrics vary their index of refraction with wavelength
+//! using Cauchy's equation `n = A + B / λ²`. Each scattering event picks one
+//! colour channel at random, refracts with the index at that channel's
+//! wavelength and carries only that channel, so prisms split white light
+//! into colour fringes.
 
-use super::{ArcMaterial, Colour, Float, HitRecord, Material, Random, Ray, ScatterRecord};
+use super::{
+    ArcMaterial, Colour, Float, HitRecord, Material, Random, Ray, Sampler, ScatterRecord, Table,
+    Vec3,
+};
 use std::fmt;
 use std::sync::Arc;
 
+/// Wavelengths of the red, green and blue channels in micrometres.
+const CHANNEL_WAVELENGTHS: [Float; 3] = [0.61, 0.55, 0.465];
+
+/// Wavelength in micrometres at which the index of refraction is given.
+const REFERENCE_WAVELENGTH: Float = 0.55;
+
 /// Models a dielectric material.
 #[derive(Clone)]
 pub struct Dielectric {
@@ -14,6 +29,10 @@ pub struct Dielectric {
 
     /// Reciprocal of `ref_idx`.
     one_over_ref_idx: Float,
+
+    /// Cauchy `B` coefficient in square micrometres. Zero disables
+    /// dispersion.
+    cauchy_b: Float,
 }
 
 impl Dielectric {
@@ -21,11 +40,45 @@ impl Dielectric {
     ///
     /// * `ri` - Index of refraction.
     pub fn new(ri: Float) -> ArcMaterial {
+        Dielectric::dispersive(ri, 0.0)
+    }
+
+    /// Creates a new dielectric material whose index of refraction varies
+    /// with wavelength, e.g. 0.0042 for crown glass and 0.01 for flint
+    /// glass.
+    ///
+    /// * `ri` - Index of refraction at 550nm.
+    /// * `cauchy_b` - Cauchy `B` coefficient in square micrometres.
+    pub fn dispersive(ri: Float, cauchy_b: Float) -> ArcMaterial {
         Arc::new(Dielectric {
             ref_idx: ri,
             one_over_ref_idx: 1.0 / ri,
+            cauchy_b: cauchy_b.max(0.0),
         })
     }
+
+    /// Returns the index of refraction, its reciprocal and the attenuation
+    /// of a scattering event. Dispersive dielectrics pick a random colour
+    /// channel and scale it by the number of channels to keep the expected
+    /// colour white.
+    fn sample_channel(&self) -> (Float, Float, Colour) {
+        if self.cauchy_b <= 0.0 {
+            return (
+                self.ref_idx,
+                self.one_over_ref_idx,
+                Colour::new(1.0, 1.0, 1.0),
+            );
+        }
+
+        let channel = ((Sampler::get_1d() * 3.0) as usize).min(2);
+        let a = self.ref_idx - self.cauchy_b / (REFERENCE_WAVELENGTH * REFERENCE_WAVELENGTH);
+        let lambda = CHANNEL_WAVELENGTHS[channel];
+
+        let mut rgb = [0.0; 3];
+        rgb[channel] = 3.0;
+        let ref_idx = a + self.cauchy_b / (lambda * lambda);
+        (ref_idx, 1.0 / ref_idx, Colour::new(rgb[0], rgb[1], rgb[2]))
+    }
 }
 
 impl fmt::Display for Dielectric {
@@ -35,8 +88,8 @@ impl fmt::Display for Dielectric {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "dielectric(ref_idx: {}, one_over_ref_idx: {})",
-            self.ref_idx, self.one_over_ref_idx
+            "dielectric(ref_idx: {}, one_over_ref_idx: {}, cauchy_b: {})",
+            self.ref_idx, self.one_over_ref_idx, self.cauchy_b
         )
     }
 }
@@ -49,6 +102,7 @@ impl fmt::Debug for Dielectric {
         f.debug_struct("Dielectric")
             .field("ref_idx", &self.ref_idx)
             .field("one_over_ref_idx", &self.one_over_ref_idx)
+            .field("cauchy_b", &self.cauchy_b)
             .finish()
     }
 }
@@ -60,12 +114,33 @@ impl fmt::Debug for Dielectric {
 /// * `cosine` - Cosine of angle between the direction from which the
 ///              incident light is coming and the normal.
 /// * `ref_idx` - Refractive index.
-fn schlick(cosine: Float, ref_idx: Float) -> Float {
+pub fn schlick(cosine: Float, ref_idx: Float) -> Float {
     let r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
     let r0 = r0 * r0;
     r0 + (1.0 - r0) * (1.0 - cosine).powf(5.0)
 }
 
+/// Returns the unit normal at an intersection perturbed by its minimum
+/// roughness, so paths that clamp roughness blur reflections and
+/// refractions through smooth glass. Perturbed normals facing away from the
+/// incident ray are discarded.
+///
+/// * `rec` - The `HitRecord`.
+/// * `direction` - Unit direction of the incident ray.
+fn fuzzed_normal(rec: &HitRecord, direction: Vec3) -> Vec3 {
+    let normal = rec.normal.unit_vector();
+    if rec.min_roughness <= 0.0 {
+        return normal;
+    }
+
+    let fuzzed = (normal + rec.min_roughness * Random::vec3_in_unit_sphere()).unit_vector();
+    if fuzzed.dot(direction) < 0.0 {
+        fuzzed
+    } else {
+        normal
+    }
+}
+
 impl Material for Dielectric {
     /// Scatter an incident ray and determine the attenuation.
     /// If the incident ray is absorbed, `None` is returned.
@@ -75,17 +150,17 @@ impl Material for Dielectric {
     /// * `ray_in` - Incident ray.
     /// * `rec` - The `HitRecord`.
     fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
-        // No attenuation
-        let attenuation = Colour::new(1.0, 1.0, 1.0);
+        // No attenuation unless dispersive.
+        let (ref_idx, one_over_ref_idx, attenuation) = self.sample_channel();
 
         let etai_over_etat = if rec.front_face {
-            self.one_over_ref_idx
+            one_over_ref_idx
         } else {
-            self.ref_idx
+            ref_idx
         };
 
         let unit_direction = ray_in.direction.unit_vector();
-        let unit_normal = rec.normal.unit_vector();
+        let unit_normal = fuzzed_normal(rec, unit_direction);
 
         let cos_theta = -unit_direction.dot(unit_normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
@@ -93,17 +168,25 @@ impl Material for Dielectric {
         if etai_over_etat * sin_theta > 1.0 {
             let reflected = unit_direction.reflect(unit_normal);
             Some(ScatterRecord {
-                specular_ray: Some(Ray::new(rec.point, reflected, ray_in.time)),
+                specular_ray: Some(Ray::new(
+                    rec.offset_origin(reflected),
+                    reflected,
+                    ray_in.time,
+                )),
                 attenuation,
                 scattered_ray: None,
                 pdf: None,
             })
         } else {
             let reflect_prob = schlick(cos_theta, etai_over_etat);
-            if Random::sample::<Float>() < reflect_prob {
+            if Sampler::get_1d() < reflect_prob {
                 let reflected = unit_direction.reflect(unit_normal);
                 Some(ScatterRecord {
-                    specular_ray: Some(Ray::new(rec.point, reflected, ray_in.time)),
+                    specular_ray: Some(Ray::new(
+                        rec.offset_origin(reflected),
+                        reflected,
+                        ray_in.time,
+                    )),
                     attenuation,
                     scattered_ray: None,
                     pdf: None,
@@ -111,7 +194,11 @@ impl Material for Dielectric {
             } else {
                 let refracted = unit_direction.refract(unit_normal, etai_over_etat);
                 Some(ScatterRecord {
-                    specular_ray: Some(Ray::new(rec.point, refracted, ray_in.time)),
+                    specular_ray: Some(Ray::new(
+                        rec.offset_origin(refracted),
+                        refracted,
+                        ray_in.time,
+                    )),
                     attenuation,
                     scattered_ray: None,
                     pdf: None,
@@ -119,4 +206,15 @@ impl Material for Dielectric {
             }
         }
     }
+
+    /// Returns a description of the material in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "dielectric".into());
+        t.insert("ior".into(), self.ref_idx.into());
+        if self.cauchy_b > 0.0 {
+            t.insert("cauchy_b".into(), self.cauchy_b.into());
+        }
+        t
+    }
 }
@@ -2,7 +2,7 @@
 //!
 //! A library for handling diffuse light emissive material.
 
-use super::{ArcMaterial, ArcTexture, Colour, HitRecord, Material, Ray};
+use super::{ArcMaterial, ArcTexture, Colour, Float, HitRecord, Material, Ray, Table};
 use std::fmt;
 use std::sync::Arc;
 
@@ -11,6 +11,12 @@ use std::sync::Arc;
 pub struct DiffuseLight {
     /// The emission provided by a texture.
     emit: ArcTexture,
+
+    /// Scales the emission.
+    intensity: Float,
+
+    /// Emit from both sides of the surface instead of the front face only.
+    two_sided: bool,
 }
 
 impl DiffuseLight {
@@ -18,8 +24,22 @@ impl DiffuseLight {
     ///
     /// * `emeit` - Emission provided by a texture.
     pub fn new(emit: ArcTexture) -> ArcMaterial {
+        DiffuseLight::with_options(emit, 1.0, false)
+    }
+
+    /// Creates a new diffuse light material with scaled emission. Emission
+    /// is looked up at the hit UVs so image textures emit spatially varying
+    /// light. One-sided lights emit along the outward normal so objects may
+    /// need flipping to face the scene.
+    ///
+    /// * `emit` - Emission provided by a texture.
+    /// * `intensity` - Scales the emission.
+    /// * `two_sided` - Emit from both sides of the surface.
+    pub fn with_options(emit: ArcTexture, intensity: Float, two_sided: bool) -> ArcMaterial {
         Arc::new(DiffuseLight {
             emit: Arc::clone(&emit),
+            intensity,
+            two_sided,
         })
     }
 }
@@ -29,7 +49,11 @@ impl fmt::Display for DiffuseLight {
     ///
     /// * `f` - Formatter.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "diffuse_light(emit: {})", self.emit)
+        write!(
+            f,
+            "diffuse_light(emit: {}, intensity: {}, two_sided: {})",
+            self.emit, self.intensity, self.two_sided
+        )
     }
 }
 
@@ -40,6 +64,8 @@ impl fmt::Debug for DiffuseLight {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("DiffuseLight")
             .field("emit", &self.emit)
+            .field("intensity", &self.intensity)
+            .field("two_sided", &self.two_sided)
             .finish()
     }
 }
@@ -50,10 +76,29 @@ impl Material for DiffuseLight {
     /// * `ray_in` - Incident ray.
     /// * `rec` - The `HitRecord`.
     fn emission(&self, _ray_in: &Ray, rec: &HitRecord) -> Colour {
-        if rec.front_face {
-            self.emit.value(rec.u, rec.v, &rec.point)
+        if rec.front_face || self.two_sided {
+            self.emit.value(rec.u, rec.v, &rec.point) * self.intensity
         } else {
             Colour::zero()
         }
     }
+
+    /// Returns true if the light has any intensity.
+    fn is_emissive(&self) -> bool {
+        self.intensity > 0.0
+    }
+
+    /// Returns a description of the material in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "diffuse_light".into());
+        t.insert("emit".into(), self.emit.describe());
+        if self.intensity != 1.0 {
+            t.insert("intensity".into(), self.intensity.into());
+        }
+        if self.two_sided {
+            t.insert("two_sided".into(), true.into());
+        }
+        t
+    }
 }
@@ -0,0 +1,409 @@
+//! # GGX
+//!
+//! A library for sampling the GGX (Trowbridge-Reitz) microfacet distribution
+//! used by rough materials. Microfacet normals are drawn from the
+//! distribution of normals visible from the incident direction (Heitz 2018,
+//! "Sampling the GGX Distribution of Visible Normals") which never produces
+//! back facing microfacets and keeps the sample weights close to one even at
+//! grazing angles.
+//!
+//! Directions are given in a local frame with the surface normal along the
+//! z-axis.
+
+use super::{dielectric::schlick, Float, Sampler, Vec3, ONB, PDF, PI, TWO_PI};
+
+/// Smallest GGX alpha. Smaller values are numerically unstable and
+/// indistinguishable from a smooth surface.
+const MIN_ALPHA: Float = 1.0e-3;
+
+/// Remap perceptual roughness in `[0, 1]` to the GGX alpha parameter so
+/// roughness changes the appearance roughly linearly.
+///
+/// * `roughness` - Perceptual roughness.
+pub fn roughness_to_alpha(roughness: Float) -> Float {
+    (roughness * roughness).max(MIN_ALPHA)
+}
+
+/// Sample a microfacet normal visible from a direction.
+///
+/// * `wo` - Unit direction towards the viewer in the local frame.
+/// * `alpha` - GGX alpha.
+/// * `u1` - Uniform random number in `[0, 1)`.
+/// * `u2` - Uniform random number in `[0, 1)`.
+pub fn sample_visible_normal(wo: Vec3, alpha: Float, u1: Float, u2: Float) -> Vec3 {
+    // Stretch the view direction so the distribution becomes a hemisphere.
+    let vh = Vec3::new(alpha * wo.x(), alpha * wo.y(), wo.z()).unit_vector();
+
+    // Orthonormal basis around the stretched view direction.
+    let len_sq = vh.x() * vh.x() + vh.y() * vh.y();
+    let t1 = if len_sq > 0.0 {
+        Vec3::new(-vh.y(), vh.x(), 0.0) / len_sq.sqrt()
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let t2 = vh.cross(t1);
+
+    // Sample the projected area of the visible hemisphere.
+    let r = u1.sqrt();
+    let phi = TWO_PI * u2;
+    let p1 = r * phi.cos();
+    let s = 0.5 * (1.0 + vh.z());
+    let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+
+    // Reproject onto the hemisphere and unstretch.
+    let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+    Vec3::new(alpha * nh.x(), alpha * nh.y(), nh.z().max(0.0)).unit_vector()
+}
+
+/// Returns the density of microfacet normals.
+///
+/// * `h` - Unit microfacet normal in the local frame.
+/// * `alpha` - GGX alpha.
+pub fn distribution(h: Vec3, alpha: Float) -> Float {
+    let cos2 = h.z() * h.z();
+    if cos2 <= 0.0 {
+        return 0.0;
+    }
+
+    let a2 = alpha * alpha;
+    let d = cos2 * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+/// Returns the Smith masking function for a direction, i.e. the fraction of
+/// microfacets visible from it.
+///
+/// * `cos_theta` - Cosine of the angle between the direction and the
+///   surface normal.
+/// * `alpha` - GGX alpha.
+pub fn smith_g1(cos_theta: Float, alpha: Float) -> Float {
+    if cos_theta == 0.0 {
+        return 0.0;
+    }
+    1.0 / (1.0 + smith_lambda(cos_theta, alpha))
+}
+
+/// Returns the height correlated Smith masking-shadowing function for a
+/// pair of directions, i.e. the fraction of microfacets visible from both.
+///
+/// * `cos_o` - Cosine of the angle between the outgoing direction and the
+///   surface normal.
+/// * `cos_i` - Cosine of the angle between the incident direction and the
+///   surface normal.
+/// * `alpha` - GGX alpha.
+pub fn smith_g(cos_o: Float, cos_i: Float, alpha: Float) -> Float {
+    if cos_o == 0.0 || cos_i == 0.0 {
+        return 0.0;
+    }
+    1.0 / (1.0 + smith_lambda(cos_o, alpha) + smith_lambda(cos_i, alpha))
+}
+
+/// Returns the Smith auxiliary function for a direction.
+///
+/// * `cos_theta` - Cosine of the angle between the direction and the
+///   surface normal.
+/// * `alpha` - GGX alpha.
+fn smith_lambda(cos_theta: Float, alpha: Float) -> Float {
+    let cos2 = cos_theta * cos_theta;
+    let tan2 = (1.0 - cos2).max(0.0) / cos2;
+    0.5 * (-1.0 + (1.0 + alpha * alpha * tan2).sqrt())
+}
+
+/// Models the GGX microfacet BSDF at a point on a surface as seen from a
+/// direction. Conductors only reflect. Dielectrics reflect and refract
+/// weighted by the Fresnel reflectance of the microfacet normal.
+///
+/// The BSDF is sampled from the visible normals so it also serves as the
+/// PDF of the scattered directions.
+#[derive(Debug, Clone)]
+pub struct Microfacet {
+    /// Local frame with the surface normal on the side of the viewer.
+    uvw: ONB,
+
+    /// Unit direction towards the viewer in the local frame.
+    wo: Vec3,
+
+    /// GGX alpha.
+    alpha: Float,
+
+    /// Ratio of the refractive index on the side of the viewer to the one
+    /// on the other side for dielectrics, `None` for conductors.
+    etai_over_etat: Option<Float>,
+}
+
+impl Microfacet {
+    /// Create a new microfacet BSDF.
+    ///
+    /// * `normal` - Unit surface normal facing the viewer.
+    /// * `wo` - Unit direction towards the viewer.
+    /// * `alpha` - GGX alpha.
+    /// * `etai_over_etat` - Ratio of refractive indices for dielectrics,
+    ///   `None` for conductors.
+    pub fn new(normal: Vec3, wo: Vec3, alpha: Float, etai_over_etat: Option<Float>) -> Microfacet {
+        let uvw = ONB::new(normal);
+        let wo = Microfacet::to_local(&uvw, wo);
+        Microfacet {
+            uvw,
+            wo,
+            alpha,
+            etai_over_etat,
+        }
+    }
+
+    /// Returns true if the viewer is above the surface.
+    pub fn is_visible(&self) -> bool {
+        self.wo.z() > 0.0
+    }
+
+    /// Returns the BSDF times the cosine of the angle between the scattered
+    /// direction and the surface normal.
+    ///
+    /// * `direction` - Scattered direction.
+    pub fn eval(&self, direction: Vec3) -> Float {
+        let wi = Microfacet::to_local(&self.uvw, direction.unit_vector());
+        let (h, reflected) = match self.half_vector(wi) {
+            Some(h) => h,
+            None => return 0.0,
+        };
+
+        let wo = self.wo;
+        let d = distribution(h, self.alpha);
+        let g = smith_g(wo.z(), wi.z().abs(), self.alpha);
+        let f = self.fresnel(wo.dot(h));
+
+        if reflected {
+            d * g * f / (4.0 * wo.z())
+        } else {
+            let denom = self.refraction_denominator(wo, wi, h);
+            d * g * (1.0 - f) * wi.dot(h).abs() * wo.dot(h) / (wo.z() * denom)
+        }
+    }
+
+    /// Returns a microfacet normal and whether a scattered direction is a
+    /// reflection. Directions that can't be reached from the viewer return
+    /// `None`.
+    ///
+    /// * `wi` - Unit scattered direction in the local frame.
+    fn half_vector(&self, wi: Vec3) -> Option<(Vec3, bool)> {
+        let wo = self.wo;
+        if wo.z() <= 0.0 || wi.z() == 0.0 {
+            return None;
+        }
+
+        let reflected = wi.z() > 0.0;
+        let h = match self.etai_over_etat {
+            _ if reflected => wo + wi,
+            Some(eta) => wo + wi / eta,
+            None => return None,
+        };
+        if h.length_squared() == 0.0 {
+            return None;
+        }
+
+        let h = h.unit_vector();
+        let h = if h.z() < 0.0 { -h } else { h };
+
+        // Microfacets must face the viewer and the scattered direction must
+        // be on the matching side of them.
+        if wo.dot(h) <= 0.0 || (wi.dot(h) > 0.0) != reflected {
+            return None;
+        }
+        Some((h, reflected))
+    }
+
+    /// Returns the Fresnel reflectance of a microfacet. Conductors reflect
+    /// everything and their colour is applied by the material.
+    ///
+    /// * `cos_theta` - Cosine of the angle between the viewer and the
+    ///   microfacet normal.
+    fn fresnel(&self, cos_theta: Float) -> Float {
+        match self.etai_over_etat {
+            Some(eta) => {
+                let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+                if eta * sin_theta > 1.0 {
+                    1.0
+                } else {
+                    schlick(cos_theta, eta)
+                }
+            }
+            None => 1.0,
+        }
+    }
+
+    /// Returns the squared denominator of the change of variables from
+    /// microfacet normals to refracted directions.
+    ///
+    /// * `wo` - Unit direction towards the viewer in the local frame.
+    /// * `wi` - Unit refracted direction in the local frame.
+    /// * `h` - Unit microfacet normal in the local frame.
+    fn refraction_denominator(&self, wo: Vec3, wi: Vec3, h: Vec3) -> Float {
+        let eta = self.etai_over_etat.unwrap_or(1.0);
+        let denom = wi.dot(h) + wo.dot(h) * eta;
+        denom * denom
+    }
+
+    /// Returns a direction in the local frame.
+    ///
+    /// * `uvw` - Local frame.
+    /// * `v` - Direction.
+    fn to_local(uvw: &ONB, v: Vec3) -> Vec3 {
+        Vec3::new(v.dot(uvw.u()), v.dot(uvw.v()), v.dot(uvw.w()))
+    }
+}
+
+impl PDF for Microfacet {
+    /// Returns the value of a PDF at a location.
+    ///
+    /// * `direction` - Scattered direction.
+    fn value(&self, direction: Vec3) -> Float {
+        let wi = Microfacet::to_local(&self.uvw, direction.unit_vector());
+        let (h, reflected) = match self.half_vector(wi) {
+            Some(h) => h,
+            None => return 0.0,
+        };
+
+        // Density of visible normals.
+        let wo = self.wo;
+        let cos_o_h = wo.dot(h);
+        let visible = smith_g1(wo.z(), self.alpha) * distribution(h, self.alpha) * cos_o_h / wo.z();
+
+        let f = self.fresnel(cos_o_h);
+        if reflected {
+            visible * f / (4.0 * cos_o_h)
+        } else {
+            visible * (1.0 - f) * wi.dot(h).abs() / self.refraction_denominator(wo, wi, h)
+        }
+    }
+
+    /// Returns a random direction based on PDF.
+    fn generate(&self) -> Vec3 {
+        let (u1, u2) = Sampler::get_2d();
+        let h = sample_visible_normal(self.wo, self.alpha, u1, u2);
+
+        let incident = -self.wo;
+        let wi = match self.etai_over_etat {
+            Some(eta) if Sampler::get_1d() >= self.fresnel(self.wo.dot(h)) => {
+                incident.refract(h, eta)
+            }
+            _ => incident.reflect(h),
+        };
+        self.uvw.local_from_vec3(&wi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Random;
+    use super::*;
+
+    /// Number of samples of the estimators.
+    const SAMPLES: usize = 200_000;
+
+    /// Returns the mean and variance of an estimator of the reflectance of a
+    /// rough conductor.
+    ///
+    /// * `m` - Microfacet BSDF.
+    /// * `sample` - Returns a scattered direction in the local frame and its
+    ///   density from two uniform random numbers.
+    fn reflectance<F>(m: &Microfacet, sample: F) -> (Float, Float)
+    where
+        F: Fn(Float, Float) -> (Vec3, Float),
+    {
+        Random::seed(1);
+        let (mut sum, mut sum_sq) = (0.0, 0.0);
+        for _ in 0..SAMPLES {
+            let (wi, pdf) = sample(Random::sample(), Random::sample());
+            let estimate = if wi.z() > 0.0 && pdf > 0.0 {
+                m.eval(m.uvw.local_from_vec3(&wi)) / pdf
+            } else {
+                0.0
+            };
+            sum += estimate;
+            sum_sq += estimate * estimate;
+        }
+        let mean = sum / SAMPLES as Float;
+        (mean, sum_sq / SAMPLES as Float - mean * mean)
+    }
+
+    /// Sampling visible normals estimates the same reflectance as sampling
+    /// the distribution of normals with less variance at grazing angles.
+    #[test]
+    fn visible_normals_reduce_variance() {
+        let alpha = 0.5;
+        for cos_o in [0.9, 0.5, 0.1 as Float] {
+            let wo = Vec3::new((1.0 - cos_o * cos_o).sqrt(), 0.0, cos_o);
+            let m = Microfacet::new(Vec3::new(0.0, 0.0, 1.0), wo, alpha, None);
+
+            let (vndf_mean, vndf_var) = reflectance(&m, |u1, u2| {
+                let h = sample_visible_normal(m.wo, alpha, u1, u2);
+                let wi = (-m.wo).reflect(h);
+                (wi, m.value(m.uvw.local_from_vec3(&wi)))
+            });
+
+            let (ndf_mean, ndf_var) = reflectance(&m, |u1, u2| {
+                let tan2 = alpha * alpha * u1 / (1.0 - u1);
+                let cos_h = 1.0 / (1.0 + tan2).sqrt();
+                let sin_h = (1.0 - cos_h * cos_h).max(0.0).sqrt();
+                let phi = TWO_PI * u2;
+                let h = Vec3::new(sin_h * phi.cos(), sin_h * phi.sin(), cos_h);
+                let wi = (-m.wo).reflect(h);
+                let pdf = distribution(h, alpha) * cos_h / (4.0 * m.wo.dot(h).abs());
+                (wi, pdf)
+            });
+
+            assert!(
+                (vndf_mean - ndf_mean).abs() < 0.02,
+                "cos_o = {}: means {} and {} differ",
+                cos_o,
+                vndf_mean,
+                ndf_mean
+            );
+            if cos_o < 0.2 {
+                assert!(
+                    vndf_var < ndf_var,
+                    "cos_o = {}: variance {} not below {}",
+                    cos_o,
+                    vndf_var,
+                    ndf_var
+                );
+            }
+        }
+    }
+
+    /// The density of scattered directions integrates to at most one over
+    /// the sphere of directions.
+    #[test]
+    fn pdf_integrates_to_at_most_one() {
+        let (nz, nphi) = (400, 800);
+        let (dz, dphi) = (2.0 / nz as Float, TWO_PI / nphi as Float);
+
+        for etai_over_etat in [None, Some(1.0 / 1.5), Some(1.5)] {
+            for alpha in [0.2, 0.5, 1.0] {
+                for cos_o in [0.95, 0.5, 0.1 as Float] {
+                    let wo = Vec3::new((1.0 - cos_o * cos_o).sqrt(), 0.0, cos_o);
+                    let m = Microfacet::new(Vec3::new(0.0, 0.0, 1.0), wo, alpha, etai_over_etat);
+
+                    let mut integral = 0.0;
+                    for i in 0..nz {
+                        let z = -1.0 + (i as Float + 0.5) * dz;
+                        let r = (1.0 - z * z).sqrt();
+                        for j in 0..nphi {
+                            let phi = (j as Float + 0.5) * dphi;
+                            let direction = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+                            integral += m.value(direction) * dz * dphi;
+                        }
+                    }
+
+                    assert!(
+                        integral <= 1.01,
+                        "eta = {:?}, alpha = {}, cos_o = {}: integral {}",
+                        etai_over_etat,
+                        alpha,
+                        cos_o,
+                        integral
+                    );
+                }
+            }
+        }
+    }
+}
@@ -2,7 +2,9 @@
 //!
 //! A library for handling isotropic material for constant medium effects.
 
-use super::{ArcMaterial, ArcTexture, HitRecord, Material, Random, Ray, ScatterRecord};
+use super::{
+    ArcMaterial, ArcTexture, Float, HitRecord, Material, Ray, ScatterRecord, SpherePDF, Table, PI,
+};
 use std::fmt;
 use std::sync::Arc;
 
@@ -48,22 +50,37 @@ impl Material for Isotropic {
     /// Scatter an incident ray and determine the attenuation.
     /// If the incident ray is absorbed, `None` is returned.
     ///
-    /// We want the probability to be higher for ray scattering close to
-    /// the normal, but the distribution has to be more uniform.
+    /// Light is scattered equally in all directions. Scattering is
+    /// described by a PDF so points inside media sample the lights like
+    /// diffuse surfaces do. Rays towards a light pass through the rest of
+    /// the medium, which accounts for its transmittance.
     ///
-    /// * `ray_in` - Incident ray.
+    /// * `_ray_in` - Incident ray (ignored).
     /// * `rec` - The `HitRecord`.
-    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
-        // Scattering will pick a uniform random direction.
-        let scatter_direction = Random::vec3_in_unit_sphere();
-        let scattered_ray = Some(Ray::new(rec.point, scatter_direction, ray_in.time));
-        let attenuation = self.albedo.value(rec.u, rec.v, &rec.point);
-
+    fn scatter(&self, _ray_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
         Some(ScatterRecord {
-            scattered_ray,
-            attenuation,
+            attenuation: self.albedo.value(rec.u, rec.v, &rec.point),
+            pdf: Some(Arc::new(SpherePDF::new())),
+            scattered_ray: None, // pdf handles it
             specular_ray: None,
-            pdf: None,
         })
     }
+
+    /// Return the PDF value of scattering in a direction which is the same
+    /// for all directions.
+    ///
+    /// * `_ray_in` - Incident ray (ignored).
+    /// * `_rec` - The `HitRecord` (ignored).
+    /// * `_scattered` - Scattered ray (ignored).
+    fn scattering_pdf(&self, _ray_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> Float {
+        1.0 / (4.0 * PI)
+    }
+
+    /// Returns a description of the material in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "isotropic".into());
+        t.insert("albedo".into(), self.albedo.describe());
+        t
+    }
 }
@@ -3,7 +3,7 @@
 //! A library for handling Lambertian diffuse material.
 
 use super::{
-    ArcMaterial, ArcTexture, CosinePDF, Float, HitRecord, Material, Ray, ScatterRecord, PI,
+    ArcMaterial, ArcTexture, CosinePDF, Float, HitRecord, Material, Ray, ScatterRecord, Table, PI,
 };
 use std::fmt;
 use std::sync::Arc;
@@ -82,4 +82,12 @@ impl Material for Lambertian {
             cosine / PI
         }
     }
+
+    /// Returns a description of the material in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "lambertian".into());
+        t.insert("albedo".into(), self.albedo.describe());
+        t
+    }
 }
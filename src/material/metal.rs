@@ -2,7 +2,10 @@
 //!
 //! A library for handling reflective material.
 
-use super::{ArcMaterial, ArcTexture, Float, HitRecord, Material, Random, Ray, ScatterRecord};
+use super::{
+    ArcMaterial, ArcTexture, Float, FuzzPDF, HitRecord, Material, Ray, ScatterRecord, Table, Vec3,
+    PDF,
+};
 use std::fmt;
 use std::sync::Arc;
 
@@ -27,6 +30,25 @@ impl Metal {
             fuzz,
         })
     }
+
+    /// Returns the fuzz at a hit point. Paths may clamp the fuzz to blur
+    /// reflections of later bounces.
+    ///
+    /// * `rec` - The `HitRecord`.
+    fn fuzz(&self, rec: &HitRecord) -> Float {
+        self.fuzz.max(rec.min_roughness)
+    }
+
+    /// Returns the unit surface normal and the unit mirror reflection
+    /// direction of an incident ray.
+    ///
+    /// * `ray_in` - Incident ray.
+    /// * `rec` - The `HitRecord`.
+    fn reflect(ray_in: &Ray, rec: &HitRecord) -> (Vec3, Vec3) {
+        let unit_normal = rec.normal.unit_vector();
+        let reflected = ray_in.direction.unit_vector().reflect(unit_normal);
+        (unit_normal, reflected)
+    }
 }
 
 impl fmt::Display for Metal {
@@ -54,27 +76,67 @@ impl Material for Metal {
     /// Scatter an incident ray and determine the attenuation.
     /// If the incident ray is absorbed, `None` is returned.
     ///
-    /// Model the reflections. For grazing angles, the ray is absorbed.
-    /// Use a small sphere based on `fuzz` to randomize the reflected
-    /// direction for blurry reflection.
+    /// Model the reflections. Mirror reflections are specular. Otherwise a
+    /// small sphere based on `fuzz` randomizes the reflected direction for
+    /// blurry reflection and its PDF lets lights be sampled too. Directions
+    /// below the surface are absorbed.
     ///
     /// * `ray_in` - Incident ray.
     /// * `rec` - The `HitRecord`.
     fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
-        let unit_normal = rec.normal.unit_vector();
-        let reflected = ray_in.direction.unit_vector().reflect(unit_normal);
+        let (unit_normal, reflected) = Metal::reflect(ray_in, rec);
+        if reflected.dot(unit_normal) <= 0.0 {
+            return None;
+        }
 
-        let scatter_direction = reflected + self.fuzz * Random::vec3_in_unit_sphere();
+        let attenuation = self.albedo.value(rec.u, rec.v, &rec.point);
 
-        if scatter_direction.dot(unit_normal) > 0.0 {
+        let fuzz = self.fuzz(rec);
+        if fuzz > 0.0 {
+            Some(ScatterRecord {
+                attenuation,
+                pdf: Some(Arc::new(FuzzPDF::new(reflected, fuzz))),
+                scattered_ray: None, // pdf handles it
+                specular_ray: None,
+            })
+        } else {
             Some(ScatterRecord {
-                specular_ray: Some(Ray::new(rec.point, scatter_direction, ray_in.time)),
-                attenuation: self.albedo.value(rec.u, rec.v, &rec.point),
+                specular_ray: Some(Ray::new(
+                    rec.offset_origin(reflected),
+                    reflected,
+                    ray_in.time,
+                )),
+                attenuation,
                 scattered_ray: None,
                 pdf: None,
             })
+        }
+    }
+
+    /// Return the PDF value at a point on the surface. Used for importance
+    /// sampling. Fuzzy reflections are sampled exactly so this is the
+    /// density of the blurred reflection above the surface. Mirror
+    /// reflections have no density.
+    ///
+    /// * `ray_in` - Incident ray.
+    /// * `rec` - The `HitRecord`.
+    /// * `scattered` - The scattered ray.
+    fn scattering_pdf(&self, ray_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Float {
+        let fuzz = self.fuzz(rec);
+        let (unit_normal, reflected) = Metal::reflect(ray_in, rec);
+        if fuzz <= 0.0 || scattered.direction.dot(unit_normal) <= 0.0 {
+            0.0
         } else {
-            None
+            FuzzPDF::new(reflected, fuzz).value(scattered.direction)
         }
     }
+
+    /// Returns a description of the material in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "metal".into());
+        t.insert("albedo".into(), self.albedo.describe());
+        t.insert("fuzz".into(), self.fuzz.into());
+        t
+    }
 }
@@ -0,0 +1,161 @@
+//! # Mix
+//!
+//! A library for blending two materials, e.g. paint partially worn off a
+//! metal. Each scattering event uses one of the materials chosen at random
+//! with the blend factor as the probability of the second one.
+//!
+//! The choice is made from a hash of the intersection and the incident ray
+//! instead of the sampler so `scatter` and `scattering_pdf` agree on the
+//! material for the same event.
+
+use super::{
+    ArcMaterial, ArcTexture, Colour, Float, HitRecord, Material, Ray, ScatterRecord, Table,
+};
+use std::fmt;
+use std::sync::Arc;
+
+/// Models a blend of two materials.
+#[derive(Clone)]
+pub struct Mix {
+    /// First material.
+    a: ArcMaterial,
+
+    /// Second material.
+    b: ArcMaterial,
+
+    /// Blend factor given by the average of the texture's colour channels.
+    /// Zero is the first material and one the second.
+    amount: ArcTexture,
+}
+
+impl Mix {
+    /// Creates a new blend of two materials.
+    ///
+    /// * `a` - First material.
+    /// * `b` - Second material.
+    /// * `amount` - Blend factor given by the average of the texture's
+    ///   colour channels. Zero is the first material and one the second.
+    pub fn new(a: ArcMaterial, b: ArcMaterial, amount: ArcTexture) -> ArcMaterial {
+        Arc::new(Mix { a, b, amount })
+    }
+
+    /// Returns the blend factor at an intersection.
+    ///
+    /// * `rec` - The `HitRecord`.
+    fn amount(&self, rec: &HitRecord) -> Float {
+        let c = self.amount.value(rec.u, rec.v, &rec.point);
+        ((c.x() + c.y() + c.z()) / 3.0).clamp(0.0, 1.0)
+    }
+
+    /// Returns the material chosen for a scattering event.
+    ///
+    /// * `ray_in` - Incident ray.
+    /// * `rec` - The `HitRecord`.
+    fn choose(&self, ray_in: &Ray, rec: &HitRecord) -> &ArcMaterial {
+        if event_hash(ray_in, rec) < self.amount(rec) {
+            &self.b
+        } else {
+            &self.a
+        }
+    }
+}
+
+impl fmt::Display for Mix {
+    /// Display the mix parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "mix(a: {}, b: {}, amount: {})",
+            self.a, self.b, self.amount
+        )
+    }
+}
+
+impl fmt::Debug for Mix {
+    /// Display the mix parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mix")
+            .field("a", &self.a)
+            .field("b", &self.b)
+            .field("amount", &self.amount)
+            .finish()
+    }
+}
+
+impl Material for Mix {
+    /// Scatter an incident ray and determine the attenuation.
+    /// If the incident ray is absorbed, `None` is returned.
+    ///
+    /// * `ray_in` - Incident ray.
+    /// * `rec` - The `HitRecord`.
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        self.choose(ray_in, rec).scatter(ray_in, rec)
+    }
+
+    /// Return the PDF value at a point on the surface. Used for importance
+    /// sampling.
+    ///
+    /// * `ray_in` - Incident ray.
+    /// * `rec` - The `HitRecord`.
+    /// * `scattered` - The scattered ray.
+    fn scattering_pdf(&self, ray_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Float {
+        self.choose(ray_in, rec)
+            .scattering_pdf(ray_in, rec, scattered)
+    }
+
+    /// Return the emitted colour value blended from both materials.
+    ///
+    /// * `ray_in` - Incident ray.
+    /// * `rec` - The `HitRecord`.
+    fn emission(&self, ray_in: &Ray, rec: &HitRecord) -> Colour {
+        let amount = self.amount(rec);
+        self.a.emission(ray_in, rec) * (1.0 - amount) + self.b.emission(ray_in, rec) * amount
+    }
+
+    /// Returns true if either material emits light.
+    fn is_emissive(&self) -> bool {
+        self.a.is_emissive() || self.b.is_emissive()
+    }
+
+    /// Returns a description of the material in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "mix".into());
+        t.insert("a".into(), self.a.describe().into());
+        t.insert("b".into(), self.b.describe().into());
+        t.insert("amount".into(), self.amount.describe());
+        t
+    }
+}
+
+/// Returns a number in `[0, 1)` that is fixed for a scattering event and
+/// uncorrelated between events.
+///
+/// * `ray_in` - Incident ray.
+/// * `rec` - The `HitRecord`.
+fn event_hash(ray_in: &Ray, rec: &HitRecord) -> Float {
+    let values = [
+        rec.point.x(),
+        rec.point.y(),
+        rec.point.z(),
+        ray_in.direction.x(),
+        ray_in.direction.y(),
+        ray_in.direction.z(),
+        ray_in.time,
+    ];
+
+    // Splitmix64 finalizer over the bits of each value.
+    let mut h: u64 = 0;
+    for v in values.iter() {
+        h = (h ^ v.to_bits()).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        h ^= h >> 31;
+    }
+
+    (h >> 11) as Float / (1_u64 << 53) as Float
+}
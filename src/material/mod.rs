@@ -2,25 +2,37 @@
 //!
 //! A library for handling materials.
 
+mod absorber;
 mod dielectric;
 mod diffuse_light;
+mod ggx;
 mod isotropic;
 mod lambertian;
 mod metal;
+mod mix;
+mod rough_dielectric;
+mod rough_metal;
 
-use super::algebra::{Colour, Ray};
-use super::common::{ArcPDF, CosinePDF, Float, Random, PI};
+use super::algebra::{Colour, Ray, Vec3, ONB};
+use super::common::{
+    ArcPDF, CosinePDF, Float, FuzzPDF, Random, Sampler, SpherePDF, PDF, PI, TWO_PI,
+};
 use super::object::HitRecord;
 use super::texture::ArcTexture;
 use std::fmt;
 use std::sync::Arc;
+use toml::Table;
 
 // Re-exports.
+pub use self::absorber::Absorber;
 pub use self::dielectric::Dielectric;
 pub use self::diffuse_light::DiffuseLight;
 pub use self::isotropic::Isotropic;
 pub use self::lambertian::Lambertian;
 pub use self::metal::Metal;
+pub use self::mix::Mix;
+pub use self::rough_dielectric::RoughDielectric;
+pub use self::rough_metal::RoughMetal;
 
 /// Models the result of scattering a ray.
 #[derive(Debug, Clone)]
@@ -66,6 +78,14 @@ pub trait Material: fmt::Display + fmt::Debug {
     fn emission(&self, _ray_in: &Ray, _rec: &HitRecord) -> Colour {
         Colour::zero()
     }
+
+    /// Returns true if the material emits light. Default is false.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// Returns a description of the material in the scene file format.
+    fn describe(&self) -> Table;
 }
 
 /// Atomic reference counted `Material`.
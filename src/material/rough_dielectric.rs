@@ -0,0 +1,140 @@
+//! # RoughDielectric
+//!
+//! A library for handling rough dielectric material, e.g. frosted glass,
+//! with the GGX microfacet BSDF. Reflected and refracted directions are
+//! importance sampled and have a density, so light sources are sampled
+//! through rough glass.
+
+use super::{
+    ggx, ArcMaterial, Colour, Dielectric, Float, HitRecord, Material, Ray, ScatterRecord, Table,
+};
+use std::fmt;
+use std::sync::Arc;
+
+/// Models a rough dielectric material.
+#[derive(Clone)]
+pub struct RoughDielectric {
+    /// Index of refraction.
+    ref_idx: Float,
+
+    /// Reciprocal of `ref_idx`.
+    one_over_ref_idx: Float,
+
+    /// Perceptual roughness.
+    roughness: Float,
+
+    /// GGX alpha remapped from `roughness`.
+    alpha: Float,
+}
+
+impl RoughDielectric {
+    /// Creates a new rough dielectric material. A smooth surface returns a
+    /// `Dielectric` instead.
+    ///
+    /// * `ri` - Index of refraction.
+    /// * `roughness` - Perceptual roughness in `[0, 1]`.
+    pub fn new(ri: Float, roughness: Float) -> ArcMaterial {
+        let roughness = roughness.clamp(0.0, 1.0);
+        if roughness == 0.0 {
+            return Dielectric::new(ri);
+        }
+
+        Arc::new(RoughDielectric {
+            ref_idx: ri,
+            one_over_ref_idx: 1.0 / ri,
+            roughness,
+            alpha: ggx::roughness_to_alpha(roughness),
+        })
+    }
+
+    /// Returns the microfacet BSDF at an intersection.
+    ///
+    /// * `ray_in` - Incident ray.
+    /// * `rec` - The `HitRecord`.
+    fn microfacet(&self, ray_in: &Ray, rec: &HitRecord) -> ggx::Microfacet {
+        let etai_over_etat = if rec.front_face {
+            self.one_over_ref_idx
+        } else {
+            self.ref_idx
+        };
+
+        ggx::Microfacet::new(
+            rec.normal.unit_vector(),
+            -ray_in.direction.unit_vector(),
+            self.alpha.max(ggx::roughness_to_alpha(rec.min_roughness)),
+            Some(etai_over_etat),
+        )
+    }
+}
+
+impl fmt::Display for RoughDielectric {
+    /// Display the rough dielectric parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "rough_dielectric(ref_idx: {}, one_over_ref_idx: {}, roughness: {})",
+            self.ref_idx, self.one_over_ref_idx, self.roughness
+        )
+    }
+}
+
+impl fmt::Debug for RoughDielectric {
+    /// Display the rough dielectric parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RoughDielectric")
+            .field("ref_idx", &self.ref_idx)
+            .field("one_over_ref_idx", &self.one_over_ref_idx)
+            .field("roughness", &self.roughness)
+            .finish()
+    }
+}
+
+impl Material for RoughDielectric {
+    /// Scatter an incident ray and determine the attenuation.
+    /// If the incident ray is absorbed, `None` is returned.
+    ///
+    /// Reflections and refractions are sampled about microfacet normals
+    /// visible from the incident direction and chosen by their Fresnel
+    /// reflectance.
+    ///
+    /// * `ray_in` - Incident ray.
+    /// * `rec` - The `HitRecord`.
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let pdf = self.microfacet(ray_in, rec);
+        if !pdf.is_visible() {
+            return None;
+        }
+
+        // No attenuation
+        Some(ScatterRecord {
+            attenuation: Colour::new(1.0, 1.0, 1.0),
+            pdf: Some(Arc::new(pdf)),
+            scattered_ray: None, // pdf handles it
+            specular_ray: None,
+        })
+    }
+
+    /// Return the PDF value at a point on the surface. Used for importance
+    /// sampling. This is the BSDF times the cosine of the scattered
+    /// direction.
+    ///
+    /// * `ray_in` - Incident ray.
+    /// * `rec` - The `HitRecord`.
+    /// * `scattered` - The scattered ray.
+    fn scattering_pdf(&self, ray_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Float {
+        self.microfacet(ray_in, rec).eval(scattered.direction)
+    }
+
+    /// Returns a description of the material in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "rough_dielectric".into());
+        t.insert("ior".into(), self.ref_idx.into());
+        t.insert("roughness".into(), self.roughness.into());
+        t
+    }
+}
@@ -0,0 +1,128 @@
+//! # RoughMetal
+//!
+//! A library for handling rough reflective material with the GGX microfacet
+//! BRDF. Unlike the fuzzy reflections of `Metal` the scattered directions
+//! are importance sampled and have a density, so rough metals are lit by
+//! light sampling like diffuse materials.
+
+use super::{
+    ggx, ArcMaterial, ArcTexture, Float, HitRecord, Material, Metal, Ray, ScatterRecord, Table,
+};
+use std::fmt;
+use std::sync::Arc;
+
+/// Models a rough metal.
+#[derive(Clone)]
+pub struct RoughMetal {
+    /// Reflectance at normal incidence provided by a texture.
+    albedo: ArcTexture,
+
+    /// Perceptual roughness.
+    roughness: Float,
+
+    /// GGX alpha remapped from `roughness`.
+    alpha: Float,
+}
+
+impl RoughMetal {
+    /// Creates a new rough metal material. A smooth surface is a perfect
+    /// mirror and returns a `Metal` instead.
+    ///
+    /// * `albedo` - Reflectance at normal incidence provided by a texture.
+    /// * `roughness` - Perceptual roughness in `[0, 1]`.
+    pub fn new(albedo: ArcTexture, roughness: Float) -> ArcMaterial {
+        let roughness = roughness.clamp(0.0, 1.0);
+        if roughness == 0.0 {
+            return Metal::new(albedo, 0.0);
+        }
+
+        Arc::new(RoughMetal {
+            albedo: Arc::clone(&albedo),
+            roughness,
+            alpha: ggx::roughness_to_alpha(roughness),
+        })
+    }
+
+    /// Returns the microfacet BRDF at an intersection. The roughness is
+    /// clamped to the minimum roughness of the intersection.
+    ///
+    /// * `ray_in` - Incident ray.
+    /// * `rec` - The `HitRecord`.
+    fn microfacet(&self, ray_in: &Ray, rec: &HitRecord) -> ggx::Microfacet {
+        ggx::Microfacet::new(
+            rec.normal.unit_vector(),
+            -ray_in.direction.unit_vector(),
+            self.alpha.max(ggx::roughness_to_alpha(rec.min_roughness)),
+            None,
+        )
+    }
+}
+
+impl fmt::Display for RoughMetal {
+    /// Display the rough metal parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "rough_metal(albedo: {}, roughness: {})",
+            self.albedo, self.roughness
+        )
+    }
+}
+
+impl fmt::Debug for RoughMetal {
+    /// Display the rough metal parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RoughMetal")
+            .field("albedo", &self.albedo)
+            .field("roughness", &self.roughness)
+            .finish()
+    }
+}
+
+impl Material for RoughMetal {
+    /// Scatter an incident ray and determine the attenuation.
+    /// If the incident ray is absorbed, `None` is returned.
+    ///
+    /// Reflections are sampled about microfacet normals visible from the
+    /// incident direction.
+    ///
+    /// * `ray_in` - Incident ray.
+    /// * `rec` - The `HitRecord`.
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let pdf = self.microfacet(ray_in, rec);
+        if !pdf.is_visible() {
+            return None;
+        }
+
+        Some(ScatterRecord {
+            attenuation: self.albedo.value(rec.u, rec.v, &rec.point),
+            pdf: Some(Arc::new(pdf)),
+            scattered_ray: None, // pdf handles it
+            specular_ray: None,
+        })
+    }
+
+    /// Return the PDF value at a point on the surface. Used for importance
+    /// sampling. This is the BRDF times the cosine of the scattered
+    /// direction without the albedo.
+    ///
+    /// * `ray_in` - Incident ray.
+    /// * `rec` - The `HitRecord`.
+    /// * `scattered` - The scattered ray.
+    fn scattering_pdf(&self, ray_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Float {
+        self.microfacet(ray_in, rec).eval(scattered.direction)
+    }
+
+    /// Returns a description of the material in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "rough_metal".into());
+        t.insert("albedo".into(), self.albedo.describe());
+        t.insert("roughness".into(), self.roughness.into());
+        t
+    }
+}
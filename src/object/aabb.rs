@@ -5,7 +5,19 @@
 
 use super::{Float, Point3, Ray, AXES};
 use std::fmt;
-use std::mem::swap;
+
+/// Error bound for the 3 floating point operations in the slab test used to
+/// conservatively grow the far slab distance, `1 + 2γ(3)`.
+const ROBUST_SCALE: Float = 1.0 + 2.0 * gamma(3);
+
+/// Returns the bound `γ(n) = nε / (1 - nε)` on the relative error of `n`
+/// floating point operations where `ε` is the machine epsilon.
+///
+/// * `n` - Number of floating point operations.
+const fn gamma(n: i32) -> Float {
+    let e = (n as Float) * Float::EPSILON * 0.5;
+    e / (1.0 - e)
+}
 
 /// Models an axis aligned bounding box.
 #[derive(Debug, Copy, Clone)]
@@ -55,31 +67,64 @@ impl AABB {
         AABB::new(small, big)
     }
 
-    /// Returns `true` if a ray intersects the AABB; `false` otherwise.
+    /// Returns the centre of the box.
+    pub fn centroid(&self) -> Point3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Returns the surface area of the box.
+    pub fn surface_area(&self) -> Float {
+        let d = self.max - self.min;
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    /// Returns the parametric interval `(t_enter, t_exit)` along the ray that
+    /// lies inside the AABB clipped to `[t_min, t_max]` if the ray intersects
+    /// the AABB; `None` otherwise.
+    ///
+    /// The entry distance `t_enter` can be used to order traversal of
+    /// bounding volumes so that nearer ones are visited first.
+    ///
+    /// This uses the slab test by Williams et al. with the conservative
+    /// rounding by Ize. Rays parallel to an axis have an infinite inverse
+    /// direction component. If the origin lies exactly on that slab's plane
+    /// the slab distances are NaN; the comparisons below are written so NaNs
+    /// never replace the running interval.
     ///
     /// * `ray` - The incident ray.
     /// * `t_min` - The minium parameter for intersections.
     /// * `t_max` - The maximum parameter for intersections.
-    pub fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> bool {
+    pub fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<(Float, Float)> {
+        let mut tmin = t_min;
+        let mut tmax = t_max;
+
+        let bounds = [self.min, self.max];
+
         for a in AXES {
-            let inv_d = 1.0 / ray.direction[*a];
+            let inv_d = ray.inv_direction[*a];
+            let sign = ray.sign[*a];
 
-            let mut t0 = (self.min[*a] - ray.origin[*a]) * inv_d;
-            let mut t1 = (self.max[*a] - ray.origin[*a]) * inv_d;
+            // The ray's sign bit selects the near and far slab directly.
+            let t0 = (bounds[sign][*a] - ray.origin[*a]) * inv_d;
+            let mut t1 = (bounds[1 - sign][*a] - ray.origin[*a]) * inv_d;
 
-            if inv_d < 0.0 {
-                swap(&mut t0, &mut t1);
-            }
+            // Make the far distance slightly larger to account for floating
+            // point rounding in the computation above.
+            t1 *= ROBUST_SCALE;
 
-            let tmin = if t0 > t_min { t0 } else { t_min };
+            if t0 > tmin {
+                tmin = t0;
+            }
 
-            let tmax = if t1 < t_max { t1 } else { t_max };
+            if t1 < tmax {
+                tmax = t1;
+            }
 
-            if tmax <= tmin {
-                return false;
+            if tmax < tmin {
+                return None;
             }
         }
 
-        true
+        Some((tmin, tmax))
     }
 }
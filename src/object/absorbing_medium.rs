@@ -0,0 +1,150 @@
+//! # AbsorbingMedium
+//!
+//! A library for handling ray intersections within a medium that absorbs
+//! light without scattering it, e.g. tinted glass interiors and murky
+//! water. Rays that aren't absorbed pass straight through, which is much
+//! cheaper than scattering media.
+//!
+//! Collisions are sampled at the absorption of the most absorbed channel.
+//! A collision absorbs that channel and attenuates the others by their
+//! absorption relative to it, so the expected transmittance of each channel
+//! follows Beer's law.
+
+use super::{
+    boundary_intervals, Absorber, ArcHittable, ArcMaterial, Colour, Float, HitRecord, Hittable,
+    Random, Ray, Scratch, Table, Vec3, AABB,
+};
+use std::fmt;
+use std::sync::Arc;
+
+/// Smallest transmitted colour channel. Smaller values absorb everything.
+const MIN_TRANSMITTANCE: Float = 1.0e-6;
+
+/// Models a medium that only absorbs light.
+#[derive(Debug, Clone)]
+pub struct AbsorbingMedium {
+    /// Boundary
+    boundary: ArcHittable,
+
+    /// Colour transmitted through a unit distance at unit density.
+    colour: Colour,
+
+    /// Density of medium.
+    density: Float,
+
+    /// -1/σ where σ is the absorption of the most absorbed channel.
+    neg_inv_absorption: Float,
+
+    /// Material of collisions (this will be an absorber).
+    absorber: ArcMaterial,
+}
+
+impl fmt::Display for AbsorbingMedium {
+    /// Display the absorbing medium parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "absorbing_medium(boundary: {}, colour: {}, density: {}, absorber: {})",
+            self.boundary, self.colour, self.density, self.absorber
+        )
+    }
+}
+
+impl AbsorbingMedium {
+    /// Create a new absorbing medium.
+    ///
+    /// * `boundary` - Object determines surface boundary. It should be a
+    ///   closed surface but need not be convex.
+    /// * `colour` - Colour transmitted through a unit distance at unit
+    ///   density.
+    /// * `density` - Density of medium.
+    pub fn new(boundary: ArcHittable, colour: Colour, density: Float) -> ArcHittable {
+        // Beer's law absorption of each channel.
+        let sigma = |c: Float| -density.max(0.0) * c.clamp(MIN_TRANSMITTANCE, 1.0).ln();
+        let absorption = Vec3::new(sigma(colour.x()), sigma(colour.y()), sigma(colour.z()));
+        let majorant = absorption.max_component();
+
+        let weight = if majorant > 0.0 {
+            Colour::new(1.0, 1.0, 1.0) - absorption / majorant
+        } else {
+            Colour::new(1.0, 1.0, 1.0)
+        };
+
+        Arc::new(AbsorbingMedium {
+            boundary,
+            colour,
+            density,
+            neg_inv_absorption: -1.0 / majorant,
+            absorber: Absorber::new(weight),
+        })
+    }
+}
+
+impl Hittable for AbsorbingMedium {
+    /// Calculate the intersection of a ray with the medium.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `t_min` - The minium parameter for intersections.
+    /// * `t_max` - The maximum parameter for intersections.
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        if !self.neg_inv_absorption.is_finite() {
+            return None;
+        }
+
+        Scratch::with(|scratch| {
+            let intervals = &mut scratch.intervals;
+            boundary_intervals(&self.boundary, ray, intervals);
+
+            let ray_length = ray.direction.length();
+            let mut hit_distance = self.neg_inv_absorption * Random::sample::<Float>().ln();
+
+            for &(t0, t1) in intervals.iter() {
+                let t0 = t0.max(t_min).max(0.0);
+                let t1 = t1.min(t_max);
+
+                if t0 >= t1 {
+                    continue;
+                }
+
+                let distance_inside_boundary = (t1 - t0) * ray_length;
+                if hit_distance > distance_inside_boundary {
+                    hit_distance -= distance_inside_boundary;
+                    continue;
+                }
+
+                let t = t0 + hit_distance / ray_length;
+                return Some(HitRecord::new(
+                    ray,
+                    t,
+                    ray.at(t),
+                    Vec3::new(1.0, 0.0, 0.0), // arbitrary normal
+                    Arc::clone(&self.absorber),
+                    0.0, // arbitrary
+                    1.0, // arbitrary
+                ));
+            }
+
+            None
+        })
+    }
+
+    /// Create a bounding box across time interval `[t0, t1]`.
+    ///
+    /// * `time0` - Start time of motion.
+    /// * `time1` - End time of motion.
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
+        self.boundary.bounding_box(time0, time1)
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "absorbing_medium".into());
+        t.insert("boundary".into(), self.boundary.describe().into());
+        t.insert("colour".into(), self.colour.into());
+        t.insert("density".into(), self.density.into());
+        t
+    }
+}
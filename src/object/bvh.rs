@@ -1,44 +1,139 @@
 //! # BVH
 //!
-//! A library for bounding volume hierarchy.
+//! A library for bounding volume hierarchy. The hierarchy is built with the
+//! surface area heuristic using binned partitioning and flattened into a
+//! contiguous list of nodes that is traversed iteratively. Small subtrees of
+//! spheres are collapsed into leaves holding a `SphereBatch`.
 
-use super::{ArcHittable, Axis, Float, HitRecord, Hittable, Random, Ray, AABB};
+use super::{
+    describe_group, emissive_leaves, ArcHittable, Axis, BvhCache, Float, HitRecord, Hittable,
+    HittableList, Point3, Ray, Scratch, SphereBatch, Table, Value, AABB, AXES, INFINITY,
+    SPHERE_BATCH_SIZE, X_AXIS,
+};
 use std::fmt;
 use std::sync::Arc;
 
-/// Models a node in a bounding volume hierarchy.
+/// Number of bins used to find the split with the lowest cost.
+const SAH_BINS: usize = 12;
+
+/// Models a bounding volume hierarchy.
 pub struct BVH {
-    /// Left child. Leaf nodes would be any Hittable other than a BVH node.
-    left: ArcHittable,
+    /// Nodes in depth first order. The root is the first node and the first
+    /// child of an interior node immediately follows it.
+    nodes: Vec<Node>,
 
-    /// Right child. Leaf nodes would be any Hittable other than a BVH node.
-    right: ArcHittable,
+    /// Objects referenced by leaf nodes. Batched spheres replace the
+    /// spheres they hold.
+    objects: Vec<ArcHittable>,
+}
 
-    /// Indicates `left` == `right`. This helps avoid using `Option<ArcHittable>`
-    /// for `left` and `right` and simplify the `split()` function.
-    leaf: bool,
+/// Models a node in a flattened bounding volume hierarchy.
+#[derive(Debug, Copy, Clone)]
+pub struct Node {
+    /// Bounding box for objects in the node.
+    pub bbox: AABB,
 
-    /// Bounding box for objects in the current node.
-    bbox: Option<AABB>,
+    /// Leaf or interior node.
+    pub kind: NodeKind,
+}
+
+/// Type of node in a flattened bounding volume hierarchy.
+#[derive(Debug, Copy, Clone)]
+pub enum NodeKind {
+    /// Leaf node with the index of its object.
+    Leaf(usize),
+
+    /// Interior node with the index of its second child and the axis its
+    /// objects were split along.
+    Interior { second: usize, axis: Axis },
 }
 
 impl BVH {
     /// Create a new bounding volume hierarchy.
     ///
     /// Notes:
-    /// * This function will panic if any object doesn't have a bounding box.
-    /// * The objects list will be re-ordered.
+    /// * Objects without a bounding box, e.g. infinite planes, are kept
+    ///   outside the tree and tested separately by a `HittableList`.
+    /// * The objects list will be re-ordered with the unbounded objects
+    ///   last.
     ///
     /// * `objects` - List of objects
     /// * `time0` - Start time of motion.
     /// * `time1` - End time of motion.
     pub fn new(objects: &mut Vec<ArcHittable>, time0: Float, time1: Float) -> ArcHittable {
-        split(objects, 0, objects.len(), time0, time1)
+        BVH::with_cache(objects, time0, time1, None)
+    }
+
+    /// Create a new bounding volume hierarchy like `BVH::new()`. With a
+    /// cache the nodes are loaded from it when the objects have the same
+    /// bounding boxes as a previous build.
+    ///
+    /// * `objects` - List of objects
+    /// * `time0` - Start time of motion.
+    /// * `time1` - End time of motion.
+    /// * `cache` - Optional cache of built hierarchies.
+    pub fn with_cache(
+        objects: &mut Vec<ArcHittable>,
+        time0: Float,
+        time1: Float,
+        cache: Option<&BvhCache>,
+    ) -> ArcHittable {
+        let (bounded, unbounded): (Vec<ArcHittable>, Vec<ArcHittable>) = objects
+            .drain(..)
+            .partition(|o| o.bounding_box(time0, time1).is_some());
+        *objects = bounded;
+
+        if !unbounded.is_empty() {
+            let mut list = HittableList::new();
+            if !objects.is_empty() {
+                list.add(BVH::with_cache(objects, time0, time1, cache));
+            }
+            for o in unbounded.iter() {
+                list.add(Arc::clone(o));
+            }
+            objects.extend(unbounded);
+            return Arc::new(list);
+        }
+
+        let bboxes: Vec<AABB> = objects
+            .iter()
+            .filter_map(|o| o.bounding_box(time0, time1))
+            .collect();
+
+        let key = cache.map(|_| BvhCache::key(&bboxes, time0, time1));
+        let cached = cache
+            .zip(key)
+            .and_then(|(c, key)| c.load(key, objects.len()));
+        let (nodes, order) = match cached {
+            Some(cached) => cached,
+            None => {
+                let mut order: Vec<usize> = (0..objects.len()).collect();
+                let mut nodes = Vec::with_capacity(2 * objects.len());
+                split(&bboxes, &mut order, 0, &mut nodes);
+                if let (Some(cache), Some(key)) = (cache, key) {
+                    cache.save(key, &nodes, &order);
+                }
+                (nodes, order)
+            }
+        };
+
+        *objects = order.iter().map(|&i| Arc::clone(&objects[i])).collect();
+
+        // Batching happens after caching so cached nodes always refer to
+        // the original objects.
+        let mut batched_nodes = Vec::with_capacity(nodes.len());
+        let mut batched_objects = Vec::with_capacity(objects.len());
+        batch_spheres(&nodes, objects, 0, &mut batched_nodes, &mut batched_objects);
+
+        Arc::new(BVH {
+            nodes: batched_nodes,
+            objects: batched_objects,
+        })
     }
 }
 
 impl fmt::Display for BVH {
-    /// Display the bounding volume hierarchy recursively.
+    /// Display the bounding volume hierarchy.
     ///
     /// * `f` - Formatter.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -47,14 +142,14 @@ impl fmt::Display for BVH {
 }
 
 impl fmt::Debug for BVH {
-    /// Display the bounding volume hierarchy recursively.
+    /// Display the bounding volume hierarchy.
     ///
     /// * `f` - Formatter.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("BVH")
-            .field("left", &self.left)
-            .field("right", &self.right)
-            .field("bbox", &self.bbox)
+            .field("nodes", &self.nodes.len())
+            .field("objects", &self.objects)
+            .field("bbox", &self.nodes[0].bbox)
             .finish()
     }
 }
@@ -66,24 +161,45 @@ impl Hittable for BVH {
     /// * `t_min` - The minium parameter for intersections.
     /// * `t_max` - The maximum parameter for intersections.
     fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
-        // If the ray doesn't hit the bounding volume at this level, terminate
-        // search against subtree.
-        if self.bbox.is_none() || !self.bbox.unwrap().hit(ray, t_min, t_max) {
-            None
-        } else if self.leaf {
-            self.left.hit(ray, t_min, t_max)
-        } else if let Some(left_hit) = self.left.hit(ray, t_min, t_max) {
-            // Check if we get a hit on the right subtree that is closer
-            // than left_hit.t.
-            if let Some(right_hit) = self.right.hit(ray, t_min, left_hit.t) {
-                Some(right_hit)
-            } else {
-                Some(left_hit)
+        Scratch::with(|scratch| {
+            let stack = &mut scratch.traversal_stack;
+            stack.push(0);
+
+            let mut closest: Option<HitRecord> = None;
+            let mut t_closest = t_max;
+
+            while let Some(idx) = stack.pop() {
+                // If the ray misses the node's bounding volume before the
+                // closest hit so far, terminate search against that subtree.
+                let node = &self.nodes[idx];
+                if node.bbox.hit(ray, t_min, t_closest).is_none() {
+                    continue;
+                }
+
+                match node.kind {
+                    NodeKind::Leaf(object) => {
+                        if let Some(rec) = self.objects[object].hit(ray, t_min, t_closest) {
+                            t_closest = rec.t;
+                            closest = Some(rec);
+                        }
+                    }
+
+                    NodeKind::Interior { second, axis } => {
+                        // Visit the child nearer along the split axis first
+                        // so the closest hit shrinks the interval sooner.
+                        if ray.sign[axis] == 1 {
+                            stack.push(idx + 1);
+                            stack.push(second);
+                        } else {
+                            stack.push(second);
+                            stack.push(idx + 1);
+                        }
+                    }
+                }
             }
-        } else {
-            // Try right subtree.
-            self.right.hit(ray, t_min, t_max)
-        }
+
+            closest
+        })
     }
 
     /// Create a bounding box across time interval `[t0, t1]`.
@@ -96,98 +212,269 @@ impl Hittable for BVH {
     /// The time parameters are ignored since the BVH node's bounding box
     /// will already include it based on bounding boxes of the objects in it.
     fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
-        self.bbox
+        Some(self.nodes[0].bbox)
+    }
+
+    /// Returns the emissive objects of the leaf nodes.
+    fn emissive_parts(&self) -> Option<Vec<ArcHittable>> {
+        Some(self.objects.iter().flat_map(emissive_leaves).collect())
+    }
+
+    /// Returns a description of the object in the scene file format. The
+    /// tree is flattened into a single group of its leaf objects.
+    fn describe(&self) -> Table {
+        let mut objects = Vec::new();
+        for object in self.objects.iter() {
+            let mut t = object.describe();
+            match t.remove("objects") {
+                // Splice the objects of nested BVHs.
+                Some(Value::Array(nested)) if is_bvh_group(&t) => {
+                    objects.extend(nested.into_iter().filter_map(|v| match v {
+                        Value::Table(o) => Some(o),
+                        _ => None,
+                    }))
+                }
+                Some(nested) => {
+                    t.insert("objects".into(), nested);
+                    objects.push(t);
+                }
+                None => objects.push(t),
+            }
+        }
+
+        describe_group(objects, true)
     }
 }
 
-/// Split a list of objects into a bounding volume hierarchy.
+/// Split a list of objects into a bounding volume hierarchy and append its
+/// nodes in depth first order. Returns the bounding box of the subtree.
 ///
 /// __Notes:__
-/// * The list of objects gets re-ordered in this algorithm.
-/// * If bounding box is not is not returned for any object,
-///   function will panic. This is fine since its built before the
-///   raytracing algorithm is executed.
+/// * The object indices get re-ordered in this algorithm.
 ///
-/// * `objects` - List of objects.
-/// * `start` - Starting index in `objects`.
-/// * `n` - Number of objects to split.
-/// * `time0` - Start time of motion.
-/// * `time1` - End time of motion.
-fn split(
-    objects: &mut Vec<ArcHittable>,
-    start: usize,
-    n: usize,
-    time0: Float,
-    time1: Float,
-) -> ArcHittable {
-    let axis = Random::sample_in_range::<Axis>(0, 2);
-
-    let (left, right, leaf) = if n == 1 {
-        (
-            Arc::clone(&objects[start]),
-            Arc::clone(&objects[start]),
-            true,
-        )
-    } else {
-        let end = start + n - 1;
-        let slice = &mut objects[start..=end];
-
-        slice.sort_unstable_by(|a, b| {
-            let bbox_a = a.bounding_box(time0, time1);
-            let bbox_b = b.bounding_box(time0, time1);
-
-            match (bbox_a, bbox_b) {
-                (Some(bbox_a), Some(bbox_b)) => {
-                    let m1 = bbox_a.min[axis];
-                    let m2 = bbox_b.min[axis];
-                    if m1 < m2 {
-                        std::cmp::Ordering::Less
-                    } else if m1 > m2 {
-                        std::cmp::Ordering::Greater
-                    } else {
-                        std::cmp::Ordering::Equal
-                    }
-                }
+/// * `bboxes` - Bounding boxes of all objects.
+/// * `order` - Indices of the objects to split.
+/// * `start` - Position of the first index of `order` in the final order.
+/// * `nodes` - Flattened nodes.
+fn split(bboxes: &[AABB], order: &mut [usize], start: usize, nodes: &mut Vec<Node>) -> AABB {
+    let idx = nodes.len();
 
-                _ => panic!("No objects in BVH::split"),
-            }
+    if order.len() == 1 {
+        let bbox = bboxes[order[0]];
+        nodes.push(Node {
+            bbox,
+            kind: NodeKind::Leaf(start),
         });
+        return bbox;
+    }
 
-        if n == 2 {
-            (
-                Arc::clone(&objects[start]),
-                Arc::clone(&objects[start + 1]),
-                false,
-            )
-        } else {
-            let half = n / 2;
-            let even = n % 2 == 0;
-            let half2 = if even { half } else { half + 1 };
-
-            let l = split(objects, start, half, time0, time1);
-            let r = split(objects, start + half, half2, time0, time1);
-            (l, r, false)
-        }
+    let (half, axis) = partition(bboxes, order);
+
+    // Reserve the node and fill it in once the second child's index is known.
+    nodes.push(Node {
+        bbox: AABB::new(Point3::zero(), Point3::zero()),
+        kind: NodeKind::Leaf(start),
+    });
+
+    let (first, rest) = order.split_at_mut(half);
+    let left = split(bboxes, first, start, nodes);
+    let second = nodes.len();
+    let right = split(bboxes, rest, start + half, nodes);
+
+    let bbox = AABB::surrounding_box(left, right);
+    nodes[idx] = Node {
+        bbox,
+        kind: NodeKind::Interior { second, axis },
     };
+    bbox
+}
+
+/// Copy the subtree of a node to a new list of nodes and objects, replacing
+/// subtrees of at most `SPHERE_BATCH_SIZE` plain spheres with a leaf holding
+/// a `SphereBatch`.
+///
+/// * `nodes` - Flattened nodes.
+/// * `objects` - Objects referenced by the leaf nodes.
+/// * `idx` - Index of the node to copy.
+/// * `batched_nodes` - Copied nodes.
+/// * `batched_objects` - Objects referenced by the copied leaf nodes.
+fn batch_spheres(
+    nodes: &[Node],
+    objects: &[ArcHittable],
+    idx: usize,
+    batched_nodes: &mut Vec<Node>,
+    batched_objects: &mut Vec<ArcHittable>,
+) {
+    let node = nodes[idx];
 
-    let bbox = if leaf {
-        left.bounding_box(time0, time1)
-    } else {
-        match (
-            left.bounding_box(time0, time1),
-            right.bounding_box(time0, time1),
-        ) {
-            (Some(bbox_left), Some(bbox_right)) => {
-                Some(AABB::surrounding_box(bbox_left, bbox_right))
+    if let NodeKind::Interior { .. } = node.kind {
+        let mut leaves = Vec::with_capacity(SPHERE_BATCH_SIZE);
+        if collect_leaves(nodes, idx, &mut leaves) {
+            let spheres: Option<Vec<_>> = leaves.iter().map(|&o| objects[o].sphere()).collect();
+            if let Some(spheres) = spheres {
+                batched_nodes.push(Node {
+                    bbox: node.bbox,
+                    kind: NodeKind::Leaf(batched_objects.len()),
+                });
+                batched_objects.push(SphereBatch::new(&spheres));
+                return;
             }
-            _ => panic!("No objects in BVH::split"),
         }
+    }
+
+    match node.kind {
+        NodeKind::Leaf(object) => {
+            batched_nodes.push(Node {
+                bbox: node.bbox,
+                kind: NodeKind::Leaf(batched_objects.len()),
+            });
+            batched_objects.push(Arc::clone(&objects[object]));
+        }
+
+        NodeKind::Interior { second, axis } => {
+            // Reserve the node and fill it in once the second child's index
+            // is known.
+            let new_idx = batched_nodes.len();
+            batched_nodes.push(node);
+
+            batch_spheres(nodes, objects, idx + 1, batched_nodes, batched_objects);
+            let new_second = batched_nodes.len();
+            batch_spheres(nodes, objects, second, batched_nodes, batched_objects);
+
+            batched_nodes[new_idx].kind = NodeKind::Interior {
+                second: new_second,
+                axis,
+            };
+        }
+    }
+}
+
+/// Collect the objects of the leaves in the subtree of a node. Returns
+/// false as soon as there are more than `SPHERE_BATCH_SIZE` of them.
+///
+/// * `nodes` - Flattened nodes.
+/// * `idx` - Index of the subtree's root.
+/// * `leaves` - Indices of the objects.
+fn collect_leaves(nodes: &[Node], idx: usize, leaves: &mut Vec<usize>) -> bool {
+    match nodes[idx].kind {
+        NodeKind::Leaf(object) => {
+            leaves.push(object);
+            leaves.len() <= SPHERE_BATCH_SIZE
+        }
+        NodeKind::Interior { second, .. } => {
+            collect_leaves(nodes, idx + 1, leaves) && collect_leaves(nodes, second, leaves)
+        }
+    }
+}
+
+/// Partition objects into two groups using the surface area heuristic and
+/// return the number of objects in the first group and the axis of the split.
+///
+/// Object centroids are binned along each axis and the split between bins
+/// with the lowest cost, the sum of the surface area of each group's bounds
+/// times its number of objects, is chosen. If all centroids coincide the
+/// objects are split in half.
+///
+/// * `bboxes` - Bounding boxes of all objects.
+/// * `order` - Indices of the objects to partition. At least 2 are needed.
+fn partition(bboxes: &[AABB], order: &mut [usize]) -> (usize, Axis) {
+    let n = order.len();
+
+    let bboxes: Vec<AABB> = order.iter().map(|&i| bboxes[i]).collect();
+
+    let centroids: Vec<Point3> = bboxes.iter().map(AABB::centroid).collect();
+    let bounds = centroids
+        .iter()
+        .skip(1)
+        .fold(AABB::new(centroids[0], centroids[0]), |b, &c| {
+            AABB::surrounding_box(b, AABB::new(c, c))
+        });
+
+    // Returns the bin of a centroid along an axis.
+    let bin = |c: Point3, axis: Axis| {
+        let extent = bounds.max[axis] - bounds.min[axis];
+        let b = ((c[axis] - bounds.min[axis]) / extent * SAH_BINS as Float) as usize;
+        b.min(SAH_BINS - 1)
     };
 
-    Arc::new(BVH {
-        left,
-        right,
-        bbox,
-        leaf,
-    })
+    // Find the axis and the last bin of the first group with the lowest cost.
+    let mut best: Option<(Float, Axis, usize)> = None;
+    for &axis in AXES {
+        if bounds.max[axis] <= bounds.min[axis] {
+            continue;
+        }
+
+        let mut counts = [0_usize; SAH_BINS];
+        let mut bin_bboxes: [Option<AABB>; SAH_BINS] = [None; SAH_BINS];
+        for (bbox, &c) in bboxes.iter().zip(centroids.iter()) {
+            let b = bin(c, axis);
+            counts[b] += 1;
+            bin_bboxes[b] = Some(surround(bin_bboxes[b], *bbox));
+        }
+
+        // Sweep from the right to get the cost of the second group for each split.
+        let mut right_costs = [0.0; SAH_BINS];
+        let (mut count, mut bbox) = (0, None);
+        for b in (1..SAH_BINS).rev() {
+            count += counts[b];
+            if let Some(bin_bbox) = bin_bboxes[b] {
+                bbox = Some(surround(bbox, bin_bbox));
+            }
+            right_costs[b - 1] = match bbox {
+                Some(bbox) if count > 0 => count as Float * bbox.surface_area(),
+                _ => INFINITY,
+            };
+        }
+
+        // Sweep from the left and add the cost of the first group.
+        let (mut count, mut bbox) = (0, None);
+        for b in 0..SAH_BINS - 1 {
+            count += counts[b];
+            if let Some(bin_bbox) = bin_bboxes[b] {
+                bbox = Some(surround(bbox, bin_bbox));
+            }
+            if let Some(bbox) = bbox {
+                let cost = count as Float * bbox.surface_area() + right_costs[b];
+                if count > 0 && cost < best.map_or(INFINITY, |(c, _, _)| c) {
+                    best = Some((cost, axis, b));
+                }
+            }
+        }
+    }
+
+    match best {
+        Some((_, axis, last)) => {
+            let (first, second): (Vec<_>, Vec<_>) = order
+                .iter()
+                .zip(centroids.iter())
+                .partition(|(_, &c)| bin(c, axis) <= last);
+
+            let half = first.len();
+            let ordered: Vec<usize> = first.into_iter().chain(second).map(|(&i, _)| i).collect();
+            order.copy_from_slice(&ordered);
+            (half, axis)
+        }
+        None => (n / 2, X_AXIS),
+    }
+}
+
+/// Returns a bounding box that surrounds an optional box and another box.
+///
+/// * `a` - Optional first box.
+/// * `b` - Second box.
+fn surround(a: Option<AABB>, b: AABB) -> AABB {
+    match a {
+        Some(a) => AABB::surrounding_box(a, b),
+        None => b,
+    }
+}
+
+/// Returns true if a description (without its objects) is an untransformed
+/// group built from a BVH.
+///
+/// * `t` - The description.
+fn is_bvh_group(t: &Table) -> bool {
+    t.len() == 2
+        && t.get("type").and_then(Value::as_str) == Some("group")
+        && t.get("bvh").and_then(Value::as_bool) == Some(true)
 }
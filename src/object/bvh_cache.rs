@@ -0,0 +1,291 @@
+//! # BvhCache
+//!
+//! A library for caching built bounding volume hierarchies on disk. A
+//! hierarchy only depends on the bounding boxes of its objects, so they are
+//! hashed to name the cache file and a later render of the same scene loads
+//! the nodes and object order instead of building them again. The objects
+//! themselves, e.g. the triangles of meshes, are still loaded from the scene.
+//!
+//! The key is an FNV-1a hash so it stays the same across Rust releases and
+//! processes, unlike the standard library's hasher.
+
+use super::bvh::{Node, NodeKind};
+use super::{Float, Messages, Point3, AABB, AXES};
+use std::fs;
+use std::hash::Hasher;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+/// Identifies cache files and the version of their format.
+const MAGIC: &[u8; 8] = b"RTBVH001";
+
+/// Hierarchies with fewer objects build quickly and are not cached.
+const MIN_OBJECTS: usize = 1024;
+
+/// FNV-1a offset basis.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a prime.
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a hasher.
+struct Fnv1a(u64);
+
+impl Hasher for Fnv1a {
+    /// Returns the hash.
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    /// Hash bytes.
+    ///
+    /// * `bytes` - The bytes.
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes.iter() {
+            self.0 = (self.0 ^ *b as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Hash an unsigned 64-bit integer in little-endian byte order so keys
+    /// are the same on all platforms.
+    ///
+    /// * `i` - The integer.
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+}
+
+/// Loads and saves bounding volume hierarchies in a cache directory.
+#[derive(Debug, Clone)]
+pub struct BvhCache {
+    /// Directory of the cache files.
+    dir: PathBuf,
+
+    /// Receives messages about loaded and saved hierarchies.
+    messages: Messages,
+}
+
+impl BvhCache {
+    /// Create a new cache.
+    ///
+    /// * `dir` - Directory of the cache files.
+    pub fn new(dir: PathBuf) -> BvhCache {
+        BvhCache {
+            dir,
+            messages: Messages::default(),
+        }
+    }
+
+    /// Report messages about loaded and saved hierarchies to the given
+    /// messages.
+    ///
+    /// * `messages` - Receives the messages.
+    pub fn with_messages(self, messages: Messages) -> BvhCache {
+        BvhCache { messages, ..self }
+    }
+
+    /// Returns the cache key of a hierarchy.
+    ///
+    /// * `bboxes` - Bounding boxes of the objects in their original order.
+    /// * `time0` - Start time of motion.
+    /// * `time1` - End time of motion.
+    pub fn key(bboxes: &[AABB], time0: Float, time1: Float) -> u64 {
+        let mut hasher = Fnv1a(FNV_OFFSET);
+        hasher.write_u64(bboxes.len() as u64);
+        hasher.write_u64(time0.to_bits());
+        hasher.write_u64(time1.to_bits());
+        for bbox in bboxes.iter() {
+            for p in [bbox.min, bbox.max] {
+                for &axis in AXES {
+                    hasher.write_u64(p[axis].to_bits());
+                }
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Returns the nodes and object order of a cached hierarchy or `None`
+    /// if it isn't cached or the cache file is invalid.
+    ///
+    /// * `key` - Cache key.
+    /// * `n` - Number of objects.
+    pub fn load(&self, key: u64, n: usize) -> Option<(Vec<Node>, Vec<usize>)> {
+        let path = self.path(key, n)?;
+        let file = fs::File::open(&path).ok()?;
+
+        match read(&mut BufReader::new(file), key, n) {
+            Ok(cached) => {
+                self.messages
+                    .push(format!("Loaded BVH from {}", path.display()));
+                Some(cached)
+            }
+            Err(err) => {
+                self.messages
+                    .push(format!("Ignoring BVH cache {}: {}", path.display(), err));
+                None
+            }
+        }
+    }
+
+    /// Save the nodes and object order of a hierarchy. Errors are reported
+    /// but otherwise ignored since the cache is only an optimisation.
+    ///
+    /// * `key` - Cache key.
+    /// * `nodes` - Flattened nodes.
+    /// * `order` - Original indices of the re-ordered objects.
+    pub fn save(&self, key: u64, nodes: &[Node], order: &[usize]) {
+        let path = match self.path(key, order.len()) {
+            Some(path) => path,
+            None => return,
+        };
+
+        // Write to a temporary file first so concurrent renders never see a
+        // partially written cache file.
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::File::create(&tmp))
+            .and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                write(&mut writer, key, nodes, order)?;
+                writer.flush()
+            })
+            .and_then(|_| fs::rename(&tmp, &path));
+
+        match result {
+            Ok(_) => self
+                .messages
+                .push(format!("Saved BVH to {}", path.display())),
+            Err(err) => {
+                let _ = fs::remove_file(&tmp);
+                self.messages
+                    .push(format!("Unable to save BVH to {}: {}", path.display(), err));
+            }
+        }
+    }
+
+    /// Returns the path of a cache file or `None` if the hierarchy isn't
+    /// cached.
+    ///
+    /// * `key` - Cache key.
+    /// * `n` - Number of objects.
+    fn path(&self, key: u64, n: usize) -> Option<PathBuf> {
+        if n < MIN_OBJECTS {
+            return None;
+        }
+        Some(self.dir.join(format!("{:016x}.bvh", key)))
+    }
+}
+
+/// Write a hierarchy in little-endian binary format.
+///
+/// * `w` - Writer.
+/// * `key` - Cache key.
+/// * `nodes` - Flattened nodes.
+/// * `order` - Original indices of the re-ordered objects.
+fn write<W: Write>(w: &mut W, key: u64, nodes: &[Node], order: &[usize]) -> io::Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&key.to_le_bytes())?;
+    w.write_all(&(order.len() as u64).to_le_bytes())?;
+    w.write_all(&(nodes.len() as u64).to_le_bytes())?;
+
+    for node in nodes.iter() {
+        for p in [node.bbox.min, node.bbox.max] {
+            for &axis in AXES {
+                w.write_all(&p[axis].to_le_bytes())?;
+            }
+        }
+
+        let (tag, a, b) = match node.kind {
+            NodeKind::Leaf(object) => (0_u8, object, 0),
+            NodeKind::Interior { second, axis } => (1_u8, second, axis),
+        };
+        w.write_all(&[tag])?;
+        w.write_all(&(a as u64).to_le_bytes())?;
+        w.write_all(&(b as u64).to_le_bytes())?;
+    }
+
+    for &i in order.iter() {
+        w.write_all(&(i as u64).to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Read a hierarchy and check that it is consistent with the objects so a
+/// corrupt file can't cause out of bounds accesses or endless traversals.
+///
+/// * `r` - Reader.
+/// * `key` - Expected cache key.
+/// * `n` - Expected number of objects.
+fn read<R: Read>(r: &mut R, key: u64, n: usize) -> Result<(Vec<Node>, Vec<usize>), String> {
+    let mut magic = [0_u8; 8];
+    r.read_exact(&mut magic).map_err(|e| e.to_string())?;
+    if &magic != MAGIC {
+        return Err("unknown format".to_string());
+    }
+    if read_u64(r)? != key {
+        return Err("key mismatch".to_string());
+    }
+    if read_u64(r)? != n as u64 {
+        return Err("object count mismatch".to_string());
+    }
+
+    let count = read_u64(r)? as usize;
+    if count == 0 || count >= 2 * n {
+        return Err(format!("invalid node count {}", count));
+    }
+
+    let mut nodes = Vec::with_capacity(count);
+    for idx in 0..count {
+        let min = Point3::new(read_f64(r)?, read_f64(r)?, read_f64(r)?);
+        let max = Point3::new(read_f64(r)?, read_f64(r)?, read_f64(r)?);
+
+        let mut tag = [0_u8; 1];
+        r.read_exact(&mut tag).map_err(|e| e.to_string())?;
+        let a = read_u64(r)? as usize;
+        let b = read_u64(r)? as usize;
+
+        // Children always follow their parent so traversal terminates.
+        let kind = match tag[0] {
+            0 if a < n => NodeKind::Leaf(a),
+            1 if idx + 1 < a && a < count && b < 3 => NodeKind::Interior { second: a, axis: b },
+            _ => return Err(format!("invalid node {}", idx)),
+        };
+
+        nodes.push(Node {
+            bbox: AABB::new(min, max),
+            kind,
+        });
+    }
+
+    let mut seen = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    for _ in 0..n {
+        let i = read_u64(r)? as usize;
+        if i >= n || seen[i] {
+            return Err("invalid object order".to_string());
+        }
+        seen[i] = true;
+        order.push(i);
+    }
+
+    Ok((nodes, order))
+}
+
+/// Read a little-endian unsigned 64-bit integer.
+///
+/// * `r` - Reader.
+fn read_u64<R: Read>(r: &mut R) -> Result<u64, String> {
+    let mut buf = [0_u8; 8];
+    r.read_exact(&mut buf).map_err(|e| e.to_string())?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Read a little-endian 64-bit float.
+///
+/// * `r` - Reader.
+fn read_f64<R: Read>(r: &mut R) -> Result<Float, String> {
+    read_u64(r).map(Float::from_bits)
+}
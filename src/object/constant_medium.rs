@@ -4,8 +4,8 @@
 //! effects like smoke and fog.
 
 use super::{
-    ArcHittable, ArcMaterial, ArcTexture, Float, HitRecord, Hittable, Isotropic, Random, Ray, Vec3,
-    AABB, INFINITY, MIN_THICKNESS,
+    ArcHittable, ArcMaterial, ArcTexture, Float, HitRecord, Hittable, Isotropic, Random, Ray,
+    Scratch, Table, Vec3, AABB, INFINITY, MIN_THICKNESS,
 };
 use std::fmt;
 use std::sync::Arc;
@@ -39,8 +39,8 @@ impl fmt::Display for ConstantMedium {
 impl ConstantMedium {
     /// Create a new constant medium.
     ///
-    /// * `boundary` - Object determines surface boundary (for now only
-    ///   convex objects work)
+    /// * `boundary` - Object determines surface boundary. It should be a
+    ///   closed surface but need not be convex.
     /// * `density` - Density of medium.
     /// * `albedo` - Provides diffuse colour.
     pub fn new(boundary: ArcHittable, density: Float, albedo: ArcTexture) -> ArcHittable {
@@ -52,6 +52,27 @@ impl ConstantMedium {
     }
 }
 
+/// Collect the intervals along a ray that lie inside the boundary of a
+/// medium. Successive pairs of boundary crossings bound a segment inside the
+/// medium. This also handles non-convex boundaries that the ray enters and
+/// exits multiple times.
+///
+/// * `boundary` - Boundary of the medium.
+/// * `ray` - The ray.
+/// * `intervals` - Receives the `(t_enter, t_exit)` intervals.
+pub fn boundary_intervals(boundary: &ArcHittable, ray: &Ray, intervals: &mut Vec<(Float, Float)>) {
+    let mut t = -INFINITY;
+    while let Some(enter) = boundary.hit(ray, t, INFINITY) {
+        match boundary.hit(ray, enter.t + MIN_THICKNESS, INFINITY) {
+            Some(exit) => {
+                intervals.push((enter.t, exit.t));
+                t = exit.t + MIN_THICKNESS;
+            }
+            None => break,
+        }
+    }
+}
+
 impl Hittable for ConstantMedium {
     /// Calculate the intersection of a ray with the objects.
     ///
@@ -63,63 +84,58 @@ impl Hittable for ConstantMedium {
         let enable_debug = false;
         let debugging = enable_debug && Random::sample::<Float>() < 0.00001;
 
-        let rec1 = self.boundary.hit(ray, -INFINITY, INFINITY);
-        if rec1.is_none() {
-            return None;
-        }
-        let mut t0 = rec1.unwrap().t;
-
-        let rec2 = self.boundary.hit(ray, t0 + MIN_THICKNESS, INFINITY);
-        if rec2.is_none() {
-            return None;
-        }
-        let mut t1 = rec2.unwrap().t;
-
-        if debugging {
-            eprintln!("\nt0={}, t1={}", t0, t1);
-        }
-
-        if t0 < t_min {
-            t0 = t_min;
-        }
-        if t1 > t_max {
-            t1 = t_max;
-        }
-
-        if t0 >= t1 {
-            return None;
-        }
-
-        if t0 < 0.0 {
-            t0 = 0.0;
-        }
-
-        let ray_length = ray.direction.length();
-        let distance_inside_boundary = (t1 - t0) * ray_length;
-        let hit_distance = self.neg_inv_density * Random::sample::<Float>().ln();
-
-        if hit_distance > distance_inside_boundary {
-            return None;
-        }
-
-        let t = t0 + hit_distance / ray_length;
-
-        let rec = HitRecord::new(
-            ray,
-            t,
-            ray.at(t),
-            Vec3::new(1.0, 0.0, 0.0), // arbitrary normal
-            Arc::clone(&self.phase_function),
-            0.0, // arbitrary
-            1.0, // arbitrary
-        );
-
-        if debugging {
-            eprintln!("hit_distance = {}", hit_distance);
-            eprintln!("rec.t = {}", rec.t);
-            eprintln!("rec.p = {}", rec.point);
-        }
-        Some(rec)
+        Scratch::with(|scratch| {
+            let intervals = &mut scratch.intervals;
+            boundary_intervals(&self.boundary, ray, intervals);
+
+            if debugging {
+                for (t0, t1) in intervals.iter() {
+                    eprintln!("\nt0={}, t1={}", t0, t1);
+                }
+            }
+
+            let ray_length = ray.direction.length();
+            let mut hit_distance = self.neg_inv_density * Random::sample::<Float>().ln();
+
+            for &(t0, t1) in intervals.iter() {
+                let t0 = t0.max(t_min).max(0.0);
+                let t1 = t1.min(t_max);
+
+                if t0 >= t1 {
+                    continue;
+                }
+
+                let distance_inside_boundary = (t1 - t0) * ray_length;
+                if hit_distance > distance_inside_boundary {
+                    // Ray passes through this segment; continue with the
+                    // remaining distance in the next segment.
+                    hit_distance -= distance_inside_boundary;
+                    continue;
+                }
+
+                let t = t0 + hit_distance / ray_length;
+
+                let rec = HitRecord::new(
+                    ray,
+                    t,
+                    ray.at(t),
+                    Vec3::new(1.0, 0.0, 0.0), // arbitrary normal
+                    Arc::clone(&self.phase_function),
+                    0.0, // arbitrary
+                    1.0, // arbitrary
+                );
+
+                if debugging {
+                    eprintln!("hit_distance = {}", hit_distance);
+                    eprintln!("rec.t = {}", rec.t);
+                    eprintln!("rec.p = {}", rec.point);
+                }
+
+                return Some(rec);
+            }
+
+            None
+        })
     }
 
     /// Create a bounding box across time interval `[t0, t1]`.
@@ -129,4 +145,18 @@ impl Hittable for ConstantMedium {
     fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
         self.boundary.bounding_box(time0, time1)
     }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "constant_medium".into());
+        t.insert("boundary".into(), self.boundary.describe().into());
+        t.insert("density".into(), (-1.0 / self.neg_inv_density).into());
+
+        // The phase function is an isotropic material built from the albedo.
+        if let Some(albedo) = self.phase_function.describe().remove("albedo") {
+            t.insert("albedo".into(), albedo);
+        }
+        t
+    }
 }
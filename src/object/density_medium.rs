@@ -0,0 +1,283 @@
+//! # DensityMedium
+//!
+//! A library for handling ray intersections within a medium whose density
+//! varies, e.g. clouds and non-uniform smoke. The density is sampled from a
+//! 3D grid or a texture and scattering distances are found with delta
+//! tracking: the medium is treated as a constant medium at its maximum
+//! density and tentative collisions are kept with the probability of the
+//! local density relative to the maximum. This is unbiased for any density
+//! below the maximum.
+
+use super::{
+    boundary_intervals, ArcHittable, ArcMaterial, ArcTexture, Float, HitRecord, Hittable,
+    Isotropic, Point3, Random, Ray, Scratch, Table, Value, Vec3, AABB,
+};
+use std::fmt;
+use std::sync::Arc;
+
+/// Models a 3D grid of densities that spans the bounding box of a medium.
+/// Densities are stored at the centres of the cells and interpolated
+/// trilinearly.
+#[derive(Debug, Clone)]
+pub struct DensityGrid {
+    /// Number of cells along the x, y and z axes.
+    size: [usize; 3],
+
+    /// Densities with x varying fastest, then y, then z.
+    values: Vec<Float>,
+}
+
+impl DensityGrid {
+    /// Create a new density grid. Returns `None` if the number of values
+    /// doesn't match the size, the size overflows or any value is negative.
+    ///
+    /// * `size` - Number of cells along the x, y and z axes.
+    /// * `values` - Densities with x varying fastest, then y, then z.
+    pub fn new(size: [usize; 3], values: Vec<Float>) -> Option<DensityGrid> {
+        let count = size[0].checked_mul(size[1])?.checked_mul(size[2])?;
+        if count == 0 || values.len() != count || values.iter().any(|v| v.is_nan() || *v < 0.0) {
+            return None;
+        }
+
+        Some(DensityGrid { size, values })
+    }
+
+    /// Returns the largest density in the grid.
+    pub fn max(&self) -> Float {
+        self.values.iter().fold(0.0, |m, v| m.max(*v))
+    }
+
+    /// Returns the interpolated density at a position within the grid.
+    ///
+    /// * `p` - Position in `[0, 1]` along each axis.
+    pub fn value(&self, p: Vec3) -> Float {
+        // Cell coordinates of the sample, the lower neighbour and the weight
+        // of the upper neighbour along each axis.
+        let mut lo = [0; 3];
+        let mut hi = [0; 3];
+        let mut w = [0.0; 3];
+        for a in 0..3 {
+            let n = self.size[a];
+            let x = (p[a] * n as Float - 0.5).clamp(0.0, (n - 1) as Float);
+            lo[a] = x.floor() as usize;
+            hi[a] = (lo[a] + 1).min(n - 1);
+            w[a] = x - lo[a] as Float;
+        }
+
+        let at =
+            |x: usize, y: usize, z: usize| self.values[x + self.size[0] * (y + self.size[1] * z)];
+
+        let mut density = 0.0;
+        for (k, z) in [(1.0 - w[2], lo[2]), (w[2], hi[2])] {
+            for (j, y) in [(1.0 - w[1], lo[1]), (w[1], hi[1])] {
+                for (i, x) in [(1.0 - w[0], lo[0]), (w[0], hi[0])] {
+                    density += i * j * k * at(x, y, z);
+                }
+            }
+        }
+        density
+    }
+
+    /// Returns a description of the grid in the scene file format.
+    pub fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert(
+            "size".into(),
+            Value::Array(self.size.iter().map(|n| (*n as i64).into()).collect()),
+        );
+        t.insert(
+            "values".into(),
+            Value::Array(self.values.iter().map(|v| (*v).into()).collect()),
+        );
+        t
+    }
+}
+
+/// Source of the density of a medium relative to its maximum density.
+#[derive(Debug, Clone)]
+pub enum DensityField {
+    /// A grid spanning the bounding box of the boundary. The grid is
+    /// normalised by its largest value.
+    Grid(DensityGrid),
+
+    /// The average of the texture's colour channels clamped to `[0, 1]`,
+    /// e.g. a noise texture.
+    Texture(ArcTexture),
+}
+
+impl DensityField {
+    /// Returns a description of the density field in the scene file format.
+    fn describe(&self) -> (&'static str, Value) {
+        match self {
+            DensityField::Grid(grid) => ("grid", Value::Table(grid.describe())),
+            DensityField::Texture(texture) => ("texture", texture.describe()),
+        }
+    }
+}
+
+/// Models a medium with varying density for effects like clouds and smoke.
+#[derive(Debug, Clone)]
+pub struct DensityMedium {
+    /// Boundary
+    boundary: ArcHittable,
+
+    /// Bounding box of the boundary the density grid spans.
+    bbox: AABB,
+
+    /// Relative density.
+    field: DensityField,
+
+    /// Maximum density.
+    density: Float,
+
+    /// Scale applied to grid values to get a relative density.
+    grid_scale: Float,
+
+    /// Phase function (this will be an isotropic material).
+    phase_function: ArcMaterial,
+}
+
+impl fmt::Display for DensityMedium {
+    /// Display the density medium parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "density_medium(boundary: {}, field: {:?}, density: {}, phase_function: {})",
+            self.boundary, self.field, self.density, self.phase_function
+        )
+    }
+}
+
+impl DensityMedium {
+    /// Create a new medium with varying density.
+    ///
+    /// Notes:
+    /// * This function will panic if the boundary doesn't have a bounding
+    ///   box.
+    ///
+    /// * `boundary` - Object determines surface boundary. It should be a
+    ///   closed surface but need not be convex.
+    /// * `field` - Density relative to the maximum density.
+    /// * `density` - Maximum density of the medium.
+    /// * `albedo` - Provides diffuse colour.
+    pub fn new(
+        boundary: ArcHittable,
+        field: DensityField,
+        density: Float,
+        albedo: ArcTexture,
+    ) -> ArcHittable {
+        // Motion is not supported. So (0.0, 1.0) ok.
+        let bbox = boundary
+            .bounding_box(0.0, 1.0)
+            .expect("Missing bounding box for density medium");
+
+        let grid_scale = match &field {
+            DensityField::Grid(grid) if grid.max() > 0.0 => 1.0 / grid.max(),
+            _ => 0.0,
+        };
+
+        Arc::new(DensityMedium {
+            boundary,
+            bbox,
+            field,
+            density,
+            grid_scale,
+            phase_function: Isotropic::new(albedo),
+        })
+    }
+
+    /// Returns the density at a point relative to the maximum density.
+    ///
+    /// * `p` - The point.
+    fn relative_density(&self, p: Point3) -> Float {
+        match &self.field {
+            DensityField::Grid(grid) => {
+                let extent = self.bbox.max - self.bbox.min;
+                let q = p - self.bbox.min;
+                let local = Vec3::new(q.x() / extent.x(), q.y() / extent.y(), q.z() / extent.z());
+                grid.value(local) * self.grid_scale
+            }
+
+            DensityField::Texture(texture) => {
+                let c = texture.value(0.0, 0.0, &p);
+                ((c.x() + c.y() + c.z()) / 3.0).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+impl Hittable for DensityMedium {
+    /// Calculate the intersection of a ray with the medium.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `t_min` - The minium parameter for intersections.
+    /// * `t_max` - The maximum parameter for intersections.
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        if self.density.is_nan() || self.density <= 0.0 {
+            return None;
+        }
+
+        Scratch::with(|scratch| {
+            let intervals = &mut scratch.intervals;
+            boundary_intervals(&self.boundary, ray, intervals);
+
+            let ray_length = ray.direction.length();
+
+            for &(t0, t1) in intervals.iter() {
+                let t1 = t1.min(t_max);
+                let mut t = t0.max(t_min).max(0.0);
+
+                // Step between tentative collisions at the maximum density
+                // and keep each with the probability of the local density.
+                loop {
+                    t -= (1.0 - Random::sample::<Float>()).ln() / (self.density * ray_length);
+                    if t >= t1 {
+                        break;
+                    }
+
+                    let p = ray.at(t);
+                    if Random::sample::<Float>() < self.relative_density(p) {
+                        return Some(HitRecord::new(
+                            ray,
+                            t,
+                            p,
+                            Vec3::new(1.0, 0.0, 0.0), // arbitrary normal
+                            Arc::clone(&self.phase_function),
+                            0.0, // arbitrary
+                            1.0, // arbitrary
+                        ));
+                    }
+                }
+            }
+
+            None
+        })
+    }
+
+    /// Create a bounding box across time interval `[t0, t1]`.
+    ///
+    /// * `time0` - Start time of motion.
+    /// * `time1` - End time of motion.
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
+        self.boundary.bounding_box(time0, time1)
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "density_medium".into());
+        t.insert("boundary".into(), self.boundary.describe().into());
+        t.insert("density".into(), self.density.into());
+
+        let (key, field) = self.field.describe();
+        t.insert(key.into(), field);
+
+        // The phase function is an isotropic material built from the albedo.
+        if let Some(albedo) = self.phase_function.describe().remove("albedo") {
+            t.insert("albedo".into(), albedo);
+        }
+        t
+    }
+}
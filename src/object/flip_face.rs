@@ -2,7 +2,10 @@
 //!
 //! A library for flipping surface normals on an object.
 
-use super::{ArcHittable, Float, HitRecord, Hittable, Point3, Ray, Vec3, AABB};
+use super::{
+    describe_transform, wrap_emissive_leaves, ArcHittable, Float, HitRecord, Hittable, Point3, Ray,
+    Table, Vec3, AABB,
+};
 use std::fmt;
 use std::sync::Arc;
 
@@ -52,6 +55,17 @@ impl Hittable for FlipFace {
         self.object.bounding_box(time0, time1)
     }
 
+    /// Returns true if the object emits light.
+    fn is_emissive(&self) -> bool {
+        self.object.is_emissive()
+    }
+
+    /// Returns the emissive parts of the object, each with its faces
+    /// flipped.
+    fn emissive_parts(&self) -> Option<Vec<ArcHittable>> {
+        wrap_emissive_leaves(&self.object, FlipFace::new)
+    }
+
     /// Sample PDF value at hit point and given direction.
     ///
     /// * `origin` - Hit point.
@@ -66,4 +80,21 @@ impl Hittable for FlipFace {
     fn random(&self, origin: Point3) -> Vec3 {
         self.object.random(origin)
     }
+
+    /// Returns the surface area sampled by `sample_area()`.
+    fn area(&self) -> Float {
+        self.object.area()
+    }
+
+    /// Returns a point picked uniformly over the surface of the object and
+    /// the outward normal there.
+    fn sample_area(&self) -> Option<(Point3, Vec3)> {
+        let (p, normal) = self.object.sample_area()?;
+        Some((p, -normal))
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        describe_transform(&self.object, "flip_face", true.into())
+    }
 }
@@ -2,7 +2,7 @@
 //!
 //! A library for handling surface intersection details.
 
-use super::{ArcMaterial, Float, Point3, Ray, Vec3};
+use super::{ArcMaterial, Float, Point3, Ray, Vec3, RAY_EPSILON_SCALE};
 use std::fmt;
 use std::sync::Arc;
 
@@ -15,9 +15,13 @@ pub struct HitRecord {
     /// Intersection point.
     pub point: Point3,
 
-    /// Surface normal.
+    /// Shading normal, e.g. interpolated from vertex normals. It faces the
+    /// same side of the surface as `geometric_normal`.
     pub normal: Vec3,
 
+    /// Normal of the actual surface facing the incident ray.
+    pub geometric_normal: Vec3,
+
     /// Determines if incident ray is inside `false` or outside `true`.
     pub front_face: bool,
 
@@ -29,6 +33,19 @@ pub struct HitRecord {
 
     /// The 2-D parametric v-coordinate of the surface.
     pub v: Float,
+
+    /// Velocity of the surface at the intersection point. This is zero for
+    /// static objects.
+    pub velocity: Vec3,
+
+    /// Light linking ID of the top-level object that was hit. This is zero
+    /// unless the scene uses light linking.
+    pub link_id: u32,
+
+    /// Roughness glossy materials are clamped to at this bounce of the path.
+    /// This is set by the renderer and zero unless the scene clamps
+    /// roughness by bounce depth.
+    pub min_roughness: Float,
 }
 
 impl HitRecord {
@@ -63,9 +80,13 @@ impl HitRecord {
             point,
             front_face,
             normal,
+            geometric_normal: normal,
             material: Arc::clone(&material),
             u,
             v,
+            velocity: Vec3::zero(),
+            link_id: 0,
+            min_roughness: 0.0,
         }
     }
 
@@ -76,20 +97,33 @@ impl HitRecord {
             point: p,
             front_face: self.front_face,
             normal: self.normal,
+            geometric_normal: self.geometric_normal,
             material: Arc::clone(&self.material),
             u: self.u,
             v: self.v,
+            velocity: self.velocity,
+            link_id: self.link_id,
+            min_roughness: self.min_roughness,
         }
     }
 
-    /// Returns a copy with the normal changed
-    pub fn update_normal(&self, ray: &Ray, outward_normal: Vec3) -> HitRecord {
-        let front_face = ray.direction.dot(outward_normal) < 0.0;
+    /// Returns a copy with the shading and geometric normals changed
+    ///
+    /// * `ray` - The incident ray.
+    /// * `outward_normal` - Outward shading normal.
+    /// * `outward_geometric_normal` - Outward geometric normal.
+    pub fn update_normal(
+        &self,
+        ray: &Ray,
+        outward_normal: Vec3,
+        outward_geometric_normal: Vec3,
+    ) -> HitRecord {
+        let front_face = ray.direction.dot(outward_geometric_normal) < 0.0;
 
-        let normal = if front_face {
-            outward_normal
+        let (normal, geometric_normal) = if front_face {
+            (outward_normal, outward_geometric_normal)
         } else {
-            -outward_normal
+            (-outward_normal, -outward_geometric_normal)
         };
 
         HitRecord {
@@ -97,9 +131,64 @@ impl HitRecord {
             point: self.point,
             front_face,
             normal,
+            geometric_normal,
             material: Arc::clone(&self.material),
             u: self.u,
             v: self.v,
+            velocity: self.velocity,
+            link_id: self.link_id,
+            min_roughness: self.min_roughness,
+        }
+    }
+
+    /// Returns a copy with the shading normal changed. The shading normal is
+    /// flipped if needed to face the same side as the geometric normal.
+    ///
+    /// * `outward_normal` - Outward shading normal.
+    pub fn update_shading_normal(&self, outward_normal: Vec3) -> HitRecord {
+        let normal = if outward_normal.dot(self.geometric_normal) < 0.0 {
+            -outward_normal
+        } else {
+            outward_normal
+        };
+
+        HitRecord {
+            normal,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the origin of a ray leaving the surface in a direction. The
+    /// point is offset along the geometric normal to the side the ray leaves
+    /// from so it can't hit the surface it starts on. Offsetting along the
+    /// shading normal instead leaks light through surfaces with interpolated
+    /// normals.
+    ///
+    /// * `direction` - Direction of the ray leaving the surface.
+    pub fn offset_origin(&self, direction: Vec3) -> Point3 {
+        let scale = (0..3).fold(1.0, |m: Float, a| m.max(self.point[a].abs()));
+        let offset = self.geometric_normal * (scale * RAY_EPSILON_SCALE);
+
+        if direction.dot(self.geometric_normal) < 0.0 {
+            self.point - offset
+        } else {
+            self.point + offset
+        }
+    }
+
+    /// Returns a copy with the velocity changed
+    pub fn update_velocity(&self, velocity: Vec3) -> HitRecord {
+        HitRecord {
+            velocity,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy with the light linking ID changed
+    pub fn update_link_id(&self, link_id: u32) -> HitRecord {
+        HitRecord {
+            link_id,
+            ..self.clone()
         }
     }
 
@@ -110,9 +199,13 @@ impl HitRecord {
             point: self.point,
             front_face: !self.front_face,
             normal: self.normal,
+            geometric_normal: self.geometric_normal,
             material: Arc::clone(&self.material),
             u: self.u,
             v: self.v,
+            velocity: self.velocity,
+            link_id: self.link_id,
+            min_roughness: self.min_roughness,
         }
     }
 }
@@ -124,9 +217,18 @@ impl fmt::Display for HitRecord {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "hit_record(t: {}, point: {}, normal: {}, \
-            front_face: {}, material: {}, u: {}, v: {})",
-            self.t, self.point, self.normal, self.front_face, self.material, self.u, self.v,
+            "hit_record(t: {}, point: {}, normal: {}, geometric_normal: {}, \
+            front_face: {}, material: {}, u: {}, v: {}, velocity: {}, link_id: {})",
+            self.t,
+            self.point,
+            self.normal,
+            self.geometric_normal,
+            self.front_face,
+            self.material,
+            self.u,
+            self.v,
+            self.velocity,
+            self.link_id,
         )
     }
 }
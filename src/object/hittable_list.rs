@@ -3,7 +3,10 @@
 //! A library for handling ray intersections with a collection of
 //! geometric objects.
 
-use super::{ArcHittable, Float, HitRecord, Hittable, Point3, Random, Ray, Vec3, AABB};
+use super::{
+    describe_group, emissive_leaves, ArcHittable, Float, HitRecord, Hittable, Point3, Ray, Sampler,
+    Table, Vec3, AABB,
+};
 use std::fmt;
 use std::sync::Arc;
 
@@ -57,19 +60,27 @@ impl Hittable for HittableList {
                     t,
                     point,
                     normal,
+                    geometric_normal,
                     front_face,
                     material,
                     u,
                     v,
+                    velocity,
+                    link_id,
+                    min_roughness,
                 }) => (
                     Some(HitRecord {
                         t,
                         point,
                         normal,
+                        geometric_normal,
                         front_face,
                         material: Arc::clone(&material),
                         u,
                         v,
+                        velocity,
+                        link_id,
+                        min_roughness,
                     }),
                     t,
                 ),
@@ -105,6 +116,17 @@ impl Hittable for HittableList {
         }
     }
 
+    /// Returns true if all of the objects emit light so the list can be
+    /// sampled as a whole.
+    fn is_emissive(&self) -> bool {
+        !self.objects.is_empty() && self.objects.iter().all(|o| o.is_emissive())
+    }
+
+    /// Returns the emissive objects of the list and nested groups.
+    fn emissive_parts(&self) -> Option<Vec<ArcHittable>> {
+        Some(self.objects.iter().flat_map(emissive_leaves).collect())
+    }
+
     /// Sample PDF value at hit point and given direction.
     ///
     /// * `origin` - Hit point.
@@ -127,7 +149,38 @@ impl Hittable for HittableList {
         } else if size == 1 {
             self.objects[0].random(origin)
         } else {
-            self.objects[Random::sample_in_range(0, size - 1)].random(origin)
+            let idx = (Sampler::get_1d() * size as Float) as usize;
+            self.objects[idx.min(size - 1)].random(origin)
         }
     }
+
+    /// Returns the surface area sampled by `sample_area()`.
+    fn area(&self) -> Float {
+        self.objects.iter().map(|o| o.area()).sum()
+    }
+
+    /// Returns a point picked uniformly over the surface of the object and
+    /// the outward normal there.
+    fn sample_area(&self) -> Option<(Point3, Vec3)> {
+        // Pick an object in proportion to its area.
+        let mut target = Sampler::get_1d() * self.area();
+        let mut picked = None;
+        for o in self.objects.iter() {
+            let area = o.area();
+            if area <= 0.0 {
+                continue;
+            }
+            picked = Some(o);
+            if target < area {
+                break;
+            }
+            target -= area;
+        }
+        picked?.sample_area()
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        describe_group(self.objects.iter().map(|o| o.describe()).collect(), false)
+    }
 }
@@ -0,0 +1,112 @@
+//! # Instance
+//!
+//! A library for handling ray intersections with instances of shared
+//! geometry. Each instance holds a reference to the geometry and its own
+//! transformation so thousands of copies of a mesh only store the mesh and
+//! its bounding volume hierarchy once. A bounding volume hierarchy built
+//! over the instances forms the top level of a two-level hierarchy.
+
+use super::{
+    wrap_emissive_leaves, ArcHittable, Float, HitRecord, Hittable, Mat4, Point3, Ray, Table,
+    Transform, Vec3, AABB,
+};
+use std::fmt;
+use std::sync::Arc;
+
+/// Models an instance of shared geometry.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    /// The shared geometry.
+    geometry: ArcHittable,
+
+    /// Object to world transformation.
+    matrix: Mat4,
+
+    /// The geometry transformed into the world.
+    transformed: ArcHittable,
+}
+
+impl fmt::Display for Instance {
+    /// Display the instance parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "instance(geometry: {}, matrix: {})",
+            self.geometry, self.matrix
+        )
+    }
+}
+
+impl Instance {
+    /// Create a new instance.
+    ///
+    /// Notes:
+    /// * This function will panic if the matrix is singular.
+    ///
+    /// * `geometry`: The shared geometry.
+    /// * `matrix`: Object to world transformation.
+    pub fn new(geometry: ArcHittable, matrix: Mat4) -> ArcHittable {
+        Arc::new(Instance {
+            geometry: Arc::clone(&geometry),
+            matrix,
+            transformed: Transform::new(geometry, matrix),
+        })
+    }
+}
+
+impl Hittable for Instance {
+    /// Calculate the intersection of a ray with the instance.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `t_min` - The minium parameter for intersections.
+    /// * `t_max` - The maximum parameter for intersections.
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        self.transformed.hit(ray, t_min, t_max)
+    }
+
+    /// Create a bounding box across time interval `[t0, t1]`.
+    ///
+    /// * `time0` - Start time of motion.
+    /// * `time1` - End time of motion.
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
+        self.transformed.bounding_box(time0, time1)
+    }
+
+    /// Returns true if the object emits light.
+    fn is_emissive(&self) -> bool {
+        self.transformed.is_emissive()
+    }
+
+    /// Returns the emissive parts of the geometry, each with the instance's
+    /// transform.
+    fn emissive_parts(&self) -> Option<Vec<ArcHittable>> {
+        wrap_emissive_leaves(&self.transformed, |o| o)
+    }
+
+    /// Sample PDF value at hit point and given direction.
+    ///
+    /// * `origin` - Hit point.
+    /// * `v` - Direction to sample.
+    fn pdf_value(&self, origin: Point3, v: Vec3) -> Float {
+        self.transformed.pdf_value(origin, v)
+    }
+
+    /// Generate a random direction towards this instance.
+    ///
+    /// * `origin` - Hit point.
+    fn random(&self, origin: Point3) -> Vec3 {
+        self.transformed.random(origin)
+    }
+
+    /// Returns a description of the object in the scene file format. The
+    /// geometry is described inline.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "instance".into());
+        t.insert("geometry".into(), self.geometry.describe().into());
+        t.insert("matrix".into(), self.matrix.into());
+        t
+    }
+}
@@ -0,0 +1,347 @@
+//! # LightLink
+//!
+//! A library for light linking. Light linking restricts the lights that
+//! illuminate an object. Top-level objects of scenes that use light linking
+//! are tagged with a link ID so the renderer can tell which object a ray hit
+//! and which object it was scattered from.
+//!
+//! Link IDs also carry a shadow softness override. Lights with a softness
+//! other than one are replaced by scaled copies when they are sampled or hit
+//! from the objects they illuminate. A larger copy casts softer shadows and
+//! emits proportionally less per unit area so the light's power is unchanged.
+
+use super::{
+    wrap_emissive_leaves, ArcHittable, Float, HitRecord, Hittable, HittableList, Point3, Ray,
+    Table, Vec3, AABB,
+};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+/// Tags the intersections of an object with a light linking ID.
+#[derive(Debug, Clone)]
+pub struct LightLink {
+    /// The object.
+    object: ArcHittable,
+
+    /// Light linking ID of the object.
+    link_id: u32,
+
+    /// Scene file fields describing the object's name and links. These are
+    /// added to the description of the object.
+    fields: Table,
+}
+
+impl fmt::Display for LightLink {
+    /// Display the light link parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "light_link(object: {}, link_id: {})",
+            self.object, self.link_id
+        )
+    }
+}
+
+impl LightLink {
+    /// Tag an object with a light linking ID.
+    ///
+    /// * `object` - The object.
+    /// * `link_id` - Light linking ID. Must not be zero.
+    /// * `fields` - Scene file fields describing the object's name and links.
+    pub fn new(object: ArcHittable, link_id: u32, fields: Table) -> ArcHittable {
+        Arc::new(LightLink {
+            object: Arc::clone(&object),
+            link_id,
+            fields,
+        })
+    }
+}
+
+impl Hittable for LightLink {
+    /// Calculate the intersection of a ray with the object and tag it with
+    /// the link ID.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `t_min` - The minium parameter for intersections.
+    /// * `t_max` - The maximum parameter for intersections.
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        self.object
+            .hit(ray, t_min, t_max)
+            .map(|rec| rec.update_link_id(self.link_id))
+    }
+
+    /// Create a bounding box across time interval `[t0, t1]`.
+    ///
+    /// * `time0` - Start time of motion.
+    /// * `time1` - End time of motion.
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
+        self.object.bounding_box(time0, time1)
+    }
+
+    /// Returns true if the object emits light.
+    fn is_emissive(&self) -> bool {
+        self.object.is_emissive()
+    }
+
+    /// Returns the emissive parts of the object, each with the same light
+    /// link.
+    fn emissive_parts(&self) -> Option<Vec<ArcHittable>> {
+        wrap_emissive_leaves(&self.object, |o| {
+            LightLink::new(o, self.link_id, self.fields.clone())
+        })
+    }
+
+    /// Sample PDF value at hit point and given direction.
+    ///
+    /// * `origin` - Hit point.
+    /// * `v` - Direction to sample.
+    fn pdf_value(&self, origin: Point3, v: Vec3) -> Float {
+        self.object.pdf_value(origin, v)
+    }
+
+    /// Generate a random direction towards this object.
+    ///
+    /// * `origin` - Hit point.
+    fn random(&self, origin: Point3) -> Vec3 {
+        self.object.random(origin)
+    }
+
+    /// Returns the surface area sampled by `sample_area()`.
+    fn area(&self) -> Float {
+        self.object.area()
+    }
+
+    /// Returns a point picked uniformly over the surface of the object and
+    /// the outward normal there.
+    fn sample_area(&self) -> Option<(Point3, Vec3)> {
+        self.object.sample_area()
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = self.object.describe();
+        for (k, v) in self.fields.iter() {
+            t.insert(k.clone(), v.clone());
+        }
+        t
+    }
+}
+
+/// A light scaled uniformly about the centre of its bounding box.
+#[derive(Debug, Clone)]
+struct ScaledLight {
+    /// The light.
+    object: ArcHittable,
+
+    /// Centre of the scaling.
+    centre: Point3,
+
+    /// Scale factor.
+    scale: Float,
+}
+
+impl fmt::Display for ScaledLight {
+    /// Display the scaled light parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "scaled_light(object: {}, centre: {}, scale: {})",
+            self.object, self.centre, self.scale
+        )
+    }
+}
+
+impl ScaledLight {
+    /// Returns a point in the space of the unscaled light.
+    ///
+    /// * `p` - The point.
+    fn to_object(&self, p: Point3) -> Point3 {
+        self.centre + (p - self.centre) / self.scale
+    }
+}
+
+impl Hittable for ScaledLight {
+    /// Calculate the intersection of a ray with the scaled light. The ray is
+    /// scaled with the light so the ray parameter is unchanged.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `t_min` - The minium parameter for intersections.
+    /// * `t_max` - The maximum parameter for intersections.
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        let scaled_r = Ray::new(
+            self.to_object(ray.origin),
+            ray.direction / self.scale,
+            ray.time,
+        );
+        self.object.hit(&scaled_r, t_min, t_max).map(|rec| {
+            rec.update_point(self.centre + (rec.point - self.centre) * self.scale)
+                .update_normal(ray, rec.normal, rec.geometric_normal)
+        })
+    }
+
+    /// Create a bounding box across time interval `[t0, t1]`.
+    ///
+    /// * `time0` - Start time of motion.
+    /// * `time1` - End time of motion.
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
+        self.object.bounding_box(time0, time1).map(|bbox| {
+            AABB::new(
+                self.centre + (bbox.min - self.centre) * self.scale,
+                self.centre + (bbox.max - self.centre) * self.scale,
+            )
+        })
+    }
+
+    /// Sample PDF value at hit point and given direction. Uniform scaling
+    /// preserves solid angles so the unscaled light is sampled from the
+    /// scaled point.
+    ///
+    /// * `origin` - Hit point.
+    /// * `v` - Direction to sample.
+    fn pdf_value(&self, origin: Point3, v: Vec3) -> Float {
+        self.object.pdf_value(self.to_object(origin), v)
+    }
+
+    /// Generate a random direction towards this object.
+    ///
+    /// * `origin` - Hit point.
+    fn random(&self, origin: Point3) -> Vec3 {
+        self.object.random(self.to_object(origin))
+    }
+
+    /// Returns a description of the object in the scene file format. Scaled
+    /// lights are derived from the shadow softness of the scene so this is
+    /// the description of the light.
+    fn describe(&self) -> Table {
+        self.object.describe()
+    }
+}
+
+/// The lights that illuminate each object of a scene with light linking.
+#[derive(Debug, Clone)]
+pub struct LightLinks {
+    /// Lights sampled from objects that are not lit by every light, keyed by
+    /// the object's link ID, with the number of lights.
+    lights: HashMap<u32, (ArcHittable, usize)>,
+
+    /// Pairs of object and light link IDs where the light does not
+    /// illuminate the object.
+    excluded: HashSet<(u32, u32)>,
+
+    /// Shadow softness of objects and lights other than one, keyed by link ID.
+    softness: HashMap<u32, Float>,
+
+    /// Scaled copies of the lights that illuminate an object with a shadow
+    /// softness other than one, keyed by the object's link ID. Each copy has
+    /// the light's link ID and its scale.
+    scaled: HashMap<u32, Vec<(u32, ArcHittable, Float)>>,
+}
+
+impl LightLinks {
+    /// Create the light links of a scene.
+    ///
+    /// * `lights` - The lights with their link IDs.
+    /// * `objects` - Link IDs of the rendered objects.
+    /// * `excluded` - Pairs of object and light link IDs where the light does
+    ///   not illuminate the object.
+    /// * `softness` - Shadow softness of objects and lights keyed by link ID.
+    ///   The softness of a light as seen from an object is the product of
+    ///   both and scales the light's size.
+    /// * `time0` - Start time of motion.
+    /// * `time1` - End time of motion.
+    pub fn new(
+        lights: &[(u32, ArcHittable)],
+        objects: &[u32],
+        excluded: HashSet<(u32, u32)>,
+        softness: HashMap<u32, Float>,
+        time0: Float,
+        time1: Float,
+    ) -> Arc<LightLinks> {
+        let softness: HashMap<u32, Float> =
+            softness.into_iter().filter(|(_, s)| *s != 1.0).collect();
+
+        let mut links = LightLinks {
+            lights: HashMap::new(),
+            excluded,
+            softness,
+            scaled: HashMap::new(),
+        };
+
+        for o in objects.iter().copied() {
+            let mut list = HittableList::new();
+            let mut scaled = Vec::new();
+            let mut n = 0;
+
+            for (l, light) in lights.iter() {
+                if !links.illuminates(*l, o) {
+                    continue;
+                }
+                n += 1;
+
+                let scale = links.softness(*l, o);
+                match light.bounding_box(time0, time1) {
+                    Some(bbox) if scale != 1.0 => {
+                        let light: ArcHittable = Arc::new(ScaledLight {
+                            object: Arc::clone(light),
+                            centre: (bbox.min + bbox.max) * 0.5,
+                            scale,
+                        });
+                        list.add(Arc::clone(&light));
+                        scaled.push((*l, light, scale));
+                    }
+                    _ => list.add(Arc::clone(light)),
+                }
+            }
+
+            // Objects lit by every unscaled light sample the scene's lights.
+            if n < lights.len() || !scaled.is_empty() {
+                let list: ArcHittable = Arc::new(list);
+                links.lights.insert(o, (list, n));
+            }
+            if !scaled.is_empty() {
+                links.scaled.insert(o, scaled);
+            }
+        }
+
+        Arc::new(links)
+    }
+
+    /// Returns the lights that illuminate an object and their number, or
+    /// `None` if it is lit by every light.
+    ///
+    /// * `object` - Link ID of the object.
+    pub fn lights(&self, object: u32) -> Option<(&ArcHittable, usize)> {
+        self.lights.get(&object).map(|(list, n)| (list, *n))
+    }
+
+    /// Returns true if a light illuminates an object.
+    ///
+    /// * `light` - Link ID of the light.
+    /// * `object` - Link ID of the object.
+    pub fn illuminates(&self, light: u32, object: u32) -> bool {
+        !self.excluded.contains(&(object, light))
+    }
+
+    /// Returns the scale of a light's size as seen from an object.
+    ///
+    /// * `light` - Link ID of the light.
+    /// * `object` - Link ID of the object.
+    pub fn softness(&self, light: u32, object: u32) -> Float {
+        let get = |id| self.softness.get(&id).copied().unwrap_or(1.0);
+        get(light) * get(object)
+    }
+
+    /// Returns the scaled copies of the lights that illuminate an object
+    /// with their link IDs and scales. The unscaled lights should be ignored
+    /// by rays scattered from the object.
+    ///
+    /// * `object` - Link ID of the object.
+    pub fn scaled_lights(&self, object: u32) -> &[(u32, ArcHittable, Float)] {
+        self.scaled.get(&object).map_or(&[], Vec::as_slice)
+    }
+}
@@ -4,40 +4,70 @@
 
 #![allow(dead_code)]
 mod aabb;
+mod absorbing_medium;
 mod bvh;
+mod bvh_cache;
 mod constant_medium;
+mod density_medium;
 mod flip_face;
 mod hit_record;
 mod hittable_list;
+mod instance;
+mod light_link;
 mod moving_sphere;
+mod plane;
 mod rotate;
+mod sdf;
+mod section;
 mod sphere;
+mod sphere_batch;
+mod transform;
 mod translate;
+mod triangle;
+mod uv_map;
+mod visibility;
 mod xy_rect;
 mod xyz_box;
 mod xz_rect;
 mod yz_rect;
 
-use super::algebra::{Axis, Point3, Ray, Vec3, AXES, ONB, X_AXIS, Y_AXIS, Z_AXIS};
-use super::common::{Float, Random, INFINITY, MIN_THICKNESS, PI, PI_OVER_2, RAY_EPSILON, TWO_PI};
-use super::material::{ArcMaterial, Isotropic};
+use super::algebra::{Axis, Colour, Mat4, Point3, Quat, Ray, Vec3, AXES, ONB, X_AXIS};
+use super::common::{
+    Float, Messages, Random, Sampler, Scratch, INFINITY, MIN_THICKNESS, PI, PI_OVER_2, RAY_EPSILON,
+    RAY_EPSILON_SCALE, TWO_PI,
+};
+use super::material::{Absorber, ArcMaterial, Isotropic};
 use super::texture::ArcTexture;
 use std::fmt;
 use std::sync::Arc;
+use toml::{Table, Value};
 
 /// Re-exports.
 pub use self::aabb::AABB;
+pub use self::absorbing_medium::AbsorbingMedium;
 pub use self::bvh::BVH;
-pub use self::constant_medium::ConstantMedium;
+pub use self::bvh_cache::BvhCache;
+pub use self::constant_medium::{boundary_intervals, ConstantMedium};
+pub use self::density_medium::{DensityField, DensityGrid, DensityMedium};
 pub use self::flip_face::FlipFace;
 pub use self::hit_record::HitRecord;
 pub use self::hittable_list::HittableList;
+pub use self::instance::Instance;
+pub use self::light_link::{LightLink, LightLinks};
 pub use self::moving_sphere::MovingSphere;
+pub use self::plane::Plane;
 pub use self::rotate::Rotate;
+pub use self::sdf::{Sdf, SdfFn, SdfShape};
+pub use self::section::{Section, SectionPlane};
 pub use self::sphere::Sphere;
+pub use self::sphere_batch::{SphereBatch, SPHERE_BATCH_SIZE};
+pub use self::transform::Transform;
 pub use self::translate::Translate;
+pub use self::triangle::Triangle;
+pub use self::uv_map::{UvMap, UvMapping};
+pub use self::visibility::Visibility;
 pub use self::xy_rect::XYrect;
-pub use self::xyz_box::XYZbox;
+pub use self::xyz_box::{FaceUv, XYZbox, FACE_NAMES};
 pub use self::xz_rect::XZrect;
 pub use self::yz_rect::YZrect;
 
@@ -72,6 +102,44 @@ pub trait Hittable: fmt::Display + fmt::Debug {
     fn random(&self, _origin: Point3) -> Vec3 {
         Vec3::new(1.0, 0.0, 0.0) // Arbitrary direction.
     }
+
+    /// Returns the surface area sampled by `sample_area()`. Objects whose
+    /// surface can't be sampled return 0.
+    fn area(&self) -> Float {
+        0.0
+    }
+
+    /// Returns a point picked uniformly over the surface of the object and
+    /// the outward normal there. Used to start paths on lights. Objects
+    /// whose surface can't be sampled return `None`.
+    fn sample_area(&self) -> Option<(Point3, Vec3)> {
+        None
+    }
+
+    /// Returns true if the object emits light and supports sampling
+    /// directions towards it with `pdf_value()` and `random()`, so the scene
+    /// can sample it as a light. Objects that can't be sampled return false.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// Returns the emissive parts of a group or transformed object, each
+    /// wrapped in the transforms applied to it, so they can be sampled as
+    /// lights without the rest of the group. Objects that are sampled as a
+    /// whole return `None`. Use `emissive_leaves()` to collect them.
+    fn emissive_parts(&self) -> Option<Vec<ArcHittable>> {
+        None
+    }
+
+    /// Returns the centre, radius and material of a plain sphere so the
+    /// bounding volume hierarchy can batch spheres. Other objects return
+    /// `None`.
+    fn sphere(&self) -> Option<(Point3, Float, ArcMaterial)> {
+        None
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table;
 }
 
 /// Atomic reference counted `Hittable` object.
@@ -84,3 +152,85 @@ pub fn get_sphere_uv(p: &Point3) -> (Float, Float) {
     let theta = p.y().asin();
     (1.0 - (phi + PI) / TWO_PI, (theta + PI_OVER_2) / PI)
 }
+
+/// Returns the parts of an object that are sampled as lights. Groups are
+/// searched recursively for emissive objects which are wrapped in the
+/// transforms applied to them.
+///
+/// * `object` - The object.
+pub fn emissive_leaves(object: &ArcHittable) -> Vec<ArcHittable> {
+    match object.emissive_parts() {
+        Some(parts) => parts,
+        None if object.is_emissive() => vec![Arc::clone(object)],
+        None => Vec::new(),
+    }
+}
+
+/// Returns the emissive parts of a transformed object. Returns `None` if the
+/// object is sampled as a whole so the transformed object is too.
+///
+/// * `object` - The object being transformed.
+/// * `wrap`   - Applies the transform to a part of the object.
+pub fn wrap_emissive_leaves<F>(object: &ArcHittable, wrap: F) -> Option<Vec<ArcHittable>>
+where
+    F: Fn(ArcHittable) -> ArcHittable,
+{
+    let leaves = emissive_leaves(object);
+    match leaves.as_slice() {
+        [leaf] if Arc::as_ptr(leaf) as *const () == Arc::as_ptr(object) as *const () => None,
+        _ => Some(leaves.into_iter().map(wrap).collect()),
+    }
+}
+
+/// Order in which the scene loader applies transforms to an object.
+const TRANSFORM_ORDER: [&str; 10] = [
+    "uv_mapping",
+    "flip_face",
+    "scale",
+    "rotate_x",
+    "rotate_y",
+    "rotate_z",
+    "rotate",
+    "translate",
+    "matrix",
+    "visible",
+];
+
+/// Returns the description of a transformed object in the scene file format.
+/// The transform is added to the description of the object if the scene
+/// loader would apply it after the object's own transforms. Otherwise the
+/// object is wrapped in a group with the transform.
+///
+/// * `object` - The object being transformed.
+/// * `key` - The transform field.
+/// * `value` - The transform value.
+pub fn describe_transform(object: &ArcHittable, key: &str, value: Value) -> Table {
+    let t = object.describe();
+
+    let rank = TRANSFORM_ORDER.iter().position(|k| *k == key).unwrap_or(0);
+    let mut t = if TRANSFORM_ORDER[rank..].iter().any(|k| t.contains_key(*k)) {
+        describe_group(vec![t], false)
+    } else {
+        t
+    };
+
+    t.insert(key.into(), value);
+    t
+}
+
+/// Returns the description of a group of objects in the scene file format.
+///
+/// * `objects` - Descriptions of the objects.
+/// * `bvh` - Build a bounding volume hierarchy for the group.
+pub fn describe_group(objects: Vec<Table>, bvh: bool) -> Table {
+    let mut t = Table::new();
+    t.insert("type".into(), "group".into());
+    if bvh {
+        t.insert("bvh".into(), true.into());
+    }
+    t.insert(
+        "objects".into(),
+        Value::Array(objects.into_iter().map(Value::Table).collect()),
+    );
+    t
+}
@@ -4,7 +4,8 @@
 //! along a linear path.
 
 use super::{
-    get_sphere_uv, ArcHittable, ArcMaterial, Float, HitRecord, Hittable, Point3, Ray, Vec3, AABB,
+    get_sphere_uv, ArcHittable, ArcMaterial, Float, HitRecord, Hittable, Point3, Ray, Table, Vec3,
+    AABB,
 };
 use std::fmt;
 use std::sync::Arc;
@@ -85,6 +86,15 @@ impl MovingSphere {
         }
     }
 
+    /// Returns the velocity of the sphere.
+    pub fn velocity(&self) -> Vec3 {
+        if self.time0 == self.time1 {
+            Vec3::zero() // avoid divide by 0 by assuming no motion.
+        } else {
+            (self.center1 - self.center0) / (self.time1 - self.time0)
+        }
+    }
+
     /// Returns a `HitRecord` containing the intersection point, outward
     /// surface normal for the incident ray and given parameter.
     ///
@@ -107,6 +117,7 @@ impl MovingSphere {
             u,
             v,
         )
+        .update_velocity(self.velocity())
     }
 }
 
@@ -152,4 +163,17 @@ impl Hittable for MovingSphere {
         let box1 = AABB::new(self.center(time1) - r, self.center(time1) + r);
         Some(AABB::surrounding_box(box0, box1))
     }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "moving_sphere".into());
+        t.insert("center0".into(), self.center0.into());
+        t.insert("center1".into(), self.center1.into());
+        t.insert("time0".into(), self.time0.into());
+        t.insert("time1".into(), self.time1.into());
+        t.insert("radius".into(), self.radius.into());
+        t.insert("material".into(), self.material.describe().into());
+        t
+    }
 }
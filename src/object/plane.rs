@@ -0,0 +1,118 @@
+//! # Plane
+//!
+//! A library for handling ray intersections with infinite planes. Planes
+//! don't have a bounding box so bounding volume hierarchies keep them
+//! outside the tree.
+
+use super::{
+    ArcHittable, ArcMaterial, Float, HitRecord, Hittable, Point3, Ray, Table, Vec3, AABB, ONB,
+};
+use std::fmt;
+use std::sync::Arc;
+
+/// Models an infinite plane.
+#[derive(Debug, Clone)]
+pub struct Plane {
+    /// A point on the plane.
+    point: Point3,
+
+    /// Unit normal of the plane.
+    normal: Vec3,
+
+    /// Tangent frame of the plane with the normal as the w-axis.
+    onb: ONB,
+
+    /// Size of the square the texture coordinates repeat over.
+    uv_scale: Float,
+
+    /// Surface material.
+    material: ArcMaterial,
+}
+
+impl fmt::Display for Plane {
+    /// Display the plane parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "plane(point: {}, normal: {}, uv_scale: {}, material: {})",
+            self.point, self.normal, self.uv_scale, self.material
+        )
+    }
+}
+
+impl Plane {
+    /// Create a new infinite plane.
+    ///
+    /// * `point` - A point on the plane.
+    /// * `normal` - Normal of the plane.
+    /// * `uv_scale` - Size of the square the texture coordinates repeat over.
+    /// * `material` - Surface material.
+    pub fn new(point: Point3, normal: Vec3, uv_scale: Float, material: ArcMaterial) -> ArcHittable {
+        let normal = normal.unit_vector();
+
+        Arc::new(Plane {
+            point,
+            normal,
+            onb: ONB::new(normal),
+            uv_scale,
+            material: Arc::clone(&material),
+        })
+    }
+}
+
+impl Hittable for Plane {
+    /// Calculate the intersection of a ray with the plane.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `t_min` - The minium parameter for intersections.
+    /// * `t_max` - The maximum parameter for intersections.
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        let denom = self.normal.dot(ray.direction);
+        if denom == 0.0 {
+            return None;
+        }
+
+        let t = self.normal.dot(self.point - ray.origin) / denom;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        // Texture coordinates are the position along the tangents of the
+        // plane so textures repeat every `uv_scale` units.
+        let p = ray.at(t);
+        let offset = (p - self.point) / self.uv_scale;
+        let u = offset.dot(self.onb.u());
+        let v = offset.dot(self.onb.v());
+
+        Some(HitRecord::new(
+            ray,
+            t,
+            p,
+            self.normal,
+            Arc::clone(&self.material),
+            u - u.floor(),
+            v - v.floor(),
+        ))
+    }
+
+    /// Planes are infinite so they don't have a bounding box.
+    ///
+    /// * `_time0` - Start time of motion (ignored).
+    /// * `_time1` - End time of motion (ignored).
+    fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
+        None
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "plane".into());
+        t.insert("point".into(), self.point.into());
+        t.insert("normal".into(), self.normal.into());
+        t.insert("uv_scale".into(), self.uv_scale.into());
+        t.insert("material".into(), self.material.describe().into());
+        t
+    }
+}
@@ -1,10 +1,11 @@
 //! # Rotate
 //!
-//! A library for handling ray intersections with rotated objects.
+//! A library for handling ray intersections with objects rotated about an
+//! arbitrary axis through the origin.
 
 use super::{
-    ArcHittable, Axis, Float, HitRecord, Hittable, Point3, Ray, Vec3, AABB, INFINITY, X_AXIS,
-    Y_AXIS, Z_AXIS,
+    describe_transform, wrap_emissive_leaves, ArcHittable, Float, HitRecord, Hittable, Point3,
+    Quat, Ray, Table, Value, Vec3, AABB, INFINITY,
 };
 use std::fmt;
 use std::sync::Arc;
@@ -15,17 +16,12 @@ pub struct Rotate {
     /// Holds a `Hittable`.
     object: ArcHittable,
 
-    /// Axis of rotation.
-    axis: Axis,
+    /// Unit quaternion of the rotation.
+    rotation: Quat,
 
-    /// sin(`radians`).
-    sin_theta: Float,
-
-    /// cos(`radians`).
-    cos_theta: Float,
-
-    /// Bounding box.
-    bbox: AABB,
+    /// Bounding box. Unbounded objects, e.g. infinite planes, stay
+    /// unbounded.
+    bbox: Option<AABB>,
 }
 
 impl fmt::Display for Rotate {
@@ -35,34 +31,34 @@ impl fmt::Display for Rotate {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "rotate(object: {}, axis: {}, bbox: {}, sin_theta: {}, cos_theta: {})",
-            self.object, self.axis, self.bbox, self.sin_theta, self.cos_theta,
+            "rotate(object: {}, rotation: {}, bbox: {:?})",
+            self.object, self.rotation, self.bbox,
         )
     }
 }
 
 impl Rotate {
-    /// Create a new axis aligned box.
+    /// Create a new rotated object.
     ///
     /// * `object`: Holds a `Hittable`.
     /// * `axis`: Axis of rotation.
     /// * `degrees: Float` - Rotation angle.
-    pub fn new(object: ArcHittable, axis: Axis, degrees: Float) -> ArcHittable {
-        let radians = degrees.to_radians();
-
-        let sin_theta = radians.sin();
-        let cos_theta = radians.cos();
-
-        match get_rotated_bbox(Arc::clone(&object), axis, sin_theta, cos_theta) {
-            Ok(bbox) => Arc::new(Rotate {
-                object: Arc::clone(&object),
-                axis,
-                sin_theta,
-                cos_theta,
-                bbox,
-            }),
-            Err(e) => panic!("{}", e),
-        }
+    pub fn new(object: ArcHittable, axis: Vec3, degrees: Float) -> ArcHittable {
+        Rotate::from_quat(object, Quat::from_axis_angle(axis, degrees))
+    }
+
+    /// Create a new rotated object from a quaternion.
+    ///
+    /// * `object`: Holds a `Hittable`.
+    /// * `rotation`: The rotation.
+    pub fn from_quat(object: ArcHittable, rotation: Quat) -> ArcHittable {
+        let rotation = rotation.normalize();
+
+        Arc::new(Rotate {
+            object: Arc::clone(&object),
+            rotation,
+            bbox: get_rotated_bbox(Arc::clone(&object), rotation),
+        })
     }
 }
 
@@ -74,15 +70,22 @@ impl Hittable for Rotate {
     /// * `t_max` - The maximum parameter for intersections.
     fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
         // Rotate ray into the coordinate frame of the object.
-        let o = rotate_neg(&ray.origin, self.axis, self.sin_theta, self.cos_theta);
-        let d = rotate_neg(&ray.direction, self.axis, self.sin_theta, self.cos_theta);
+        let inverse = self.rotation.conjugate();
+        let o = inverse.rotate(ray.origin);
+        let d = inverse.rotate(ray.direction);
         let rotated_r = Ray::new(o, d, ray.time);
 
         if let Some(rec) = self.object.hit(&rotated_r, t_min, t_max) {
-            // Rotate hit point and normal out of the coordinate frame of the object.
-            let p = rotate(&rec.point, self.axis, self.sin_theta, self.cos_theta);
-            let n = rotate(&rec.normal, self.axis, self.sin_theta, self.cos_theta);
-            Some(rec.update_point(p).update_normal(&rotated_r, n))
+            // Rotate hit point, normal and velocity out of the coordinate frame of the object.
+            let p = self.rotation.rotate(rec.point);
+            let n = self.rotation.rotate(rec.normal);
+            let g = self.rotation.rotate(rec.geometric_normal);
+            let vel = self.rotation.rotate(rec.velocity);
+            Some(
+                rec.update_point(p)
+                    .update_normal(&rotated_r, n, g)
+                    .update_velocity(vel),
+            )
         } else {
             None
         }
@@ -93,7 +96,18 @@ impl Hittable for Rotate {
     /// * `time0` - Start time of motion.
     /// * `time1` - End time of motion.
     fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
-        Some(self.bbox)
+        self.bbox
+    }
+
+    /// Returns true if the object emits light.
+    fn is_emissive(&self) -> bool {
+        self.object.is_emissive()
+    }
+
+    /// Returns the emissive parts of the object, each with the same
+    /// transform.
+    fn emissive_parts(&self) -> Option<Vec<ArcHittable>> {
+        wrap_emissive_leaves(&self.object, |o| Rotate::from_quat(o, self.rotation))
     }
 
     /// Sample PDF value at hit point and given direction.
@@ -101,37 +115,48 @@ impl Hittable for Rotate {
     /// * `origin` - Hit point.
     /// * `v` - Direction to sample.
     fn pdf_value(&self, origin: Point3, v: Vec3) -> Float {
-        self.object.pdf_value(
-            rotate_neg(&origin, self.axis, self.sin_theta, self.cos_theta),
-            rotate_neg(&v, self.axis, self.sin_theta, self.cos_theta),
-        )
+        let inverse = self.rotation.conjugate();
+        self.object
+            .pdf_value(inverse.rotate(origin), inverse.rotate(v))
     }
 
     /// Generate a random direction towards this object.
     ///
     /// * `origin` - Hit point.
     fn random(&self, origin: Point3) -> Vec3 {
-        self.object.random(rotate_neg(
-            &origin,
-            self.axis,
-            self.sin_theta,
-            self.cos_theta,
-        ))
+        let v = self.object.random(self.rotation.conjugate().rotate(origin));
+        self.rotation.rotate(v)
+    }
+
+    /// Returns the surface area sampled by `sample_area()`.
+    fn area(&self) -> Float {
+        self.object.area()
+    }
+
+    /// Returns a point picked uniformly over the surface of the object and
+    /// the outward normal there.
+    fn sample_area(&self) -> Option<(Point3, Vec3)> {
+        let (p, normal) = self.object.sample_area()?;
+        Some((self.rotation.rotate(p), self.rotation.rotate(normal)))
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        let (axis, degrees) = self.rotation.to_axis_angle();
+
+        let mut t = Table::new();
+        t.insert("axis".into(), axis.into());
+        t.insert("degrees".into(), degrees.into());
+        describe_transform(&self.object, "rotate", Value::Table(t))
     }
 }
 
-/// Calculates the bounding box for an object rotated about a coordinate axis.
+/// Calculates the bounding box for a rotated object. Returns `None` if the
+/// object doesn't have a bounding box.
 ///
-/// * `object` - Object to rotate.bbox_y_rot
-/// * `axis` - Axis of rotation
-/// * `sin_theta` - Sine of angle of rotation.
-/// * `cos_theta` - Cosine of angle of rotation.
-fn get_rotated_bbox<'a>(
-    object: ArcHittable,
-    axis: Axis,
-    sin_theta: Float,
-    cos_theta: Float,
-) -> Result<AABB, &'a str> {
+/// * `object` - Object to rotate.
+/// * `rotation` - The rotation.
+fn get_rotated_bbox(object: ArcHittable, rotation: Quat) -> Option<AABB> {
     // Motion is not supported. So (0.0, 1.0) ok.
     if let Some(bbox) = object.bounding_box(0.0, 1.0) {
         let mut min: [Float; 3] = [INFINITY, INFINITY, INFINITY];
@@ -150,7 +175,7 @@ fn get_rotated_bbox<'a>(
                     let y = jj * bbox.max.y() + (1.0 - jj) * bbox.min.y();
                     let z = kk * bbox.max.z() + (1.0 - kk) * bbox.min.z();
 
-                    let tester = rotate(&Vec3::new(x, y, z), axis, sin_theta, cos_theta);
+                    let tester = rotation.rotate(Vec3::new(x, y, z));
 
                     for c in 0..3 {
                         min[c] = min[c].min(tester[c]);
@@ -160,48 +185,8 @@ fn get_rotated_bbox<'a>(
             }
         }
 
-        return Ok(AABB::new(Point3::from_array(min), Point3::from_array(max)));
+        return Some(AABB::new(Point3::from_array(min), Point3::from_array(max)));
     }
 
-    Err("Missing bounding box for rotated object")
-}
-
-/// Rotate a point/vector around a coordinate axis by angle θ.
-///
-/// * `v` - Point/vector.
-/// * `sin_theta` - sin(θ).
-/// * `cos_theta` - cos(θ).
-fn rotate<'a>(v: &Vec3, axis: Axis, sin_theta: Float, cos_theta: Float) -> Vec3 {
-    let (x, y, z) = (v[0], v[1], v[2]);
-
-    match axis {
-        X_AXIS => {
-            let newy = y * cos_theta - z * sin_theta;
-            let newz = z * cos_theta + y * sin_theta;
-            Vec3::new(x, newy, newz)
-        }
-        Y_AXIS => {
-            let newx = x * cos_theta + z * sin_theta;
-            let newz = z * cos_theta - x * sin_theta;
-            Vec3::new(newx, y, newz)
-        }
-        Z_AXIS => {
-            let newx = x * cos_theta - y * sin_theta;
-            let newy = y * cos_theta + x * sin_theta;
-            Vec3::new(newx, newy, z)
-        }
-        _ => panic!("Invalid axis {}", axis),
-    }
-}
-
-/// Rotate a point/vector around a coordinate axis by -θ.
-///
-/// Note that the sin/cos of θ is provided and we use the identities
-/// `sin(-θ) = -sin(θ)` and `cos(-θ) = cos(θ)`.
-///
-/// * `v` - Point/vector.
-/// * `sin_theta` - sin(θ).
-/// * `cos_theta` - cos(θ).
-fn rotate_neg<'a>(v: &Vec3, axis: Axis, sin_theta: Float, cos_theta: Float) -> Vec3 {
-    rotate(v, axis, -sin_theta, cos_theta)
+    None
 }
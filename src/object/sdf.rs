@@ -0,0 +1,375 @@
+//! # Sdf
+//!
+//! A library for handling ray intersections with surfaces given by signed
+//! distance functions. Rays are sphere traced, i.e. they step by the
+//! distance to the nearest surface until they are close enough to it, and
+//! normals are estimated from the gradient of the distance. This makes it
+//! easy to build organic shapes by blending simple ones.
+
+use super::{
+    get_sphere_uv, ArcHittable, ArcMaterial, Float, HitRecord, Hittable, Point3, Ray, Table, Value,
+    Vec3, AABB, MIN_THICKNESS,
+};
+use std::fmt;
+use std::sync::Arc;
+
+/// Maximum number of steps along a ray.
+const MAX_STEPS: usize = 512;
+
+/// Distance to the surface that counts as a hit.
+const HIT_DISTANCE: Float = 1.0e-5;
+
+/// Offset used to estimate the gradient of the distance.
+const GRADIENT_OFFSET: Float = 1.0e-5;
+
+/// A signed distance function supplied by the caller.
+pub type SdfFn = Arc<dyn Fn(Point3) -> Float + Send + Sync>;
+
+/// Models a signed distance function. Distances are negative inside the
+/// surface.
+#[derive(Clone)]
+pub enum SdfShape {
+    /// Sphere.
+    Sphere { center: Point3, radius: Float },
+
+    /// Axis-aligned box.
+    Box { center: Point3, half_size: Vec3 },
+
+    /// Torus around the y-axis.
+    Torus {
+        center: Point3,
+        major_radius: Float,
+        minor_radius: Float,
+    },
+
+    /// Union of shapes blended over a distance `k`.
+    SmoothUnion { shapes: Vec<SdfShape>, k: Float },
+
+    /// A distance function supplied by the caller. It must not
+    /// overestimate the distance to the surface, which has to lie inside
+    /// the bounding box.
+    Custom { distance: SdfFn, bbox: AABB },
+}
+
+impl fmt::Debug for SdfShape {
+    /// Display the shape parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SdfShape::Sphere { center, radius } => f
+                .debug_struct("Sphere")
+                .field("center", center)
+                .field("radius", radius)
+                .finish(),
+            SdfShape::Box { center, half_size } => f
+                .debug_struct("Box")
+                .field("center", center)
+                .field("half_size", half_size)
+                .finish(),
+            SdfShape::Torus {
+                center,
+                major_radius,
+                minor_radius,
+            } => f
+                .debug_struct("Torus")
+                .field("center", center)
+                .field("major_radius", major_radius)
+                .field("minor_radius", minor_radius)
+                .finish(),
+            SdfShape::SmoothUnion { shapes, k } => f
+                .debug_struct("SmoothUnion")
+                .field("shapes", shapes)
+                .field("k", k)
+                .finish(),
+            SdfShape::Custom { bbox, .. } => f.debug_struct("Custom").field("bbox", bbox).finish(),
+        }
+    }
+}
+
+impl SdfShape {
+    /// Returns the signed distance from a point to the surface.
+    ///
+    /// * `p` - The point.
+    pub fn distance(&self, p: Point3) -> Float {
+        match self {
+            SdfShape::Sphere { center, radius } => (p - *center).length() - radius,
+
+            SdfShape::Box { center, half_size } => {
+                let d = p - *center;
+                let q = Vec3::new(
+                    d.x().abs() - half_size.x(),
+                    d.y().abs() - half_size.y(),
+                    d.z().abs() - half_size.z(),
+                );
+                let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0));
+                outside.length() + q.x().max(q.y()).max(q.z()).min(0.0)
+            }
+
+            SdfShape::Torus {
+                center,
+                major_radius,
+                minor_radius,
+            } => {
+                let d = p - *center;
+                let ring = (d.x() * d.x() + d.z() * d.z()).sqrt() - major_radius;
+                (ring * ring + d.y() * d.y()).sqrt() - minor_radius
+            }
+
+            SdfShape::SmoothUnion { shapes, k } => shapes
+                .iter()
+                .map(|s| s.distance(p))
+                .reduce(|a, b| smooth_min(a, b, *k))
+                .unwrap_or(Float::INFINITY),
+
+            SdfShape::Custom { distance, .. } => distance(p),
+        }
+    }
+
+    /// Returns a bounding box that contains the surface.
+    pub fn bounding_box(&self) -> AABB {
+        match self {
+            SdfShape::Sphere { center, radius } => {
+                let r = Vec3::new(*radius, *radius, *radius);
+                AABB::new(*center - r, *center + r)
+            }
+
+            SdfShape::Box { center, half_size } => {
+                AABB::new(*center - *half_size, *center + *half_size)
+            }
+
+            SdfShape::Torus {
+                center,
+                major_radius,
+                minor_radius,
+            } => {
+                let r = major_radius + minor_radius;
+                let h = Vec3::new(r, *minor_radius, r);
+                AABB::new(*center - h, *center + h)
+            }
+
+            SdfShape::SmoothUnion { shapes, k } => {
+                // Blending grows the surface by at most a quarter of `k`.
+                let bbox = shapes
+                    .iter()
+                    .map(|s| s.bounding_box())
+                    .reduce(AABB::surrounding_box)
+                    .unwrap_or_else(|| AABB::new(Point3::zero(), Point3::zero()));
+                let grow = Vec3::new(0.25 * k, 0.25 * k, 0.25 * k);
+                AABB::new(bbox.min - grow, bbox.max + grow)
+            }
+
+            SdfShape::Custom { bbox, .. } => *bbox,
+        }
+    }
+
+    /// Returns a description of the shape in the scene file format. Custom
+    /// distance functions can't be described and are replaced by their
+    /// bounding box.
+    pub fn describe(&self) -> Table {
+        let mut t = Table::new();
+
+        match self {
+            SdfShape::Sphere { center, radius } => {
+                t.insert("type".into(), "sphere".into());
+                t.insert("center".into(), (*center).into());
+                t.insert("radius".into(), (*radius).into());
+            }
+
+            SdfShape::Box { center, half_size } => {
+                t.insert("type".into(), "box".into());
+                t.insert("center".into(), (*center).into());
+                t.insert("size".into(), (*half_size * 2.0).into());
+            }
+
+            SdfShape::Torus {
+                center,
+                major_radius,
+                minor_radius,
+            } => {
+                t.insert("type".into(), "torus".into());
+                t.insert("center".into(), (*center).into());
+                t.insert("major_radius".into(), (*major_radius).into());
+                t.insert("minor_radius".into(), (*minor_radius).into());
+            }
+
+            SdfShape::SmoothUnion { shapes, k } => {
+                t.insert("type".into(), "smooth_union".into());
+                t.insert(
+                    "shapes".into(),
+                    Value::Array(shapes.iter().map(|s| Value::Table(s.describe())).collect()),
+                );
+                t.insert("k".into(), (*k).into());
+            }
+
+            SdfShape::Custom { bbox, .. } => {
+                t.insert("type".into(), "box".into());
+                t.insert("center".into(), bbox.centroid().into());
+                t.insert("size".into(), (bbox.max - bbox.min).into());
+            }
+        }
+
+        t
+    }
+}
+
+/// Models a surface given by a signed distance function.
+#[derive(Debug, Clone)]
+pub struct Sdf {
+    /// The signed distance function.
+    shape: SdfShape,
+
+    /// Bounding box of the surface with some padding.
+    bbox: AABB,
+
+    /// Surface material.
+    material: ArcMaterial,
+}
+
+impl fmt::Display for Sdf {
+    /// Display the SDF parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "sdf(shape: {:?}, bbox: {}, material: {})",
+            self.shape, self.bbox, self.material
+        )
+    }
+}
+
+impl Sdf {
+    /// Create a new surface from a signed distance function.
+    ///
+    /// * `shape` - The signed distance function.
+    /// * `material` - Surface material.
+    pub fn new(shape: SdfShape, material: ArcMaterial) -> ArcHittable {
+        // Pad the bounding box so rays entering it start clear of surfaces
+        // that touch it.
+        let bbox = shape.bounding_box();
+        let pad = Vec3::new(MIN_THICKNESS, MIN_THICKNESS, MIN_THICKNESS);
+
+        Arc::new(Sdf {
+            bbox: AABB::new(bbox.min - pad, bbox.max + pad),
+            shape,
+            material: Arc::clone(&material),
+        })
+    }
+
+    /// Create a new surface from a signed distance function supplied by the
+    /// caller.
+    ///
+    /// * `distance` - Signed distance function. It must not overestimate
+    ///   the distance to the surface.
+    /// * `bbox` - Bounding box that contains the surface.
+    /// * `material` - Surface material.
+    pub fn from_fn(distance: SdfFn, bbox: AABB, material: ArcMaterial) -> ArcHittable {
+        Sdf::new(SdfShape::Custom { distance, bbox }, material)
+    }
+
+    /// Returns the outward surface normal at a point estimated from the
+    /// gradient of the distance.
+    ///
+    /// * `p` - The point.
+    fn normal(&self, p: Point3) -> Vec3 {
+        // Tetrahedral central differences need 4 evaluations instead of 6.
+        let h = GRADIENT_OFFSET;
+        let k = [
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ];
+
+        k.iter()
+            .fold(Vec3::zero(), |n, &k| n + k * self.shape.distance(p + k * h))
+            .unit_vector()
+    }
+}
+
+impl Hittable for Sdf {
+    /// Calculate the intersection of a ray with the surface.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `t_min` - The minium parameter for intersections.
+    /// * `t_max` - The maximum parameter for intersections.
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        let (t_start, t_end) = self.bbox.hit(ray, t_min, t_max)?;
+        let speed = ray.direction.length();
+
+        // Rays that start on the surface, e.g. reflected and refracted rays,
+        // march on the side they are heading to and must move away from the
+        // surface before they can hit it again.
+        let d = self.shape.distance(ray.at(t_start));
+        let on_surface = d.abs() < HIT_DISTANCE;
+        let side = if !on_surface {
+            d.signum()
+        } else if self.normal(ray.at(t_start)).dot(ray.direction) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+
+        let mut armed = !on_surface;
+        let mut t = t_start;
+        for _ in 0..MAX_STEPS {
+            let p = ray.at(t);
+            let d = side * self.shape.distance(p);
+
+            if d >= HIT_DISTANCE {
+                armed = true;
+            } else if armed {
+                let normal = self.normal(p);
+                let (u, v) = get_sphere_uv(&(p - self.bbox.centroid()).unit_vector());
+                return Some(HitRecord::new(
+                    ray,
+                    t,
+                    p,
+                    normal,
+                    Arc::clone(&self.material),
+                    u,
+                    v,
+                ));
+            }
+
+            t += d.max(HIT_DISTANCE) / speed;
+            if t > t_end {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    /// Create a bounding box across time interval `[t0, t1]`.
+    ///
+    /// * `_time0` - Start time of motion (ignored).
+    /// * `_time1` - End time of motion (ignored).
+    fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
+        Some(self.bbox)
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "sdf".into());
+        t.insert("shape".into(), self.shape.describe().into());
+        t.insert("material".into(), self.material.describe().into());
+        t
+    }
+}
+
+/// Returns the polynomial smooth minimum of two distances.
+///
+/// * `a` - First distance.
+/// * `b` - Second distance.
+/// * `k` - Distance over which the surfaces are blended.
+fn smooth_min(a: Float, b: Float, k: Float) -> Float {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+
+    let h = (k - (a - b).abs()).max(0.0) / k;
+    a.min(b) - h * h * k * 0.25
+}
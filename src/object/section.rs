@@ -0,0 +1,226 @@
+//! # Section
+//!
+//! A library for cutting objects with section planes. Geometry on the side
+//! of a plane its normal points to is removed so interiors can be seen, e.g.
+//! for cutaway renders. Capped planes close the cut surfaces of solid objects
+//! with the material of the object that was cut.
+
+use super::{
+    wrap_emissive_leaves, ArcHittable, Float, HitRecord, Hittable, Point3, Ray, Table, Vec3, AABB,
+};
+use std::fmt;
+use std::sync::Arc;
+
+/// Small offset used to step past removed intersections.
+const STEP_EPSILON: Float = 1e-6;
+
+/// Models a section plane.
+#[derive(Debug, Copy, Clone)]
+pub struct SectionPlane {
+    /// A point on the plane.
+    pub point: Point3,
+
+    /// Unit normal pointing to the side that is removed.
+    pub normal: Vec3,
+
+    /// Whether cut surfaces of solid objects are capped.
+    pub cap: bool,
+}
+
+impl fmt::Display for SectionPlane {
+    /// Display the section plane parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "section_plane(point: {}, normal: {}, cap: {})",
+            self.point, self.normal, self.cap
+        )
+    }
+}
+
+impl SectionPlane {
+    /// Create a new section plane.
+    ///
+    /// * `point` - A point on the plane.
+    /// * `normal` - Normal pointing to the side that is removed.
+    /// * `cap` - Whether cut surfaces of solid objects are capped.
+    pub fn new(point: Point3, normal: Vec3, cap: bool) -> SectionPlane {
+        SectionPlane {
+            point,
+            normal: normal.unit_vector(),
+            cap,
+        }
+    }
+
+    /// Returns true if a point is on the side of the plane that is kept.
+    ///
+    /// * `p` - The point.
+    fn keeps(&self, p: Point3) -> bool {
+        (p - self.point).dot(self.normal) <= 0.0
+    }
+
+    /// Returns a description of the plane in the scene file format.
+    pub fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("point".into(), self.point.into());
+        t.insert("normal".into(), self.normal.into());
+        t.insert("cap".into(), self.cap.into());
+        t
+    }
+}
+
+/// Models an object cut by section planes.
+#[derive(Debug, Clone)]
+pub struct Section {
+    /// The object.
+    object: ArcHittable,
+
+    /// The section planes.
+    planes: Vec<SectionPlane>,
+}
+
+impl fmt::Display for Section {
+    /// Display the section parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let planes: Vec<String> = self.planes.iter().map(|p| p.to_string()).collect();
+        write!(
+            f,
+            "section(object: {}, planes: [{}])",
+            self.object,
+            planes.join(", ")
+        )
+    }
+}
+
+impl Section {
+    /// Cut an object with section planes.
+    ///
+    /// * `object` - The object.
+    /// * `planes` - The section planes.
+    pub fn new(object: ArcHittable, planes: Vec<SectionPlane>) -> ArcHittable {
+        Arc::new(Section {
+            object: Arc::clone(&object),
+            planes,
+        })
+    }
+
+    /// Returns true if a point is kept by every plane.
+    ///
+    /// * `p` - The point.
+    fn keeps(&self, p: Point3) -> bool {
+        self.planes.iter().all(|plane| plane.keeps(p))
+    }
+
+    /// Returns the closest cap between the parameters of a ray. The ray must
+    /// cross a capped plane from the removed side into the kept region.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `t_min` - The minium parameter for intersections.
+    /// * `t_max` - The maximum parameter for intersections.
+    fn cap(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<(Float, Vec3)> {
+        self.planes
+            .iter()
+            .filter(|plane| plane.cap)
+            .filter_map(|plane| {
+                let rate = ray.direction.dot(plane.normal);
+                if rate >= 0.0 {
+                    return None;
+                }
+
+                let t = (plane.point - ray.origin).dot(plane.normal) / rate;
+                if t <= t_min || t >= t_max {
+                    return None;
+                }
+
+                // The crossing must not be removed by another plane.
+                let p = ray.at(t);
+                let kept = self
+                    .planes
+                    .iter()
+                    .all(|other| std::ptr::eq(other, plane) || other.keeps(p));
+                if kept {
+                    Some((t, plane.normal))
+                } else {
+                    None
+                }
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+}
+
+impl Hittable for Section {
+    /// Calculate the intersection of a ray with the kept part of the object.
+    /// When the ray reaches the inside of a solid through a capped plane the
+    /// cap is hit instead.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `t_min` - The minium parameter for intersections.
+    /// * `t_max` - The maximum parameter for intersections.
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        let mut t = t_min;
+
+        while let Some(rec) = self.object.hit(ray, t, t_max) {
+            if !self.keeps(rec.point) {
+                t = rec.t + STEP_EPSILON;
+                continue;
+            }
+
+            // A back face means the ray is inside a solid.
+            if !rec.front_face {
+                if let Some((t_cap, normal)) = self.cap(ray, t_min, rec.t) {
+                    let p = ray.at(t_cap);
+                    let cap = HitRecord::new(ray, t_cap, p, normal, rec.material, 0.0, 0.0);
+                    return Some(cap.update_link_id(rec.link_id));
+                }
+            }
+
+            return Some(rec);
+        }
+
+        None
+    }
+
+    /// Create a bounding box across time interval `[t0, t1]`.
+    ///
+    /// * `time0` - Start time of motion.
+    /// * `time1` - End time of motion.
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
+        self.object.bounding_box(time0, time1)
+    }
+
+    /// Returns true if the object emits light.
+    fn is_emissive(&self) -> bool {
+        self.object.is_emissive()
+    }
+
+    /// Returns the emissive parts of the object, each cut by the same
+    /// planes.
+    fn emissive_parts(&self) -> Option<Vec<ArcHittable>> {
+        wrap_emissive_leaves(&self.object, |o| Section::new(o, self.planes.clone()))
+    }
+
+    /// Sample PDF value at hit point and given direction.
+    ///
+    /// * `origin` - Hit point.
+    /// * `v` - Direction to sample.
+    fn pdf_value(&self, origin: Point3, v: Vec3) -> Float {
+        self.object.pdf_value(origin, v)
+    }
+
+    /// Generate a random direction towards this object.
+    ///
+    /// * `origin` - Hit point.
+    fn random(&self, origin: Point3) -> Vec3 {
+        self.object.random(origin)
+    }
+
+    /// Returns a description of the object in the scene file format. The
+    /// section planes are saved with the scene.
+    fn describe(&self) -> Table {
+        self.object.describe()
+    }
+}
@@ -3,8 +3,8 @@
 //! A library for handling ray intersections with a sphere
 
 use super::{
-    get_sphere_uv, ArcHittable, ArcMaterial, Float, HitRecord, Hittable, Point3, Random, Ray, Vec3,
-    AABB, INFINITY, ONB, RAY_EPSILON, TWO_PI,
+    get_sphere_uv, ArcHittable, ArcMaterial, Float, HitRecord, Hittable, Point3, Ray, Sampler,
+    Table, Vec3, AABB, INFINITY, ONB, RAY_EPSILON, TWO_PI,
 };
 use std::fmt;
 use std::sync::Arc;
@@ -115,6 +115,11 @@ impl Hittable for Sphere {
         Some(AABB::new(self.center - r, self.center + r))
     }
 
+    /// Returns true if the material of the object emits light.
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
     /// Sample PDF value at hit point and given direction.
     ///
     /// * `origin` - Hit point.
@@ -138,6 +143,37 @@ impl Hittable for Sphere {
         let direction = self.center - origin;
         let distance_squared = direction.length_squared();
         let uvw = ONB::new(direction);
-        uvw.local_from_vec3(&Random::vec3_to_sphere(self.radius, distance_squared))
+        uvw.local_from_vec3(&Sampler::vec3_to_sphere(self.radius, distance_squared))
+    }
+
+    /// Returns the surface area sampled by `sample_area()`.
+    fn area(&self) -> Float {
+        2.0 * TWO_PI * self.radius * self.radius
+    }
+
+    /// Returns a point picked uniformly over the surface of the object and
+    /// the outward normal there.
+    fn sample_area(&self) -> Option<(Point3, Vec3)> {
+        let (s, t) = Sampler::get_2d();
+        let z = 1.0 - 2.0 * t;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = TWO_PI * s;
+        let normal = Vec3::new(phi.cos() * r, phi.sin() * r, z);
+        Some((self.center + normal * self.radius.abs(), normal))
+    }
+
+    /// Returns the centre, radius and material of the sphere.
+    fn sphere(&self) -> Option<(Point3, Float, ArcMaterial)> {
+        Some((self.center, self.radius, Arc::clone(&self.material)))
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "sphere".into());
+        t.insert("center".into(), self.center.into());
+        t.insert("radius".into(), self.radius.into());
+        t.insert("material".into(), self.material.describe().into());
+        t
     }
 }
@@ -0,0 +1,236 @@
+//! # Sphere batch
+//!
+//! A library for intersecting rays with a small batch of spheres stored as
+//! a structure of arrays. The centres, radii and material indices are kept
+//! in flat arrays so the intersection loop runs over contiguous memory
+//! without branches and can be vectorized. The bounding volume hierarchy
+//! replaces small subtrees of spheres with batches.
+
+use super::{
+    describe_group, get_sphere_uv, ArcHittable, ArcMaterial, Float, HitRecord, Hittable, Point3,
+    Ray, Sphere, Table, Vec3, AABB, INFINITY,
+};
+use std::fmt;
+use std::sync::Arc;
+
+/// Maximum number of spheres in a batch.
+pub const SPHERE_BATCH_SIZE: usize = 8;
+
+/// Models a batch of spheres.
+#[derive(Debug, Clone)]
+pub struct SphereBatch {
+    /// Number of spheres.
+    len: usize,
+
+    /// X-coordinates of the centres.
+    center_x: [Float; SPHERE_BATCH_SIZE],
+
+    /// Y-coordinates of the centres.
+    center_y: [Float; SPHERE_BATCH_SIZE],
+
+    /// Z-coordinates of the centres.
+    center_z: [Float; SPHERE_BATCH_SIZE],
+
+    /// Radii. Unused entries have radius 0 and are never hit.
+    radius: [Float; SPHERE_BATCH_SIZE],
+
+    /// Index of each sphere's material in `materials`.
+    material: [u8; SPHERE_BATCH_SIZE],
+
+    /// Distinct materials of the spheres.
+    materials: Vec<ArcMaterial>,
+}
+
+impl SphereBatch {
+    /// Create a new batch of spheres.
+    ///
+    /// * `spheres` - Center, radius and material of up to
+    ///   `SPHERE_BATCH_SIZE` spheres.
+    pub fn new(spheres: &[(Point3, Float, ArcMaterial)]) -> ArcHittable {
+        assert!(
+            spheres.len() <= SPHERE_BATCH_SIZE,
+            "a sphere batch holds at most {} spheres",
+            SPHERE_BATCH_SIZE
+        );
+
+        let mut batch = SphereBatch {
+            len: spheres.len(),
+            center_x: [0.0; SPHERE_BATCH_SIZE],
+            center_y: [0.0; SPHERE_BATCH_SIZE],
+            center_z: [0.0; SPHERE_BATCH_SIZE],
+            radius: [0.0; SPHERE_BATCH_SIZE],
+            material: [0; SPHERE_BATCH_SIZE],
+            materials: Vec::new(),
+        };
+
+        for (i, (center, radius, material)) in spheres.iter().enumerate() {
+            batch.center_x[i] = center.x();
+            batch.center_y[i] = center.y();
+            batch.center_z[i] = center.z();
+            batch.radius[i] = *radius;
+
+            // Spheres commonly share materials so only keep one copy.
+            let idx = match batch
+                .materials
+                .iter()
+                .position(|m| Arc::ptr_eq(m, material))
+            {
+                Some(idx) => idx,
+                None => {
+                    batch.materials.push(Arc::clone(material));
+                    batch.materials.len() - 1
+                }
+            };
+            batch.material[i] = idx as u8;
+        }
+
+        Arc::new(batch)
+    }
+
+    /// Returns the centre of a sphere.
+    ///
+    /// * `i` - Index of the sphere.
+    fn center(&self, i: usize) -> Point3 {
+        Point3::new(self.center_x[i], self.center_y[i], self.center_z[i])
+    }
+
+    /// Returns the material of a sphere.
+    ///
+    /// * `i` - Index of the sphere.
+    fn material(&self, i: usize) -> &ArcMaterial {
+        &self.materials[self.material[i] as usize]
+    }
+}
+
+impl fmt::Display for SphereBatch {
+    /// Display the sphere batch parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "sphere_batch(")?;
+        for i in 0..self.len {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(
+                f,
+                "sphere(center: {}, radius: {}, material: {})",
+                self.center(i),
+                self.radius[i],
+                self.material(i)
+            )?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Hittable for SphereBatch {
+    /// Calculate the intersection of a ray with the spheres. The parameters
+    /// of all spheres are computed first and the closest is picked after so
+    /// the first loop has no branches.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `t_min` - The minium parameter for intersections.
+    /// * `t_max` - The maximum parameter for intersections.
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        let (ox, oy, oz) = (ray.origin.x(), ray.origin.y(), ray.origin.z());
+        let (dx, dy, dz) = (ray.direction.x(), ray.direction.y(), ray.direction.z());
+        let a = ray.direction.length_squared();
+
+        let mut ts = [INFINITY; SPHERE_BATCH_SIZE];
+        for (i, slot) in ts.iter_mut().enumerate() {
+            let (ocx, ocy, ocz) = (
+                ox - self.center_x[i],
+                oy - self.center_y[i],
+                oz - self.center_z[i],
+            );
+            let half_b = ocx * dx + ocy * dy + ocz * dz;
+            let c = ocx * ocx + ocy * ocy + ocz * ocz - self.radius[i] * self.radius[i];
+            let discriminant = half_b * half_b - a * c;
+
+            // Misses give a NaN root which fails both comparisons.
+            let root = discriminant.sqrt();
+            let near = (-half_b - root) / a;
+            let far = (-half_b + root) / a;
+            let t = if near > t_min { near } else { far };
+            *slot = if discriminant > 0.0 && t > t_min && t < t_max {
+                t
+            } else {
+                INFINITY
+            };
+        }
+
+        let (i, t) = ts[..self.len]
+            .iter()
+            .enumerate()
+            .fold(
+                (0, INFINITY),
+                |(bi, bt), (i, &t)| {
+                    if t < bt {
+                        (i, t)
+                    } else {
+                        (bi, bt)
+                    }
+                },
+            );
+        if t >= t_max {
+            return None;
+        }
+
+        let center = self.center(i);
+        let point = ray.at(t);
+        let outward_normal = (point - center) / self.radius[i];
+        let (u, v) = get_sphere_uv(&outward_normal);
+
+        Some(HitRecord::new(
+            ray,
+            t,
+            point,
+            outward_normal,
+            Arc::clone(self.material(i)),
+            u,
+            v,
+        ))
+    }
+
+    /// Create a bounding box across time interval `[t0, t1]`.
+    ///
+    /// * `_time0` - Start time of motion (ignored).
+    /// * `_time1` - End time of motion (ignored).
+    fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
+        (0..self.len)
+            .map(|i| {
+                let r = self.radius[i].abs();
+                let r = Vec3::new(r, r, r);
+                AABB::new(self.center(i) - r, self.center(i) + r)
+            })
+            .reduce(AABB::surrounding_box)
+    }
+
+    /// Returns the spheres with emissive materials.
+    fn emissive_parts(&self) -> Option<Vec<ArcHittable>> {
+        Some(
+            (0..self.len)
+                .filter(|&i| self.material(i).is_emissive())
+                .map(|i| Sphere::new(self.center(i), self.radius[i], Arc::clone(self.material(i))))
+                .collect(),
+        )
+    }
+
+    /// Returns a description of the spheres in the scene file format. The
+    /// batch is described as a group that the bounding volume hierarchy
+    /// splices into its own objects.
+    fn describe(&self) -> Table {
+        let spheres = (0..self.len)
+            .map(|i| {
+                let mut t = Table::new();
+                t.insert("type".into(), "sphere".into());
+                t.insert("center".into(), self.center(i).into());
+                t.insert("radius".into(), self.radius[i].into());
+                t.insert("material".into(), self.material(i).describe().into());
+                t
+            })
+            .collect();
+        describe_group(spheres, true)
+    }
+}
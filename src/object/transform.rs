@@ -0,0 +1,172 @@
+//! # Transform
+//!
+//! A library for handling ray intersections with objects transformed by an
+//! affine matrix, e.g. any combination of translation, rotation, scaling and
+//! shearing.
+
+use super::{
+    describe_transform, wrap_emissive_leaves, ArcHittable, Float, HitRecord, Hittable, Mat4,
+    Point3, Ray, Table, Vec3, AABB, INFINITY,
+};
+use std::fmt;
+use std::sync::Arc;
+
+/// Models an object transformed by an affine matrix.
+#[derive(Debug, Clone)]
+pub struct Transform {
+    /// Holds a `Hittable`.
+    object: ArcHittable,
+
+    /// Object to world transformation.
+    matrix: Mat4,
+
+    /// World to object transformation.
+    inverse: Mat4,
+
+    /// Absolute value of the determinant of the linear part of `matrix`.
+    det: Float,
+}
+
+impl fmt::Display for Transform {
+    /// Display the transformation parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "transform(object: {}, matrix: {})",
+            self.object, self.matrix
+        )
+    }
+}
+
+impl Transform {
+    /// Create a new transformed object. Transforming a transformed object
+    /// is fine but combining the matrices first avoids the extra wrapper.
+    ///
+    /// Notes:
+    /// * This function will panic if the matrix is singular.
+    ///
+    /// * `object`: Holds a `Hittable`.
+    /// * `matrix`: Object to world transformation.
+    pub fn new(object: ArcHittable, matrix: Mat4) -> ArcHittable {
+        match matrix.inverse() {
+            Some(inverse) => Arc::new(Transform {
+                object: Arc::clone(&object),
+                matrix,
+                inverse,
+                det: matrix.determinant3().abs(),
+            }),
+            None => panic!("Singular matrix for transformed object {}", matrix),
+        }
+    }
+}
+
+impl Hittable for Transform {
+    /// Calculate the intersection of a ray with the objects.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `t_min` - The minium parameter for intersections.
+    /// * `t_max` - The maximum parameter for intersections.
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        // Transform the ray into the coordinate frame of the object. The
+        // direction isn't normalized so the ray parameter is unchanged.
+        let o = self.inverse.transform_point(ray.origin);
+        let d = self.inverse.transform_vector(ray.direction);
+        let local_r = Ray::new(o, d, ray.time);
+
+        self.object.hit(&local_r, t_min, t_max).map(|rec| {
+            // The normals face the ray so recover the outward normals before
+            // transforming them out of the coordinate frame of the object.
+            let sign = if rec.front_face { 1.0 } else { -1.0 };
+            let n = self.inverse.transform_normal(rec.normal * sign);
+            let g = self.inverse.transform_normal(rec.geometric_normal * sign);
+            let p = self.matrix.transform_point(rec.point);
+            let vel = self.matrix.transform_vector(rec.velocity);
+
+            rec.update_point(p)
+                .update_normal(ray, n.unit_vector(), g.unit_vector())
+                .update_velocity(vel)
+        })
+    }
+
+    /// Create a bounding box across time interval `[t0, t1]`.
+    ///
+    /// * `time0` - Start time of motion.
+    /// * `time1` - End time of motion.
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
+        let bbox = self.object.bounding_box(time0, time1)?;
+
+        let mut min = [INFINITY; 3];
+        let mut max = [-INFINITY; 3];
+        for corner in 0..8 {
+            let pick = |bit: usize, axis: usize| {
+                if corner & bit == 0 {
+                    bbox.min[axis]
+                } else {
+                    bbox.max[axis]
+                }
+            };
+            let p = Point3::new(pick(1, 0), pick(2, 1), pick(4, 2));
+            let q = self.matrix.transform_point(p);
+            for c in 0..3 {
+                min[c] = min[c].min(q[c]);
+                max[c] = max[c].max(q[c]);
+            }
+        }
+
+        Some(AABB::new(Point3::from_array(min), Point3::from_array(max)))
+    }
+
+    /// Returns true if the object emits light.
+    fn is_emissive(&self) -> bool {
+        self.object.is_emissive()
+    }
+
+    /// Returns the emissive parts of the object, each with the same
+    /// transform.
+    fn emissive_parts(&self) -> Option<Vec<ArcHittable>> {
+        wrap_emissive_leaves(&self.object, |o| {
+            Arc::new(Transform {
+                object: o,
+                matrix: self.matrix,
+                inverse: self.inverse,
+                det: self.det,
+            })
+        })
+    }
+
+    /// Sample PDF value at hit point and given direction. The solid angle
+    /// density is scaled by the change in solid angle of the transformation
+    /// of directions.
+    ///
+    /// * `origin` - Hit point.
+    /// * `v` - Direction to sample.
+    fn pdf_value(&self, origin: Point3, v: Vec3) -> Float {
+        let w = self.inverse.transform_vector(v).unit_vector();
+        let pdf = self
+            .object
+            .pdf_value(self.inverse.transform_point(origin), w);
+
+        // A unit direction w maps to A w / |A w| with Jacobian det(A) / |A w|^3.
+        let aw = self.matrix.transform_vector(w).length();
+        pdf * aw * aw * aw / self.det
+    }
+
+    /// Generate a random direction towards this object.
+    ///
+    /// * `origin` - Hit point.
+    fn random(&self, origin: Point3) -> Vec3 {
+        let v = self.object.random(self.inverse.transform_point(origin));
+        self.matrix.transform_vector(v)
+    }
+
+    /// Returns a description of the object in the scene file format. The
+    /// identity transformation is left out like the scene loader does.
+    fn describe(&self) -> Table {
+        if self.matrix == Mat4::IDENTITY {
+            return self.object.describe();
+        }
+        describe_transform(&self.object, "matrix", self.matrix.into())
+    }
+}
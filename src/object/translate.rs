@@ -2,7 +2,10 @@
 //!
 //! A library for handling ray intersections with translated objects.
 
-use super::{ArcHittable, Float, HitRecord, Hittable, Point3, Ray, Vec3, AABB};
+use super::{
+    describe_transform, wrap_emissive_leaves, ArcHittable, Float, HitRecord, Hittable, Point3, Ray,
+    Table, Vec3, AABB,
+};
 use std::fmt;
 use std::sync::Arc;
 
@@ -51,10 +54,11 @@ impl Hittable for Translate {
     fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
         let moved_r = Ray::new(ray.origin - self.offset, ray.direction, ray.time);
         if let Some(rec) = self.object.hit(&moved_r, t_min, t_max) {
-            Some(
-                rec.update_point(rec.point + self.offset)
-                    .update_normal(&moved_r, rec.normal),
-            )
+            Some(rec.update_point(rec.point + self.offset).update_normal(
+                &moved_r,
+                rec.normal,
+                rec.geometric_normal,
+            ))
         } else {
             None
         }
@@ -72,6 +76,17 @@ impl Hittable for Translate {
         }
     }
 
+    /// Returns true if the object emits light.
+    fn is_emissive(&self) -> bool {
+        self.object.is_emissive()
+    }
+
+    /// Returns the emissive parts of the object, each with the same
+    /// transform.
+    fn emissive_parts(&self) -> Option<Vec<ArcHittable>> {
+        wrap_emissive_leaves(&self.object, |o| Translate::new(o, self.offset))
+    }
+
     /// Sample PDF value at hit point and given direction.
     ///
     /// * `origin` - Hit point.
@@ -86,4 +101,21 @@ impl Hittable for Translate {
     fn random(&self, origin: Point3) -> Vec3 {
         self.object.random(origin - self.offset)
     }
+
+    /// Returns the surface area sampled by `sample_area()`.
+    fn area(&self) -> Float {
+        self.object.area()
+    }
+
+    /// Returns a point picked uniformly over the surface of the object and
+    /// the outward normal there.
+    fn sample_area(&self) -> Option<(Point3, Vec3)> {
+        let (p, normal) = self.object.sample_area()?;
+        Some((p + self.offset, normal))
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        describe_transform(&self.object, "translate", self.offset.into())
+    }
 }
@@ -0,0 +1,284 @@
+//! # Triangle
+//!
+//! A library for handling ray intersections with triangles.
+
+use super::{
+    ArcHittable, ArcMaterial, Float, HitRecord, Hittable, Point3, Ray, Sampler, Table, Value, Vec3,
+    AABB, INFINITY, MIN_THICKNESS, RAY_EPSILON,
+};
+use std::fmt;
+use std::sync::Arc;
+
+/// Models a triangle with per-vertex normals and texture coordinates.
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    /// Vertex positions in counter-clockwise order.
+    vertices: [Point3; 3],
+
+    /// Vertex normals used for smooth shading. If not provided the geometric
+    /// normal is used.
+    normals: Option<[Vec3; 3]>,
+
+    /// Vertex texture coordinates.
+    uvs: [(Float, Float); 3],
+
+    /// Edge from vertex 0 to vertex 1.
+    edge1: Vec3,
+
+    /// Edge from vertex 0 to vertex 2.
+    edge2: Vec3,
+
+    /// Geometric normal (unit vector) based on counter-clockwise winding.
+    normal: Vec3,
+
+    /// Area
+    area: Float,
+
+    /// Surface material.
+    material: ArcMaterial,
+}
+
+impl fmt::Display for Triangle {
+    /// Display the triangle parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "triangle(v0: {}, v1: {}, v2: {}, area: {}, material: {})",
+            self.vertices[0], self.vertices[1], self.vertices[2], self.area, self.material
+        )
+    }
+}
+
+impl Triangle {
+    /// Create a new flat shaded triangle. The texture coordinates are the
+    /// barycentric coordinates `(0, 0)`, `(1, 0)` and `(0, 1)` at the vertices.
+    /// Returns `None` if the triangle has no area.
+    ///
+    /// * `v0` - First vertex.
+    /// * `v1` - Second vertex.
+    /// * `v2` - Third vertex.
+    /// * `material` - Surface material.
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, material: ArcMaterial) -> Option<ArcHittable> {
+        Triangle::with_attributes(
+            [v0, v1, v2],
+            None,
+            [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            material,
+        )
+    }
+
+    /// Create a new triangle with vertex attributes. Returns `None` if the
+    /// triangle has no area since it has no normal and can't be hit or
+    /// sampled.
+    ///
+    /// * `vertices` - Vertex positions in counter-clockwise order.
+    /// * `normals` - Optional vertex normals for smooth shading.
+    /// * `uvs` - Vertex texture coordinates.
+    /// * `material` - Surface material.
+    pub fn with_attributes(
+        vertices: [Point3; 3],
+        normals: Option<[Vec3; 3]>,
+        uvs: [(Float, Float); 3],
+        material: ArcMaterial,
+    ) -> Option<ArcHittable> {
+        let area = Triangle::area_of(&vertices);
+        if !(area.is_finite() && area > 0.0) {
+            return None;
+        }
+
+        let edge1 = vertices[1] - vertices[0];
+        let edge2 = vertices[2] - vertices[0];
+        let n = edge1.cross(edge2);
+
+        Some(Arc::new(Triangle {
+            vertices,
+            normals,
+            uvs,
+            edge1,
+            edge2,
+            normal: n.unit_vector(),
+            area,
+            material: Arc::clone(&material),
+        }))
+    }
+
+    /// Returns the area of a triangle. It is 0 if the vertices are collinear
+    /// and NaN or infinite if they aren't finite.
+    ///
+    /// * `vertices` - Vertex positions.
+    pub fn area_of(vertices: &[Point3; 3]) -> Float {
+        0.5 * (vertices[1] - vertices[0])
+            .cross(vertices[2] - vertices[0])
+            .length()
+    }
+}
+
+impl Hittable for Triangle {
+    /// Calculate the intersection of a ray with the object using the
+    /// Möller-Trumbore algorithm.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `t_min` - The minium parameter for intersections.
+    /// * `t_max` - The maximum parameter for intersections.
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        let p = ray.direction.cross(self.edge2);
+        let det = self.edge1.dot(p);
+        if det.abs() < Float::EPSILON {
+            return None; // Ray is parallel to the triangle.
+        }
+
+        let inv_det = 1.0 / det;
+
+        let s = ray.origin - self.vertices[0];
+        let b1 = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let q = s.cross(self.edge1);
+        let b2 = ray.direction.dot(q) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = self.edge2.dot(q) * inv_det;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        // Interpolate vertex attributes with barycentric coordinates.
+        let b0 = 1.0 - b1 - b2;
+
+        let u = self.uvs[0].0 * b0 + self.uvs[1].0 * b1 + self.uvs[2].0 * b2;
+        let v = self.uvs[0].1 * b0 + self.uvs[1].1 * b1 + self.uvs[2].1 * b2;
+
+        let rec = HitRecord::new(
+            ray,
+            t,
+            ray.at(t),
+            self.normal,
+            Arc::clone(&self.material),
+            u,
+            v,
+        );
+
+        Some(match self.normals {
+            Some(n) => rec.update_shading_normal((n[0] * b0 + n[1] * b1 + n[2] * b2).unit_vector()),
+            None => rec,
+        })
+    }
+
+    /// Create a bounding box across time interval `[t0, t1]`.
+    ///
+    /// * `_time0` - Start time of motion (ignored).
+    /// * `_time1` - End time of motion (ignored).
+    fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
+        let [v0, v1, v2] = self.vertices;
+
+        // The bounding box must have non-zero width in each dimension, so pad
+        // all dimensions a small amount for axis aligned triangles.
+        let pad = Vec3::new(MIN_THICKNESS, MIN_THICKNESS, MIN_THICKNESS);
+
+        Some(AABB::new(
+            Point3::new(
+                v0.x().min(v1.x()).min(v2.x()),
+                v0.y().min(v1.y()).min(v2.y()),
+                v0.z().min(v1.z()).min(v2.z()),
+            ) - pad,
+            Point3::new(
+                v0.x().max(v1.x()).max(v2.x()),
+                v0.y().max(v1.y()).max(v2.y()),
+                v0.z().max(v1.z()).max(v2.z()),
+            ) + pad,
+        ))
+    }
+
+    /// Returns true if the material of the object emits light.
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    /// Sample PDF value at hit point and given direction.
+    ///
+    /// * `origin` - Hit point.
+    /// * `v` - Direction to sample.
+    fn pdf_value(&self, origin: Point3, v: Vec3) -> Float {
+        if self.area <= 0.0 {
+            return 0.0;
+        }
+
+        let ray = Ray::new(origin, v, 0.0);
+        if let Some(rec) = self.hit(&ray, RAY_EPSILON, INFINITY) {
+            // Distance to hit is rec.t * v.length().
+            // So distance^2 = rec.t * rec.t * v.length_squared().
+            let v_len_sq = v.length_squared();
+            let v_len = v_len_sq.sqrt();
+            let v_unit = v / v_len;
+
+            // Use the geometric normal since the area measure is based on it.
+            let distance_squared = rec.t * rec.t * v_len_sq;
+            let cosine = v_unit.dot(self.normal).abs();
+
+            distance_squared / (cosine * self.area)
+        } else {
+            0.0
+        }
+    }
+
+    /// Generate a random direction towards this object.
+    ///
+    /// * `origin` - Hit point.
+    fn random(&self, origin: Point3) -> Vec3 {
+        // Uniformly sample barycentric coordinates.
+        let (s, t) = Sampler::get_2d();
+        let r = s.sqrt();
+        let b1 = r * (1.0 - t);
+        let b2 = r * t;
+
+        let random_point = self.vertices[0] + self.edge1 * b1 + self.edge2 * b2;
+        random_point - origin
+    }
+
+    /// Returns the surface area sampled by `sample_area()`.
+    fn area(&self) -> Float {
+        self.area
+    }
+
+    /// Returns a point picked uniformly over the surface of the object and
+    /// the outward normal there.
+    fn sample_area(&self) -> Option<(Point3, Vec3)> {
+        let (s, t) = Sampler::get_2d();
+        let r = s.sqrt();
+        let b1 = r * (1.0 - t);
+        let b2 = r * t;
+        Some((
+            self.vertices[0] + self.edge1 * b1 + self.edge2 * b2,
+            self.normal,
+        ))
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        let vec3_array = |a: &[Vec3; 3]| Value::Array(a.iter().map(|v| (*v).into()).collect());
+
+        let mut t = Table::new();
+        t.insert("type".into(), "triangle".into());
+        t.insert("vertices".into(), vec3_array(&self.vertices));
+        if let Some(normals) = &self.normals {
+            t.insert("normals".into(), vec3_array(normals));
+        }
+        t.insert(
+            "uvs".into(),
+            Value::Array(
+                self.uvs
+                    .iter()
+                    .map(|(u, v)| Value::Array(vec![(*u).into(), (*v).into()]))
+                    .collect(),
+            ),
+        );
+        t.insert("material".into(), self.material.describe().into());
+        t
+    }
+}
@@ -0,0 +1,225 @@
+//! # UvMap
+//!
+//! A library for replacing the texture coordinates of an object with ones
+//! projected from its bounding box so image textures can be applied to
+//! objects without their own parameterisation, e.g. boxes and meshes.
+
+use super::{
+    describe_transform, get_sphere_uv, wrap_emissive_leaves, ArcHittable, Float, HitRecord,
+    Hittable, Point3, Ray, Table, Vec3, AABB, PI, TWO_PI,
+};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Projections used to calculate texture coordinates.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum UvMapping {
+    /// Longitude and latitude around the centre of the bounding box.
+    Spherical,
+
+    /// Angle around and height along the y-axis through the centre of the
+    /// bounding box.
+    Cylindrical,
+
+    /// Projection along the z-axis onto the bounding box.
+    Planar,
+
+    /// Projection onto the face of the bounding box the surface faces most.
+    Box,
+}
+
+impl fmt::Display for UvMapping {
+    /// Display the mapping name as used in scene files.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            UvMapping::Spherical => "spherical",
+            UvMapping::Cylindrical => "cylindrical",
+            UvMapping::Planar => "planar",
+            UvMapping::Box => "box",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for UvMapping {
+    type Err = String;
+
+    /// Parse the mapping name ignoring case.
+    ///
+    /// * `s` - The name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "spherical" => Ok(UvMapping::Spherical),
+            "cylindrical" => Ok(UvMapping::Cylindrical),
+            "planar" => Ok(UvMapping::Planar),
+            "box" => Ok(UvMapping::Box),
+            _ => Err(format!("unknown mapping `{}`", s)),
+        }
+    }
+}
+
+/// Models an object with projected texture coordinates.
+#[derive(Debug, Clone)]
+pub struct UvMap {
+    /// Object whose texture coordinates are replaced.
+    object: ArcHittable,
+
+    /// Projection used to calculate texture coordinates.
+    mapping: UvMapping,
+
+    /// Bounding box the projection is fitted to.
+    bbox: AABB,
+}
+
+impl fmt::Display for UvMap {
+    /// Display the mapping parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "uv_map(object: {}, mapping: {}, bbox: {})",
+            self.object, self.mapping, self.bbox
+        )
+    }
+}
+
+impl UvMap {
+    /// Create a new object with projected texture coordinates. Returns
+    /// `None` if the object doesn't have a bounding box to fit the
+    /// projection to.
+    ///
+    /// * `object` - Object whose texture coordinates are replaced.
+    /// * `mapping` - Projection used to calculate texture coordinates.
+    pub fn new(object: ArcHittable, mapping: UvMapping) -> Option<ArcHittable> {
+        // Motion is not supported. So (0.0, 1.0) ok.
+        let bbox = object.bounding_box(0.0, 1.0)?;
+
+        Some(Arc::new(UvMap {
+            object: Arc::clone(&object),
+            mapping,
+            bbox,
+        }))
+    }
+
+    /// Calculate the texture coordinates of a point on the object.
+    ///
+    /// * `p` - The point.
+    /// * `normal` - Surface normal at the point.
+    fn uv(&self, p: Point3, normal: Vec3) -> (Float, Float) {
+        // Fraction of the way across the bounding box along an axis.
+        let fraction = |axis: usize| {
+            let extent = self.bbox.max[axis] - self.bbox.min[axis];
+            if extent > 0.0 {
+                (p[axis] - self.bbox.min[axis]) / extent
+            } else {
+                0.5
+            }
+        };
+
+        let centre = (self.bbox.min + self.bbox.max) * 0.5;
+
+        match self.mapping {
+            UvMapping::Spherical => get_sphere_uv(&(p - centre).unit_vector()),
+
+            UvMapping::Cylindrical => {
+                let d = p - centre;
+                let phi = d.z().atan2(d.x());
+                (1.0 - (phi + PI) / TWO_PI, fraction(1))
+            }
+
+            UvMapping::Planar => (fraction(0), fraction(1)),
+
+            UvMapping::Box => {
+                let (x, y, z) = (normal.x().abs(), normal.y().abs(), normal.z().abs());
+                if x >= y && x >= z {
+                    (fraction(2), fraction(1))
+                } else if y >= z {
+                    (fraction(0), fraction(2))
+                } else {
+                    (fraction(0), fraction(1))
+                }
+            }
+        }
+    }
+}
+
+impl Hittable for UvMap {
+    /// Calculate the intersection of a ray with the object.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `t_min` - The minium parameter for intersections.
+    /// * `t_max` - The maximum parameter for intersections.
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        self.object.hit(ray, t_min, t_max).map(|mut rec| {
+            let (u, v) = self.uv(rec.point, rec.normal);
+            rec.u = u;
+            rec.v = v;
+            rec
+        })
+    }
+
+    /// Create a bounding box across time interval `[t0, t1]`.
+    ///
+    /// * `time0` - Start time of motion.
+    /// * `time1` - End time of motion.
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
+        self.object.bounding_box(time0, time1)
+    }
+
+    /// Returns true if the object emits light.
+    fn is_emissive(&self) -> bool {
+        self.object.is_emissive()
+    }
+
+    /// Returns the emissive parts of the object, each with the same
+    /// mapping.
+    fn emissive_parts(&self) -> Option<Vec<ArcHittable>> {
+        wrap_emissive_leaves(&self.object, |o| {
+            // Keep the bounding box of the whole object so the texture
+            // coordinates don't change.
+            Arc::new(UvMap {
+                object: o,
+                mapping: self.mapping,
+                bbox: self.bbox,
+            })
+        })
+    }
+
+    /// Sample PDF value at hit point and given direction.
+    ///
+    /// * `origin` - Hit point.
+    /// * `v` - Direction to sample.
+    fn pdf_value(&self, origin: Point3, v: Vec3) -> Float {
+        self.object.pdf_value(origin, v)
+    }
+
+    /// Generate a random direction towards this object.
+    ///
+    /// * `origin` - Hit point.
+    fn random(&self, origin: Point3) -> Vec3 {
+        self.object.random(origin)
+    }
+
+    /// Returns the surface area sampled by `sample_area()`.
+    fn area(&self) -> Float {
+        self.object.area()
+    }
+
+    /// Returns a point picked uniformly over the surface of the object and
+    /// the outward normal there.
+    fn sample_area(&self) -> Option<(Point3, Vec3)> {
+        self.object.sample_area()
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        describe_transform(&self.object, "uv_mapping", self.mapping.to_string().into())
+    }
+}
@@ -0,0 +1,121 @@
+//! # Visibility
+//!
+//! A library for objects that are only visible during parts of the shutter
+//! interval. Rays are tested against the object only when their time falls
+//! in one of the visible intervals, so objects can appear and disappear
+//! between frames without reloading the scene.
+
+use super::{
+    describe_transform, wrap_emissive_leaves, ArcHittable, Float, HitRecord, Hittable, Point3, Ray,
+    Table, Value, Vec3, AABB,
+};
+use std::fmt;
+use std::sync::Arc;
+
+/// Models an object that is visible during some time intervals.
+#[derive(Debug, Clone)]
+pub struct Visibility {
+    /// Holds a `Hittable`.
+    object: ArcHittable,
+
+    /// Time intervals `[start, end]` during which the object is visible.
+    intervals: Vec<(Float, Float)>,
+}
+
+impl fmt::Display for Visibility {
+    /// Display the visibility parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "visibility(object: {}, intervals: {:?})",
+            self.object, self.intervals
+        )
+    }
+}
+
+impl Visibility {
+    /// Create a new object that is visible during some time intervals.
+    ///
+    /// * `object` - Holds a `Hittable`.
+    /// * `intervals` - Time intervals `[start, end]` during which the object
+    ///   is visible.
+    pub fn new(object: ArcHittable, intervals: Vec<(Float, Float)>) -> ArcHittable {
+        Arc::new(Visibility {
+            object: Arc::clone(&object),
+            intervals,
+        })
+    }
+
+    /// Returns true if the object is visible at a given time.
+    ///
+    /// * `time` - The time.
+    fn is_visible(&self, time: Float) -> bool {
+        self.intervals
+            .iter()
+            .any(|(start, end)| time >= *start && time <= *end)
+    }
+}
+
+impl Hittable for Visibility {
+    /// Calculate the intersection of a ray with the object if it is visible
+    /// at the time of the ray.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `t_min` - The minium parameter for intersections.
+    /// * `t_max` - The maximum parameter for intersections.
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        if self.is_visible(ray.time) {
+            self.object.hit(ray, t_min, t_max)
+        } else {
+            None
+        }
+    }
+
+    /// Create a bounding box across time interval `[t0, t1]`. This is the
+    /// bounding box of the object whether or not it is visible since bounding
+    /// volume hierarchies need a box for every object.
+    ///
+    /// * `time0` - Start time of motion.
+    /// * `time1` - End time of motion.
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
+        self.object.bounding_box(time0, time1)
+    }
+
+    /// Returns true if the object emits light.
+    fn is_emissive(&self) -> bool {
+        self.object.is_emissive()
+    }
+
+    /// Returns the emissive parts of the object, each visible at the same
+    /// times.
+    fn emissive_parts(&self) -> Option<Vec<ArcHittable>> {
+        wrap_emissive_leaves(&self.object, |o| Visibility::new(o, self.intervals.clone()))
+    }
+
+    /// Sample PDF value at hit point and given direction.
+    ///
+    /// * `origin` - Hit point.
+    /// * `v` - Direction to sample.
+    fn pdf_value(&self, origin: Point3, v: Vec3) -> Float {
+        self.object.pdf_value(origin, v)
+    }
+
+    /// Generate a random direction towards this object.
+    ///
+    /// * `origin` - Hit point.
+    fn random(&self, origin: Point3) -> Vec3 {
+        self.object.random(origin)
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        let intervals = self
+            .intervals
+            .iter()
+            .map(|(start, end)| Value::Array(vec![(*start).into(), (*end).into()]))
+            .collect();
+        describe_transform(&self.object, "visible", Value::Array(intervals))
+    }
+}
@@ -4,7 +4,7 @@
 //! the xy-plane.
 
 use super::{
-    ArcHittable, ArcMaterial, Float, HitRecord, Hittable, Point3, Random, Ray, Vec3, AABB,
+    ArcHittable, ArcMaterial, Float, HitRecord, Hittable, Point3, Ray, Sampler, Table, Vec3, AABB,
     INFINITY, MIN_THICKNESS, RAY_EPSILON,
 };
 use std::fmt;
@@ -119,6 +119,11 @@ impl Hittable for XYrect {
         ))
     }
 
+    /// Returns true if the material of the object emits light.
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
     /// Sample PDF value at hit point and given direction.
     ///
     /// * `origin` - Hit point.
@@ -145,9 +150,37 @@ impl Hittable for XYrect {
     ///
     /// * `origin` - Hit point.
     fn random(&self, origin: Point3) -> Vec3 {
-        let x = Random::sample_in_range(self.x0, self.x1);
-        let y = Random::sample_in_range(self.y0, self.y1);
+        let (s, t) = Sampler::get_2d();
+        let x = self.x0 + s * (self.x1 - self.x0);
+        let y = self.y0 + t * (self.y1 - self.y0);
         let random_point = Point3::new(x, y, self.z);
         random_point - origin
     }
+
+    /// Returns the surface area sampled by `sample_area()`.
+    fn area(&self) -> Float {
+        self.area
+    }
+
+    /// Returns a point picked uniformly over the surface of the object and
+    /// the outward normal there.
+    fn sample_area(&self) -> Option<(Point3, Vec3)> {
+        let (s, t) = Sampler::get_2d();
+        let x = self.x0 + s * (self.x1 - self.x0);
+        let y = self.y0 + t * (self.y1 - self.y0);
+        Some((Point3::new(x, y, self.z), Vec3::new(0.0, 0.0, 1.0)))
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "xy_rect".into());
+        t.insert("x0".into(), self.x0.into());
+        t.insert("x1".into(), self.x1.into());
+        t.insert("y0".into(), self.y0.into());
+        t.insert("y1".into(), self.y1.into());
+        t.insert("k".into(), self.z.into());
+        t.insert("material".into(), self.material.describe().into());
+        t
+    }
 }
@@ -4,11 +4,73 @@
 
 use super::{
     ArcHittable, ArcMaterial, FlipFace, Float, HitRecord, Hittable, HittableList, Point3, Ray,
-    Vec3, XYrect, XZrect, YZrect, AABB,
+    Table, Value, Vec3, XYrect, XZrect, YZrect, AABB, INFINITY,
 };
 use std::fmt;
 use std::sync::Arc;
 
+/// Names of the faces of a box in the order of the outward normals +x, -x,
+/// +y, -y, +z, -z.
+pub const FACE_NAMES: [&str; 6] = ["right", "left", "top", "bottom", "front", "back"];
+
+/// Models the transform applied to the texture coordinates of a face of a
+/// box. This places the face in a region of an image texture, e.g. packaging
+/// artwork laid out as a net.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FaceUv {
+    /// Scale of the texture coordinates along u and v.
+    pub scale: (Float, Float),
+
+    /// Rotation of the texture coordinates in degrees about the centre of
+    /// the face.
+    pub rotate: Float,
+
+    /// Offset added to the texture coordinates after rotating and scaling.
+    pub offset: (Float, Float),
+}
+
+impl Default for FaceUv {
+    /// Returns the identity transform so the face spans the whole texture.
+    fn default() -> Self {
+        FaceUv {
+            scale: (1.0, 1.0),
+            rotate: 0.0,
+            offset: (0.0, 0.0),
+        }
+    }
+}
+
+impl FaceUv {
+    /// Returns the transformed texture coordinates.
+    ///
+    /// * `u` - Texture coordinate in [0, 1] across the face.
+    /// * `v` - Texture coordinate in [0, 1] across the face.
+    pub fn apply(&self, u: Float, v: Float) -> (Float, Float) {
+        let (sin, cos) = self.rotate.to_radians().sin_cos();
+        let (du, dv) = (u - 0.5, v - 0.5);
+        let (u, v) = (0.5 + du * cos - dv * sin, 0.5 + du * sin + dv * cos);
+        (
+            u * self.scale.0 + self.offset.0,
+            v * self.scale.1 + self.offset.1,
+        )
+    }
+
+    /// Returns a description of the transform in the scene file format.
+    pub fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert(
+            "scale".into(),
+            Value::Array(vec![self.scale.0.into(), self.scale.1.into()]),
+        );
+        t.insert("rotate".into(), self.rotate.into());
+        t.insert(
+            "offset".into(),
+            Value::Array(vec![self.offset.0.into(), self.offset.1.into()]),
+        );
+        t
+    }
+}
+
 /// Models an axis-aligned box.
 /// **NOTE:** XYZbox is used to avoid conflict with Rust's `Box`.
 #[derive(Debug, Clone)]
@@ -21,6 +83,13 @@ pub struct XYZbox {
 
     /// Holds a `HittableList` containing the 6 axis aligned planes.
     sides: ArcHittable,
+
+    /// Surface material.
+    material: ArcMaterial,
+
+    /// Texture coordinate transforms of the faces in the order of
+    /// `FACE_NAMES`. If not set every face spans the whole texture.
+    face_uvs: Option<[FaceUv; 6]>,
 }
 
 impl fmt::Display for XYZbox {
@@ -43,6 +112,37 @@ impl XYZbox {
     /// * `p1` - Holds maximum (x1, y1, z1) coordinates.
     /// * `material` - Surface material.
     pub fn new(p0: Point3, p1: Point3, material: ArcMaterial) -> ArcHittable {
+        Arc::new(XYZbox::build(p0, p1, material, None))
+    }
+
+    /// Create a new axis aligned box with the texture coordinates of each
+    /// face transformed independently.
+    ///
+    /// * `p0` - Holds minimum (x0, y0, z0) coordinates.
+    /// * `p1` - Holds maximum (x1, y1, z1) coordinates.
+    /// * `material` - Surface material.
+    /// * `face_uvs` - Texture coordinate transforms in the order of `FACE_NAMES`.
+    pub fn with_face_uvs(
+        p0: Point3,
+        p1: Point3,
+        material: ArcMaterial,
+        face_uvs: [FaceUv; 6],
+    ) -> ArcHittable {
+        Arc::new(XYZbox::build(p0, p1, material, Some(face_uvs)))
+    }
+
+    /// Build the box and its sides.
+    ///
+    /// * `p0` - Holds minimum (x0, y0, z0) coordinates.
+    /// * `p1` - Holds maximum (x1, y1, z1) coordinates.
+    /// * `material` - Surface material.
+    /// * `face_uvs` - Optional texture coordinate transforms of the faces.
+    fn build(
+        p0: Point3,
+        p1: Point3,
+        material: ArcMaterial,
+        face_uvs: Option<[FaceUv; 6]>,
+    ) -> XYZbox {
         let mut sides = HittableList::new();
 
         sides.add(XYrect::new(
@@ -96,11 +196,35 @@ impl XYZbox {
             Arc::clone(&material),
         )));
 
-        Arc::new(XYZbox {
+        XYZbox {
             box_min: p0,
             box_max: p1,
             sides: Arc::new(sides),
-        })
+            material,
+            face_uvs,
+        }
+    }
+
+    /// Returns the index into `FACE_NAMES` of the face nearest a point on
+    /// the box.
+    ///
+    /// * `p` - Point on the box.
+    fn face(&self, p: Point3) -> usize {
+        let mut face = 0;
+        let mut nearest = INFINITY;
+        for axis in 0..3 {
+            let d_max = (p[axis] - self.box_max[axis]).abs();
+            let d_min = (p[axis] - self.box_min[axis]).abs();
+            if d_max < nearest {
+                nearest = d_max;
+                face = 2 * axis;
+            }
+            if d_min < nearest {
+                nearest = d_min;
+                face = 2 * axis + 1;
+            }
+        }
+        face
     }
 }
 
@@ -111,7 +235,17 @@ impl Hittable for XYZbox {
     /// * `t_min` - The minium parameter for intersections.
     /// * `t_max` - The maximum parameter for intersections.
     fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
-        self.sides.hit(ray, t_min, t_max)
+        let rec = self.sides.hit(ray, t_min, t_max)?;
+        match &self.face_uvs {
+            Some(face_uvs) => {
+                let mut rec = rec;
+                let (u, v) = face_uvs[self.face(rec.point)].apply(rec.u, rec.v);
+                rec.u = u;
+                rec.v = v;
+                Some(rec)
+            }
+            None => Some(rec),
+        }
     }
 
     /// Create a bounding box across time interval `[t0, t1]`.
@@ -122,6 +256,11 @@ impl Hittable for XYZbox {
         Some(AABB::new(self.box_min, self.box_max))
     }
 
+    /// Returns true if the object emits light.
+    fn is_emissive(&self) -> bool {
+        self.sides.is_emissive()
+    }
+
     /// Sample PDF value at hit point and given direction.
     ///
     /// * `origin` - Hit point.
@@ -136,4 +275,34 @@ impl Hittable for XYZbox {
     fn random(&self, origin: Point3) -> Vec3 {
         self.sides.random(origin)
     }
+
+    /// Returns the surface area sampled by `sample_area()`.
+    fn area(&self) -> Float {
+        self.sides.area()
+    }
+
+    /// Returns a point picked uniformly over the surface of the object and
+    /// the outward normal there.
+    fn sample_area(&self) -> Option<(Point3, Vec3)> {
+        self.sides.sample_area()
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "box".into());
+        t.insert("min".into(), self.box_min.into());
+        t.insert("max".into(), self.box_max.into());
+        t.insert("material".into(), self.material.describe().into());
+        if let Some(face_uvs) = &self.face_uvs {
+            let mut faces = Table::new();
+            for (name, face_uv) in FACE_NAMES.iter().zip(face_uvs.iter()) {
+                if *face_uv != FaceUv::default() {
+                    faces.insert((*name).into(), face_uv.describe().into());
+                }
+            }
+            t.insert("face_uv".into(), faces.into());
+        }
+        t
+    }
 }
@@ -4,7 +4,7 @@
 //! the yz-plane.
 
 use super::{
-    ArcHittable, ArcMaterial, Float, HitRecord, Hittable, Point3, Random, Ray, Vec3, AABB,
+    ArcHittable, ArcMaterial, Float, HitRecord, Hittable, Point3, Ray, Sampler, Table, Vec3, AABB,
     INFINITY, MIN_THICKNESS, RAY_EPSILON,
 };
 use std::fmt;
@@ -119,6 +119,11 @@ impl Hittable for YZrect {
         ))
     }
 
+    /// Returns true if the material of the object emits light.
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
     /// Sample PDF value at hit point and given direction.
     ///
     /// * `origin` - Hit point.
@@ -145,9 +150,37 @@ impl Hittable for YZrect {
     ///
     /// * `origin` - Hit point.
     fn random(&self, origin: Point3) -> Vec3 {
-        let y = Random::sample_in_range(self.y0, self.y1);
-        let z = Random::sample_in_range(self.z0, self.z1);
+        let (s, t) = Sampler::get_2d();
+        let y = self.y0 + s * (self.y1 - self.y0);
+        let z = self.z0 + t * (self.z1 - self.z0);
         let random_point = Point3::new(self.x, y, z);
         random_point - origin
     }
+
+    /// Returns the surface area sampled by `sample_area()`.
+    fn area(&self) -> Float {
+        self.area
+    }
+
+    /// Returns a point picked uniformly over the surface of the object and
+    /// the outward normal there.
+    fn sample_area(&self) -> Option<(Point3, Vec3)> {
+        let (s, t) = Sampler::get_2d();
+        let y = self.y0 + s * (self.y1 - self.y0);
+        let z = self.z0 + t * (self.z1 - self.z0);
+        Some((Point3::new(self.x, y, z), Vec3::new(1.0, 0.0, 0.0)))
+    }
+
+    /// Returns a description of the object in the scene file format.
+    fn describe(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("type".into(), "yz_rect".into());
+        t.insert("y0".into(), self.y0.into());
+        t.insert("y1".into(), self.y1.into());
+        t.insert("z0".into(), self.z0.into());
+        t.insert("z1".into(), self.z1.into());
+        t.insert("k".into(), self.x.into());
+        t.insert("material".into(), self.material.describe().into());
+        t
+    }
 }
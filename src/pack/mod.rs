@@ -0,0 +1,181 @@
+//! # Pack
+//!
+//! A library for bundling a scene file with the assets it references, e.g.
+//! image textures, environment maps and included PBRT files, into a zip
+//! archive for sharing reproducible scenes.
+//!
+//! The scene is loaded to find its assets. The scene file is stored at the
+//! root of the archive and assets referenced by plain relative paths keep
+//! them, including ones found in the asset search paths, so they resolve
+//! next to the extracted scene file. Assets referenced by absolute paths or
+//! paths outside the scene's directory are stored in `assets/` and the
+//! references in the scene file and included PBRT files are rewritten.
+
+mod zip;
+
+use self::zip::ZipWriter;
+use super::scene::{Assets, LoadContext, Scene};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::BufWriter;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// Summary of a packed scene.
+#[derive(Debug, Clone)]
+pub struct PackReport {
+    /// Names of the files in the archive.
+    pub files: Vec<String>,
+
+    /// Total size of the files before compression.
+    pub bytes: u64,
+}
+
+/// Bundle a scene file and its assets into a zip archive.
+///
+/// * `scene_file` - Path to the scene file.
+/// * `output` - Path of the zip archive.
+/// * `search_paths` - Directories searched for assets that aren't next to
+///   the scene file.
+pub fn pack_scene(
+    scene_file: &str,
+    output: &str,
+    search_paths: &[PathBuf],
+) -> Result<PackReport, String> {
+    // Load the scene to find its assets. The image size doesn't matter for
+    // finding them.
+    let context = LoadContext {
+        assets: Arc::new(Assets::new(search_paths.to_vec())),
+        ..LoadContext::default()
+    };
+    Scene::from_file(scene_file, 1, 1, false, &context)?;
+    let resolved = context.assets.take_resolved();
+
+    // Decide where each asset goes in the archive.
+    let mut names: HashSet<String> = HashSet::new();
+    let mut placed: HashMap<PathBuf, String> = HashMap::new();
+    let mut assets: Vec<(String, PathBuf)> = Vec::new();
+    let mut rewrites: Vec<(String, String)> = Vec::new();
+
+    for (reference, path) in resolved {
+        if !path.is_file() {
+            return Err(format!(
+                "{}: asset {} not found",
+                scene_file,
+                path.display()
+            ));
+        }
+
+        // The same asset can be referenced more than once and by different
+        // paths.
+        if let Some(name) = placed.get(&path) {
+            if archive_name(&reference).as_ref() != Some(name) {
+                rewrites.push((reference, name.clone()));
+            }
+            continue;
+        }
+
+        let name = match archive_name(&reference) {
+            Some(name) if !names.contains(&name) => name,
+            _ => {
+                let file_name = path
+                    .file_name()
+                    .map_or_else(|| "asset".to_string(), |f| f.to_string_lossy().to_string());
+                let name = (0..)
+                    .map(|i| match i {
+                        0 => format!("assets/{}", file_name),
+                        i => format!("assets/{}_{}", i, file_name),
+                    })
+                    .find(|n| !names.contains(n))
+                    .unwrap();
+                rewrites.push((reference.clone(), name.clone()));
+                name
+            }
+        };
+
+        names.insert(name.clone());
+        placed.insert(path.clone(), name.clone());
+        assets.push((name, path));
+    }
+
+    let scene_name = Path::new(scene_file)
+        .file_name()
+        .map_or_else(|| "scene".to_string(), |f| f.to_string_lossy().to_string());
+    if names.contains(&scene_name) {
+        return Err(format!("{}: an asset has the same name", scene_file));
+    }
+
+    let file =
+        fs::File::create(output).map_err(|e| format!("Unable to create {}: {}", output, e))?;
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+    let mut report = PackReport {
+        files: Vec::new(),
+        bytes: 0,
+    };
+
+    let mut add = |name: &str, path: &Path, rewrite: bool| -> Result<(), String> {
+        let mut data =
+            fs::read(path).map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        if rewrite {
+            data = rewrite_references(data, &rewrites);
+        }
+        zip.add(name, &data)
+            .map_err(|e| format!("Unable to write {}: {}", output, e))?;
+        report.files.push(name.to_string());
+        report.bytes += data.len() as u64;
+        Ok(())
+    };
+
+    add(&scene_name, Path::new(scene_file), true)?;
+    for (name, path) in assets.iter() {
+        let included = path.extension().is_some_and(|e| e == "pbrt");
+        add(name, path, included)?;
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Unable to write {}: {}", output, e))?;
+    Ok(report)
+}
+
+/// Returns the name of an asset in the archive if it is referenced by a
+/// relative path that stays inside the scene's directory.
+///
+/// * `reference` - The path the asset is referenced by.
+fn archive_name(reference: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in Path::new(reference).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?.to_string()),
+            Component::CurDir => (),
+            _ => return None,
+        }
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("/"))
+    }
+}
+
+/// Replace quoted references to assets that moved in the archive.
+///
+/// * `data` - Contents of a scene file.
+/// * `rewrites` - Pairs of old and new references.
+fn rewrite_references(data: Vec<u8>, rewrites: &[(String, String)]) -> Vec<u8> {
+    let mut text = match String::from_utf8(data) {
+        Ok(text) => text,
+        Err(e) => return e.into_bytes(),
+    };
+
+    for (old, new) in rewrites.iter() {
+        for quote in ['"', '\''] {
+            text = text.replace(
+                &format!("{}{}{}", quote, old, quote),
+                &format!("{}{}{}", quote, new, quote),
+            );
+        }
+    }
+
+    text.into_bytes()
+}
@@ -0,0 +1,174 @@
+//! # Zip
+//!
+//! A minimal writer for zip archives. Files are deflated unless that makes
+//! them larger, in which case they are stored. Timestamps are fixed so the
+//! same files always produce the same archive. Archives larger than 4 GiB
+//! or with more than 65535 files (ZIP64) are not supported.
+
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::convert::TryFrom;
+use std::io::{self, Write};
+
+/// Compression method for stored files.
+const METHOD_STORED: u16 = 0;
+
+/// Compression method for deflated files.
+const METHOD_DEFLATED: u16 = 8;
+
+/// Version needed to extract deflated files (2.0).
+const VERSION: u16 = 20;
+
+/// MS-DOS date of 1980-01-01, the earliest date zip archives can record.
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+/// Central directory record of a file.
+struct Entry {
+    /// Name of the file in the archive.
+    name: String,
+
+    /// CRC-32 of the uncompressed data.
+    crc: u32,
+
+    /// Compression method.
+    method: u16,
+
+    /// Size of the compressed data.
+    compressed_size: u32,
+
+    /// Size of the uncompressed data.
+    size: u32,
+
+    /// Offset of the local file header.
+    offset: u32,
+}
+
+/// Writes files to a zip archive.
+pub struct ZipWriter<W: Write> {
+    /// Output stream.
+    out: W,
+
+    /// Files written so far.
+    entries: Vec<Entry>,
+
+    /// Number of bytes written so far.
+    offset: u64,
+}
+
+impl<W: Write> ZipWriter<W> {
+    /// Create a new zip writer.
+    ///
+    /// * `out` - Output stream.
+    pub fn new(out: W) -> ZipWriter<W> {
+        ZipWriter {
+            out,
+            entries: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    /// Add a file to the archive.
+    ///
+    /// * `name` - Name of the file in the archive using `/` separators.
+    /// * `data` - Contents of the file.
+    pub fn add(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let deflated = encoder.finish()?;
+
+        let (method, contents) = if deflated.len() < data.len() {
+            (METHOD_DEFLATED, deflated.as_slice())
+        } else {
+            (METHOD_STORED, data)
+        };
+
+        let entry = Entry {
+            name: name.to_string(),
+            crc: crc32fast::hash(data),
+            method,
+            compressed_size: to_u32(contents.len() as u64)?,
+            size: to_u32(data.len() as u64)?,
+            offset: to_u32(self.offset)?,
+        };
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&0x04034b50_u32.to_le_bytes());
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        header.extend_from_slice(&0_u16.to_le_bytes()); // flags
+        header.extend_from_slice(&entry.method.to_le_bytes());
+        header.extend_from_slice(&0_u16.to_le_bytes()); // time
+        header.extend_from_slice(&DOS_DATE.to_le_bytes());
+        header.extend_from_slice(&entry.crc.to_le_bytes());
+        header.extend_from_slice(&entry.compressed_size.to_le_bytes());
+        header.extend_from_slice(&entry.size.to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0_u16.to_le_bytes()); // extra field length
+        header.extend_from_slice(name.as_bytes());
+
+        self.write(&header)?;
+        self.write(contents)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Write the central directory and return the output stream.
+    pub fn finish(mut self) -> io::Result<W> {
+        let start = to_u32(self.offset)?;
+
+        let mut directory = Vec::new();
+        for entry in self.entries.iter() {
+            directory.extend_from_slice(&0x02014b50_u32.to_le_bytes());
+            directory.extend_from_slice(&VERSION.to_le_bytes()); // made by
+            directory.extend_from_slice(&VERSION.to_le_bytes()); // needed
+            directory.extend_from_slice(&0_u16.to_le_bytes()); // flags
+            directory.extend_from_slice(&entry.method.to_le_bytes());
+            directory.extend_from_slice(&0_u16.to_le_bytes()); // time
+            directory.extend_from_slice(&DOS_DATE.to_le_bytes());
+            directory.extend_from_slice(&entry.crc.to_le_bytes());
+            directory.extend_from_slice(&entry.compressed_size.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&0_u16.to_le_bytes()); // extra field length
+            directory.extend_from_slice(&0_u16.to_le_bytes()); // comment length
+            directory.extend_from_slice(&0_u16.to_le_bytes()); // disk number
+            directory.extend_from_slice(&0_u16.to_le_bytes()); // internal attributes
+            directory.extend_from_slice(&0_u32.to_le_bytes()); // external attributes
+            directory.extend_from_slice(&entry.offset.to_le_bytes());
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let count = u16::try_from(self.entries.len())
+            .map_err(|_| io::Error::other("too many files in archive"))?;
+        let mut end = Vec::with_capacity(22);
+        end.extend_from_slice(&0x06054b50_u32.to_le_bytes());
+        end.extend_from_slice(&0_u16.to_le_bytes()); // disk number
+        end.extend_from_slice(&0_u16.to_le_bytes()); // disk with directory
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&to_u32(directory.len() as u64)?.to_le_bytes());
+        end.extend_from_slice(&start.to_le_bytes());
+        end.extend_from_slice(&0_u16.to_le_bytes()); // comment length
+
+        self.write(&directory)?;
+        self.write(&end)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// Write bytes to the output stream and keep track of the offset.
+    ///
+    /// * `bytes` - The bytes.
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+/// Returns a size or offset as 32 bits or an error if the archive would
+/// need ZIP64 extensions.
+///
+/// * `n` - The size or offset.
+fn to_u32(n: u64) -> io::Result<u32> {
+    u32::try_from(n).map_err(|_| io::Error::other("archive exceeds 4 GiB"))
+}
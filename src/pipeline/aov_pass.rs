@@ -0,0 +1,54 @@
+//! # AOV pass
+//!
+//! A pre-pass that renders arbitrary output variables into auxiliary images.
+
+use super::{render_aovs, Aov, ArcPass, Pass, PassContext, Stage};
+use std::fmt;
+use std::sync::Arc;
+
+/// Models a pass that renders AOVs.
+#[derive(Debug, Clone)]
+pub struct AovPass {
+    /// The AOVs to render.
+    aovs: Vec<Aov>,
+}
+
+impl AovPass {
+    /// Create a new AOV pass.
+    ///
+    /// * `aovs` - The AOVs to render.
+    pub fn new(aovs: Vec<Aov>) -> ArcPass {
+        Arc::new(AovPass { aovs })
+    }
+}
+
+impl fmt::Display for AovPass {
+    /// Display the AOV pass parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<String> = self.aovs.iter().map(|a| a.to_string()).collect();
+        write!(f, "aov(aovs: [{}])", names.join(", "))
+    }
+}
+
+impl Pass for AovPass {
+    /// AOVs are rendered before the main integrator so post passes can use
+    /// them.
+    fn stage(&self) -> Stage {
+        Stage::Pre
+    }
+
+    /// Render the AOVs and store them as auxiliary images named after the
+    /// AOVs.
+    ///
+    /// * `ctx` - The pass context.
+    fn run(&self, ctx: &mut PassContext) -> Result<(), String> {
+        let images = render_aovs(ctx.scene.clone(), &ctx.config, &self.aovs, &ctx.messages)?;
+
+        for (aov, image) in self.aovs.iter().zip(images) {
+            ctx.set_buffer(&aov.to_string(), image);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+//! # Bloom
+//!
+//! A post pass that makes bright regions of the image glow by adding a
+//! blurred copy of them to the image.
+
+use super::{get_colour, put_colour, ArcPass, Colour, Float, Pass, PassContext, Stage};
+use image::Rgb32FImage;
+use std::fmt;
+use std::sync::Arc;
+
+/// Models a bloom pass.
+#[derive(Debug, Clone)]
+pub struct Bloom {
+    /// Luminance above which pixels bloom.
+    threshold: Float,
+
+    /// Radius of the glow in pixels.
+    radius: Float,
+
+    /// Strength of the glow.
+    intensity: Float,
+}
+
+impl Bloom {
+    /// Create a new bloom pass.
+    ///
+    /// * `threshold` - Luminance above which pixels bloom.
+    /// * `radius` - Radius of the glow in pixels.
+    /// * `intensity` - Strength of the glow.
+    pub fn new(threshold: Float, radius: Float, intensity: Float) -> ArcPass {
+        Arc::new(Bloom {
+            threshold,
+            radius: radius.max(0.0),
+            intensity,
+        })
+    }
+}
+
+impl fmt::Display for Bloom {
+    /// Display the bloom parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "bloom(threshold: {}, radius: {}, intensity: {})",
+            self.threshold, self.radius, self.intensity
+        )
+    }
+}
+
+impl Pass for Bloom {
+    /// Bloom is applied to the rendered image.
+    fn stage(&self) -> Stage {
+        Stage::Post
+    }
+
+    /// Blur the parts of the image brighter than the threshold and add them
+    /// back to the image.
+    ///
+    /// * `ctx` - The pass context.
+    fn run(&self, ctx: &mut PassContext) -> Result<(), String> {
+        let (width, height) = ctx.image.dimensions();
+
+        // Keep the part of each pixel above the threshold preserving its hue.
+        let mut bright = Rgb32FImage::new(width, height);
+        for j in 0..height {
+            for i in 0..width {
+                let c = get_colour(&ctx.image, i, j);
+                let l = c.luminance();
+                if l > self.threshold {
+                    put_colour(&mut bright, i, j, c * ((l - self.threshold) / l));
+                }
+            }
+        }
+
+        let kernel = gaussian_kernel(self.radius);
+        let bright = convolve(&bright, &kernel, 1, 0);
+        let bright = convolve(&bright, &kernel, 0, 1);
+
+        for j in 0..height {
+            for i in 0..width {
+                let c = get_colour(&ctx.image, i, j) + get_colour(&bright, i, j) * self.intensity;
+                put_colour(&mut ctx.image, i, j, c);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns normalized weights of a Gaussian kernel covering three standard
+/// deviations, starting at the centre.
+///
+/// * `radius` - Radius of the kernel in pixels.
+fn gaussian_kernel(radius: Float) -> Vec<Float> {
+    let n = radius.ceil() as usize;
+    let sigma = (radius / 3.0).max(Float::EPSILON);
+
+    let weights: Vec<Float> = (0..=n)
+        .map(|x| (-((x * x) as Float) / (2.0 * sigma * sigma)).exp())
+        .collect();
+
+    // The centre weight is used once and the others on both sides.
+    let sum = weights[0] + 2.0 * weights[1..].iter().sum::<Float>();
+    weights.iter().map(|w| w / sum).collect()
+}
+
+/// Convolve an image with a symmetric one-dimensional kernel along a
+/// direction. Pixels past the edges are clamped to the edges.
+///
+/// * `image` - The image.
+/// * `kernel` - Kernel weights starting at the centre.
+/// * `dx` - Step along the x-axis.
+/// * `dy` - Step along the y-axis.
+fn convolve(image: &Rgb32FImage, kernel: &[Float], dx: i64, dy: i64) -> Rgb32FImage {
+    let (width, height) = image.dimensions();
+    let mut result = Rgb32FImage::new(width, height);
+
+    let clamp = |v: i64, max: u32| v.clamp(0, max as i64 - 1) as u32;
+
+    for j in 0..height {
+        for i in 0..width {
+            let mut sum = Colour::zero();
+            for (k, w) in kernel.iter().enumerate() {
+                let k = k as i64;
+                let a = get_colour(
+                    image,
+                    clamp(i as i64 + k * dx, width),
+                    clamp(j as i64 + k * dy, height),
+                );
+                sum += a * *w;
+                if k > 0 {
+                    let b = get_colour(
+                        image,
+                        clamp(i as i64 - k * dx, width),
+                        clamp(j as i64 - k * dy, height),
+                    );
+                    sum += b * *w;
+                }
+            }
+            put_colour(&mut result, i, j, sum);
+        }
+    }
+
+    result
+}
@@ -0,0 +1,145 @@
+//! # Denoise
+//!
+//! A post pass that reduces Monte Carlo noise with a joint bilateral filter.
+//! The filter averages nearby pixels with similar colours that lie on
+//! surfaces with similar normals and albedo, so edges and texture detail are
+//! preserved. Normal and albedo AOVs rendered by an earlier pass are reused,
+//! otherwise they are rendered.
+
+use super::{
+    aov_buffers, get_colour, put_colour, Aov, ArcPass, Colour, Float, Pass, PassContext, Stage,
+};
+use image::Rgb32FImage;
+use std::fmt;
+use std::sync::Arc;
+
+/// Models a denoising pass.
+#[derive(Debug, Clone)]
+pub struct Denoise {
+    /// Radius of the filter in pixels.
+    radius: u32,
+
+    /// Standard deviation of the spatial weights in pixels.
+    sigma_spatial: Float,
+
+    /// Standard deviation of the colour weights. Colours are compared after
+    /// compressing them to [0, 1) so bright outliers don't dominate.
+    sigma_colour: Float,
+
+    /// Standard deviation of the normal weights.
+    sigma_normal: Float,
+
+    /// Standard deviation of the albedo weights.
+    sigma_albedo: Float,
+}
+
+impl Denoise {
+    /// Create a new denoising pass.
+    ///
+    /// * `radius` - Radius of the filter in pixels.
+    /// * `sigma_spatial` - Standard deviation of the spatial weights.
+    /// * `sigma_colour` - Standard deviation of the colour weights.
+    /// * `sigma_normal` - Standard deviation of the normal weights.
+    /// * `sigma_albedo` - Standard deviation of the albedo weights.
+    pub fn new(
+        radius: u32,
+        sigma_spatial: Float,
+        sigma_colour: Float,
+        sigma_normal: Float,
+        sigma_albedo: Float,
+    ) -> ArcPass {
+        Arc::new(Denoise {
+            radius,
+            sigma_spatial,
+            sigma_colour,
+            sigma_normal,
+            sigma_albedo,
+        })
+    }
+}
+
+impl fmt::Display for Denoise {
+    /// Display the denoising parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "denoise(radius: {}, sigma_spatial: {}, sigma_colour: {}, sigma_normal: {}, sigma_albedo: {})",
+            self.radius, self.sigma_spatial, self.sigma_colour, self.sigma_normal, self.sigma_albedo
+        )
+    }
+}
+
+impl Pass for Denoise {
+    /// Denoising is applied to the rendered image.
+    fn stage(&self) -> Stage {
+        Stage::Post
+    }
+
+    /// Filter the image.
+    ///
+    /// * `ctx` - The pass context.
+    fn run(&self, ctx: &mut PassContext) -> Result<(), String> {
+        let guides = aov_buffers(ctx, &[Aov::Normal, Aov::Albedo])?;
+        let (normals, albedo) = (&guides[0], &guides[1]);
+
+        let (width, height) = ctx.image.dimensions();
+        let mut result = Rgb32FImage::new(width, height);
+
+        let weight =
+            |d2: Float, sigma: Float| (-d2 / (2.0 * sigma * sigma).max(Float::EPSILON)).exp();
+        let compress = |c: Colour| {
+            Colour::new(
+                c.x().max(0.0) / (1.0 + c.x().max(0.0)),
+                c.y().max(0.0) / (1.0 + c.y().max(0.0)),
+                c.z().max(0.0) / (1.0 + c.z().max(0.0)),
+            )
+        };
+
+        let r = self.radius as i64;
+        for j in 0..height {
+            for i in 0..width {
+                let c0 = get_colour(&ctx.image, i, j);
+                let t0 = compress(c0);
+                let n0 = get_colour(normals, i, j);
+                let a0 = get_colour(albedo, i, j);
+
+                let mut sum = Colour::zero();
+                let mut total = 0.0;
+
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        let x = i as i64 + dx;
+                        let y = j as i64 + dy;
+                        if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                            continue;
+                        }
+                        let (x, y) = (x as u32, y as u32);
+
+                        let c = get_colour(&ctx.image, x, y);
+                        let w = weight((dx * dx + dy * dy) as Float, self.sigma_spatial)
+                            * weight((compress(c) - t0).length_squared(), self.sigma_colour)
+                            * weight(
+                                (get_colour(normals, x, y) - n0).length_squared(),
+                                self.sigma_normal,
+                            )
+                            * weight(
+                                (get_colour(albedo, x, y) - a0).length_squared(),
+                                self.sigma_albedo,
+                            );
+
+                        sum += c * w;
+                        total += w;
+                    }
+                }
+
+                // The centre pixel always has weight 1.
+                put_colour(&mut result, i, j, sum / total);
+            }
+        }
+
+        ctx.image = result;
+        Ok(())
+    }
+}
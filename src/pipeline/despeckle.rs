@@ -0,0 +1,127 @@
+//! # Despeckle
+//!
+//! A post pass that removes residual fireflies. The luminance of each pixel
+//! is compared with the mean and standard deviation of its neighbours and
+//! only outliers are replaced by the median of the neighbourhood, so the rest
+//! of the image is left untouched and there is no blur.
+
+use super::{get_colour, put_colour, ArcPass, Colour, Float, Pass, PassContext, Stage};
+use std::fmt;
+use std::sync::Arc;
+
+/// Smallest standard deviation relative to the mean luminance of the
+/// neighbours. This keeps small variations in flat regions from counting as
+/// outliers.
+const MIN_RELATIVE_DEVIATION: Float = 0.25;
+
+/// Models a firefly removal pass.
+#[derive(Debug, Clone)]
+pub struct Despeckle {
+    /// Radius of the neighbourhood in pixels.
+    radius: u32,
+
+    /// Number of standard deviations above the mean luminance of the
+    /// neighbours at which a pixel is an outlier.
+    threshold: Float,
+}
+
+impl Despeckle {
+    /// Create a new firefly removal pass.
+    ///
+    /// * `radius` - Radius of the neighbourhood in pixels.
+    /// * `threshold` - Number of standard deviations for outliers.
+    pub fn new(radius: u32, threshold: Float) -> ArcPass {
+        Arc::new(Despeckle {
+            radius: radius.max(1),
+            threshold,
+        })
+    }
+}
+
+impl fmt::Display for Despeckle {
+    /// Display the despeckle parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "despeckle(radius: {}, threshold: {})",
+            self.radius, self.threshold
+        )
+    }
+}
+
+impl Pass for Despeckle {
+    /// Fireflies are removed from the rendered image.
+    fn stage(&self) -> Stage {
+        Stage::Post
+    }
+
+    /// Replace outlier pixels with the median of their neighbours.
+    ///
+    /// * `ctx` - The pass context.
+    fn run(&self, ctx: &mut PassContext) -> Result<(), String> {
+        let source = ctx.image.clone();
+        let (width, height) = source.dimensions();
+
+        let r = self.radius as i64;
+        let mut neighbours: Vec<Colour> = Vec::with_capacity(((2 * r + 1) * (2 * r + 1)) as usize);
+        let mut replaced = 0;
+
+        for j in 0..height {
+            for i in 0..width {
+                neighbours.clear();
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        let x = i as i64 + dx;
+                        let y = j as i64 + dy;
+                        if (dx == 0 && dy == 0)
+                            || x < 0
+                            || y < 0
+                            || x >= width as i64
+                            || y >= height as i64
+                        {
+                            continue;
+                        }
+                        neighbours.push(get_colour(&source, x as u32, y as u32));
+                    }
+                }
+
+                if neighbours.len() < 2 {
+                    continue;
+                }
+
+                let n = neighbours.len() as Float;
+                let mean = neighbours.iter().map(|c| c.luminance()).sum::<Float>() / n;
+                let variance = neighbours
+                    .iter()
+                    .map(|c| (c.luminance() - mean).powi(2))
+                    .sum::<Float>()
+                    / (n - 1.0);
+                let deviation = variance.sqrt().max(MIN_RELATIVE_DEVIATION * mean);
+
+                let l = get_colour(&source, i, j).luminance();
+                if l - mean > self.threshold * deviation {
+                    put_colour(&mut ctx.image, i, j, median(&mut neighbours));
+                    replaced += 1;
+                }
+            }
+        }
+
+        ctx.messages
+            .push(format!("Replaced {} outlier pixels", replaced));
+        Ok(())
+    }
+}
+
+/// Returns the median of each colour channel. The colours are re-ordered.
+///
+/// * `colours` - The colours.
+fn median(colours: &mut [Colour]) -> Colour {
+    let mid = colours.len() / 2;
+    let mut channel = |k: usize| {
+        colours.select_nth_unstable_by(mid, |a, b| a[k].total_cmp(&b[k]));
+        colours[mid][k]
+    };
+    Colour::new(channel(0), channel(1), channel(2))
+}
@@ -0,0 +1,55 @@
+//! # Exposure
+//!
+//! A post pass that scales the brightness of the image.
+
+use super::{get_colour, put_colour, ArcPass, Float, Pass, PassContext, Stage};
+use std::fmt;
+use std::sync::Arc;
+
+/// Models an exposure adjustment in photographic stops.
+#[derive(Debug, Clone)]
+pub struct Exposure {
+    /// Exposure change in stops. Each stop doubles the brightness.
+    stops: Float,
+}
+
+impl Exposure {
+    /// Create a new exposure pass.
+    ///
+    /// * `stops` - Exposure change in stops.
+    pub fn new(stops: Float) -> ArcPass {
+        Arc::new(Exposure { stops })
+    }
+}
+
+impl fmt::Display for Exposure {
+    /// Display the exposure parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "exposure(stops: {})", self.stops)
+    }
+}
+
+impl Pass for Exposure {
+    /// Exposure is applied to the rendered image.
+    fn stage(&self) -> Stage {
+        Stage::Post
+    }
+
+    /// Scale the image by 2^stops.
+    ///
+    /// * `ctx` - The pass context.
+    fn run(&self, ctx: &mut PassContext) -> Result<(), String> {
+        let scale = self.stops.exp2();
+
+        let (width, height) = ctx.image.dimensions();
+        for j in 0..height {
+            for i in 0..width {
+                let c = get_colour(&ctx.image, i, j) * scale;
+                put_colour(&mut ctx.image, i, j, c);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,433 @@
+//! # Pipeline
+//!
+//! A library for running the render as a pipeline of passes. Pre-passes run
+//! before the main integrator and render auxiliary images like AOVs. Post
+//! passes run after it and filter the rendered image, e.g. denoising, bloom,
+//! exposure and tone mapping. Passes are described by tables with a `type` key
+//! and run in the order given by the scene file followed by the command line.
+//!
+//! ```toml
+//! [[passes]]
+//! type = "aov"            # pre-pass
+//! aovs = ["normal", "albedo"]
+//!
+//! [[passes]]
+//! type = "denoise"        # post pass
+//! radius = 3
+//!
+//! [[passes]]
+//! type = "tonemap"        # post pass
+//! operator = "aces"
+//!
+//! [[passes]]
+//! type = "watermark"      # post pass
+//! path = "logo.png"
+//! corner = "bottom_right" # optional top_left, top_right, bottom_left
+//! opacity = 0.5           # optional
+//! margin = 16             # optional distance from the edges in pixels
+//! ```
+
+mod aov_pass;
+mod bloom;
+mod denoise;
+mod despeckle;
+mod exposure;
+mod tonemap;
+mod watermark;
+
+use super::algebra::Colour;
+use super::app_config::AppConfig;
+use super::common::{Float, Messages};
+use super::renderer::{aux_output_path, render_aovs, save_framebuffer, Aov};
+use super::scene::Scene;
+use super::signal::interrupted;
+use image::{Rgb, Rgb32FImage};
+use std::fmt;
+use std::sync::Arc;
+use toml::{Table, Value};
+
+// Re-exports.
+pub use self::aov_pass::AovPass;
+pub use self::bloom::Bloom;
+pub use self::denoise::Denoise;
+pub use self::despeckle::Despeckle;
+pub use self::exposure::Exposure;
+pub use self::tonemap::Tonemap;
+pub use self::watermark::Watermark;
+
+/// Default radius of the despeckle pass in pixels.
+const DESPECKLE_RADIUS: u32 = 1;
+
+/// Default number of standard deviations at which the despeckle pass treats a
+/// pixel as an outlier.
+const DESPECKLE_THRESHOLD: Float = 3.0;
+
+/// When a pass runs relative to the main integrator.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stage {
+    /// Before the main integrator.
+    Pre,
+
+    /// After the main integrator.
+    Post,
+}
+
+/// Images and data shared by the passes of a pipeline.
+pub struct PassContext {
+    /// The scene.
+    pub scene: Scene,
+
+    /// Program configuration.
+    pub config: AppConfig,
+
+    /// The image rendered by the main integrator, oriented with the first row
+    /// at the top. It is empty until the main integrator has run.
+    pub image: Rgb32FImage,
+
+    /// Named auxiliary images. These are saved next to the image.
+    pub buffers: Vec<(String, Rgb32FImage)>,
+
+    /// Messages about the passes and saved images for the caller to show.
+    pub messages: Messages,
+}
+
+impl PassContext {
+    /// Create a new pass context.
+    ///
+    /// * `scene` - The scene.
+    /// * `config` - Program configuration.
+    pub fn new(scene: Scene, config: &AppConfig) -> PassContext {
+        PassContext {
+            scene,
+            config: config.clone(),
+            image: Rgb32FImage::new(0, 0),
+            buffers: Vec::new(),
+            messages: Messages::default(),
+        }
+    }
+
+    /// Returns the auxiliary image with the given name.
+    ///
+    /// * `name` - Name of the image.
+    pub fn buffer(&self, name: &str) -> Option<&Rgb32FImage> {
+        self.buffers.iter().find(|(n, _)| n == name).map(|(_, b)| b)
+    }
+
+    /// Add or replace an auxiliary image.
+    ///
+    /// * `name` - Name of the image.
+    /// * `buffer` - The image.
+    pub fn set_buffer(&mut self, name: &str, buffer: Rgb32FImage) {
+        match self.buffers.iter_mut().find(|(n, _)| n == name) {
+            Some((_, b)) => *b = buffer,
+            None => self.buffers.push((name.to_string(), buffer)),
+        }
+    }
+}
+
+/// Models a pass of the render pipeline.
+pub trait Pass: fmt::Display {
+    /// Returns when the pass runs.
+    fn stage(&self) -> Stage;
+
+    /// Run the pass.
+    ///
+    /// * `ctx` - The pass context.
+    fn run(&self, ctx: &mut PassContext) -> Result<(), String>;
+}
+
+/// Atomic reference counted `Pass`.
+pub type ArcPass = Arc<dyn Pass + Send + Sync>;
+
+/// The passes run before and after the main integrator.
+pub struct Pipeline {
+    /// Passes run before the main integrator.
+    pre: Vec<ArcPass>,
+
+    /// Passes run after the main integrator.
+    post: Vec<ArcPass>,
+}
+
+impl Pipeline {
+    /// Build the pipeline from the AOVs and despeckle pass given on the
+    /// command line followed by the passes of the scene file and those given
+    /// on the command line.
+    ///
+    /// * `scene` - The scene.
+    /// * `config` - Program configuration.
+    pub fn build(scene: &Scene, config: &AppConfig) -> Result<Pipeline, String> {
+        let mut pipeline = Pipeline {
+            pre: Vec::new(),
+            post: Vec::new(),
+        };
+
+        if !config.aovs.is_empty() {
+            pipeline.add(AovPass::new(config.aovs.clone()));
+        }
+
+        // Remove fireflies before any other filtering spreads them.
+        if config.despeckle {
+            pipeline.add(Despeckle::new(DESPECKLE_RADIUS, DESPECKLE_THRESHOLD));
+        }
+
+        let specs = scene.passes.iter().map(|t| (t, "scene"));
+        let specs = specs.chain(config.passes.iter().map(|t| (t, "--pass")));
+
+        for (idx, (t, source)) in specs.enumerate() {
+            let pass = pass_from_table(t).map_err(|e| format!("{} pass {}: {}", source, idx, e))?;
+            pipeline.add(pass);
+        }
+
+        Ok(pipeline)
+    }
+
+    /// Add a pass after the other passes of its stage.
+    ///
+    /// * `pass` - The pass.
+    pub fn add(&mut self, pass: ArcPass) {
+        match pass.stage() {
+            Stage::Pre => self.pre.push(pass),
+            Stage::Post => self.post.push(pass),
+        }
+    }
+
+    /// Run the passes that come before the main integrator.
+    ///
+    /// * `ctx` - The pass context.
+    pub fn run_pre(&self, ctx: &mut PassContext) -> Result<(), String> {
+        run_passes(&self.pre, ctx)
+    }
+
+    /// Run the passes that come after the main integrator.
+    ///
+    /// * `ctx` - The pass context.
+    pub fn run_post(&self, ctx: &mut PassContext) -> Result<(), String> {
+        run_passes(&self.post, ctx)
+    }
+
+    /// Save the image and auxiliary images. Auxiliary images are saved as
+    /// floating point images named after the image, e.g. `image.normal.exr`
+    /// or `image.normal.hdr` without the `exr` feature.
+    ///
+    /// * `ctx` - The pass context.
+    /// * `path` - Output file path. File extension determines image type.
+    pub fn save(&self, ctx: &PassContext, path: &str) {
+        let messages = &ctx.messages;
+
+        messages.push(format!("Saving output image to {}", path));
+        if let Err(e) = save_framebuffer(&ctx.image, path, ctx.config.gamma) {
+            messages.push(format!("Error writing output image: {}", e));
+        }
+
+        for (name, buffer) in ctx.buffers.iter() {
+            let aux_path = aux_output_path(path, name);
+            messages.push(format!("Saving {} to {}", name, aux_path));
+
+            if let Err(e) = buffer.save(&aux_path) {
+                messages.push(format!("Error writing {}: {}", name, e));
+            }
+        }
+    }
+}
+
+/// Run passes in order. Passes are skipped once an interrupt is requested.
+///
+/// * `passes` - The passes.
+/// * `ctx` - The pass context.
+fn run_passes(passes: &[ArcPass], ctx: &mut PassContext) -> Result<(), String> {
+    for pass in passes.iter() {
+        if interrupted() {
+            break;
+        }
+
+        ctx.messages.push(format!("Running {}", pass));
+        pass.run(ctx)?;
+    }
+    Ok(())
+}
+
+/// Build a pass from its description.
+///
+/// * `t` - Description of the pass.
+fn pass_from_table(t: &Table) -> Result<ArcPass, String> {
+    let kind = t
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or("missing `type`")?;
+
+    match kind {
+        "aov" => {
+            let aovs = match t.get("aovs") {
+                Some(Value::Array(values)) => values
+                    .iter()
+                    .map(|v| {
+                        v.as_str()
+                            .ok_or_else(|| "`aovs` must be strings".to_string())
+                            .and_then(aov)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                Some(Value::String(s)) => s.split(',').map(aov).collect::<Result<_, _>>()?,
+                Some(_) => return Err("`aovs` must be an array of strings".to_string()),
+                None => vec![Aov::Normal, Aov::Albedo],
+            };
+            Ok(AovPass::new(aovs))
+        }
+
+        "denoise" => {
+            let radius = param_float(t, "radius", 3.0)? as u32;
+            Ok(Denoise::new(
+                radius,
+                param_float(t, "sigma_spatial", (radius as Float / 2.0).max(0.5))?,
+                param_float(t, "sigma_colour", 0.2)?,
+                param_float(t, "sigma_normal", 0.3)?,
+                param_float(t, "sigma_albedo", 0.1)?,
+            ))
+        }
+
+        "despeckle" => Ok(Despeckle::new(
+            param_float(t, "radius", DESPECKLE_RADIUS as Float)? as u32,
+            param_float(t, "threshold", DESPECKLE_THRESHOLD)?,
+        )),
+
+        "bloom" => Ok(Bloom::new(
+            param_float(t, "threshold", 1.0)?,
+            param_float(t, "radius", 8.0)?,
+            param_float(t, "intensity", 0.1)?,
+        )),
+
+        "exposure" => Ok(Exposure::new(param_float(t, "stops", 0.0)?)),
+
+        "tonemap" => {
+            let name = match t.get("operator") {
+                Some(Value::String(s)) => s.as_str(),
+                Some(_) => return Err("`operator` must be a string".to_string()),
+                None => "aces",
+            };
+            Tonemap::new(name)
+        }
+
+        "watermark" => {
+            let path = match t.get("path") {
+                Some(Value::String(s)) => s.as_str(),
+                Some(_) => return Err("`path` must be a string".to_string()),
+                None => return Err("missing `path`".to_string()),
+            };
+            let corner = match t.get("corner") {
+                Some(Value::String(s)) => s.as_str(),
+                Some(_) => return Err("`corner` must be a string".to_string()),
+                None => "bottom_right",
+            };
+            Watermark::new(
+                path,
+                corner,
+                param_float(t, "opacity", 0.5)?,
+                param_float(t, "margin", 16.0)?.max(0.0) as u32,
+            )
+        }
+
+        _ => Err(format!("unknown pass type `{}`", kind)),
+    }
+}
+
+/// Returns an AOV by name.
+///
+/// * `name` - Name of the AOV.
+fn aov(name: &str) -> Result<Aov, String> {
+    name.parse::<Aov>()
+        .map_err(|_| format!("unknown AOV `{}`", name))
+}
+
+/// Returns a numeric parameter of a pass or a default value.
+///
+/// * `t` - Description of the pass.
+/// * `key` - Name of the parameter.
+/// * `default` - Default value.
+fn param_float(t: &Table, key: &str, default: Float) -> Result<Float, String> {
+    match t.get(key) {
+        Some(Value::Float(v)) => Ok(*v as Float),
+        Some(Value::Integer(v)) => Ok(*v as Float),
+        Some(_) => Err(format!("`{}` must be a number", key)),
+        None => Ok(default),
+    }
+}
+
+/// Parse a pass given on the command line of the form
+/// `type:key=value,key=value`, e.g. `bloom:threshold=1.5,intensity=0.2`.
+///
+/// * `s` - The pass.
+pub fn parse_pass(s: &str) -> Result<Table, String> {
+    let (kind, params) = s.split_once(':').unwrap_or((s, ""));
+
+    let kind = kind.trim();
+    if kind.is_empty() {
+        return Err(format!("expected TYPE[:KEY=VALUE,...], found `{}`", s));
+    }
+
+    let mut t = Table::new();
+    t.insert("type".into(), kind.into());
+
+    for param in params.split(',').filter(|p| !p.trim().is_empty()) {
+        let (key, value) = param
+            .split_once('=')
+            .ok_or_else(|| format!("expected KEY=VALUE, found `{}`", param))?;
+
+        // Values are parsed as TOML and fall back to plain strings.
+        let value = value.trim();
+        let value = format!("value = {}", value)
+            .parse::<Table>()
+            .ok()
+            .and_then(|mut v| v.remove("value"))
+            .unwrap_or_else(|| Value::String(value.to_string()));
+
+        t.insert(key.trim().to_string(), value);
+    }
+
+    Ok(t)
+}
+
+/// Returns the colour of a pixel.
+///
+/// * `image` - The image.
+/// * `i` - Pixel x-coordinate.
+/// * `j` - Pixel y-coordinate.
+fn get_colour(image: &Rgb32FImage, i: u32, j: u32) -> Colour {
+    let p = image.get_pixel(i, j);
+    Colour::new(p[0] as Float, p[1] as Float, p[2] as Float)
+}
+
+/// Set the colour of a pixel.
+///
+/// * `image` - The image.
+/// * `i` - Pixel x-coordinate.
+/// * `j` - Pixel y-coordinate.
+/// * `colour` - The colour.
+fn put_colour(image: &mut Rgb32FImage, i: u32, j: u32, colour: Colour) {
+    image.put_pixel(i, j, Rgb(colour.to_rgb32f()));
+}
+
+/// Render AOVs for the scene of a pass context that are not already present
+/// and returns them in the same order as `aovs`.
+///
+/// * `ctx` - The pass context.
+/// * `aovs` - The AOVs.
+fn aov_buffers(ctx: &PassContext, aovs: &[Aov]) -> Result<Vec<Rgb32FImage>, String> {
+    let missing: Vec<Aov> = aovs
+        .iter()
+        .filter(|a| ctx.buffer(&a.to_string()).is_none())
+        .copied()
+        .collect();
+
+    let mut rendered = if missing.is_empty() {
+        Vec::new().into_iter()
+    } else {
+        render_aovs(ctx.scene.clone(), &ctx.config, &missing, &ctx.messages)?.into_iter()
+    };
+
+    Ok(aovs
+        .iter()
+        .map(|a| match ctx.buffer(&a.to_string()) {
+            Some(b) => b.clone(),
+            None => rendered.next().unwrap_or_default(),
+        })
+        .collect())
+}
@@ -0,0 +1,83 @@
+//! # Tonemap
+//!
+//! A post pass that compresses high dynamic range values into [0, 1].
+
+use super::{get_colour, put_colour, ArcPass, Colour, Float, Pass, PassContext, Stage};
+use std::fmt;
+use std::sync::Arc;
+
+/// Tone mapping operators.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Operator {
+    /// Reinhard's operator x / (1 + x).
+    Reinhard,
+
+    /// Narkowicz's fit of the ACES filmic curve.
+    Aces,
+}
+
+/// Models a tone mapping pass.
+#[derive(Debug, Clone)]
+pub struct Tonemap {
+    /// The tone mapping operator.
+    operator: Operator,
+}
+
+impl Tonemap {
+    /// Create a new tone mapping pass.
+    ///
+    /// * `operator` - Name of the operator, `reinhard` or `aces`.
+    pub fn new(operator: &str) -> Result<ArcPass, String> {
+        let operator = match operator {
+            "reinhard" => Operator::Reinhard,
+            "aces" => Operator::Aces,
+            _ => return Err(format!("unknown tone mapping operator `{}`", operator)),
+        };
+        Ok(Arc::new(Tonemap { operator }))
+    }
+
+    /// Returns the tone mapped value of a colour channel.
+    ///
+    /// * `x` - Linear value.
+    fn map(&self, x: Float) -> Float {
+        let x = x.max(0.0);
+        match self.operator {
+            Operator::Reinhard => x / (1.0 + x),
+            Operator::Aces => {
+                let y = (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+                y.clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Tonemap {
+    /// Display the tone mapping parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "tonemap(operator: {:?})", self.operator)
+    }
+}
+
+impl Pass for Tonemap {
+    /// Tone mapping is applied to the rendered image.
+    fn stage(&self) -> Stage {
+        Stage::Post
+    }
+
+    /// Tone map each colour channel of the image.
+    ///
+    /// * `ctx` - The pass context.
+    fn run(&self, ctx: &mut PassContext) -> Result<(), String> {
+        let (width, height) = ctx.image.dimensions();
+        for j in 0..height {
+            for i in 0..width {
+                let c = get_colour(&ctx.image, i, j);
+                let c = Colour::new(self.map(c.x()), self.map(c.y()), self.map(c.z()));
+                put_colour(&mut ctx.image, i, j, c);
+            }
+        }
+        Ok(())
+    }
+}
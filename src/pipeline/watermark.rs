@@ -0,0 +1,139 @@
+//! # Watermark
+//!
+//! A post pass that composites a semi-transparent overlay image, e.g. a
+//! logo, onto a corner of the rendered image. Add it after tone mapping so
+//! the overlay keeps its colours.
+
+use super::{get_colour, put_colour, ArcPass, Colour, Float, Pass, PassContext, Stage};
+use image::RgbaImage;
+use std::fmt;
+use std::sync::Arc;
+
+/// Corners of the image an overlay can be placed in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Corner {
+    /// Top left corner.
+    TopLeft,
+
+    /// Top right corner.
+    TopRight,
+
+    /// Bottom left corner.
+    BottomLeft,
+
+    /// Bottom right corner.
+    BottomRight,
+}
+
+/// Models a watermark pass.
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    /// Path to the overlay image.
+    path: String,
+
+    /// The overlay image.
+    overlay: RgbaImage,
+
+    /// Corner the overlay is placed in.
+    corner: Corner,
+
+    /// Opacity multiplied with the alpha channel of the overlay.
+    opacity: Float,
+
+    /// Distance of the overlay from the edges of the image in pixels.
+    margin: u32,
+}
+
+impl Watermark {
+    /// Create a new watermark pass.
+    ///
+    /// * `path` - Path to the overlay image, e.g. a PNG with transparency.
+    /// * `corner` - Name of the corner, `top_left`, `top_right`,
+    ///   `bottom_left` or `bottom_right`.
+    /// * `opacity` - Opacity in `[0, 1]` multiplied with the alpha channel
+    ///   of the overlay.
+    /// * `margin` - Distance of the overlay from the edges of the image in
+    ///   pixels.
+    pub fn new(path: &str, corner: &str, opacity: Float, margin: u32) -> Result<ArcPass, String> {
+        let corner = match corner {
+            "top_left" => Corner::TopLeft,
+            "top_right" => Corner::TopRight,
+            "bottom_left" => Corner::BottomLeft,
+            "bottom_right" => Corner::BottomRight,
+            _ => return Err(format!("unknown corner `{}`", corner)),
+        };
+
+        let overlay = image::open(path)
+            .map_err(|e| format!("Unable to open {}: {}", path, e))?
+            .into_rgba8();
+
+        Ok(Arc::new(Watermark {
+            path: path.to_string(),
+            overlay,
+            corner,
+            opacity: opacity.clamp(0.0, 1.0),
+            margin,
+        }))
+    }
+}
+
+impl fmt::Display for Watermark {
+    /// Display the watermark parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "watermark(path: {}, corner: {:?}, opacity: {}, margin: {})",
+            self.path, self.corner, self.opacity, self.margin
+        )
+    }
+}
+
+impl Pass for Watermark {
+    /// The overlay is applied to the rendered image.
+    fn stage(&self) -> Stage {
+        Stage::Post
+    }
+
+    /// Blend the overlay over its corner of the image. Parts of the overlay
+    /// outside the image are clipped.
+    ///
+    /// * `ctx` - The pass context.
+    fn run(&self, ctx: &mut PassContext) -> Result<(), String> {
+        let (width, height) = ctx.image.dimensions();
+        let (w, h) = self.overlay.dimensions();
+
+        let x0 = match self.corner {
+            Corner::TopLeft | Corner::BottomLeft => self.margin as i64,
+            Corner::TopRight | Corner::BottomRight => width as i64 - w as i64 - self.margin as i64,
+        };
+        let y0 = match self.corner {
+            Corner::TopLeft | Corner::TopRight => self.margin as i64,
+            Corner::BottomLeft | Corner::BottomRight => {
+                height as i64 - h as i64 - self.margin as i64
+            }
+        };
+
+        for (x, y, p) in self.overlay.enumerate_pixels() {
+            let (i, j) = (x0 + x as i64, y0 + y as i64);
+            if i < 0 || j < 0 || i >= width as i64 || j >= height as i64 {
+                continue;
+            }
+
+            let alpha = self.opacity * p[3] as Float / 255.0;
+            if alpha <= 0.0 {
+                continue;
+            }
+
+            // Undo the gamma correction applied when the image is saved.
+            let channel = |c: u8| ctx.config.gamma.decode(c as Float / 255.0);
+            let overlay = Colour::new(channel(p[0]), channel(p[1]), channel(p[2]));
+
+            let (i, j) = (i as u32, j as u32);
+            let c = get_colour(&ctx.image, i, j);
+            put_colour(&mut ctx.image, i, j, c * (1.0 - alpha) + overlay * alpha);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+//! # Anaglyph
+//!
+//! A library for rendering red/cyan stereo anaglyph images.
+
+use super::{render_batch, AppConfig, Progress, RenderJob, Scene};
+use image::Rgb32FImage;
+
+/// Render a red/cyan anaglyph of a scene. The scene is rendered from a left
+/// and right eye camera offset by `config.eye_separation`. The red channel is
+/// taken from the left eye and the green and blue channels from the right
+/// eye. The returned linear image is oriented with the first row at the top.
+///
+/// * `scene`    - The scene to render.
+/// * `config`   - Program configuration.
+/// * `progress` - Receives the fraction of both eyes completed as tiles
+///   finish and messages about the render.
+pub fn render_anaglyph(
+    scene: Scene,
+    config: &AppConfig,
+    progress: &dyn Progress,
+) -> Result<Rgb32FImage, String> {
+    let half_separation = 0.5 * config.eye_separation;
+
+    let mut left = scene.clone();
+    left.camera = scene.camera.stereo_eye(-half_separation);
+
+    let mut right = scene;
+    right.camera = right.camera.stereo_eye(half_separation);
+
+    // Both eyes share a thread pool so they are rendered concurrently.
+    let jobs = vec![
+        RenderJob::new(left, config.clone()),
+        RenderJob::new(right, config.clone()),
+    ];
+    let mut images = render_batch(jobs, config.threads(), progress)?;
+
+    let right = images.pop().ok_or("Missing right eye image")?;
+    let mut image = images.pop().ok_or("Missing left eye image")?;
+
+    for (dst, src) in image.pixels_mut().zip(right.pixels()) {
+        dst[1] = src[1];
+        dst[2] = src[2];
+    }
+
+    Ok(image)
+}
@@ -0,0 +1,332 @@
+//! # AOV
+//!
+//! A library for rendering arbitrary output variables (AOVs). These are
+//! auxiliary per-pixel images, like normals, depth or motion vectors, which
+//! are written alongside the beauty image for use by compositing and denoising
+//! tools.
+
+use super::{
+    get_tile_bounds, interrupted, AppConfig, Colour, Float, Messages, RecursiveTracer, Sampler,
+    Scene, ThreadPool, TileBounds,
+};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use image::{Rgb, Rgb32FImage};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{mpsc, Arc};
+
+/// Arbitrary output variable types.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum Aov {
+    /// World space normal of the visible surface facing the camera.
+    Normal,
+
+    /// Depth of the visible surface along the camera's viewing direction.
+    /// Pixels that see the background are infinitely far.
+    Depth,
+
+    /// Reflectance of the visible surface. Emissive surfaces use their
+    /// emitted colour clamped to [0, 1].
+    Albedo,
+
+    /// Light reaching the camera directly from emitters and the background
+    /// or after a single bounce.
+    Direct,
+
+    /// Light reaching the camera after two or more bounces. The sum of the
+    /// direct and indirect AOVs converges to the beauty image.
+    Indirect,
+
+    /// Screen space motion of the visible surface over the shutter interval
+    /// in pixels. The x-axis points right and the y-axis points down.
+    Motion,
+}
+
+impl fmt::Display for Aov {
+    /// Display the AOV name as used on the command line.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Albedo => "albedo",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+            Aov::Motion => "motion",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Aov {
+    type Err = String;
+
+    /// Parse the AOV name ignoring case.
+    ///
+    /// * `s` - The name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "normal" => Ok(Aov::Normal),
+            "depth" => Ok(Aov::Depth),
+            "albedo" => Ok(Aov::Albedo),
+            "direct" => Ok(Aov::Direct),
+            "indirect" => Ok(Aov::Indirect),
+            "motion" => Ok(Aov::Motion),
+            _ => Err(format!("unknown AOV `{}`", s)),
+        }
+    }
+}
+
+impl RecursiveTracer {
+    /// Returns the value of an AOV for a pixel. Geometric AOVs are evaluated
+    /// with a single ray through the centre of the pixel and lens at the start
+    /// of the shutter interval so they are not blurred by antialiasing,
+    /// defocus or motion. Lighting AOVs are averaged over the pixel samples
+    /// like the beauty image.
+    ///
+    /// * `aov` - The AOV.
+    /// * `i`   - Pixel x-coordinate.
+    /// * `j`   - Pixel y-coordinate.
+    fn trace_aov(&self, aov: Aov, i: u32, j: u32) -> [f32; 3] {
+        let w = self.config.image_width as Float;
+        let h = self.config.image_height as Float;
+
+        match aov {
+            // Direct lighting ends the path after the first bounce.
+            Aov::Direct => return self.trace_lighting(i, j, 2, 0),
+            Aov::Indirect => return self.trace_lighting(i, j, self.config.max_depth, 2),
+            _ => (),
+        }
+
+        let s = (i as Float + 0.5) / w;
+        let t = (j as Float + 0.5) / h;
+
+        let camera = &self.scene.camera;
+        let ray = camera.get_centre_ray(s, t);
+
+        let rec = match self.camera_hit(&ray) {
+            Some(rec) => rec,
+            None if aov == Aov::Depth => return [f32::INFINITY; 3],
+            None => return [0.0; 3],
+        };
+
+        match aov {
+            Aov::Normal => rec.normal.to_rgb32f(),
+
+            Aov::Depth => [camera.depth(rec.point) as f32; 3],
+
+            Aov::Albedo => match rec.material.scatter(&ray, &rec) {
+                Some(sr) => sr.attenuation.to_rgb32f(),
+                None => {
+                    let e = rec.material.emission(&ray, &rec);
+                    let clamp = |c: Float| c.clamp(0.0, 1.0);
+                    [
+                        clamp(e.x()) as f32,
+                        clamp(e.y()) as f32,
+                        clamp(e.z()) as f32,
+                    ]
+                }
+            },
+
+            Aov::Direct | Aov::Indirect => [0.0; 3],
+
+            Aov::Motion => {
+                // Project the start and end points through the camera as it
+                // is at the start and end of the shutter interval so turning
+                // cameras move static objects.
+                let (time0, time1) = camera.shutter();
+                let p1 = rec.point + rec.velocity * (time1 - time0);
+
+                match (
+                    camera.project_at(rec.point, time0),
+                    camera.project_at(p1, time1),
+                ) {
+                    (Some((s0, t0)), Some((s1, t1))) => {
+                        // Image rows are rendered bottom to top.
+                        let dx = (s1 - s0) * w;
+                        let dy = -(t1 - t0) * h;
+                        [dx as f32, dy as f32, 0.0]
+                    }
+                    _ => [0.0; 3],
+                }
+            }
+        }
+    }
+
+    /// Returns the average radiance of the pixel samples ignoring light
+    /// emitted at the first `skip` path vertices.
+    ///
+    /// * `i`     - Pixel x-coordinate.
+    /// * `j`     - Pixel y-coordinate.
+    /// * `depth` - Maximum depth for recursion.
+    /// * `skip`  - Number of path vertices whose emission is ignored.
+    fn trace_lighting(&self, i: u32, j: u32, depth: u32, skip: u32) -> [f32; 3] {
+        let w = self.config.image_width as Float;
+        let h = self.config.image_height as Float;
+        let n = self.config.max_samples();
+
+        let mut sum = Colour::zero();
+        let mut valid = 0;
+        for s in 0..n {
+            Sampler::start_sample(self.config.sampler, i, j, s, n);
+            let (sx, sy) = Sampler::get_2d();
+
+            let u = (i as Float + sx) / w;
+            let v = (j as Float + sy) / h;
+
+            let ray = self.scene.camera.get_ray(u, v);
+            let colour = self.camera_path_colour(&ray, depth.min(self.config.max_depth), skip);
+
+            // Drop samples with NaN or infinite components like the beauty
+            // image does.
+            if colour.is_finite() {
+                sum += colour;
+                valid += 1;
+            }
+        }
+        Sampler::finish();
+
+        if valid == 0 {
+            [0.0; 3]
+        } else {
+            sum.average_sample(valid).to_rgb32f()
+        }
+    }
+}
+
+/// Render AOVs for a scene. The returned images are in the same order as
+/// `aovs` and are oriented with the first row at the top.
+///
+/// * `scene`    - The scene to render.
+/// * `config`   - Program configuration.
+/// * `aovs`     - The AOVs to render.
+/// * `messages` - Receives warnings about the render.
+pub fn render_aovs(
+    scene: Scene,
+    config: &AppConfig,
+    aovs: &[Aov],
+    messages: &Messages,
+) -> Result<Vec<Rgb32FImage>, String> {
+    let pool = ThreadPool::build(config.threads(), config.worker_options())
+        .map_err(|e| format!("{:?}", e))?;
+    for warning in pool.warnings() {
+        messages.push(warning.clone());
+    }
+
+    let renderer = Arc::new(RecursiveTracer::new(scene, config.clone()));
+    let aovs: Arc<Vec<Aov>> = Arc::new(aovs.to_vec());
+
+    // Workers send back the AOV values for each tile.
+    let (sender, receiver) = mpsc::channel::<(TileBounds, Vec<Vec<[f32; 3]>>)>();
+
+    for tile_idx in 0..config.n_tiles() {
+        // Stop queuing tiles once an interrupt is requested.
+        if interrupted() {
+            break;
+        }
+
+        let renderer = Arc::clone(&renderer);
+        let aovs = Arc::clone(&aovs);
+        let sender = sender.clone();
+
+        pool.execute(move || {
+            // Drain tiles that were queued before the interrupt.
+            if interrupted() {
+                return;
+            }
+
+            let config = &renderer.config;
+            let tile_bounds = get_tile_bounds(&config.render_context(), tile_idx);
+
+            let values = aovs
+                .iter()
+                .map(|aov| {
+                    let mut values = Vec::new();
+                    for j in tile_bounds.y_min..=tile_bounds.y_max {
+                        for i in tile_bounds.x_min..=tile_bounds.x_max {
+                            values.push(renderer.trace_aov(*aov, i, j));
+                        }
+                    }
+                    values
+                })
+                .collect();
+
+            // The receiver only goes away if rendering was abandoned.
+            let _ = sender.send((tile_bounds, values));
+        });
+    }
+
+    // Drop our sender so the receiver terminates once all tiles are done.
+    drop(sender);
+
+    let mut images: Vec<Rgb32FImage> = aovs
+        .iter()
+        .map(|_| Rgb32FImage::new(config.image_width, config.image_height))
+        .collect();
+
+    for (tile_bounds, values) in receiver.iter() {
+        for (image, values) in images.iter_mut().zip(values.iter()) {
+            let mut values = values.iter();
+            for j in tile_bounds.y_min..=tile_bounds.y_max {
+                for i in tile_bounds.x_min..=tile_bounds.x_max {
+                    if let Some(v) = values.next() {
+                        // Rows are rendered bottom to top.
+                        image.put_pixel(i, config.image_height - 1 - j, Rgb(*v));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(images)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algebra::{Point3, Vec3};
+    use crate::camera::Shake;
+    use crate::scene::Scenery;
+
+    /// A static object seen by a camera turning during the shutter interval
+    /// moves across the image.
+    #[test]
+    fn turning_camera_moves_static_objects() {
+        let mut config = AppConfig::default();
+        config.image_width = 40;
+        config.image_height = 40;
+
+        let mut scene = Scene::new(Scenery::CornellBox, 40, 40, true);
+        let tracer = RecursiveTracer::new(scene.clone(), config.clone());
+        assert_eq!(tracer.trace_aov(Aov::Motion, 20, 20), [0.0; 3]);
+
+        // Turn towards +x, which is to the left in the image, by the end time.
+        scene.camera = scene
+            .camera
+            .with_end_orientation(Point3::new(378.0, 278.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let tracer = RecursiveTracer::new(scene, config);
+        let [dx, dy, _] = tracer.trace_aov(Aov::Motion, 20, 20);
+
+        // The image moves right as the camera turns left.
+        assert!(dx > 1.0, "dx = {}", dx);
+        assert!(dy.abs() < 0.1, "dy = {}", dy);
+    }
+
+    /// A static object seen by a shaking camera moves across the image.
+    #[test]
+    fn shaking_camera_moves_static_objects() {
+        let mut config = AppConfig::default();
+        config.image_width = 40;
+        config.image_height = 40;
+
+        let mut scene = Scene::new(Scenery::CornellBox, 40, 40, true);
+        scene.camera = scene.camera.with_shake(Shake::new(2.0, 10.0, 3.0, 1));
+        let tracer = RecursiveTracer::new(scene, config);
+        let [dx, dy, _] = tracer.trace_aov(Aov::Motion, 20, 20);
+
+        assert!(dx.abs() + dy.abs() > 0.1, "dx = {}, dy = {}", dx, dy);
+    }
+}
@@ -0,0 +1,688 @@
+//! # Bidirectional path tracing
+//!
+//! A library for tracing paths from both the camera and the lights and
+//! connecting their vertices. Light that reaches the visible parts of a scene
+//! through small openings is found by the paths from the lights, which the
+//! recursive tracer rarely samples. The strategies are combined with
+//! multiple importance sampling using the balance heuristic.
+//!
+//! Paths from the lights start on emissive objects whose surface can be
+//! sampled, e.g. rectangles, spheres and triangles, optionally flipped,
+//! translated or rotated. Point, spot and directional lights, environment
+//! maps and the sky are only sampled from the camera paths. Light linking
+//! and shadow softness are ignored.
+
+use super::{Colour, Float, HitRecord, Point3, Ray, RecursiveTracer, Sampler, Scene};
+use crate::algebra::Vec3;
+use crate::common::{CosinePDF, PDF, PI};
+use crate::object::ArcHittable;
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use std::fmt;
+use std::sync::Arc;
+
+/// Algorithms used to estimate the light arriving at the camera.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum Integrator {
+    /// Paths traced from the camera that sample the lights at each bounce.
+    Path,
+
+    /// Paths traced from the camera and the lights connected to each other.
+    Bdpt,
+}
+
+impl fmt::Display for Integrator {
+    /// Display the integrator name as used on the command line.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Integrator::Path => "path",
+            Integrator::Bdpt => "bdpt",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Kinds of path vertices.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum VertexKind {
+    /// The camera at the start of a camera path.
+    Camera,
+
+    /// A point on an emitter at the start of a light path.
+    Light,
+
+    /// A point where a path hit an object.
+    Surface,
+}
+
+/// Models a vertex of a camera or light path.
+#[derive(Debug, Clone)]
+struct Vertex {
+    /// Kind of vertex.
+    kind: VertexKind,
+
+    /// Position of the vertex.
+    point: Point3,
+
+    /// Geometric normal. It faces the arriving ray on surfaces and the front
+    /// of the emitter on lights.
+    normal: Vec3,
+
+    /// Intersection and the ray that arrived at a surface.
+    hit: Option<(HitRecord, Ray)>,
+
+    /// Attenuation of the material at a surface that scatters with a density.
+    attenuation: Colour,
+
+    /// Whether the surface scatters with a density so the vertex can be
+    /// connected to vertices of the other path.
+    connectable: bool,
+
+    /// Radiance emitted by a light from its front and back.
+    emission: (Colour, Colour),
+
+    /// Throughput of the path up to the vertex divided by the density of
+    /// sampling it.
+    beta: Colour,
+
+    /// Whether the vertex scatters specularly.
+    delta: bool,
+
+    /// Density per unit area of sampling the vertex from the previous vertex
+    /// of its path.
+    pdf_fwd: Float,
+
+    /// Density per unit area of sampling the vertex from the next vertex of
+    /// its path, i.e. by the other path.
+    pdf_rev: Float,
+}
+
+impl Vertex {
+    /// Create a vertex of the given kind with no material or emission.
+    ///
+    /// * `kind` - Kind of vertex.
+    /// * `point` - Position of the vertex.
+    /// * `normal` - Geometric normal.
+    /// * `beta` - Throughput of the path up to the vertex.
+    fn new(kind: VertexKind, point: Point3, normal: Vec3, beta: Colour) -> Vertex {
+        Vertex {
+            kind,
+            point,
+            normal,
+            hit: None,
+            attenuation: Colour::zero(),
+            connectable: false,
+            emission: (Colour::zero(), Colour::zero()),
+            beta,
+            delta: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+        }
+    }
+
+    /// Create the vertex at the start of a camera path.
+    ///
+    /// * `ray` - The camera ray.
+    fn camera(ray: &Ray) -> Vertex {
+        let mut vertex = Vertex::new(
+            VertexKind::Camera,
+            ray.origin,
+            Vec3::zero(),
+            Colour::new(1.0, 1.0, 1.0),
+        );
+        vertex.pdf_fwd = 1.0;
+        vertex
+    }
+
+    /// Create the vertex at the start of a light path.
+    ///
+    /// * `point` - Point on the emitter.
+    /// * `normal` - Outward normal of the emitter.
+    /// * `emission` - Radiance emitted from the front and back.
+    /// * `pdf` - Density per unit area of sampling the point.
+    fn light(point: Point3, normal: Vec3, emission: (Colour, Colour), pdf: Float) -> Vertex {
+        let mut vertex = Vertex::new(
+            VertexKind::Light,
+            point,
+            normal,
+            Colour::new(1.0, 1.0, 1.0) / pdf,
+        );
+        vertex.emission = emission;
+        vertex.pdf_fwd = pdf;
+        vertex
+    }
+
+    /// Create a vertex where a ray hit an object.
+    ///
+    /// * `rec` - The intersection.
+    /// * `ray` - The ray that hit the object.
+    /// * `beta` - Throughput of the path up to the vertex.
+    fn surface(rec: HitRecord, ray: Ray, beta: Colour) -> Vertex {
+        let mut vertex = Vertex::new(VertexKind::Surface, rec.point, rec.geometric_normal, beta);
+        vertex.hit = Some((rec, ray));
+        vertex
+    }
+
+    /// Returns the light vertex equivalent to a surface vertex on an
+    /// emitter. Its front faces the arriving ray.
+    fn as_light(&self) -> Option<Vertex> {
+        let (rec, ray) = self.hit.as_ref()?;
+        let emission = (
+            rec.material.emission(ray, rec),
+            rec.material.emission(ray, &rec.flip_front_face()),
+        );
+        Some(Vertex::light(self.point, self.normal, emission, 1.0))
+    }
+
+    /// Returns the material's BSDF times the cosine of the direction towards
+    /// a point, or black if the vertex can't be connected.
+    ///
+    /// * `towards` - The point.
+    fn scattering(&self, towards: Point3) -> Colour {
+        match &self.hit {
+            Some((rec, ray_in)) if self.connectable => {
+                let direction = towards - self.point;
+                let scattered = Ray::new(rec.offset_origin(direction), direction, ray_in.time);
+                self.attenuation * rec.material.scattering_pdf(ray_in, rec, &scattered)
+            }
+            _ => Colour::zero(),
+        }
+    }
+
+    /// Returns the radiance a light vertex emits towards a point.
+    ///
+    /// * `towards` - The point.
+    fn emitted(&self, towards: Point3) -> Colour {
+        if (towards - self.point).dot(self.normal) > 0.0 {
+            self.emission.0
+        } else {
+            self.emission.1
+        }
+    }
+
+    /// Returns the density per unit solid angle of a light vertex emitting
+    /// towards a point. Directions are cosine weighted about the normal of
+    /// a side that emits, picked with equal probability if both do.
+    ///
+    /// * `towards` - The point.
+    fn emission_pdf(&self, towards: Point3) -> Float {
+        let cosine = (towards - self.point).unit_vector().dot(self.normal);
+        let front = self.emission.0.max_component() > 0.0;
+        let back = self.emission.1.max_component() > 0.0;
+
+        let emits = if cosine > 0.0 { front } else { back };
+        if !emits {
+            return 0.0;
+        }
+
+        let side = if front && back { 0.5 } else { 1.0 };
+        side * cosine.abs() / PI
+    }
+
+    /// Converts a density per unit solid angle of sampling the direction
+    /// from this vertex towards another into a density per unit area at the
+    /// other vertex.
+    ///
+    /// * `pdf` - Density per unit solid angle.
+    /// * `next` - The other vertex.
+    fn convert_density(&self, pdf: Float, next: &Vertex) -> Float {
+        let w = next.point - self.point;
+        let distance_squared = w.length_squared();
+        if distance_squared <= 0.0 {
+            return 0.0;
+        }
+
+        let pdf = pdf / distance_squared;
+        if next.kind == VertexKind::Camera {
+            pdf
+        } else {
+            pdf * next.normal.dot(w).abs() / distance_squared.sqrt()
+        }
+    }
+
+    /// Returns the density per unit area of this vertex sampling another
+    /// when the path arrived from a previous vertex.
+    ///
+    /// * `prev` - The previous vertex, `None` for lights.
+    /// * `next` - The vertex sampled.
+    fn pdf(&self, prev: Option<&Vertex>, next: &Vertex) -> Float {
+        let pdf = match (self.kind, &self.hit, prev) {
+            (VertexKind::Light, _, _) => self.emission_pdf(next.point),
+            (VertexKind::Surface, Some((rec, ray)), Some(prev)) => {
+                let ray_in = Ray::new(prev.point, self.point - prev.point, ray.time);
+                direction_pdf(rec, &ray_in, next.point - self.point)
+            }
+            _ => 0.0,
+        };
+        self.convert_density(pdf, next)
+    }
+}
+
+/// Returns the density per unit solid angle of a material scattering a ray
+/// in a direction.
+///
+/// * `rec` - The intersection.
+/// * `ray_in` - Ray arriving at the intersection, possibly from the other
+///   side of the surface than the ray that found it.
+/// * `direction` - Scattered direction.
+fn direction_pdf(rec: &HitRecord, ray_in: &Ray, direction: Vec3) -> Float {
+    // Materials expect the normal to face the arriving ray.
+    let rec = if ray_in.direction.dot(rec.geometric_normal) > 0.0 {
+        HitRecord {
+            front_face: !rec.front_face,
+            normal: -rec.normal,
+            geometric_normal: -rec.geometric_normal,
+            ..rec.clone()
+        }
+    } else {
+        rec.clone()
+    };
+
+    rec.material
+        .scatter(ray_in, &rec)
+        .and_then(|sr| sr.pdf)
+        .map_or(0.0, |pdf| pdf.value(direction))
+}
+
+/// Implements bidirectional path tracing on top of the recursive tracer,
+/// which provides the scene, intersections and background.
+pub struct BidirectionalTracer {
+    /// Emitters light paths start on and their areas.
+    emitters: Vec<(ArcHittable, Float)>,
+}
+
+impl BidirectionalTracer {
+    /// Create a new bidirectional path tracer for a scene.
+    ///
+    /// * `scene` - The scene.
+    pub fn new(scene: &Scene) -> BidirectionalTracer {
+        let emitters = scene
+            .emitters
+            .iter()
+            .map(|emitter| (Arc::clone(emitter), emitter.area()))
+            .filter(|(_, area)| *area > 0.0)
+            .collect();
+
+        BidirectionalTracer { emitters }
+    }
+
+    /// Returns the colour seen along a camera ray. Every vertex of a camera
+    /// path is connected to every vertex of a light path, except the camera
+    /// itself, and paths of the camera that hit emitters or escape the scene
+    /// are counted as well.
+    ///
+    /// * `tracer` - The recursive tracer.
+    /// * `ray` - The camera ray.
+    /// * `depth` - Maximum number of bounces of a path.
+    pub fn ray_colour(&self, tracer: &RecursiveTracer, ray: &Ray, depth: u32) -> Colour {
+        if depth == 0 {
+            return Colour::zero();
+        }
+
+        // Camera rays that miss see the backplate or background.
+        let rec = match tracer.camera_hit(ray) {
+            Some(rec) => rec,
+            None => return tracer.camera_path_colour(ray, depth, 0),
+        };
+
+        let max_vertices = depth as usize;
+        let mut camera = vec![Vertex::camera(ray)];
+        let escaped = self.random_walk(
+            tracer,
+            &mut camera,
+            *ray,
+            Colour::new(1.0, 1.0, 1.0),
+            1.0,
+            max_vertices + 1,
+            Some(rec),
+        );
+        let light = self.light_path(tracer, ray.time, max_vertices);
+
+        let mut colour = match escaped {
+            Some((beta, ray)) => beta * tracer.miss_colour(&ray),
+            None => Colour::zero(),
+        };
+
+        for t in 2..=camera.len() {
+            let pt = &camera[t - 1];
+
+            // Point, spot and directional lights can only be sampled from
+            // the camera paths.
+            if let (true, Some((rec, ray_in))) = (pt.connectable, &pt.hit) {
+                colour += pt.beta * tracer.light_sources_colour(ray_in, rec, pt.attenuation, 0);
+            }
+
+            for s in 0..=light.len().min(max_vertices + 1 - t) {
+                colour += self.connect(tracer, &camera, &light, s, t);
+            }
+        }
+
+        colour
+    }
+
+    /// Traces a path from a point on a randomly picked emitter.
+    ///
+    /// * `tracer` - The recursive tracer.
+    /// * `time` - Time of the camera ray.
+    /// * `max_vertices` - Maximum number of vertices of the path.
+    fn light_path(
+        &self,
+        tracer: &RecursiveTracer,
+        time: Float,
+        max_vertices: usize,
+    ) -> Vec<Vertex> {
+        let mut path = Vec::new();
+
+        let n = self.emitters.len();
+        if n == 0 || max_vertices == 0 {
+            return path;
+        }
+
+        let idx = ((Sampler::get_1d() * n as Float) as usize).min(n - 1);
+        let (emitter, area) = &self.emitters[idx];
+        let (point, normal) = match emitter.sample_area() {
+            Some(sample) => sample,
+            None => return path,
+        };
+
+        // Find the surface of the emitter at the point with a ray from its
+        // front for the emission on either side.
+        let epsilon = tracer.ray_epsilon;
+        let probe = Ray::new(point + normal * epsilon, -normal, time);
+        let rec = match emitter.hit(&probe, 0.0, 2.0 * epsilon) {
+            Some(rec) => rec,
+            None => return path,
+        };
+        let emission = (
+            rec.material.emission(&probe, &rec),
+            rec.material.emission(&probe, &rec.flip_front_face()),
+        );
+
+        let pdf_point = 1.0 / (n as Float * area);
+        let vertex = Vertex::light(point, normal, emission, pdf_point);
+
+        // Pick a side that emits and a cosine weighted direction from it.
+        let front = emission.0.max_component() > 0.0;
+        let back = emission.1.max_component() > 0.0;
+        if !front && !back {
+            return path;
+        }
+        let side = if front && (!back || Sampler::get_1d() < 0.5) {
+            normal
+        } else {
+            -normal
+        };
+        let direction = CosinePDF::new(side).generate();
+
+        let pdf_direction = vertex.emission_pdf(point + direction);
+        if pdf_direction <= 0.0 {
+            return path;
+        }
+        let beta = vertex.emitted(point + direction) * direction.dot(normal).abs()
+            / (pdf_point * pdf_direction);
+
+        path.push(vertex);
+        let ray = Ray::new(rec.offset_origin(direction), direction, time);
+        self.random_walk(
+            tracer,
+            &mut path,
+            ray,
+            beta,
+            pdf_direction,
+            max_vertices,
+            None,
+        );
+        path
+    }
+
+    /// Extends a path by scattering rays until a ray is absorbed, escapes the
+    /// scene or the path has the maximum number of vertices. Returns the
+    /// throughput and the ray if a ray escaped the scene.
+    ///
+    /// * `tracer` - The recursive tracer.
+    /// * `path` - The path. It must have at least one vertex.
+    /// * `ray` - Ray leaving the last vertex of the path.
+    /// * `beta` - Throughput of the path along the ray.
+    /// * `pdf` - Density per unit solid angle of sampling the ray.
+    /// * `max_vertices` - Maximum number of vertices of the path.
+    /// * `hit` - Intersection of the ray if already known.
+    #[allow(clippy::too_many_arguments)]
+    fn random_walk(
+        &self,
+        tracer: &RecursiveTracer,
+        path: &mut Vec<Vertex>,
+        ray: Ray,
+        beta: Colour,
+        pdf: Float,
+        max_vertices: usize,
+        hit: Option<HitRecord>,
+    ) -> Option<(Colour, Ray)> {
+        let (mut ray, mut beta, mut pdf, mut hit) = (ray, beta, pdf, hit);
+
+        while path.len() < max_vertices {
+            let rec = match hit.take().or_else(|| {
+                let (epsilon, limit) = tracer.ray_range(&ray);
+                tracer.scene.world.hit(&ray, epsilon, limit)
+            }) {
+                Some(rec) => rec,
+                None => return Some((beta, ray)),
+            };
+
+            let prev = path.len() - 1;
+            let mut vertex = Vertex::surface(rec.clone(), ray, beta);
+            vertex.pdf_fwd = path[prev].convert_density(pdf, &vertex);
+
+            let sr = match rec.material.scatter(&ray, &rec) {
+                Some(sr) => sr,
+                None => {
+                    path.push(vertex);
+                    break;
+                }
+            };
+
+            if let Some(specular_ray) = sr.specular_ray {
+                vertex.delta = true;
+                path.push(vertex);
+                path[prev].pdf_rev = 0.0;
+
+                beta *= sr.attenuation;
+                pdf = 0.0;
+                ray = specular_ray;
+            } else if let Some(scatter_pdf) = sr.pdf {
+                vertex.connectable = true;
+                vertex.attenuation = sr.attenuation;
+
+                let direction = scatter_pdf.generate();
+                let scattered = Ray::new(rec.offset_origin(direction), direction, ray.time);
+                let pdf_val = scatter_pdf.value(direction);
+                let f = sr.attenuation * rec.material.scattering_pdf(&ray, &rec, &scattered);
+
+                // Density of scattering back towards the previous vertex.
+                let reverse = Ray::new(rec.point + direction, -direction, ray.time);
+                let pdf_rev = direction_pdf(&rec, &reverse, path[prev].point - rec.point);
+                path[prev].pdf_rev = vertex.convert_density(pdf_rev, &path[prev]);
+                path.push(vertex);
+
+                if pdf_val <= 0.0 || f.max_component() <= 0.0 {
+                    break;
+                }
+                beta = beta * f / pdf_val;
+                pdf = pdf_val;
+                ray = scattered;
+            } else {
+                path.push(vertex);
+                break;
+            }
+        }
+
+        None
+    }
+
+    /// Returns the weighted contribution of the path made of the first `t`
+    /// vertices of the camera path and the first `s` vertices of the light
+    /// path.
+    ///
+    /// * `tracer` - The recursive tracer.
+    /// * `camera` - The camera path.
+    /// * `light` - The light path.
+    /// * `s` - Number of light path vertices.
+    /// * `t` - Number of camera path vertices, at least 2.
+    fn connect(
+        &self,
+        tracer: &RecursiveTracer,
+        camera: &[Vertex],
+        light: &[Vertex],
+        s: usize,
+        t: usize,
+    ) -> Colour {
+        let pt = &camera[t - 1];
+
+        let colour = if s == 0 {
+            // The camera path hit an emitter.
+            match &pt.hit {
+                Some((rec, ray_in)) => pt.beta * rec.material.emission(ray_in, rec),
+                None => Colour::zero(),
+            }
+        } else {
+            let qs = &light[s - 1];
+            if !pt.connectable || (qs.kind != VertexKind::Light && !qs.connectable) {
+                return Colour::zero();
+            }
+
+            let distance_squared = (qs.point - pt.point).length_squared();
+            let f_qs = if qs.kind == VertexKind::Light {
+                let w = (pt.point - qs.point).unit_vector();
+                qs.emitted(pt.point) * w.dot(qs.normal).abs()
+            } else {
+                qs.scattering(pt.point)
+            };
+            let colour = pt.beta * pt.scattering(qs.point) * f_qs * qs.beta / distance_squared;
+
+            if colour.max_component() <= 0.0 || !self.visible(tracer, pt, qs) {
+                return Colour::zero();
+            }
+            colour
+        };
+
+        if colour.max_component() <= 0.0 {
+            return Colour::zero();
+        }
+        colour * self.mis_weight(tracer, camera, light, s, t)
+    }
+
+    /// Returns true if nothing blocks the segment between a camera path
+    /// vertex and a light path vertex.
+    ///
+    /// * `tracer` - The recursive tracer.
+    /// * `pt` - The camera path vertex.
+    /// * `qs` - The light path vertex.
+    fn visible(&self, tracer: &RecursiveTracer, pt: &Vertex, qs: &Vertex) -> bool {
+        let (rec, ray_in) = match &pt.hit {
+            Some(hit) => hit,
+            None => return false,
+        };
+
+        let origin = rec.offset_origin(qs.point - pt.point);
+        let ray = Ray::new(origin, qs.point - origin, ray_in.time);
+        let (epsilon, _) = tracer.ray_range(&ray);
+        tracer
+            .scene
+            .world
+            .hit(&ray, epsilon, 1.0 - epsilon)
+            .is_none()
+    }
+
+    /// Returns the density per unit area of a light path starting at the
+    /// point a camera path hit an emitter, or 0 if light paths can't start
+    /// on the emitter.
+    ///
+    /// * `tracer` - The recursive tracer.
+    /// * `prev` - The camera path vertex before the emitter.
+    /// * `pt` - The camera path vertex on the emitter.
+    fn light_origin_pdf(&self, tracer: &RecursiveTracer, prev: &Vertex, pt: &Vertex) -> Float {
+        let time = pt.hit.as_ref().map_or(0.0, |(_, ray)| ray.time);
+        let ray = Ray::new(prev.point, pt.point - prev.point, time);
+        let (epsilon, _) = tracer.ray_range(&ray);
+
+        let n = self.emitters.len() as Float;
+        self.emitters
+            .iter()
+            .find(|(emitter, _)| emitter.hit(&ray, 1.0 - epsilon, 1.0 + epsilon).is_some())
+            .map_or(0.0, |(_, area)| 1.0 / (n * area))
+    }
+
+    /// Returns the multiple importance sampling weight of a path using the
+    /// balance heuristic over the strategies that could have sampled it.
+    ///
+    /// * `tracer` - The recursive tracer.
+    /// * `camera` - The camera path.
+    /// * `light` - The light path.
+    /// * `s` - Number of light path vertices.
+    /// * `t` - Number of camera path vertices, at least 2.
+    fn mis_weight(
+        &self,
+        tracer: &RecursiveTracer,
+        camera: &[Vertex],
+        light: &[Vertex],
+        s: usize,
+        t: usize,
+    ) -> Float {
+        // Densities and delta flags of the vertices of the connected path.
+        let density = |v: &Vertex| (v.pdf_fwd, v.pdf_rev, v.delta);
+        let mut cam: Vec<(Float, Float, bool)> = camera[..t].iter().map(density).collect();
+        let mut lig: Vec<(Float, Float, bool)> = light[..s].iter().map(density).collect();
+
+        // Reverse densities of the vertices next to the connection.
+        let pt = &camera[t - 1];
+        let pt_minus = &camera[t - 2];
+        if s == 0 {
+            // Only camera paths hit emitters light paths can't start on.
+            let origin = self.light_origin_pdf(tracer, pt_minus, pt);
+            if origin <= 0.0 {
+                return 1.0;
+            }
+            cam[t - 1].1 = origin;
+            cam[t - 2].1 = pt.as_light().map_or(0.0, |l| l.pdf(None, pt_minus));
+        } else {
+            let qs = &light[s - 1];
+            let qs_minus = if s > 1 { Some(&light[s - 2]) } else { None };
+            cam[t - 1].1 = qs.pdf(qs_minus, pt);
+            cam[t - 2].1 = pt.pdf(Some(qs), pt_minus);
+            lig[s - 1].1 = pt.pdf(Some(pt_minus), qs);
+            if let Some(qs_minus) = qs_minus {
+                lig[s - 2].1 = qs.pdf(Some(pt), qs_minus);
+            }
+            lig[s - 1].2 = false;
+        }
+        cam[t - 1].2 = false;
+
+        // Delta densities are 0 so they are skipped by the delta flags.
+        let remap = |pdf: Float| if pdf != 0.0 { pdf } else { 1.0 };
+
+        // Strategies with fewer camera vertices. Paths from the lights aren't
+        // connected to the camera so at least 2 camera vertices remain.
+        let mut sum = 0.0;
+        let mut ri = 1.0;
+        for i in (2..t).rev() {
+            ri *= remap(cam[i].1) / remap(cam[i].0);
+            if !cam[i].2 && !cam[i - 1].2 {
+                sum += ri;
+            }
+        }
+
+        // Strategies with fewer light vertices.
+        let mut ri = 1.0;
+        for i in (0..s).rev() {
+            ri *= remap(lig[i].1) / remap(lig[i].0);
+            let delta_before = i > 0 && lig[i - 1].2;
+            if !lig[i].2 && !delta_before {
+                sum += ri;
+            }
+        }
+
+        1.0 / (1.0 + sum)
+    }
+}
@@ -0,0 +1,154 @@
+//! # Energy audit
+//!
+//! A library for checking that materials conserve energy. The throughput
+//! weight of every scattering event, i.e. the attenuation times the ratio of
+//! the scattering PDF to the sampling PDF, is summed per material. Its mean
+//! estimates the directional albedo of the material which can't exceed 1, so
+//! materials whose mean is above 1 by more than the sampling error are
+//! flagged as gaining energy.
+
+use super::{Colour, Float};
+use crate::material::ArcMaterial;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Number of standard errors the mean energy has to be above 1 for a
+/// material to be flagged.
+const CONFIDENCE_Z: Float = 3.0;
+
+/// Mean energy above 1 that is always ignored, e.g. rounding errors of
+/// specular materials.
+const TOLERANCE: Float = 1e-3;
+
+/// Throughput weights of the scattering events of a material.
+#[derive(Debug, Clone)]
+struct MaterialEnergy {
+    /// Description of the material.
+    name: String,
+
+    /// Number of scattering events.
+    samples: u64,
+
+    /// Sum of the weights.
+    sum: Colour,
+
+    /// Sum of the squared maximum component of the weights.
+    sum_squared: Float,
+
+    /// Largest component of any weight.
+    max: Float,
+}
+
+impl MaterialEnergy {
+    /// Returns the mean weight.
+    fn mean(&self) -> Colour {
+        self.sum / self.samples.max(1) as Float
+    }
+
+    /// Returns the standard error of the mean of the maximum component of
+    /// the weights.
+    fn standard_error(&self) -> Float {
+        if self.samples < 2 {
+            return 0.0;
+        }
+        let n = self.samples as Float;
+        let mean = self.mean().max_component();
+        let variance = (self.sum_squared / n - mean * mean).max(0.0) * n / (n - 1.0);
+        (variance / n).sqrt()
+    }
+
+    /// Returns true if the material returns more energy than it receives.
+    fn gains_energy(&self) -> bool {
+        let excess = self.mean().max_component() - 1.0;
+        excess > TOLERANCE && excess > CONFIDENCE_Z * self.standard_error()
+    }
+}
+
+/// Collects throughput weights of scattering events per material.
+#[derive(Debug, Default)]
+pub struct EnergyAudit {
+    /// Weights keyed by the address of the material.
+    materials: Mutex<HashMap<usize, MaterialEnergy>>,
+}
+
+impl EnergyAudit {
+    /// Create a new empty audit.
+    pub fn new() -> Arc<EnergyAudit> {
+        Arc::new(EnergyAudit::default())
+    }
+
+    /// Record the throughput weight of a scattering event.
+    ///
+    /// * `material` - The material that scattered the ray.
+    /// * `weight`   - Throughput weight of the event.
+    pub fn record(&self, material: &ArcMaterial, weight: Colour) {
+        if !weight.is_finite() {
+            return;
+        }
+
+        let key = Arc::as_ptr(material) as *const () as usize;
+
+        let mut materials = self.materials.lock().unwrap();
+        let energy = materials.entry(key).or_insert_with(|| MaterialEnergy {
+            name: material.to_string(),
+            samples: 0,
+            sum: Colour::zero(),
+            sum_squared: 0.0,
+            max: 0.0,
+        });
+
+        let m = weight.max_component();
+        energy.samples += 1;
+        energy.sum += weight;
+        energy.sum_squared += m * m;
+        energy.max = energy.max.max(m);
+    }
+
+    /// Returns the number of materials that gain energy.
+    pub fn flagged(&self) -> usize {
+        let materials = self.materials.lock().unwrap();
+        materials.values().filter(|e| e.gains_energy()).count()
+    }
+}
+
+impl fmt::Display for EnergyAudit {
+    /// Display the audit with one line per material, starting with the
+    /// material with the highest mean energy.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut materials: Vec<MaterialEnergy> =
+            self.materials.lock().unwrap().values().cloned().collect();
+        materials.sort_by(|a, b| {
+            let (ma, mb) = (a.mean().max_component(), b.mean().max_component());
+            mb.total_cmp(&ma)
+        });
+
+        writeln!(f, "Energy audit of {} materials:", materials.len())?;
+        writeln!(
+            f,
+            "{:>12} {:>26} {:>10}  material",
+            "events", "mean energy (r, g, b)", "max"
+        )?;
+
+        for e in materials.iter() {
+            let mean = e.mean();
+            writeln!(
+                f,
+                "{:>12} {:>26} {:>10.4}  {}{}",
+                e.samples,
+                format!("({:.4}, {:.4}, {:.4})", mean.x(), mean.y(), mean.z()),
+                e.max,
+                e.name,
+                if e.gains_energy() {
+                    "  <-- GAINS ENERGY"
+                } else {
+                    ""
+                }
+            )?;
+        }
+
+        Ok(())
+    }
+}
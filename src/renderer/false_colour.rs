@@ -0,0 +1,107 @@
+//! # FalseColour
+//!
+//! A library for visualizing per-pixel sampling statistics as false colour
+//! images.
+
+use super::{AppConfig, Colour, Float, PixelStats};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use std::fmt;
+
+/// Viridis colour map approximated by 6th degree polynomials per channel.
+const VIRIDIS: [[Float; 7]; 3] = [
+    [
+        0.277_727_327_168_290_9,
+        0.105_093_043_108_577_9,
+        -0.330_861_828_725_556_6,
+        -4.634_230_498_983_486,
+        6.228_269_936_347_081,
+        4.776_384_997_670_288,
+        -5.435_455_855_934_631,
+    ],
+    [
+        0.005_407_344_544_966_578,
+        1.404_613_529_898_575,
+        0.214_847_559_468_213,
+        -5.799_100_973_351_585,
+        14.179_933_763_856_79,
+        -13.745_145_107_630_77,
+        4.645_852_612_178_535,
+    ],
+    [
+        0.334_099_838_177_362_3,
+        1.384_590_162_594_685,
+        0.095_095_163_028_169_97,
+        -19.332_440_956_349_86,
+        56.690_552_600_815_69,
+        -65.353_032_150_190_45,
+        26.312_435_378_274_05,
+    ],
+];
+
+/// Per-pixel statistics that can be rendered instead of the image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum Visualization {
+    /// Number of samples taken relative to the maximum.
+    Samples,
+
+    /// Standard deviation `s` of the sample luminance mapped to
+    /// `s / (1 + s)`.
+    Variance,
+}
+
+impl fmt::Display for Visualization {
+    /// Display the visualization name as used on the command line.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Visualization::Samples => "samples",
+            Visualization::Variance => "variance",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Visualization {
+    /// Returns the linear false colour of a pixel.
+    ///
+    /// * `stats` - Sampling statistics of the pixel.
+    /// * `config` - Program configuration.
+    pub fn colour(&self, stats: &PixelStats, config: &AppConfig) -> Colour {
+        let t = match self {
+            Visualization::Samples => {
+                let mut max_samples = config.max_samples() as Float;
+                if config.dof_adaptive {
+                    max_samples *= config.dof_max_scale.max(1.0);
+                }
+                stats.samples as Float / max_samples
+            }
+
+            Visualization::Variance => {
+                let s = stats.variance.sqrt();
+                s / (1.0 + s)
+            }
+        };
+
+        // The colour map is defined for display so undo the gamma applied
+        // when the image is saved.
+        config.gamma.decode_colour(viridis(t))
+    }
+}
+
+/// Returns the viridis colour for a value in [0, 1].
+///
+/// * `t` - The value. Values outside [0, 1] are clamped.
+pub fn viridis(t: Float) -> Colour {
+    let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+
+    let channel = |c: &[Float; 7]| c.iter().rev().fold(0.0, |acc, k| acc * t + k);
+
+    Colour::new(
+        channel(&VIRIDIS[0]).clamp(0.0, 1.0),
+        channel(&VIRIDIS[1]).clamp(0.0, 1.0),
+        channel(&VIRIDIS[2]).clamp(0.0, 1.0),
+    )
+}
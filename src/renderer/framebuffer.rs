@@ -0,0 +1,233 @@
+//! # Framebuffer
+//!
+//! A library for converting and saving the linear floating point images
+//! produced by the renderer.
+
+use super::{clamp, Colour, Float};
+use image::{ImageFormat, Rgb32FImage, Rgba, RgbaImage};
+use std::fmt;
+
+/// Transfer function used to encode linear values for display. The default
+/// is the legacy gamma 2.0 so existing renders are reproducible.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum Gamma {
+    /// Gamma 2.0 (square root) used by earlier versions of the renderer.
+    /// Existing reference images were encoded with this.
+    #[default]
+    Legacy,
+
+    /// Power law with the given gamma value, e.g. 2.2.
+    Power(Float),
+
+    /// The piecewise sRGB transfer function.
+    Srgb,
+}
+
+impl fmt::Display for Gamma {
+    /// Display the transfer function as used on the command line.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Gamma::Legacy => write!(f, "2.0-legacy"),
+            Gamma::Power(g) => write!(f, "{}", g),
+            Gamma::Srgb => write!(f, "srgb"),
+        }
+    }
+}
+
+impl Gamma {
+    /// Returns the display value of a linear value. Negative values are
+    /// treated as 0.
+    ///
+    /// * `v` - Linear value.
+    pub fn encode(&self, v: Float) -> Float {
+        let v = v.max(0.0);
+        match self {
+            Gamma::Legacy => v.sqrt(),
+            Gamma::Power(g) => v.powf(1.0 / g),
+            Gamma::Srgb => {
+                if v <= 0.0031308 {
+                    12.92 * v
+                } else {
+                    1.055 * v.powf(1.0 / 2.4) - 0.055
+                }
+            }
+        }
+    }
+
+    /// Returns the linear value of a display value. Negative values are
+    /// treated as 0.
+    ///
+    /// * `v` - Display value.
+    pub fn decode(&self, v: Float) -> Float {
+        let v = v.max(0.0);
+        match self {
+            Gamma::Legacy => v * v,
+            Gamma::Power(g) => v.powf(*g),
+            Gamma::Srgb => {
+                if v <= 0.04045 {
+                    v / 12.92
+                } else {
+                    ((v + 0.055) / 1.055).powf(2.4)
+                }
+            }
+        }
+    }
+
+    /// Returns the display colour of a linear colour.
+    ///
+    /// * `c` - Linear colour.
+    pub fn encode_colour(&self, c: Colour) -> Colour {
+        Colour::new(self.encode(c.x()), self.encode(c.y()), self.encode(c.z()))
+    }
+
+    /// Returns the linear colour of a display colour.
+    ///
+    /// * `c` - Display colour.
+    pub fn decode_colour(&self, c: Colour) -> Colour {
+        Colour::new(self.decode(c.x()), self.decode(c.y()), self.decode(c.z()))
+    }
+}
+
+/// Convert a linear floating point image to a gamma corrected 8-bit image.
+/// Values outside [0, 1] are clamped.
+///
+/// * `image` - The linear image.
+/// * `gamma` - Transfer function used to encode the image.
+pub fn to_rgba_image(image: &Rgb32FImage, gamma: Gamma) -> RgbaImage {
+    RgbaImage::from_fn(image.width(), image.height(), |i, j| {
+        let p = image.get_pixel(i, j);
+        let c = Colour::new(p[0] as Float, p[1] as Float, p[2] as Float);
+
+        // Replace NaN components with zero.
+        let c = Colour::new(
+            if c.x().is_nan() { 0.0 } else { c.x() },
+            if c.y().is_nan() { 0.0 } else { c.y() },
+            if c.z().is_nan() { 0.0 } else { c.z() },
+        );
+
+        let c = gamma.encode_colour(c);
+        Rgba(
+            Colour::new(
+                256.0 * clamp(c.x(), 0.0, 0.999),
+                256.0 * clamp(c.y(), 0.0, 0.999),
+                256.0 * clamp(c.z(), 0.0, 0.999),
+            )
+            .to_rgba(),
+        )
+    })
+}
+
+/// Returns true if the file extension of a path is a high dynamic range
+/// image format that can store the linear image as is.
+///
+/// * `path` - Output file path.
+fn is_hdr_path(path: &str) -> bool {
+    matches!(
+        ImageFormat::from_path(path),
+        Ok(ImageFormat::Hdr) | Ok(ImageFormat::OpenExr)
+    )
+}
+
+/// Save a linear floating point image. High dynamic range formats (`.exr` and
+/// `.hdr`) are written as is. Other formats are gamma corrected and clamped.
+///
+/// * `image` - The linear image.
+/// * `path`  - Output file path. File extension determines image type.
+/// * `gamma` - Transfer function used to encode other formats.
+pub fn save_framebuffer(image: &Rgb32FImage, path: &str, gamma: Gamma) -> Result<(), String> {
+    let result = if is_hdr_path(path) {
+        image.save(path)
+    } else {
+        to_rgba_image(image, gamma).save(path)
+    };
+    result.map_err(|e| format!("{}", e))
+}
+
+/// Load an image with display values in [0, 1]. High dynamic range images
+/// are gamma corrected and clamped like images saved in other formats so
+/// renders saved in any format can be compared.
+///
+/// * `path`  - Image file path.
+/// * `gamma` - Transfer function used to encode high dynamic range images.
+pub fn load_display_image(path: &str, gamma: Gamma) -> Result<Rgb32FImage, String> {
+    let image = image::open(path)
+        .map_err(|e| format!("{}: {}", path, e))?
+        .to_rgb32f();
+
+    if is_hdr_path(path) {
+        Ok(Rgb32FImage::from_fn(
+            image.width(),
+            image.height(),
+            |i, j| {
+                let p = image.get_pixel(i, j);
+                image::Rgb(p.0.map(|c| (gamma.encode(c as Float) as f32).min(1.0)))
+            },
+        ))
+    } else {
+        Ok(image)
+    }
+}
+
+/// Load an image with linear values. High dynamic range images are loaded as
+/// is and the gamma applied when saving other formats is undone.
+///
+/// * `path`  - Image file path.
+/// * `gamma` - Transfer function the image was saved with.
+pub fn load_linear_image(path: &str, gamma: Gamma) -> Result<Rgb32FImage, String> {
+    let image = image::open(path)
+        .map_err(|e| format!("{}: {}", path, e))?
+        .to_rgb32f();
+
+    if is_hdr_path(path) {
+        Ok(image)
+    } else {
+        Ok(Rgb32FImage::from_fn(
+            image.width(),
+            image.height(),
+            |i, j| {
+                let p = image.get_pixel(i, j);
+                image::Rgb(p.0.map(|c| gamma.decode(c as Float) as f32))
+            },
+        ))
+    }
+}
+
+/// Returns the output path for a preview image by inserting `preview`
+/// before the extension of the beauty image path, e.g. `image.png` becomes
+/// `image.preview.png`.
+///
+/// * `output_path` - Path of the beauty image.
+pub fn preview_output_path(output_path: &str) -> String {
+    match output_path.rfind('.') {
+        Some(idx) if idx > 0 => format!("{}.preview{}", &output_path[..idx], &output_path[idx..]),
+        _ => format!("{}.preview.png", output_path),
+    }
+}
+
+/// File extension of auxiliary images. OpenEXR keeps negative and infinite
+/// values so it is used when available.
+#[cfg(feature = "exr")]
+pub const AUX_EXTENSION: &str = "exr";
+
+/// File extension of auxiliary images. Radiance HDR is the only floating
+/// point format available without the `exr` feature.
+#[cfg(not(feature = "exr"))]
+pub const AUX_EXTENSION: &str = "hdr";
+
+/// Returns the output path for an auxiliary image by inserting its name
+/// before the extension of the beauty image path. Auxiliary images are
+/// written as floating point images so the values are not quantized, e.g.
+/// `image.png` becomes `image.normal.exr`, or `image.normal.hdr` without the
+/// `exr` feature.
+///
+/// * `output_path` - Path of the beauty image.
+/// * `name`        - Name of the auxiliary image.
+pub fn aux_output_path(output_path: &str, name: &str) -> String {
+    let stem = match output_path.rfind('.') {
+        Some(idx) if idx > 0 => &output_path[..idx],
+        _ => output_path,
+    };
+    format!("{}.{}.{}", stem, name, AUX_EXTENSION)
+}
@@ -0,0 +1,257 @@
+//! # Gradient domain
+//!
+//! A library for experimental gradient-domain path tracing.
+//!
+//! Each sample traces a base path through a pixel and two shifted paths
+//! through its right and upper neighbours reusing the same random numbers
+//! (random replay). The differences between the shifted and base paths are
+//! low variance estimates of the image gradients since the paths are highly
+//! correlated. The final image is reconstructed from a coarse (primal) image
+//! and the gradients by solving a screened Poisson equation.
+
+use super::{
+    get_tile_bounds, interrupted, AppConfig, Colour, Float, Progress, Random, RecursiveTracer,
+    Scene, ThreadPool, TileBounds,
+};
+use image::{Rgb, Rgb32FImage};
+use std::sync::{mpsc, Arc};
+
+/// Weight of the primal image relative to the gradients in the screened
+/// Poisson reconstruction.
+const ALPHA: Float = 0.2;
+
+/// Number of Gauss-Seidel iterations used for the reconstruction.
+const ITERATIONS: usize = 200;
+
+/// Linear radiance estimates for a pixel.
+#[derive(Copy, Clone)]
+struct GradientSample {
+    /// Estimate for the pixel.
+    primal: Colour,
+
+    /// Estimate for the difference to the pixel on the right.
+    dx: Colour,
+
+    /// Estimate for the difference to the pixel above.
+    dy: Colour,
+}
+
+impl RecursiveTracer {
+    /// Returns the primal and gradient estimates for a pixel averaged over
+    /// all samples.
+    ///
+    /// * `i` - Pixel x-coordinate.
+    /// * `j` - Pixel y-coordinate.
+    fn trace_gradients(&self, i: u32, j: u32) -> GradientSample {
+        let x = i as Float;
+        let y = j as Float;
+
+        let w = self.config.image_width as Float;
+        let h = self.config.image_height as Float;
+        let n = self.config.max_samples();
+
+        let has_dx = i + 1 < self.config.image_width;
+        let has_dy = j + 1 < self.config.image_height;
+
+        let mut sum = GradientSample {
+            primal: Colour::zero(),
+            dx: Colour::zero(),
+            dy: Colour::zero(),
+        };
+
+        for _ in 0..n {
+            let state = Random::save();
+
+            let s = Random::samples::<Float>(2);
+            let base = self.radiance((x + s[0]) / w, (y + s[1]) / h);
+            sum.primal += base;
+
+            if has_dx {
+                Random::restore(&state);
+                let s = Random::samples::<Float>(2);
+                let shifted = self.radiance((x + 1.0 + s[0]) / w, (y + s[1]) / h);
+                sum.dx += shifted - base;
+            }
+
+            if has_dy {
+                Random::restore(&state);
+                let s = Random::samples::<Float>(2);
+                let shifted = self.radiance((x + s[0]) / w, (y + 1.0 + s[1]) / h);
+                sum.dy += shifted - base;
+            }
+        }
+
+        let inv_n = 1.0 / n.max(1) as Float;
+        GradientSample {
+            primal: sanitize(sum.primal) * inv_n,
+            dx: sanitize(sum.dx) * inv_n,
+            dy: sanitize(sum.dy) * inv_n,
+        }
+    }
+
+    /// Returns the linear radiance along a camera ray.
+    ///
+    /// * `u` - Horizontal parameter along the image plane.
+    /// * `v` - Vertical parameter along the image plane.
+    fn radiance(&self, u: Float, v: Float) -> Colour {
+        let ray = self.scene.camera.get_ray(u, v);
+        self.ray_colour(&ray, self.config.max_depth)
+    }
+}
+
+/// Render a scene with gradient-domain path tracing. The returned linear
+/// image is oriented with the first row at the top.
+///
+/// * `scene`    - The scene to render.
+/// * `config`   - Program configuration.
+/// * `progress` - Receives the fraction of the render completed as tiles
+///   finish and messages about the render.
+pub fn render_gradient_domain(
+    scene: Scene,
+    config: &AppConfig,
+    progress: &dyn Progress,
+) -> Result<Rgb32FImage, String> {
+    let pool = ThreadPool::build(config.threads(), config.worker_options())
+        .map_err(|e| format!("{:?}", e))?;
+    for warning in pool.warnings() {
+        progress.message(warning);
+    }
+
+    let renderer = Arc::new(RecursiveTracer::new(scene, config.clone()));
+
+    let width = config.image_width as usize;
+    let height = config.image_height as usize;
+
+    // Workers send back the estimates for each tile.
+    let (sender, receiver) = mpsc::channel::<(TileBounds, Vec<GradientSample>)>();
+
+    let n_tiles = config.n_tiles();
+    for tile_idx in 0..n_tiles {
+        // Stop queuing tiles once an interrupt is requested.
+        if interrupted() {
+            break;
+        }
+
+        let renderer = Arc::clone(&renderer);
+        let sender = sender.clone();
+
+        pool.execute(move || {
+            // Drain tiles that were queued before the interrupt.
+            if interrupted() {
+                return;
+            }
+
+            let config = &renderer.config;
+            let tile_bounds = get_tile_bounds(&config.render_context(), tile_idx);
+
+            let mut samples = Vec::new();
+            for j in tile_bounds.y_min..=tile_bounds.y_max {
+                for i in tile_bounds.x_min..=tile_bounds.x_max {
+                    samples.push(renderer.trace_gradients(i, j));
+                }
+            }
+
+            // The receiver only goes away if rendering was abandoned.
+            let _ = sender.send((tile_bounds, samples));
+        });
+    }
+
+    // Drop our sender so the receiver terminates once all tiles are done.
+    drop(sender);
+
+    let empty = GradientSample {
+        primal: Colour::zero(),
+        dx: Colour::zero(),
+        dy: Colour::zero(),
+    };
+    let mut buffer = vec![empty; width * height];
+
+    for (done, (tile_bounds, samples)) in receiver.iter().enumerate() {
+        let mut samples = samples.into_iter();
+        for j in tile_bounds.y_min..=tile_bounds.y_max {
+            for i in tile_bounds.x_min..=tile_bounds.x_max {
+                if let Some(sample) = samples.next() {
+                    buffer[j as usize * width + i as usize] = sample;
+                }
+            }
+        }
+
+        progress.update((done + 1) as f32 / n_tiles as f32);
+    }
+
+    progress.message("Reconstructing image from gradients");
+    let pixels = reconstruct(&buffer, width, height);
+
+    // Rows are rendered bottom to top.
+    let mut image = Rgb32FImage::new(config.image_width, config.image_height);
+    for (idx, colour) in pixels.iter().enumerate() {
+        let i = (idx % width) as u32;
+        let j = (idx / width) as u32;
+
+        let c = Colour::new(
+            colour.x().max(0.0),
+            colour.y().max(0.0),
+            colour.z().max(0.0),
+        );
+        image.put_pixel(i, config.image_height - 1 - j, Rgb(c.to_rgb32f()));
+    }
+
+    Ok(image)
+}
+
+/// Solve the screened Poisson equation for the image whose values are close
+/// to the primal estimates and whose finite differences are close to the
+/// gradient estimates (L2 reconstruction). Gauss-Seidel iterations are used
+/// starting from the primal image.
+///
+/// * `buffer` - Primal and gradient estimates in row major order.
+/// * `width`  - Image width in pixels.
+/// * `height` - Image height in pixels.
+fn reconstruct(buffer: &[GradientSample], width: usize, height: usize) -> Vec<Colour> {
+    let alpha2 = ALPHA * ALPHA;
+
+    let mut image: Vec<Colour> = buffer.iter().map(|s| s.primal).collect();
+
+    for _ in 0..ITERATIONS {
+        for y in 0..height {
+            for x in 0..width {
+                let idx = y * width + x;
+
+                let mut sum = buffer[idx].primal * alpha2;
+                let mut weight = alpha2;
+
+                // Forward differences to the right and above.
+                if x + 1 < width {
+                    sum = sum + image[idx + 1] - buffer[idx].dx;
+                    weight += 1.0;
+                }
+                if y + 1 < height {
+                    sum = sum + image[idx + width] - buffer[idx].dy;
+                    weight += 1.0;
+                }
+
+                // Forward differences from the left and below.
+                if x > 0 {
+                    sum = sum + image[idx - 1] + buffer[idx - 1].dx;
+                    weight += 1.0;
+                }
+                if y > 0 {
+                    sum = sum + image[idx - width] + buffer[idx - width].dy;
+                    weight += 1.0;
+                }
+
+                image[idx] = sum / weight;
+            }
+        }
+    }
+
+    image
+}
+
+/// Replace NaN and infinite components with zero.
+///
+/// * `c` - The colour.
+fn sanitize(c: Colour) -> Colour {
+    let f = |v: Float| if v.is_finite() { v } else { 0.0 };
+    Colour::new(f(c.x()), f(c.y()), f(c.z()))
+}
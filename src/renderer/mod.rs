@@ -2,11 +2,73 @@
 //!
 //! A library for renderering algorithm.
 
-use super::algebra::{Colour, Ray};
+mod anaglyph;
+mod aov;
+mod bdpt;
+mod energy_audit;
+mod false_colour;
+mod framebuffer;
+mod gradient_domain;
+mod path_export;
+mod progressive;
+mod watchdog;
+
+pub use self::anaglyph::render_anaglyph;
+pub use self::aov::{render_aovs, Aov};
+pub use self::bdpt::{BidirectionalTracer, Integrator};
+pub use self::energy_audit::EnergyAudit;
+pub use self::false_colour::{viridis, Visualization};
+pub use self::framebuffer::{
+    aux_output_path, load_display_image, load_linear_image, preview_output_path, save_framebuffer,
+    to_rgba_image, Gamma,
+};
+pub use self::gradient_domain::render_gradient_domain;
+pub use self::path_export::{path_summary, save_paths, trace_pixel_paths};
+pub use self::progressive::Accumulator;
+
+use super::algebra::{Colour, Point3, Ray};
 use super::app_config::AppConfig;
-use super::common::{Float, HittablePDF, MixturePDF, Random, INFINITY, PDF, RAY_EPSILON};
+use super::common::{
+    clamp, ArcPDF, EnvironmentPDF, Float, HittablePDF, Messages, MixturePDF, Progress, Random,
+    Sampler, INFINITY,
+};
+use super::light::ArcLight;
+use super::object::HitRecord;
 use super::scene::Scene;
-use std::sync::Arc;
+use super::server::PreviewServer;
+use super::signal::interrupted;
+use super::threadpool::{ThreadPool, WorkerOptions};
+use super::tiles::{get_tile_bounds, render_tile_cancellable, TileBounds};
+use image::{imageops, Rgb32FImage};
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use watchdog::{TileKey, Watchdog};
+
+thread_local! {
+    /// Number of samples traced by the current thread.
+    static SAMPLES_TRACED: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Returns the number of samples traced by the current thread. The difference
+/// before and after rendering a tile is the number of samples in the tile.
+pub fn samples_traced() -> u64 {
+    SAMPLES_TRACED.get()
+}
+
+/// Sampling statistics of a pixel.
+pub struct PixelStats {
+    /// Average linear colour.
+    pub colour: Colour,
+
+    /// Number of samples taken.
+    pub samples: u32,
+
+    /// Sample variance of the luminance.
+    pub variance: Float,
+}
 
 /// Implements recursive raytracer that uses importance sampling.
 pub struct RecursiveTracer {
@@ -15,35 +77,209 @@ pub struct RecursiveTracer {
 
     /// Application configuration.
     pub config: AppConfig,
+
+    /// Energy audit of the materials if enabled.
+    pub audit: Option<Arc<EnergyAudit>>,
+
+    /// Offset of rays leaving surfaces.
+    ray_epsilon: Float,
+
+    /// Longest distance along a ray tested for intersections.
+    ray_t_max: Float,
+
+    /// Lights sampled with shadow rays, including the sun of the sky.
+    light_sources: Vec<ArcLight>,
+
+    /// Bidirectional path tracer if selected as the integrator.
+    bidirectional: Option<BidirectionalTracer>,
 }
 
 impl RecursiveTracer {
-    /// Trace a ray through the scene return accumulated colour. The function will
-    /// generate multiple samples per pixel.
+    /// Create a new ray tracer for a scene.
+    ///
+    /// * `scene`  - The scene.
+    /// * `config` - Application configuration.
+    pub fn new(scene: Scene, config: AppConfig) -> RecursiveTracer {
+        let audit = if config.energy_audit {
+            Some(EnergyAudit::new())
+        } else {
+            None
+        };
+
+        let (ray_epsilon, ray_t_max) = scene.ray_limits();
+
+        // The sun only lights the scene with the sky, not an environment map.
+        let sun = match (&scene.environment, &scene.sky) {
+            (None, Some(sky)) => sky.sun(),
+            _ => None,
+        };
+        let light_sources = scene.light_sources.iter().cloned().chain(sun).collect();
+
+        let bidirectional = match config.integrator {
+            Integrator::Bdpt => Some(BidirectionalTracer::new(&scene)),
+            Integrator::Path => None,
+        };
+
+        RecursiveTracer {
+            scene,
+            config,
+            audit,
+            ray_epsilon,
+            ray_t_max,
+            light_sources,
+            bidirectional,
+        }
+    }
+
+    /// Trace a ray through the scene return the average linear colour. With
+    /// a visualization selected the false colour of the pixel's sampling
+    /// statistics is returned instead.
     ///
     /// * `i` - Pixel x-coordinate.
     /// * `j` - Pixel y-coordinate.
-    /// * `config` - Program configuration.
-    /// * `tracer` - The rendering algorithm.
     pub fn trace_ray(&self, i: u32, j: u32) -> Colour {
-        let x = i as Float;
-        let y = j as Float;
+        let stats = self.trace_pixel(i, j);
 
-        let w = self.config.image_width as Float;
-        let h = self.config.image_height as Float;
-        let n = self.config.samples_per_pixel;
+        match self.config.visualize {
+            Some(visualization) => visualization.colour(&stats, &self.config),
+            None => stats.colour,
+        }
+    }
+
+    /// Trace rays through a pixel and return the average linear colour with
+    /// sampling statistics. The function will generate multiple samples per
+    /// pixel. With a noise threshold, sampling stops early once the
+    /// confidence interval of the pixel luminance is narrow enough.
+    ///
+    /// * `i` - Pixel x-coordinate.
+    /// * `j` - Pixel y-coordinate.
+    fn trace_pixel(&self, i: u32, j: u32) -> PixelStats {
+        let max_samples = self.samples_for_pixel(i as Float, j as Float);
+        let min_samples = self.config.min_samples.clamp(1, max_samples);
 
-        (0..n)
-            .fold(Colour::zero(), |colour, _| {
-                let s = Random::samples::<Float>(2);
+        // Running sum of colours and running mean/variance of luminance
+        // using Welford's algorithm.
+        let mut sum = Colour::zero();
+        let mut mean = 0.0;
+        let mut m2 = 0.0;
 
-                let u = (x + s[0]) / w;
-                let v = (y + s[1]) / h;
+        let mut n = 0;
+        let mut valid = 0;
+        while n < max_samples {
+            let colour = self.trace_sample(i, j, n, max_samples);
+            n += 1;
 
-                let ray = self.scene.camera.get_ray(u, v);
-                colour + self.ray_colour(&ray, self.config.max_depth)
-            })
-            .to_colour_from_sample(n)
+            // Drop samples with NaN or infinite components rather than let
+            // them poison the pixel.
+            if !colour.is_finite() {
+                continue;
+            }
+            sum += colour;
+            valid += 1;
+
+            let l = colour.luminance();
+            let delta = l - mean;
+            mean += delta / valid as Float;
+            m2 += delta * (l - mean);
+
+            if let Some(threshold) = self.config.noise_threshold {
+                if n >= min_samples && converged(mean, m2, valid, threshold) {
+                    break;
+                }
+            }
+        }
+        PixelStats {
+            colour: if valid == 0 {
+                Colour::zero()
+            } else {
+                sum.average_sample(valid)
+            },
+            samples: n,
+            variance: if valid > 1 {
+                m2 / (valid - 1) as Float
+            } else {
+                0.0
+            },
+        }
+    }
+
+    /// Trace the samples of a progressive pass through a pixel. Each pass
+    /// takes the next `--progressive` samples of the pixel. Returns the sum
+    /// of the sample colours and the number of samples in the sum. Samples
+    /// with NaN or infinite components are dropped.
+    ///
+    /// * `i` - Pixel x-coordinate.
+    /// * `j` - Pixel y-coordinate.
+    /// * `pass` - Index of the pass.
+    pub fn trace_pass(&self, i: u32, j: u32, pass: u32) -> (Colour, u32) {
+        let max_samples = self.samples_for_pixel(i as Float, j as Float);
+        let per_pass = self.config.progressive.unwrap_or(max_samples).max(1);
+
+        let first = pass.saturating_mul(per_pass).min(max_samples);
+        let last = first.saturating_add(per_pass).min(max_samples);
+
+        let mut sum = Colour::zero();
+        let mut valid = 0;
+        for n in first..last {
+            let colour = self.trace_sample(i, j, n, max_samples);
+            if colour.is_finite() {
+                sum += colour;
+                valid += 1;
+            }
+        }
+
+        (sum, valid)
+    }
+
+    /// Trace a ray through a pixel for one of its samples.
+    ///
+    /// * `i` - Pixel x-coordinate.
+    /// * `j` - Pixel y-coordinate.
+    /// * `n` - Index of the sample within the pixel.
+    /// * `count` - Number of samples in the pixel.
+    fn trace_sample(&self, i: u32, j: u32, n: u32, count: u32) -> Colour {
+        Sampler::start_sample(self.config.sampler, i, j, n, count);
+        let (sx, sy) = Sampler::get_2d();
+
+        let u = (i as Float + sx) / self.config.image_width as Float;
+        let v = (j as Float + sy) / self.config.image_height as Float;
+
+        let ray = self.scene.camera.get_ray(u, v);
+        let colour = self.ray_colour(&ray, self.config.max_depth);
+        Sampler::finish();
+
+        SAMPLES_TRACED.set(SAMPLES_TRACED.get() + 1);
+        colour
+    }
+
+    /// Returns the number of samples to take for a pixel. With depth of field
+    /// aware sampling enabled, the sample count is scaled by the size of the
+    /// circle of confusion in pixels because defocused regions converge
+    /// slowest. The depth is estimated with a ray through the centre of the
+    /// lens and pixel.
+    ///
+    /// * `x` - Pixel x-coordinate.
+    /// * `y` - Pixel y-coordinate.
+    fn samples_for_pixel(&self, x: Float, y: Float) -> u32 {
+        let n = self.config.max_samples();
+
+        let camera = &self.scene.camera;
+        if !self.config.dof_adaptive || !camera.has_defocus() {
+            return n;
+        }
+
+        let u = (x + 0.5) / self.config.image_width as Float;
+        let v = (y + 0.5) / self.config.image_height as Float;
+        let ray = camera.get_centre_ray(u, v);
+
+        let depth = self
+            .camera_hit(&ray)
+            .map_or(INFINITY, |rec| camera.depth(rec.point));
+
+        let coc_pixels = camera.circle_of_confusion(depth) * self.config.image_width as Float;
+        let scale = (1.0 + coc_pixels).min(self.config.dof_max_scale.max(1.0));
+
+        (n as Float * scale).ceil() as u32
     }
 
     /// Recursively traces a ray through the scene and generates the colour seen
@@ -52,23 +288,91 @@ impl RecursiveTracer {
     /// * `ray` - The ray.
     /// * `depth` - Maximum depth for recursion.
     fn ray_colour(&self, ray: &Ray, depth: u32) -> Colour {
+        match &self.bidirectional {
+            Some(bdpt) => bdpt.ray_colour(self, ray, depth),
+            None => self.camera_path_colour(ray, depth, 0),
+        }
+    }
+
+    /// Traces a camera ray through the scene like `path_colour()`. Camera
+    /// rays only see geometry between the camera's clipping planes.
+    ///
+    /// * `ray` - The camera ray.
+    /// * `depth` - Maximum depth for recursion.
+    /// * `skip` - Number of path vertices whose emission is ignored.
+    fn camera_path_colour(&self, ray: &Ray, depth: u32, skip: u32) -> Colour {
+        if depth == 0 {
+            return Colour::zero();
+        }
+
+        let hit = self.camera_hit(ray).map(|rec| (rec, 1.0));
+
+        // The point where a camera ray crosses the focal plane projects to
+        // its position on the image even with a lens.
+        if let (None, Some(backplate), 0) = (&hit, &self.scene.backplate, skip) {
+            let camera = &self.scene.camera;
+            if let Some((s, t)) = camera.project(ray.origin + ray.direction) {
+                return backplate.value(s, t);
+            }
+        }
+
+        self.shade(ray, hit, depth, skip, 0)
+    }
+
+    /// Recursively traces a ray through the scene and generates the colour seen
+    /// at the image plane ignoring light emitted at the first `skip` vertices
+    /// of the path, where a ray escaping to the background counts as a vertex.
+    ///
+    /// * `ray` - The ray.
+    /// * `depth` - Maximum depth for recursion.
+    /// * `skip` - Number of path vertices whose emission is ignored.
+    /// * `from` - Light linking ID of the object the ray was scattered from,
+    ///   zero for camera rays.
+    fn path_colour(&self, ray: &Ray, depth: u32, skip: u32, from: u32) -> Colour {
         // Terminate the recursion if maximum depth is reached.
         if depth <= 0 {
             return Colour::zero();
         }
 
-        // Note the RAY_EPSILON is used to avoid starting the ray inside the
-        // surface caused due to floating point approximation errors generated
-        // by the intersection routine.
-        let hit = self.scene.world.hit(&ray, RAY_EPSILON, INFINITY);
+        let hit = self.hit_from(ray, from);
+        self.shade(ray, hit, depth, skip, from)
+    }
+
+    /// Returns the colour seen along a ray given its closest intersection
+    /// and the factor the emission at the intersection is scaled by.
+    ///
+    /// * `ray` - The ray.
+    /// * `hit` - The closest intersection and its emission scale.
+    /// * `depth` - Maximum depth for recursion.
+    /// * `skip` - Number of path vertices whose emission is ignored.
+    /// * `from` - Light linking ID of the object the ray was scattered from,
+    ///   zero for camera rays.
+    fn shade(
+        &self,
+        ray: &Ray,
+        hit: Option<(HitRecord, Float)>,
+        depth: u32,
+        skip: u32,
+        from: u32,
+    ) -> Colour {
         if hit.is_none() {
-            return (self.scene.background)(ray);
+            if skip > 0 {
+                return Colour::zero();
+            }
+            return self.miss_colour(ray);
         }
 
-        let rec = hit.unwrap();
+        let (mut rec, emission_scale) = hit.unwrap();
+        rec.min_roughness = self.min_roughness(depth);
 
-        // Calculate emission from material.
-        let emission = rec.material.emission(ray, &rec);
+        // Calculate emission from material. Lights only illuminate the objects
+        // they are linked to.
+        let emission = if skip > 0 || !self.illuminates(rec.link_id, from) {
+            Colour::zero()
+        } else {
+            rec.material.emission(ray, &rec) * emission_scale
+        };
+        let skip = skip.saturating_sub(1);
 
         // If material did not absorb the ray and scattered it, continue tracing
         // the new ray.
@@ -80,34 +384,666 @@ impl RecursiveTracer {
         let sr = scatter.unwrap();
 
         if let Some(specular_ray) = sr.specular_ray {
-            // Specular materials
-            let colour = self.ray_colour(&specular_ray, depth - 1);
-            emission + sr.attenuation * colour
+            // Perfectly specular materials can't sample lights so lights are
+            // only found by following the reflected or refracted ray.
+            self.audit(&rec, sr.attenuation);
+            let colour = self.path_colour(&specular_ray, depth - 1, skip, rec.link_id);
+            emission + self.clamp_indirect(sr.attenuation * colour, depth)
         } else if let Some(scattered_ray) = sr.scattered_ray {
             // This handles isotropic material.
-            let colour = self.ray_colour(&scattered_ray, depth - 1);
-            emission + sr.attenuation * colour
+            self.audit(&rec, sr.attenuation);
+            let colour = self.path_colour(&scattered_ray, depth - 1, skip, rec.link_id);
+            emission + self.clamp_indirect(sr.attenuation * colour, depth)
         } else if let Some(pdf) = sr.pdf {
-            // Diffuse material
-            let lights = Arc::clone(&self.scene.lights);
-
-            let light_pdf = Arc::new(HittablePDF::new(lights, rec.point));
+            // Diffuse and glossy materials. Sampling the mixture of the light
+            // and material densities weights both strategies so neither
+            // lights hit by the material's directions nor glossy highlights
+            // of sampled lights are counted twice.
             let diffuse_pdf = Arc::clone(&pdf);
 
-            let p = MixturePDF::new(light_pdf, diffuse_pdf);
+            let p: ArcPDF = match self.light_pdf(rec.point, rec.link_id) {
+                Some(light_pdf) => Arc::new(MixturePDF::new(light_pdf, diffuse_pdf)),
+                None => diffuse_pdf,
+            };
 
-            let scattered = Ray::new(rec.point, p.generate(), ray.time);
+            let direction = p.generate();
+            let scattered = Ray::new(rec.offset_origin(direction), direction, ray.time);
             let pdf_val = p.value(scattered.direction);
             if pdf_val > 0.0 {
                 let scattering_pdf = rec.material.scattering_pdf(&ray, &rec, &scattered);
+                self.audit(&rec, sr.attenuation * scattering_pdf / pdf_val);
 
-                let colour = self.ray_colour(&scattered, depth - 1);
-                emission + sr.attenuation * scattering_pdf * colour / pdf_val
+                let colour = self.path_colour(&scattered, depth - 1, skip, rec.link_id);
+                let contribution = sr.attenuation * scattering_pdf * colour / pdf_val;
+                let direct = self.light_sources_colour(ray, &rec, sr.attenuation, skip);
+                emission + self.clamp_indirect(contribution + direct, depth)
             } else {
-                emission
+                emission + self.light_sources_colour(ray, &rec, sr.attenuation, skip)
             }
         } else {
             emission
         }
     }
+
+    /// Returns the colour seen by a ray that doesn't hit any object. The
+    /// environment map takes precedence over the sky, which takes
+    /// precedence over the background.
+    ///
+    /// * `ray` - The ray.
+    fn miss_colour(&self, ray: &Ray) -> Colour {
+        match (&self.scene.environment, &self.scene.sky) {
+            (Some(environment), _) => environment.value(ray.direction),
+            (None, Some(sky)) => sky.value(ray.direction),
+            (None, None) => (self.scene.background)(ray),
+        }
+    }
+
+    /// Returns the roughness glossy materials are clamped to at a bounce.
+    /// It grows linearly with the number of bounces since the camera.
+    ///
+    /// * `depth` - Remaining recursion depth of the ray being shaded.
+    fn min_roughness(&self, depth: u32) -> Float {
+        match self.scene.roughness_clamp {
+            Some(strength) => {
+                let bounce = self.config.max_depth.saturating_sub(depth);
+                (strength * bounce as Float).min(1.0)
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Records the throughput weight of a scattering event in the energy
+    /// audit if enabled.
+    ///
+    /// * `rec` - The intersection the ray was scattered at.
+    /// * `weight` - Throughput weight of the event.
+    fn audit(&self, rec: &HitRecord, weight: Colour) {
+        if let Some(audit) = &self.audit {
+            audit.record(&rec.material, weight);
+        }
+    }
+
+    /// Clamps the radiance scattered towards a ray to `--clamp-radiance`.
+    /// Only contributions arriving at secondary hits are clamped so directly
+    /// lit surfaces keep their brightness. The colour is scaled down
+    /// uniformly to preserve its hue.
+    ///
+    /// * `colour` - Scattered radiance.
+    /// * `depth` - Remaining recursion depth of the ray being shaded.
+    fn clamp_indirect(&self, colour: Colour, depth: u32) -> Colour {
+        match self.config.clamp_radiance {
+            Some(limit) if depth < self.config.max_depth => {
+                let max = colour.max_component();
+                if max > limit {
+                    colour * (limit / max)
+                } else {
+                    colour
+                }
+            }
+            _ => colour,
+        }
+    }
+
+    /// Returns the PDF used to sample light sources from a point. Directions
+    /// are sampled towards the lights that illuminate the object and, if there
+    /// is an environment map, towards its bright regions. Returns `None` if
+    /// there is nothing to sample or light sampling is disabled.
+    ///
+    /// * `origin` - Point being lit.
+    /// * `link_id` - Light linking ID of the object being lit.
+    fn light_pdf(&self, origin: Point3, link_id: u32) -> Option<ArcPDF> {
+        if self.config.no_nee {
+            return None;
+        }
+
+        let (lights, has_lights) = match self
+            .scene
+            .light_links
+            .as_ref()
+            .and_then(|links| links.lights(link_id))
+        {
+            Some((lights, n)) => (lights, n > 0),
+            None => (&self.scene.lights, self.scene.has_lights),
+        };
+
+        let lights: Option<ArcPDF> = if has_lights {
+            Some(Arc::new(HittablePDF::new(Arc::clone(lights), origin)))
+        } else {
+            None
+        };
+
+        let environment: Option<ArcPDF> =
+            self.scene.environment.as_ref().map(|environment| {
+                Arc::new(EnvironmentPDF::new(Arc::clone(environment))) as ArcPDF
+            });
+
+        match (lights, environment) {
+            (Some(lights), Some(environment)) => {
+                Some(Arc::new(MixturePDF::new(lights, environment)))
+            }
+            (lights, environment) => lights.or(environment),
+        }
+    }
+
+    /// Returns the light from the scene's point, spot and directional lights
+    /// scattered towards a ray at a diffuse surface. Scattered rays never
+    /// hit these lights so each one is sampled with a shadow ray instead,
+    /// unless light sampling is disabled.
+    ///
+    /// * `ray` - The incident ray.
+    /// * `rec` - The intersection being lit.
+    /// * `attenuation` - Attenuation of the material at the intersection.
+    /// * `skip` - Number of path vertices after the intersection whose
+    ///   emission is ignored.
+    fn light_sources_colour(
+        &self,
+        ray: &Ray,
+        rec: &HitRecord,
+        attenuation: Colour,
+        skip: u32,
+    ) -> Colour {
+        if skip > 0 || self.config.no_nee {
+            return Colour::zero();
+        }
+
+        self.light_sources
+            .iter()
+            .fold(Colour::zero(), |colour, light| {
+                colour + self.light_source_colour(light, ray, rec, attenuation)
+            })
+    }
+
+    /// Returns the light from a single light source scattered towards a ray
+    /// or black if the light is occluded.
+    ///
+    /// * `light` - The light.
+    /// * `ray` - The incident ray.
+    /// * `rec` - The intersection being lit.
+    /// * `attenuation` - Attenuation of the material at the intersection.
+    fn light_source_colour(
+        &self,
+        light: &ArcLight,
+        ray: &Ray,
+        rec: &HitRecord,
+        attenuation: Colour,
+    ) -> Colour {
+        let sample = match light.sample(rec.point) {
+            Some(sample) if sample.pdf > 0.0 => sample,
+            _ => return Colour::zero(),
+        };
+
+        let shadow = Ray::new(
+            rec.offset_origin(sample.direction),
+            sample.direction,
+            ray.time,
+        );
+        let scattering_pdf = rec.material.scattering_pdf(ray, rec, &shadow);
+        if scattering_pdf <= 0.0 {
+            return Colour::zero();
+        }
+
+        let (epsilon, limit) = self.ray_range(&shadow);
+        let t_max = (sample.distance - self.ray_epsilon).min(limit);
+        if t_max > epsilon && self.scene.world.hit(&shadow, epsilon, t_max).is_some() {
+            return Colour::zero();
+        }
+
+        attenuation * scattering_pdf * sample.radiance / sample.pdf
+    }
+
+    /// Returns the closest intersection of a camera ray between the camera's
+    /// clipping planes.
+    ///
+    /// * `ray` - The camera ray.
+    fn camera_hit(&self, ray: &Ray) -> Option<HitRecord> {
+        // Note the ray epsilon is used to avoid starting the ray inside the
+        // surface caused due to floating point approximation errors generated
+        // by the intersection routine.
+        let (t_min, t_max) = self.scene.camera.clip_range(ray);
+        let (epsilon, limit) = self.ray_range(ray);
+        self.scene
+            .world
+            .hit(ray, t_min.max(epsilon), t_max.min(limit))
+    }
+
+    /// Returns the range of the ray parameter tested for intersections.
+    /// The scene's ray epsilon and maximum distance are distances so they
+    /// are scaled by the length of the ray direction.
+    ///
+    /// * `ray` - The ray.
+    fn ray_range(&self, ray: &Ray) -> (Float, Float) {
+        let length = ray.direction.length();
+        (self.ray_epsilon / length, self.ray_t_max / length)
+    }
+
+    /// Returns the closest intersection of a ray scattered from an object and
+    /// the factor its emission is scaled by. Lights with a shadow softness as
+    /// seen from the object are replaced by their scaled copies, which emit
+    /// less per unit area so the light's power is unchanged.
+    ///
+    /// * `ray` - The ray.
+    /// * `from` - Light linking ID of the object the ray was scattered from,
+    ///   zero for camera rays.
+    fn hit_from(&self, ray: &Ray, from: u32) -> Option<(HitRecord, Float)> {
+        let scaled = match &self.scene.light_links {
+            Some(links) if from != 0 => links.scaled_lights(from),
+            _ => &[],
+        };
+
+        // Skip past the unscaled lights.
+        let (epsilon, limit) = self.ray_range(ray);
+        let mut t_min = epsilon;
+        let mut hit = None;
+        while let Some(rec) = self.scene.world.hit(ray, t_min, limit) {
+            if scaled.iter().any(|(light, _, _)| *light == rec.link_id) {
+                t_min = rec.t + epsilon;
+            } else {
+                hit = Some((rec, 1.0));
+                break;
+            }
+        }
+
+        for (_, light, scale) in scaled.iter() {
+            let t_max = hit.as_ref().map_or(limit, |(rec, _)| rec.t);
+            if let Some(rec) = light.hit(ray, epsilon, t_max) {
+                hit = Some((rec, 1.0 / (scale * scale)));
+            }
+        }
+
+        hit
+    }
+
+    /// Returns true if light emitted by an object illuminates another object.
+    ///
+    /// * `light` - Light linking ID of the emitting object.
+    /// * `object` - Light linking ID of the object being lit, zero for the
+    ///   camera.
+    fn illuminates(&self, light: u32, object: u32) -> bool {
+        match &self.scene.light_links {
+            Some(links) if object != 0 => links.illuminates(light, object),
+            _ => true,
+        }
+    }
+}
+
+/// A scene and the configuration used to render it. The output path of the
+/// configuration is where the rendered image for the job is meant to go.
+pub struct RenderJob {
+    /// The scene.
+    pub scene: Scene,
+
+    /// Configuration for this render.
+    pub config: AppConfig,
+}
+
+impl RenderJob {
+    /// Create a new render job.
+    ///
+    /// * `scene`  - The scene to render.
+    /// * `config` - Configuration for this render.
+    pub fn new(scene: Scene, config: AppConfig) -> Self {
+        Self { scene, config }
+    }
+}
+
+/// z-score for the 95% confidence interval used by adaptive sampling.
+const CONFIDENCE_Z: Float = 1.96;
+
+/// Smallest mean luminance used for the relative noise threshold so that
+/// dark pixels can converge.
+const MIN_MEAN_LUMINANCE: Float = 1.0 / 256.0;
+
+/// Returns true if the 95% confidence interval of the mean luminance of a
+/// pixel is within the noise threshold relative to the mean.
+///
+/// * `mean` - Mean luminance of the samples.
+/// * `m2` - Sum of squared differences from the mean.
+/// * `n` - Number of samples.
+/// * `threshold` - Noise threshold.
+fn converged(mean: Float, m2: Float, n: u32, threshold: Float) -> bool {
+    if n < 2 {
+        return false;
+    }
+
+    let variance = m2 / (n - 1) as Float;
+    let error = CONFIDENCE_Z * (variance / n as Float).sqrt();
+    error <= threshold * mean.max(MIN_MEAN_LUMINANCE)
+}
+
+/// Samples per pixel of the fast preview rendered with `--preview-pass`.
+const PREVIEW_PASS_SAMPLES: u32 = 4;
+
+/// Render a scene to an in-memory linear floating point image buffer.
+///
+/// It uses its own thread pool and tile buffers so multiple scenes with
+/// different configurations can be rendered in the same process. Only the
+/// interrupt flag set by `signal::install_interrupt_handler()` is shared.
+/// The returned image is oriented with the first row at the top.
+///
+/// With `--preview-pass` a half resolution image with a few samples per
+/// pixel is rendered first and saved next to the output path, and published
+/// to the preview server, before the full render starts. The preview server
+/// is stopped once the final image has been published so the address can be
+/// used by the next render.
+///
+/// * `scene`    - The scene to render.
+/// * `config`   - Program configuration.
+/// * `progress` - Receives the fraction of each render completed as tiles
+///   finish and messages about the render.
+pub fn render_scene(
+    scene: Scene,
+    config: &AppConfig,
+    progress: &dyn Progress,
+) -> Result<Rgb32FImage, String> {
+    let threads = config.threads();
+
+    let preview = match &config.serve {
+        Some(addr) => {
+            let preview = PreviewServer::start(addr)?;
+            progress.message(&format!("Serving preview at http://{}/", preview.addr()));
+            Some(preview)
+        }
+        None => None,
+    };
+
+    if config.preview_pass {
+        let job = preview_pass_job(scene.clone(), config);
+        let image = render_jobs(vec![job], threads, preview.as_deref(), progress)?.remove(0);
+
+        let path = preview_output_path(&config.output_path);
+        progress.message(&format!("Saving preview image to {}", path));
+        if let Err(e) = save_framebuffer(&image, &path, config.gamma) {
+            progress.message(&format!("Error writing preview image: {}", e));
+        }
+    }
+
+    let job = RenderJob::new(scene, config.clone());
+    let image = render_jobs(vec![job], threads, preview.as_deref(), progress)?.remove(0);
+
+    // Publish the final image before the preview server is stopped.
+    if let Some(preview) = &preview {
+        if let Err(e) = preview.publish(&to_rgba_image(&image, config.gamma), preview.progress()) {
+            progress.message(&e);
+        }
+    }
+
+    Ok(image)
+}
+
+/// Returns the job for the fast preview of a scene. The image is half the
+/// width and height with a few samples per pixel and no adaptive sampling.
+///
+/// * `scene`  - The scene to render.
+/// * `config` - Program configuration.
+fn preview_pass_job(scene: Scene, config: &AppConfig) -> RenderJob {
+    let mut config = config.clone();
+    config.image_width = (config.image_width / 2).max(1);
+    config.image_height = (config.image_height / 2).max(1);
+    config.samples_per_pixel = config.max_samples().min(PREVIEW_PASS_SAMPLES);
+    config.max_samples = None;
+    config.noise_threshold = None;
+    config.energy_audit = false;
+    RenderJob::new(scene, config)
+}
+
+/// How often the watchdog checks for stalled tiles.
+const WATCHDOG_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often the image is published to the preview server.
+const PREVIEW_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Samples per pixel are divided by this for tiles requeued by the watchdog.
+const REQUEUE_SAMPLES_DIVISOR: u32 = 4;
+
+/// A rendered tile sent back from a worker.
+type RenderedTile = (TileKey, TileBounds, Rgb32FImage);
+
+/// Render several independent jobs concurrently on a shared thread pool.
+///
+/// The tiles of all jobs are queued to the same pool so small renders (e.g.
+/// thumbnails) keep all threads busy. The linear floating point images are
+/// returned in the same order as `jobs` and are oriented with the first row
+/// at the top. The
+/// number of threads in each job's configuration is ignored.
+///
+/// A watchdog logs tiles that take much longer than the median tile. If
+/// requested in the configuration, those tiles are also requeued at reduced
+/// quality and whichever attempt finishes first is used.
+///
+/// * `jobs`     - The render jobs.
+/// * `threads`  - Number of threads in the shared thread pool.
+/// * `progress` - Receives the fraction of all jobs completed as tiles
+///   finish and messages about the render.
+pub fn render_batch(
+    jobs: Vec<RenderJob>,
+    threads: usize,
+    progress: &dyn Progress,
+) -> Result<Vec<Rgb32FImage>, String> {
+    render_jobs(jobs, threads, None, progress)
+}
+
+/// Render several independent jobs concurrently on a shared thread pool and
+/// publish the image of the first job to a preview server as tiles finish.
+///
+/// * `jobs`        - The render jobs.
+/// * `threads`     - Number of threads in the shared thread pool.
+/// * `preview`     - Optional preview server.
+/// * `on_progress` - Receives the fraction of all jobs completed and
+///   messages about the render.
+fn render_jobs(
+    jobs: Vec<RenderJob>,
+    threads: usize,
+    preview: Option<&PreviewServer>,
+    on_progress: &dyn Progress,
+) -> Result<Vec<Rgb32FImage>, String> {
+    // Scheduling options are shared by all jobs so use the first job's.
+    let options = jobs
+        .first()
+        .map_or_else(WorkerOptions::default, |job| job.config.worker_options());
+    let pool = ThreadPool::build(threads, options).map_err(|e| format!("{:?}", e))?;
+    for warning in pool.warnings() {
+        on_progress.message(warning);
+    }
+
+    let renderers: Vec<Arc<RecursiveTracer>> = jobs
+        .into_iter()
+        .map(|job| Arc::new(RecursiveTracer::new(job.scene, job.config)))
+        .collect();
+
+    let mut images: Vec<Rgb32FImage> = renderers
+        .iter()
+        .map(|r| Rgb32FImage::new(r.config.image_width, r.config.image_height))
+        .collect();
+
+    let gamma = renderers
+        .first()
+        .map_or_else(Gamma::default, |r| r.config.gamma);
+    let watchdog_factor = renderers.first().map_or(0.0, |r| r.config.watchdog_factor);
+    let requeue = renderers.first().is_some_and(|r| r.config.watchdog_requeue);
+
+    let watchdog = Arc::new(Mutex::new(Watchdog::new(watchdog_factor)));
+
+    // Tiles that have been copied to the image. Used to abandon the other
+    // attempt of a requeued tile.
+    let completed: Arc<Mutex<HashSet<(usize, usize)>>> = Arc::new(Mutex::new(HashSet::new()));
+
+    // Workers send back rendered tiles tagged with their job which get copied
+    // to the corresponding image here.
+    let (sender, receiver) = mpsc::channel::<RenderedTile>();
+
+    let start = Instant::now();
+    let n_tiles: usize = renderers.iter().map(|r| r.config.n_tiles()).sum();
+
+    let queue_tile = |renderer: &Arc<RecursiveTracer>,
+                      key: TileKey,
+                      sender: &mpsc::Sender<RenderedTile>| {
+        let renderer = Arc::clone(renderer);
+        let sender = sender.clone();
+        let watchdog = Arc::clone(&watchdog);
+        let completed = Arc::clone(&completed);
+
+        pool.execute(move || {
+            let (job_idx, tile_idx, _) = key;
+
+            let cancelled = || {
+                // Drain tiles that were queued before the interrupt.
+                interrupted() || completed.lock().unwrap().contains(&(job_idx, tile_idx))
+            };
+            if cancelled() {
+                return;
+            }
+
+            let ctx = renderer.config.render_context();
+            let tile_bounds = get_tile_bounds(&ctx, tile_idx);
+
+            watchdog.lock().unwrap().tile_started(key);
+
+            let tile_size = ctx.tile_size as u32;
+            let mut tile_pixels = Rgb32FImage::new(tile_size, tile_size);
+            let done =
+                render_tile_cancellable(renderer, &ctx, &tile_bounds, &mut tile_pixels, cancelled);
+
+            watchdog.lock().unwrap().tile_finished(key, done);
+
+            if done {
+                // The receiver only goes away if rendering was abandoned.
+                let _ = sender.send((key, tile_bounds, tile_pixels));
+            }
+        });
+    };
+
+    let mut done = 0;
+    let mut last_preview = Instant::now();
+
+    thread::scope(|scope| {
+        // Queue tiles in a separate thread since queuing blocks while the
+        // pool is busy and we need to keep an eye on the tiles in progress.
+        {
+            let sender = sender.clone();
+            let renderers = &renderers;
+            let queue_tile = &queue_tile;
+
+            scope.spawn(move || {
+                for (job_idx, renderer) in renderers.iter().enumerate() {
+                    for tile_idx in renderer.config.tile_indices() {
+                        // Stop queuing tiles once an interrupt is requested.
+                        if interrupted() {
+                            return;
+                        }
+                        queue_tile(renderer, (job_idx, tile_idx, false), &sender);
+                    }
+                }
+            });
+        }
+
+        // Reduced quality renderers for requeued tiles, created on demand.
+        let mut reduced: Vec<Option<Arc<RecursiveTracer>>> = vec![None; renderers.len()];
+
+        // Keep a sender for requeuing tiles. It is dropped once an interrupt
+        // is requested so the receiver terminates when the workers drain.
+        let mut sender = Some(sender);
+
+        while done < n_tiles {
+            match receiver.recv_timeout(WATCHDOG_INTERVAL) {
+                Ok(((job_idx, tile_idx, _), tile_bounds, tile_pixels)) => {
+                    if !completed.lock().unwrap().insert((job_idx, tile_idx)) {
+                        continue; // The other attempt finished first.
+                    }
+
+                    imageops::replace(
+                        &mut images[job_idx],
+                        &tile_pixels,
+                        tile_bounds.x_min as i64,
+                        tile_bounds.y_min as i64,
+                    );
+
+                    done += 1;
+                    let progress = done as f32 / n_tiles as f32;
+                    on_progress.update(progress);
+
+                    if let Some(preview) = preview {
+                        if done == n_tiles || last_preview.elapsed() >= PREVIEW_INTERVAL {
+                            // Rows are rendered bottom to top.
+                            let image = imageops::flip_vertical(&images[0]);
+                            if let Err(e) = preview.publish(&to_rgba_image(&image, gamma), progress)
+                            {
+                                on_progress.message(&e);
+                            }
+                            last_preview = Instant::now();
+                        }
+                    }
+                }
+
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if interrupted() {
+                        sender = None;
+                        continue;
+                    }
+
+                    let stalled = watchdog.lock().unwrap().stalled();
+                    for ((job_idx, tile_idx, requeued), elapsed) in stalled {
+                        let config = &renderers[job_idx].config;
+                        let b = get_tile_bounds(&config.render_context(), tile_idx);
+                        on_progress.message(&format!(
+                            "Tile {} ({}, {}) - ({}, {}) of job {} is taking {:.2} seconds",
+                            tile_idx,
+                            b.x_min,
+                            b.y_min,
+                            b.x_max,
+                            b.y_max,
+                            job_idx,
+                            elapsed.as_secs_f32()
+                        ));
+
+                        if let (true, false, Some(sender)) = (requeue, requeued, &sender) {
+                            let renderer = reduced[job_idx].get_or_insert_with(|| {
+                                let mut config = config.clone();
+                                config.samples_per_pixel =
+                                    (config.max_samples() / REQUEUE_SAMPLES_DIVISOR).max(1);
+                                config.max_samples = None;
+
+                                // Requeued tiles count towards the job's audit.
+                                let mut renderer =
+                                    RecursiveTracer::new(renderers[job_idx].scene.clone(), config);
+                                renderer.audit = renderers[job_idx].audit.clone();
+                                Arc::new(renderer)
+                            });
+
+                            on_progress.message(&format!(
+                                "Requeuing tile {} with {} samples per pixel",
+                                tile_idx, renderer.config.samples_per_pixel
+                            ));
+                            queue_tile(renderer, (job_idx, tile_idx, true), sender);
+                        }
+                    }
+                }
+
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+
+    if interrupted() {
+        on_progress.message(&format!(
+            "Render interrupted: {} of {} tiles completed in {:.2} seconds",
+            done,
+            n_tiles,
+            start.elapsed().as_secs_f32()
+        ));
+    }
+
+    for audit in renderers.iter().filter_map(|r| r.audit.as_ref()) {
+        on_progress.message(audit.to_string().trim_end());
+
+        let flagged = audit.flagged();
+        if flagged > 0 {
+            on_progress.message(&format!(
+                "{} materials return more energy than they receive",
+                flagged
+            ));
+        }
+    }
+
+    // Rows are rendered bottom to top.
+    Ok(images.iter().map(imageops::flip_vertical).collect())
 }
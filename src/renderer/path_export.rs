@@ -0,0 +1,258 @@
+//! # Path export
+//!
+//! A library for tracing a few paths through a single pixel and saving their
+//! vertices as polylines in OBJ or PLY files. Loading the file alongside the
+//! scene geometry in a 3D viewer shows how light bounces around the scene,
+//! which helps when debugging black pixels or fireflies.
+
+use super::{
+    AppConfig, ArcPDF, Colour, Float, MixturePDF, Point3, Ray, RecursiveTracer, Sampler, Scene,
+};
+use std::fmt::Write;
+use std::fs;
+use std::sync::Arc;
+
+/// A path traced through a pixel.
+pub struct TracedPath {
+    /// Vertices of the path starting at the camera. Paths that escape the
+    /// scene end with a point past the scene bounds along the escaping ray.
+    pub vertices: Vec<Point3>,
+
+    /// Radiance carried back to the camera along the path.
+    pub radiance: Colour,
+
+    /// Whether the path escaped to the background.
+    pub escaped: bool,
+}
+
+impl RecursiveTracer {
+    /// Traces a path through the scene and records its vertices. This follows
+    /// the same scattering decisions as `ray_colour()` without clamping.
+    ///
+    /// * `ray` - The camera ray.
+    /// * `escape_length` - Length of the segment recorded for escaping rays.
+    fn trace_path_vertices(&self, ray: &Ray, escape_length: Float) -> TracedPath {
+        let mut ray = *ray;
+        let mut vertices = vec![ray.origin];
+        let mut throughput = Colour::new(1.0, 1.0, 1.0);
+        let mut radiance = Colour::zero();
+        let mut escaped = false;
+
+        // Light linking ID of the object the ray was scattered from.
+        let mut from = 0;
+
+        for depth in 0..self.config.max_depth {
+            let hit = if depth == 0 {
+                self.camera_hit(&ray).map(|rec| (rec, 1.0))
+            } else {
+                self.hit_from(&ray, from)
+            };
+
+            let (rec, emission_scale) = match hit {
+                Some(hit) => hit,
+                None => {
+                    let background = self.miss_colour(&ray);
+                    radiance += throughput * background;
+                    vertices.push(ray.origin + ray.direction.unit_vector() * escape_length);
+                    escaped = true;
+                    break;
+                }
+            };
+
+            vertices.push(rec.point);
+            if self.illuminates(rec.link_id, from) {
+                radiance += throughput * rec.material.emission(&ray, &rec) * emission_scale;
+            }
+            from = rec.link_id;
+
+            let sr = match rec.material.scatter(&ray, &rec) {
+                Some(sr) => sr,
+                None => break,
+            };
+
+            if let Some(specular_ray) = sr.specular_ray {
+                throughput *= sr.attenuation;
+                ray = specular_ray;
+            } else if let Some(scattered_ray) = sr.scattered_ray {
+                throughput *= sr.attenuation;
+                ray = scattered_ray;
+            } else if let Some(pdf) = sr.pdf {
+                let p: ArcPDF = match self.light_pdf(rec.point, rec.link_id) {
+                    Some(light_pdf) => Arc::new(MixturePDF::new(light_pdf, pdf)),
+                    None => pdf,
+                };
+
+                let direction = p.generate();
+                let scattered = Ray::new(rec.offset_origin(direction), direction, ray.time);
+                let pdf_val = p.value(scattered.direction);
+                if pdf_val <= 0.0 {
+                    break;
+                }
+
+                let scattering_pdf = rec.material.scattering_pdf(&ray, &rec, &scattered);
+                throughput *= sr.attenuation * scattering_pdf / pdf_val;
+                ray = scattered;
+            } else {
+                break;
+            }
+        }
+
+        TracedPath {
+            vertices,
+            radiance,
+            escaped,
+        }
+    }
+}
+
+/// Trace paths through a pixel. The pixel is given in image coordinates with
+/// the origin at the top left. Each path uses the same sample as the pixel
+/// sample with the same index in a render.
+///
+/// * `scene` - The scene.
+/// * `config` - Program configuration.
+/// * `x` - Pixel x-coordinate.
+/// * `y` - Pixel y-coordinate.
+/// * `n_paths` - Number of paths to trace.
+pub fn trace_pixel_paths(
+    scene: Scene,
+    config: &AppConfig,
+    x: u32,
+    y: u32,
+    n_paths: u32,
+) -> Result<Vec<TracedPath>, String> {
+    if x >= config.image_width || y >= config.image_height {
+        return Err(format!(
+            "pixel {},{} is outside the {}x{} image",
+            x, y, config.image_width, config.image_height
+        ));
+    }
+
+    let renderer = RecursiveTracer::new(scene, config.clone());
+
+    // Length of the final segment of escaping paths so they are visible
+    // past the scene geometry.
+    let (time0, time1) = renderer.scene.camera.shutter();
+    let escape_length = renderer
+        .scene
+        .world
+        .bounding_box(time0, time1)
+        .map_or(1.0, |b| (b.max - b.min).length().max(1.0));
+
+    // Rows are rendered bottom to top.
+    let i = x;
+    let j = config.image_height - 1 - y;
+
+    let w = config.image_width as Float;
+    let h = config.image_height as Float;
+
+    let paths = (0..n_paths)
+        .map(|n| {
+            Sampler::start_sample(config.sampler, i, j, n, n_paths);
+            let (sx, sy) = Sampler::get_2d();
+
+            let u = (i as Float + sx) / w;
+            let v = (j as Float + sy) / h;
+
+            let ray = renderer.scene.camera.get_ray(u, v);
+            renderer.trace_path_vertices(&ray, escape_length)
+        })
+        .collect();
+    Sampler::finish();
+
+    Ok(paths)
+}
+
+/// Save traced paths as polylines. Files with a `.ply` extension are written
+/// as ASCII PLY files with one edge per path segment. Anything else is
+/// written as an OBJ file with one line element per path.
+///
+/// * `paths` - The paths.
+/// * `path` - Output file path.
+pub fn save_paths(paths: &[TracedPath], path: &str) -> Result<(), String> {
+    let contents = if path.to_lowercase().ends_with(".ply") {
+        to_ply(paths)
+    } else {
+        to_obj(paths)
+    };
+    fs::write(path, contents).map_err(|e| format!("Unable to write {}: {}", path, e))
+}
+
+/// Returns the paths in OBJ format.
+///
+/// * `paths` - The paths.
+fn to_obj(paths: &[TracedPath]) -> String {
+    let mut s = String::new();
+    let mut first = 1;
+
+    for (n, p) in paths.iter().enumerate() {
+        let _ = writeln!(s, "# {}", path_summary(p));
+        let _ = writeln!(s, "o path_{}", n);
+        for v in p.vertices.iter() {
+            let _ = writeln!(s, "v {} {} {}", v.x(), v.y(), v.z());
+        }
+
+        let indices: Vec<String> = (first..first + p.vertices.len())
+            .map(|i| i.to_string())
+            .collect();
+        let _ = writeln!(s, "l {}", indices.join(" "));
+
+        first += p.vertices.len();
+    }
+
+    s
+}
+
+/// Returns the paths in ASCII PLY format.
+///
+/// * `paths` - The paths.
+fn to_ply(paths: &[TracedPath]) -> String {
+    let n_vertices: usize = paths.iter().map(|p| p.vertices.len()).sum();
+    let n_edges: usize = paths
+        .iter()
+        .map(|p| p.vertices.len().saturating_sub(1))
+        .sum();
+
+    let mut s = String::new();
+    let _ = writeln!(s, "ply");
+    let _ = writeln!(s, "format ascii 1.0");
+    for p in paths.iter() {
+        let _ = writeln!(s, "comment {}", path_summary(p));
+    }
+    let _ = writeln!(s, "element vertex {}", n_vertices);
+    let _ = writeln!(s, "property float x");
+    let _ = writeln!(s, "property float y");
+    let _ = writeln!(s, "property float z");
+    let _ = writeln!(s, "element edge {}", n_edges);
+    let _ = writeln!(s, "property int vertex1");
+    let _ = writeln!(s, "property int vertex2");
+    let _ = writeln!(s, "end_header");
+
+    for v in paths.iter().flat_map(|p| p.vertices.iter()) {
+        let _ = writeln!(s, "{} {} {}", v.x(), v.y(), v.z());
+    }
+
+    let mut first = 0;
+    for p in paths.iter() {
+        for i in first..(first + p.vertices.len()).saturating_sub(1) {
+            let _ = writeln!(s, "{} {}", i, i + 1);
+        }
+        first += p.vertices.len();
+    }
+
+    s
+}
+
+/// Returns a one line summary of a path.
+///
+/// * `p` - The path.
+pub fn path_summary(p: &TracedPath) -> String {
+    format!(
+        "{} bounces, {}, radiance {} {} {}",
+        p.vertices.len().saturating_sub(2),
+        if p.escaped { "escaped" } else { "terminated" },
+        p.radiance.x(),
+        p.radiance.y(),
+        p.radiance.z()
+    )
+}
@@ -0,0 +1,58 @@
+//! # Progressive
+//!
+//! A library for accumulating the samples of progressive passes. Each pass
+//! adds a few samples to every pixel and the running average of all passes so
+//! far is displayed, so the image starts out noisy and refines over time.
+
+use super::{Colour, TileBounds};
+use std::sync::Mutex;
+
+/// Running sums of the samples of each pixel.
+pub struct Accumulator {
+    /// Image width.
+    width: u32,
+
+    /// Sum of sample colours and number of samples for each pixel.
+    pixels: Mutex<Vec<(Colour, u32)>>,
+}
+
+impl Accumulator {
+    /// Create a new accumulator with no samples.
+    ///
+    /// * `width` - Image width.
+    /// * `height` - Image height.
+    pub fn new(width: u32, height: u32) -> Accumulator {
+        Accumulator {
+            width,
+            pixels: Mutex::new(vec![(Colour::zero(), 0); (width * height) as usize]),
+        }
+    }
+
+    /// Add the samples of a pass over a tile and return the average colour
+    /// of each pixel so far. Samples and averages are in row order within
+    /// the tile.
+    ///
+    /// * `tile_bounds` - Tile bounds in image coordinates.
+    /// * `samples` - Sum of sample colours and number of samples per pixel.
+    pub fn add_tile(&self, tile_bounds: &TileBounds, samples: &[(Colour, u32)]) -> Vec<Colour> {
+        let mut pixels = self.pixels.lock().expect("Unable to lock accumulator");
+
+        let coords = (tile_bounds.y_min..=tile_bounds.y_max)
+            .flat_map(|j| (tile_bounds.x_min..=tile_bounds.x_max).map(move |i| (i, j)));
+
+        coords
+            .zip(samples.iter())
+            .map(|((i, j), (sum, n))| {
+                let pixel = &mut pixels[(j * self.width + i) as usize];
+                pixel.0 += *sum;
+                pixel.1 += n;
+
+                if pixel.1 == 0 {
+                    Colour::zero()
+                } else {
+                    pixel.0.average_sample(pixel.1)
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,116 @@
+//! # Watchdog
+//!
+//! A library for detecting tiles that take far longer to render than the
+//! rest, e.g. a tile dominated by a dense volume.
+
+use super::Float;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Minimum number of completed tiles before render times are compared.
+const MIN_COMPLETED: usize = 8;
+
+/// Minimum time before a tile is considered stalled regardless of the median
+/// so tiny renders don't report noise.
+const MIN_STALL: Duration = Duration::from_secs(2);
+
+/// Identifies a tile attempt with the job index, tile index and whether it
+/// was requeued.
+pub type TileKey = (usize, usize, bool);
+
+/// Tracks tile render times and detects stalled tiles.
+#[derive(Debug)]
+pub struct Watchdog {
+    /// A tile is stalled if it takes longer than this multiple of the median
+    /// tile render time.
+    factor: Float,
+
+    /// Start time of tiles being rendered.
+    in_flight: HashMap<TileKey, Instant>,
+
+    /// Render times of completed tiles.
+    durations: Vec<Duration>,
+
+    /// Stalled tiles that were already reported.
+    reported: HashSet<TileKey>,
+}
+
+impl Watchdog {
+    /// Create a new watchdog.
+    ///
+    /// * `factor` - A tile is stalled if it takes longer than this multiple of
+    ///   the median tile render time.
+    pub fn new(factor: Float) -> Self {
+        Self {
+            factor,
+            in_flight: HashMap::new(),
+            durations: Vec::new(),
+            reported: HashSet::new(),
+        }
+    }
+
+    /// Record that a tile has started rendering.
+    ///
+    /// * `key` - The tile.
+    pub fn tile_started(&mut self, key: TileKey) {
+        self.in_flight.insert(key, Instant::now());
+    }
+
+    /// Record that a tile has finished or was abandoned.
+    ///
+    /// * `key`       - The tile.
+    /// * `completed` - Whether the tile was rendered completely.
+    pub fn tile_finished(&mut self, key: TileKey, completed: bool) {
+        if let Some(start) = self.in_flight.remove(&key) {
+            if completed {
+                self.durations.push(start.elapsed());
+            }
+        }
+    }
+
+    /// Returns the tiles that have been rendering for longer than `factor`
+    /// times the median render time along with their elapsed time. Each tile
+    /// is only returned once.
+    pub fn stalled(&mut self) -> Vec<(TileKey, Duration)> {
+        if self.factor <= 0.0 || self.durations.len() < MIN_COMPLETED {
+            return vec![];
+        }
+
+        let mut durations = self.durations.clone();
+        durations.sort_unstable();
+        let median = durations[durations.len() / 2];
+
+        // Saturate so huge or invalid factors never report a tile instead of
+        // panicking.
+        let threshold = Duration::try_from_secs_f64(median.as_secs_f64() * self.factor)
+            .unwrap_or(Duration::MAX)
+            .max(MIN_STALL);
+
+        let mut stalled = vec![];
+        for (key, start) in self.in_flight.iter() {
+            let elapsed = start.elapsed();
+            if elapsed > threshold && self.reported.insert(*key) {
+                stalled.push((*key, elapsed));
+            }
+        }
+        stalled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Factors too large for a duration never report a tile.
+    #[test]
+    fn huge_factor_does_not_panic() {
+        let mut watchdog = Watchdog::new(Float::MAX);
+        for tile in 0..MIN_COMPLETED {
+            watchdog.tile_started((0, tile, false));
+            watchdog.tile_finished((0, tile, false), true);
+        }
+        watchdog.tile_started((0, MIN_COMPLETED, false));
+
+        assert!(watchdog.stalled().is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+//! # Assets
+//!
+//! A library for resolving the paths of assets referenced by scene files,
+//! e.g. image textures, environment maps and included files. Relative paths
+//! are looked up next to the scene file first and then in the search paths
+//! in order. Every resolved asset is recorded so a scene can be packed with
+//! the files it uses.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Resolves asset paths while loading a scene.
+#[derive(Debug, Default)]
+pub struct Assets {
+    /// Directories searched for assets that aren't next to the scene file.
+    search_paths: Vec<PathBuf>,
+
+    /// Assets resolved so far with the path they were referenced by.
+    resolved: Mutex<Vec<(String, PathBuf)>>,
+}
+
+impl Assets {
+    /// Create a new resolver. Without search paths only the directory of
+    /// the scene file is searched.
+    ///
+    /// * `search_paths` - Directories searched in order.
+    pub fn new(search_paths: Vec<PathBuf>) -> Assets {
+        Assets {
+            search_paths,
+            resolved: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Resolve a path referenced by a scene file. Absolute paths are used
+    /// as is. Relative paths resolve to the first existing file next to the
+    /// scene file or in the search paths, falling back to the path next to
+    /// the scene file so errors name the expected location.
+    ///
+    /// * `dir` - Directory of the scene file.
+    /// * `path` - The referenced path.
+    pub fn resolve(&self, dir: &Path, path: &str) -> PathBuf {
+        let p = Path::new(path);
+
+        let resolved = if p.is_absolute() {
+            p.to_path_buf()
+        } else {
+            std::iter::once(dir)
+                .chain(self.search_paths.iter().map(PathBuf::as_path))
+                .map(|d| d.join(p))
+                .find(|candidate| candidate.exists())
+                .unwrap_or_else(|| dir.join(p))
+        };
+
+        self.resolved
+            .lock()
+            .unwrap()
+            .push((path.to_string(), resolved.clone()));
+        resolved
+    }
+
+    /// Returns the assets resolved since the last call with the path they
+    /// were referenced by.
+    pub fn take_resolved(&self) -> Vec<(String, PathBuf)> {
+        std::mem::take(&mut *self.resolved.lock().unwrap())
+    }
+}
@@ -0,0 +1,44 @@
+//! # Load context
+//!
+//! A library for the state shared by everything built while loading a
+//! scene. Each load gets its own context so loading one scene doesn't
+//! affect the next.
+
+use super::{Assets, BvhCache, MemoryBudget, Messages};
+use std::sync::Arc;
+
+/// State shared while loading a scene.
+#[derive(Debug, Clone, Default)]
+pub struct LoadContext {
+    /// Memory budget for textures and meshes.
+    pub budget: MemoryBudget,
+
+    /// Cache of built bounding volume hierarchies of large groups of
+    /// objects. Nothing is cached without one.
+    pub bvh_cache: Option<BvhCache>,
+
+    /// Resolves the paths of assets referenced by the scene.
+    pub assets: Arc<Assets>,
+
+    /// Warnings and other messages reported while loading the scene for the
+    /// caller to show.
+    pub messages: Messages,
+}
+
+impl LoadContext {
+    /// Create a new load context. The budget and cache report their
+    /// messages to the context's messages.
+    ///
+    /// * `budget` - Memory budget for textures and meshes.
+    /// * `bvh_cache` - Optional cache of built bounding volume hierarchies.
+    /// * `assets` - Resolves the paths of assets referenced by the scene.
+    pub fn new(budget: MemoryBudget, bvh_cache: Option<BvhCache>, assets: Assets) -> LoadContext {
+        let messages = Messages::default();
+        LoadContext {
+            budget: budget.with_messages(messages.clone()),
+            bvh_cache: bvh_cache.map(|cache| cache.with_messages(messages.clone())),
+            assets: Arc::new(assets),
+            messages,
+        }
+    }
+}
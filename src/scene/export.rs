@@ -0,0 +1,263 @@
+//! # Export
+//!
+//! A library for saving scenes in the TOML scene description format read by
+//! the scene loader.
+
+use super::{background_name, Scene};
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+
+impl Scene {
+    /// Returns the scene in the scene file format.
+    pub fn to_table(&self) -> Result<Table, String> {
+        let background = background_name(self.background)
+            .ok_or("scene: background cannot be saved to a scene file")?;
+
+        let mut t = Table::new();
+        t.insert("background".into(), background.into());
+
+        if let Some(environment) = &self.environment {
+            let (path, intensity, storage) = environment.source();
+            t.insert("environment".into(), path.into());
+            t.insert("environment_intensity".into(), intensity.into());
+            t.insert("environment_storage".into(), storage.to_string().into());
+        }
+
+        if let Some(sky) = &self.sky {
+            let (sun_elevation, sun_azimuth, turbidity, intensity, sun_intensity) = sky.source();
+            let mut s = Table::new();
+            s.insert("sun_elevation".into(), sun_elevation.into());
+            s.insert("sun_azimuth".into(), sun_azimuth.into());
+            s.insert("turbidity".into(), turbidity.into());
+            s.insert("intensity".into(), intensity.into());
+            s.insert("sun_intensity".into(), sun_intensity.into());
+            t.insert("sky".into(), s.into());
+        }
+
+        if let Some(epsilon) = self.ray_epsilon {
+            t.insert("ray_epsilon".into(), epsilon.into());
+        }
+        if let Some(t_max) = self.ray_t_max {
+            t.insert("ray_t_max".into(), t_max.into());
+        }
+
+        if let Some(strength) = self.roughness_clamp {
+            t.insert("roughness_clamp".into(), true.into());
+            t.insert("roughness_clamp_strength".into(), strength.into());
+        }
+
+        if let Some(backplate) = &self.backplate {
+            t.insert("backplate".into(), backplate.path().into());
+        }
+
+        t.insert("camera".into(), self.camera.describe().into());
+        let mut objects = group_objects(self.world.describe());
+
+        if self.has_lights {
+            let lights = light_only(&mut objects, group_objects(self.lights.describe()));
+            if !lights.is_empty() {
+                t.insert("lights".into(), Value::Array(lights));
+            }
+        }
+
+        t.insert("objects".into(), objects);
+
+        if !self.light_sources.is_empty() {
+            let lights = self
+                .light_sources
+                .iter()
+                .map(|l| Value::Table(l.describe()))
+                .collect();
+            t.insert("light_sources".into(), Value::Array(lights));
+        }
+
+        if !self.sections.is_empty() {
+            let sections = self
+                .sections
+                .iter()
+                .map(|s| Value::Table(s.describe()))
+                .collect();
+            t.insert("sections".into(), Value::Array(sections));
+        }
+
+        if !self.passes.is_empty() {
+            let passes = self.passes.iter().cloned().map(Value::Table).collect();
+            t.insert("passes".into(), Value::Array(passes));
+        }
+
+        Ok(t)
+    }
+
+    /// Save the scene to a TOML scene description file. Relative image paths
+    /// are made absolute so the file can be saved anywhere.
+    ///
+    /// * `path` - Path to the scene file.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut t = self.to_table()?;
+        absolute_paths(&mut t)?;
+
+        let contents = toml::to_string(&t).map_err(|e| format!("{}: {}", path, e))?;
+        fs::write(path, contents).map_err(|e| format!("Unable to write {}: {}", path, e))
+    }
+}
+
+/// Returns the objects of a group as an array of tables. Groups with
+/// transforms are kept as a single object.
+///
+/// * `t` - Description of the group.
+fn group_objects(t: Table) -> Value {
+    let is_plain_group = t.get("type").and_then(Value::as_str) == Some("group")
+        && t.keys()
+            .all(|k| k == "type" || k == "bvh" || k == "objects");
+
+    match t.get("objects") {
+        Some(objects) if is_plain_group => objects.clone(),
+        _ => Value::Array(vec![Value::Table(t)]),
+    }
+}
+
+/// Returns the lights that aren't objects of the scene. Objects that are
+/// lights are marked with `light = true` instead so loading the scene doesn't
+/// add them twice.
+///
+/// * `objects` - Objects of the scene.
+/// * `lights` - Lights of the scene.
+fn light_only(objects: &mut Value, lights: Value) -> Vec<Value> {
+    let (Value::Array(objects), Value::Array(lights)) = (objects, lights) else {
+        return Vec::new();
+    };
+
+    lights
+        .into_iter()
+        .filter(|light| {
+            let mut is_object = false;
+            for object in objects.iter_mut().filter_map(Value::as_table_mut) {
+                let mut t = object.clone();
+                t.remove("light");
+                if Some(&t) == light.as_table() {
+                    object.insert("light".into(), true.into());
+                    is_object = true;
+                }
+            }
+            !is_object
+        })
+        .collect()
+}
+
+/// Make the paths of image textures, environment maps and backplates
+/// absolute.
+///
+/// * `t` - Table to update.
+fn absolute_paths(t: &mut Table) -> Result<(), String> {
+    let is_image = t.get("type").and_then(Value::as_str) == Some("image");
+
+    for (key, value) in t.iter_mut() {
+        match value {
+            Value::String(p)
+                if (is_image && key == "path") || key == "environment" || key == "backplate" =>
+            {
+                let abs = std::path::absolute(Path::new(p.as_str()))
+                    .map_err(|e| format!("Unable to resolve {}: {}", p, e))?;
+                *p = abs.to_string_lossy().into_owned();
+            }
+            Value::Table(child) => absolute_paths(child)?,
+            Value::Array(values) => {
+                for child in values.iter_mut().filter_map(Value::as_table_mut) {
+                    absolute_paths(child)?;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{LoadContext, Scenery, Variation};
+    use super::*;
+    use std::env;
+
+    /// Saving a built-in scene and loading it back describes the same scene.
+    #[test]
+    fn save_and_load_built_in_scenes() {
+        for scenery in Scenery::ALL.iter() {
+            let variation = Variation::new(Some(7), 0.1, None);
+            let context = LoadContext::default();
+            let scene = Scene::generate(*scenery, &variation, 40, 30, true, &context);
+
+            let mut expected = scene.to_table().unwrap();
+            absolute_paths(&mut expected).unwrap();
+
+            let path = env::temp_dir().join(format!(
+                "raytracing_series-{}-{:?}.toml",
+                std::process::id(),
+                scenery
+            ));
+            let path = path.to_str().unwrap();
+            scene.save(path).unwrap();
+
+            let loaded = Scene::from_file(path, 40, 30, true, &LoadContext::default());
+            fs::remove_file(path).unwrap();
+            let loaded = loaded.unwrap_or_else(|e| panic!("{:?}: {}", scenery, e));
+
+            assert_same(
+                &Value::Table(loaded.to_table().unwrap()),
+                &Value::Table(expected),
+                &format!("{:?}", scenery),
+            );
+        }
+    }
+
+    /// Assert two descriptions are the same. Floating point values derived
+    /// when the scene is built, e.g. the camera's `look_at`, may differ in
+    /// the last bits. Objects are compared in any order since the split axes
+    /// of bounding volume hierarchies are random.
+    ///
+    /// * `a` - First description.
+    /// * `b` - Second description.
+    /// * `ctx` - Location in the descriptions.
+    fn assert_same(a: &Value, b: &Value, ctx: &str) {
+        match (a, b) {
+            (Value::Float(x), Value::Float(y)) => assert!(
+                (x - y).abs() <= 1e-9 * x.abs().max(y.abs()).max(1.0),
+                "{}: {} != {}",
+                ctx,
+                x,
+                y
+            ),
+            (Value::Table(x), Value::Table(y)) => {
+                assert_eq!(
+                    x.keys().collect::<Vec<_>>(),
+                    y.keys().collect::<Vec<_>>(),
+                    "{}",
+                    ctx
+                );
+                for (k, v) in x.iter() {
+                    assert_same(v, &y[k], &format!("{}.{}", ctx, k));
+                }
+            }
+            (Value::Array(x), Value::Array(y)) => {
+                assert_eq!(x.len(), y.len(), "{}", ctx);
+                let (x, y) = (sorted(x), sorted(y));
+                for (i, (v, w)) in x.iter().zip(y.iter()).enumerate() {
+                    assert_same(v, w, &format!("{}[{}]", ctx, i));
+                }
+            }
+            _ => assert_eq!(a, b, "{}", ctx),
+        }
+    }
+
+    /// Returns the values of an array with tables sorted by their contents.
+    ///
+    /// * `values` - The values.
+    fn sorted(values: &[Value]) -> Vec<&Value> {
+        let mut values: Vec<&Value> = values.iter().collect();
+        if values.iter().all(|v| v.is_table()) {
+            values.sort_by_cached_key(|v| v.to_string());
+        }
+        values
+    }
+}
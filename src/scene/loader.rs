@@ -0,0 +1,1987 @@
+//! # Loader
+//!
+//! A library for loading scenes from TOML scene description files.
+//!
+//! A scene file has a `camera` table, named `textures` and `materials`
+//! tables and an `objects` array of tables. Textures and materials can be
+//! referenced by name or defined inline. Colours can be used anywhere a
+//! texture is expected.
+//!
+//! ```toml
+//! background = "black" # or "gradient"
+//! environment = "sky.hdr" # optional equirectangular environment map
+//! environment_intensity = 1.0 # optional
+//! environment_storage = "rgb9e5" # optional full, half, rgb9e5
+//! backplate = "street.jpg" # optional photo seen by camera rays that miss
+//! sky = { sun_elevation = 30, sun_azimuth = 45, turbidity = 3 }
+//!                         # optional daylight sky and sun, also takes
+//!                         # `intensity` and `sun_intensity` (0 disables the sun)
+//!
+//! debug_object_ids = false # optional, colour each object by a unique ID
+//! ray_epsilon = 0.001     # optional offset of rays leaving surfaces
+//! ray_t_max = 10000       # optional longest distance tested along a ray
+//!                         # both are derived from the scene bounds if unset
+//! roughness_clamp = false # optional, raise the roughness of glossy materials
+//!                         # with each bounce to suppress fireflies
+//! roughness_clamp_strength = 0.1 # optional roughness added per bounce
+//!
+//! [camera]
+//! look_from = [278, 278, -800]
+//! look_at = [278, 278, 0]
+//! vup = [0, 1, 0]         # optional
+//! vfov = 40
+//! aperture = 0.0          # optional
+//! focus_dist = 10.0       # optional
+//! time0 = 0.0             # optional
+//! time1 = 1.0             # optional
+//! near = 0.0              # optional depth of the near clipping plane
+//! far = 1000.0            # optional depth of the far clipping plane
+//! look_at1 = [300, 278, 0] # optional point looked at by time1, turns the camera
+//! vup1 = [0, 1, 0]        # optional up direction at time1, defaults to vup
+//! shake = { rotation = 0.5, translation = 1, frequency = 4, seed = 1 } # optional
+//!                         # handheld shake in degrees and scene units over time
+//!
+//! [textures.checker]
+//! type = "checker"        # solid, blackbody, checker, image, noise
+//! even = [0.2, 0.3, 0.1]
+//! odd = [0.9, 0.9, 0.9]
+//! scale = 10.0
+//!
+//! [textures.sky]
+//! type = "image"
+//! path = "sky.exr"
+//! storage = "half"        # optional full, half, rgb9e5 for .hdr/.exr images
+//!
+//! [textures.earth]
+//! type = "image"
+//! path = "earth_21k.jpg"
+//! tile_size = 256         # optional, load tiles of very large images on demand
+//! cache_tiles = 256       # optional, tiles kept in memory (default 256)
+//!
+//! [materials.white]
+//! type = "lambertian"     # lambertian, metal, rough_metal, dielectric,
+//!                         # rough_dielectric, diffuse_light, isotropic, mix
+//! albedo = [0.73, 0.73, 0.73]
+//!
+//! [materials.frosted]
+//! type = "dielectric"
+//! ior = 1.5
+//! roughness = 0.2         # optional, 0 (default) is smooth glass
+//! cauchy_b = 0.0042       # optional dispersion of smooth glass in µm²
+//!
+//! [materials.brushed]
+//! type = "rough_metal"
+//! albedo = [0.9, 0.9, 0.9]
+//! roughness = 0.3         # 0 is a mirror
+//!
+//! [materials.worn]
+//! type = "mix"            # picks `b` with probability `amount` per bounce
+//! a = "brushed"           # material names or tables
+//! b = "white"
+//! amount = 0.3            # or a texture, e.g. "rust_mask"
+//!
+//! [materials.light]
+//! type = "diffuse_light"
+//! emit = [15, 15, 15]     # or { type = "blackbody", kelvin = 3200, intensity = 15 }
+//! intensity = 1           # optional, scales the emission
+//! two_sided = false       # optional, emit from the back face too
+//!
+//! [[objects]]
+//! type = "xz_rect"        # sphere, moving_sphere, xy_rect, xz_rect, yz_rect,
+//!                         # plane, box, triangle, sdf, constant_medium,
+//!                         # density_medium, absorbing_medium
+//! x0 = 213
+//! x1 = 343
+//! z0 = 227
+//! z1 = 332
+//! k = 554
+//! material = "light"
+//! flip_face = true        # optional
+//! light = true            # optional, sample this object as a light. Emissive
+//!                         # objects are sampled automatically
+//! name = "ceiling_light"  # optional, used by `--set ceiling_light.k=550` and `--focus-object`
+//! illuminates = ["tall_box"] # optional light linking, see below
+//!
+//! [[objects]]
+//! type = "box"
+//! min = [0, 0, 0]
+//! max = [165, 330, 165]
+//! material = "white"
+//! face_uv = { front = { scale = [0.25, 0.5], offset = [0.25, 0.25], rotate = 0 } }
+//!                         # optional per-face texture coordinates, see below
+//! uv_mapping = "box"      # optional spherical, cylindrical, planar or box
+//!                         # texture coordinates, see below
+//! scale = 1.0             # optional uniform or [x, y, z] scale
+//! rotate_y = 15           # optional rotate_x, rotate_y, rotate_z in degrees
+//! rotate = { axis = [1, 1, 0], degrees = 30 } # optional rotation about any axis
+//! translate = [265, 0, 295] # optional
+//! matrix = [[1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0], [0, 0, 0, 1]] # optional, rows applied last
+//! visible = [0.0, 0.5]    # optional time interval or array of intervals
+//! shadow_softness = 2.0   # optional, scale the size of lights, see below
+//!
+//! [[objects]]
+//! type = "plane"          # infinite plane, e.g. a ground plane
+//! point = [0, 0, 0]
+//! normal = [0, 1, 0]
+//! uv_scale = 1.0          # optional, size of the square textures repeat over
+//! material = "white"
+//!
+//! [[objects]]
+//! type = "sdf"            # sphere traced signed distance function
+//! material = "white"
+//! shape = { type = "smooth_union", k = 0.5, shapes = [
+//!     { type = "sphere", center = [0, 1, 0], radius = 1 },
+//!     { type = "torus", center = [0, 0, 0], major_radius = 2, minor_radius = 0.5 },
+//!     { type = "box", center = [2, 0, 0], size = [1, 1, 1] },
+//! ] }
+//!
+//! [[objects]]
+//! type = "density_medium" # medium with varying density, e.g. clouds
+//! boundary = { type = "sphere", center = [0, 0, 0], radius = 1, material = "white" }
+//! density = 2.0           # maximum density
+//! albedo = [1, 1, 1]
+//! texture = "clouds"      # density from a texture or a grid spanning the
+//! # grid = { size = [2, 1, 1], values = [0.0, 1.0] } # boundary's bounding box
+//!
+//! [[objects]]
+//! type = "absorbing_medium" # medium that only absorbs, e.g. murky water
+//! boundary = { type = "box", min = [0, 0, 0], max = [1, 1, 1], material = "white" }
+//! colour = [0.6, 0.8, 0.7] # colour left after a unit distance at unit density
+//! density = 1.0
+//!
+//! [[objects]]
+//! type = "group"          # objects share the group's transforms
+//! bvh = true              # optional, build a bounding volume hierarchy
+//! objects = [
+//!     { type = "sphere", center = [0, 0, 0], radius = 1, material = "white" },
+//! ]
+//!
+//! [geometry.tree]         # shared geometry, built once for all instances
+//! type = "group"          # groups build a bounding volume hierarchy by default
+//! objects = [
+//!     { type = "sphere", center = [0, 1, 0], radius = 1, material = "white" },
+//! ]
+//!
+//! [[objects]]
+//! type = "instance"       # a copy of shared geometry with its own transforms
+//! geometry = "tree"       # name or inline object definition
+//! translate = [10, 0, 0]
+//!
+//! [[lights]]              # optional, sampled as a light but not rendered
+//! type = "xz_rect"
+//! x0 = 213
+//! x1 = 343
+//! z0 = 227
+//! z1 = 332
+//! k = 554
+//! material = "light"
+//!
+//! [[light_sources]]       # optional, lights without geometry
+//! type = "spot"           # point, spot, directional
+//! position = [278, 500, 278]
+//! look_at = [278, 0, 278]
+//! intensity = [40000, 40000, 40000]
+//! cone_angle = 30         # degrees between the axis and the edge
+//! falloff_angle = 20      # optional, where the falloff starts
+//!
+//! [[sections]]            # optional, cut away geometry for cutaway renders
+//! point = [278, 278, 278]
+//! normal = [0, 0, -1]     # side that is removed
+//! cap = true              # optional, close cut surfaces of solid objects
+//!
+//! [[passes]]              # optional, render pipeline passes run in order
+//! type = "bloom"          # aov, denoise, bloom, exposure, tonemap
+//! threshold = 1.0
+//! ```
+//!
+//! Light linking restricts the lights that illuminate top-level objects.
+//! Lights list the named objects they illuminate with `illuminates` or the
+//! ones they don't with `ignores`. Objects list the named lights that
+//! illuminate them with `lit_by` or the ones that don't with `not_lit_by`.
+//! Lights seen directly by the camera are not affected.
+//!
+//! `uv_mapping` replaces an object's texture coordinates with ones projected
+//! from its untransformed bounding box so image textures can be applied to
+//! boxes, groups and meshes. `spherical` uses longitude and latitude around
+//! the centre, `cylindrical` the angle around and height along the y-axis,
+//! `planar` projects along the z-axis and `box` projects onto the face of the
+//! box the surface faces most.
+//!
+//! `face_uv` on a box transforms the texture coordinates of each face so an
+//! image texture wraps the box like packaging artwork laid out as a net. The
+//! faces are `right`, `left`, `top`, `bottom`, `front` and `back` (+x, -x,
+//! +y, -y, +z, -z). Each face's coordinates are rotated by `rotate` degrees
+//! about its centre, multiplied by `scale` (a number or [u, v]) and moved by
+//! `offset` ([u, v]). Faces that aren't listed span the whole texture.
+//!
+//! `shadow_softness` on a light or an object scales the size of lights as
+//! seen from the objects they illuminate without changing their power. The
+//! scale is the product of the light's and the object's softness so values
+//! above one soften shadows and values below one sharpen them.
+
+use super::{
+    background_from_name, blackbody, build_bvh, build_hittable_list, AbsorbingMedium, ArcHittable,
+    ArcLight, ArcMaterial, ArcTexture, Axis, Backplate, Camera, Checker, ColourStorage,
+    ConstantMedium, DensityField, DensityGrid, DensityMedium, Dielectric, DiffuseLight,
+    DirectionalLight, Environment, FaceUv, FlipFace, Float, Image, Instance, Isotropic, Lambertian,
+    LightLink, LightLinks, LoadContext, Mat4, MemoryBudget, Metal, Mix, MovingSphere, Noise,
+    ObjectId, Plane, Point3, PointLight, Quat, RoughDielectric, RoughMetal, Scene, Sdf, SdfShape,
+    Section, SectionPlane, Shake, Sky, SolidColour, Sphere, SpotLight, Transform, Triangle, UvMap,
+    UvMapping, Vec3, Visibility, XYZbox, XYrect, XZrect, YZrect, FACE_NAMES, INFINITY, X_AXIS,
+    Y_AXIS, Z_AXIS,
+};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use toml::{Table, Value};
+
+/// Number of tiles of a virtual image texture kept in memory by default.
+const DEFAULT_CACHE_TILES: usize = 256;
+
+impl Scene {
+    /// Load a scene from a TOML scene description file. Files with a
+    /// `.pbrt` extension are imported with `Scene::from_pbrt`.
+    ///
+    /// * `path` - Path to the scene file.
+    /// * `image_width` - Image width.
+    /// * `image_height` - Image height.
+    /// * `bvh_enabled` - Use bounding volume hierarchy.
+    /// * `context` - State shared while loading the scene.
+    pub fn from_file(
+        path: &str,
+        image_width: u32,
+        image_height: u32,
+        bvh_enabled: bool,
+        context: &LoadContext,
+    ) -> Result<Scene, String> {
+        if Path::new(path).extension().is_some_and(|e| e == "pbrt") {
+            return Scene::from_pbrt(path, image_width, image_height, bvh_enabled, context);
+        }
+
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+
+        let dir = Path::new(path)
+            .parent()
+            .map_or_else(PathBuf::new, Path::to_path_buf);
+
+        Scene::from_toml(
+            &contents,
+            &dir,
+            image_width,
+            image_height,
+            bvh_enabled,
+            context,
+        )
+        .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Load a scene from a TOML scene description.
+    ///
+    /// * `contents` - The scene description.
+    /// * `dir` - Directory used to resolve relative file paths.
+    /// * `image_width` - Image width.
+    /// * `image_height` - Image height.
+    /// * `bvh_enabled` - Use bounding volume hierarchy.
+    /// * `context` - State shared while loading the scene.
+    pub fn from_toml(
+        contents: &str,
+        dir: &Path,
+        image_width: u32,
+        image_height: u32,
+        bvh_enabled: bool,
+        context: &LoadContext,
+    ) -> Result<Scene, String> {
+        let doc: Table = contents.parse().map_err(|e| format!("{}", e))?;
+        Scene::from_table(&doc, dir, image_width, image_height, bvh_enabled, context)
+    }
+
+    /// Build a scene from a parsed TOML scene description.
+    ///
+    /// * `doc` - The scene description.
+    /// * `dir` - Directory used to resolve relative file paths.
+    /// * `image_width` - Image width.
+    /// * `image_height` - Image height.
+    /// * `bvh_enabled` - Use bounding volume hierarchy.
+    /// * `context` - State shared while loading the scene.
+    pub fn from_table(
+        doc: &Table,
+        dir: &Path,
+        image_width: u32,
+        image_height: u32,
+        bvh_enabled: bool,
+        context: &LoadContext,
+    ) -> Result<Scene, String> {
+        // Replace all materials with per-object debug colours.
+        let debug_doc;
+        let doc = if opt_bool(doc, "debug_object_ids", "scene")?.unwrap_or(false) {
+            debug_doc = with_object_ids(doc);
+            &debug_doc
+        } else {
+            doc
+        };
+
+        let mut loader = Loader::new(doc, dir, context)?;
+
+        let aspect_ratio = image_width as Float / image_height as Float;
+        let camera = parse_camera(table(doc, "camera", "scene")?, aspect_ratio)?;
+
+        let name = opt_str(doc, "background", "scene")?.unwrap_or("black");
+        let background = background_from_name(name)
+            .ok_or_else(|| format!("scene: unknown background `{}`", name))?;
+
+        let mut world: Vec<ArcHittable> = Vec::new();
+        let mut lights: Vec<ArcHittable> = Vec::new();
+
+        let objects = match doc.get("objects") {
+            Some(Value::Array(objects)) => objects.as_slice(),
+            Some(_) => return Err("scene: `objects` must be an array of tables".to_string()),
+            None => &[],
+        };
+
+        // Objects that are only used to sample light sources.
+        let light_only = match doc.get("lights") {
+            Some(Value::Array(objects)) => objects.as_slice(),
+            Some(_) => return Err("scene: `lights` must be an array of tables".to_string()),
+            None => &[],
+        };
+
+        // With light linking every top-level object is tagged with a link ID.
+        let linking = objects
+            .iter()
+            .chain(light_only.iter())
+            .any(|o| LINK_FIELDS.iter().any(|k| o.get(*k).is_some()));
+        let mut links: Vec<LinkEntry> = Vec::new();
+
+        // Named objects, e.g. to focus the camera on.
+        let mut named_objects: HashMap<String, ArcHittable> = HashMap::new();
+
+        for (idx, object) in objects.iter().enumerate() {
+            let ctx = format!("objects[{}]", idx);
+            let t = object
+                .as_table()
+                .ok_or_else(|| format!("{}: must be a table", ctx))?;
+
+            if loader.is_degenerate_triangle(t, &ctx)? {
+                continue;
+            }
+
+            let mut hittable = loader.object(t, &ctx)?;
+            let is_light = opt_bool(t, "light", &ctx)?.unwrap_or(false);
+
+            if let Some(name) = opt_str(t, "name", &ctx)? {
+                named_objects
+                    .entry(name.to_string())
+                    .or_insert_with(|| Arc::clone(&hittable));
+            }
+
+            if linking {
+                hittable = link_entry(&mut links, hittable, t, ctx, is_light, true);
+            }
+
+            world.push(Arc::clone(&hittable));
+            if is_light {
+                lights.push(hittable);
+            }
+        }
+
+        if world.is_empty() {
+            return Err("scene: no objects".to_string());
+        }
+
+        for (idx, object) in light_only.iter().enumerate() {
+            let ctx = format!("lights[{}]", idx);
+            let t = object
+                .as_table()
+                .ok_or_else(|| format!("{}: must be a table", ctx))?;
+
+            if loader.is_degenerate_triangle(t, &ctx)? {
+                continue;
+            }
+
+            let mut hittable = loader.object(t, &ctx)?;
+            if linking {
+                hittable = link_entry(&mut links, hittable, t, ctx, true, false);
+            }
+            lights.push(hittable);
+        }
+
+        let mut scene = Scene::new_scene_with_cache(
+            &world,
+            &lights,
+            camera,
+            background,
+            bvh_enabled,
+            context.bvh_cache.as_ref(),
+        );
+        scene.named_objects = named_objects;
+
+        if linking {
+            let (time0, time1) = scene.camera.shutter();
+            scene.light_links = Some(resolve_light_links(&links, time0, time1)?);
+        }
+
+        scene.sections = match doc.get("sections") {
+            Some(Value::Array(sections)) => sections
+                .iter()
+                .enumerate()
+                .map(|(idx, s)| {
+                    let ctx = format!("sections[{}]", idx);
+                    let t = s
+                        .as_table()
+                        .ok_or_else(|| format!("{}: must be a table", ctx))?;
+                    section_plane(t, &ctx)
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err("scene: `sections` must be an array of tables".to_string()),
+            None => Vec::new(),
+        };
+        if !scene.sections.is_empty() {
+            scene.world = Section::new(Arc::clone(&scene.world), scene.sections.clone());
+        }
+
+        scene.light_sources = match doc.get("light_sources") {
+            Some(Value::Array(lights)) => lights
+                .iter()
+                .enumerate()
+                .map(|(idx, l)| {
+                    let ctx = format!("light_sources[{}]", idx);
+                    let t = l
+                        .as_table()
+                        .ok_or_else(|| format!("{}: must be a table", ctx))?;
+                    light_source(t, &ctx)
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err("scene: `light_sources` must be an array of tables".to_string()),
+            None => Vec::new(),
+        };
+
+        for key in ["ray_epsilon", "ray_t_max"] {
+            if let Some(v) = opt_float(doc, key, "scene")? {
+                if v.is_nan() || v <= 0.0 {
+                    return Err(format!("scene: `{}` must be positive", key));
+                }
+            }
+        }
+        scene.ray_epsilon = opt_float(doc, "ray_epsilon", "scene")?;
+        scene.ray_t_max = opt_float(doc, "ray_t_max", "scene")?;
+
+        if opt_bool(doc, "roughness_clamp", "scene")?.unwrap_or(false) {
+            let strength = opt_float(doc, "roughness_clamp_strength", "scene")?.unwrap_or(0.1);
+            if strength.is_nan() || strength <= 0.0 {
+                return Err("scene: `roughness_clamp_strength` must be positive".to_string());
+            }
+            scene.roughness_clamp = Some(strength);
+        }
+
+        // Passes are validated when the render pipeline is built.
+        scene.passes = match doc.get("passes") {
+            Some(Value::Array(passes)) => passes
+                .iter()
+                .enumerate()
+                .map(|(idx, p)| {
+                    p.as_table()
+                        .cloned()
+                        .ok_or_else(|| format!("passes[{}]: must be a table", idx))
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err("scene: `passes` must be an array of tables".to_string()),
+            None => Vec::new(),
+        };
+
+        if let Some(path) = opt_str(doc, "backplate", "scene")? {
+            let path = loader.resolve(path);
+            scene.backplate = Some(Backplate::new(&path.to_string_lossy(), &context.budget)?);
+        }
+
+        if let Some(path) = opt_str(doc, "environment", "scene")? {
+            let path = loader.resolve(path);
+            let intensity = opt_float(doc, "environment_intensity", "scene")?.unwrap_or(1.0);
+            let storage = opt_storage(doc, "environment_storage", "scene")?;
+            scene.environment = Some(Environment::new(
+                &path.to_string_lossy(),
+                intensity,
+                storage,
+                &context.budget,
+            )?);
+        }
+
+        scene.sky = match opt_table(doc, "sky", "scene")? {
+            Some(t) => Some(parse_sky(t, "sky")?),
+            None => None,
+        };
+
+        Ok(scene)
+    }
+}
+
+/// Resolves named textures and materials while building objects.
+struct Loader<'a> {
+    /// Directory used to resolve relative file paths.
+    dir: &'a Path,
+
+    /// State shared while loading the scene.
+    context: &'a LoadContext,
+
+    /// Texture definitions.
+    texture_defs: Table,
+
+    /// Material definitions.
+    material_defs: Table,
+
+    /// Shared geometry definitions.
+    geometry_defs: Table,
+
+    /// Textures that have been built.
+    textures: HashMap<String, ArcTexture>,
+
+    /// Materials that have been built.
+    materials: HashMap<String, ArcMaterial>,
+
+    /// Shared geometry that has been built.
+    geometry: HashMap<String, ArcHittable>,
+
+    /// Textures, materials and geometry currently being built. Used to
+    /// detect reference cycles.
+    pending: HashSet<String>,
+}
+
+impl<'a> Loader<'a> {
+    /// Create a new loader.
+    ///
+    /// * `doc` - The scene description.
+    /// * `dir` - Directory used to resolve relative file paths.
+    /// * `context` - State shared while loading the scene.
+    fn new(doc: &Table, dir: &'a Path, context: &'a LoadContext) -> Result<Self, String> {
+        Ok(Self {
+            dir,
+            context,
+            texture_defs: opt_table(doc, "textures", "scene")?
+                .cloned()
+                .unwrap_or_default(),
+            material_defs: opt_table(doc, "materials", "scene")?
+                .cloned()
+                .unwrap_or_default(),
+            geometry_defs: opt_table(doc, "geometry", "scene")?
+                .cloned()
+                .unwrap_or_default(),
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            geometry: HashMap::new(),
+            pending: HashSet::new(),
+        })
+    }
+
+    /// Returns a texture from a name, colour or inline definition.
+    ///
+    /// * `value` - The texture value.
+    /// * `ctx` - Context for error messages.
+    fn texture(&mut self, value: &Value, ctx: &str) -> Result<ArcTexture, String> {
+        match value {
+            Value::String(name) => self.named_texture(name, ctx),
+            Value::Array(_) => {
+                let c = to_vec3(value, ctx)?;
+                Ok(SolidColour::from_rgb(c.x(), c.y(), c.z()))
+            }
+            Value::Table(t) => self.texture_def(t, ctx),
+            _ => Err(format!("{}: expected texture name, colour or table", ctx)),
+        }
+    }
+
+    /// Returns a named texture building it if necessary.
+    ///
+    /// * `name` - Texture name.
+    /// * `ctx` - Context for error messages.
+    fn named_texture(&mut self, name: &str, ctx: &str) -> Result<ArcTexture, String> {
+        if let Some(texture) = self.textures.get(name) {
+            return Ok(Arc::clone(texture));
+        }
+
+        let def = match self.texture_defs.get(name) {
+            Some(Value::Table(t)) => t.clone(),
+            Some(_) => return Err(format!("textures.{}: must be a table", name)),
+            None => return Err(format!("{}: unknown texture `{}`", ctx, name)),
+        };
+
+        if !self.pending.insert(name.to_string()) {
+            return Err(format!("textures.{}: references itself", name));
+        }
+
+        let texture = self.texture_def(&def, &format!("textures.{}", name))?;
+
+        self.pending.remove(name);
+        self.textures.insert(name.to_string(), Arc::clone(&texture));
+        Ok(texture)
+    }
+
+    /// Build a texture from its definition.
+    ///
+    /// * `t` - The texture definition.
+    /// * `ctx` - Context for error messages.
+    fn texture_def(&mut self, t: &Table, ctx: &str) -> Result<ArcTexture, String> {
+        match str(t, "type", ctx)? {
+            "solid" => {
+                let c = vec3(t, "colour", ctx)?;
+                Ok(SolidColour::from_rgb(c.x(), c.y(), c.z()))
+            }
+
+            "blackbody" => {
+                let kelvin = float(t, "kelvin", ctx)?;
+                if kelvin <= 0.0 {
+                    return Err(format!("{}: `kelvin` must be positive", ctx));
+                }
+                let intensity = opt_float(t, "intensity", ctx)?.unwrap_or(1.0);
+                Ok(SolidColour::new(blackbody(kelvin) * intensity))
+            }
+
+            "checker" => {
+                let even = self.texture(get(t, "even", ctx)?, &format!("{}.even", ctx))?;
+                let odd = self.texture(get(t, "odd", ctx)?, &format!("{}.odd", ctx))?;
+                match opt_float(t, "scale", ctx)? {
+                    Some(s) => Ok(Checker::scaled(even, odd, s)),
+                    None => Ok(Checker::new(even, odd)),
+                }
+            }
+
+            "image" => {
+                let path = self.resolve(str(t, "path", ctx)?);
+                if !path.is_file() {
+                    return Err(format!("{}: image {} not found", ctx, path.display()));
+                }
+                let storage = opt_storage(t, "storage", ctx)?;
+                match opt_usize(t, "tile_size", ctx)? {
+                    Some(0) => Err(format!("{}: tile_size must be positive", ctx)),
+                    Some(tile_size) => {
+                        let cache_tiles = match opt_usize(t, "cache_tiles", ctx)? {
+                            Some(0) => {
+                                return Err(format!("{}: cache_tiles must be positive", ctx))
+                            }
+                            Some(n) => n,
+                            None => DEFAULT_CACHE_TILES,
+                        };
+                        Ok(Image::virtual_texture(
+                            &path.to_string_lossy(),
+                            tile_size as u32,
+                            cache_tiles,
+                        ))
+                    }
+                    None => Ok(Image::with_storage(
+                        &path.to_string_lossy(),
+                        storage,
+                        &self.context.budget,
+                    )),
+                }
+            }
+
+            "noise" => Ok(Noise::new(
+                opt_float(t, "scale", ctx)?.unwrap_or(1.0),
+                opt_usize(t, "turbulence_depth", ctx)?.unwrap_or(7),
+                opt_float(t, "turbulence_size", ctx)?.unwrap_or(1.0),
+                opt_usize(t, "grid_size", ctx)?.unwrap_or(256),
+                opt_axis(t, "axis", ctx)?.unwrap_or(Z_AXIS),
+            )),
+
+            "object_id" => {
+                let id =
+                    opt_usize(t, "id", ctx)?.ok_or_else(|| format!("{}: missing `id`", ctx))?;
+                Ok(ObjectId::new(id as u64))
+            }
+
+            other => Err(format!("{}: unknown texture type `{}`", ctx, other)),
+        }
+    }
+
+    /// Returns a material from a name or inline definition.
+    ///
+    /// * `value` - The material value.
+    /// * `ctx` - Context for error messages.
+    fn material(&mut self, value: &Value, ctx: &str) -> Result<ArcMaterial, String> {
+        match value {
+            Value::String(name) => {
+                if let Some(material) = self.materials.get(name) {
+                    return Ok(Arc::clone(material));
+                }
+
+                let def = match self.material_defs.get(name) {
+                    Some(Value::Table(t)) => t.clone(),
+                    Some(_) => return Err(format!("materials.{}: must be a table", name)),
+                    None => return Err(format!("{}: unknown material `{}`", ctx, name)),
+                };
+
+                let key = format!("materials.{}", name);
+                if !self.pending.insert(key.clone()) {
+                    return Err(format!("{}: references itself", key));
+                }
+
+                let material = self.material_def(&def, &key)?;
+
+                self.pending.remove(&key);
+                self.materials
+                    .insert(name.to_string(), Arc::clone(&material));
+                Ok(material)
+            }
+            Value::Table(t) => self.material_def(t, ctx),
+            _ => Err(format!("{}: expected material name or table", ctx)),
+        }
+    }
+
+    /// Build a material from its definition.
+    ///
+    /// * `t` - The material definition.
+    /// * `ctx` - Context for error messages.
+    fn material_def(&mut self, t: &Table, ctx: &str) -> Result<ArcMaterial, String> {
+        match str(t, "type", ctx)? {
+            "lambertian" => Ok(Lambertian::new(self.texture_field(t, "albedo", ctx)?)),
+            "metal" => Ok(Metal::new(
+                self.texture_field(t, "albedo", ctx)?,
+                opt_float(t, "fuzz", ctx)?.unwrap_or(0.0),
+            )),
+            "dielectric" if t.contains_key("cauchy_b") => Ok(Dielectric::dispersive(
+                float(t, "ior", ctx)?,
+                float(t, "cauchy_b", ctx)?,
+            )),
+            "dielectric" | "rough_dielectric" => Ok(RoughDielectric::new(
+                float(t, "ior", ctx)?,
+                opt_float(t, "roughness", ctx)?.unwrap_or(0.0),
+            )),
+            "rough_metal" => Ok(RoughMetal::new(
+                self.texture_field(t, "albedo", ctx)?,
+                float(t, "roughness", ctx)?,
+            )),
+            "diffuse_light" => {
+                let intensity = opt_float(t, "intensity", ctx)?.unwrap_or(1.0);
+                if intensity < 0.0 {
+                    return Err(format!("{}: intensity must not be negative", ctx));
+                }
+                Ok(DiffuseLight::with_options(
+                    self.texture_field(t, "emit", ctx)?,
+                    intensity,
+                    opt_bool(t, "two_sided", ctx)?.unwrap_or(false),
+                ))
+            }
+            "mix" => {
+                let a = self.material(get(t, "a", ctx)?, &format!("{}.a", ctx))?;
+                let b = self.material(get(t, "b", ctx)?, &format!("{}.b", ctx))?;
+                let amount = match get(t, "amount", ctx)? {
+                    Value::Float(_) | Value::Integer(_) => {
+                        let f = float(t, "amount", ctx)?;
+                        SolidColour::from_rgb(f, f, f)
+                    }
+                    _ => self.texture_field(t, "amount", ctx)?,
+                };
+                Ok(Mix::new(a, b, amount))
+            }
+            "isotropic" => Ok(Isotropic::new(self.texture_field(t, "albedo", ctx)?)),
+            other => Err(format!("{}: unknown material type `{}`", ctx, other)),
+        }
+    }
+
+    /// Returns the texture for a field of a table.
+    ///
+    /// * `t` - The table.
+    /// * `key` - The field.
+    /// * `ctx` - Context for error messages.
+    fn texture_field(&mut self, t: &Table, key: &str, ctx: &str) -> Result<ArcTexture, String> {
+        self.texture(get(t, key, ctx)?, &format!("{}.{}", ctx, key))
+    }
+
+    /// Returns true if the object is a triangle without area and reports
+    /// that it is dropped. Such triangles can't be hit or sampled.
+    ///
+    /// * `t` - The object definition.
+    /// * `ctx` - Context for error messages.
+    fn is_degenerate_triangle(&self, t: &Table, ctx: &str) -> Result<bool, String> {
+        if str(t, "type", ctx)? != "triangle" {
+            return Ok(false);
+        }
+
+        let area = Triangle::area_of(&vec3_array(t, "vertices", ctx)?);
+        if area.is_finite() && area > 0.0 {
+            return Ok(false);
+        }
+
+        self.context
+            .messages
+            .push(format!("{}: dropped triangle without area", ctx));
+        Ok(true)
+    }
+
+    /// Build an object and apply its transforms.
+    ///
+    /// * `t` - The object definition.
+    /// * `ctx` - Context for error messages.
+    fn object(&mut self, t: &Table, ctx: &str) -> Result<ArcHittable, String> {
+        let mut object = self.shape(t, ctx)?;
+
+        if let Some(s) = opt_str(t, "uv_mapping", ctx)? {
+            let mapping = s
+                .parse::<UvMapping>()
+                .map_err(|_| format!("{}: unknown uv_mapping `{}`", ctx, s))?;
+            object = UvMap::new(object, mapping)
+                .ok_or_else(|| format!("{}: `uv_mapping` needs a bounded object", ctx))?;
+        }
+
+        if opt_bool(t, "flip_face", ctx)?.unwrap_or(false) {
+            object = FlipFace::new(object);
+        }
+
+        // Combine the scale, rotations, translation and matrix into one
+        // transformation applied in that order.
+        let mut matrix = Mat4::IDENTITY;
+
+        match t.get("scale") {
+            Some(Value::Array(_)) => matrix = Mat4::scale(vec3(t, "scale", ctx)?) * matrix,
+            Some(_) => {
+                let s = float(t, "scale", ctx)?;
+                matrix = Mat4::scale(Vec3::new(s, s, s)) * matrix;
+            }
+            None => (),
+        }
+
+        for (key, axis) in [
+            ("rotate_x", X_AXIS),
+            ("rotate_y", Y_AXIS),
+            ("rotate_z", Z_AXIS),
+        ] {
+            if let Some(degrees) = opt_float(t, key, ctx)? {
+                matrix = Mat4::rotate_axis(axis, degrees) * matrix;
+            }
+        }
+
+        if t.contains_key("rotate") {
+            matrix = rotation(t, "rotate", ctx)?.to_mat4() * matrix;
+        }
+
+        if t.contains_key("translate") {
+            matrix = Mat4::translate(vec3(t, "translate", ctx)?) * matrix;
+        }
+
+        if t.contains_key("matrix") {
+            matrix = mat4(t, "matrix", ctx)? * matrix;
+        }
+
+        let instance = str(t, "type", ctx)? == "instance";
+        if matrix != Mat4::IDENTITY || instance {
+            if matrix.inverse().is_none() {
+                return Err(format!("{}: transformation is not invertible", ctx));
+            }
+            object = if instance {
+                Instance::new(object, matrix)
+            } else {
+                Transform::new(object, matrix)
+            };
+        }
+
+        if t.contains_key("visible") {
+            object = Visibility::new(object, visible_intervals(t, ctx)?);
+        }
+
+        Ok(object)
+    }
+
+    /// Build the untransformed shape of an object.
+    ///
+    /// * `t` - The object definition.
+    /// * `ctx` - Context for error messages.
+    fn shape(&mut self, t: &Table, ctx: &str) -> Result<ArcHittable, String> {
+        let kind = str(t, "type", ctx)?;
+
+        if kind == "constant_medium" {
+            let boundary = match get(t, "boundary", ctx)? {
+                Value::Table(b) => self.object(b, &format!("{}.boundary", ctx))?,
+                _ => return Err(format!("{}: `boundary` must be a table", ctx)),
+            };
+            return Ok(ConstantMedium::new(
+                boundary,
+                float(t, "density", ctx)?,
+                self.texture_field(t, "albedo", ctx)?,
+            ));
+        }
+
+        if kind == "absorbing_medium" {
+            let boundary = match get(t, "boundary", ctx)? {
+                Value::Table(b) => self.object(b, &format!("{}.boundary", ctx))?,
+                _ => return Err(format!("{}: `boundary` must be a table", ctx)),
+            };
+            return Ok(AbsorbingMedium::new(
+                boundary,
+                vec3(t, "colour", ctx)?,
+                float(t, "density", ctx)?,
+            ));
+        }
+
+        if kind == "density_medium" {
+            let boundary = match get(t, "boundary", ctx)? {
+                Value::Table(b) => self.object(b, &format!("{}.boundary", ctx))?,
+                _ => return Err(format!("{}: `boundary` must be a table", ctx)),
+            };
+            if boundary.bounding_box(0.0, 1.0).is_none() {
+                return Err(format!("{}: `boundary` must be bounded", ctx));
+            }
+
+            let field = match (t.get("grid"), t.get("texture")) {
+                (Some(_), None) => {
+                    DensityField::Grid(density_grid(t, "grid", ctx, &self.context.budget)?)
+                }
+                (None, Some(_)) => DensityField::Texture(self.texture_field(t, "texture", ctx)?),
+                _ => return Err(format!("{}: needs one of `grid` or `texture`", ctx)),
+            };
+
+            return Ok(DensityMedium::new(
+                boundary,
+                field,
+                float(t, "density", ctx)?,
+                self.texture_field(t, "albedo", ctx)?,
+            ));
+        }
+
+        if kind == "instance" {
+            return self.geometry(get(t, "geometry", ctx)?, &format!("{}.geometry", ctx));
+        }
+
+        if kind == "group" {
+            let objects = match get(t, "objects", ctx)? {
+                Value::Array(objects) => objects,
+                _ => return Err(format!("{}: `objects` must be an array of tables", ctx)),
+            };
+
+            let mut group: Vec<ArcHittable> = Vec::new();
+            for (idx, object) in objects.iter().enumerate() {
+                let ctx = format!("{}.objects[{}]", ctx, idx);
+                match object {
+                    Value::Table(o) if self.is_degenerate_triangle(o, &ctx)? => (),
+                    Value::Table(o) => group.push(self.object(o, &ctx)?),
+                    _ => return Err(format!("{}: must be a table", ctx)),
+                }
+            }
+
+            if group.is_empty() {
+                return Err(format!("{}: group has no objects", ctx));
+            }
+
+            return if opt_bool(t, "bvh", ctx)?.unwrap_or(false) {
+                Ok(build_bvh(&group, self.context.bvh_cache.as_ref()))
+            } else {
+                Ok(build_hittable_list(&group))
+            };
+        }
+
+        let material = self.material(get(t, "material", ctx)?, &format!("{}.material", ctx))?;
+
+        match kind {
+            "sphere" => Ok(Sphere::new(
+                vec3(t, "center", ctx)?,
+                float(t, "radius", ctx)?,
+                material,
+            )),
+
+            "moving_sphere" => Ok(MovingSphere::new(
+                vec3(t, "center0", ctx)?,
+                vec3(t, "center1", ctx)?,
+                float(t, "time0", ctx)?,
+                float(t, "time1", ctx)?,
+                float(t, "radius", ctx)?,
+                material,
+            )),
+
+            "xy_rect" => Ok(XYrect::new(
+                float(t, "x0", ctx)?,
+                float(t, "x1", ctx)?,
+                float(t, "y0", ctx)?,
+                float(t, "y1", ctx)?,
+                float(t, "k", ctx)?,
+                material,
+            )),
+
+            "xz_rect" => Ok(XZrect::new(
+                float(t, "x0", ctx)?,
+                float(t, "x1", ctx)?,
+                float(t, "z0", ctx)?,
+                float(t, "z1", ctx)?,
+                float(t, "k", ctx)?,
+                material,
+            )),
+
+            "sdf" => Ok(Sdf::new(
+                sdf_shape(get(t, "shape", ctx)?, &format!("{}.shape", ctx))?,
+                material,
+            )),
+
+            "plane" => {
+                let uv_scale = opt_float(t, "uv_scale", ctx)?.unwrap_or(1.0);
+                if uv_scale <= 0.0 {
+                    return Err(format!("{}: `uv_scale` must be positive", ctx));
+                }
+
+                Ok(Plane::new(
+                    vec3(t, "point", ctx)?,
+                    vec3(t, "normal", ctx)?,
+                    uv_scale,
+                    material,
+                ))
+            }
+
+            "yz_rect" => Ok(YZrect::new(
+                float(t, "y0", ctx)?,
+                float(t, "y1", ctx)?,
+                float(t, "z0", ctx)?,
+                float(t, "z1", ctx)?,
+                float(t, "k", ctx)?,
+                material,
+            )),
+
+            "box" => match opt_table(t, "face_uv", ctx)? {
+                Some(faces) => Ok(XYZbox::with_face_uvs(
+                    vec3(t, "min", ctx)?,
+                    vec3(t, "max", ctx)?,
+                    material,
+                    face_uvs(faces, &format!("{}.face_uv", ctx))?,
+                )),
+                None => Ok(XYZbox::new(
+                    vec3(t, "min", ctx)?,
+                    vec3(t, "max", ctx)?,
+                    material,
+                )),
+            },
+
+            "triangle" => {
+                let vertices = vec3_array(t, "vertices", ctx)?;
+
+                let normals = if t.contains_key("normals") {
+                    Some(vec3_array(t, "normals", ctx)?)
+                } else {
+                    None
+                };
+
+                let uvs = if t.contains_key("uvs") {
+                    uv_array(t, "uvs", ctx)?
+                } else {
+                    [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]
+                };
+
+                self.context
+                    .budget
+                    .reserve_mesh(std::mem::size_of::<Triangle>(), ctx)?;
+                Triangle::with_attributes(vertices, normals, uvs, material)
+                    .ok_or_else(|| format!("{}: triangle has no area", ctx))
+            }
+
+            other => Err(format!("{}: unknown object type `{}`", ctx, other)),
+        }
+    }
+
+    /// Returns shared geometry from a name or inline definition building it
+    /// if necessary. Groups build a bounding volume hierarchy unless `bvh`
+    /// is set to false so instances form the top level of a two-level
+    /// hierarchy.
+    ///
+    /// * `value` - The geometry value.
+    /// * `ctx` - Context for error messages.
+    fn geometry(&mut self, value: &Value, ctx: &str) -> Result<ArcHittable, String> {
+        let (name, def) = match value {
+            Value::String(name) => {
+                if let Some(geometry) = self.geometry.get(name) {
+                    return Ok(Arc::clone(geometry));
+                }
+
+                match self.geometry_defs.get(name) {
+                    Some(Value::Table(t)) => (Some(name), t.clone()),
+                    Some(_) => return Err(format!("geometry.{}: must be a table", name)),
+                    None => return Err(format!("{}: unknown geometry `{}`", ctx, name)),
+                }
+            }
+            Value::Table(t) => (None, t.clone()),
+            _ => return Err(format!("{}: expected geometry name or table", ctx)),
+        };
+
+        let mut def = def;
+        if def.get("type").and_then(Value::as_str) == Some("group") && !def.contains_key("bvh") {
+            def.insert("bvh".into(), true.into());
+        }
+
+        let name = match name {
+            Some(name) => name,
+            None => return self.object(&def, ctx),
+        };
+
+        let key = format!("geometry.{}", name);
+        if !self.pending.insert(key.clone()) {
+            return Err(format!("{}: references itself", key));
+        }
+
+        let geometry = self.object(&def, &key)?;
+
+        self.pending.remove(&key);
+        self.geometry
+            .insert(name.to_string(), Arc::clone(&geometry));
+        Ok(geometry)
+    }
+
+    /// Resolve a path relative to the scene file or the asset search paths.
+    ///
+    /// * `path` - The path.
+    fn resolve(&self, path: &str) -> PathBuf {
+        self.context.assets.resolve(self.dir, path)
+    }
+}
+
+/// Build the camera.
+///
+/// * `t` - The camera definition.
+/// * `aspect_ratio` - The aspect ratio of image.
+fn parse_camera(t: &Table, aspect_ratio: Float) -> Result<Camera, String> {
+    let ctx = "camera";
+
+    let vup = if t.contains_key("vup") {
+        vec3(t, "vup", ctx)?
+    } else {
+        Vec3::new(0.0, 1.0, 0.0)
+    };
+
+    let near = opt_float(t, "near", ctx)?.unwrap_or(0.0);
+    let far = opt_float(t, "far", ctx)?.unwrap_or(INFINITY);
+    if near < 0.0 || far <= near {
+        return Err(format!("{}: expected 0 <= near < far", ctx));
+    }
+
+    let mut camera = Camera::new(
+        vec3(t, "look_from", ctx)?,
+        vec3(t, "look_at", ctx)?,
+        vup,
+        float(t, "vfov", ctx)?,
+        aspect_ratio,
+        opt_float(t, "aperture", ctx)?.unwrap_or(0.0),
+        opt_float(t, "focus_dist", ctx)?.unwrap_or(10.0),
+        opt_float(t, "time0", ctx)?.unwrap_or(0.0),
+        opt_float(t, "time1", ctx)?.unwrap_or(1.0),
+    )
+    .with_clipping(near, far);
+
+    if t.contains_key("look_at1") {
+        let vup1 = if t.contains_key("vup1") {
+            vec3(t, "vup1", ctx)?
+        } else {
+            vup
+        };
+        camera = camera.with_end_orientation(vec3(t, "look_at1", ctx)?, vup1);
+    }
+
+    if t.contains_key("shake") {
+        let s = table(t, "shake", ctx)?;
+        let ctx = "camera.shake";
+
+        let frequency = opt_float(s, "frequency", ctx)?.unwrap_or(1.0);
+        if frequency <= 0.0 {
+            return Err(format!("{}: `frequency` must be positive", ctx));
+        }
+
+        camera = camera.with_shake(Shake::new(
+            opt_float(s, "rotation", ctx)?.unwrap_or(0.0),
+            opt_float(s, "translation", ctx)?.unwrap_or(0.0),
+            frequency,
+            opt_usize(s, "seed", ctx)?.unwrap_or(0) as u64,
+        ));
+    }
+
+    Ok(camera)
+}
+
+/// Returns the time intervals during which an object is visible. These are
+/// given as a single `[start, end]` pair or an array of pairs.
+///
+/// * `t` - The object definition.
+/// * `ctx` - Context for error messages.
+fn visible_intervals(t: &Table, ctx: &str) -> Result<Vec<(Float, Float)>, String> {
+    let ctx = format!("{}.visible", ctx);
+    let err = || format!("{}: expected [start, end] or an array of them", ctx);
+
+    let values = match t.get("visible") {
+        Some(Value::Array(values)) => values,
+        _ => return Err(err()),
+    };
+
+    // A single pair of numbers.
+    if values.iter().all(|v| !v.is_array()) {
+        return interval(values, &ctx).map(|i| vec![i]);
+    }
+
+    values
+        .iter()
+        .map(|v| match v {
+            Value::Array(pair) => interval(pair, &ctx),
+            _ => Err(err()),
+        })
+        .collect()
+}
+
+/// Returns a time interval given as a `[start, end]` pair.
+///
+/// * `pair` - The pair.
+/// * `ctx` - Context for error messages.
+fn interval(pair: &[Value], ctx: &str) -> Result<(Float, Float), String> {
+    match pair {
+        [start, end] => {
+            let (start, end) = (to_float(start, ctx)?, to_float(end, ctx)?);
+            if start > end {
+                return Err(format!("{}: start {} is after end {}", ctx, start, end));
+            }
+            Ok((start, end))
+        }
+        _ => Err(format!("{}: expected [start, end]", ctx)),
+    }
+}
+
+/// Parse a section plane.
+///
+/// * `t` - Description of the plane.
+/// * `ctx` - Context for error messages.
+fn section_plane(t: &Table, ctx: &str) -> Result<SectionPlane, String> {
+    let normal = vec3(t, "normal", ctx)?;
+    if normal.length_squared() == 0.0 {
+        return Err(format!("{}: `normal` must not be zero", ctx));
+    }
+
+    Ok(SectionPlane::new(
+        vec3(t, "point", ctx)?,
+        normal,
+        opt_bool(t, "cap", ctx)?.unwrap_or(false),
+    ))
+}
+
+/// Parse a daylight sky.
+///
+/// * `t` - Description of the sky.
+/// * `ctx` - Context for error messages.
+fn parse_sky(t: &Table, ctx: &str) -> Result<Arc<Sky>, String> {
+    let sun_elevation = opt_float(t, "sun_elevation", ctx)?.unwrap_or(45.0);
+    if !(0.0..=90.0).contains(&sun_elevation) {
+        return Err(format!("{}: `sun_elevation` must be in [0, 90]", ctx));
+    }
+
+    let turbidity = opt_float(t, "turbidity", ctx)?.unwrap_or(3.0);
+    if !(2.0..=10.0).contains(&turbidity) {
+        return Err(format!("{}: `turbidity` must be in [2, 10]", ctx));
+    }
+
+    Ok(Sky::new(
+        sun_elevation,
+        opt_float(t, "sun_azimuth", ctx)?.unwrap_or(0.0),
+        turbidity,
+        opt_float(t, "intensity", ctx)?.unwrap_or(1.0),
+        opt_float(t, "sun_intensity", ctx)?.unwrap_or(1.0),
+    ))
+}
+
+/// Parse a point, spot or directional light.
+///
+/// * `t` - Description of the light.
+/// * `ctx` - Context for error messages.
+fn light_source(t: &Table, ctx: &str) -> Result<ArcLight, String> {
+    match str(t, "type", ctx)? {
+        "point" => Ok(PointLight::new(
+            vec3(t, "position", ctx)?,
+            vec3(t, "intensity", ctx)?,
+        )),
+        "spot" => {
+            let position = vec3(t, "position", ctx)?;
+            let look_at = vec3(t, "look_at", ctx)?;
+            if (look_at - position).length_squared() == 0.0 {
+                return Err(format!("{}: `look_at` must differ from `position`", ctx));
+            }
+
+            let cone_angle = float(t, "cone_angle", ctx)?;
+            if cone_angle <= 0.0 || cone_angle > 180.0 {
+                return Err(format!("{}: `cone_angle` must be in (0, 180]", ctx));
+            }
+            let falloff_angle = opt_float(t, "falloff_angle", ctx)?.unwrap_or(cone_angle);
+
+            Ok(SpotLight::new(
+                position,
+                look_at,
+                vec3(t, "intensity", ctx)?,
+                cone_angle,
+                falloff_angle,
+            ))
+        }
+        "directional" => {
+            let direction = vec3(t, "direction", ctx)?;
+            if direction.length_squared() == 0.0 {
+                return Err(format!("{}: `direction` must not be zero", ctx));
+            }
+            Ok(DirectionalLight::new(
+                direction,
+                vec3(t, "irradiance", ctx)?,
+            ))
+        }
+        other => Err(format!("{}: unknown light type `{}`", ctx, other)),
+    }
+}
+
+/// Returns a required field of a table.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn get<'t>(t: &'t Table, key: &str, ctx: &str) -> Result<&'t Value, String> {
+    t.get(key)
+        .ok_or_else(|| format!("{}: missing field `{}`", ctx, key))
+}
+
+/// Returns a required table field.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn table<'t>(t: &'t Table, key: &str, ctx: &str) -> Result<&'t Table, String> {
+    opt_table(t, key, ctx)?.ok_or_else(|| format!("{}: missing table `{}`", ctx, key))
+}
+
+/// Returns an optional table field.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn opt_table<'t>(t: &'t Table, key: &str, ctx: &str) -> Result<Option<&'t Table>, String> {
+    match t.get(key) {
+        Some(Value::Table(v)) => Ok(Some(v)),
+        Some(_) => Err(format!("{}: `{}` must be a table", ctx, key)),
+        None => Ok(None),
+    }
+}
+
+/// Returns a required string field.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn str<'t>(t: &'t Table, key: &str, ctx: &str) -> Result<&'t str, String> {
+    opt_str(t, key, ctx)?.ok_or_else(|| format!("{}: missing field `{}`", ctx, key))
+}
+
+/// Returns an optional string field.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn opt_str<'t>(t: &'t Table, key: &str, ctx: &str) -> Result<Option<&'t str>, String> {
+    match t.get(key) {
+        Some(Value::String(v)) => Ok(Some(v)),
+        Some(_) => Err(format!("{}: `{}` must be a string", ctx, key)),
+        None => Ok(None),
+    }
+}
+
+/// Returns an optional boolean field.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn opt_bool(t: &Table, key: &str, ctx: &str) -> Result<Option<bool>, String> {
+    match t.get(key) {
+        Some(Value::Boolean(v)) => Ok(Some(*v)),
+        Some(_) => Err(format!("{}: `{}` must be a boolean", ctx, key)),
+        None => Ok(None),
+    }
+}
+
+/// Returns a required number field.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn float(t: &Table, key: &str, ctx: &str) -> Result<Float, String> {
+    opt_float(t, key, ctx)?.ok_or_else(|| format!("{}: missing field `{}`", ctx, key))
+}
+
+/// Returns an optional number field. Integers are accepted.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn opt_float(t: &Table, key: &str, ctx: &str) -> Result<Option<Float>, String> {
+    t.get(key)
+        .map(|v| to_float(v, &format!("{}.{}", ctx, key)))
+        .transpose()
+}
+
+/// Fields of top-level objects that link lights and objects.
+const LINK_FIELDS: [&str; 5] = [
+    "illuminates",
+    "ignores",
+    "lit_by",
+    "not_lit_by",
+    "shadow_softness",
+];
+
+/// A top-level object of a scene with light linking.
+struct LinkEntry<'a> {
+    /// Light linking ID.
+    link_id: u32,
+
+    /// Description of the object.
+    t: &'a Table,
+
+    /// Context for error messages.
+    ctx: String,
+
+    /// Whether the object is sampled as a light.
+    is_light: bool,
+
+    /// Whether the object is rendered.
+    in_world: bool,
+
+    /// The tagged object.
+    hittable: ArcHittable,
+}
+
+/// Tag a top-level object with the next light linking ID and record it.
+/// Returns the tagged object.
+///
+/// * `links` - Objects tagged so far.
+/// * `hittable` - The object.
+/// * `t` - Description of the object.
+/// * `ctx` - Context for error messages.
+/// * `is_light` - Whether the object is sampled as a light.
+/// * `in_world` - Whether the object is rendered.
+fn link_entry<'a>(
+    links: &mut Vec<LinkEntry<'a>>,
+    hittable: ArcHittable,
+    t: &'a Table,
+    ctx: String,
+    is_light: bool,
+    in_world: bool,
+) -> ArcHittable {
+    let link_id = links.len() as u32 + 1;
+
+    // Keep the name and links so the scene can be saved.
+    let fields: Table = t
+        .iter()
+        .filter(|(k, _)| *k == "name" || LINK_FIELDS.contains(&k.as_str()))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+
+    let hittable = LightLink::new(hittable, link_id, fields);
+    links.push(LinkEntry {
+        link_id,
+        t,
+        ctx,
+        is_light,
+        in_world,
+        hittable: Arc::clone(&hittable),
+    });
+    hittable
+}
+
+/// Resolve the light links of a scene. Lights list the objects they
+/// illuminate with `illuminates` or the objects they don't with `ignores`.
+/// Objects list the lights that illuminate them with `lit_by` or the lights
+/// that don't with `not_lit_by`. Objects and lights are referred to by name.
+/// Both can set a `shadow_softness` that scales the size of lights.
+///
+/// * `links` - The top-level objects.
+/// * `time0` - Start time of motion.
+/// * `time1` - End time of motion.
+fn resolve_light_links(
+    links: &[LinkEntry],
+    time0: Float,
+    time1: Float,
+) -> Result<Arc<LightLinks>, String> {
+    let mut names: HashMap<&str, &LinkEntry> = HashMap::new();
+    for e in links.iter() {
+        if let Some(name) = opt_str(e.t, "name", &e.ctx)? {
+            if names.insert(name, e).is_some() {
+                return Err(format!("{}: duplicate object name `{}`", e.ctx, name));
+            }
+        }
+    }
+
+    // Returns the link IDs of the named objects of a field.
+    let lookup = |e: &LinkEntry, key: &str, lights: bool| -> Result<Option<HashSet<u32>>, String> {
+        let values = match e.t.get(key) {
+            Some(Value::Array(values)) => values,
+            Some(_) => return Err(format!("{}: `{}` must be an array of names", e.ctx, key)),
+            None => return Ok(None),
+        };
+
+        values
+            .iter()
+            .map(|v| {
+                let name = v
+                    .as_str()
+                    .ok_or_else(|| format!("{}: `{}` must be an array of names", e.ctx, key))?;
+                match names.get(name) {
+                    Some(target) if lights && !target.is_light => {
+                        Err(format!("{}.{}: `{}` is not a light", e.ctx, key, name))
+                    }
+                    Some(target) => Ok(target.link_id),
+                    None => Err(format!("{}.{}: no object named `{}`", e.ctx, key, name)),
+                }
+            })
+            .collect::<Result<HashSet<u32>, String>>()
+            .map(Some)
+    };
+
+    let mut excluded: HashSet<(u32, u32)> = HashSet::new();
+    let mut softness: HashMap<u32, Float> = HashMap::new();
+
+    for e in links.iter() {
+        if let Some(s) = opt_float(e.t, "shadow_softness", &e.ctx)? {
+            if s <= 0.0 {
+                return Err(format!("{}: `shadow_softness` must be positive", e.ctx));
+            }
+            softness.insert(e.link_id, s);
+        }
+
+        for key in ["illuminates", "ignores"] {
+            if e.t.contains_key(key) && !e.is_light {
+                return Err(format!("{}: `{}` is only valid for lights", e.ctx, key));
+            }
+        }
+
+        if let Some(objects) = lookup(e, "illuminates", false)? {
+            for o in links.iter().filter(|o| o.in_world) {
+                if !objects.contains(&o.link_id) {
+                    excluded.insert((o.link_id, e.link_id));
+                }
+            }
+        }
+
+        if let Some(objects) = lookup(e, "ignores", false)? {
+            for o in objects {
+                excluded.insert((o, e.link_id));
+            }
+        }
+
+        if let Some(lights) = lookup(e, "lit_by", true)? {
+            for l in links.iter().filter(|l| l.is_light) {
+                if !lights.contains(&l.link_id) {
+                    excluded.insert((e.link_id, l.link_id));
+                }
+            }
+        }
+
+        if let Some(lights) = lookup(e, "not_lit_by", true)? {
+            for l in lights {
+                excluded.insert((e.link_id, l));
+            }
+        }
+    }
+
+    let lights: Vec<(u32, ArcHittable)> = links
+        .iter()
+        .filter(|e| e.is_light)
+        .map(|e| (e.link_id, Arc::clone(&e.hittable)))
+        .collect();
+
+    let objects: Vec<u32> = links
+        .iter()
+        .filter(|e| e.in_world)
+        .map(|e| e.link_id)
+        .collect();
+
+    Ok(LightLinks::new(
+        &lights, &objects, excluded, softness, time0, time1,
+    ))
+}
+
+/// Returns a copy of a scene description where every object is a lambertian
+/// surface coloured by a unique object ID. Constant media use the ID colour
+/// as their albedo. IDs are assigned in the order objects appear with the
+/// objects in groups numbered individually.
+///
+/// * `doc` - The scene description.
+fn with_object_ids(doc: &Table) -> Table {
+    let mut doc = doc.clone();
+    let mut next_id = 0;
+
+    if let Some(Value::Array(objects)) = doc.get_mut("objects") {
+        for object in objects.iter_mut().filter_map(Value::as_table_mut) {
+            assign_object_ids(object, &mut next_id);
+        }
+    }
+
+    // Instances of shared geometry share its IDs.
+    if let Some(Value::Table(geometry)) = doc.get_mut("geometry") {
+        for (_, object) in geometry.iter_mut() {
+            if let Value::Table(object) = object {
+                assign_object_ids(object, &mut next_id);
+            }
+        }
+    }
+
+    doc
+}
+
+/// Replace the materials of an object and its group members with object ID
+/// textures.
+///
+/// * `t` - The object definition.
+/// * `next_id` - The next unused object ID.
+fn assign_object_ids(t: &mut Table, next_id: &mut u64) {
+    let mut id_texture = || {
+        let mut texture = Table::new();
+        texture.insert("type".into(), "object_id".into());
+        texture.insert("id".into(), (*next_id as i64).into());
+        *next_id += 1;
+        Value::Table(texture)
+    };
+
+    match t.get("type").and_then(Value::as_str) {
+        Some("group") => {
+            if let Some(Value::Array(objects)) = t.get_mut("objects") {
+                for object in objects.iter_mut().filter_map(Value::as_table_mut) {
+                    assign_object_ids(object, next_id);
+                }
+            }
+        }
+
+        Some("constant_medium") => {
+            t.insert("albedo".into(), id_texture());
+        }
+
+        Some("instance") => {
+            if let Some(Value::Table(geometry)) = t.get_mut("geometry") {
+                assign_object_ids(geometry, next_id);
+            }
+        }
+
+        _ => {
+            let mut material = Table::new();
+            material.insert("type".into(), "lambertian".into());
+            material.insert("albedo".into(), id_texture());
+            t.insert("material".into(), Value::Table(material));
+        }
+    }
+}
+
+/// Returns an optional non-negative integer field.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn opt_usize(t: &Table, key: &str, ctx: &str) -> Result<Option<usize>, String> {
+    match t.get(key) {
+        Some(Value::Integer(v)) if *v >= 0 => Ok(Some(*v as usize)),
+        Some(_) => Err(format!("{}: `{}` must be a non-negative integer", ctx, key)),
+        None => Ok(None),
+    }
+}
+
+/// Returns an optional axis field which is one of `"x"`, `"y"` or `"z"`.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn opt_axis(t: &Table, key: &str, ctx: &str) -> Result<Option<Axis>, String> {
+    match opt_str(t, key, ctx)? {
+        Some("x") => Ok(Some(X_AXIS)),
+        Some("y") => Ok(Some(Y_AXIS)),
+        Some("z") => Ok(Some(Z_AXIS)),
+        Some(other) => Err(format!("{}: unknown axis `{}`", ctx, other)),
+        None => Ok(None),
+    }
+}
+
+/// Returns an optional colour storage format field which is one of
+/// `"full"`, `"half"` or `"rgb9e5"`. Defaults to `"rgb9e5"`.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn opt_storage(t: &Table, key: &str, ctx: &str) -> Result<ColourStorage, String> {
+    match opt_str(t, key, ctx)? {
+        Some(s) => s
+            .parse::<ColourStorage>()
+            .map_err(|_| format!("{}: unknown storage `{}`", ctx, s)),
+        None => Ok(ColourStorage::Rgb9e5),
+    }
+}
+
+/// Returns a required 3-D vector field.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn vec3(t: &Table, key: &str, ctx: &str) -> Result<Vec3, String> {
+    to_vec3(get(t, key, ctx)?, &format!("{}.{}", ctx, key))
+}
+
+/// Returns a required field containing a matrix as an array of 4 rows of 4
+/// numbers.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn mat4(t: &Table, key: &str, ctx: &str) -> Result<Mat4, String> {
+    let ctx = format!("{}.{}", ctx, key);
+    let err = || format!("{}: expected an array of 4 rows of 4 numbers", ctx);
+
+    let rows = match get(t, key, &ctx)? {
+        Value::Array(rows) if rows.len() == 4 => rows,
+        _ => return Err(err()),
+    };
+
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in rows.iter().enumerate() {
+        match row {
+            Value::Array(r) if r.len() == 4 => {
+                for (j, v) in r.iter().enumerate() {
+                    m[i][j] = to_float(v, &ctx)?;
+                }
+            }
+            _ => return Err(err()),
+        }
+    }
+
+    Ok(Mat4::new(m))
+}
+
+/// Returns a required field containing a rotation as a table with an `axis`
+/// vector and an angle in `degrees`.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn rotation(t: &Table, key: &str, ctx: &str) -> Result<Quat, String> {
+    let r = table(t, key, ctx)?;
+    let ctx = format!("{}.{}", ctx, key);
+
+    let axis = vec3(r, "axis", &ctx)?;
+    if axis.length_squared() == 0.0 {
+        return Err(format!("{}: `axis` must not be zero", ctx));
+    }
+    Ok(Quat::from_axis_angle(axis, float(r, "degrees", &ctx)?))
+}
+
+/// Returns a required field containing a density grid as a table with the
+/// number of cells along each axis in `size` and the densities in `values`
+/// with x varying fastest, then y, then z.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+/// * `budget` - Memory budget the grid is reserved in.
+fn density_grid(
+    t: &Table,
+    key: &str,
+    ctx: &str,
+    budget: &MemoryBudget,
+) -> Result<DensityGrid, String> {
+    let g = table(t, key, ctx)?;
+    let ctx = format!("{}.{}", ctx, key);
+
+    let size = match get(g, "size", &ctx)? {
+        Value::Array(a) if a.len() == 3 => {
+            let mut size = [0; 3];
+            for (n, v) in size.iter_mut().zip(a.iter()) {
+                *n = match v {
+                    Value::Integer(i) if *i > 0 => *i as usize,
+                    _ => return Err(format!("{}: `size` must be positive integers", ctx)),
+                };
+            }
+            size
+        }
+        _ => return Err(format!("{}: `size` must be an array of 3 integers", ctx)),
+    };
+
+    let values = match get(g, "values", &ctx)? {
+        Value::Array(a) => a
+            .iter()
+            .map(|v| to_float(v, &format!("{}.values", ctx)))
+            .collect::<Result<Vec<Float>, String>>()?,
+        _ => return Err(format!("{}: `values` must be an array of numbers", ctx)),
+    };
+
+    let too_large = || format!("{}: `size` is too large", ctx);
+    let count = size[0]
+        .checked_mul(size[1])
+        .and_then(|n| n.checked_mul(size[2]))
+        .ok_or_else(too_large)?;
+    let bytes = count
+        .checked_mul(std::mem::size_of::<Float>())
+        .ok_or_else(too_large)?;
+    budget.reserve_mesh(bytes, &ctx)?;
+    DensityGrid::new(size, values).ok_or_else(|| {
+        format!(
+            "{}: expected {} non-negative `values` for `size`",
+            ctx, count
+        )
+    })
+}
+
+/// Returns a signed distance function from its definition. Shapes are a
+/// `sphere` with a `center` and `radius`, a `box` with a `center` and
+/// `size`, a `torus` around the y-axis with a `center`, `major_radius` and
+/// `minor_radius` or a `smooth_union` of `shapes` blended over `k`.
+///
+/// * `value` - The shape definition.
+/// * `ctx` - Context for error messages.
+fn sdf_shape(value: &Value, ctx: &str) -> Result<SdfShape, String> {
+    let t = match value {
+        Value::Table(t) => t,
+        _ => return Err(format!("{}: must be a table", ctx)),
+    };
+
+    let positive = |key: &str| -> Result<Float, String> {
+        let x = float(t, key, ctx)?;
+        if x > 0.0 {
+            Ok(x)
+        } else {
+            Err(format!("{}: `{}` must be positive", ctx, key))
+        }
+    };
+
+    match str(t, "type", ctx)? {
+        "sphere" => Ok(SdfShape::Sphere {
+            center: vec3(t, "center", ctx)?,
+            radius: positive("radius")?,
+        }),
+
+        "box" => Ok(SdfShape::Box {
+            center: vec3(t, "center", ctx)?,
+            half_size: vec3(t, "size", ctx)? * 0.5,
+        }),
+
+        "torus" => Ok(SdfShape::Torus {
+            center: vec3(t, "center", ctx)?,
+            major_radius: positive("major_radius")?,
+            minor_radius: positive("minor_radius")?,
+        }),
+
+        "smooth_union" => {
+            let shapes = match get(t, "shapes", ctx)? {
+                Value::Array(shapes) if !shapes.is_empty() => shapes,
+                _ => return Err(format!("{}: `shapes` must be a non-empty array", ctx)),
+            };
+
+            Ok(SdfShape::SmoothUnion {
+                shapes: shapes
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, s)| sdf_shape(s, &format!("{}.shapes[{}]", ctx, idx)))
+                    .collect::<Result<Vec<_>, String>>()?,
+                k: opt_float(t, "k", ctx)?.unwrap_or(0.0).max(0.0),
+            })
+        }
+
+        other => Err(format!("{}: unknown sdf shape `{}`", ctx, other)),
+    }
+}
+
+/// Returns a required field containing an array of 3 vectors.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn vec3_array(t: &Table, key: &str, ctx: &str) -> Result<[Vec3; 3], String> {
+    let ctx = format!("{}.{}", ctx, key);
+    match get(t, key, &ctx)? {
+        Value::Array(a) if a.len() == 3 => Ok([
+            to_vec3(&a[0], &ctx)?,
+            to_vec3(&a[1], &ctx)?,
+            to_vec3(&a[2], &ctx)?,
+        ]),
+        _ => Err(format!("{}: expected an array of 3 vectors", ctx)),
+    }
+}
+
+/// Returns a required field containing an array of 3 texture coordinates.
+///
+/// * `t` - The table.
+/// * `key` - The field.
+/// * `ctx` - Context for error messages.
+fn uv_array(t: &Table, key: &str, ctx: &str) -> Result<[(Float, Float); 3], String> {
+    let ctx = format!("{}.{}", ctx, key);
+    let uv = |v: &Value| match v {
+        Value::Array(a) if a.len() == 2 => Ok((to_float(&a[0], &ctx)?, to_float(&a[1], &ctx)?)),
+        _ => Err(format!("{}: expected [u, v]", ctx)),
+    };
+
+    match get(t, key, &ctx)? {
+        Value::Array(a) if a.len() == 3 => Ok([uv(&a[0])?, uv(&a[1])?, uv(&a[2])?]),
+        _ => Err(format!(
+            "{}: expected an array of 3 texture coordinates",
+            ctx
+        )),
+    }
+}
+
+/// Returns the texture coordinate transforms of the faces of a box in the
+/// order of `FACE_NAMES`. Faces that aren't listed use the identity
+/// transform.
+///
+/// * `t` - The table of faces.
+/// * `ctx` - Context for error messages.
+fn face_uvs(t: &Table, ctx: &str) -> Result<[FaceUv; 6], String> {
+    if let Some(name) = t.keys().find(|k| !FACE_NAMES.contains(&k.as_str())) {
+        return Err(format!(
+            "{}: unknown face `{}`, expected one of {}",
+            ctx,
+            name,
+            FACE_NAMES.join(", ")
+        ));
+    }
+
+    let mut face_uvs = [FaceUv::default(); 6];
+    for (face_uv, name) in face_uvs.iter_mut().zip(FACE_NAMES.iter()) {
+        let f = match opt_table(t, name, ctx)? {
+            Some(f) => f,
+            None => continue,
+        };
+        let ctx = format!("{}.{}", ctx, name);
+
+        if let Some(v) = f.get("scale") {
+            face_uv.scale = match v {
+                Value::Array(_) => to_uv(v, &format!("{}.scale", ctx))?,
+                _ => {
+                    let s = to_float(v, &format!("{}.scale", ctx))?;
+                    (s, s)
+                }
+            };
+        }
+        if let Some(v) = f.get("offset") {
+            face_uv.offset = to_uv(v, &format!("{}.offset", ctx))?;
+        }
+        face_uv.rotate = opt_float(f, "rotate", &ctx)?.unwrap_or(0.0);
+    }
+    Ok(face_uvs)
+}
+
+/// Convert a value to a pair of texture coordinates.
+///
+/// * `v` - The value.
+/// * `ctx` - Context for error messages.
+fn to_uv(v: &Value, ctx: &str) -> Result<(Float, Float), String> {
+    match v {
+        Value::Array(a) if a.len() == 2 => Ok((to_float(&a[0], ctx)?, to_float(&a[1], ctx)?)),
+        _ => Err(format!("{}: expected [u, v]", ctx)),
+    }
+}
+
+/// Convert a value to a number. Integers are accepted.
+///
+/// * `v` - The value.
+/// * `ctx` - Context for error messages.
+fn to_float(v: &Value, ctx: &str) -> Result<Float, String> {
+    match v {
+        Value::Float(f) => Ok(*f as Float),
+        Value::Integer(i) => Ok(*i as Float),
+        _ => Err(format!("{}: expected a number", ctx)),
+    }
+}
+
+/// Convert a value to a 3-D vector.
+///
+/// * `v` - The value.
+/// * `ctx` - Context for error messages.
+fn to_vec3(v: &Value, ctx: &str) -> Result<Point3, String> {
+    match v {
+        Value::Array(a) if a.len() == 3 => Ok(Vec3::new(
+            to_float(&a[0], ctx)?,
+            to_float(&a[1], ctx)?,
+            to_float(&a[2], ctx)?,
+        )),
+        _ => Err(format!("{}: expected [x, y, z]", ctx)),
+    }
+}
@@ -4,20 +4,39 @@
 
 #![allow(dead_code)]
 
+mod assets;
+mod context;
+mod export;
+mod loader;
+mod overrides;
+mod pbrt;
+mod scatter;
+mod variation;
+
+pub use self::assets::Assets;
+pub use self::context::LoadContext;
+pub use self::overrides::SceneOverride;
+pub use self::scatter::{Domain, Scatter};
+pub use self::variation::Variation;
+
 use super::algebra::*;
 use super::background::*;
 use super::camera::*;
 use super::common::*;
+use super::light::*;
 use super::material::*;
 use super::object::*;
 use super::texture::*;
+#[cfg(feature = "cli")]
 use clap::ValueEnum;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::sync::Arc;
-use std::time::Instant;
+use toml::Table;
 
 /// Scene types.
-#[derive(Debug, Copy, Clone, ValueEnum)]
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
 pub enum Scenery {
     LambertianDiffuse,
     Metal,
@@ -39,6 +58,78 @@ pub enum Scenery {
     RotateSpheres,
     SpecularReflections,
     FinalRestOfYourLife,
+    Triangles,
+}
+
+impl Scenery {
+    /// All built-in scenes.
+    pub const ALL: [Scenery; 21] = [
+        Scenery::LambertianDiffuse,
+        Scenery::Metal,
+        Scenery::Dielectric,
+        Scenery::WideAngle,
+        Scenery::Telephoto,
+        Scenery::DefocusBlur,
+        Scenery::FinalOneWeekend,
+        Scenery::MotionBlur,
+        Scenery::CheckeredFloor,
+        Scenery::CheckeredSpheres,
+        Scenery::PerlinSpheres,
+        Scenery::Earth,
+        Scenery::SimpleLight,
+        Scenery::EmptyCornellBox,
+        Scenery::CornellBox,
+        Scenery::SmokeAndFog,
+        Scenery::FinalNextWeek,
+        Scenery::RotateSpheres,
+        Scenery::SpecularReflections,
+        Scenery::FinalRestOfYourLife,
+        Scenery::Triangles,
+    ];
+
+    /// Returns true if the scene has randomly generated objects that are
+    /// varied by the scene seed, density and count.
+    pub fn uses_variation(&self) -> bool {
+        matches!(
+            self,
+            Scenery::FinalOneWeekend
+                | Scenery::MotionBlur
+                | Scenery::CheckeredFloor
+                | Scenery::FinalNextWeek
+        )
+    }
+}
+
+impl fmt::Display for Scenery {
+    /// Display the scene name as used on the command line.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Scenery::LambertianDiffuse => "lambertian-diffuse",
+            Scenery::Metal => "metal",
+            Scenery::Dielectric => "dielectric",
+            Scenery::WideAngle => "wide-angle",
+            Scenery::Telephoto => "telephoto",
+            Scenery::DefocusBlur => "defocus-blur",
+            Scenery::FinalOneWeekend => "final-one-weekend",
+            Scenery::MotionBlur => "motion-blur",
+            Scenery::CheckeredFloor => "checkered-floor",
+            Scenery::CheckeredSpheres => "checkered-spheres",
+            Scenery::PerlinSpheres => "perlin-spheres",
+            Scenery::Earth => "earth",
+            Scenery::SimpleLight => "simple-light",
+            Scenery::EmptyCornellBox => "empty-cornell-box",
+            Scenery::CornellBox => "cornell-box",
+            Scenery::SmokeAndFog => "smoke-and-fog",
+            Scenery::FinalNextWeek => "final-next-week",
+            Scenery::RotateSpheres => "rotate-spheres",
+            Scenery::SpecularReflections => "specular-reflections",
+            Scenery::FinalRestOfYourLife => "final-rest-of-your-life",
+            Scenery::Triangles => "triangles",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 /// Models a scene.
@@ -53,19 +144,90 @@ pub struct Scene {
     /// Lights in the scene.
     pub lights: ArcHittable,
 
+    /// Emissive objects of the scene. Paths traced from the lights start on
+    /// these.
+    pub emitters: Vec<ArcHittable>,
+
     /// Background.
     pub background: BackgroundFn,
+
+    /// Environment map used instead of the background when present.
+    pub environment: Option<Arc<Environment>>,
+
+    /// Daylight sky used instead of the background when present. An
+    /// environment map takes precedence.
+    pub sky: Option<Arc<Sky>>,
+
+    /// Photo shown where camera rays don't hit any object.
+    pub backplate: Option<Arc<Backplate>>,
+
+    /// Point, spot and directional lights sampled with shadow rays.
+    pub light_sources: Vec<ArcLight>,
+
+    /// Indicates whether `lights` contains any objects.
+    pub has_lights: bool,
+
+    /// Descriptions of the render pipeline passes from the scene file.
+    pub passes: Vec<Table>,
+
+    /// Light links restricting the lights that illuminate objects.
+    pub light_links: Option<Arc<LightLinks>>,
+
+    /// Section planes cutting the objects of the scene.
+    pub sections: Vec<SectionPlane>,
+
+    /// Top-level objects of a scene file by name.
+    pub named_objects: HashMap<String, ArcHittable>,
+
+    /// Offset of rays leaving surfaces. Derived from the bounds of the scene
+    /// if not set.
+    pub ray_epsilon: Option<Float>,
+
+    /// Longest distance along a ray tested for intersections. Derived from
+    /// the bounds of the scene if not set.
+    pub ray_t_max: Option<Float>,
+
+    /// Roughness added per bounce to the minimum roughness of glossy and
+    /// specular materials. This blurs caustics and glossy reflections seen
+    /// through other reflections to reduce fireflies. `None` disables it.
+    pub roughness_clamp: Option<Float>,
 }
 
 impl Scene {
-    /// Create a new scene.
+    /// Create a new scene without a memory budget.
     ///
     /// * `scenery` - Scene.
     /// * `image_width` - Image width.
     /// * `image_height` - Image height.
     /// * `bvh_enabled` - Use bounding volume hierarchy.
     pub fn new(scenery: Scenery, image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
-        match scenery {
+        Scene::generate(
+            scenery,
+            &Variation::default(),
+            image_width,
+            image_height,
+            bvh_enabled,
+            &LoadContext::default(),
+        )
+    }
+
+    /// Create a new scene varying the randomly generated objects.
+    ///
+    /// * `scenery` - Scene.
+    /// * `variation` - Seed and number of randomly generated objects.
+    /// * `image_width` - Image width.
+    /// * `image_height` - Image height.
+    /// * `bvh_enabled` - Use bounding volume hierarchy.
+    /// * `context` - State shared while loading the scene.
+    pub fn generate(
+        scenery: Scenery,
+        variation: &Variation,
+        image_width: u32,
+        image_height: u32,
+        bvh_enabled: bool,
+        context: &LoadContext,
+    ) -> Scene {
+        variation.generate(|| match scenery {
             Scenery::LambertianDiffuse => diffuse_spheres(image_width, image_height, bvh_enabled),
             Scenery::Metal => metal_spheres(image_width, image_height, bvh_enabled),
             Scenery::Dielectric => dielectric_spheres(image_width, image_height, bvh_enabled),
@@ -78,19 +240,25 @@ impl Scene {
             Scenery::DefocusBlur => {
                 defocus_blue_dielectric_spheres(image_width, image_height, bvh_enabled)
             }
-            Scenery::FinalOneWeekend => final_one_weekend(image_width, image_height, bvh_enabled),
-            Scenery::MotionBlur => motion_blur(image_width, image_height, bvh_enabled),
-            Scenery::CheckeredFloor => checkered_floor(image_width, image_height, bvh_enabled),
+            Scenery::FinalOneWeekend => {
+                final_one_weekend(image_width, image_height, bvh_enabled, variation)
+            }
+            Scenery::MotionBlur => motion_blur(image_width, image_height, bvh_enabled, variation),
+            Scenery::CheckeredFloor => {
+                checkered_floor(image_width, image_height, bvh_enabled, variation)
+            }
             Scenery::CheckeredSpheres => checkered_spheres(image_width, image_height, bvh_enabled),
             Scenery::PerlinSpheres => perlin_spheres(image_width, image_height, bvh_enabled),
-            Scenery::Earth => earth(image_width, image_height, bvh_enabled),
+            Scenery::Earth => earth(image_width, image_height, bvh_enabled, context),
             Scenery::SimpleLight => simple_light(image_width, image_height, bvh_enabled),
             Scenery::EmptyCornellBox => empty_cornell_box(image_width, image_height, bvh_enabled),
             Scenery::CornellBox => cornell_box(image_width, image_height, bvh_enabled),
             Scenery::SmokeAndFog => {
                 cornell_box_smoke_and_fog(image_width, image_height, bvh_enabled)
             }
-            Scenery::FinalNextWeek => final_next_week(image_width, image_height, bvh_enabled),
+            Scenery::FinalNextWeek => {
+                final_next_week(image_width, image_height, bvh_enabled, variation, context)
+            }
             Scenery::RotateSpheres => rotate_spheres(image_width, image_height, bvh_enabled),
             Scenery::SpecularReflections => {
                 specular_reflections(image_width, image_height, bvh_enabled)
@@ -98,9 +266,143 @@ impl Scene {
             Scenery::FinalRestOfYourLife => {
                 final_rest_of_your_life(image_width, image_height, bvh_enabled)
             }
+            Scenery::Triangles => triangles(image_width, image_height, bvh_enabled),
+        })
+    }
+
+    /// Returns the bounding box of the objects in the scene over the shutter
+    /// interval of the camera or `None` if the world has no bounds.
+    pub fn bounds(&self) -> Option<AABB> {
+        let (time0, time1) = self.camera.shutter();
+        self.world.bounding_box(time0, time1)
+    }
+
+    /// Returns the offset of rays leaving surfaces and the longest distance
+    /// along a ray tested for intersections. Unset values are derived from
+    /// the bounds of the objects and the camera position so scenes of any
+    /// scale avoid self intersections without losing detail.
+    pub fn ray_limits(&self) -> (Float, Float) {
+        let origin = self.camera.origin();
+        let bbox = self
+            .bounds()
+            .map(|b| AABB::surrounding_box(b, AABB::new(origin, origin)))
+            .filter(|b| (b.max - b.min).length().is_finite());
+
+        let epsilon = self.ray_epsilon.unwrap_or_else(|| match bbox {
+            Some(b) => {
+                let scale = (0..3)
+                    .map(|a| b.min[a].abs().max(b.max[a].abs()))
+                    .fold(0.0, Float::max);
+                (scale * RAY_EPSILON_SCALE).max(Float::EPSILON)
+            }
+            None => RAY_EPSILON,
+        });
+
+        // No intersection can be further away than the diagonal of the box.
+        // Twice the diagonal leaves room for camera rays that start off the
+        // camera position due to the lens or camera shake.
+        let t_max = self.ray_t_max.unwrap_or_else(|| match bbox {
+            Some(b) => 2.0 * (b.max - b.min).length(),
+            None => INFINITY,
+        });
+
+        (epsilon, t_max)
+    }
+
+    /// Returns the scene with the camera moved along its viewing direction to
+    /// frame the bounding sphere of the objects.
+    pub fn auto_frame(&self) -> Result<Scene, String> {
+        let bbox = self
+            .bounds()
+            .ok_or_else(|| "Scene has no bounds to frame".to_string())?;
+
+        let centre = (bbox.min + bbox.max) * 0.5;
+        let radius = 0.5 * (bbox.max - bbox.min).length();
+        if !radius.is_finite() || radius <= 0.0 {
+            return Err(format!("Unable to frame scene bounds {}", bbox));
         }
+
+        Ok(Scene {
+            camera: self.camera.frame(centre, radius),
+            ..self.clone()
+        })
+    }
+
+    /// Returns the scene with the camera focused on the centre of the
+    /// bounding box of a named object.
+    ///
+    /// * `name` - Name of the object.
+    /// * `look_at` - Whether to turn the camera to look at the object.
+    pub fn focus_on(&self, name: &str, look_at: bool) -> Result<Scene, String> {
+        let object = self
+            .named_objects
+            .get(name)
+            .ok_or_else(|| format!("Scene has no object named `{}`", name))?;
+
+        let (time0, time1) = self.camera.shutter();
+        let bbox = object
+            .bounding_box(time0, time1)
+            .ok_or_else(|| format!("Object `{}` has no bounds to focus on", name))?;
+
+        let centre = (bbox.min + bbox.max) * 0.5;
+        let camera = self
+            .camera
+            .focus_on(centre, look_at)
+            .ok_or_else(|| format!("Object `{}` is behind the camera", name))?;
+
+        Ok(Scene {
+            camera,
+            ..self.clone()
+        })
     }
 
+    /// Returns the scene with a different background. The sun and sky
+    /// replace the background and any environment map.
+    ///
+    /// * `kind` - The background.
+    /// * `sun_elevation` - Angle of the sun above the horizon in degrees.
+    /// * `sun_azimuth` - Angle of the sun from the +z axis towards the +x
+    ///   axis in degrees.
+    /// * `turbidity` - Haze of the atmosphere from 2 to 10.
+    pub fn with_background(
+        &self,
+        kind: BackgroundKind,
+        sun_elevation: Float,
+        sun_azimuth: Float,
+        turbidity: Float,
+    ) -> Scene {
+        let (background, sky): (BackgroundFn, _) = match kind {
+            BackgroundKind::Black => (black_background, None),
+            BackgroundKind::Gradient => (gradient_background, None),
+            BackgroundKind::SunSky => (
+                black_background,
+                Some(Sky::new(sun_elevation, sun_azimuth, turbidity, 1.0, 1.0)),
+            ),
+        };
+
+        let environment = if sky.is_some() {
+            None
+        } else {
+            self.environment.clone()
+        };
+
+        Scene {
+            background,
+            environment,
+            sky,
+            ..self.clone()
+        }
+    }
+
+    /// Create a new scene. Emissive objects of the world are sampled as
+    /// lights automatically.
+    ///
+    /// * `world` - Objects in the scene.
+    /// * `lights` - Additional objects sampled as lights, e.g. glass spheres
+    ///   that focus light.
+    /// * `camera` - The camera.
+    /// * `background` - Background.
+    /// * `bvh_enabled` - Use bounding volume hierarchy.
     fn new_scene(
         world: &Vec<ArcHittable>,
         lights: &Vec<ArcHittable>,
@@ -108,29 +410,76 @@ impl Scene {
         background: BackgroundFn,
         bvh_enabled: bool,
     ) -> Scene {
+        Scene::new_scene_with_cache(world, lights, camera, background, bvh_enabled, None)
+    }
+
+    /// Create a new scene like `Scene::new_scene()` loading and saving the
+    /// bounding volume hierarchy in a cache.
+    ///
+    /// * `world` - Objects in the scene.
+    /// * `lights` - Additional objects sampled as lights.
+    /// * `camera` - The camera.
+    /// * `background` - Background.
+    /// * `bvh_enabled` - Use bounding volume hierarchy.
+    /// * `bvh_cache` - Optional cache of built bounding volume hierarchies.
+    fn new_scene_with_cache(
+        world: &Vec<ArcHittable>,
+        lights: &Vec<ArcHittable>,
+        camera: Camera,
+        background: BackgroundFn,
+        bvh_enabled: bool,
+        bvh_cache: Option<&BvhCache>,
+    ) -> Scene {
+        let lights = discover_lights(world, lights);
+
         Scene {
-            world: build_world(world, bvh_enabled),
-            lights: build_hittable_list(lights),
+            world: build_world(world, bvh_enabled, bvh_cache),
+            lights: build_hittable_list(&lights),
+            emitters: lights.iter().filter(|o| o.is_emissive()).cloned().collect(),
             camera,
             background,
+            environment: None,
+            sky: None,
+            backplate: None,
+            light_sources: Vec::new(),
+            has_lights: !lights.is_empty(),
+            passes: Vec::new(),
+            light_links: None,
+            sections: Vec::new(),
+            named_objects: HashMap::new(),
+            ray_epsilon: None,
+            ray_t_max: None,
+            roughness_clamp: None,
         }
     }
 }
 
-fn build_world(world: &Vec<ArcHittable>, bvh_enabled: bool) -> ArcHittable {
-    let start = Instant::now();
+/// Returns the emissive objects of the world followed by the given lights
+/// that aren't emissive objects of the world. Groups are searched for their
+/// emissive objects so the rest of a group isn't sampled as a light.
+///
+/// * `world` - Objects in the scene.
+/// * `lights` - Objects sampled as lights.
+fn discover_lights(world: &[ArcHittable], lights: &[ArcHittable]) -> Vec<ArcHittable> {
+    let mut seen = HashSet::new();
+    world
+        .iter()
+        .flat_map(emissive_leaves)
+        .chain(lights.iter().cloned())
+        .filter(|o| seen.insert(Arc::as_ptr(o) as *const () as usize))
+        .collect()
+}
 
-    let world = if bvh_enabled {
-        eprint!("BVH: ");
-        build_bvh(&world)
+fn build_world(
+    world: &Vec<ArcHittable>,
+    bvh_enabled: bool,
+    bvh_cache: Option<&BvhCache>,
+) -> ArcHittable {
+    if bvh_enabled {
+        build_bvh(world, bvh_cache)
     } else {
-        eprint!("HittableList: ");
-        build_hittable_list(&world)
-    };
-
-    eprintln!("{} seconds", start.elapsed().as_secs_f32());
-
-    world
+        build_hittable_list(world)
+    }
 }
 
 fn build_hittable_list(objects: &Vec<ArcHittable>) -> ArcHittable {
@@ -143,12 +492,12 @@ fn build_hittable_list(objects: &Vec<ArcHittable>) -> ArcHittable {
     Arc::new(world)
 }
 
-fn build_bvh(objects: &Vec<ArcHittable>) -> ArcHittable {
+fn build_bvh(objects: &Vec<ArcHittable>, bvh_cache: Option<&BvhCache>) -> ArcHittable {
     let mut obj: Vec<ArcHittable> = Vec::new();
     for o in objects {
         obj.push(Arc::clone(&o));
     }
-    BVH::new(&mut obj, 0.0, 1.0)
+    BVH::with_cache(&mut obj, 0.0, 1.0, bvh_cache)
 }
 
 fn default_camera(image_width: u32, image_height: u32) -> Camera {
@@ -261,14 +610,11 @@ fn diffuse_spheres(image_width: u32, image_height: u32, bvh_enabled: bool) -> Sc
         ),
     ];
 
-    let lights = light_box(1000.0);
-    for light in lights.clone() {
-        world.push(Arc::clone(&light));
-    }
+    world.extend(light_box(1000.0));
 
     Scene::new_scene(
         &world,
-        &lights,
+        &Vec::new(),
         default_camera(image_width, image_height),
         gradient_background,
         bvh_enabled,
@@ -299,14 +645,11 @@ fn metal_spheres(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scen
         ),
     ];
 
-    let lights = light_box(1000.0);
-    for light in lights.clone() {
-        world.push(Arc::clone(&light));
-    }
+    world.extend(light_box(1000.0));
 
     Scene::new_scene(
         &world,
-        &lights,
+        &Vec::new(),
         default_camera(image_width, image_height),
         gradient_background,
         bvh_enabled,
@@ -344,14 +687,11 @@ fn dielectric_spheres_objects() -> Vec<ArcHittable> {
 fn dielectric_spheres(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
     let mut world = dielectric_spheres_objects();
 
-    let lights = light_box(1000.0);
-    for light in lights.clone() {
-        world.push(Arc::clone(&light));
-    }
+    world.extend(light_box(1000.0));
 
     Scene::new_scene(
         &world,
-        &lights,
+        &Vec::new(),
         default_camera(image_width, image_height),
         gradient_background,
         bvh_enabled,
@@ -361,10 +701,7 @@ fn dielectric_spheres(image_width: u32, image_height: u32, bvh_enabled: bool) ->
 fn wide_angle_dielectric_spheres(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
     let mut world = dielectric_spheres_objects();
 
-    let lights = light_box(1000.0);
-    for light in lights.clone() {
-        world.push(Arc::clone(&light));
-    }
+    world.extend(light_box(1000.0));
 
     let camera = Camera::new(
         Point3::new(-2.0, 2.0, 1.0),
@@ -378,16 +715,19 @@ fn wide_angle_dielectric_spheres(image_width: u32, image_height: u32, bvh_enable
         1.0,
     );
 
-    Scene::new_scene(&world, &lights, camera, gradient_background, bvh_enabled)
+    Scene::new_scene(
+        &world,
+        &Vec::new(),
+        camera,
+        gradient_background,
+        bvh_enabled,
+    )
 }
 
 fn telephoto_dielectric_spheres(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
     let mut world = dielectric_spheres_objects();
 
-    let lights = light_box(1000.0);
-    for light in lights.clone() {
-        world.push(Arc::clone(&light));
-    }
+    world.extend(light_box(1000.0));
 
     let camera = Camera::new(
         Point3::new(-2.0, 2.0, 1.0),
@@ -401,7 +741,13 @@ fn telephoto_dielectric_spheres(image_width: u32, image_height: u32, bvh_enabled
         1.0,
     );
 
-    Scene::new_scene(&world, &lights, camera, gradient_background, bvh_enabled)
+    Scene::new_scene(
+        &world,
+        &Vec::new(),
+        camera,
+        gradient_background,
+        bvh_enabled,
+    )
 }
 
 fn defocus_blue_dielectric_spheres(
@@ -411,10 +757,7 @@ fn defocus_blue_dielectric_spheres(
 ) -> Scene {
     let mut world = dielectric_spheres_objects();
 
-    let lights = light_box(1000.0);
-    for light in lights.clone() {
-        world.push(Arc::clone(&light));
-    }
+    world.extend(light_box(1000.0));
 
     let lookfrom = Point3::new(3.0, 3.0, 2.0);
     let lookat = Point3::new(0.0, 0.0, -1.0);
@@ -431,11 +774,25 @@ fn defocus_blue_dielectric_spheres(
         1.0,
     );
 
-    Scene::new_scene(&world, &lights, camera, gradient_background, bvh_enabled)
+    Scene::new_scene(
+        &world,
+        &Vec::new(),
+        camera,
+        gradient_background,
+        bvh_enabled,
+    )
 }
 
 /// Generate some fixed spheres and a lot of smaller random spheres.
-fn random_spheres(motion_blur: bool, checkered_floor: bool) -> Vec<ArcHittable> {
+///
+/// * `motion_blur` - Move the small diffuse spheres during the shutter.
+/// * `checkered_floor` - Use a checkered texture on the floor.
+/// * `variation` - Number of small spheres.
+fn random_spheres(
+    motion_blur: bool,
+    checkered_floor: bool,
+    variation: &Variation,
+) -> Vec<ArcHittable> {
     let mut world: Vec<ArcHittable> = Vec::new();
 
     let albedo = if checkered_floor {
@@ -452,50 +809,53 @@ fn random_spheres(motion_blur: bool, checkered_floor: bool) -> Vec<ArcHittable>
         Lambertian::new(albedo),
     ));
 
-    for a in -11..11 {
-        for b in -11..11 {
-            let choose_mat = Random::sample::<Float>();
-
-            let center = Point3::new(
-                a as Float + 0.9 * Random::sample::<Float>(),
-                0.2,
-                b as Float + 0.9 * Random::sample::<Float>(),
-            );
-
-            if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-                if choose_mat < 0.8 {
-                    // Diffuse
-                    let albedo = (Random::vec3() * Random::vec3()).as_colour();
-
-                    if motion_blur {
-                        let y = Random::sample_in_range(0.0, 0.5);
-                        world.push(MovingSphere::new(
-                            center,
-                            center + Vec3::new(0.0, y, 0.0),
-                            0.0,
-                            1.0,
-                            0.2,
-                            Lambertian::new(SolidColour::new(albedo)),
-                        ));
-                    } else {
-                        world.push(Sphere::new(
-                            center,
-                            0.2,
-                            Lambertian::new(SolidColour::new(albedo)),
-                        ));
-                    }
-                } else if choose_mat < 0.95 {
-                    // Metal
-                    let albedo = SolidColour::new(Random::vec3_in_range(0.5, 1.0).as_colour());
-                    let fuzz = Random::sample_in_range(0.0, 0.5);
-                    world.push(Sphere::new(center, 0.2, Metal::new(albedo, fuzz)));
-                } else {
-                    // Glass
-                    world.push(Sphere::new(center, 0.2, Dielectric::new(1.5)));
-                }
+    let small_spheres = Scatter::new(Domain::Surface {
+        min: Point3::new(-11.0, 0.2, -11.0),
+        max: Point3::new(11.0, 0.2, 11.0),
+        cells: variation.cells((22, 22)),
+        jitter: 0.9,
+    })
+    .with_min_distance(0.4);
+    world.extend(small_spheres.instances(|placement| {
+        let center = placement.position;
+        let radius = 0.2 * placement.scale;
+
+        if (center - Point3::new(4.0, 0.2, 0.0)).length() <= 0.9 {
+            return None;
+        }
+
+        let choose_mat = Random::sample::<Float>();
+        if choose_mat < 0.8 {
+            // Diffuse
+            let albedo = (Random::vec3() * Random::vec3()).as_colour();
+
+            if motion_blur {
+                let y = Random::sample_in_range(0.0, 0.5);
+                Some(MovingSphere::new(
+                    center,
+                    center + Vec3::new(0.0, y, 0.0),
+                    0.0,
+                    1.0,
+                    radius,
+                    Lambertian::new(SolidColour::new(albedo)),
+                ))
+            } else {
+                Some(Sphere::new(
+                    center,
+                    radius,
+                    Lambertian::new(SolidColour::new(albedo)),
+                ))
             }
+        } else if choose_mat < 0.95 {
+            // Metal
+            let albedo = SolidColour::new(Random::vec3_in_range(0.5, 1.0).as_colour());
+            let fuzz = Random::sample_in_range(0.0, 0.5);
+            Some(Sphere::new(center, radius, Metal::new(albedo, fuzz)))
+        } else {
+            // Glass
+            Some(Sphere::new(center, radius, Dielectric::new(1.5)))
         }
-    }
+    }));
 
     world.push(Sphere::new(
         Point3::new(0.0, 1.0, 0.0),
@@ -518,51 +878,57 @@ fn random_spheres(motion_blur: bool, checkered_floor: bool) -> Vec<ArcHittable>
     world
 }
 
-fn final_one_weekend(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
-    let mut world = random_spheres(false, false);
+fn final_one_weekend(
+    image_width: u32,
+    image_height: u32,
+    bvh_enabled: bool,
+    variation: &Variation,
+) -> Scene {
+    let mut world = random_spheres(false, false, variation);
 
-    let lights = light_box(1000.0);
-    for light in lights.clone() {
-        world.push(Arc::clone(&light));
-    }
+    world.extend(light_box(1000.0));
 
     Scene::new_scene(
         &world,
-        &lights,
+        &Vec::new(),
         random_spheres_camera(image_width, image_height),
         gradient_background,
         bvh_enabled,
     )
 }
 
-fn motion_blur(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
-    let mut world = random_spheres(true, false);
+fn motion_blur(
+    image_width: u32,
+    image_height: u32,
+    bvh_enabled: bool,
+    variation: &Variation,
+) -> Scene {
+    let mut world = random_spheres(true, false, variation);
 
-    let lights = light_box(1000.0);
-    for light in lights.clone() {
-        world.push(Arc::clone(&light));
-    }
+    world.extend(light_box(1000.0));
 
     Scene::new_scene(
         &world,
-        &lights,
+        &Vec::new(),
         random_spheres_camera(image_width, image_height),
         gradient_background,
         bvh_enabled,
     )
 }
 
-fn checkered_floor(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
-    let mut world = random_spheres(true, true);
+fn checkered_floor(
+    image_width: u32,
+    image_height: u32,
+    bvh_enabled: bool,
+    variation: &Variation,
+) -> Scene {
+    let mut world = random_spheres(true, true, variation);
 
-    let lights = light_box(1000.0);
-    for light in lights.clone() {
-        world.push(Arc::clone(&light));
-    }
+    world.extend(light_box(1000.0));
 
     Scene::new_scene(
         &world,
-        &lights,
+        &Vec::new(),
         random_spheres_camera(image_width, image_height),
         gradient_background,
         bvh_enabled,
@@ -589,14 +955,11 @@ fn checkered_spheres(image_width: u32, image_height: u32, bvh_enabled: bool) ->
         Lambertian::new(Arc::clone(&checker)),
     ));
 
-    let lights = light_box(1000.0);
-    for light in lights.clone() {
-        world.push(Arc::clone(&light));
-    }
+    world.extend(light_box(1000.0));
 
     Scene::new_scene(
         &world,
-        &lights,
+        &Vec::new(),
         checkered_spheres_camera(image_width, image_height),
         gradient_background,
         bvh_enabled,
@@ -640,24 +1003,25 @@ fn perlin_spheres(image_width: u32, image_height: u32, bvh_enabled: bool) -> Sce
         Lambertian::new(Arc::clone(&noise)),
     ));
 
-    let lights = light_box(3000.0);
-    for light in lights.clone() {
-        world.push(Arc::clone(&light));
-    }
+    world.extend(light_box(3000.0));
 
     Scene::new_scene(
         &world,
-        &lights,
+        &Vec::new(),
         checkered_spheres_camera(image_width, image_height),
         gradient_background,
         bvh_enabled,
     )
 }
 
-fn earth(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
+fn earth(image_width: u32, image_height: u32, bvh_enabled: bool, context: &LoadContext) -> Scene {
     let mut world: Vec<ArcHittable> = Vec::new();
 
-    let earth_texture = Image::new("images/world.topo.bathy.200412.3x5400x2700.jpg");
+    let earth_texture = Image::with_storage(
+        "images/world.topo.bathy.200412.3x5400x2700.jpg",
+        ColourStorage::Rgb9e5,
+        &context.budget,
+    );
 
     world.push(Sphere::new(
         Point3::zero(),
@@ -665,10 +1029,7 @@ fn earth(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
         Lambertian::new(Arc::clone(&earth_texture)),
     ));
 
-    let lights = light_box(1000.0);
-    for light in lights.clone() {
-        world.push(Arc::clone(&light));
-    }
+    world.extend(light_box(1000.0));
 
     let camera = Camera::new(
         Point3::new(0.0, 0.0, 12.0),
@@ -682,7 +1043,13 @@ fn earth(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
         1.0,
     );
 
-    Scene::new_scene(&world, &lights, camera, gradient_background, bvh_enabled)
+    Scene::new_scene(
+        &world,
+        &Vec::new(),
+        camera,
+        gradient_background,
+        bvh_enabled,
+    )
 }
 
 fn simple_light(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
@@ -692,10 +1059,8 @@ fn simple_light(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene
     let sphere_light = Sphere::new(Point3::new(0.0, 7.0, 0.0), 2.0, Arc::clone(&light));
     let rect_light = XYrect::new(3.0, 5.0, 1.0, 3.0, -2.0, Arc::clone(&light));
 
-    world.push(Arc::clone(&sphere_light));
-    world.push(Arc::clone(&rect_light));
-
-    let lights = vec![Arc::clone(&sphere_light), Arc::clone(&rect_light)];
+    world.push(sphere_light);
+    world.push(rect_light);
 
     let camera = Camera::new(
         Point3::new(26.0, 3.0, 6.0),
@@ -709,7 +1074,7 @@ fn simple_light(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene
         1.0,
     );
 
-    Scene::new_scene(&world, &lights, camera, black_background, bvh_enabled)
+    Scene::new_scene(&world, &Vec::new(), camera, black_background, bvh_enabled)
 }
 
 fn cornell_box_base<'a>() -> (HashMap<&'a str, ArcHittable>, HashMap<&'a str, ArcMaterial>) {
@@ -773,20 +1138,11 @@ fn cornell_box_base<'a>() -> (HashMap<&'a str, ArcHittable>, HashMap<&'a str, Ar
 fn empty_cornell_box(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
     let (objects, _) = cornell_box_base();
 
-    let mut world: Vec<ArcHittable> = Vec::new();
-    let mut lights: Vec<ArcHittable> = Vec::new();
-
-    for (key, object) in objects {
-        world.push(Arc::clone(&object));
-
-        if key == "top_light" {
-            lights.push(Arc::clone(&object));
-        }
-    }
+    let world: Vec<ArcHittable> = objects.into_values().collect();
 
     Scene::new_scene(
         &world,
-        &lights,
+        &Vec::new(),
         cornell_box_camera(image_width, image_height),
         black_background,
         bvh_enabled,
@@ -800,46 +1156,29 @@ fn cornell_box(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene
         .get("white")
         .expect("White material not found for cornell box.");
 
-    let mut world: Vec<ArcHittable> = Vec::new();
-    let mut lights: Vec<ArcHittable> = Vec::new();
-
-    for (key, object) in objects {
-        world.push(Arc::clone(&object));
-
-        if key == "top_light" {
-            lights.push(Arc::clone(&object));
-        }
-    }
+    let mut world: Vec<ArcHittable> = objects.into_values().collect();
 
-    world.push(Translate::new(
-        Rotate::new(
-            XYZbox::new(
-                Point3::zero(),
-                Point3::new(165.0, 330.0, 165.0),
-                Arc::clone(&white),
-            ),
-            Y_AXIS,
-            15.0,
+    world.push(Transform::new(
+        XYZbox::new(
+            Point3::zero(),
+            Point3::new(165.0, 330.0, 165.0),
+            Arc::clone(white),
         ),
-        Vec3::new(265.0, 0.0, 295.0),
+        Mat4::translate(Vec3::new(265.0, 0.0, 295.0)) * Mat4::rotate_axis(Y_AXIS, 15.0),
     ));
 
-    world.push(Translate::new(
-        Rotate::new(
-            XYZbox::new(
-                Point3::zero(),
-                Point3::new(165.0, 165.0, 165.0),
-                Arc::clone(&white),
-            ),
-            Y_AXIS,
-            -18.0,
+    world.push(Transform::new(
+        XYZbox::new(
+            Point3::zero(),
+            Point3::new(165.0, 165.0, 165.0),
+            Arc::clone(white),
         ),
-        Vec3::new(130.0, 0.0, 65.0),
+        Mat4::translate(Vec3::new(130.0, 0.0, 65.0)) * Mat4::rotate_axis(Y_AXIS, -18.0),
     ));
 
     Scene::new_scene(
         &world,
-        &lights,
+        &Vec::new(),
         cornell_box_camera(image_width, image_height),
         black_background,
         bvh_enabled,
@@ -866,38 +1205,28 @@ fn cornell_box_smoke_and_fog(image_width: u32, image_height: u32, bvh_enabled: b
         554.0,
         Arc::clone(&diffuse_light),
     ));
-    world.push(Arc::clone(&light));
-
-    let lights = vec![Arc::clone(&light)];
+    world.push(light);
 
     let white = materials
         .get("white")
         .expect("White material not found for cornell box.");
 
-    let box1 = Translate::new(
-        Rotate::new(
-            XYZbox::new(
-                Point3::zero(),
-                Point3::new(165.0, 330.0, 165.0),
-                Arc::clone(&white),
-            ),
-            Y_AXIS,
-            15.0,
+    let box1 = Transform::new(
+        XYZbox::new(
+            Point3::zero(),
+            Point3::new(165.0, 330.0, 165.0),
+            Arc::clone(white),
         ),
-        Vec3::new(265.0, 0.0, 295.0),
+        Mat4::translate(Vec3::new(265.0, 0.0, 295.0)) * Mat4::rotate_axis(Y_AXIS, 15.0),
     );
 
-    let box2 = Translate::new(
-        Rotate::new(
-            XYZbox::new(
-                Point3::zero(),
-                Point3::new(165.0, 165.0, 165.0),
-                Arc::clone(&white),
-            ),
-            Y_AXIS,
-            -18.0,
+    let box2 = Transform::new(
+        XYZbox::new(
+            Point3::zero(),
+            Point3::new(165.0, 165.0, 165.0),
+            Arc::clone(white),
         ),
-        Vec3::new(130.0, 0.0, 65.0),
+        Mat4::translate(Vec3::new(130.0, 0.0, 65.0)) * Mat4::rotate_axis(Y_AXIS, -18.0),
     );
 
     world.push(ConstantMedium::new(
@@ -914,38 +1243,41 @@ fn cornell_box_smoke_and_fog(image_width: u32, image_height: u32, bvh_enabled: b
 
     Scene::new_scene(
         &world,
-        &lights,
+        &Vec::new(),
         cornell_box_camera(image_width, image_height),
         black_background,
         bvh_enabled,
     )
 }
 
-fn final_next_week(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
+fn final_next_week(
+    image_width: u32,
+    image_height: u32,
+    bvh_enabled: bool,
+    variation: &Variation,
+    context: &LoadContext,
+) -> Scene {
     let mut world: Vec<ArcHittable> = Vec::new();
 
     let ground = Lambertian::new(SolidColour::from_rgb(0.48, 0.83, 0.53));
 
-    let mut boxes1: Vec<ArcHittable> = Vec::new();
-
-    let boxes_per_side = 20;
-    for i in 0..boxes_per_side {
-        for j in 0..boxes_per_side {
-            let w = 100.0;
-            let x0 = -1000.0 + (i as Float) * w;
-            let z0 = -1000.0 + (j as Float) * w;
-            let y0 = 0.0;
-            let x1 = x0 + w;
-            let y1 = Random::sample_in_range(1.0, 101.0);
-            let z1 = z0 + w;
-
-            boxes1.push(XYZbox::new(
-                Point3::new(x0, y0, z0),
-                Point3::new(x1, y1, z1),
-                Arc::clone(&ground),
-            ));
-        }
-    }
+    let w = 100.0;
+    let ground_boxes = Scatter::new(Domain::Surface {
+        min: Point3::new(-1000.0, 0.0, -1000.0),
+        max: Point3::new(1000.0, 0.0, 1000.0),
+        cells: (20, 20),
+        jitter: 0.0,
+    });
+    let mut boxes1 = ground_boxes.instances(|placement| {
+        let p0 = placement.position;
+        let y1 = Random::sample_in_range(1.0, 101.0);
+
+        Some(XYZbox::new(
+            p0,
+            Point3::new(p0.x() + w, y1, p0.z() + w),
+            Arc::clone(&ground),
+        ))
+    });
 
     world.push(BVH::new(&mut boxes1, 0.0, 1.0));
 
@@ -958,9 +1290,7 @@ fn final_next_week(image_width: u32, image_height: u32, bvh_enabled: bool) -> Sc
         554.0,
         Arc::clone(&light),
     ));
-    world.push(Arc::clone(&rect_light));
-
-    let lights = vec![Arc::clone(&rect_light)];
+    world.push(rect_light);
 
     let center1 = Point3::new(400.0, 400.0, 200.0);
     let center2 = center1 + Vec3::new(30.0, 0.0, 0.0);
@@ -1002,7 +1332,11 @@ fn final_next_week(image_width: u32, image_height: u32, bvh_enabled: bool) -> Sc
         SolidColour::from_rgb(1.0, 1.0, 1.0),
     ));
 
-    let earth_texture = Image::new("images/world.topo.bathy.200412.3x5400x2700.jpg");
+    let earth_texture = Image::with_storage(
+        "images/world.topo.bathy.200412.3x5400x2700.jpg",
+        ColourStorage::Rgb9e5,
+        &context.budget,
+    );
     let emat = Lambertian::new(earth_texture);
     world.push(Sphere::new(Point3::new(400.0, 200.0, 400.0), 100.0, emat));
 
@@ -1013,20 +1347,23 @@ fn final_next_week(image_width: u32, image_height: u32, bvh_enabled: bool) -> Sc
         Lambertian::new(pertext),
     ));
 
-    let mut boxes2: Vec<ArcHittable> = Vec::new();
     let white = Lambertian::new(SolidColour::from_rgb(0.73, 0.73, 0.73));
-    let ns = 1000;
-    for _j in 0..ns {
-        boxes2.push(Sphere::new(
-            Random::vec3_in_range(0.0, 165.0),
-            10.0,
+    let cluster = Scatter::new(Domain::Volume {
+        min: Point3::new(0.0, 0.0, 0.0),
+        max: Point3::new(165.0, 165.0, 165.0),
+        count: variation.count(1000),
+    });
+    let mut boxes2 = cluster.instances(|placement| {
+        Some(Sphere::new(
+            placement.position,
+            10.0 * placement.scale,
             Arc::clone(&white),
-        ));
-    }
+        ))
+    });
 
-    world.push(Translate::new(
-        Rotate::new(BVH::new(&mut boxes2, 0.0, 1.0), Y_AXIS, 15.0),
-        Vec3::new(-100.0, 270.0, 395.0),
+    world.push(Transform::new(
+        BVH::new(&mut boxes2, 0.0, 1.0),
+        Mat4::translate(Vec3::new(-100.0, 270.0, 395.0)) * Mat4::rotate_axis(Y_AXIS, 15.0),
     ));
 
     let camera = Camera::new(
@@ -1041,7 +1378,7 @@ fn final_next_week(image_width: u32, image_height: u32, bvh_enabled: bool) -> Sc
         1.0,
     );
 
-    Scene::new_scene(&world, &lights, camera, black_background, bvh_enabled)
+    Scene::new_scene(&world, &Vec::new(), camera, black_background, bvh_enabled)
 }
 
 fn rotate_spheres(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
@@ -1052,10 +1389,7 @@ fn rotate_spheres(image_width: u32, image_height: u32, bvh_enabled: bool) -> Sce
     let blue = Lambertian::new(SolidColour::from_rgb(0.2, 0.2, 0.8));
     let metal = Metal::new(SolidColour::from_rgb(0.8, 0.8, 0.8), 0.25);
 
-    let lights = light_box(1000.0);
-    for light in lights.clone() {
-        world.push(Arc::clone(&light));
-    }
+    world.extend(light_box(1000.0));
 
     let max_angle = 90.0;
     let max_radius = 0.1;
@@ -1064,22 +1398,19 @@ fn rotate_spheres(image_width: u32, image_height: u32, bvh_enabled: bool) -> Sce
     for angle in (0..n).map(|i| i as Float * max_angle / (n as Float)) {
         let f = 1.0 - angle / max_angle;
 
-        world.push(Rotate::new(
+        world.push(Transform::new(
             Sphere::new(Point3::new(f, 0.0, 0.0), max_radius * f, Arc::clone(&red)),
-            Z_AXIS,
-            angle,
+            Mat4::rotate_axis(Z_AXIS, angle),
         ));
 
-        world.push(Rotate::new(
+        world.push(Transform::new(
             Sphere::new(Point3::new(0.0, 0.0, f), max_radius * f, Arc::clone(&green)),
-            Y_AXIS,
-            angle,
+            Mat4::rotate_axis(Y_AXIS, angle),
         ));
 
-        world.push(Rotate::new(
+        world.push(Transform::new(
             Sphere::new(Point3::new(0.0, f, 0.0), max_radius * f, Arc::clone(&blue)),
-            X_AXIS,
-            angle,
+            Mat4::rotate_axis(X_AXIS, angle),
         ));
     }
 
@@ -1101,7 +1432,7 @@ fn rotate_spheres(image_width: u32, image_height: u32, bvh_enabled: bool) -> Sce
         1.0,
     );
 
-    Scene::new_scene(&world, &lights, camera, black_background, bvh_enabled)
+    Scene::new_scene(&world, &Vec::new(), camera, black_background, bvh_enabled)
 }
 
 fn specular_reflections(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
@@ -1113,46 +1444,29 @@ fn specular_reflections(image_width: u32, image_height: u32, bvh_enabled: bool)
 
     let aluminum = Metal::new(SolidColour::from_rgb(0.8, 0.85, 0.88), 0.0);
 
-    let mut world: Vec<ArcHittable> = Vec::new();
-    let mut lights: Vec<ArcHittable> = Vec::new();
-
-    for (key, object) in objects {
-        world.push(Arc::clone(&object));
-
-        if key == "top_light" {
-            lights.push(Arc::clone(&object));
-        }
-    }
+    let mut world: Vec<ArcHittable> = objects.into_values().collect();
 
-    world.push(Translate::new(
-        Rotate::new(
-            XYZbox::new(
-                Point3::zero(),
-                Point3::new(165.0, 330.0, 165.0),
-                Arc::clone(&aluminum),
-            ),
-            Y_AXIS,
-            15.0,
+    world.push(Transform::new(
+        XYZbox::new(
+            Point3::zero(),
+            Point3::new(165.0, 330.0, 165.0),
+            Arc::clone(&aluminum),
         ),
-        Vec3::new(265.0, 0.0, 295.0),
+        Mat4::translate(Vec3::new(265.0, 0.0, 295.0)) * Mat4::rotate_axis(Y_AXIS, 15.0),
     ));
 
-    world.push(Translate::new(
-        Rotate::new(
-            XYZbox::new(
-                Point3::zero(),
-                Point3::new(165.0, 165.0, 165.0),
-                Arc::clone(&white),
-            ),
-            Y_AXIS,
-            -18.0,
+    world.push(Transform::new(
+        XYZbox::new(
+            Point3::zero(),
+            Point3::new(165.0, 165.0, 165.0),
+            Arc::clone(white),
         ),
-        Vec3::new(130.0, 0.0, 65.0),
+        Mat4::translate(Vec3::new(130.0, 0.0, 65.0)) * Mat4::rotate_axis(Y_AXIS, -18.0),
     ));
 
     Scene::new_scene(
         &world,
-        &lights,
+        &Vec::new(),
         cornell_box_camera(image_width, image_height),
         black_background,
         bvh_enabled,
@@ -1166,35 +1480,24 @@ fn final_rest_of_your_life(image_width: u32, image_height: u32, bvh_enabled: boo
         .get("white")
         .expect("White material not found for cornell box.");
 
-    let mut world: Vec<ArcHittable> = Vec::new();
-    let mut lights: Vec<ArcHittable> = Vec::new();
-
-    for (key, object) in objects {
-        world.push(Arc::clone(&object));
-
-        if key == "top_light" {
-            lights.push(Arc::clone(&object));
-        }
-    }
+    let mut world: Vec<ArcHittable> = objects.into_values().collect();
 
-    world.push(Translate::new(
-        Rotate::new(
-            XYZbox::new(
-                Point3::zero(),
-                Point3::new(165.0, 330.0, 165.0),
-                Arc::clone(&white),
-            ),
-            Y_AXIS,
-            15.0,
+    world.push(Transform::new(
+        XYZbox::new(
+            Point3::zero(),
+            Point3::new(165.0, 330.0, 165.0),
+            Arc::clone(white),
         ),
-        Vec3::new(265.0, 0.0, 295.0),
+        Mat4::translate(Vec3::new(265.0, 0.0, 295.0)) * Mat4::rotate_axis(Y_AXIS, 15.0),
     ));
 
     let glass_mat = Dielectric::new(1.5);
     let glass = Sphere::new(Point3::new(190.0, 90.0, 190.0), 90.0, glass_mat);
 
     world.push(Arc::clone(&glass));
-    lights.push(Arc::clone(&glass));
+
+    // Sample the glass sphere to find the caustic paths through it.
+    let lights = vec![Arc::clone(&glass)];
 
     Scene::new_scene(
         &world,
@@ -1204,3 +1507,137 @@ fn final_rest_of_your_life(image_width: u32, image_height: u32, bvh_enabled: boo
         bvh_enabled,
     )
 }
+
+fn triangles(image_width: u32, image_height: u32, bvh_enabled: bool) -> Scene {
+    let (objects, materials) = cornell_box_base();
+
+    let white = materials
+        .get("white")
+        .expect("White material not found for cornell box.");
+
+    let light = materials
+        .get("light")
+        .expect("Light material not found for cornell box.");
+
+    let mut world: Vec<ArcHittable> = Vec::new();
+
+    for (key, object) in objects {
+        if key != "top_light" {
+            world.push(Arc::clone(&object));
+        }
+    }
+
+    // Triangular light facing down.
+    let top_light = Triangle::new(
+        Point3::new(213.0, 554.0, 227.0),
+        Point3::new(343.0, 554.0, 227.0),
+        Point3::new(278.0, 554.0, 332.0),
+        Arc::clone(light),
+    );
+    world.extend(top_light);
+
+    // Square pyramid.
+    let a = Point3::new(300.0, 0.0, 250.0);
+    let b = Point3::new(460.0, 0.0, 250.0);
+    let c = Point3::new(460.0, 0.0, 410.0);
+    let d = Point3::new(300.0, 0.0, 410.0);
+    let apex = Point3::new(380.0, 250.0, 330.0);
+
+    for (v0, v1) in [(a, b), (b, c), (c, d), (d, a)] {
+        world.extend(Triangle::new(v0, apex, v1, Arc::clone(white)));
+    }
+
+    world.push(Transform::new(
+        XYZbox::new(
+            Point3::zero(),
+            Point3::new(165.0, 165.0, 165.0),
+            Arc::clone(white),
+        ),
+        Mat4::translate(Vec3::new(130.0, 0.0, 65.0)) * Mat4::rotate_axis(Y_AXIS, -18.0),
+    ));
+
+    Scene::new_scene(
+        &world,
+        &Vec::new(),
+        cornell_box_camera(image_width, image_height),
+        black_background,
+        bvh_enabled,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// Only the emissive objects of a group with other objects are sampled
+    /// as lights and they keep the transform of the group.
+    #[test]
+    fn discover_emissive_objects_of_mixed_group() {
+        let light = XZrect::new(
+            -1.0,
+            1.0,
+            -1.0,
+            1.0,
+            2.0,
+            DiffuseLight::new(SolidColour::from_rgb(4.0, 4.0, 4.0)),
+        );
+        let ball = Sphere::new(
+            Point3::zero(),
+            1.0,
+            Lambertian::new(SolidColour::from_rgb(0.5, 0.5, 0.5)),
+        );
+
+        let mut group = HittableList::new();
+        group.add(Arc::clone(&ball));
+        group.add(Arc::clone(&light));
+        let offset = Vec3::new(10.0, 0.0, 0.0);
+        let world = vec![Translate::new(Arc::new(group), offset)];
+
+        let lights = discover_lights(&world, &[]);
+        assert_eq!(lights.len(), 1);
+
+        let expected = light.bounding_box(0.0, 1.0).unwrap();
+        let bbox = lights[0].bounding_box(0.0, 1.0).unwrap();
+        assert!((bbox.min - expected.min - offset).length() < 1e-9);
+        assert!((bbox.max - expected.max - offset).length() < 1e-9);
+
+        // The light is sampled on its own.
+        assert!(lights[0].pdf_value(Point3::new(10.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)) > 0.0);
+        assert_eq!(
+            lights[0].pdf_value(Point3::new(10.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+            0.0
+        );
+    }
+
+    /// Triangles without area are dropped from scene files with a message
+    /// and can't be built directly.
+    #[test]
+    fn drop_triangles_without_area() {
+        let white = Lambertian::new(SolidColour::from_rgb(0.5, 0.5, 0.5));
+        let p = Point3::new(1.0, 2.0, 3.0);
+        assert!(Triangle::new(p, p, Point3::zero(), white).is_none());
+
+        let contents = format!(
+            "{}\n{}",
+            include_str!("../../scenes/cornell_box.toml"),
+            r#"
+[[objects]]
+type = "triangle"
+vertices = [[0, 0, 0], [1, 1, 1], [2, 2, 2]]
+material = "white"
+"#
+        );
+        let context = LoadContext::default();
+        Scene::from_toml(&contents, Path::new("."), 40, 40, true, &context).unwrap();
+
+        let messages = context.messages.take();
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.contains("dropped triangle without area")),
+            "{:?}",
+            messages
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! # Overrides
+//!
+//! A library for overriding scene parameters from the command line with
+//! dot-paths such as `camera.vfov=30` or `light.emit=[20, 20, 20]`.
+//!
+//! The first segment of a path names a top-level key of the scene file, a
+//! named material, a named texture or an object with a matching `name`, in
+//! that order. The remaining segments are table keys or array indices.
+//! Values are parsed as TOML and fall back to plain strings.
+
+use super::{LoadContext, Scene, Scenery, Variation};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use toml::{Table, Value};
+
+/// A parameter override given with `--set`.
+#[derive(Debug, Clone)]
+pub struct SceneOverride {
+    /// Dot separated path to the parameter.
+    pub path: String,
+
+    /// New value.
+    pub value: Value,
+}
+
+impl FromStr for SceneOverride {
+    type Err = String;
+
+    /// Parse an override of the form `path=value`.
+    ///
+    /// * `s` - The override.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected PATH=VALUE, found `{}`", s))?;
+
+        let path = path.trim();
+        if path.is_empty() || path.split('.').any(str::is_empty) {
+            return Err(format!("invalid path `{}`", path));
+        }
+
+        let value = value.trim();
+        let value = format!("value = {}", value)
+            .parse::<Table>()
+            .ok()
+            .and_then(|mut t| t.remove("value"))
+            .unwrap_or_else(|| Value::String(value.to_string()));
+
+        Ok(SceneOverride {
+            path: path.to_string(),
+            value,
+        })
+    }
+}
+
+impl Scene {
+    /// Load a scene from a scene file or build a built-in scene and apply
+    /// parameter overrides. Overrides of TOML scene files are applied to the
+    /// file so named materials and textures can be referenced. Other scenes
+    /// are converted to the scene file format first.
+    ///
+    /// * `scene_file` - Optional path to a scene file.
+    /// * `scenery` - Built-in scene used without a scene file.
+    /// * `overrides` - Parameter overrides.
+    /// * `variation` - Seed and number of randomly generated objects.
+    /// * `image_width` - Image width.
+    /// * `image_height` - Image height.
+    /// * `bvh_enabled` - Use bounding volume hierarchy.
+    /// * `context` - State shared while loading the scene.
+    #[allow(clippy::too_many_arguments)]
+    pub fn load(
+        scene_file: Option<&str>,
+        scenery: Scenery,
+        overrides: &[SceneOverride],
+        variation: &Variation,
+        image_width: u32,
+        image_height: u32,
+        bvh_enabled: bool,
+        context: &LoadContext,
+    ) -> Result<Scene, String> {
+        let is_toml =
+            scene_file.is_some_and(|p| Path::new(p).extension().is_some_and(|e| e == "toml"));
+
+        let (mut doc, dir) = match scene_file {
+            Some(path) if is_toml && !overrides.is_empty() => {
+                let contents = fs::read_to_string(path)
+                    .map_err(|e| format!("Unable to read {}: {}", path, e))?;
+                let doc: Table = contents.parse().map_err(|e| format!("{}: {}", path, e))?;
+                let dir = Path::new(path)
+                    .parent()
+                    .map_or_else(PathBuf::new, Path::to_path_buf);
+                (doc, dir)
+            }
+
+            _ => {
+                let scene = match scene_file {
+                    Some(path) => variation.generate(|| {
+                        Scene::from_file(path, image_width, image_height, bvh_enabled, context)
+                    })?,
+                    None => Scene::generate(
+                        scenery,
+                        variation,
+                        image_width,
+                        image_height,
+                        bvh_enabled,
+                        context,
+                    ),
+                };
+
+                if overrides.is_empty() {
+                    return Ok(scene);
+                }
+
+                (scene.to_table()?, PathBuf::new())
+            }
+        };
+
+        for o in overrides {
+            apply_override(&mut doc, o).map_err(|e| format!("--set {}: {}", o.path, e))?;
+        }
+
+        variation.generate(|| {
+            Scene::from_table(&doc, &dir, image_width, image_height, bvh_enabled, context)
+        })
+    }
+}
+
+/// Apply an override to a scene description.
+///
+/// * `doc` - The scene description.
+/// * `o` - The override.
+fn apply_override(doc: &mut Table, o: &SceneOverride) -> Result<(), String> {
+    let segments: Vec<&str> = o.path.split('.').collect();
+    let (first, rest) = segments.split_first().ok_or("empty path")?;
+
+    if rest.is_empty() {
+        doc.insert(first.to_string(), o.value.clone());
+        return Ok(());
+    }
+
+    let mut value = resolve_root(doc, first)?;
+    let (last, rest) = rest.split_last().ok_or("empty path")?;
+
+    for segment in rest {
+        value = match value {
+            Value::Table(t) => t
+                .get_mut(*segment)
+                .ok_or_else(|| format!("no `{}`", segment))?,
+            Value::Array(a) => index(a, segment)?,
+            _ => return Err(format!("`{}` is not a table or array", segment)),
+        };
+    }
+
+    match value {
+        Value::Table(t) => {
+            t.insert(last.to_string(), o.value.clone());
+        }
+        Value::Array(a) => *index(a, last)? = o.value.clone(),
+        _ => return Err(format!("cannot set `{}` on a value", last)),
+    }
+
+    Ok(())
+}
+
+/// Returns the value named by the first segment of a path.
+///
+/// * `doc` - The scene description.
+/// * `name` - The first segment.
+fn resolve_root<'a>(doc: &'a mut Table, name: &str) -> Result<&'a mut Value, String> {
+    if doc.contains_key(name) {
+        return Ok(doc.get_mut(name).unwrap());
+    }
+
+    for section in ["materials", "textures", "geometry"] {
+        let found = doc
+            .get(section)
+            .and_then(Value::as_table)
+            .is_some_and(|t| t.contains_key(name));
+
+        if found {
+            return Ok(doc
+                .get_mut(section)
+                .and_then(Value::as_table_mut)
+                .and_then(|t| t.get_mut(name))
+                .unwrap());
+        }
+    }
+
+    doc.get_mut("objects")
+        .and_then(Value::as_array_mut)
+        .and_then(|objects| {
+            objects
+                .iter_mut()
+                .find(|o| o.get("name").and_then(Value::as_str) == Some(name))
+        })
+        .ok_or_else(|| {
+            format!(
+                "no scene parameter, material, texture or object named `{}`",
+                name
+            )
+        })
+}
+
+/// Returns an array element by index.
+///
+/// * `a` - The array.
+/// * `segment` - Path segment with the index.
+fn index<'a>(a: &'a mut [Value], segment: &str) -> Result<&'a mut Value, String> {
+    let len = a.len();
+    segment
+        .parse::<usize>()
+        .ok()
+        .and_then(move |i| a.get_mut(i))
+        .ok_or_else(|| format!("`{}` is not an index below {}", segment, len))
+}
@@ -0,0 +1,1117 @@
+//! # PBRT
+//!
+//! A library for importing a subset of PBRT v3 scene files.
+//!
+//! The supported subset covers the directives that most benchmark scenes
+//! rely on:
+//!
+//! * Transforms: `Identity`, `Translate`, `Scale`, `Rotate`, `LookAt`,
+//!   `Transform`, `ConcatTransform`, `CoordinateSystem`, `CoordSysTransform`.
+//! * Structure: `WorldBegin`, `WorldEnd`, `AttributeBegin`, `AttributeEnd`,
+//!   `TransformBegin`, `TransformEnd`, `ReverseOrientation`, `Include`.
+//! * `Camera "perspective"` with `fov`, `lensradius` and `focaldistance`.
+//! * `Shape "sphere"` and `Shape "trianglemesh"` with `P`, `indices`, `N`
+//!   and `uv`.
+//! * `Material`, `MakeNamedMaterial` and `NamedMaterial`. `matte` maps to
+//!   lambertian, `mirror` maps to metal, `metal` maps to rough metal and
+//!   `glass` maps to rough dielectric with their roughness. `plastic`, `substrate`, `uber`, `translucent` and `disney`
+//!   are approximated by their diffuse colour.
+//! * `Texture` of type `color`/`spectrum` with `imagemap`, `checkerboard`
+//!   and `constant` classes.
+//! * `AreaLightSource "diffuse"` including `twosided` and
+//!   `LightSource "infinite"` with a `mapname`.
+//!
+//! Everything else (`Sampler`, `Film`, `Integrator`, media, instancing,
+//! point lights, other shapes) is skipped with a warning. Image dimensions
+//! and sample counts come from the command line. PBRT uses a left-handed
+//! coordinate system so scenes are mirrored along the x-axis to produce the
+//! same image.
+
+use super::{
+    black_background, ArcHittable, ArcMaterial, ArcTexture, Camera, Checker, Colour, ColourStorage,
+    DiffuseLight, Environment, FlipFace, Float, Image, Lambertian, LoadContext, Mat4, Metal,
+    Point3, RoughDielectric, RoughMetal, Scene, SolidColour, Sphere, Triangle, Vec3,
+};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Mirrors PBRT's left-handed world along the x-axis.
+const HANDEDNESS: Mat4 = Mat4 {
+    m: [
+        [-1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ],
+};
+
+/// Default focal distance of the PBRT perspective camera.
+const DEFAULT_FOCAL_DISTANCE: Float = 1e6;
+
+/// Texture coordinates of triangle vertices without `uv` values.
+const DEFAULT_UVS: [(Float, Float); 3] = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)];
+
+/// Approximate RGB index of refraction of copper, the PBRT metal default.
+const COPPER_ETA: [Float; 3] = [0.200, 0.924, 1.102];
+
+/// Approximate RGB absorption coefficient of copper, the PBRT metal default.
+const COPPER_K: [Float; 3] = [3.912, 2.452, 2.142];
+
+impl Scene {
+    /// Load a scene from a PBRT v3 scene file. Only a subset of PBRT is
+    /// supported. Unsupported directives are skipped with a warning.
+    ///
+    /// * `path` - Path to the scene file.
+    /// * `image_width` - Image width.
+    /// * `image_height` - Image height.
+    /// * `bvh_enabled` - Use bounding volume hierarchy.
+    /// * `context` - State shared while loading the scene.
+    pub fn from_pbrt(
+        path: &str,
+        image_width: u32,
+        image_height: u32,
+        bvh_enabled: bool,
+        context: &LoadContext,
+    ) -> Result<Scene, String> {
+        let dir = Path::new(path)
+            .parent()
+            .map_or_else(PathBuf::new, Path::to_path_buf);
+
+        let aspect_ratio = image_width as Float / image_height as Float;
+        let mut importer = Importer::new(dir, aspect_ratio, context.clone());
+        importer.parse_file(Path::new(path))?;
+
+        let camera = importer
+            .camera
+            .ok_or_else(|| format!("{}: no Camera", path))?;
+
+        if importer.world.is_empty() {
+            return Err(format!("{}: no supported shapes", path));
+        }
+
+        let mut scene = Scene::new_scene_with_cache(
+            &importer.world,
+            &Vec::new(),
+            camera,
+            black_background,
+            bvh_enabled,
+            context.bvh_cache.as_ref(),
+        );
+        scene.environment = importer.environment;
+        Ok(scene)
+    }
+}
+
+/// PBRT scene file tokens.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Directive name.
+    Ident(String),
+
+    /// Quoted string.
+    Str(String),
+
+    /// Number.
+    Num(Float),
+
+    /// Boolean.
+    Bool(bool),
+
+    /// Start of an array.
+    Open,
+
+    /// End of an array.
+    Close,
+}
+
+/// A parameter value.
+#[derive(Debug, Clone)]
+enum ParamValue {
+    /// Number.
+    Num(Float),
+
+    /// String.
+    Str(String),
+
+    /// Boolean.
+    Bool(bool),
+}
+
+/// A typed parameter such as `"float fov" [45]`.
+#[derive(Debug, Clone)]
+struct Param {
+    /// Parameter type.
+    ty: String,
+
+    /// Parameter name.
+    name: String,
+
+    /// Parameter values.
+    values: Vec<ParamValue>,
+}
+
+/// Parameter list of a directive.
+#[derive(Debug, Clone, Default)]
+struct Params(Vec<Param>);
+
+impl Params {
+    /// Returns a parameter by name.
+    ///
+    /// * `name` - Parameter name.
+    fn find(&self, name: &str) -> Option<&Param> {
+        self.0.iter().find(|p| p.name == name)
+    }
+
+    /// Returns the numeric values of a parameter.
+    ///
+    /// * `name` - Parameter name.
+    fn floats(&self, name: &str) -> Result<Option<Vec<Float>>, String> {
+        self.find(name)
+            .map(|p| {
+                p.values
+                    .iter()
+                    .map(|v| match v {
+                        ParamValue::Num(x) => Ok(*x),
+                        _ => Err(format!("`{}` must be numeric", name)),
+                    })
+                    .collect()
+            })
+            .transpose()
+    }
+
+    /// Returns the first numeric value of a parameter or a default.
+    ///
+    /// * `name` - Parameter name.
+    /// * `default` - Value used if the parameter is missing.
+    fn float(&self, name: &str, default: Float) -> Result<Float, String> {
+        match self.floats(name)? {
+            Some(v) => v
+                .first()
+                .copied()
+                .ok_or_else(|| format!("`{}` has no values", name)),
+            None => Ok(default),
+        }
+    }
+
+    /// Returns the first boolean value of a parameter or a default.
+    ///
+    /// * `name` - Parameter name.
+    /// * `default` - Value used if the parameter is missing.
+    fn bool(&self, name: &str, default: bool) -> Result<bool, String> {
+        match self.find(name).map(|p| p.values.first()) {
+            Some(Some(ParamValue::Bool(b))) => Ok(*b),
+            Some(_) => Err(format!("`{}` must be a bool", name)),
+            None => Ok(default),
+        }
+    }
+
+    /// Returns the first string value of a parameter.
+    ///
+    /// * `name` - Parameter name.
+    fn string(&self, name: &str) -> Option<&str> {
+        self.find(name).and_then(|p| match p.values.first() {
+            Some(ParamValue::Str(s)) => Some(s.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Returns an RGB colour parameter. A single number is treated as grey.
+    ///
+    /// * `name` - Parameter name.
+    fn colour(&self, name: &str) -> Result<Option<Colour>, String> {
+        let p = match self.find(name) {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+
+        match p.ty.as_str() {
+            "rgb" | "color" | "float" => (),
+            ty => return Err(format!("`{}` of type {} is not supported", name, ty)),
+        }
+
+        match self.floats(name)?.unwrap_or_default().as_slice() {
+            [x] => Ok(Some(Colour::new(*x, *x, *x))),
+            [r, g, b] => Ok(Some(Colour::new(*r, *g, *b))),
+            _ => Err(format!("`{}` must have 1 or 3 values", name)),
+        }
+    }
+}
+
+/// Graphics state saved by `AttributeBegin`.
+#[derive(Clone)]
+struct GraphicsState {
+    /// Current material. `None` makes shapes invisible.
+    material: Option<ArcMaterial>,
+
+    /// Emitted radiance of the current area light and whether it emits
+    /// from both sides.
+    area_light: Option<(Colour, bool)>,
+
+    /// Flip surface normals.
+    reverse_orientation: bool,
+}
+
+/// Imports PBRT directives into scene objects.
+struct Importer {
+    /// Directory used to resolve relative file paths.
+    dir: PathBuf,
+
+    /// State shared while loading the scene.
+    context: LoadContext,
+
+    /// Image aspect ratio.
+    aspect_ratio: Float,
+
+    /// Current transformation matrix.
+    ctm: Mat4,
+
+    /// Current graphics state.
+    state: GraphicsState,
+
+    /// Saved transforms and graphics states. Transform blocks only save
+    /// the transform.
+    stack: Vec<(Mat4, Option<GraphicsState>)>,
+
+    /// Named materials.
+    materials: HashMap<String, Option<ArcMaterial>>,
+
+    /// Named textures.
+    textures: HashMap<String, ArcTexture>,
+
+    /// Named coordinate systems.
+    coordinate_systems: HashMap<String, Mat4>,
+
+    /// Inside an `ObjectBegin` block.
+    in_object: bool,
+
+    /// The camera.
+    camera: Option<Camera>,
+
+    /// Environment map from an infinite light.
+    environment: Option<Arc<Environment>>,
+
+    /// Objects in the scene.
+    world: Vec<ArcHittable>,
+
+    /// Warnings already reported.
+    warnings: HashSet<String>,
+}
+
+impl Importer {
+    /// Create a new importer.
+    ///
+    /// * `dir` - Directory used to resolve relative file paths.
+    /// * `aspect_ratio` - Image aspect ratio.
+    /// * `context` - State shared while loading the scene.
+    fn new(dir: PathBuf, aspect_ratio: Float, context: LoadContext) -> Importer {
+        Importer {
+            dir,
+            context,
+            aspect_ratio,
+            ctm: Mat4::IDENTITY,
+            state: GraphicsState {
+                material: Some(Lambertian::new(SolidColour::from_rgb(0.5, 0.5, 0.5))),
+                area_light: None,
+                reverse_orientation: false,
+            },
+            stack: Vec::new(),
+            materials: HashMap::new(),
+            textures: HashMap::new(),
+            coordinate_systems: HashMap::new(),
+            in_object: false,
+            camera: None,
+            environment: None,
+            world: Vec::new(),
+            warnings: HashSet::new(),
+        }
+    }
+
+    /// Parse a scene file and apply its directives.
+    ///
+    /// * `path` - Path to the scene file.
+    fn parse_file(&mut self, path: &Path) -> Result<(), String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        let tokens = tokenize(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let mut idx = 0;
+        while idx < tokens.len() {
+            let (name, line) = match &tokens[idx] {
+                (Token::Ident(name), line) => (name.as_str(), *line),
+                (token, line) => {
+                    return Err(format!(
+                        "{}:{}: expected a directive, found {:?}",
+                        path.display(),
+                        line,
+                        token
+                    ))
+                }
+            };
+            idx += 1;
+
+            // Arguments run until the next directive. `ActiveTransform`
+            // is the only directive that takes a bare identifier.
+            let start = idx;
+            if name == "ActiveTransform" && idx < tokens.len() {
+                idx += 1;
+            }
+            while idx < tokens.len() && !matches!(tokens[idx].0, Token::Ident(_)) {
+                idx += 1;
+            }
+            let args: Vec<Token> = tokens[start..idx].iter().map(|(t, _)| t.clone()).collect();
+
+            self.directive(name, &args)
+                .map_err(|e| format!("{}:{}: {}: {}", path.display(), line, name, e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply a directive.
+    ///
+    /// * `name` - Directive name.
+    /// * `args` - Directive arguments.
+    fn directive(&mut self, name: &str, args: &[Token]) -> Result<(), String> {
+        let (positional, params) = split_args(args)?;
+
+        match name {
+            "Identity" => self.ctm = Mat4::IDENTITY,
+
+            "Translate" => {
+                let v = numbers(&positional, 3)?;
+                self.ctm = self.ctm * Mat4::translate(Vec3::new(v[0], v[1], v[2]));
+            }
+
+            "Scale" => {
+                let v = numbers(&positional, 3)?;
+                self.ctm = self.ctm * Mat4::scale(Vec3::new(v[0], v[1], v[2]));
+            }
+
+            "Rotate" => {
+                let v = numbers(&positional, 4)?;
+                self.ctm = self.ctm * Mat4::rotate(Vec3::new(v[1], v[2], v[3]), v[0]);
+            }
+
+            "LookAt" => {
+                let v = numbers(&positional, 9)?;
+                let m = look_at(
+                    Point3::new(v[0], v[1], v[2]),
+                    Point3::new(v[3], v[4], v[5]),
+                    Vec3::new(v[6], v[7], v[8]),
+                )?;
+                self.ctm = self.ctm * m;
+            }
+
+            "Transform" => self.ctm = Mat4::from_column_major(&numbers(&positional, 16)?),
+
+            "ConcatTransform" => {
+                self.ctm = self.ctm * Mat4::from_column_major(&numbers(&positional, 16)?);
+            }
+
+            "CoordinateSystem" => {
+                self.coordinate_systems
+                    .insert(string(&positional, 0)?.to_string(), self.ctm);
+            }
+
+            "CoordSysTransform" => {
+                let name = string(&positional, 0)?;
+                match self.coordinate_systems.get(name) {
+                    Some(m) => self.ctm = *m,
+                    None => self.warn(format!("unknown coordinate system `{}`", name)),
+                }
+            }
+
+            "Camera" => self.camera(string(&positional, 0)?, &params)?,
+
+            "WorldBegin" => {
+                self.ctm = Mat4::IDENTITY;
+                self.coordinate_systems
+                    .insert("world".to_string(), Mat4::IDENTITY);
+            }
+
+            "AttributeBegin" => self.stack.push((self.ctm, Some(self.state.clone()))),
+
+            "TransformBegin" => self.stack.push((self.ctm, None)),
+
+            "AttributeEnd" | "TransformEnd" => match self.stack.pop() {
+                Some((ctm, state)) => {
+                    self.ctm = ctm;
+                    if let Some(state) = state {
+                        self.state = state;
+                    }
+                }
+                None => return Err("unmatched end of block".to_string()),
+            },
+
+            "ReverseOrientation" => {
+                self.state.reverse_orientation = !self.state.reverse_orientation;
+            }
+
+            "Include" | "Import" => {
+                let path = self.resolve(string(&positional, 0)?);
+                self.parse_file(&path)?;
+            }
+
+            "Texture" => {
+                let name = string(&positional, 0)?;
+                let ty = string(&positional, 1)?;
+                let class = string(&positional, 2)?;
+                if ty == "float" {
+                    self.warn(format!("skipping float texture `{}`", name));
+                } else if let Some(texture) = self.texture(class, &params)? {
+                    self.textures.insert(name.to_string(), texture);
+                }
+            }
+
+            "Material" => {
+                self.state.material = self.material(string(&positional, 0)?, &params)?;
+            }
+
+            "MakeNamedMaterial" => {
+                let name = string(&positional, 0)?;
+                let ty = params.string("type").unwrap_or("matte").to_string();
+                let material = self.material(&ty, &params)?;
+                self.materials.insert(name.to_string(), material);
+            }
+
+            "NamedMaterial" => {
+                let name = string(&positional, 0)?;
+                match self.materials.get(name) {
+                    Some(material) => self.state.material = material.clone(),
+                    None => return Err(format!("unknown material `{}`", name)),
+                }
+            }
+
+            "AreaLightSource" => match string(&positional, 0)? {
+                "diffuse" => {
+                    let l = params.colour("L")?.unwrap_or(Colour::new(1.0, 1.0, 1.0));
+                    let s = params
+                        .colour("scale")?
+                        .unwrap_or(Colour::new(1.0, 1.0, 1.0));
+                    let two_sided = params.bool("twosided", false)?;
+                    self.state.area_light = Some((l * s, two_sided));
+                }
+                other => self.warn(format!("skipping {} area light", other)),
+            },
+
+            "LightSource" => self.light(string(&positional, 0)?, &params)?,
+
+            "Shape" => self.shape(string(&positional, 0)?, &params)?,
+
+            "ObjectBegin" => {
+                self.warn("object instancing is not supported".to_string());
+                self.stack.push((self.ctm, Some(self.state.clone())));
+                self.in_object = true;
+            }
+
+            "ObjectEnd" => {
+                let (ctm, state) = self.stack.pop().ok_or("unmatched ObjectEnd")?;
+                self.ctm = ctm;
+                if let Some(state) = state {
+                    self.state = state;
+                }
+                self.in_object = false;
+            }
+
+            "WorldEnd" | "Sampler" | "Film" | "PixelFilter" | "Integrator" | "Accelerator" => (),
+
+            other => self.warn(format!("skipping unsupported directive {}", other)),
+        }
+
+        Ok(())
+    }
+
+    /// Create the camera from the current transformation matrix.
+    ///
+    /// * `kind` - Camera type.
+    /// * `params` - Camera parameters.
+    fn camera(&mut self, kind: &str, params: &Params) -> Result<(), String> {
+        if kind != "perspective" {
+            self.warn(format!("{} camera is rendered as perspective", kind));
+        }
+
+        let camera_to_world = self
+            .ctm
+            .inverse()
+            .ok_or("camera transform is not invertible")?;
+        self.coordinate_systems
+            .insert("camera".to_string(), camera_to_world);
+
+        let m = HANDEDNESS * camera_to_world;
+        let look_from = m.transform_point(Point3::zero());
+        let look_at = m.transform_point(Point3::new(0.0, 0.0, 1.0));
+        let vup = m.transform_vector(Vec3::new(0.0, 1.0, 0.0));
+
+        // PBRT's field of view spans the shorter image axis.
+        let fov = params.float("fov", 90.0)?;
+        let vfov = if self.aspect_ratio < 1.0 {
+            let half = (0.5 * fov.to_radians()).tan() / self.aspect_ratio;
+            2.0 * half.atan().to_degrees()
+        } else {
+            fov
+        };
+
+        self.camera = Some(Camera::new(
+            look_from,
+            look_at,
+            vup,
+            vfov,
+            self.aspect_ratio,
+            2.0 * params.float("lensradius", 0.0)?,
+            params.float("focaldistance", DEFAULT_FOCAL_DISTANCE)?,
+            0.0,
+            1.0,
+        ));
+
+        Ok(())
+    }
+
+    /// Create a texture.
+    ///
+    /// * `class` - Texture class.
+    /// * `params` - Texture parameters.
+    fn texture(&mut self, class: &str, params: &Params) -> Result<Option<ArcTexture>, String> {
+        match class {
+            "constant" => Ok(Some(self.texture_param(params, "value", 1.0)?)),
+
+            "checkerboard" => {
+                let tex1 = self.texture_param(params, "tex1", 1.0)?;
+                let tex2 = self.texture_param(params, "tex2", 0.0)?;
+                let scale = params.float("uscale", 1.0)?;
+                Ok(Some(Checker::scaled(tex1, tex2, scale)))
+            }
+
+            "imagemap" => {
+                let filename = params.string("filename").ok_or("`filename` is required")?;
+                let path = self.resolve(filename);
+                if !path.is_file() {
+                    return Err(format!("image {} not found", path.display()));
+                }
+                Ok(Some(Image::with_storage(
+                    &path.to_string_lossy(),
+                    ColourStorage::Rgb9e5,
+                    &self.context.budget,
+                )))
+            }
+
+            other => {
+                self.warn(format!("skipping {} texture", other));
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns a colour texture parameter. Named textures, colours and
+    /// single floats are supported.
+    ///
+    /// * `params` - Parameters.
+    /// * `name` - Parameter name.
+    /// * `default` - Grey level used if the parameter is missing.
+    fn texture_param(
+        &mut self,
+        params: &Params,
+        name: &str,
+        default: Float,
+    ) -> Result<ArcTexture, String> {
+        if let Some(p) = params.find(name) {
+            if p.ty == "texture" {
+                let texture = params.string(name).unwrap_or_default();
+                return match self.textures.get(texture) {
+                    Some(t) => Ok(Arc::clone(t)),
+                    None => {
+                        self.warn(format!("unknown texture `{}`", texture));
+                        Ok(SolidColour::from_rgb(default, default, default))
+                    }
+                };
+            }
+        }
+
+        match params.colour(name) {
+            Ok(Some(c)) => Ok(SolidColour::new(c)),
+            Ok(None) => Ok(SolidColour::from_rgb(default, default, default)),
+            Err(e) => {
+                self.warn(e);
+                Ok(SolidColour::from_rgb(default, default, default))
+            }
+        }
+    }
+
+    /// Create a material. Returns `None` for materials that make surfaces
+    /// invisible.
+    ///
+    /// * `kind` - Material type.
+    /// * `params` - Material parameters.
+    fn material(&mut self, kind: &str, params: &Params) -> Result<Option<ArcMaterial>, String> {
+        let material = match kind {
+            "" | "none" | "interface" => return Ok(None),
+
+            "matte" => Lambertian::new(self.texture_param(params, "Kd", 0.5)?),
+
+            "plastic" | "uber" | "translucent" => {
+                Lambertian::new(self.texture_param(params, "Kd", 0.25)?)
+            }
+
+            "substrate" => Lambertian::new(self.texture_param(params, "Kd", 0.5)?),
+
+            "disney" => Lambertian::new(self.texture_param(params, "color", 0.5)?),
+
+            "mirror" => Metal::new(self.texture_param(params, "Kr", 0.9)?, 0.0),
+
+            "metal" => {
+                let eta = params.colour("eta").unwrap_or(None);
+                let k = params.colour("k").unwrap_or(None);
+                let eta = eta.map_or(COPPER_ETA, |c| [c.x(), c.y(), c.z()]);
+                let k = k.map_or(COPPER_K, |c| [c.x(), c.y(), c.z()]);
+
+                // Reflectance at normal incidence for a conductor.
+                let r = |i: usize| {
+                    ((eta[i] - 1.0).powi(2) + k[i] * k[i]) / ((eta[i] + 1.0).powi(2) + k[i] * k[i])
+                };
+
+                let roughness = params.float("uroughness", params.float("roughness", 0.01)?)?;
+                let roughness = if params.bool("remaproughness", true)? {
+                    pbrt_roughness(roughness)
+                } else {
+                    roughness.max(0.0).sqrt()
+                };
+                RoughMetal::new(SolidColour::from_rgb(r(0), r(1), r(2)), roughness)
+            }
+
+            "glass" => {
+                let eta = params.float("eta", params.float("index", 1.5)?)?;
+                let roughness = params.float("uroughness", params.float("roughness", 0.0)?)?;
+                let roughness = if params.bool("remaproughness", true)? {
+                    pbrt_roughness(roughness)
+                } else {
+                    roughness.max(0.0).sqrt()
+                };
+                RoughDielectric::new(eta, roughness)
+            }
+
+            other => {
+                self.warn(format!("{} material is rendered as matte", other));
+                Lambertian::new(self.texture_param(params, "Kd", 0.5)?)
+            }
+        };
+
+        Ok(Some(material))
+    }
+
+    /// Add a light source.
+    ///
+    /// * `kind` - Light type.
+    /// * `params` - Light parameters.
+    fn light(&mut self, kind: &str, params: &Params) -> Result<(), String> {
+        if kind != "infinite" {
+            self.warn(format!("skipping {} light", kind));
+            return Ok(());
+        }
+
+        let mapname = match params.string("mapname") {
+            Some(mapname) => mapname,
+            None => {
+                self.warn("skipping infinite light without a `mapname`".to_string());
+                return Ok(());
+            }
+        };
+
+        let path = self.resolve(mapname);
+        let l = params.colour("L")?.unwrap_or(Colour::new(1.0, 1.0, 1.0));
+        let s = params
+            .colour("scale")?
+            .unwrap_or(Colour::new(1.0, 1.0, 1.0));
+        let intensity = (l * s).luminance();
+
+        self.environment = Some(Environment::new(
+            &path.to_string_lossy(),
+            intensity,
+            ColourStorage::Rgb9e5,
+            &self.context.budget,
+        )?);
+
+        Ok(())
+    }
+
+    /// Add a shape with the current material and transform.
+    ///
+    /// * `kind` - Shape type.
+    /// * `params` - Shape parameters.
+    fn shape(&mut self, kind: &str, params: &Params) -> Result<(), String> {
+        if self.in_object {
+            return Ok(());
+        }
+
+        let material = match (self.state.area_light, &self.state.material) {
+            (Some((l, two_sided)), _) => {
+                DiffuseLight::with_options(SolidColour::new(l), 1.0, two_sided)
+            }
+            (None, Some(material)) => Arc::clone(material),
+            (None, None) => return Ok(()),
+        };
+
+        let object_to_world = HANDEDNESS * self.ctm;
+
+        let objects = match kind {
+            "sphere" => {
+                let radius = params.float("radius", 1.0)?;
+                let center = object_to_world.transform_point(Point3::zero());
+
+                // Spheres only support uniform scaling so use the average.
+                let scale = (0..3)
+                    .map(|i| {
+                        let mut axis = [0.0; 3];
+                        axis[i] = 1.0;
+                        self.ctm
+                            .transform_vector(Vec3::new(axis[0], axis[1], axis[2]))
+                            .length()
+                    })
+                    .sum::<Float>()
+                    / 3.0;
+
+                let sphere = Sphere::new(center, radius * scale, material);
+                if self.state.reverse_orientation {
+                    vec![FlipFace::new(sphere)]
+                } else {
+                    vec![sphere]
+                }
+            }
+
+            "trianglemesh" => self.triangle_mesh(params, &object_to_world, material)?,
+
+            other => {
+                self.warn(format!("skipping {} shape", other));
+                return Ok(());
+            }
+        };
+
+        self.world.extend(objects);
+
+        Ok(())
+    }
+
+    /// Create the triangles of a triangle mesh.
+    ///
+    /// * `params` - Shape parameters.
+    /// * `object_to_world` - Object to world transform.
+    /// * `material` - Surface material.
+    fn triangle_mesh(
+        &mut self,
+        params: &Params,
+        object_to_world: &Mat4,
+        material: ArcMaterial,
+    ) -> Result<Vec<ArcHittable>, String> {
+        let p = params.floats("P")?.ok_or("`P` is required")?;
+        if p.len() % 3 != 0 {
+            return Err("`P` must have 3 values per vertex".to_string());
+        }
+        let n_vertices = p.len() / 3;
+
+        let indices: Vec<usize> = match params.floats("indices")? {
+            Some(indices) => indices.iter().map(|i| *i as usize).collect(),
+            None if n_vertices == 3 => vec![0, 1, 2],
+            None => return Err("`indices` is required".to_string()),
+        };
+        if !indices.len().is_multiple_of(3) || indices.iter().any(|i| *i >= n_vertices) {
+            return Err("`indices` must be triangles of valid vertex indices".to_string());
+        }
+
+        let normals = params.floats("N")?.filter(|n| n.len() == p.len());
+        let uvs = params
+            .floats("uv")?
+            .or(params.floats("st")?)
+            .filter(|uv| uv.len() == 2 * n_vertices);
+
+        self.context.budget.reserve_mesh(
+            indices.len() / 3 * std::mem::size_of::<Triangle>(),
+            "trianglemesh",
+        )?;
+
+        let normal_matrix = object_to_world
+            .inverse()
+            .ok_or("shape transform is not invertible")?;
+
+        let point = |i: usize| {
+            let v = Point3::new(p[3 * i], p[3 * i + 1], p[3 * i + 2]);
+            object_to_world.transform_point(v)
+        };
+
+        let normal = |n: &Vec<Float>, i: usize| {
+            let v = Vec3::new(n[3 * i], n[3 * i + 1], n[3 * i + 2]);
+            normal_matrix.transform_normal(v).unit_vector()
+        };
+
+        // Keep the winding so the geometric normal matches PBRT after the
+        // handedness change and any reflections in the transform.
+        let reverse = (self.ctm.determinant3() >= 0.0) != self.state.reverse_orientation;
+        let order = if reverse { [0, 2, 1] } else { [0, 1, 2] };
+
+        // Triangles without area are dropped since they can't be hit.
+        let triangles: Vec<ArcHittable> = indices
+            .chunks(3)
+            .filter_map(|tri| {
+                let idx = order.map(|k| tri[k]);
+                let vertices = idx.map(&point);
+                let n = normals.as_ref().map(|n| idx.map(|i| normal(n, i)));
+                let uvs = match &uvs {
+                    Some(uv) => idx.map(|i| (uv[2 * i], uv[2 * i + 1])),
+                    None => order.map(|k| DEFAULT_UVS[k]),
+                };
+                Triangle::with_attributes(vertices, n, uvs, Arc::clone(&material))
+            })
+            .collect();
+
+        let dropped = indices.len() / 3 - triangles.len();
+        if dropped > 0 {
+            self.warn(format!("dropped {} triangles without area", dropped));
+        }
+        Ok(triangles)
+    }
+
+    /// Resolve a path relative to the scene file or the asset search paths.
+    ///
+    /// * `path` - The path.
+    fn resolve(&self, path: &str) -> PathBuf {
+        self.context.assets.resolve(&self.dir, path)
+    }
+
+    /// Report a warning once.
+    ///
+    /// * `message` - The warning.
+    fn warn(&mut self, message: String) {
+        if self.warnings.insert(message.clone()) {
+            self.context.messages.push(format!("PBRT: {}", message));
+        }
+    }
+}
+
+/// Split the tokens of a directive into positional arguments and a
+/// parameter list. Parameter declarations are strings with a type and a name
+/// such as `"float fov"`. Arrays in positional arguments are flattened.
+///
+/// * `args` - The tokens.
+fn split_args(args: &[Token]) -> Result<(Vec<Token>, Params), String> {
+    let is_declaration =
+        |t: &Token| matches!(t, Token::Str(s) if s.split_whitespace().count() == 2);
+
+    let mut positional = Vec::new();
+    let mut idx = 0;
+    while idx < args.len() && !is_declaration(&args[idx]) {
+        match &args[idx] {
+            Token::Open | Token::Close => (),
+            t => positional.push(t.clone()),
+        }
+        idx += 1;
+    }
+
+    let mut params = Params::default();
+    while idx < args.len() {
+        let decl = match &args[idx] {
+            Token::Str(s) if is_declaration(&args[idx]) => s,
+            t => return Err(format!("expected a parameter declaration, found {:?}", t)),
+        };
+        idx += 1;
+
+        let mut words = decl.split_whitespace();
+        let ty = words.next().unwrap_or_default().to_string();
+        let name = words.next().unwrap_or_default().to_string();
+
+        let mut values = Vec::new();
+        let single = !matches!(args.get(idx), Some(Token::Open));
+        if !single {
+            idx += 1;
+        }
+
+        while idx < args.len() {
+            let value = match &args[idx] {
+                Token::Close if !single => {
+                    idx += 1;
+                    break;
+                }
+                Token::Num(x) => ParamValue::Num(*x),
+                Token::Str(s) if !is_declaration(&args[idx]) => ParamValue::Str(s.clone()),
+                Token::Bool(b) => ParamValue::Bool(*b),
+                _ => break,
+            };
+            values.push(value);
+            idx += 1;
+
+            if single {
+                break;
+            }
+        }
+
+        params.0.push(Param { ty, name, values });
+    }
+
+    Ok((positional, params))
+}
+
+/// Returns exactly `n` numeric positional arguments.
+///
+/// * `args` - Positional arguments.
+/// * `n` - Number of arguments.
+fn numbers(args: &[Token], n: usize) -> Result<Vec<Float>, String> {
+    let v: Vec<Float> = args
+        .iter()
+        .filter_map(|t| match t {
+            Token::Num(x) => Some(*x),
+            _ => None,
+        })
+        .collect();
+
+    if v.len() == n && args.len() == n {
+        Ok(v)
+    } else {
+        Err(format!("expected {} numbers", n))
+    }
+}
+
+/// Returns a string positional argument.
+///
+/// * `args` - Positional arguments.
+/// * `idx` - Index of the argument.
+fn string(args: &[Token], idx: usize) -> Result<&str, String> {
+    match args.get(idx) {
+        Some(Token::Str(s)) => Ok(s),
+        _ => Err(format!(
+            "expected a string argument at position {}",
+            idx + 1
+        )),
+    }
+}
+
+/// Split a PBRT scene description into tokens with their line numbers.
+///
+/// * `contents` - The scene description.
+fn tokenize(contents: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = contents.chars().peekable();
+    let mut line = 1;
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            '\n' => {
+                line += 1;
+                chars.next();
+            }
+
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+
+            '#' => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+
+            '[' => {
+                tokens.push((Token::Open, line));
+                chars.next();
+            }
+
+            ']' => {
+                tokens.push((Token::Close, line));
+                chars.next();
+            }
+
+            '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\n') | None => return Err(format!("{}: unterminated string", line)),
+                        Some(c) => s.push(c),
+                    }
+                }
+                tokens.push((Token::Str(s), line));
+            }
+
+            c if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => {
+                let mut s = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_digit() || "+-.eE".contains(c) {
+                        s.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let x = s
+                    .parse::<Float>()
+                    .map_err(|_| format!("{}: invalid number `{}`", line, s))?;
+                tokens.push((Token::Num(x), line));
+            }
+
+            c if c.is_ascii_alphabetic() => {
+                let mut s = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '_' {
+                        s.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let token = match s.as_str() {
+                    "true" => Token::Bool(true),
+                    "false" => Token::Bool(false),
+                    _ => Token::Ident(s),
+                };
+                tokens.push((token, line));
+            }
+
+            c => return Err(format!("{}: unexpected character `{}`", line, c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Returns the world to camera matrix of a camera at `eye` looking at
+/// `look`.
+///
+/// * `eye` - Camera position.
+/// * `look` - Point the camera looks at.
+/// * `up` - Up direction.
+fn look_at(eye: Point3, look: Point3, up: Vec3) -> Result<Mat4, String> {
+    let dir = (look - eye).unit_vector();
+    let right = up.unit_vector().cross(dir);
+    if right.length() == 0.0 {
+        return Err("up vector and viewing direction are parallel".to_string());
+    }
+    let right = right.unit_vector();
+    let new_up = dir.cross(right);
+
+    let camera_to_world = Mat4::new([
+        [right.x(), new_up.x(), dir.x(), eye.x()],
+        [right.y(), new_up.y(), dir.y(), eye.y()],
+        [right.z(), new_up.z(), dir.z(), eye.z()],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+
+    camera_to_world
+        .inverse()
+        .ok_or_else(|| "invalid LookAt".to_string())
+}
+
+/// Returns the perceptual roughness of a PBRT roughness that is remapped to
+/// the GGX alpha parameter with PBRT's `RoughnessToAlpha`.
+///
+/// * `roughness` - PBRT roughness.
+fn pbrt_roughness(roughness: Float) -> Float {
+    if roughness <= 0.0 {
+        return 0.0;
+    }
+
+    let x = roughness.max(1.0e-3).ln();
+    let alpha = 1.62142
+        + 0.819955 * x
+        + 0.1734 * x * x
+        + 0.0171201 * x * x * x
+        + 0.000640711 * x * x * x * x;
+    alpha.max(0.0).sqrt()
+}
@@ -0,0 +1,419 @@
+//! # Scatter
+//!
+//! A library for distributing instances of a prototype object over a surface
+//! or through a volume. Each instance gets a random position, rotation about
+//! the y-axis and uniform scale within the given bounds. A seed makes the
+//! distribution repeatable without disturbing other random numbers.
+//!
+//! Poisson-disk placement fills a region with blue-noise points that are at
+//! least a given distance apart. A minimum distance can also be enforced for
+//! the other domains by dropping instances that are too close to earlier
+//! ones.
+//!
+//! Curves place instances along a cubic Bézier path, e.g. for fences, chains
+//! and spirals. Instances can be turned to follow the direction of the path.
+
+use super::{ArcHittable, Float, Instance, Mat4, Point3, Random, Vec3, TWO_PI, Y_AXIS};
+use std::collections::HashMap;
+
+/// Number of candidates tried around a point before it is retired.
+const POISSON_CANDIDATES: usize = 30;
+
+/// Region the instances are distributed in.
+#[derive(Debug, Copy, Clone)]
+pub enum Domain {
+    /// Jittered cells of a grid on the plane `y = min.y` between the x and z
+    /// bounds. There is one instance per cell placed at the minimum corner
+    /// of the cell and offset by `jitter` times a random fraction of the
+    /// cell size.
+    Surface {
+        min: Point3,
+        max: Point3,
+        cells: (usize, usize),
+        jitter: Float,
+    },
+
+    /// Uniformly distributed points inside an axis aligned box.
+    Volume {
+        min: Point3,
+        max: Point3,
+        count: usize,
+    },
+
+    /// Poisson-disk points inside an axis aligned box that are at least
+    /// `radius` apart. Boxes that are flat in y fill the plane `y = min.y`.
+    PoissonDisk {
+        min: Point3,
+        max: Point3,
+        radius: Float,
+    },
+
+    /// Instances at evenly spaced parameters along a cubic Bézier curve
+    /// from the first to the last control point. When `follow` is set the
+    /// x-axis of each instance is turned about the y-axis towards the
+    /// direction of the curve.
+    Curve {
+        points: [Point3; 4],
+        count: usize,
+        follow: bool,
+    },
+}
+
+/// Placement of one instance.
+#[derive(Debug, Copy, Clone)]
+pub struct Placement {
+    /// Index of the instance.
+    pub index: usize,
+
+    /// Position of the instance.
+    pub position: Point3,
+
+    /// Rotation about the y-axis in degrees including any turn to follow a
+    /// curve.
+    pub rotation: Float,
+
+    /// Uniform scale.
+    pub scale: Float,
+}
+
+impl Placement {
+    /// Rotate and move an object modelled about the origin to the placement.
+    /// Scale depends on the object so it is left to the prototype, e.g. by
+    /// scaling the radius of a sphere. The object is shared by instances so
+    /// a mesh prototype is only stored once.
+    ///
+    /// * `object` - The object.
+    pub fn transform(&self, object: ArcHittable) -> ArcHittable {
+        Instance::new(
+            object,
+            Mat4::translate(self.position) * Mat4::rotate_axis(Y_AXIS, self.rotation),
+        )
+    }
+}
+
+/// Distributes instances of a prototype object.
+#[derive(Debug, Copy, Clone)]
+pub struct Scatter {
+    /// Region the instances are distributed in.
+    domain: Domain,
+
+    /// Bounds of the rotation about the y-axis in degrees.
+    rotation: (Float, Float),
+
+    /// Bounds of the uniform scale.
+    scale: (Float, Float),
+
+    /// Seed for the random numbers.
+    seed: Option<u64>,
+
+    /// Minimum distance between instances.
+    min_distance: Option<Float>,
+}
+
+impl Scatter {
+    /// Create a new scatter without rotation or scaling that uses the
+    /// current random number generator.
+    ///
+    /// * `domain` - Region the instances are distributed in.
+    pub fn new(domain: Domain) -> Scatter {
+        Scatter {
+            domain,
+            rotation: (0.0, 0.0),
+            scale: (1.0, 1.0),
+            seed: None,
+            min_distance: None,
+        }
+    }
+
+    /// Returns the scatter with random rotations about the y-axis.
+    ///
+    /// * `min` - Minimum rotation in degrees.
+    /// * `max` - Maximum rotation in degrees.
+    pub fn with_rotation(self, min: Float, max: Float) -> Scatter {
+        Scatter {
+            rotation: (min, max),
+            ..self
+        }
+    }
+
+    /// Returns the scatter with random uniform scales.
+    ///
+    /// * `min` - Minimum scale.
+    /// * `max` - Maximum scale.
+    pub fn with_scale(self, min: Float, max: Float) -> Scatter {
+        Scatter {
+            scale: (min, max),
+            ..self
+        }
+    }
+
+    /// Returns the scatter with a seed for repeatable distributions.
+    ///
+    /// * `seed` - The seed.
+    pub fn with_seed(self, seed: u64) -> Scatter {
+        Scatter {
+            seed: Some(seed),
+            ..self
+        }
+    }
+
+    /// Returns the scatter with instances that are closer than a minimum
+    /// distance to an earlier instance removed.
+    ///
+    /// * `distance` - Minimum distance between instances.
+    pub fn with_min_distance(self, distance: Float) -> Scatter {
+        Scatter {
+            min_distance: Some(distance),
+            ..self
+        }
+    }
+
+    /// Returns the placements of the instances.
+    pub fn placements(&self) -> Vec<Placement> {
+        // Seeded distributions restore the random number generator after.
+        let state = self.seed.map(|s| {
+            let state = Random::save();
+            Random::seed(s);
+            state
+        });
+
+        // Turns about the y-axis that follow a curve.
+        let mut headings = Vec::new();
+
+        let positions = match self.domain {
+            Domain::Surface {
+                min,
+                max,
+                cells: (nx, nz),
+                jitter,
+            } => {
+                let dx = (max.x() - min.x()) / nx as Float;
+                let dz = (max.z() - min.z()) / nz as Float;
+                (0..nx)
+                    .flat_map(|i| (0..nz).map(move |j| (i, j)))
+                    .map(|(i, j)| {
+                        Point3::new(
+                            min.x() + (i as Float + jitter * Random::sample::<Float>()) * dx,
+                            min.y(),
+                            min.z() + (j as Float + jitter * Random::sample::<Float>()) * dz,
+                        )
+                    })
+                    .collect::<Vec<Point3>>()
+            }
+            Domain::Volume { min, max, count } => (0..count)
+                .map(|_| min + (max - min) * Random::vec3())
+                .collect(),
+            Domain::PoissonDisk { min, max, radius } => poisson_disk(min, max, radius),
+            Domain::Curve {
+                points,
+                count,
+                follow,
+            } => {
+                let ts: Vec<Float> = (0..count)
+                    .map(|i| i as Float / (count.max(2) - 1) as Float)
+                    .collect();
+                if follow {
+                    headings = ts
+                        .iter()
+                        .map(|&t| {
+                            let d = bezier_tangent(&points, t);
+                            (-d.z()).atan2(d.x()).to_degrees()
+                        })
+                        .collect();
+                }
+                ts.iter().map(|&t| bezier(&points, t)).collect()
+            }
+        };
+
+        let positions: Vec<(Point3, Float)> = positions
+            .into_iter()
+            .enumerate()
+            .map(|(i, p)| (p, headings.get(i).copied().unwrap_or(0.0)))
+            .collect();
+
+        let positions = match self.min_distance {
+            Some(distance) => {
+                let mut grid = Grid::new(distance);
+                positions
+                    .into_iter()
+                    .filter(|&(p, _)| {
+                        let free = grid.is_free(p);
+                        if free {
+                            grid.insert(p);
+                        }
+                        free
+                    })
+                    .collect()
+            }
+            None => positions,
+        };
+
+        let placements = positions
+            .into_iter()
+            .enumerate()
+            .map(|(index, (position, heading))| Placement {
+                index,
+                position,
+                rotation: heading + sample(self.rotation),
+                scale: sample(self.scale),
+            })
+            .collect();
+
+        if let Some(state) = state {
+            Random::restore(&state);
+        }
+
+        placements
+    }
+
+    /// Returns the instances built by a prototype for each placement. The
+    /// prototype can skip a placement by returning `None`.
+    ///
+    /// * `prototype` - Builds the instance for a placement.
+    pub fn instances<F>(&self, prototype: F) -> Vec<ArcHittable>
+    where
+        F: FnMut(&Placement) -> Option<ArcHittable>,
+    {
+        self.placements().iter().filter_map(prototype).collect()
+    }
+}
+
+/// Returns a random value within bounds or the minimum if they are equal.
+///
+/// * `bounds` - Minimum and maximum.
+fn sample(bounds: (Float, Float)) -> Float {
+    if bounds.0 < bounds.1 {
+        Random::sample_in_range(bounds.0, bounds.1)
+    } else {
+        bounds.0
+    }
+}
+
+/// Returns a point on a cubic Bézier curve.
+///
+/// * `p` - Control points.
+/// * `t` - Curve parameter in [0, 1].
+fn bezier(p: &[Point3; 4], t: Float) -> Point3 {
+    let u = 1.0 - t;
+    p[0] * (u * u * u) + p[1] * (3.0 * u * u * t) + p[2] * (3.0 * u * t * t) + p[3] * (t * t * t)
+}
+
+/// Returns the derivative of a cubic Bézier curve.
+///
+/// * `p` - Control points.
+/// * `t` - Curve parameter in [0, 1].
+fn bezier_tangent(p: &[Point3; 4], t: Float) -> Vec3 {
+    let u = 1.0 - t;
+    (p[1] - p[0]) * (3.0 * u * u) + (p[2] - p[1]) * (6.0 * u * t) + (p[3] - p[2]) * (3.0 * t * t)
+}
+
+/// Returns Poisson-disk points inside a box using Bridson's algorithm.
+///
+/// * `min` - Minimum corner of the box.
+/// * `max` - Maximum corner of the box.
+/// * `radius` - Minimum distance between points.
+fn poisson_disk(min: Point3, max: Point3, radius: Float) -> Vec<Point3> {
+    let flat = max.y() <= min.y();
+    let inside = |p: Point3| {
+        p.x() >= min.x()
+            && p.x() <= max.x()
+            && p.y() >= min.y()
+            && p.y() <= max.y()
+            && p.z() >= min.z()
+            && p.z() <= max.z()
+    };
+
+    let mut grid = Grid::new(radius);
+    let first = min + (max - min) * Random::vec3();
+    grid.insert(first);
+
+    let mut points = vec![first];
+    let mut active = vec![0];
+
+    while !active.is_empty() {
+        let k = Random::sample_in_range(0, active.len());
+        let centre = points[active[k]];
+
+        // Candidates are placed in the shell between `radius` and twice it.
+        let found = (0..POISSON_CANDIDATES)
+            .map(|_| {
+                let direction = if flat {
+                    let phi = Random::sample_in_range(0.0, TWO_PI);
+                    Vec3::new(phi.cos(), 0.0, phi.sin())
+                } else {
+                    Random::unit_vec3()
+                };
+                centre + direction * radius * (1.0 + Random::sample::<Float>())
+            })
+            .find(|&p| inside(p) && grid.is_free(p));
+
+        match found {
+            Some(p) => {
+                grid.insert(p);
+                active.push(points.len());
+                points.push(p);
+            }
+            None => {
+                active.swap_remove(k);
+            }
+        }
+    }
+
+    points
+}
+
+/// Uniform grid of points used to find points closer than a distance.
+struct Grid {
+    /// Minimum distance between points and the size of the cells.
+    distance: Float,
+
+    /// Points in each cell.
+    cells: HashMap<(i64, i64, i64), Vec<Point3>>,
+}
+
+impl Grid {
+    /// Create a new empty grid.
+    ///
+    /// * `distance` - Minimum distance between points.
+    fn new(distance: Float) -> Grid {
+        Grid {
+            distance,
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Returns the cell containing a point.
+    ///
+    /// * `p` - The point.
+    fn cell(&self, p: Point3) -> (i64, i64, i64) {
+        (
+            (p.x() / self.distance).floor() as i64,
+            (p.y() / self.distance).floor() as i64,
+            (p.z() / self.distance).floor() as i64,
+        )
+    }
+
+    /// Returns true if no point is closer than the minimum distance.
+    ///
+    /// * `p` - The point.
+    fn is_free(&self, p: Point3) -> bool {
+        let (i, j, k) = self.cell(p);
+        let d2 = self.distance * self.distance;
+
+        (i - 1..=i + 1).all(|x| {
+            (j - 1..=j + 1).all(|y| {
+                (k - 1..=k + 1).all(|z| match self.cells.get(&(x, y, z)) {
+                    Some(points) => points.iter().all(|&q| (q - p).length_squared() >= d2),
+                    None => true,
+                })
+            })
+        })
+    }
+
+    /// Add a point.
+    ///
+    /// * `p` - The point.
+    fn insert(&mut self, p: Point3) {
+        self.cells.entry(self.cell(p)).or_default().push(p);
+    }
+}
@@ -0,0 +1,106 @@
+//! # Variation
+//!
+//! A library for varying the built-in scenes that generate objects randomly,
+//! e.g. the small spheres of the final scene of Raytracing in One Weekend and
+//! the cluster of spheres of the final scene of Raytracing The Next Week.
+//!
+//! A seed makes the generated layout, materials and textures repeatable so a
+//! favourite arrangement can be rendered again. The density scales the
+//! number of generated objects and a count replaces it.
+
+use super::{Float, Random};
+
+/// Parameters of randomly generated scenes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Variation {
+    /// Seed for the random numbers used to build the scene. `None` continues
+    /// with the current random number generator.
+    pub seed: Option<u64>,
+
+    /// Factor scaling the number of generated objects.
+    pub density: Float,
+
+    /// Number of generated objects. Overrides the density.
+    pub count: Option<usize>,
+}
+
+impl Default for Variation {
+    /// Returns the variation that builds the scenes as designed.
+    fn default() -> Self {
+        Variation {
+            seed: None,
+            density: 1.0,
+            count: None,
+        }
+    }
+}
+
+impl Variation {
+    /// Create a new variation.
+    ///
+    /// * `seed` - Optional seed for the random numbers.
+    /// * `density` - Factor scaling the number of generated objects.
+    /// * `count` - Optional number of generated objects.
+    pub fn new(seed: Option<u64>, density: Float, count: Option<usize>) -> Variation {
+        Variation {
+            seed,
+            density,
+            count,
+        }
+    }
+
+    /// Returns the variation with a seed drawn from the random number
+    /// generator if it has none, so the generated scene can be built again
+    /// with the same seed.
+    pub fn seeded(self) -> Variation {
+        Variation {
+            seed: Some(self.seed.unwrap_or_else(Random::sample)),
+            ..self
+        }
+    }
+
+    /// Build a scene with the random number generator seeded by the seed, if
+    /// any. The random number generator is restored after so the render
+    /// isn't affected.
+    ///
+    /// * `build` - Builds the scene.
+    pub fn generate<T, F: FnOnce() -> T>(&self, build: F) -> T {
+        match self.seed {
+            Some(seed) => {
+                let state = Random::save();
+                Random::seed(seed);
+                let result = build();
+                Random::restore(&state);
+                result
+            }
+            None => build(),
+        }
+    }
+
+    /// Returns the number of objects to generate.
+    ///
+    /// * `default` - Number of objects the scene generates by design.
+    pub fn count(&self, default: usize) -> usize {
+        self.count
+            .unwrap_or_else(|| (default as Float * self.density.max(0.0)).round() as usize)
+    }
+
+    /// Returns the cells of a grid with one object per cell. The number of
+    /// cells is about the number of objects to generate and the grid keeps
+    /// its aspect ratio.
+    ///
+    /// * `cells` - Cells of the grid the scene generates by design.
+    pub fn cells(&self, cells: (usize, usize)) -> (usize, usize) {
+        let (nx, nz) = cells;
+        let n = nx * nz;
+        if n == 0 {
+            return cells;
+        }
+
+        let scale = (self.count(n) as Float / n as Float).sqrt();
+        (
+            (nx as Float * scale).round() as usize,
+            (nz as Float * scale).round() as usize,
+        )
+    }
+}
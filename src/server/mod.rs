@@ -0,0 +1,413 @@
+//! # Server
+//!
+//! A library for serving a live preview of a render over HTTP so headless
+//! renders on remote machines can be watched in a browser. The in-progress
+//! image is streamed as MJPEG, which browsers display in a plain `<img>`
+//! element, and the progress is available as text.
+//!
+//! * `/`            - Page showing the stream and progress.
+//! * `/stream.mjpg` - MJPEG stream of the image.
+//! * `/status`      - Progress of the render as text.
+//!
+//! Requests are read with a timeout and a size limit and the number of open
+//! connections is capped so clients can't tie up the renderer's machine.
+//!
+//! The server runs until the guard returned by `PreviewServer::start()` is
+//! dropped, which closes the listening socket so the address can be bound
+//! again.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, RgbaImage};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Quality of the JPEG frames.
+const JPEG_QUALITY: u8 = 80;
+
+/// How long a stream waits for a new frame before resending the last one.
+/// This keeps idle connections alive and detects closed ones.
+const FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a client may take to send its request or accept data.
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum number of bytes read of a request including its headers.
+const MAX_REQUEST_BYTES: u64 = 8 * 1024;
+
+/// Maximum number of connections served at the same time. Each one is
+/// handled by its own thread.
+const MAX_CONNECTIONS: usize = 16;
+
+/// Boundary between the parts of the MJPEG stream.
+const BOUNDARY: &str = "frame";
+
+/// Page showing the stream and polling the progress.
+const INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head><title>Render preview</title></head>
+<body style="background: #222; color: #ddd; font-family: monospace">
+<p id="status"></p>
+<img src="/stream.mjpg" style="max-width: 100%">
+<script>
+setInterval(() => fetch("/status").then(r => r.text()).then(t => {
+  document.getElementById("status").textContent = t;
+}), 1000);
+</script>
+</body>
+</html>
+"#;
+
+/// Latest frame published by the renderer.
+struct Frame {
+    /// Frame number which increases with each published frame.
+    number: u64,
+
+    /// JPEG encoded image.
+    jpeg: Arc<Vec<u8>>,
+
+    /// Fraction of the render completed.
+    progress: f32,
+}
+
+/// Serves the latest published frame of a render.
+pub struct PreviewServer {
+    /// Latest frame.
+    frame: Mutex<Frame>,
+
+    /// Signalled when a frame is published.
+    published: Condvar,
+
+    /// Number of open connections.
+    connections: AtomicUsize,
+
+    /// Set when the server is stopped.
+    stopped: AtomicBool,
+}
+
+/// Keeps a preview server running. Dropping it stops the server and waits
+/// for the listening socket to close.
+pub struct PreviewGuard {
+    /// The server.
+    server: Arc<PreviewServer>,
+
+    /// Address the server listens on.
+    addr: SocketAddr,
+
+    /// Thread accepting connections.
+    listener: Option<JoinHandle<()>>,
+}
+
+impl PreviewGuard {
+    /// Returns the address the server listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Deref for PreviewGuard {
+    type Target = PreviewServer;
+
+    /// Returns the server.
+    fn deref(&self) -> &PreviewServer {
+        &self.server
+    }
+}
+
+impl Drop for PreviewGuard {
+    /// Stop the server. Streams are woken up so they return and the listener
+    /// is woken up with a connection of its own so it closes its socket.
+    fn drop(&mut self) {
+        {
+            let _frame = self.server.frame.lock().unwrap();
+            self.server.stopped.store(true, Ordering::SeqCst);
+            self.server.published.notify_all();
+        }
+
+        // Connect to the loopback address if listening on all interfaces.
+        let mut addr = self.addr;
+        if addr.ip().is_unspecified() {
+            addr.set_ip(match addr.ip() {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            });
+        }
+
+        // The listener only notices the stop flag once it accepts a
+        // connection so don't wait for it if that isn't possible.
+        if TcpStream::connect_timeout(&addr, IO_TIMEOUT).is_ok() {
+            if let Some(listener) = self.listener.take() {
+                let _ = listener.join();
+            }
+        }
+    }
+}
+
+/// Counts a connection as open until it is dropped.
+struct Connection {
+    /// The server the client is connected to.
+    server: Arc<PreviewServer>,
+}
+
+impl Connection {
+    /// Count a new connection unless the limit is reached.
+    ///
+    /// * `server` - The server the client is connected to.
+    fn open(server: &Arc<PreviewServer>) -> Option<Connection> {
+        server
+            .connections
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < MAX_CONNECTIONS).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Connection {
+                server: Arc::clone(server),
+            })
+    }
+}
+
+impl Drop for Connection {
+    /// Count the connection as closed.
+    fn drop(&mut self) {
+        self.server.connections.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl PreviewServer {
+    /// Start serving a preview in background threads. The server stops when
+    /// the returned guard is dropped.
+    ///
+    /// * `addr` - Address to listen on, e.g. `0.0.0.0:8080`.
+    pub fn start(addr: &str) -> Result<PreviewGuard, String> {
+        let listener = TcpListener::bind(addr)
+            .map_err(|e| format!("Unable to serve preview on {}: {}", addr, e))?;
+        let local = listener
+            .local_addr()
+            .map_err(|e| format!("Unable to serve preview on {}: {}", addr, e))?;
+
+        let server = Arc::new(PreviewServer {
+            frame: Mutex::new(Frame {
+                number: 0,
+                jpeg: Arc::new(Vec::new()),
+                progress: 0.0,
+            }),
+            published: Condvar::new(),
+            connections: AtomicUsize::new(0),
+            stopped: AtomicBool::new(false),
+        });
+
+        let s = Arc::clone(&server);
+        let handle = thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                if s.stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                // Slow clients must not block the server.
+                if stream.set_read_timeout(Some(IO_TIMEOUT)).is_err()
+                    || stream.set_write_timeout(Some(IO_TIMEOUT)).is_err()
+                {
+                    continue;
+                }
+
+                match Connection::open(&s) {
+                    Some(connection) => {
+                        thread::spawn(move || {
+                            // Errors only mean the client went away.
+                            let _ = connection.server.handle(stream);
+                        });
+                    }
+                    None => {
+                        let _ = respond(
+                            &mut stream,
+                            "503 Service Unavailable",
+                            "text/plain",
+                            b"Too many connections",
+                        );
+                    }
+                }
+            }
+        });
+
+        Ok(PreviewGuard {
+            server,
+            addr: local,
+            listener: Some(handle),
+        })
+    }
+
+    /// Returns the fraction of the render completed in the latest frame.
+    pub fn progress(&self) -> f32 {
+        self.frame.lock().unwrap().progress
+    }
+
+    /// Publish a new frame to all streams.
+    ///
+    /// * `image`    - The image with the first row at the top.
+    /// * `progress` - Fraction of the render completed.
+    pub fn publish(&self, image: &RgbaImage, progress: f32) -> Result<(), String> {
+        // JPEG has no alpha channel.
+        let rgb = DynamicImage::ImageRgba8(image.clone()).to_rgb8();
+
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY)
+            .encode_image(&rgb)
+            .map_err(|e| format!("Error encoding preview frame: {}", e))?;
+
+        let mut frame = self.frame.lock().unwrap();
+        frame.number += 1;
+        frame.jpeg = Arc::new(jpeg);
+        frame.progress = progress;
+        self.published.notify_all();
+        Ok(())
+    }
+
+    /// Handle an HTTP request.
+    ///
+    /// * `stream` - Connection to the client.
+    fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?).take(MAX_REQUEST_BYTES);
+
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+
+        // Skip the headers.
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+
+        let path = request.split_whitespace().nth(1).unwrap_or("/");
+        match path {
+            "/" => respond(&mut stream, "200 OK", "text/html", INDEX_HTML.as_bytes()),
+            "/status" => {
+                let progress = self.frame.lock().unwrap().progress;
+                let status = format!("Progress {:.2}%", 100_f32 * progress);
+                respond(&mut stream, "200 OK", "text/plain", status.as_bytes())
+            }
+            "/stream.mjpg" => self.stream(&mut stream),
+            _ => respond(&mut stream, "404 Not Found", "text/plain", b"Not found"),
+        }
+    }
+
+    /// Send frames as they are published until the client disconnects or
+    /// the server is stopped. While nothing has been published an empty part
+    /// is sent instead so closed connections are noticed.
+    ///
+    /// * `stream` - Connection to the client.
+    fn stream(&self, stream: &mut TcpStream) -> io::Result<()> {
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\n\
+             Content-Type: multipart/x-mixed-replace; boundary={}\r\n\
+             Cache-Control: no-cache\r\n\
+             Connection: close\r\n\r\n",
+            BOUNDARY
+        )?;
+
+        let mut last = 0;
+        loop {
+            let (jpeg, fresh) = {
+                let frame = self.frame.lock().unwrap();
+                let (frame, _) = self
+                    .published
+                    .wait_timeout_while(frame, FRAME_TIMEOUT, |f| {
+                        f.number == last && !self.stopped.load(Ordering::SeqCst)
+                    })
+                    .unwrap();
+                let fresh = frame.number != last;
+                last = frame.number;
+                (Arc::clone(&frame.jpeg), fresh)
+            };
+
+            let stopped = self.stopped.load(Ordering::SeqCst);
+            if stopped && !fresh {
+                return Ok(());
+            }
+
+            if jpeg.is_empty() {
+                // Nothing has been published yet.
+                write!(
+                    stream,
+                    "--{}\r\nContent-Type: text/plain\r\nContent-Length: 0\r\n\r\n\r\n",
+                    BOUNDARY
+                )?;
+            } else {
+                write!(
+                    stream,
+                    "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+                    BOUNDARY,
+                    jpeg.len()
+                )?;
+                stream.write_all(&jpeg)?;
+                stream.write_all(b"\r\n")?;
+            }
+            stream.flush()?;
+
+            // Send the final frame before returning.
+            if stopped {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Write a complete HTTP response.
+///
+/// * `stream`       - Connection to the client.
+/// * `status`       - Status code and reason.
+/// * `content_type` - MIME type of the body.
+/// * `body`         - The body.
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stopping the server releases its address even while a client is
+    /// waiting for the first frame.
+    #[test]
+    fn address_can_be_bound_again() {
+        let guard = PreviewServer::start("127.0.0.1:0").unwrap();
+        let addr = guard.addr;
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.set_read_timeout(Some(IO_TIMEOUT)).unwrap();
+        client
+            .write_all(b"GET /stream.mjpg HTTP/1.1\r\n\r\n")
+            .unwrap();
+
+        let mut reader = BufReader::new(client);
+        let mut status = String::new();
+        reader.read_line(&mut status).unwrap();
+        assert!(status.starts_with("HTTP/1.1 200 OK"), "{}", status);
+
+        drop(guard);
+
+        // The stream ends once the server is stopped.
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+
+        let guard = PreviewServer::start(&addr.to_string()).unwrap();
+        assert_eq!(guard.addr, addr);
+    }
+}
@@ -0,0 +1,58 @@
+//! # Signal
+//!
+//! A library for handling interrupt (Ctrl-C) requests so renders can be
+//! cancelled cleanly.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Exit status used when the render was interrupted. This follows the shell
+/// convention of 128 + SIGINT.
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// Set when an interrupt has been requested.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Install a handler for SIGINT that records the interrupt request instead
+/// of terminating the process. Long running loops should poll
+/// `interrupted()` and wind down. Returns an error if the handler can't be
+/// installed.
+#[cfg(unix)]
+pub fn install_interrupt_handler() -> Result<(), String> {
+    extern "C" fn handler(_signum: libc::c_int) {
+        // Only async-signal-safe operations are allowed here.
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    }
+
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handler as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        action.sa_flags = libc::SA_RESTART;
+
+        if libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut()) != 0 {
+            return Err("Unable to install interrupt handler".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Install a handler for SIGINT. This is not supported on this platform so
+/// interrupts will terminate the process.
+#[cfg(not(unix))]
+pub fn install_interrupt_handler() -> Result<(), String> {
+    Ok(())
+}
+
+/// Returns true if an interrupt has been requested.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Exit with a distinct status code if the render was interrupted. This is
+/// called after the partial image has been saved.
+pub fn exit_if_interrupted() {
+    if interrupted() {
+        eprintln!("Render was interrupted. Output image is incomplete.");
+        std::process::exit(EXIT_INTERRUPTED);
+    }
+}
@@ -4,7 +4,7 @@
 //! 2 textures.
 
 #![allow(dead_code)]
-use super::{ArcTexture, Colour, Float, Point3, Texture};
+use super::{ArcTexture, Colour, Float, Point3, Table, Texture, Value};
 use std::fmt;
 use std::sync::Arc;
 
@@ -73,4 +73,16 @@ impl Texture for Checker {
             self.even.value(u, v, p)
         }
     }
+
+    /// Returns a description of the texture in the scene file format. The
+    /// scene loader passes `even` as the first texture which is stored in
+    /// `odd`.
+    fn describe(&self) -> Value {
+        let mut t = Table::new();
+        t.insert("type".into(), "checker".into());
+        t.insert("even".into(), self.odd.describe());
+        t.insert("odd".into(), self.even.describe());
+        t.insert("scale".into(), self.scale.into());
+        t.into()
+    }
 }
@@ -3,11 +3,26 @@
 //! A library for handling image textures.
 
 #![allow(dead_code)]
-use super::{clamp, ArcTexture, Colour, Float, Point3, Texture};
-use image::{Rgb, RgbImage};
+use super::{
+    clamp, ArcTexture, Colour, ColourBuffer, ColourStorage, Float, MemoryBudget, Point3, Table,
+    Texture, TileCache, Value,
+};
+use image::{ImageFormat, Rgb, RgbImage};
 use std::fmt;
 use std::sync::{Arc, RwLock};
 
+/// Pixels of an image texture.
+enum Pixels {
+    /// 8-bit colour values.
+    Ldr(RgbImage),
+
+    /// Linear high dynamic range colour values.
+    Hdr(ColourBuffer),
+
+    /// Tiles loaded on demand.
+    Tiled(TileCache),
+}
+
 /// Models an image texture
 #[derive(Clone)]
 pub struct Image {
@@ -18,28 +33,96 @@ pub struct Image {
     height: u32,
 
     /// The image
-    img: Arc<RwLock<RgbImage>>,
+    img: Arc<RwLock<Pixels>>,
+
+    /// Path to the image.
+    path: String,
+
+    /// Storage format for high dynamic range images.
+    storage: ColourStorage,
 }
 
 impl Image {
-    /// Creates a new image texture.
+    /// Creates a new image texture without a memory budget. High dynamic
+    /// range images are stored in the RGB9E5 format.
     ///
-    /// * `t0` - Provides first colour for the imageboard pattern.
-    /// * `t1` - Provides second colour for the imageboard pattern.
+    /// * `path` - Path to the image.
     pub fn new(path: &str) -> ArcTexture {
-        // Read image and convert to RGB.
-        let img = image::open(path)
-            .expect(format!("Unable to open {}", path).as_ref())
-            .into_rgb8();
+        Image::with_storage(path, ColourStorage::Rgb9e5, &MemoryBudget::default())
+    }
 
-        // Read metadata before we wrap it in a Rc<RefCell<RgbImage>> to
-        // avoid borrowing it.
-        let width = img.width();
-        let height = img.height();
+    /// Creates a new image texture. Images with 8-bit colour values are
+    /// stored as is. High dynamic range images like `.hdr` and `.exr` files
+    /// are stored in the given format and decoded on the fly.
+    ///
+    /// * `path` - Path to the image.
+    /// * `storage` - Storage format for high dynamic range images.
+    /// * `budget` - Memory budget the image is downsampled to fit in.
+    pub fn with_storage(path: &str, storage: ColourStorage, budget: &MemoryBudget) -> ArcTexture {
+        let is_hdr = matches!(
+            ImageFormat::from_path(path),
+            Ok(ImageFormat::Hdr) | Ok(ImageFormat::OpenExr)
+        );
+
+        // Downsample the image if it doesn't fit in the memory budget.
+        let bytes_per_pixel = if is_hdr { storage.bytes_per_pixel() } else { 3 };
+        let img = budget
+            .open_image(path, bytes_per_pixel)
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        // Read the dimensions before the pixels are moved into storage.
+        let (img, width, height) = if is_hdr {
+            let img = img.into_rgb32f();
+            let (width, height) = img.dimensions();
+            (
+                Pixels::Hdr(ColourBuffer::from_image(&img, storage)),
+                width,
+                height,
+            )
+        } else {
+            let img = img.into_rgb8();
+            let (width, height) = img.dimensions();
+            (Pixels::Ldr(img), width, height)
+        };
 
         let img = Arc::new(RwLock::new(img));
 
-        Arc::new(Image { img, width, height })
+        Arc::new(Image {
+            img,
+            width,
+            height,
+            path: path.to_string(),
+            storage,
+        })
+    }
+
+    /// Creates a new image texture for very large images using sparse
+    /// virtual texturing. The image is split into tiles that are loaded
+    /// when rays first hit them and at most `cache_tiles` tiles are kept in
+    /// memory. High dynamic range images keep full precision.
+    ///
+    /// * `path` - Path to the image.
+    /// * `tile_size` - Width and height of a tile.
+    /// * `cache_tiles` - Maximum number of tiles kept in memory.
+    pub fn virtual_texture(path: &str, tile_size: u32, cache_tiles: usize) -> ArcTexture {
+        let is_hdr = matches!(
+            ImageFormat::from_path(path),
+            Ok(ImageFormat::Hdr) | Ok(ImageFormat::OpenExr)
+        );
+
+        let tiles = TileCache::new(path, is_hdr, tile_size, cache_tiles)
+            .unwrap_or_else(|e| panic!("Unable to tile {}: {}", path, e));
+        let (width, height) = (tiles.width(), tiles.height());
+
+        let img = Arc::new(RwLock::new(Pixels::Tiled(tiles)));
+
+        Arc::new(Image {
+            img,
+            width,
+            height,
+            path: path.to_string(),
+            storage: ColourStorage::Full,
+        })
     }
 }
 
@@ -60,6 +143,7 @@ impl fmt::Debug for Image {
         f.debug_struct("Image")
             .field("width", &self.width)
             .field("height", &self.height)
+            .field("path", &self.path)
             .finish()
     }
 }
@@ -91,9 +175,26 @@ impl Texture for Image {
             j = self.height - 1;
         }
 
-        let img = self.img.read().unwrap();
-        let Rgb(p) = img.get_pixel(i, j);
+        match &*self.img.read().unwrap() {
+            Pixels::Ldr(img) => {
+                let Rgb(p) = img.get_pixel(i, j);
+                Colour::new(p[0] as Float, p[1] as Float, p[2] as Float) * COLOUR_SCALE
+            }
+            Pixels::Hdr(buffer) => buffer.get((j * self.width + i) as usize),
+            Pixels::Tiled(tiles) => tiles.get(i, j),
+        }
+    }
 
-        Colour::new(p[0] as Float, p[1] as Float, p[2] as Float) * COLOUR_SCALE
+    /// Returns a description of the texture in the scene file format.
+    fn describe(&self) -> Value {
+        let mut t = Table::new();
+        t.insert("type".into(), "image".into());
+        t.insert("path".into(), self.path.clone().into());
+        t.insert("storage".into(), self.storage.to_string().into());
+        if let Pixels::Tiled(tiles) = &*self.img.read().unwrap() {
+            t.insert("tile_size".into(), (tiles.tile_size() as i64).into());
+            t.insert("cache_tiles".into(), (tiles.capacity() as i64).into());
+        }
+        t.into()
     }
 }
@@ -5,13 +5,16 @@
 mod checker;
 mod image;
 mod noise;
+mod object_id;
 mod perlin;
 mod solid_colour;
+mod tile_cache;
 
-use super::algebra::{Axis, Colour, Point3, Vec3};
-use super::common::{clamp, Float, Random};
+use super::algebra::{Axis, Colour, Point3, Vec3, X_AXIS, Y_AXIS};
+use super::common::{clamp, ColourBuffer, ColourStorage, Float, MemoryBudget, Random};
 use std::fmt;
 use std::sync::Arc;
+use toml::{Table, Value};
 
 /// Models a solid colour as a texture.
 pub use self::solid_colour::SolidColour;
@@ -22,12 +25,18 @@ pub use self::checker::Checker;
 /// Models a 3-dimension checkerboard pattern.
 pub use self::noise::Noise;
 
+/// Models a debug texture that colours objects by ID.
+pub use self::object_id::ObjectId;
+
 /// Perlin noise generator.
 pub use self::perlin::Perlin;
 
 /// Models an image texture
 pub use self::image::Image;
 
+/// Models a tiled image whose tiles are loaded on demand.
+pub use self::tile_cache::TileCache;
+
 /// Models textures.
 pub trait Texture: fmt::Display + fmt::Debug {
     /// Return the texture colour at the given parametric coordinates.
@@ -36,6 +45,9 @@ pub trait Texture: fmt::Display + fmt::Debug {
     /// * `v` - Paramteric coordinate.
     /// * `p` - Intersection point.
     fn value(&self, u: Float, v: Float, p: &Point3) -> Colour;
+
+    /// Returns a description of the texture in the scene file format.
+    fn describe(&self) -> Value;
 }
 
 /// Atomic reference counted `Texture`.
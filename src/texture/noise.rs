@@ -3,7 +3,9 @@
 //! A library for the 3-dimensional noise texture
 
 #![allow(dead_code)]
-use super::{ArcTexture, Axis, Colour, Float, Perlin, Point3, Texture};
+use super::{
+    ArcTexture, Axis, Colour, Float, Perlin, Point3, Table, Texture, Value, X_AXIS, Y_AXIS,
+};
 use std::fmt;
 use std::sync::{Arc, RwLock};
 
@@ -76,4 +78,28 @@ impl Texture for Noise {
         let scale = self.scale * p[self.axis];
         Colour::one() * (0.5 * (1.0 + (scale + turb).sin()))
     }
+
+    /// Returns a description of the texture in the scene file format.
+    fn describe(&self) -> Value {
+        let axis = match self.axis {
+            X_AXIS => "x",
+            Y_AXIS => "y",
+            _ => "z",
+        };
+
+        let mut t = Table::new();
+        t.insert("type".into(), "noise".into());
+        t.insert("scale".into(), self.scale.into());
+        t.insert(
+            "turbulence_depth".into(),
+            (self.turbulence_depth as i64).into(),
+        );
+        t.insert("turbulence_size".into(), self.turbulence_size.into());
+        t.insert(
+            "grid_size".into(),
+            (self.perlin.read().unwrap().size() as i64).into(),
+        );
+        t.insert("axis".into(), axis.into());
+        t.into()
+    }
 }
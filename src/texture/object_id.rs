@@ -0,0 +1,79 @@
+//! # ObjectId
+//!
+//! A library for a debug texture that colours objects by their ID.
+
+use super::{ArcTexture, Colour, Float, Point3, Table, Texture, Value};
+use std::fmt;
+use std::sync::Arc;
+
+/// Darkest channel value so no object is rendered black.
+const MIN_CHANNEL: Float = 0.1;
+
+/// Models a debug texture with a stable random colour for an object ID.
+#[derive(Debug, Clone)]
+pub struct ObjectId {
+    /// The object ID.
+    id: u64,
+
+    /// The colour hashed from the ID.
+    colour: Colour,
+}
+
+impl ObjectId {
+    /// Creates a new object ID texture.
+    ///
+    /// * `id` - The object ID.
+    pub fn new(id: u64) -> ArcTexture {
+        Arc::new(ObjectId {
+            id,
+            colour: hash_colour(id),
+        })
+    }
+}
+
+impl fmt::Display for ObjectId {
+    /// Display the object ID texture parameters.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "object_id(id: {}, colour: {})", self.id, self.colour)
+    }
+}
+
+impl Texture for ObjectId {
+    /// Return the colour of the object ID regardless of texture coordinates
+    /// and intersection point.
+    ///
+    /// * `_u` - Paramteric coordinate (ignored).
+    /// * `_v` - Paramteric coordinate (ignored).
+    /// * `_p` - Intersection point (ignored).
+    fn value(&self, _u: Float, _v: Float, _p: &Point3) -> Colour {
+        self.colour
+    }
+
+    /// Returns a description of the texture in the scene file format.
+    fn describe(&self) -> Value {
+        let mut t = Table::new();
+        t.insert("type".into(), "object_id".into());
+        t.insert("id".into(), (self.id as i64).into());
+        t.into()
+    }
+}
+
+/// Hash an ID to a colour with the SplitMix64 finalizer so neighbouring IDs
+/// get unrelated colours.
+///
+/// * `id` - The ID.
+fn hash_colour(id: u64) -> Colour {
+    let mut z = id.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+
+    let channel = |shift: u32| {
+        let x = ((z >> shift) & 0xff) as Float / 255.0;
+        MIN_CHANNEL + (1.0 - MIN_CHANNEL) * x
+    };
+
+    Colour::new(channel(0), channel(8), channel(16))
+}
@@ -43,6 +43,11 @@ impl Perlin {
         }
     }
 
+    /// Returns the grid size.
+    pub fn size(&self) -> usize {
+        self.random.len()
+    }
+
     /// Evaluates the noise function at a point.
     ///
     /// * `p` - Point to evaluate the noise function.
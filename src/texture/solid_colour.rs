@@ -2,7 +2,7 @@
 //!
 //! A library for handling solid colour as a texture.
 
-use super::{ArcTexture, Colour, Float, Point3, Texture};
+use super::{ArcTexture, Colour, Float, Point3, Texture, Value};
 use std::fmt;
 use std::sync::Arc;
 
@@ -52,4 +52,9 @@ impl Texture for SolidColour {
     fn value(&self, _u: Float, _v: Float, _p: &Point3) -> Colour {
         self.colour_value
     }
+
+    /// Returns a description of the texture in the scene file format.
+    fn describe(&self) -> Value {
+        self.colour_value.into()
+    }
 }
@@ -0,0 +1,560 @@
+//! # Tile cache
+//!
+//! A library for sparse virtual texturing of very large images, e.g. 21600 x
+//! 10800 earth maps. The image is split into square tiles and written to a
+//! temporary tile file a band of tile rows at a time. PNG images are decoded
+//! row by row so the whole image is never held in memory; other formats are
+//! decoded in full first.
+//!
+//! A tile is read back the first time a texture lookup lands in it and only
+//! a bounded number of recently used tiles are kept in memory, so only the
+//! parts of the image rays actually see take up memory while rendering. The
+//! cache is split into shards with their own lock and tiles are read from
+//! the tile file outside the locks so threads rarely wait on each other.
+//!
+//! On unix the tile file is unlinked as soon as it is created so it doesn't
+//! outlive the process even when the render is interrupted.
+
+use super::{Colour, Float};
+use image::ImageFormat;
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Number of tile files created so far. Used to name them.
+static TILE_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Scale 8-bit colour values to [0, 1].
+const COLOUR_SCALE: Float = 1.0 / 255.0;
+
+/// Maximum number of independently locked shards of the cache.
+const SHARDS: usize = 64;
+
+/// Marks the end of the list of tiles in a shard.
+const NIL: usize = usize::MAX;
+
+/// Formats of the texels of the tiles.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum TexelFormat {
+    /// 8-bit colour values.
+    Ldr,
+
+    /// 32-bit floating point linear colour values.
+    Hdr,
+}
+
+impl TexelFormat {
+    /// Returns the number of bytes used to store a texel.
+    fn bytes_per_texel(&self) -> usize {
+        match self {
+            TexelFormat::Ldr => 3,
+            TexelFormat::Hdr => 12,
+        }
+    }
+}
+
+/// Rows of an image read from top to bottom.
+enum Rows {
+    /// PNG image decoded a row at a time.
+    Png {
+        /// The decoder.
+        reader: Box<png::Reader<BufReader<File>>>,
+
+        /// Colour type of the decoded rows.
+        color_type: png::ColorType,
+    },
+
+    /// Image with 8-bit colour values decoded in full.
+    Ldr {
+        /// The image.
+        img: image::RgbImage,
+
+        /// Next row to read.
+        next: u32,
+    },
+
+    /// Image with floating point colour values decoded in full.
+    Hdr {
+        /// The image.
+        img: image::Rgb32FImage,
+
+        /// Next row to read.
+        next: u32,
+    },
+}
+
+impl Rows {
+    /// Open an image and return its dimensions and rows. Non-interlaced PNG
+    /// images are decoded row by row, others are decoded in full.
+    ///
+    /// * `path` - Path to the image.
+    /// * `hdr` - Keep linear floating point colour values.
+    fn open(path: &str, hdr: bool) -> Result<(u32, u32, TexelFormat, Rows), String> {
+        if !hdr && matches!(ImageFormat::from_path(path), Ok(ImageFormat::Png)) {
+            let file = File::open(path).map_err(|e| e.to_string())?;
+            let mut decoder = png::Decoder::new(BufReader::new(file));
+            decoder.set_transformations(png::Transformations::normalize_to_color8());
+            let reader = decoder.read_info().map_err(|e| e.to_string())?;
+
+            let info = reader.info();
+            if !info.interlaced {
+                let (width, height) = (info.width, info.height);
+                let (color_type, _) = reader.output_color_type();
+                return Ok((
+                    width,
+                    height,
+                    TexelFormat::Ldr,
+                    Rows::Png {
+                        reader: Box::new(reader),
+                        color_type,
+                    },
+                ));
+            }
+        }
+
+        let img = image::open(path).map_err(|e| e.to_string())?;
+        let (width, height) = (img.width(), img.height());
+        if hdr {
+            let img = img.into_rgb32f();
+            Ok((width, height, TexelFormat::Hdr, Rows::Hdr { img, next: 0 }))
+        } else {
+            let img = img.into_rgb8();
+            Ok((width, height, TexelFormat::Ldr, Rows::Ldr { img, next: 0 }))
+        }
+    }
+
+    /// Append the texels of the next rows to a band.
+    ///
+    /// * `rows` - Number of rows to read.
+    /// * `band` - Texels of the band.
+    fn read(&mut self, rows: u32, band: &mut Vec<u8>) -> Result<(), String> {
+        match self {
+            Rows::Png { reader, color_type } => {
+                for _ in 0..rows {
+                    let row = reader
+                        .next_row()
+                        .map_err(|e| e.to_string())?
+                        .ok_or("image ends early")?;
+                    let data = row.data();
+                    match color_type {
+                        png::ColorType::Rgb => band.extend_from_slice(data),
+                        png::ColorType::Rgba => data
+                            .chunks_exact(4)
+                            .for_each(|p| band.extend_from_slice(&p[..3])),
+                        png::ColorType::Grayscale => data
+                            .iter()
+                            .for_each(|&g| band.extend_from_slice(&[g, g, g])),
+                        png::ColorType::GrayscaleAlpha => data
+                            .chunks_exact(2)
+                            .for_each(|p| band.extend_from_slice(&[p[0], p[0], p[0]])),
+                        png::ColorType::Indexed => {
+                            return Err("unexpected indexed colour".to_string())
+                        }
+                    }
+                }
+            }
+            Rows::Ldr { img, next } => {
+                let row_bytes = img.width() as usize * 3;
+                let start = *next as usize * row_bytes;
+                band.extend_from_slice(&img.as_raw()[start..start + rows as usize * row_bytes]);
+                *next += rows;
+            }
+            Rows::Hdr { img, next } => {
+                let row_texels = img.width() as usize * 3;
+                let start = *next as usize * row_texels;
+                img.as_raw()[start..start + rows as usize * row_texels]
+                    .iter()
+                    .for_each(|c| band.extend_from_slice(&c.to_le_bytes()));
+                *next += rows;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Node of the list of tiles in a shard, from most to least recently used.
+struct Node {
+    /// Index of the tile.
+    tile: usize,
+
+    /// Texels of the tile.
+    texels: Arc<Vec<u8>>,
+
+    /// Previous, more recently used, node.
+    prev: usize,
+
+    /// Next, less recently used, node.
+    next: usize,
+}
+
+/// Least recently used tiles of a shard of the cache.
+struct Lru {
+    /// Maximum number of tiles.
+    capacity: usize,
+
+    /// Nodes of the list of tiles.
+    nodes: Vec<Node>,
+
+    /// Nodes keyed by tile index.
+    index: HashMap<usize, usize>,
+
+    /// Most recently used node.
+    head: usize,
+
+    /// Least recently used node.
+    tail: usize,
+}
+
+impl Lru {
+    /// Create an empty shard.
+    ///
+    /// * `capacity` - Maximum number of tiles.
+    fn new(capacity: usize) -> Lru {
+        Lru {
+            capacity,
+            nodes: Vec::with_capacity(capacity),
+            index: HashMap::with_capacity(capacity),
+            head: NIL,
+            tail: NIL,
+        }
+    }
+
+    /// Remove a node from the list.
+    ///
+    /// * `n` - The node.
+    fn unlink(&mut self, n: usize) {
+        let (prev, next) = (self.nodes[n].prev, self.nodes[n].next);
+        match prev {
+            NIL => self.head = next,
+            p => self.nodes[p].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            q => self.nodes[q].prev = prev,
+        }
+    }
+
+    /// Add a node to the front of the list.
+    ///
+    /// * `n` - The node.
+    fn push_front(&mut self, n: usize) {
+        self.nodes[n].prev = NIL;
+        self.nodes[n].next = self.head;
+        match self.head {
+            NIL => self.tail = n,
+            h => self.nodes[h].prev = n,
+        }
+        self.head = n;
+    }
+
+    /// Returns the texels of a tile if it is in memory and marks it as the
+    /// most recently used.
+    ///
+    /// * `tile` - Index of the tile.
+    fn get(&mut self, tile: usize) -> Option<Arc<Vec<u8>>> {
+        let n = *self.index.get(&tile)?;
+        if self.head != n {
+            self.unlink(n);
+            self.push_front(n);
+        }
+        Some(Arc::clone(&self.nodes[n].texels))
+    }
+
+    /// Add a tile replacing the least recently used tile if the shard is
+    /// full. If another thread added the tile first, its texels are kept.
+    ///
+    /// * `tile` - Index of the tile.
+    /// * `texels` - Texels of the tile.
+    fn insert(&mut self, tile: usize, texels: Arc<Vec<u8>>) -> Arc<Vec<u8>> {
+        if let Some(texels) = self.get(tile) {
+            return texels;
+        }
+
+        let n = if self.nodes.len() < self.capacity {
+            self.nodes.push(Node {
+                tile,
+                texels: Arc::clone(&texels),
+                prev: NIL,
+                next: NIL,
+            });
+            self.nodes.len() - 1
+        } else {
+            let n = self.tail;
+            self.unlink(n);
+            self.index.remove(&self.nodes[n].tile);
+            self.nodes[n].tile = tile;
+            self.nodes[n].texels = Arc::clone(&texels);
+            n
+        };
+
+        self.index.insert(tile, n);
+        self.push_front(n);
+        texels
+    }
+}
+
+/// Models a tiled image whose tiles are loaded on demand.
+pub struct TileCache {
+    /// Width of the image.
+    width: u32,
+
+    /// Height of the image.
+    height: u32,
+
+    /// Width and height of a tile.
+    tile_size: u32,
+
+    /// Number of tiles across the image.
+    tiles_x: u32,
+
+    /// Format of the texels.
+    format: TexelFormat,
+
+    /// Maximum number of tiles held in memory.
+    capacity: usize,
+
+    /// Path to the tile file.
+    path: PathBuf,
+
+    /// Tile file.
+    file: TileFile,
+
+    /// Shards of the cache. Tiles are assigned to shards by index.
+    shards: Vec<Mutex<Lru>>,
+}
+
+impl TileCache {
+    /// Split an image into tiles and write them to a temporary tile file.
+    /// Tiles at the right and bottom edges are padded by repeating the last
+    /// column and row of the image.
+    ///
+    /// * `path` - Path to the image.
+    /// * `hdr` - Keep linear floating point colour values.
+    /// * `tile_size` - Width and height of a tile.
+    /// * `capacity` - Maximum number of tiles held in memory.
+    pub fn new(
+        path: &str,
+        hdr: bool,
+        tile_size: u32,
+        capacity: usize,
+    ) -> Result<TileCache, String> {
+        let (width, height, format, mut rows) = Rows::open(path, hdr)?;
+        if width == 0 || height == 0 {
+            return Err("image is empty".to_string());
+        }
+
+        let tile_size = tile_size.max(1);
+        let tiles_x = width.div_ceil(tile_size);
+        let tiles_y = height.div_ceil(tile_size);
+
+        let tile_path = env::temp_dir().join(format!(
+            "raytracing_series-{}-{}.tiles",
+            process::id(),
+            TILE_FILES.fetch_add(1, Ordering::SeqCst)
+        ));
+        let write_error = |e: io::Error| format!("unable to write {}: {}", tile_path.display(), e);
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&tile_path)
+            .map_err(|e| format!("unable to create {}: {}", tile_path.display(), e))?;
+
+        // The open file stays readable after it is unlinked.
+        #[cfg(unix)]
+        let _ = fs::remove_file(&tile_path);
+
+        let texel_bytes = format.bytes_per_texel();
+        let row_bytes = width as usize * texel_bytes;
+        let tile_row_bytes = tile_size as usize * texel_bytes;
+
+        let mut writer = BufWriter::new(&file);
+        let mut band = Vec::with_capacity(row_bytes * tile_size as usize);
+        let mut tile = vec![0; tile_row_bytes * tile_size as usize];
+
+        for ty in 0..tiles_y {
+            let band_rows = tile_size.min(height - ty * tile_size);
+            band.clear();
+            rows.read(band_rows, &mut band)?;
+
+            for tx in 0..tiles_x {
+                let x0 = (tx * tile_size) as usize * texel_bytes;
+                let cols = tile_size.min(width - tx * tile_size) as usize * texel_bytes;
+
+                for (y, dst) in tile.chunks_exact_mut(tile_row_bytes).enumerate() {
+                    let src_row = (y as u32).min(band_rows - 1) as usize * row_bytes;
+                    let src = &band[src_row + x0..src_row + x0 + cols];
+                    let last = &src[cols - texel_bytes..];
+
+                    dst[..cols].copy_from_slice(src);
+                    dst[cols..]
+                        .chunks_exact_mut(texel_bytes)
+                        .for_each(|texel| texel.copy_from_slice(last));
+                }
+
+                writer.write_all(&tile).map_err(write_error)?;
+            }
+        }
+
+        writer.flush().map_err(write_error)?;
+        drop(writer);
+
+        let capacity = capacity.max(1);
+        let shards = capacity.min(SHARDS);
+        let shard_capacity = capacity.div_ceil(shards);
+
+        Ok(TileCache {
+            width,
+            height,
+            tile_size,
+            tiles_x,
+            format,
+            capacity,
+            path: tile_path,
+            file: TileFile::new(file),
+            shards: (0..shards)
+                .map(|_| Mutex::new(Lru::new(shard_capacity)))
+                .collect(),
+        })
+    }
+
+    /// Returns the width of the image.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the image.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the width and height of a tile.
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// Returns the maximum number of tiles held in memory.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of bytes used by a tile.
+    fn tile_bytes(&self) -> usize {
+        (self.tile_size * self.tile_size) as usize * self.format.bytes_per_texel()
+    }
+
+    /// Returns the colour of a texel. The tile containing it is read from the
+    /// tile file if it isn't in memory, replacing the least recently used
+    /// tile of its shard if the shard is full.
+    ///
+    /// * `i` - Column of the texel.
+    /// * `j` - Row of the texel.
+    pub fn get(&self, i: u32, j: u32) -> Colour {
+        let tile = ((j / self.tile_size) * self.tiles_x + i / self.tile_size) as usize;
+        let offset = ((j % self.tile_size) * self.tile_size + i % self.tile_size) as usize
+            * self.format.bytes_per_texel();
+
+        let shard = &self.shards[tile % self.shards.len()];
+
+        let cached = shard.lock().unwrap().get(tile);
+        let texels = match cached {
+            Some(texels) => texels,
+            None => {
+                let texels = Arc::new(self.read_tile(tile));
+                shard.lock().unwrap().insert(tile, texels)
+            }
+        };
+
+        self.decode(&texels[offset..])
+    }
+
+    /// Read a tile from the tile file.
+    ///
+    /// * `tile` - Index of the tile.
+    fn read_tile(&self, tile: usize) -> Vec<u8> {
+        let tile_bytes = self.tile_bytes();
+        let mut texels = vec![0; tile_bytes];
+
+        self.file
+            .read_at(&mut texels, (tile * tile_bytes) as u64)
+            .unwrap_or_else(|e| panic!("Unable to read tile from {}: {}", self.path.display(), e));
+
+        texels
+    }
+
+    /// Returns the colour of a texel from its bytes.
+    ///
+    /// * `bytes` - Bytes starting at the texel.
+    fn decode(&self, bytes: &[u8]) -> Colour {
+        match self.format {
+            TexelFormat::Ldr => {
+                Colour::new(bytes[0] as Float, bytes[1] as Float, bytes[2] as Float) * COLOUR_SCALE
+            }
+            TexelFormat::Hdr => {
+                let c = |k: usize| {
+                    f32::from_le_bytes([bytes[k], bytes[k + 1], bytes[k + 2], bytes[k + 3]])
+                        as Float
+                };
+                Colour::new(c(0), c(4), c(8))
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+impl Drop for TileCache {
+    /// Remove the tile file. On unix it was unlinked when it was created.
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Tile file read at arbitrary offsets from many threads.
+struct TileFile {
+    /// The file.
+    #[cfg(unix)]
+    file: File,
+
+    /// The file. Reads seek so they are serialised.
+    #[cfg(not(unix))]
+    file: Mutex<File>,
+}
+
+impl TileFile {
+    /// Wrap a tile file.
+    ///
+    /// * `file` - The file.
+    fn new(file: File) -> TileFile {
+        #[cfg(not(unix))]
+        let file = Mutex::new(file);
+        TileFile { file }
+    }
+
+    /// Read bytes starting at an offset.
+    ///
+    /// * `buf` - Buffer to fill.
+    /// * `offset` - Offset in bytes from the start of the file.
+    #[cfg(unix)]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        use std::os::unix::fs::FileExt;
+        self.file.read_exact_at(buf, offset)
+    }
+
+    /// Read bytes starting at an offset.
+    ///
+    /// * `buf` - Buffer to fill.
+    /// * `offset` - Offset in bytes from the start of the file.
+    #[cfg(not(unix))]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        use std::io::{Read, Seek, SeekFrom};
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(buf)
+    }
+}
@@ -17,12 +17,29 @@ pub struct ThreadPool {
 
     /// Indicates that the thread pool is shutting down.
     is_shutting_down: bool,
+
+    /// Scheduling options the workers were unable to apply.
+    warnings: Vec<String>,
+}
+
+/// Scheduling options applied to each worker thread.
+#[derive(Debug, Clone, Default)]
+pub struct WorkerOptions {
+    /// Nice value for worker threads (0 = normal, 19 = lowest priority).
+    pub nice: Option<i32>,
+
+    /// Logical CPUs the worker threads are allowed to run on. Empty means no restriction.
+    pub cpu_affinity: Vec<usize>,
 }
 
 impl ThreadPool {
-    /// Build a new thread pool of specified size. Returns a `PoolCreationError` if pool size is
-    /// zero.
-    pub fn build(size: usize) -> Result<Self, PoolCreationError> {
+    /// Build a new thread pool of specified size whose workers use the given scheduling options.
+    /// Returns a `PoolCreationError` if pool size is zero. Options that can't be applied are not
+    /// fatal and are returned by `warnings()`.
+    ///
+    /// * `size`    - Number of workers.
+    /// * `options` - Scheduling options for the workers.
+    pub fn build(size: usize, options: WorkerOptions) -> Result<Self, PoolCreationError> {
         if size == 0 {
             return Err(PoolCreationError::ZeroPoolSize);
         }
@@ -32,19 +49,32 @@ impl ThreadPool {
         let (sender, receiver) = mpsc::sync_channel(size);
         let receiver = Arc::new(Mutex::new(receiver));
 
-        // Allocate workers.
+        // Allocate workers. Each one reports the options it was unable to apply.
+        let (setup_sender, setup_receiver) = mpsc::channel();
         let mut workers = Vec::with_capacity(size);
         for id in 0..size {
-            workers.push(Worker::new(id, Arc::clone(&receiver)));
+            workers.push(Worker::new(
+                id,
+                Arc::clone(&receiver),
+                options.clone(),
+                setup_sender.clone(),
+            ));
         }
+        drop(setup_sender);
 
         Ok(Self {
             workers,
             sender: Some(sender),
             is_shutting_down: false,
+            warnings: setup_receiver.iter().flatten().collect(),
         })
     }
 
+    /// Returns the scheduling options the workers were unable to apply.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
     /// Execute a function in a worker thread as long as pool is not shut down.
     pub fn execute<F>(&self, f: F)
     where
@@ -59,8 +89,6 @@ impl ThreadPool {
     /// Shut down the pool.
     pub fn shutdown(&mut self) {
         if !self.is_shutting_down {
-            eprintln!("Shutting down thread pool. Please wait.");
-
             // Set flag to shutdown so this won't run more than once.
             self.is_shutting_down = true;
 
@@ -70,7 +98,6 @@ impl ThreadPool {
 
             // Wait for threads to complete.
             for worker in &mut self.workers {
-                println!("Waiting for worker {} to shutdown.", worker.id);
                 if let Some(thread) = worker.thread.take() {
                     thread.join().unwrap();
                 }
@@ -90,7 +117,6 @@ type Job = Box<dyn FnOnce() + Send + 'static>;
 
 /// Worker thread.
 struct Worker {
-    id: usize,
     thread: Option<thread::JoinHandle<()>>,
 }
 
@@ -99,29 +125,119 @@ impl Worker {
     ///
     /// * `id`       - Thread ID.
     /// * `receiver` - Receiver for job messages.
-    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
-        let thread = thread::spawn(move || loop {
-            let message = receiver.lock().unwrap().recv();
-
-            match message {
-                Ok(job) => {
-                    //eprintln!("Worker {id} got a job; executing.");
-                    job();
-                }
-                Err(_) => {
-                    eprintln!("Worker {id} disconnected; shutting down.");
-                    break;
+    /// * `options`  - Scheduling options for the worker thread.
+    /// * `setup`    - Receives the options that couldn't be applied.
+    fn new(
+        id: usize,
+        receiver: Arc<Mutex<mpsc::Receiver<Job>>>,
+        options: WorkerOptions,
+        setup: mpsc::Sender<Vec<String>>,
+    ) -> Worker {
+        let thread = thread::spawn(move || {
+            // The pool waits for this before it is used.
+            let _ = setup.send(options.apply(id));
+            drop(setup);
+
+            loop {
+                let message = receiver.lock().unwrap().recv();
+
+                match message {
+                    Ok(job) => {
+                        //eprintln!("Worker {id} got a job; executing.");
+                        job();
+                    }
+                    Err(_) => break,
                 }
             }
         });
 
         Worker {
-            id,
             thread: Some(thread),
         }
     }
 }
 
+impl WorkerOptions {
+    /// Apply the options to the calling thread. Failures are returned but are not fatal.
+    ///
+    /// * `id` - Worker ID used for reporting.
+    fn apply(&self, id: usize) -> Vec<String> {
+        let mut failures = Vec::new();
+
+        if let Some(nice) = self.nice {
+            if let Err(e) = set_thread_nice(nice) {
+                failures.push(format!("Worker {id} unable to set nice value {nice}: {e}"));
+            }
+        }
+
+        if !self.cpu_affinity.is_empty() {
+            if let Err(e) = set_thread_affinity(&self.cpu_affinity) {
+                failures.push(format!("Worker {id} unable to set CPU affinity: {e}"));
+            }
+        }
+
+        failures
+    }
+}
+
+/// Set the nice value of the calling thread. On Linux this only affects the calling thread. On
+/// other Unix systems it affects the whole process.
+///
+/// * `nice` - Nice value.
+#[cfg(unix)]
+fn set_thread_nice(nice: i32) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    let who = unsafe { libc::gettid() } as libc::id_t;
+
+    #[cfg(not(target_os = "linux"))]
+    let who = 0;
+
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, who, nice) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error().to_string())
+    }
+}
+
+/// Set the nice value of the calling thread. Not supported on this platform.
+///
+/// * `_nice` - Nice value.
+#[cfg(not(unix))]
+fn set_thread_nice(_nice: i32) -> Result<(), String> {
+    Err("thread priority is not supported on this platform".to_string())
+}
+
+/// Restrict the calling thread to the given logical CPUs.
+///
+/// * `cpus` - Logical CPU indices.
+#[cfg(target_os = "linux")]
+fn set_thread_affinity(cpus: &[usize]) -> Result<(), String> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        for cpu in cpus {
+            if *cpu >= libc::CPU_SETSIZE as usize {
+                return Err(format!("invalid CPU {}", cpu));
+            }
+            libc::CPU_SET(*cpu, &mut set);
+        }
+
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error().to_string())
+        }
+    }
+}
+
+/// Restrict the calling thread to the given logical CPUs. Not supported on this platform.
+///
+/// * `_cpus` - Logical CPU indices.
+#[cfg(not(target_os = "linux"))]
+fn set_thread_affinity(_cpus: &[usize]) -> Result<(), String> {
+    Err("CPU affinity is not supported on this platform".to_string())
+}
+
 /// Custom errors for thread pool creation.
 #[derive(Debug)]
 pub enum PoolCreationError {
@@ -1,8 +1,12 @@
+mod order;
+
 use std::sync::{Arc, Mutex};
 
-use image::{self, imageops};
+use image::{self, imageops, ImageBuffer, Pixel};
+
+use crate::{Accumulator, RecursiveTracer, COLOR_CHANNELS};
 
-use crate::{RecursiveTracer, COLOR_CHANNELS, CONFIG};
+pub use self::order::{get_tile_order, TileOrder};
 
 /// Tile bounds.
 pub struct TileBounds {
@@ -31,23 +35,71 @@ pub fn get_tile_count(tile_size: u8, dimension: u32) -> usize {
     }
 }
 
+/// Render settings needed to split an image into tiles and render them. This is passed explicitly so images with
+/// different settings can be rendered in the same process.
+#[derive(Debug, Copy, Clone)]
+pub struct RenderContext {
+    /// Image width in pixels.
+    pub image_width: u32,
+
+    /// Image height in pixels.
+    pub image_height: u32,
+
+    /// Tile size in pixels.
+    pub tile_size: u8,
+}
+
+impl RenderContext {
+    /// Create a new render context.
+    ///
+    /// * `image_width`  - Image width in pixels.
+    /// * `image_height` - Image height in pixels.
+    /// * `tile_size`    - Tile size in pixels.
+    pub fn new(image_width: u32, image_height: u32, tile_size: u8) -> RenderContext {
+        RenderContext {
+            image_width,
+            image_height,
+            tile_size,
+        }
+    }
+
+    /// Returns the number of tiles across the image.
+    pub fn n_tiles_x(&self) -> usize {
+        get_tile_count(self.tile_size, self.image_width)
+    }
+
+    /// Returns the number of tiles down the image.
+    pub fn n_tiles_y(&self) -> usize {
+        get_tile_count(self.tile_size, self.image_height)
+    }
+
+    /// Returns the number of tiles in the image.
+    pub fn n_tiles(&self) -> usize {
+        self.n_tiles_x() * self.n_tiles_y()
+    }
+}
+
 /// Calculates tile bounds based on a tile index. Tiles are counted from top-left to bottom-right.
 ///
+/// * `ctx`      - Render context.
 /// * `tile_idx` - Tile index.
-pub fn get_tile_bounds(tile_idx: usize) -> TileBounds {
-    let tile_x = (tile_idx % CONFIG.n_tiles_x()) as u32;
-    let tile_y = (tile_idx / CONFIG.n_tiles_x()) as u32;
-
-    let y_min = tile_y * CONFIG.tile_size as u32;
-    let mut y_max = y_min as u32 + CONFIG.tile_size as u32 - 1;
-    if y_max > CONFIG.image_height - 1 {
-        y_max = CONFIG.image_height - 1;
+pub fn get_tile_bounds(ctx: &RenderContext, tile_idx: usize) -> TileBounds {
+    let (tile_size, image_width, image_height) = (ctx.tile_size, ctx.image_width, ctx.image_height);
+    let n_tiles_x = ctx.n_tiles_x();
+
+    let tile_x = (tile_idx % n_tiles_x) as u32;
+    let tile_y = (tile_idx / n_tiles_x) as u32;
+
+    let y_min = tile_y * tile_size as u32;
+    let mut y_max = y_min + tile_size as u32 - 1;
+    if y_max > image_height - 1 {
+        y_max = image_height - 1;
     }
 
-    let x_min = tile_x * CONFIG.tile_size as u32;
-    let mut x_max = x_min + CONFIG.tile_size as u32 - 1;
-    if x_max > CONFIG.image_width - 1 {
-        x_max = CONFIG.image_width - 1;
+    let x_min = tile_x * tile_size as u32;
+    let mut x_max = x_min + tile_size as u32 - 1;
+    if x_max > image_width - 1 {
+        x_max = image_width - 1;
     }
 
     TileBounds {
@@ -58,29 +110,78 @@ pub fn get_tile_bounds(tile_idx: usize) -> TileBounds {
     }
 }
 
-/// Render a single tile.
+/// Render a single tile that can be abandoned part way. The `cancelled`
+/// predicate is checked before each row. Returns `false` if the tile was
+/// cancelled in which case the tile pixels are incomplete.
 ///
 /// * `renderer`    - The ray tracer to use for rendering.
+/// * `ctx`         - Render context. The tile size is the row stride of `tile_pixels`.
 /// * `tile_bounds` - Tile bounds in image coordinates.
-/// * `tile_pixels` - The tile pixels destination.
-pub fn render_tile(
+/// * `tile_pixels` - The linear RGB tile pixels destination.
+/// * `cancelled`   - Returns true if the tile is no longer needed.
+pub fn render_tile_cancellable<F>(
     renderer: Arc<RecursiveTracer>,
+    ctx: &RenderContext,
     tile_bounds: &TileBounds,
-    tile_pixels: &mut [u8],
-) {
+    tile_pixels: &mut [f32],
+    cancelled: F,
+) -> bool
+where
+    F: Fn() -> bool,
+{
     for j in tile_bounds.y_min..=tile_bounds.y_max {
+        if cancelled() {
+            return false;
+        }
+
         let ty = j - tile_bounds.y_min;
 
         for i in tile_bounds.x_min..=tile_bounds.x_max {
-            let rgba = renderer.trace_ray(i, j).to_rgba();
+            let rgb = renderer.trace_ray(i, j).to_rgb32f();
 
             let tx = i - tile_bounds.x_min;
-            let tile_offset = (ty * CONFIG.tile_size as u32 + tx) as usize * COLOR_CHANNELS;
+            let tile_offset = (ty * ctx.tile_size as u32 + tx) as usize * COLOR_CHANNELS;
 
             let dst = &mut tile_pixels[tile_offset..tile_offset + COLOR_CHANNELS];
-            dst.copy_from_slice(&rgba);
+            dst.copy_from_slice(&rgb);
         }
     }
+
+    true
+}
+
+/// Render one progressive pass over a tile. The samples are added to the
+/// accumulator and the running average of each pixel is written to the tile.
+///
+/// * `renderer`    - The ray tracer to use for rendering.
+/// * `ctx`         - Render context. The tile size is the row stride of `tile_pixels`.
+/// * `accumulator` - Running sums of the samples of previous passes.
+/// * `pass`        - Index of the pass.
+/// * `tile_bounds` - Tile bounds in image coordinates.
+/// * `tile_pixels` - The linear RGB tile pixels destination.
+pub fn render_tile_pass(
+    renderer: Arc<RecursiveTracer>,
+    ctx: &RenderContext,
+    accumulator: &Accumulator,
+    pass: u32,
+    tile_bounds: &TileBounds,
+    tile_pixels: &mut [f32],
+) {
+    let samples: Vec<_> = (tile_bounds.y_min..=tile_bounds.y_max)
+        .flat_map(|j| (tile_bounds.x_min..=tile_bounds.x_max).map(move |i| (i, j)))
+        .map(|(i, j)| renderer.trace_pass(i, j, pass))
+        .collect();
+
+    let averages = accumulator.add_tile(tile_bounds, &samples);
+
+    let tile_width = (tile_bounds.x_max - tile_bounds.x_min + 1) as usize;
+    for (k, colour) in averages.iter().enumerate() {
+        let (tx, ty) = (k % tile_width, k / tile_width);
+        let tile_offset = (ty * ctx.tile_size as usize + tx) * COLOR_CHANNELS;
+
+        let dst = &mut tile_pixels[tile_offset..tile_offset + COLOR_CHANNELS];
+        dst.copy_from_slice(&colour.to_rgb32f());
+    }
 }
 
 /// Copy a tile to to the image destination.
@@ -88,16 +189,76 @@ pub fn render_tile(
 /// * `imgbuf`      - The image buffer for rendered image.
 /// * `tile_bounds` - Tile bounds in image coordinates.
 /// * `tile_pixels` - The tile pixels source.
-pub fn copy_tile(
-    image: Arc<Mutex<image::RgbaImage>>,
+pub fn copy_tile<P>(
+    image: Arc<Mutex<ImageBuffer<P, Vec<P::Subpixel>>>>,
     tile_bounds: &TileBounds,
-    tile_pixels: &image::RgbaImage,
-) {
+    tile_pixels: &ImageBuffer<P, Vec<P::Subpixel>>,
+) where
+    P: Pixel,
+{
     let mut img = image.lock().expect("Unable to lock image buffer");
-    imageops::overlay(
+    imageops::replace(
         &mut *img,
         tile_pixels,
         tile_bounds.x_min as i64,
         tile_bounds.y_min as i64,
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, Rgb32FImage};
+
+    /// Tiles cover every pixel of images that aren't a multiple of the tile
+    /// size exactly once.
+    #[test]
+    fn tile_bounds_cover_image_once() {
+        for (width, height, tile_size) in [(10, 7, 4), (17, 33, 16), (5, 5, 8), (64, 48, 16)] {
+            let ctx = RenderContext::new(width, height, tile_size);
+            let mut covered = vec![0; (width * height) as usize];
+
+            for tile_idx in 0..ctx.n_tiles() {
+                let b = get_tile_bounds(&ctx, tile_idx);
+                assert!(b.x_max < width && b.y_max < height);
+                for y in b.y_min..=b.y_max {
+                    for x in b.x_min..=b.x_max {
+                        covered[(y * width + x) as usize] += 1;
+                    }
+                }
+            }
+
+            assert!(
+                covered.iter().all(|&n| n == 1),
+                "{}x{} with {} pixel tiles",
+                width,
+                height,
+                tile_size
+            );
+        }
+    }
+
+    /// Copying a tile puts its pixels at the tile's position and clips them
+    /// at the edges of the image.
+    #[test]
+    fn copy_tile_places_pixels() {
+        let (width, height, tile_size) = (10, 7, 4);
+        let ctx = RenderContext::new(width, height, tile_size);
+        let image = Arc::new(Mutex::new(Rgb32FImage::new(width, height)));
+
+        for tile_idx in 0..ctx.n_tiles() {
+            let b = get_tile_bounds(&ctx, tile_idx);
+            let tile = Rgb32FImage::from_fn(tile_size as u32, tile_size as u32, |x, y| {
+                Rgb([(b.x_min + x) as f32, (b.y_min + y) as f32, tile_idx as f32])
+            });
+            copy_tile(Arc::clone(&image), &b, &tile);
+        }
+
+        let image = image.lock().unwrap();
+        for (x, y, p) in image.enumerate_pixels() {
+            let tile_idx =
+                (y / tile_size as u32) as usize * ctx.n_tiles_x() + (x / tile_size as u32) as usize;
+            assert_eq!(p.0, [x as f32, y as f32, tile_idx as f32], "({}, {})", x, y);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use std::fmt;
+
+use crate::Random;
+
+/// Order in which tiles are rendered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum TileOrder {
+    /// Rows of tiles from top-left to bottom-right.
+    Row,
+
+    /// Spiral outwards from the centre of the image.
+    Spiral,
+
+    /// Hilbert curve which keeps consecutive tiles next to each other.
+    Hilbert,
+
+    /// Random order.
+    Random,
+}
+
+impl fmt::Display for TileOrder {
+    /// Display the tile order as used on the command line.
+    ///
+    /// * `f` - Formatter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            TileOrder::Row => "row",
+            TileOrder::Spiral => "spiral",
+            TileOrder::Hilbert => "hilbert",
+            TileOrder::Random => "random",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Returns the tile indices in the order they are rendered. Indices are those used by `get_tile_bounds`.
+///
+/// * `order`     - Tile order.
+/// * `n_tiles_x` - Number of tiles across the image.
+/// * `n_tiles_y` - Number of tiles down the image.
+pub fn get_tile_order(order: TileOrder, n_tiles_x: usize, n_tiles_y: usize) -> Vec<usize> {
+    let mut tiles: Vec<usize> = (0..n_tiles_x * n_tiles_y).collect();
+
+    match order {
+        TileOrder::Row => {}
+
+        TileOrder::Spiral => {
+            // Sort by the square ring around the centre and then by angle within a ring.
+            let cx = (n_tiles_x as f64 - 1.0) / 2.0;
+            let cy = (n_tiles_y as f64 - 1.0) / 2.0;
+            let key = |idx: usize| {
+                let dx = (idx % n_tiles_x) as f64 - cx;
+                let dy = (idx / n_tiles_x) as f64 - cy;
+                (dx.abs().max(dy.abs()), dy.atan2(dx))
+            };
+            tiles.sort_by(|&a, &b| {
+                let (ring_a, angle_a) = key(a);
+                let (ring_b, angle_b) = key(b);
+                ring_a.total_cmp(&ring_b).then(angle_a.total_cmp(&angle_b))
+            });
+        }
+
+        TileOrder::Hilbert => {
+            // Walk a square curve covering the tiles and skip positions outside the image.
+            let n = n_tiles_x.max(n_tiles_y).next_power_of_two();
+            tiles = (0..n * n)
+                .map(|d| hilbert_position(n, d))
+                .filter(|&(x, y)| x < n_tiles_x && y < n_tiles_y)
+                .map(|(x, y)| y * n_tiles_x + x)
+                .collect();
+        }
+
+        TileOrder::Random => Random::permute(&mut tiles),
+    }
+
+    tiles
+}
+
+/// Returns the position of a distance along a Hilbert curve filling a square.
+///
+/// * `n` - Side of the square. Must be a power of 2.
+/// * `d` - Distance along the curve.
+fn hilbert_position(n: usize, d: usize) -> (usize, usize) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+
+    let mut s = 1;
+    while s < n {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+
+        // Rotate the quadrant.
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+
+    (x, y)
+}